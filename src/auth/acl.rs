@@ -40,14 +40,58 @@ pub struct AccessControlEntry {
     pub access_level: u8,
     /// Whether the client is allowed
     pub is_allowed: bool,
-    /// Bandwidth limit in bytes/sec (0 = unlimited)
+    /// Inbound bandwidth limit in bytes/sec, replacing the server default (0 = server default)
     pub bandwidth_limit: u64,
     /// Maximum session duration in seconds
     pub max_session_duration: u64,
+    /// Concurrent sessions allowed for this key, replacing the server default (0 = server default)
+    #[serde(default)]
+    pub max_sessions: u32,
     /// Static IP assignment
     pub static_ip: Option<String>,
     /// Notes
     pub notes: Option<String>,
+    /// Time after which the entry no longer allows access (milliseconds since epoch, none = never)
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    /// Role tags (e.g. "admin", "limited") consulted by other components
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+impl AccessControlEntry {
+    /// Whether the entry has expired at `now` (milliseconds since epoch)
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.map_or(false, |expires_at| now >= expires_at)
+    }
+}
+
+/// Access decision for a client, with the role tags of its ACL entry
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessPolicy {
+    /// Whether the client may connect
+    pub allowed: bool,
+    /// Role tags granted to the client (empty when denied)
+    pub roles: Vec<String>,
+    /// When access ends (milliseconds since epoch, none = never)
+    pub expires_at: Option<u64>,
+}
+
+impl AccessPolicy {
+    /// Allow a client with no roles or expiry
+    pub fn allow() -> Self {
+        Self { allowed: true, ..Self::default() }
+    }
+
+    /// Deny a client
+    pub fn deny() -> Self {
+        Self::default()
+    }
+
+    /// Whether the client holds `role`
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
 }
 
 /// Access control list
@@ -73,17 +117,30 @@ impl AccessControlList {
 
     /// Check if a client is allowed to connect
     pub fn is_allowed(&self, public_key: &str) -> bool {
+        self.policy(public_key, utils::current_timestamp_millis()).allowed
+    }
+
+    /// Access policy for a client at `now` (milliseconds since epoch).
+    ///
+    /// An expired entry denies the client rather than falling back to the
+    /// default policy; unknown clients get the default policy with no roles.
+    pub fn policy(&self, public_key: &str, now: u64) -> AccessPolicy {
         // Look for a specific entry for this public key
-        for entry in &self.entries {
-            if entry.public_key == public_key {
-                return entry.is_allowed;
+        if let Some(entry) = self.entries.iter().find(|e| e.public_key == public_key) {
+            if !entry.is_allowed || entry.is_expired(now) {
+                return AccessPolicy::deny();
             }
+            return AccessPolicy {
+                allowed: true,
+                roles: entry.roles.clone(),
+                expires_at: entry.expires_at,
+            };
         }
 
         // Apply default policy
         match self.default_policy.as_str() {
-            "allow" => true,
-            _ => false,
+            "allow" => AccessPolicy::allow(),
+            _ => AccessPolicy::deny(),
         }
     }
 
@@ -209,6 +266,12 @@ impl AccessControlManager {
         acl.is_allowed(public_key)
     }
 
+    /// Get the access policy for a client
+    pub async fn policy(&self, public_key: &str) -> AccessPolicy {
+        let acl = self.acl.read().await;
+        acl.policy(public_key, utils::current_timestamp_millis())
+    }
+
     /// Get an access control entry
     pub async fn get_entry(&self, public_key: &str) -> Option<AccessControlEntry> {
         let acl = self.acl.read().await;
@@ -272,9 +335,14 @@ impl AccessControlManager {
         acl.default_policy.clone()
     }
 
-    /// Reload the ACL from disk
+    /// Reload the ACL from disk.
+    ///
+    /// The file is parsed in full before the in-memory list is swapped under
+    /// the write lock, so concurrent checks see either the old or the new
+    /// list. A missing or malformed file leaves the current list in place.
     pub async fn reload(&self) -> Result<(), AclError> {
-        let loaded_acl = Self::load_or_create_acl(&self.acl_path)?;
+        let content = fs::read_to_string(&self.acl_path)?;
+        let loaded_acl: AccessControlList = serde_json::from_str(&content)?;
 
         {
             let mut acl = self.acl.write().await;
@@ -294,8 +362,11 @@ impl AccessControlManager {
             is_allowed: true,
             bandwidth_limit: 0, // No limit
             max_session_duration: 86400, // 1 day
+            max_sessions: 0,
             static_ip: None,
             notes: Some("Auto-created entry".to_string()),
+            expires_at: None,
+            roles: Vec::new(),
         }
     }
 
@@ -307,8 +378,11 @@ impl AccessControlManager {
             is_allowed: false,
             bandwidth_limit: 0,
             max_session_duration: 0,
+            max_sessions: 0,
             static_ip: None,
             notes: reason.map(|s| s.to_string()),
+            expires_at: None,
+            roles: Vec::new(),
         }
     }
 }
@@ -332,8 +406,11 @@ mod tests {
             is_allowed: true,
             bandwidth_limit: 0,
             max_session_duration: 3600,
+            max_sessions: 0,
             static_ip: None,
             notes: None,
+            expires_at: None,
+            roles: Vec::new(),
         };
 
         acl.add_entry(entry);
@@ -381,8 +458,11 @@ mod tests {
             is_allowed: true,
             bandwidth_limit: 0,
             max_session_duration: 3600,
+            max_sessions: 0,
             static_ip: None,
             notes: None,
+            expires_at: None,
+            roles: Vec::new(),
         };
 
         manager.add_entry(entry).await.unwrap();
@@ -416,4 +496,65 @@ mod tests {
         assert!(!deny.is_allowed);
         assert_eq!(deny.notes, Some("Testing".to_string()));
     }
+
+    #[test]
+    fn test_acl_expired_entry() {
+        let mut acl = AccessControlList::new();
+        let mut entry = AccessControlManager::create_allow_entry("expiring");
+        entry.expires_at = Some(1_000);
+        entry.roles = vec!["admin".to_string()];
+        acl.add_entry(entry);
+
+        let policy = acl.policy("expiring", 999);
+        assert!(policy.allowed);
+        assert_eq!(policy.expires_at, Some(1_000));
+
+        // Denied once expired, even though the default policy allows unknown keys
+        assert_eq!(acl.default_policy, "allow");
+        assert_eq!(acl.policy("expiring", 1_000), AccessPolicy::deny());
+        assert!(acl.is_allowed("unknown"));
+        assert!(!acl.is_allowed("expiring"));
+    }
+
+    #[test]
+    fn test_acl_roles() {
+        let mut acl = AccessControlList::new();
+        let mut entry = AccessControlManager::create_allow_entry("operator");
+        entry.roles = vec!["admin".to_string(), "limited".to_string()];
+        acl.add_entry(entry);
+        let mut denied = AccessControlManager::create_deny_entry("banned", None);
+        denied.roles = vec!["admin".to_string()];
+        acl.add_entry(denied);
+
+        let policy = acl.policy("operator", 0);
+        assert!(policy.has_role("admin") && policy.has_role("limited"));
+        assert!(!policy.has_role("guest"));
+
+        // Denied clients hold no roles; unknown ones get none from the default policy
+        assert!(acl.policy("banned", 0).roles.is_empty());
+        assert_eq!(acl.policy("unknown", 0), AccessPolicy::allow());
+
+        // Older ACL files without the new fields still load
+        let json = r#"{"public_key":"old","access_level":1,"is_allowed":true,"bandwidth_limit":0,
+            "max_session_duration":0,"static_ip":null,"notes":null}"#;
+        let entry: AccessControlEntry = serde_json::from_str(json).unwrap();
+        assert_eq!(entry.expires_at, None);
+        assert!(entry.roles.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_acl_default_deny() {
+        let dir = tempdir().unwrap();
+        let manager = AccessControlManager::new(dir.path().join("acl.json")).await.unwrap();
+        manager.set_default_policy("deny").await.unwrap();
+        manager.add_entry(AccessControlManager::create_allow_entry("listed")).await.unwrap();
+
+        assert!(manager.policy("listed").await.allowed);
+        assert_eq!(manager.policy("unlisted").await, AccessPolicy::deny());
+
+        // An unrecognised policy in the file denies rather than allows
+        let mut acl = AccessControlList::new();
+        acl.default_policy = "permit".to_string();
+        assert!(!acl.policy("unlisted", 0).allowed);
+    }
 }
@@ -0,0 +1,157 @@
+// src/auth/audit.rs
+//! Audit trail of authentication decisions.
+//!
+//! Every authentication attempt ends in exactly one `AuthAuditEvent`:
+//! success, failure, or denial by the ACL, with the source IP, the key the
+//! client claimed (if it got that far) and the reason. Events go to the
+//! `AuditSink` installed with `AuthManager::with_audit_sink`; the default
+//! `JsonLinesAuditSink` appends one JSON object per line to a file, and
+//! other destinations such as syslog implement the same trait.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::warn;
+
+use crate::utils::current_timestamp_millis;
+
+/// Outcome of an authentication attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthDecision {
+    /// The client authenticated and may connect
+    Success,
+    /// The client failed to authenticate
+    Failure,
+    /// The client proved its key but the ACL refused it
+    DeniedByAcl,
+}
+
+/// One authentication decision
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthAuditEvent {
+    /// When the decision was made (Unix milliseconds)
+    pub timestamp: u64,
+    /// Client source IP
+    pub source_ip: IpAddr,
+    /// Public key the client claimed, once it sent one
+    pub public_key: Option<String>,
+    /// Outcome
+    pub decision: AuthDecision,
+    /// Why the attempt ended this way
+    pub reason: String,
+}
+
+impl AuthAuditEvent {
+    /// Event for a decision made now
+    pub fn new(source_ip: IpAddr, public_key: Option<&str>, decision: AuthDecision, reason: impl Into<String>) -> Self {
+        Self {
+            timestamp: current_timestamp_millis(),
+            source_ip,
+            public_key: public_key.map(str::to_string),
+            decision,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Destination for authentication audit events
+#[async_trait]
+pub trait AuditSink: Debug + Send + Sync {
+    /// Record one event. Failures are the sink's to report; authentication carries on.
+    async fn record(&self, event: &AuthAuditEvent);
+}
+
+/// Appends audit events to a file as JSON lines
+#[derive(Debug)]
+pub struct JsonLinesAuditSink {
+    /// Destination path, for logging
+    path: PathBuf,
+    /// Open audit file
+    file: Mutex<File>,
+}
+
+impl JsonLinesAuditSink {
+    /// Open (or create) `path` for appending
+    pub async fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path).await?;
+        Ok(Self { path, file: Mutex::new(file) })
+    }
+}
+
+#[async_trait]
+impl AuditSink for JsonLinesAuditSink {
+    async fn record(&self, event: &AuthAuditEvent) {
+        let mut line = match serde_json::to_string(event) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Failed to serialize audit event for {}: {}", event.source_ip, e);
+                return;
+            }
+        };
+        line.push('\n');
+
+        // One write per event keeps lines whole
+        let mut file = self.file.lock().await;
+        if let Err(e) = file.write_all(line.as_bytes()).await {
+            warn!("Failed to write audit event to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Forwards audit events to a channel, for shipping elsewhere in-process
+#[derive(Debug)]
+pub struct ChannelAuditSink {
+    tx: UnboundedSender<AuthAuditEvent>,
+}
+
+impl ChannelAuditSink {
+    /// Send events to `tx`; events are dropped once the receiver is gone
+    pub fn new(tx: UnboundedSender<AuthAuditEvent>) -> Self {
+        Self { tx }
+    }
+}
+
+#[async_trait]
+impl AuditSink for ChannelAuditSink {
+    async fn record(&self, event: &AuthAuditEvent) {
+        let _ = self.tx.send(event.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_audit_events_append_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let sink = JsonLinesAuditSink::open(&path).await.unwrap();
+        let source_ip: IpAddr = "203.0.113.7".parse().unwrap();
+        let success = AuthAuditEvent::new(source_ip, Some("client"), AuthDecision::Success, "Challenge verified");
+        sink.record(&success).await;
+        sink.record(&AuthAuditEvent::new(source_ip, None, AuthDecision::Failure, "Timed out")).await;
+
+        let contents = tokio::fs::read_to_string(&path).await.unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(serde_json::from_str::<AuthAuditEvent>(lines[0]).unwrap(), success);
+
+        // Stable wire names
+        let value: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(value["source_ip"], "203.0.113.7");
+        assert_eq!(value["public_key"], serde_json::Value::Null);
+        assert_eq!(value["decision"], "failure");
+        assert_eq!(value["reason"], "Timed out");
+        assert!(value["timestamp"].as_u64().unwrap() > 0);
+        assert_eq!(serde_json::to_value(AuthDecision::DeniedByAcl).unwrap(), "denied_by_acl");
+    }
+}
@@ -0,0 +1,51 @@
+// src/auth/backend.rs
+//! Pluggable authorization backends.
+//!
+//! `AuthManager` proves key ownership with an Ed25519 challenge or a verified
+//! TLS client certificate; the `AuthBackend` it holds decides whether a
+//! proven key may connect. The default `AclAuthBackend` consults the local
+//! ACL. Organization-specific checks, such as asking an external API,
+//! implement the same trait and are installed with `AuthManager::with_backend`.
+
+use async_trait::async_trait;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::auth::acl::{AccessControlManager, AccessPolicy};
+use crate::auth::manager::AuthError;
+
+/// Decides which authenticated keys may connect
+#[async_trait]
+pub trait AuthBackend: Debug + Send + Sync {
+    /// Whether `public_key` may connect, and the roles it holds.
+    ///
+    /// Called after the client has proven ownership of the key, and again
+    /// before a session is set up, whose roles come from the second call.
+    async fn is_client_allowed(&self, public_key: &str) -> AccessPolicy;
+
+    /// Hook run once a client's challenge signature or client certificate has
+    /// been verified and the key is allowed. Returning an error rejects the authentication.
+    async fn after_verify(&self, _public_key: &str, _client_addr: &str) -> Result<(), AuthError> {
+        Ok(())
+    }
+}
+
+/// Default backend: clients must be allowed by the local ACL
+#[derive(Debug)]
+pub struct AclAuthBackend {
+    acl_manager: Arc<AccessControlManager>,
+}
+
+impl AclAuthBackend {
+    /// Authorize clients against `acl_manager`
+    pub fn new(acl_manager: Arc<AccessControlManager>) -> Self {
+        Self { acl_manager }
+    }
+}
+
+#[async_trait]
+impl AuthBackend for AclAuthBackend {
+    async fn is_client_allowed(&self, public_key: &str) -> AccessPolicy {
+        self.acl_manager.policy(public_key).await
+    }
+}
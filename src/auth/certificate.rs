@@ -0,0 +1,100 @@
+// src/auth/certificate.rs
+//! Client identities from TLS client certificates.
+//!
+//! A client certificate whose subject key is Ed25519 names the same
+//! identity as the challenge flow: the TLS handshake has already proven
+//! possession of the private key, so the certificate key is the client's
+//! public key. Certificates with any other key type carry no usable
+//! identity, since the session key exchange needs an Ed25519 key.
+
+use solana_sdk::pubkey::Pubkey;
+
+/// DER tag of a SEQUENCE
+const DER_SEQUENCE: u8 = 0x30;
+/// DER tag of the explicit `[0] version` field of a TBSCertificate
+const DER_VERSION: u8 = 0xa0;
+
+/// AlgorithmIdentifier and BIT STRING header of an Ed25519 SubjectPublicKeyInfo (RFC 8410)
+const ED25519_SPKI_PREFIX: [u8; 10] = [0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00];
+
+/// Client public key (base58) named by a DER certificate, if its subject key is Ed25519
+pub fn certificate_public_key(der: &[u8]) -> Option<String> {
+    let (tag, certificate, _) = read_tlv(der)?;
+    if tag != DER_SEQUENCE {
+        return None;
+    }
+    let (tag, tbs, _) = read_tlv(certificate)?;
+    if tag != DER_SEQUENCE {
+        return None;
+    }
+
+    // version (optional), serialNumber, signature, issuer, validity, subject
+    let mut fields = tbs;
+    if fields.first() == Some(&DER_VERSION) {
+        fields = read_tlv(fields)?.2;
+    }
+    for _ in 0..5 {
+        fields = read_tlv(fields)?.2;
+    }
+
+    let (tag, spki, _) = read_tlv(fields)?;
+    if tag != DER_SEQUENCE || spki.len() != ED25519_SPKI_PREFIX.len() + 32 || !spki.starts_with(&ED25519_SPKI_PREFIX) {
+        return None;
+    }
+    let key: [u8; 32] = spki[ED25519_SPKI_PREFIX.len()..].try_into().ok()?;
+    Some(Pubkey::new_from_array(key).to_string())
+}
+
+/// Split one DER element off `input`, returning its tag, contents and the remaining input
+fn read_tlv(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = match first {
+        len @ 0..=0x7f => (usize::from(len), rest),
+        0x81..=0x84 => {
+            let count = usize::from(first & 0x7f);
+            if rest.len() < count {
+                return None;
+            }
+            let len = rest[..count].iter().fold(0usize, |len, &b| (len << 8) | usize::from(b));
+            (len, &rest[count..])
+        }
+        _ => return None,
+    };
+    if rest.len() < len {
+        return None;
+    }
+    Some((tag, &rest[..len], &rest[len..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pem_to_der(pem: &str) -> Vec<u8> {
+        rustls_pemfile::certs(&mut pem.as_bytes()).unwrap().remove(0)
+    }
+
+    #[test]
+    fn test_ed25519_certificate_key() {
+        // Self-signed certificate for the Ed25519 key with seed [8; 32]
+        let der = pem_to_der(include_str!("../server/testdata/tls_client.crt"));
+        assert_eq!(
+            certificate_public_key(&der).as_deref(),
+            Some("2KW2XRd9kwqet15Aha2oK3tYvd3nWbTFH1MBiRAv1BE1")
+        );
+
+        // Truncated certificates are rejected without panicking
+        for len in [0, 1, 2, 10, der.len() / 2, der.len() - 1] {
+            assert_eq!(certificate_public_key(&der[..len]), None);
+        }
+    }
+
+    #[test]
+    fn test_non_ed25519_certificate() {
+        // ECDSA P-256 server certificate
+        let der = pem_to_der(include_str!("../server/testdata/tls_server.crt"));
+        assert_eq!(certificate_public_key(&der), None);
+        assert_eq!(certificate_public_key(b"not a certificate"), None);
+    }
+}
@@ -5,7 +5,7 @@
 //! cryptographic signatures for client verification.
 
 use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use thiserror::Error;
@@ -14,6 +14,9 @@ use tracing::{debug, info, warn};
 
 // Removed unused AUTH_CHALLENGE_TIMEOUT import (using constructor arg)
 use crate::config::constants::CHALLENGE_SIZE;
+use crate::auth::pow::{adaptive_difficulty, verify_pow};
+use crate::config::settings::ChallengeAddressBinding;
+use crate::crypto::batch::SignatureBatcher;
 use crate::crypto::encryption::generate_challenge as gen_challenge;
 use crate::crypto::keys::KeyManager;
 use crate::utils;
@@ -34,6 +37,12 @@ pub enum ChallengeError {
     #[error("Signature verification failed")]
     SignatureVerificationFailed,
 
+    #[error("Proof of work missing or below difficulty {0}")]
+    InsufficientWork(u8),
+
+    #[error("Challenge already used")]
+    AlreadyUsed,
+
     #[error("Creation failed: {0}")]
     CreationFailed(String),
 
@@ -54,6 +63,8 @@ pub struct Challenge {
     pub expires_at: Instant,
     /// Client IP address
     pub client_addr: SocketAddr,
+    /// Leading zero bits required of the proof of work (0 = none)
+    pub difficulty: u8,
 }
 
 impl Challenge {
@@ -66,6 +77,7 @@ impl Challenge {
             created_at: now,
             expires_at: now + timeout,
             client_addr,
+            difficulty: 0,
         }
     }
 
@@ -95,6 +107,29 @@ pub struct ChallengeManager {
     _key_manager: Arc<KeyManager>, // Prefix if only used for creation, or remove if not needed
     /// Maximum number of active challenges
     max_challenges: usize,
+    /// How closely the responding address must match the challenged one
+    binding: ChallengeAddressBinding,
+    /// Recently consumed challenge IDs, with when they may be forgotten
+    consumed: Arc<Mutex<HashMap<String, Instant>>>,
+    /// Batches response signature checks (None = verify each on its own)
+    batcher: Option<Arc<SignatureBatcher>>,
+    /// Proof-of-work difficulty issued when no challenges are pending (0 = none)
+    pow_difficulty: u8,
+    /// Difficulty issued when every challenge slot is in use
+    max_pow_difficulty: u8,
+}
+
+/// Check whether `responding` falls within the binding of a challenge issued to `issued`
+pub fn address_within_binding(binding: ChallengeAddressBinding, issued: SocketAddr, responding: SocketAddr) -> bool {
+    match binding {
+        ChallengeAddressBinding::Addr => issued == responding,
+        ChallengeAddressBinding::Ip => issued.ip() == responding.ip(),
+        ChallengeAddressBinding::Subnet => match (issued.ip(), responding.ip()) {
+            (IpAddr::V4(a), IpAddr::V4(b)) => a.octets()[..3] == b.octets()[..3],
+            (IpAddr::V6(a), IpAddr::V6(b)) => a.segments()[..4] == b.segments()[..4],
+            _ => false,
+        },
+    }
 }
 
 impl ChallengeManager {
@@ -105,15 +140,37 @@ impl ChallengeManager {
             timeout,
             _key_manager: key_manager, // Assign to prefixed field if unused later
             max_challenges,
+            binding: ChallengeAddressBinding::default(),
+            consumed: Arc::new(Mutex::new(HashMap::new())),
+            batcher: None,
+            pow_difficulty: 0,
+            max_pow_difficulty: 0,
         }
     }
 
+    /// Set how closely a response's source address must match the challenged address
+    pub fn with_address_binding(mut self, binding: ChallengeAddressBinding) -> Self {
+        self.binding = binding;
+        self
+    }
+
+    /// Verify response signatures in batches with `batcher`
+    pub fn set_signature_batcher(&mut self, batcher: Arc<SignatureBatcher>) {
+        self.batcher = Some(batcher);
+    }
+
+    /// Require proof of work, scaling from `base` to `max` difficulty as challenge slots fill up
+    pub fn set_proof_of_work(&mut self, base: u8, max: u8) {
+        self.pow_difficulty = base;
+        self.max_pow_difficulty = max;
+    }
+
     /// Generate a new challenge for a client
     pub async fn generate_challenge(&self, client_addr: SocketAddr) -> Result<Challenge, ChallengeError> {
         let challenge_data = gen_challenge(CHALLENGE_SIZE);
         let challenge_id = utils::random_string(24);
 
-        let challenge = Challenge::new(
+        let mut challenge = Challenge::new(
             challenge_id.clone(),
             challenge_data,
             client_addr,
@@ -133,10 +190,17 @@ impl ChallengeManager {
             }
         }
 
+        challenge.difficulty = adaptive_difficulty(
+            self.pow_difficulty,
+            self.max_pow_difficulty,
+            challenges.len(),
+            self.max_challenges,
+        );
+
         // Store the challenge
         challenges.insert(challenge_id.clone(), challenge.clone());
 
-        debug!("Generated challenge {} for client {}", challenge_id, client_addr);
+        debug!("Generated challenge {} for client {} (difficulty {})", challenge_id, client_addr, challenge.difficulty);
 
         Ok(challenge)
     }
@@ -148,15 +212,32 @@ impl ChallengeManager {
         client_addr: SocketAddr,
         signature: &str,
         public_key: &str,
+        pow_nonce: Option<u64>,
     ) -> Result<(), ChallengeError> {
-        let mut challenges = self.challenges.lock().await;
-
-        // Find the challenge
-        let challenge = challenges.get(challenge_id)
-            .ok_or_else(|| ChallengeError::NotFound(challenge_id.to_string()))?;
+        // Every response consumes its challenge, whatever the outcome. The ID is
+        // moved to the consumed set under the same lock, so a concurrent or later
+        // replay is always reported as reuse.
+        let challenge = {
+            let mut challenges = self.challenges.lock().await;
+            let mut consumed = self.consumed.lock().await;
+            let now = Instant::now();
+            consumed.retain(|_, forget_at| *forget_at > now);
+
+            match challenges.remove(challenge_id) {
+                Some(challenge) => {
+                    consumed.insert(challenge_id.to_string(), now + self.timeout);
+                    challenge
+                }
+                None if consumed.contains_key(challenge_id) => {
+                    warn!("Replayed response for consumed challenge {}", challenge_id);
+                    return Err(ChallengeError::AlreadyUsed);
+                }
+                None => return Err(ChallengeError::NotFound(challenge_id.to_string())),
+            }
+        };
 
         // Verify client address
-        if challenge.client_addr != client_addr {
+        if !address_within_binding(self.binding, challenge.client_addr, client_addr) {
             warn!("Challenge address mismatch: expected {}, got {}",
                  challenge.client_addr, client_addr);
             return Err(ChallengeError::AddressMismatch);
@@ -165,16 +246,18 @@ impl ChallengeManager {
         // Check if challenge has expired
         if challenge.is_expired() {
             warn!("Expired challenge: {}", challenge_id);
-            // Remove expired challenge eagerly
-            challenges.remove(challenge_id);
             return Err(ChallengeError::Expired);
         }
 
-        // Clone the data before dropping the lock temporarily if needed, or keep lock
-        let challenge_data = challenge.data.clone();
+        let challenge_data = challenge.data;
 
-        // Drop the lock before potentially long-running crypto operations
-        // drop(challenges); // Uncomment this if verify_signature is slow
+        // One hash, checked before any signature work
+        if challenge.difficulty > 0
+            && !pow_nonce.map_or(false, |nonce| verify_pow(&challenge_data, nonce, challenge.difficulty))
+        {
+            warn!("Insufficient proof of work for challenge {}", challenge_id);
+            return Err(ChallengeError::InsufficientWork(challenge.difficulty));
+        }
 
         // Parse the client's public key
         let pubkey = solana_sdk::pubkey::Pubkey::from_str(public_key)
@@ -185,19 +268,15 @@ impl ChallengeManager {
             .map_err(|_| ChallengeError::SignatureVerificationFailed)?;
 
         // Verify the signature
-        // KeyManager is no longer directly used here, assuming verify_signature is static or accessible
-        if !KeyManager::verify_signature(&pubkey, &challenge_data, &sig) {
+        let verified = match &self.batcher {
+            Some(batcher) => batcher.verify(pubkey.as_ref(), &challenge_data, sig.as_ref()).await,
+            None => KeyManager::verify_signature(&pubkey, &challenge_data, &sig),
+        };
+        if !verified {
             warn!("Signature verification failed for challenge {}", challenge_id);
-            // Re-acquire lock to potentially update failure counts if needed
-            // let mut challenges = self.challenges.lock().await;
-            // challenges.remove(challenge_id); // Optionally remove failed attempts
             return Err(ChallengeError::SignatureVerificationFailed);
         }
 
-        // Re-acquire lock to remove the challenge
-        // let mut challenges = self.challenges.lock().await; // Re-acquire lock if dropped
-        challenges.remove(challenge_id);
-
         info!("Successfully verified challenge {} for client {}", challenge_id, client_addr);
 
         Ok(())
@@ -226,6 +305,8 @@ impl ChallengeManager {
         let before_count = challenges.len();
 
         challenges.retain(|_, c| !c.is_expired());
+        let now = Instant::now();
+        self.consumed.lock().await.retain(|_, forget_at| *forget_at > now);
 
         let removed = before_count - challenges.len();
         if removed > 0 {
@@ -273,6 +354,7 @@ mod tests {
             client_addr,
             &signature.to_string(),
             &keypair.pubkey().to_string(), // Use the signing keypair's pubkey
+            None,
         ).await;
 
         // Now it should succeed because the signature matches the public key
@@ -297,6 +379,44 @@ mod tests {
         assert_eq!(challenge_manager.challenge_count().await, 0);
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_challenge_single_use() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let key_manager = Arc::new(KeyManager::new(temp_dir.path().join("key"), Duration::from_secs(600), 100).await.unwrap());
+        let manager = Arc::new(ChallengeManager::new(key_manager, Duration::from_secs(10), 100));
+        let keypair = solana_sdk::signature::Keypair::new();
+        let public_key = keypair.pubkey().to_string();
+        let client_addr: SocketAddr = "127.0.0.1:12345".parse().unwrap();
+
+        // A failed response consumes the challenge too
+        let challenge = manager.generate_challenge(client_addr).await.unwrap();
+        let wrong = solana_sdk::signature::Keypair::new().sign_message(&challenge.data).to_string();
+        let right = keypair.sign_message(&challenge.data).to_string();
+        assert!(matches!(
+            manager.verify_challenge(&challenge.id, client_addr, &wrong, &public_key, None).await,
+            Err(ChallengeError::SignatureVerificationFailed)
+        ));
+        assert!(matches!(
+            manager.verify_challenge(&challenge.id, client_addr, &right, &public_key, None).await,
+            Err(ChallengeError::AlreadyUsed)
+        ));
+
+        // Of two simultaneous valid responses, exactly one succeeds
+        for _ in 0..20 {
+            let challenge = manager.generate_challenge(client_addr).await.unwrap();
+            let signature = keypair.sign_message(&challenge.data).to_string();
+            let attempts: Vec<_> = (0..2).map(|_| {
+                let (manager, id, signature, public_key) =
+                    (manager.clone(), challenge.id.clone(), signature.clone(), public_key.clone());
+                tokio::spawn(async move { manager.verify_challenge(&id, client_addr, &signature, &public_key, None).await })
+            }).collect();
+            let results: Vec<_> = futures::future::join_all(attempts).await.into_iter().map(Result::unwrap).collect();
+            assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+            assert!(results.iter().any(|r| matches!(r, Err(ChallengeError::AlreadyUsed))));
+        }
+        assert_eq!(manager.challenge_count().await, 0);
+    }
+
     #[test]
     fn test_challenge_expiration() {
         let client_addr: SocketAddr = "127.0.0.1:12345".parse().unwrap();
@@ -319,4 +439,95 @@ mod tests {
         // Time remaining should be zero
         assert_eq!(challenge.time_remaining(), Duration::from_secs(0));
     }
+
+    #[test]
+    fn test_address_binding_granularity() {
+        let addr = |s: &str| s.parse::<SocketAddr>().unwrap();
+        let issued = addr("203.0.113.7:40000");
+        let new_port = addr("203.0.113.7:40001");
+        let same_subnet = addr("203.0.113.99:40000");
+        let other_subnet = addr("203.0.114.7:40000");
+
+        use ChallengeAddressBinding::*;
+        assert!(address_within_binding(Addr, issued, issued));
+        assert!(!address_within_binding(Addr, issued, new_port));
+
+        assert!(address_within_binding(Ip, issued, new_port));
+        assert!(!address_within_binding(Ip, issued, same_subnet));
+
+        assert!(address_within_binding(Subnet, issued, same_subnet));
+        assert!(!address_within_binding(Subnet, issued, other_subnet));
+        assert!(address_within_binding(Subnet, addr("[2001:db8:1:2::1]:443"), addr("[2001:db8:1:2:ffff::9]:8443")));
+        assert!(!address_within_binding(Subnet, addr("[2001:db8:1:2::1]:443"), addr("[2001:db8:1:3::1]:443")));
+        assert!(!address_within_binding(Subnet, issued, addr("[::ffff:203.0.113.7]:40000")));
+    }
+
+    #[tokio::test]
+    async fn test_challenge_roaming() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let key_manager = Arc::new(KeyManager::new(temp_dir.path().join("key"), Duration::from_secs(600), 100).await.unwrap());
+        let keypair = solana_sdk::signature::Keypair::new();
+        let issued: SocketAddr = "198.51.100.10:5000".parse().unwrap();
+
+        let attempt = |binding: ChallengeAddressBinding, responding: &str| {
+            let manager = ChallengeManager::new(key_manager.clone(), Duration::from_secs(10), 10)
+                .with_address_binding(binding);
+            let responding: SocketAddr = responding.parse().unwrap();
+            let keypair = &keypair;
+            async move {
+                let challenge = manager.generate_challenge(issued).await.unwrap();
+                let signature = keypair.sign_message(&challenge.data).to_string();
+                manager.verify_challenge(&challenge.id, responding, &signature, &keypair.pubkey().to_string(), None).await
+            }
+        };
+
+        // Port change: only the full-address binding rejects it
+        assert!(matches!(attempt(ChallengeAddressBinding::Addr, "198.51.100.10:5001").await, Err(ChallengeError::AddressMismatch)));
+        assert!(attempt(ChallengeAddressBinding::Ip, "198.51.100.10:5001").await.is_ok());
+
+        // Roaming within the /24
+        assert!(matches!(attempt(ChallengeAddressBinding::Ip, "198.51.100.20:5000").await, Err(ChallengeError::AddressMismatch)));
+        assert!(attempt(ChallengeAddressBinding::Subnet, "198.51.100.20:5000").await.is_ok());
+
+        // Roaming across networks is rejected at every granularity
+        assert!(matches!(attempt(ChallengeAddressBinding::Subnet, "192.0.2.10:5000").await, Err(ChallengeError::AddressMismatch)));
+    }
+
+    #[tokio::test]
+    async fn test_proof_of_work() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let key_manager = Arc::new(KeyManager::new(temp_dir.path().join("key"), Duration::from_secs(600), 100).await.unwrap());
+        let mut manager = ChallengeManager::new(key_manager, Duration::from_secs(10), 4);
+        manager.set_proof_of_work(8, 12);
+        let keypair = solana_sdk::signature::Keypair::new();
+        let public_key = keypair.pubkey().to_string();
+        let client_addr: SocketAddr = "127.0.0.1:12345".parse().unwrap();
+
+        // Difficulty rises as challenges pile up
+        let challenges = [
+            manager.generate_challenge(client_addr).await.unwrap(),
+            manager.generate_challenge(client_addr).await.unwrap(),
+            manager.generate_challenge(client_addr).await.unwrap(),
+        ];
+        let difficulties: Vec<u8> = challenges.iter().map(|c| c.difficulty).collect();
+        assert_eq!(difficulties, vec![8, 9, 10]);
+
+        // Missing or wrong work is refused before the signature is looked at
+        let [first, second, third] = challenges;
+        let signature = keypair.sign_message(&first.data).to_string();
+        assert!(matches!(
+            manager.verify_challenge(&first.id, client_addr, &signature, &public_key, None).await,
+            Err(ChallengeError::InsufficientWork(8))
+        ));
+        let nonce = (0..).find(|n| !verify_pow(&second.data, *n, second.difficulty)).unwrap();
+        assert!(matches!(
+            manager.verify_challenge(&second.id, client_addr, "not a signature", &public_key, Some(nonce)).await,
+            Err(ChallengeError::InsufficientWork(9))
+        ));
+
+        // Valid work and signature succeed
+        let nonce = crate::auth::pow::solve_pow(&third.data, third.difficulty);
+        let signature = keypair.sign_message(&third.data).to_string();
+        assert!(manager.verify_challenge(&third.id, client_addr, &signature, &public_key, Some(nonce)).await.is_ok());
+    }
 }
@@ -4,10 +4,12 @@
 //! This module provides a central manager for handling authentication
 //! and authorization tasks.
 
-use std::net::SocketAddr;
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
 use std::path::Path;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use parking_lot::RwLock;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Signature;
 use std::str::FromStr;
@@ -15,12 +17,20 @@ use thiserror::Error;
 // Removed unused debug, info
 use tracing::{error, warn};
 
-use crate::auth::acl::{AccessControlEntry, AccessControlManager, AclError};
+use crate::auth::acl::{AccessControlEntry, AccessControlManager, AccessPolicy, AclError};
+use crate::auth::audit::{AuditSink, AuthAuditEvent};
+use crate::auth::backend::{AclAuthBackend, AuthBackend};
 use crate::auth::challenge::{ChallengeError, ChallengeManager};
+use crate::auth::resumption::{ResumedSession, ResumptionError, ResumptionStore};
 // Removed unused AUTH_CHALLENGE_TIMEOUT
-use crate::config::constants::MAX_AUTH_ATTEMPTS;
+use crate::config::constants::{
+    AUTH_FAILURE_WINDOW, AUTH_MESSAGE_TIMEOUT, CAPABILITY_QUERY_WINDOW, AUTH_PHASE_DEADLINE, CHALLENGE_RESPONSE_TIMEOUT, MAX_AUTH_ATTEMPTS,
+    MAX_SIGNATURE_BATCH,
+};
+use crate::config::settings::{AclDenyResponse, ChallengeAddressBinding};
+use crate::crypto::batch::SignatureBatcher;
 use crate::crypto::keys::KeyManager;
-use crate::utils::security::StringValidator;
+use crate::utils::security::{RateLimiter, StringValidator};
 
 /// Error type for authentication operations
 #[derive(Debug, Error)]
@@ -40,8 +50,41 @@ pub enum AuthError {
     #[error("Invalid format: {0}")]
     InvalidFormat(String),
 
-    #[error("Too many attempts")]
-    TooManyAttempts,
+    #[error("Too many failed attempts; retry in {}s", .retry_after.as_secs().max(1))]
+    TooManyAttempts { retry_after: Duration },
+
+    #[error("Resumption error: {0}")]
+    Resumption(#[from] ResumptionError),
+}
+
+/// Per-source-IP limit on failed authentications
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FailureLimit {
+    /// Failures within `window` after which a source IP is refused (0 = unlimited)
+    pub max_failures: usize,
+    /// Sliding window over which failures are counted
+    pub window: Duration,
+}
+
+/// How long a connection may take to authenticate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthTimeouts {
+    /// Wait for the client's `Auth` packet
+    pub auth_message: Duration,
+    /// Wait for the `ChallengeResponse` once the challenge is sent
+    pub challenge_response: Duration,
+    /// Limit on the whole authentication phase, whatever the individual waits
+    pub deadline: Duration,
+}
+
+impl Default for AuthTimeouts {
+    fn default() -> Self {
+        Self {
+            auth_message: AUTH_MESSAGE_TIMEOUT,
+            challenge_response: CHALLENGE_RESPONSE_TIMEOUT,
+            deadline: AUTH_PHASE_DEADLINE,
+        }
+    }
 }
 
 /// Authentication manager
@@ -51,10 +94,24 @@ pub struct AuthManager {
     acl_manager: Arc<AccessControlManager>,
     /// Challenge manager
     challenge_manager: Arc<ChallengeManager>,
+    /// Decides which authenticated keys may connect
+    backend: Box<dyn AuthBackend>,
     /// Key manager (unused field)
     _key_manager: Arc<KeyManager>, // Prefix if unused
-    /// Failed authentication attempts (client address -> count)
-    failed_attempts: Arc<tokio::sync::Mutex<std::collections::HashMap<String, usize>>>,
+    /// Times of recent failed authentication attempts per source IP, oldest first
+    failed_attempts: Arc<tokio::sync::Mutex<HashMap<IpAddr, VecDeque<Instant>>>>,
+    /// Failure limit, adjustable at runtime
+    failure_limit: RwLock<FailureLimit>,
+    /// Time allowed for each authentication step and for the whole phase
+    auth_timeouts: AuthTimeouts,
+    /// Session resumption tokens, when enabled
+    resumption: Option<Arc<ResumptionStore>>,
+    /// Destination for authentication audit events, when auditing is enabled
+    audit: Option<Arc<dyn AuditSink>>,
+    /// What clients are told when authentication fails
+    acl_deny_response: AclDenyResponse,
+    /// Per-source-IP limit on pre-authentication Hello queries; `None` when they are disabled
+    capability_queries: Option<RateLimiter>,
 }
 
 impl AuthManager {
@@ -64,6 +121,7 @@ impl AuthManager {
         key_manager: Arc<KeyManager>,
         challenge_timeout: Duration,
         max_challenges: usize,
+        challenge_binding: ChallengeAddressBinding,
     ) -> Result<Self, AuthError> {
         let acl_manager = Arc::new(AccessControlManager::new(acl_path).await
             .map_err(AuthError::Acl)?);
@@ -72,16 +130,107 @@ impl AuthManager {
             key_manager.clone(),
             challenge_timeout,
             max_challenges,
-        ));
+        ).with_address_binding(challenge_binding));
 
         Ok(Self {
+            backend: Box::new(AclAuthBackend::new(acl_manager.clone())),
             acl_manager,
             challenge_manager,
             _key_manager: key_manager, // Assign to prefixed field
-            failed_attempts: Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new())),
+            failed_attempts: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+            failure_limit: RwLock::new(FailureLimit {
+                max_failures: MAX_AUTH_ATTEMPTS,
+                window: AUTH_FAILURE_WINDOW,
+            }),
+            auth_timeouts: AuthTimeouts::default(),
+            resumption: None,
+            capability_queries: None,
+            audit: None,
+            acl_deny_response: AclDenyResponse::default(),
         })
     }
 
+    /// Refuse a source IP once it has failed `max_failures` times within `window` (0 = unlimited)
+    pub fn with_failure_limit(self, max_failures: usize, window: Duration) -> Self {
+        self.set_failure_limit(max_failures, window);
+        self
+    }
+
+    /// Change the failure limit of a running server; failures already recorded still count
+    pub fn set_failure_limit(&self, max_failures: usize, window: Duration) {
+        *self.failure_limit.write() = FailureLimit { max_failures, window };
+    }
+
+    /// Current failure limit
+    pub fn failure_limit(&self) -> FailureLimit {
+        *self.failure_limit.read()
+    }
+
+    /// Override the authentication step timeouts and phase deadline
+    pub fn with_auth_timeouts(mut self, timeouts: AuthTimeouts) -> Self {
+        self.auth_timeouts = timeouts;
+        self
+    }
+
+    /// Time allowed for each authentication step and for the whole phase
+    pub fn auth_timeouts(&self) -> AuthTimeouts {
+        self.auth_timeouts
+    }
+
+    /// Choose whether failed authentications report their reason or a generic error
+    pub fn with_acl_deny_response(mut self, response: AclDenyResponse) -> Self {
+        self.acl_deny_response = response;
+        self
+    }
+
+    /// What clients are told when authentication fails
+    pub fn acl_deny_response(&self) -> AclDenyResponse {
+        self.acl_deny_response
+    }
+
+    /// Verify challenge signatures in batches collected over `window`.
+    /// Must be called before the auth manager is shared.
+    pub fn with_signature_batching(mut self, window: Duration) -> Self {
+        Arc::get_mut(&mut self.challenge_manager)
+            .expect("signature batching must be set before the auth manager is shared")
+            .set_signature_batcher(Arc::new(SignatureBatcher::new(window, MAX_SIGNATURE_BATCH)));
+        self
+    }
+
+    /// Require proof of work with challenges, from `base` difficulty when idle up to `max` when
+    /// challenge slots are full. Must be called before the auth manager is shared.
+    pub fn with_proof_of_work(mut self, base: u8, max: u8) -> Self {
+        Arc::get_mut(&mut self.challenge_manager)
+            .expect("proof of work must be set before the auth manager is shared")
+            .set_proof_of_work(base, max);
+        self
+    }
+
+    /// Let clients resume a session within `ttl` of it ending, skipping the challenge
+    pub fn with_session_resumption(mut self, ttl: Duration) -> Self {
+        self.resumption = Some(Arc::new(ResumptionStore::new(ttl)));
+        self
+    }
+
+    /// Authorize clients with `backend` instead of the local ACL
+    pub fn with_backend(mut self, backend: Box<dyn AuthBackend>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Record every authentication decision to `sink`
+    pub fn with_audit_sink(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.audit = Some(sink);
+        self
+    }
+
+    /// Record an authentication decision, if auditing is enabled
+    pub async fn audit(&self, event: AuthAuditEvent) {
+        if let Some(sink) = &self.audit {
+            sink.record(&event).await;
+        }
+    }
+
     /// Get the ACL manager
     pub fn acl_manager(&self) -> Arc<AccessControlManager> {
         self.acl_manager.clone()
@@ -92,28 +241,46 @@ impl AuthManager {
         self.challenge_manager.clone()
     }
 
-    /// Generate a new authentication challenge for a client
-    pub async fn generate_challenge(
-        &self,
-        client_addr: &str,
-    ) -> Result<(String, Vec<u8>), AuthError> {
-        // Check failed attempts
-        let mut failed_attempts = self.failed_attempts.lock().await;
-        let attempts = failed_attempts.entry(client_addr.to_string()).or_insert(0);
+    /// Answer up to `per_minute` pre-authentication Hello queries per source IP (0 = disabled)
+    pub fn with_capability_queries(mut self, per_minute: usize) -> Self {
+        self.capability_queries = (per_minute > 0).then(|| RateLimiter::new(per_minute, CAPABILITY_QUERY_WINDOW));
+        self
+    }
 
-        if *attempts >= MAX_AUTH_ATTEMPTS {
-            return Err(AuthError::TooManyAttempts);
+    /// Whether clients may query capabilities before authenticating
+    pub fn capability_queries_enabled(&self) -> bool {
+        self.capability_queries.is_some()
+    }
+
+    /// Count a Hello query from `ip`; false when queries are disabled or `ip` is over its limit
+    pub async fn allow_capability_query(&self, ip: &IpAddr) -> bool {
+        match &self.capability_queries {
+            Some(limiter) => limiter.check_rate_limit(ip).await,
+            None => false,
         }
+    }
 
+    /// Get the session resumption store, if resumption is enabled
+    pub fn resumption(&self) -> Option<Arc<ResumptionStore>> {
+        self.resumption.clone()
+    }
+
+    /// Generate a new authentication challenge for a client, returning its ID, data and proof-of-work difficulty
+    pub async fn generate_challenge(
+        &self,
+        client_addr: &str,
+    ) -> Result<(String, Vec<u8>, u8), AuthError> {
         // Convert string to SocketAddr for challenge manager (if needed)
         let socket_addr = client_addr.parse::<SocketAddr>()
             .map_err(|_| AuthError::InvalidFormat(format!("Invalid address format: {}", client_addr)))?;
 
+        self.check_failure_limit(socket_addr.ip()).await?;
+
         // Generate challenge
         let challenge = self.challenge_manager.generate_challenge(socket_addr).await
             .map_err(AuthError::Challenge)?;
 
-        Ok((challenge.id.clone(), challenge.data.clone()))
+        Ok((challenge.id, challenge.data, challenge.difficulty))
     }
 
     /// Verify a challenge response and authenticate the client
@@ -124,6 +291,7 @@ impl AuthManager {
         signature: &str,
         public_key: &str,
         client_addr: &str,
+        pow_nonce: Option<u64>,
     ) -> Result<(), AuthError> {
         // Validate input parameters
         if !StringValidator::is_valid_solana_pubkey(public_key) {
@@ -142,19 +310,25 @@ impl AuthManager {
         let socket_addr = client_addr.parse::<SocketAddr>()
             .map_err(|_| AuthError::InvalidFormat(format!("Invalid address format: {}", client_addr)))?;
 
+        self.check_failure_limit(socket_addr.ip()).await?;
+
         // Verify the challenge with the challenge manager
         let result = self.challenge_manager.verify_challenge(
             challenge_id,
             socket_addr,
             signature, // Pass original signature string
             public_key, // Pass original public key string
+            pow_nonce,
         ).await;
 
         // Handle the result
         if let Err(e) = &result {
             match e {
-                ChallengeError::Expired | ChallengeError::SignatureVerificationFailed => {
-                    self.record_failed_attempt(client_addr).await;
+                ChallengeError::Expired
+                | ChallengeError::SignatureVerificationFailed
+                | ChallengeError::InsufficientWork(_)
+                | ChallengeError::AlreadyUsed => {
+                    self.record_failed_attempt(socket_addr.ip()).await;
                 }
                 _ => {}
             }
@@ -163,35 +337,119 @@ impl AuthManager {
         // Propagate any verification errors
         result.map_err(AuthError::Challenge)?;
 
-        // Reset failed attempts on successful verification
-        self.reset_failed_attempts(client_addr).await;
+        // Check if client is allowed by the backend, and only then reset failed
+        // attempts, so a valid signature from a denied key cannot clear them.
+        // Under the strict deny response a denied key counts against its source
+        // like a bad signature.
+        let allowed = self.backend.is_client_allowed(public_key).await.allowed;
+        match (allowed, self.acl_deny_response) {
+            (true, _) => self.reset_failed_attempts(socket_addr.ip()).await,
+            (false, AclDenyResponse::Strict) => self.record_failed_attempt(socket_addr.ip()).await,
+            (false, _) => {}
+        }
+        if !allowed {
+            return Err(AuthError::AccessDenied(format!("Access denied for {}", public_key)));
+        }
+
+        self.backend.after_verify(public_key, client_addr).await
+    }
+
+    /// Authorize a client whose key was proven by a verified TLS client certificate.
+    ///
+    /// Runs the same backend checks as a successful challenge.
+    pub async fn verify_certificate_key(&self, public_key: &str, client_addr: &str) -> Result<(), AuthError> {
+        self.authorize_proven_key(public_key, client_addr).await
+    }
+
+    /// Resume a session from a resumption token instead of a challenge.
+    ///
+    /// Rejected tokens count as failed attempts for the source IP; a
+    /// redeemed token then passes the same backend checks as a challenge.
+    pub async fn resume_session(
+        &self,
+        token: &str,
+        public_key: &str,
+        proof: &[u8],
+        client_addr: &str,
+    ) -> Result<ResumedSession, AuthError> {
+        let store = self.resumption.as_ref()
+            .ok_or_else(|| AuthError::AuthenticationFailed("Session resumption is disabled".to_string()))?;
+        let socket_addr = client_addr.parse::<SocketAddr>()
+            .map_err(|_| AuthError::InvalidFormat(format!("Invalid address format: {}", client_addr)))?;
+
+        self.check_failure_limit(socket_addr.ip()).await?;
+
+        let session = match store.redeem(token, public_key, proof).await {
+            Ok(session) => session,
+            Err(e) => {
+                self.record_failed_attempt(socket_addr.ip()).await;
+                return Err(AuthError::Resumption(e));
+            }
+        };
+        self.authorize_proven_key(public_key, client_addr).await?;
+        self.reset_failed_attempts(socket_addr.ip()).await;
+        Ok(session)
+    }
 
-        // Check if client is allowed in ACL
-        if !self.acl_manager.is_allowed(public_key).await {
+    /// Backend checks for a key proven without a challenge
+    async fn authorize_proven_key(&self, public_key: &str, client_addr: &str) -> Result<(), AuthError> {
+        if !self.backend.is_client_allowed(public_key).await.allowed {
             return Err(AuthError::AccessDenied(format!("Access denied for {}", public_key)));
         }
 
-        Ok(())
+        self.backend.after_verify(public_key, client_addr).await
+    }
+
+    /// Refuse `ip` while it has `max_failures` failures within the window
+    async fn check_failure_limit(&self, ip: IpAddr) -> Result<(), AuthError> {
+        let limit = self.failure_limit();
+        if limit.max_failures == 0 {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut failed_attempts = self.failed_attempts.lock().await;
+        let failures = match failed_attempts.get_mut(&ip) {
+            Some(failures) => failures,
+            None => return Ok(()),
+        };
+        prune_failures(failures, now, limit.window);
+        if failures.len() < limit.max_failures {
+            return Ok(());
+        }
+        // Refused until the oldest counted failure leaves the window
+        let retry_after = failures.front()
+            .map_or(Duration::ZERO, |oldest| (*oldest + limit.window).saturating_duration_since(now));
+        Err(AuthError::TooManyAttempts { retry_after })
     }
 
     /// Record a failed authentication attempt
-    async fn record_failed_attempt(&self, client_addr: &str) {
+    async fn record_failed_attempt(&self, ip: IpAddr) {
+        let limit = self.failure_limit();
+        if limit.max_failures == 0 {
+            return;
+        }
+        let now = Instant::now();
         let mut failed_attempts = self.failed_attempts.lock().await;
-        let count = failed_attempts.entry(client_addr.to_string()).or_insert(0);
-        *count += 1;
+        let failures = failed_attempts.entry(ip).or_default();
+        prune_failures(failures, now, limit.window);
+        failures.push_back(now);
+        // Only the newest `max_failures` matter for the limit
+        while failures.len() > limit.max_failures {
+            failures.pop_front();
+        }
 
-        if *count >= MAX_AUTH_ATTEMPTS {
+        if failures.len() == limit.max_failures {
             warn!(
-                "Client {} reached maximum failed authentication attempts ({})",
-                client_addr, MAX_AUTH_ATTEMPTS
+                "Client {} reached maximum failed authentication attempts ({} within {:?})",
+                ip, limit.max_failures, limit.window
             );
         }
     }
 
     /// Reset failed authentication attempts
-    async fn reset_failed_attempts(&self, client_addr: &str) {
+    async fn reset_failed_attempts(&self, ip: IpAddr) {
         let mut failed_attempts = self.failed_attempts.lock().await;
-        failed_attempts.remove(client_addr);
+        failed_attempts.remove(&ip);
     }
 
     /// Add a client to the ACL
@@ -204,9 +462,9 @@ impl AuthManager {
         self.acl_manager.remove_entry(public_key).await.map_err(AuthError::Acl)
     }
 
-    /// Check if a client is allowed to connect
-    pub async fn is_client_allowed(&self, public_key: &str) -> bool {
-        self.acl_manager.is_allowed(public_key).await
+    /// Check if a client is allowed to connect, returning its access policy
+    pub async fn is_client_allowed(&self, public_key: &str) -> AccessPolicy {
+        self.backend.is_client_allowed(public_key).await
     }
 
     /// Get client information from the ACL
@@ -219,10 +477,32 @@ impl AuthManager {
         self.challenge_manager.cleanup_expired().await
     }
 
-    /// Clean up failed attempts
-    pub async fn cleanup_failed_attempts(&self) {
+    /// Drop expired session resumption tokens
+    pub async fn cleanup_resumption_tokens(&self) -> usize {
+        match &self.resumption {
+            Some(store) => store.cleanup_expired().await,
+            None => 0,
+        }
+    }
+
+    /// Forget failed attempts that have left the window, returning the number of source IPs dropped
+    pub async fn cleanup_failed_attempts(&self) -> usize {
+        let window = self.failure_limit().window;
+        let now = Instant::now();
         let mut failed_attempts = self.failed_attempts.lock().await;
-        failed_attempts.clear();
+        let before = failed_attempts.len();
+        failed_attempts.retain(|_, failures| {
+            prune_failures(failures, now, window);
+            !failures.is_empty()
+        });
+        before - failed_attempts.len()
+    }
+}
+
+/// Drop failures older than `window` from the front of `failures`
+fn prune_failures(failures: &mut VecDeque<Instant>, now: Instant, window: Duration) {
+    while failures.front().map_or(false, |t| now.saturating_duration_since(*t) >= window) {
+        failures.pop_front();
     }
 }
 
@@ -249,13 +529,14 @@ mod tests {
             key_manager.clone(),
             Duration::from_secs(10),
             100,
+            ChallengeAddressBinding::Ip,
         ).await.unwrap();
 
         // Test client address
         let client_addr_str = "127.0.0.1:12345";
 
         // Generate challenge
-        let (challenge_id, challenge_data) = auth_manager.generate_challenge(client_addr_str).await.unwrap();
+        let (challenge_id, challenge_data, _) = auth_manager.generate_challenge(client_addr_str).await.unwrap();
 
         // Create test client's keypair
         let client_keypair = solana_sdk::signature::Keypair::new();
@@ -272,6 +553,7 @@ mod tests {
             &signature,
             &client_pubkey,
             client_addr_str,
+            None,
         ).await;
 
         // Should fail since client is not in ACL yet
@@ -286,13 +568,16 @@ mod tests {
             is_allowed: true,
             bandwidth_limit: 0,
             max_session_duration: 3600,
+            max_sessions: 0,
             static_ip: None,
             notes: None,
+            expires_at: None,
+            roles: Vec::new(),
         };
         auth_manager.add_client(entry).await.unwrap();
 
         // Regenerate challenge (since previous one was consumed or expired)
-        let (challenge_id, challenge_data) = auth_manager.generate_challenge(client_addr_str).await.unwrap();
+        let (challenge_id, challenge_data, _) = auth_manager.generate_challenge(client_addr_str).await.unwrap();
 
         // Sign the new challenge
         // Use Signer trait method // Corrected E0599
@@ -304,9 +589,276 @@ mod tests {
             &signature,
             &client_pubkey,
             client_addr_str,
+            None,
         ).await;
 
         // Should succeed now
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_capability_query_limit() {
+        let dir = tempdir().unwrap();
+        let key_manager = Arc::new(KeyManager::new(dir.path().join("key"), Duration::from_secs(60), 100).await.unwrap());
+        let auth_manager = AuthManager::new(
+            dir.path().join("acl.json"),
+            key_manager,
+            Duration::from_secs(10),
+            100,
+            ChallengeAddressBinding::Ip,
+        ).await.unwrap();
+        let ip: IpAddr = "127.0.0.1".parse().unwrap();
+
+        // Disabled unless configured
+        assert!(!auth_manager.capability_queries_enabled());
+        assert!(!auth_manager.allow_capability_query(&ip).await);
+
+        let auth_manager = auth_manager.with_capability_queries(2);
+        assert!(auth_manager.capability_queries_enabled());
+        assert!(auth_manager.allow_capability_query(&ip).await);
+        assert!(auth_manager.allow_capability_query(&ip).await);
+        assert!(!auth_manager.allow_capability_query(&ip).await);
+
+        // Counted per source IP
+        assert!(auth_manager.allow_capability_query(&"127.0.0.2".parse().unwrap()).await);
+    }
+
+    /// Denies a fixed set of keys and rejects one more after verification
+    #[derive(Debug)]
+    struct DenyListBackend {
+        denied: Vec<String>,
+        rejected_after_verify: String,
+    }
+
+    #[async_trait::async_trait]
+    impl AuthBackend for DenyListBackend {
+        async fn is_client_allowed(&self, public_key: &str) -> AccessPolicy {
+            if self.denied.iter().any(|key| key == public_key) {
+                AccessPolicy::deny()
+            } else {
+                AccessPolicy::allow()
+            }
+        }
+
+        async fn after_verify(&self, public_key: &str, _client_addr: &str) -> Result<(), AuthError> {
+            if public_key == self.rejected_after_verify {
+                return Err(AuthError::AuthenticationFailed("rejected by hook".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_auth_backend() {
+        let dir = tempdir().unwrap();
+        let key_manager = Arc::new(KeyManager::new(dir.path().join("key"), Duration::from_secs(60), 100).await.unwrap());
+        let denied = solana_sdk::signature::Keypair::new();
+        let hooked = solana_sdk::signature::Keypair::new();
+        let allowed = solana_sdk::signature::Keypair::new();
+
+        // No ACL entries: the backend alone decides
+        let auth_manager = AuthManager::new(
+            dir.path().join("acl.json"),
+            key_manager,
+            Duration::from_secs(10),
+            100,
+            ChallengeAddressBinding::Ip,
+        ).await.unwrap().with_backend(Box::new(DenyListBackend {
+            denied: vec![denied.pubkey().to_string()],
+            rejected_after_verify: hooked.pubkey().to_string(),
+        }));
+
+        let authenticate = |keypair: &solana_sdk::signature::Keypair| {
+            let public_key = keypair.pubkey().to_string();
+            let (auth_manager, keypair) = (&auth_manager, keypair.insecure_clone());
+            async move {
+                let addr = "127.0.0.1:12345";
+                let (challenge_id, data, _) = auth_manager.generate_challenge(addr).await.unwrap();
+                let signature = keypair.sign_message(&data).to_string();
+                auth_manager.verify_challenge(&challenge_id, &signature, &public_key, addr, None).await
+            }
+        };
+
+        assert!(matches!(authenticate(&denied).await, Err(AuthError::AccessDenied(_))));
+        assert!(!auth_manager.is_client_allowed(&denied.pubkey().to_string()).await.allowed);
+        assert!(matches!(authenticate(&hooked).await, Err(AuthError::AuthenticationFailed(_))));
+        assert!(authenticate(&allowed).await.is_ok());
+        assert!(auth_manager.is_client_allowed(&allowed.pubkey().to_string()).await.allowed);
+    }
+
+    /// Fail one challenge from `addr` by signing the wrong data
+    async fn fail_challenge(auth_manager: &AuthManager, keypair: &solana_sdk::signature::Keypair, addr: &str) {
+        let (challenge_id, _, _) = auth_manager.generate_challenge(addr).await.unwrap();
+        let signature = keypair.sign_message(b"not the challenge").to_string();
+        let result = auth_manager.verify_challenge(&challenge_id, &signature, &keypair.pubkey().to_string(), addr, None).await;
+        assert!(matches!(result, Err(AuthError::Challenge(ChallengeError::SignatureVerificationFailed))), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_failure_limit_per_ip() {
+        let dir = tempdir().unwrap();
+        let key_manager = Arc::new(KeyManager::new(dir.path().join("key"), Duration::from_secs(60), 100).await.unwrap());
+        let window = Duration::from_millis(300);
+        let auth_manager = AuthManager::new(
+            dir.path().join("acl.json"),
+            key_manager,
+            Duration::from_secs(10),
+            100,
+            ChallengeAddressBinding::Ip,
+        ).await.unwrap().with_failure_limit(2, window);
+        let keypair = solana_sdk::signature::Keypair::new();
+
+        // Failures count per IP, whatever the source port
+        fail_challenge(&auth_manager, &keypair, "127.0.0.1:40001").await;
+        fail_challenge(&auth_manager, &keypair, "127.0.0.1:40002").await;
+        match auth_manager.generate_challenge("127.0.0.1:40003").await {
+            Err(AuthError::TooManyAttempts { retry_after }) => assert!(retry_after <= window),
+            other => panic!("Expected TooManyAttempts, got {:?}", other),
+        }
+
+        // Other addresses are unaffected
+        assert!(auth_manager.generate_challenge("127.0.0.2:40001").await.is_ok());
+
+        // The limit lifts once the failures age out of the window
+        tokio::time::sleep(window + Duration::from_millis(50)).await;
+        assert!(auth_manager.generate_challenge("127.0.0.1:40004").await.is_ok());
+        assert_eq!(auth_manager.cleanup_failed_attempts().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_success_resets_failures() {
+        let dir = tempdir().unwrap();
+        let key_manager = Arc::new(KeyManager::new(dir.path().join("key"), Duration::from_secs(60), 100).await.unwrap());
+        let auth_manager = AuthManager::new(
+            dir.path().join("acl.json"),
+            key_manager,
+            Duration::from_secs(10),
+            100,
+            ChallengeAddressBinding::Ip,
+        ).await.unwrap().with_failure_limit(2, Duration::from_secs(60));
+        let keypair = solana_sdk::signature::Keypair::new();
+        let answer = |addr: &'static str| {
+            let auth_manager = &auth_manager;
+            let keypair = &keypair;
+            async move {
+                let (challenge_id, data, _) = auth_manager.generate_challenge(addr).await.unwrap();
+                let signature = keypair.sign_message(&data).to_string();
+                auth_manager.verify_challenge(&challenge_id, &signature, &keypair.pubkey().to_string(), addr, None).await
+            }
+        };
+
+        // A valid signature from a key the ACL denies leaves the count alone
+        let addr = "127.0.0.1:40001";
+        fail_challenge(&auth_manager, &keypair, addr).await;
+        assert!(matches!(answer(addr).await, Err(AuthError::AccessDenied(_))));
+        fail_challenge(&auth_manager, &keypair, addr).await;
+        assert!(matches!(auth_manager.generate_challenge(addr).await, Err(AuthError::TooManyAttempts { .. })));
+
+        // Once the key is allowed, a successful challenge clears it
+        auth_manager.add_client(AccessControlManager::create_allow_entry(&keypair.pubkey().to_string())).await.unwrap();
+        let addr = "127.0.0.1:40002";
+        fail_challenge(&auth_manager, &keypair, addr).await;
+        assert!(answer(addr).await.is_ok());
+        fail_challenge(&auth_manager, &keypair, addr).await;
+        assert!(auth_manager.generate_challenge(addr).await.is_ok());
+        fail_challenge(&auth_manager, &keypair, addr).await;
+        assert!(matches!(auth_manager.generate_challenge(addr).await, Err(AuthError::TooManyAttempts { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_resume_session() {
+        use crate::auth::resumption::ResumedSession;
+        use crate::crypto::encryption::sign_session_resumption;
+
+        let dir = tempdir().unwrap();
+        let key_manager = Arc::new(KeyManager::new(dir.path().join("key"), Duration::from_secs(60), 100).await.unwrap());
+        let denied = solana_sdk::signature::Keypair::new().pubkey().to_string();
+        let client = solana_sdk::signature::Keypair::new().pubkey().to_string();
+        let auth_manager = AuthManager::new(
+            dir.path().join("acl.json"),
+            key_manager,
+            Duration::from_secs(10),
+            100,
+            ChallengeAddressBinding::Ip,
+        ).await.unwrap()
+         .with_failure_limit(1, Duration::from_secs(60))
+         .with_backend(Box::new(DenyListBackend { denied: vec![denied.clone()], rejected_after_verify: String::new() }))
+         .with_session_resumption(Duration::from_secs(60));
+        let store = auth_manager.resumption().unwrap();
+        let session = ResumedSession {
+            session_key: vec![3; 32],
+            ip_address: "10.7.0.9".to_string(),
+            send_counter: Default::default(),
+            received: Default::default(),
+        };
+
+        let token = store.issue(&client).await;
+        store.arm(&token, session.clone()).await;
+        let proof = sign_session_resumption(&session.session_key, &token, &client).unwrap();
+        assert_eq!(auth_manager.resume_session(&token, &client, &proof, "127.0.0.1:1000").await.unwrap(), session);
+
+        // A redeemed token still has to pass the backend
+        let token = store.issue(&denied).await;
+        store.arm(&token, session.clone()).await;
+        let proof = sign_session_resumption(&session.session_key, &token, &denied).unwrap();
+        assert!(matches!(
+            auth_manager.resume_session(&token, &denied, &proof, "127.0.0.1:1000").await,
+            Err(AuthError::AccessDenied(_))
+        ));
+
+        // Rejected tokens count towards the failure limit
+        let result = auth_manager.resume_session(&token, &client, &proof, "127.0.0.2:1000").await;
+        assert!(matches!(result, Err(AuthError::Resumption(ResumptionError::UnknownToken))), "{:?}", result);
+        assert!(matches!(auth_manager.generate_challenge("127.0.0.2:1000").await, Err(AuthError::TooManyAttempts { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_reload_acl() {
+        let dir = tempdir().unwrap();
+        let acl_path = dir.path().join("acl.json");
+        let key_manager = Arc::new(KeyManager::new(dir.path().join("key"), Duration::from_secs(60), 100).await.unwrap());
+        let client = solana_sdk::signature::Keypair::new().pubkey().to_string();
+
+        let mut acl = crate::auth::acl::AccessControlList::new();
+        acl.set_default_policy("deny").unwrap();
+        acl.add_entry(AccessControlManager::create_allow_entry(&client));
+        std::fs::write(&acl_path, serde_json::to_string(&acl).unwrap()).unwrap();
+
+        let auth_manager = Arc::new(AuthManager::new(
+            &acl_path,
+            key_manager,
+            Duration::from_secs(10),
+            100,
+            ChallengeAddressBinding::Ip,
+        ).await.unwrap());
+        assert!(auth_manager.is_client_allowed(&client).await.allowed);
+
+        // Revoke the key on disk; checks running during the reload see one list or the other
+        acl.add_entry(AccessControlManager::create_deny_entry(&client, Some("revoked")));
+        std::fs::write(&acl_path, serde_json::to_string(&acl).unwrap()).unwrap();
+        let checks: Vec<_> = (0..8)
+            .map(|_| {
+                let auth_manager = auth_manager.clone();
+                let client = client.clone();
+                tokio::spawn(async move { auth_manager.is_client_allowed(&client).await })
+            })
+            .collect();
+        auth_manager.acl_manager().reload().await.unwrap();
+        for check in checks {
+            let policy = check.await.unwrap();
+            assert!(policy == AccessPolicy::deny() || policy.allowed, "{:?}", policy);
+        }
+        assert!(!auth_manager.is_client_allowed(&client).await.allowed);
+
+        // A malformed or missing file keeps the current list
+        std::fs::write(&acl_path, "{ not json").unwrap();
+        assert!(auth_manager.acl_manager().reload().await.is_err());
+        std::fs::remove_file(&acl_path).unwrap();
+        assert!(auth_manager.acl_manager().reload().await.is_err());
+        assert!(!auth_manager.is_client_allowed(&client).await.allowed);
+
+        // The failure limit changes in place
+        auth_manager.set_failure_limit(5, Duration::from_secs(30));
+        assert_eq!(auth_manager.failure_limit(), FailureLimit { max_failures: 5, window: Duration::from_secs(30) });
+    }
 }
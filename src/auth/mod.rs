@@ -5,8 +5,13 @@
 //! for client connections.
 
 pub mod acl;
+pub mod audit;
+pub mod backend;
+pub mod certificate;
 pub mod challenge;
 pub mod manager;
+pub mod pow;
+pub mod resumption;
 
 // Re-export commonly used items
 // Removed unused AccessControlList re-export (it's used internally via manager)
@@ -0,0 +1,130 @@
+// src/auth/pow.rs
+//! Proof of work for authentication challenges.
+//!
+//! When enabled, a client must find a nonce such that
+//! `SHA-256(challenge data || nonce as 8 big-endian bytes)` starts with at
+//! least `difficulty` zero bits before its signature is checked. Checking
+//! the work costs one hash, so floods of bogus responses are turned away
+//! before any Ed25519 verification, while each honest client pays
+//! `2^difficulty` hashes on average.
+
+use sha2::{Digest, Sha256};
+
+/// Largest difficulty the server will issue; higher values are impractical for clients
+pub const MAX_POW_DIFFICULTY: u8 = 32;
+
+/// Hash a challenge together with a candidate nonce
+pub fn pow_hash(challenge: &[u8], nonce: u64) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(challenge);
+    hasher.update(nonce.to_be_bytes());
+    hasher.finalize().into()
+}
+
+/// Number of leading zero bits in `hash`
+pub fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        if *byte != 0 {
+            return bits + byte.leading_zeros();
+        }
+        bits += 8;
+    }
+    bits
+}
+
+/// Check that `nonce` solves `challenge` at `difficulty`
+pub fn verify_pow(challenge: &[u8], nonce: u64, difficulty: u8) -> bool {
+    difficulty == 0 || leading_zero_bits(&pow_hash(challenge, nonce)) >= u32::from(difficulty)
+}
+
+/// Find the smallest nonce solving `challenge` at `difficulty`, as a client would
+pub fn solve_pow(challenge: &[u8], difficulty: u8) -> u64 {
+    (0..=u64::MAX)
+        .find(|nonce| verify_pow(challenge, *nonce, difficulty))
+        .expect("no nonce meets the difficulty")
+}
+
+/// Difficulty to issue with `pending` of `capacity` challenge slots in use.
+///
+/// Scales linearly from `base` when idle to `max` when full, so a flood
+/// of handshakes makes each further one more expensive. A `max` at or
+/// below `base` keeps the difficulty fixed.
+pub fn adaptive_difficulty(base: u8, max: u8, pending: usize, capacity: usize) -> u8 {
+    if max <= base || capacity == 0 {
+        return base;
+    }
+    let load = pending.min(capacity) as f64 / capacity as f64;
+    base + (f64::from(max - base) * load).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_valid_and_invalid_pow() {
+        // First nonce meeting difficulty 12, found independently
+        let challenge = b"challenge data";
+        assert_eq!(solve_pow(challenge, 12), 6782);
+        assert!(verify_pow(challenge, 6782, 12));
+        assert!(!verify_pow(challenge, 6781, 12));
+        // Its hash happens to have 13 leading zero bits
+        assert!(verify_pow(challenge, 6782, 13));
+        assert!(!verify_pow(challenge, 6782, 14));
+
+        // The solution is bound to the challenge
+        assert!(!verify_pow(b"other challenge", 6782, 12));
+        // Difficulty zero accepts anything
+        assert!(verify_pow(challenge, 0, 0));
+    }
+
+    #[test]
+    fn test_leading_zero_bits() {
+        assert_eq!(leading_zero_bits(&[0xff]), 0);
+        assert_eq!(leading_zero_bits(&[0x00, 0x10]), 11);
+        assert_eq!(leading_zero_bits(&[0x00, 0x00]), 16);
+        // SHA-256("abc" || 0u64) = 4b5c6fd3...: 0x4b has one leading zero bit
+        assert_eq!(pow_hash(b"abc", 0)[..4], [0x4b, 0x5c, 0x6f, 0xd3]);
+        assert_eq!(leading_zero_bits(&pow_hash(b"abc", 0)), 1);
+    }
+
+    #[test]
+    fn test_adaptive_difficulty() {
+        assert_eq!(adaptive_difficulty(8, 20, 0, 1000), 8);
+        assert_eq!(adaptive_difficulty(8, 20, 500, 1000), 14);
+        assert_eq!(adaptive_difficulty(8, 20, 1000, 1000), 20);
+        // Over capacity is treated as full
+        assert_eq!(adaptive_difficulty(8, 20, 5000, 1000), 20);
+        // Fixed difficulty
+        assert_eq!(adaptive_difficulty(8, 0, 1000, 1000), 8);
+        assert_eq!(adaptive_difficulty(0, 0, 1000, 1000), 0);
+    }
+
+    #[test]
+    #[ignore] // Benchmark; run with --ignored --nocapture
+    fn bench_pow_verification() {
+        const CHECKS: u32 = 100_000;
+        let challenge = [7u8; 32];
+        let nonce = solve_pow(&challenge, 16);
+
+        let start = Instant::now();
+        for _ in 0..CHECKS {
+            assert!(verify_pow(&challenge, nonce, 16));
+        }
+        let verify = start.elapsed();
+
+        let start = Instant::now();
+        let solved = solve_pow(&challenge, 16);
+        let solve = start.elapsed();
+        assert_eq!(solved, nonce);
+
+        println!(
+            "proof of work: verify {:?}/check, solve at difficulty 16 {:?} ({} hashes)",
+            verify / CHECKS,
+            solve,
+            nonce + 1
+        );
+    }
+}
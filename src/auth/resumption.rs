@@ -0,0 +1,275 @@
+// src/auth/resumption.rs
+//! Session resumption tokens.
+//!
+//! Clients that negotiate `session-resumption` receive a token when their
+//! session starts. The token is armed when the session ends, binding it to
+//! the client's public key, its last session key and its IP, and stays
+//! valid for a short time afterwards. The Data counters used under the key
+//! are carried along, so the resumed session, which keeps the key, never
+//! sends or accepts a counter the previous one already did. A reconnecting client presents the
+//! token in its `Auth` packet together with a MAC under the old session key
+//! (see `crypto::encryption::sign_session_resumption`), which proves it
+//! held the session without another challenge round trip.
+//!
+//! Tokens are single-use: an armed token is consumed by the first attempt
+//! to redeem it, whether or not that attempt succeeds.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+use crate::crypto::encryption::verify_session_resumption;
+use crate::crypto::nonce::SendCounter;
+use crate::crypto::replay::ReplayWindow;
+use crate::utils::random_string;
+
+/// Length of a resumption token in alphanumeric characters
+pub const RESUMPTION_TOKEN_LENGTH: usize = 32;
+
+/// Error type for resumption attempts
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ResumptionError {
+    #[error("Unknown or already used resumption token")]
+    UnknownToken,
+
+    #[error("Resumption token expired")]
+    Expired,
+
+    #[error("Session for this token is still active")]
+    SessionActive,
+
+    #[error("Resumption token was issued to another key")]
+    KeyMismatch,
+
+    #[error("Invalid resumption proof")]
+    InvalidProof,
+}
+
+/// State restored for a resumed session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResumedSession {
+    /// Session key in use when the previous session ended
+    pub session_key: Vec<u8>,
+    /// IP address the previous session held
+    pub ip_address: String,
+    /// Outbound Data counters taken under the key, so none is taken again
+    pub send_counter: SendCounter,
+    /// Inbound Data counters accepted under the key, so none is accepted again
+    pub received: ReplayWindow,
+}
+
+/// A token and what it resumes
+#[derive(Debug)]
+struct ResumptionEntry {
+    /// Client public key the token was issued to
+    public_key: String,
+    /// Session state, set once the session has ended
+    session: Option<ResumedSession>,
+    /// End of validity, set once the session has ended
+    expires_at: Option<Instant>,
+}
+
+/// Issues and redeems resumption tokens
+#[derive(Debug)]
+pub struct ResumptionStore {
+    /// Tokens by value
+    entries: Mutex<HashMap<String, ResumptionEntry>>,
+    /// How long a token stays valid after its session ends
+    ttl: Duration,
+}
+
+impl ResumptionStore {
+    /// Create a store whose tokens stay valid for `ttl` after their session ends
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    /// How long a token stays valid after its session ends
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Issue a token for a session of `public_key` that has just started.
+    ///
+    /// The token cannot be redeemed until `arm` is called at teardown.
+    pub async fn issue(&self, public_key: &str) -> String {
+        let mut entries = self.entries.lock().await;
+        let token = loop {
+            let token = random_string(RESUMPTION_TOKEN_LENGTH);
+            if !entries.contains_key(&token) {
+                break token;
+            }
+        };
+        entries.insert(token.clone(), ResumptionEntry {
+            public_key: public_key.to_string(),
+            session: None,
+            expires_at: None,
+        });
+        token
+    }
+
+    /// Make `token` redeemable for `session` until the TTL elapses.
+    /// Returns false if the token is unknown.
+    pub async fn arm(&self, token: &str, session: ResumedSession) -> bool {
+        let mut entries = self.entries.lock().await;
+        match entries.get_mut(token) {
+            Some(entry) => {
+                entry.session = Some(session);
+                entry.expires_at = Some(Instant::now() + self.ttl);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Withdraw a token whose session must not be resumed
+    pub async fn revoke(&self, token: &str) {
+        self.entries.lock().await.remove(token);
+    }
+
+    /// Redeem `token` for `public_key`, checking `proof` against the stored session key
+    pub async fn redeem(&self, token: &str, public_key: &str, proof: &[u8]) -> Result<ResumedSession, ResumptionError> {
+        let mut entries = self.entries.lock().await;
+        // A live session's token is left in place for its teardown
+        match entries.get(token) {
+            Some(entry) if entry.session.is_none() => return Err(ResumptionError::SessionActive),
+            Some(_) => {}
+            None => return Err(ResumptionError::UnknownToken),
+        }
+        let entry = entries.remove(token).ok_or(ResumptionError::UnknownToken)?;
+        drop(entries);
+
+        if entry.expires_at.map_or(true, |expires_at| Instant::now() >= expires_at) {
+            return Err(ResumptionError::Expired);
+        }
+        if entry.public_key != public_key {
+            return Err(ResumptionError::KeyMismatch);
+        }
+        let session = entry.session.ok_or(ResumptionError::UnknownToken)?;
+        if !verify_session_resumption(&session.session_key, token, public_key, proof) {
+            return Err(ResumptionError::InvalidProof);
+        }
+        Ok(session)
+    }
+
+    /// Drop armed tokens past their TTL, returning how many were removed
+    pub async fn cleanup_expired(&self) -> usize {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().await;
+        let before = entries.len();
+        entries.retain(|_, entry| entry.expires_at.map_or(true, |expires_at| now < expires_at));
+        before - entries.len()
+    }
+
+    /// Number of tokens held, live and armed
+    pub async fn token_count(&self) -> usize {
+        self.entries.lock().await.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::encryption::sign_session_resumption;
+
+    const CLIENT_KEY: &str = "GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB";
+    const OTHER_KEY: &str = "AKnL4NNf3DGWZJS6cPknBuEGnVsV4A4m5tgebLHaRSZ9";
+
+    fn session() -> ResumedSession {
+        ResumedSession {
+            session_key: vec![7; 32],
+            ip_address: "10.7.0.5".to_string(),
+            send_counter: SendCounter::default(),
+            received: ReplayWindow::default(),
+        }
+    }
+
+    fn proof(token: &str, public_key: &str) -> Vec<u8> {
+        sign_session_resumption(&session().session_key, token, public_key).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_successful_resumption() {
+        let store = ResumptionStore::new(Duration::from_secs(60));
+        let token = store.issue(CLIENT_KEY).await;
+        assert_eq!(token.len(), RESUMPTION_TOKEN_LENGTH);
+
+        // Not redeemable while the session is live
+        assert_eq!(
+            store.redeem(&token, CLIENT_KEY, &proof(&token, CLIENT_KEY)).await,
+            Err(ResumptionError::SessionActive)
+        );
+
+        assert!(store.arm(&token, session()).await);
+        assert_eq!(store.redeem(&token, CLIENT_KEY, &proof(&token, CLIENT_KEY)).await, Ok(session()));
+        assert_eq!(store.token_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_expired_token() {
+        let store = ResumptionStore::new(Duration::from_millis(20));
+        let token = store.issue(CLIENT_KEY).await;
+        store.arm(&token, session()).await;
+        tokio::time::sleep(Duration::from_millis(40)).await;
+
+        assert_eq!(
+            store.redeem(&token, CLIENT_KEY, &proof(&token, CLIENT_KEY)).await,
+            Err(ResumptionError::Expired)
+        );
+
+        // Unredeemed expired tokens are swept
+        let stale = store.issue(CLIENT_KEY).await;
+        let live = store.issue(CLIENT_KEY).await;
+        store.arm(&stale, session()).await;
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(store.cleanup_expired().await, 1);
+        assert!(store.arm(&live, session()).await);
+    }
+
+    #[tokio::test]
+    async fn test_token_reuse_rejected() {
+        let store = ResumptionStore::new(Duration::from_secs(60));
+        let token = store.issue(CLIENT_KEY).await;
+        store.arm(&token, session()).await;
+
+        assert!(store.redeem(&token, CLIENT_KEY, &proof(&token, CLIENT_KEY)).await.is_ok());
+        assert_eq!(
+            store.redeem(&token, CLIENT_KEY, &proof(&token, CLIENT_KEY)).await,
+            Err(ResumptionError::UnknownToken)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resumption_requires_key_and_proof() {
+        let store = ResumptionStore::new(Duration::from_secs(60));
+
+        let token = store.issue(CLIENT_KEY).await;
+        store.arm(&token, session()).await;
+        assert_eq!(
+            store.redeem(&token, OTHER_KEY, &proof(&token, OTHER_KEY)).await,
+            Err(ResumptionError::KeyMismatch)
+        );
+        // A failed attempt still consumes the token
+        assert_eq!(
+            store.redeem(&token, CLIENT_KEY, &proof(&token, CLIENT_KEY)).await,
+            Err(ResumptionError::UnknownToken)
+        );
+
+        let token = store.issue(CLIENT_KEY).await;
+        store.arm(&token, session()).await;
+        let wrong_key_proof = sign_session_resumption(&[8; 32], &token, CLIENT_KEY).unwrap();
+        assert_eq!(
+            store.redeem(&token, CLIENT_KEY, &wrong_key_proof).await,
+            Err(ResumptionError::InvalidProof)
+        );
+
+        // Revoked tokens cannot be armed or redeemed
+        let token = store.issue(CLIENT_KEY).await;
+        store.revoke(&token).await;
+        assert!(!store.arm(&token, session()).await);
+    }
+}
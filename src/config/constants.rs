@@ -12,6 +12,10 @@ pub const KEY_ROTATION_INTERVAL: Duration = Duration::from_secs(3600); // 1 hour
 pub const MAX_SECRET_CACHE_SIZE: usize = 2000;
 pub const SECRET_CACHE_TTL: Duration = Duration::from_secs(600); // 10 minutes
 pub const SESSION_KEY_SIZE: usize = 32;
+pub const KEY_ROTATION_HISTORY_SIZE: usize = 16; // Rotation records kept per client
+pub const KEY_ROTATION_DEFERRAL_MIN_INTERVAL: Duration = Duration::from_secs(10); // Min interval between client deferral requests
+pub const KEY_ROTATION_PACING_MAX_DELAY: Duration = Duration::from_secs(300); // Longest a rotation waits for the server-wide rate limit
+pub const DATA_KEY_ROTATION_PACKETS: u64 = 1 << 32; // Data packets in either direction under one key before rotation is forced
 pub const NONCE_SIZE: usize = 12; // For ChaCha20-Poly1305
 pub const TAG_SIZE: usize = 16; // For ChaCha20-Poly1305
 pub const PACKET_SIZE_LIMIT: usize = 16384; // 16KB
@@ -28,6 +32,11 @@ pub const CIPHER_SUITES: &[&str] = &[
 pub const MAX_CONNECTIONS_PER_IP: usize = 10;
 pub const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
 pub const MAX_PACKETS_PER_WINDOW: usize = 2000;
+pub const DISCONNECT_ACK_TIMEOUT: Duration = Duration::from_secs(2); // Max time spent sending a DisconnectAck
+pub const SESSION_REPLACE_TIMEOUT: Duration = Duration::from_secs(5); // Max wait for a replaced session to tear down
+pub const DISCONNECT_SEND_TIMEOUT: Duration = Duration::from_secs(1); // Max time spent sending one broadcast Disconnect
+pub const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(5); // Max wait for sessions to close after a broadcast Disconnect
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10); // Max time for each of the TLS handshake and WebSocket upgrade
 
 /// Traffic obfuscation constants
 pub const ENABLE_TRAFFIC_PADDING: bool = true;
@@ -39,6 +48,28 @@ pub const JITTER_MAX_MS: u64 = 50;
 /// IP allocation constants
 pub const IP_LEASE_DURATION_SECS: u64 = 86400; // 24 hours
 pub const IP_RENEWAL_THRESHOLD_SECS: u64 = 79200; // 22 hours
+pub const MIN_REQUESTED_LEASE_SECS: u64 = 60; // Shortest lease a client may request
+pub const LEASE_EXPIRY_GAUGE_INTERVAL: Duration = Duration::from_secs(30); // Refresh interval for lease expiry gauges
+pub const LEASE_PERSIST_INTERVAL: Duration = Duration::from_secs(10); // How often changed IP leases are saved
+pub const TUN_QUEUE_GAUGE_INTERVAL: Duration = Duration::from_secs(5); // Refresh interval for the TUN write queue depth gauge
+pub const SESSION_MEMORY_GAUGE_INTERVAL: Duration = Duration::from_secs(30); // Refresh interval for the session memory gauge
+
+/// Metrics exporter constants
+pub const HANDSHAKE_DURATION_BUCKETS: [f64; 10] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0]; // TLS handshake histogram bounds (seconds)
+pub const MAX_CLIENT_VERSION_LABELS: usize = 32; // Distinct client versions tracked before folding into "other"
+pub const MAX_ORIGIN_LABELS: usize = 64; // Distinct (country, ASN) pairs tracked before folding into "other"
+pub const METRICS_REQUEST_TIMEOUT: Duration = Duration::from_secs(5); // Max time to read a scrape request
+pub const ADMIN_REQUEST_TIMEOUT: Duration = Duration::from_secs(5); // Max time to read an admin HTTP request
+
+/// Fragment reassembly
+pub const FRAGMENT_REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(5); // Incomplete fragmented payloads are dropped after this
+pub const MAX_REASSEMBLY_BYTES: usize = 256 * 1024; // Bytes of incomplete payloads buffered per client
+pub const MAX_FRAGMENTS_PER_MESSAGE: u16 = 64;
+pub const MAX_INCOMPLETE_MESSAGES: usize = 16; // Incomplete fragmented payloads held per client; further new messages are refused
+
+/// Protocol versions
+pub const PROTOCOL_VERSION: &str = "1.0.0"; // Version spoken by this server; newer clients of the same major are accepted
+pub const MIN_PROTOCOL_VERSION: &str = "1.0.0"; // Oldest client version accepted
 
 /// Access control
 pub const ACCESS_CONTROL_ENABLED: bool = true;
@@ -51,14 +82,22 @@ pub const DEFAULT_LOG_LEVEL: &str = "info";
 pub const SOCKET_BUFFER_SIZE: usize = 1048576; // 1MB buffer size
 pub const TUN_MTU: u16 = 1500; // Default MTU size
 pub const PACKET_READ_BUFFER_SIZE: usize = 2048; // Buffer size for packet reads
+pub const PACKET_TOO_BIG_INTERVAL: Duration = Duration::from_secs(1); // Min interval between PacketTooBig notices per client
 
 /// Security settings
 pub const AUTH_CHALLENGE_TIMEOUT: Duration = Duration::from_secs(30);
+pub const AUTH_MESSAGE_TIMEOUT: Duration = Duration::from_secs(30); // Max wait for the client's Auth packet (and between early packets before it)
+pub const CHALLENGE_RESPONSE_TIMEOUT: Duration = Duration::from_secs(30); // Max wait for the ChallengeResponse after the Challenge is sent
+pub const AUTH_PHASE_DEADLINE: Duration = Duration::from_secs(60); // Max time from WebSocket upgrade to authenticated, however promptly each step is answered
 pub const MAX_AUTH_ATTEMPTS: usize = 3;
+pub const AUTH_FAILURE_WINDOW: Duration = Duration::from_secs(300); // Sliding window for MAX_AUTH_ATTEMPTS per source IP
+pub const CAPABILITY_QUERY_WINDOW: Duration = Duration::from_secs(60); // Window for the per-source-IP limit on pre-auth Hello queries
+pub const MAX_SIGNATURE_BATCH: usize = 64; // Challenge signatures verified together at most
 pub const SERVER_SIGNATURE_VERIFY_ENABLED: bool = true;
 pub const HMAC_VERIFY_ENABLED: bool = true;
 pub const REPLAY_PROTECTION_ENABLED: bool = true;
 pub const MAX_PACKET_COUNTER_SKEW: u64 = 100;
+pub const REPLAY_WINDOW_SIZE: u64 = 128; // Inbound Data counters remembered below the highest; reordered packets within it are accepted (at most 128)
 
 /// Get durations as functions to avoid constant Duration construction issues
 pub fn get_ip_lease_duration() -> Duration {
@@ -36,12 +36,231 @@ pub const DEFAULT_OBFUSCATION_METHOD: &str = "xor";
 /// Default key rotation interval in seconds
 pub const DEFAULT_KEY_ROTATION_INTERVAL: u64 = 3600; // 1 hour
 
+/// Default per-session key rotation jitter (percent of the interval, applied as a reduction)
+pub const DEFAULT_KEY_ROTATION_JITTER_PERCENT: u8 = 10;
+
+/// Upper bound on key rotation jitter
+pub const MAX_KEY_ROTATION_JITTER_PERCENT: u8 = 50;
+
 /// Default session timeout in seconds
 pub const DEFAULT_SESSION_TIMEOUT: u64 = 86400; // 24 hours
 
 /// Default maximum connections per IP
 pub const DEFAULT_MAX_CONNECTIONS_PER_IP: usize = 5;
 
+/// Default cap on active sessions (0 = unlimited)
+pub const DEFAULT_MAX_SESSIONS: usize = 0;
+
+/// Default cap on concurrent sessions per public key (0 = unlimited)
+pub const DEFAULT_MAX_SESSIONS_PER_KEY: usize = 0;
+
+/// Default cap on concurrent client connections, including ones still handshaking (0 = unlimited)
+pub const DEFAULT_MAX_CONNECTIONS: usize = 0;
+
+/// Default milliseconds a connection over the limit waits for a free slot (0 = reject immediately)
+pub const DEFAULT_CONNECTION_QUEUE_TIMEOUT_MS: u64 = 0;
+
+/// Upper bound on the connection queue timeout
+pub const MAX_CONNECTION_QUEUE_TIMEOUT_MS: u64 = 30_000;
+
+/// Default milliseconds challenge signatures are collected for batch verification (0 = verify individually)
+pub const DEFAULT_CHALLENGE_BATCH_WINDOW_MS: u64 = 0;
+
+/// Upper bound on the challenge batch window, which delays every authentication
+pub const MAX_CHALLENGE_BATCH_WINDOW_MS: u64 = 100;
+
+/// Default largest outbound Data payload sent unfragmented to clients that negotiate fragmentation (0 = never split)
+pub const DEFAULT_FRAGMENT_SIZE: usize = 0;
+
+/// Lower bound on a non-zero fragment size
+pub const MIN_FRAGMENT_SIZE: usize = 256;
+
+/// Default TUN device MTU
+pub const DEFAULT_TUN_MTU: u16 = super::constants::TUN_MTU;
+
+/// Lower bound on the TUN MTU (smallest datagram every IPv4 host must accept)
+pub const MIN_TUN_MTU: u16 = 576;
+
+/// Upper bound on the TUN MTU (jumbo frames)
+pub const MAX_TUN_MTU: u16 = 9000;
+
+/// Default seconds without inbound traffic before a session is disconnected (0 = disabled)
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 90;
+
+/// Lower bound on a non-zero idle timeout
+pub const MIN_IDLE_TIMEOUT_SECS: u64 = 10;
+
+/// Default consecutive unanswered heartbeats tolerated before a connection is considered dead (0 = unlimited)
+pub const DEFAULT_MAX_MISSED_PONGS: usize = 3;
+
+/// Upper bound on tolerated unanswered heartbeats
+pub const MAX_MISSED_PONGS: usize = 64;
+
+/// Default seconds between heartbeats on an established session
+pub const DEFAULT_HEARTBEAT_INTERVAL_SECS: u64 = 30;
+
+/// Default RTT (ms) above which the heartbeat interval backs off (0 = fixed interval)
+pub const DEFAULT_HEARTBEAT_RTT_THRESHOLD_MS: u64 = 0;
+
+/// Default longest heartbeat interval reached by backoff
+pub const DEFAULT_MAX_HEARTBEAT_INTERVAL_SECS: u64 = 120;
+
+/// Upper bound on any heartbeat interval
+pub const MAX_HEARTBEAT_INTERVAL_SECS: u64 = 3600;
+
+/// Default per-client inbound packet rate limit (packets/sec, 0 = unlimited)
+pub const DEFAULT_MAX_CLIENT_PACKETS_PER_SEC: u64 = 10_000;
+
+/// Default per-client inbound byte rate limit (bytes/sec, 0 = unlimited)
+pub const DEFAULT_MAX_CLIENT_BYTES_PER_SEC: u64 = 12_500_000; // 100 Mbit/s
+
+/// Default maximum sessions disconnected per second after an ACL reload revokes access
+pub const DEFAULT_ACL_REVOCATION_RATE: u32 = 20;
+
+/// Default aggregation window for repetitive log events in milliseconds
+pub const DEFAULT_LOG_AGGREGATION_WINDOW_MS: u64 = 1000;
+
+/// Default number of pre-generated session keys (0 = generate inline)
+pub const DEFAULT_SESSION_KEY_POOL_SIZE: usize = 0;
+
+/// Upper bound on the session key pool size
+pub const MAX_SESSION_KEY_POOL_SIZE: usize = 65_536;
+
+/// Default total seconds a client may postpone each scheduled key rotation (0 = not allowed)
+pub const DEFAULT_MAX_KEY_ROTATION_DEFERRAL_SECS: u64 = 300;
+
+/// Upper bound on the key rotation deferral ceiling
+pub const MAX_KEY_ROTATION_DEFERRAL_SECS: u64 = 3600;
+
+/// Default server-wide key rotations per second (0 = unlimited)
+pub const DEFAULT_MAX_KEY_ROTATIONS_PER_SEC: u32 = 50;
+
+/// Default seconds a client's IP is held after its session ends (0 = release immediately)
+pub const DEFAULT_IP_RELEASE_GRACE_SECS: u64 = 30;
+
+/// Upper bound on the IP release grace period
+pub const MAX_IP_RELEASE_GRACE_SECS: u64 = 600;
+
+/// Default longest IP lease a client may request (0 = the session timeout)
+pub const DEFAULT_MAX_LEASE_DURATION_SECS: u64 = 0;
+
+/// Default seconds between ServerLoad packets to opted-in clients (0 = disabled)
+pub const DEFAULT_SERVER_LOAD_INTERVAL_SECS: u64 = 30;
+
+/// Shortest allowed interval between ServerLoad packets
+pub const MIN_SERVER_LOAD_INTERVAL_SECS: u64 = 5;
+
+/// Default startup warmup window in seconds (0 = ready immediately)
+pub const DEFAULT_WARMUP_SECS: u64 = 0;
+
+/// Upper bound on the startup warmup window
+pub const MAX_WARMUP_SECS: u64 = 600;
+
+/// Default connections accepted per second while warming up in throttle mode
+pub const DEFAULT_WARMUP_ACCEPTS_PER_SEC: u32 = 20;
+
+/// Default failed authentications per source IP within the failure window (0 = unlimited)
+pub const DEFAULT_AUTH_FAILURE_LIMIT: usize = 10;
+
+/// Default sliding window over which failed authentications are counted, in seconds
+pub const DEFAULT_AUTH_FAILURE_WINDOW_SECS: u64 = 300;
+
+/// Upper bound on the authentication failure window
+pub const MAX_AUTH_FAILURE_WINDOW_SECS: u64 = 86_400;
+
+/// Default time a replaced server key still serves clients that were sent it, in seconds
+pub const DEFAULT_SERVER_KEY_ROTATION_OVERLAP_SECS: u64 = 300;
+
+/// Upper bound on the server key rotation overlap
+pub const MAX_SERVER_KEY_ROTATION_OVERLAP_SECS: u64 = 86_400;
+
+/// Default proof-of-work difficulty required with challenges (0 = none)
+pub const DEFAULT_POW_DIFFICULTY: u8 = 0;
+
+/// Default proof-of-work difficulty under a full challenge table (0 = fixed)
+pub const DEFAULT_MAX_POW_DIFFICULTY: u8 = 0;
+
+/// Default time a session stays resumable after it ends, in seconds (0 = resumption disabled)
+pub const DEFAULT_RESUMPTION_TTL_SECS: u64 = 60;
+
+/// Upper bound on the session resumption window
+pub const MAX_RESUMPTION_TTL_SECS: u64 = 3600;
+
+/// Default Data packets tolerated (and rejected) before the handshake completes
+pub const DEFAULT_MAX_HANDSHAKE_DATA_PACKETS: u32 = 3;
+
+/// Default fraction of connections sampled for detailed packet tracing
+pub const DEFAULT_TRACE_SAMPLE_RATE: f64 = 0.0;
+
+/// Default bound on decrypted packets queued toward the TUN device
+pub const DEFAULT_TUN_QUEUE_DEPTH: usize = 1024;
+
+/// Upper bound on the TUN write queue depth
+pub const MAX_TUN_QUEUE_DEPTH: usize = 65_536;
+
+/// Default bound on messages queued toward each client before Data is dropped
+pub const DEFAULT_SEND_QUEUE_DEPTH: usize = 256;
+
+/// Upper bound on the per-session send queue depth
+pub const MAX_SEND_QUEUE_DEPTH: usize = 16_384;
+
+/// Default largest WebSocket message a client may send, in bytes
+pub const DEFAULT_WS_MAX_MESSAGE_SIZE: usize = 512 * 1024;
+
+/// Default largest single WebSocket frame a client may send, in bytes
+pub const DEFAULT_WS_MAX_FRAME_SIZE: usize = 256 * 1024;
+
+/// Smallest WebSocket message or frame limit; a full Data packet must fit in one frame
+pub const MIN_WS_MESSAGE_SIZE: usize = 128 * 1024;
+
+/// Largest WebSocket message or frame limit
+pub const MAX_WS_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Default retries for transient shared secret derivation failures
+pub const DEFAULT_SHARED_SECRET_RETRIES: u32 = 2;
+
+/// Default backoff before the first shared secret retry in milliseconds
+pub const DEFAULT_SHARED_SECRET_RETRY_BACKOFF_MS: u64 = 50;
+
+/// Upper bound on shared secret retries, keeping handshakes bounded
+pub const MAX_SHARED_SECRET_RETRIES: u32 = 5;
+
+/// Default cap on concurrently tracked tunnel flows
+pub const DEFAULT_FLOW_MAX_TRACKED: usize = 10_000;
+
+/// Default idle timeout before a flow is exported, in seconds
+pub const DEFAULT_FLOW_IDLE_TIMEOUT_SECS: u64 = 15;
+
+/// Default active timeout after which long-lived flows are exported, in seconds
+pub const DEFAULT_FLOW_ACTIVE_TIMEOUT_SECS: u64 = 60;
+
+/// Default interval between per-client traffic samples, in seconds
+pub const DEFAULT_TRAFFIC_EXPORT_INTERVAL_SECS: u64 = 10;
+
+/// Upper bound on the traffic sampling interval, in seconds
+pub const MAX_TRAFFIC_EXPORT_INTERVAL_SECS: u64 = 3600;
+
+/// Default bucket size for the `bucket` padding policy, in bytes
+pub const DEFAULT_PADDING_BUCKET_SIZE: usize = 256;
+
+/// Smallest padding bucket size, in bytes
+pub const MIN_PADDING_BUCKET_SIZE: usize = 16;
+
+/// Largest padding bucket size, in bytes
+pub const MAX_PADDING_BUCKET_SIZE: usize = 4096;
+
+/// Default number of pinned session worker threads (0 = use the shared runtime)
+pub const DEFAULT_SESSION_WORKER_THREADS: usize = 0;
+
+/// Upper bound on pinned session worker threads
+pub const MAX_SESSION_WORKER_THREADS: usize = 256;
+
+/// Default pre-authentication Hello queries answered per source IP per minute (0 = disabled)
+pub const DEFAULT_CAPABILITY_QUERIES_PER_MINUTE: usize = 6;
+
+/// Upper bound on pre-authentication Hello queries per source IP per minute
+pub const MAX_CAPABILITY_QUERIES_PER_MINUTE: usize = 60;
+
 /// Default key file
 pub const DEFAULT_SERVER_KEY_FILE: &str = "server_keypair.json";
 
@@ -14,6 +14,8 @@ use std::sync::Arc;
 use std::time::Duration;
 use tracing::info;
 
+use crate::auth::pow::MAX_POW_DIFFICULTY;
+use crate::config::constants::{AUTH_CHALLENGE_TIMEOUT, MIN_REQUESTED_LEASE_SECS};
 use crate::config::defaults;
 use crate::crypto::keys::KeyManager;
 
@@ -81,6 +83,217 @@ impl Default for TransportSecurity {
     }
 }
 
+/// How ACL tier changes affect sessions that are already authenticated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum AclTierPolicy {
+    /// [Default] Sessions keep the tier they authenticated with until they reconnect
+    #[value(name = "snapshot")]
+    #[serde(rename = "snapshot")]
+    Snapshot,
+    
+    /// An ACL reload re-applies the current tier to every active session of the key
+    #[value(name = "reapply")]
+    #[serde(rename = "reapply")]
+    Reapply,
+}
+
+impl Default for AclTierPolicy {
+    fn default() -> Self {
+        AclTierPolicy::Snapshot
+    }
+}
+
+/// What to do when a public key with an active session authenticates again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum DuplicateSessionPolicy {
+    /// [Default] Allow concurrent sessions for the same key
+    #[value(name = "allow-multiple")]
+    #[serde(rename = "allow-multiple")]
+    AllowMultiple,
+    
+    /// Newest wins: disconnect the existing sessions before admitting the new one
+    #[value(name = "replace-existing")]
+    #[serde(rename = "replace-existing")]
+    ReplaceExisting,
+    
+    /// Oldest wins: reject the new authentication
+    #[value(name = "reject-new")]
+    #[serde(rename = "reject-new")]
+    RejectNew,
+}
+
+impl Default for DuplicateSessionPolicy {
+    fn default() -> Self {
+        DuplicateSessionPolicy::AllowMultiple
+    }
+}
+
+/// How tightly an authentication challenge is bound to the client's source address.
+///
+/// A challenge answered from outside its binding is rejected. Looser
+/// bindings tolerate clients that roam between challenge and response, at
+/// the cost of letting a response that was captured in flight be replayed
+/// from any address inside the same binding before the challenge expires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum ChallengeAddressBinding {
+    /// IP and port must match; strictest, breaks on any NAT rebinding
+    #[value(name = "addr")]
+    #[serde(rename = "addr")]
+    Addr,
+    
+    /// [Default] IP must match; tolerates source port changes
+    #[value(name = "ip")]
+    #[serde(rename = "ip")]
+    Ip,
+    
+    /// Same IPv4 /24 or IPv6 /64; tolerates roaming within a network, accepts responses from its neighbours
+    #[value(name = "subnet")]
+    #[serde(rename = "subnet")]
+    Subnet,
+}
+
+impl Default for ChallengeAddressBinding {
+    fn default() -> Self {
+        ChallengeAddressBinding::Ip
+    }
+}
+
+/// What a client learns when authentication fails.
+///
+/// Telling an ACL denial apart from a bad signature confirms to whoever
+/// holds a key that it reached the ACL stage, which helps enumerate keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum AclDenyResponse {
+    /// [Default] Report the reason: denied keys get `Unauthorized` "Access
+    /// denied by ACL", other failures their own error. Useful when debugging clients.
+    #[value(name = "verbose")]
+    #[serde(rename = "verbose")]
+    Verbose,
+    
+    /// Answer every failed authentication with the same generic error, and
+    /// count ACL denials against the source IP like bad signatures.
+    #[value(name = "strict")]
+    #[serde(rename = "strict")]
+    Strict,
+}
+
+impl Default for AclDenyResponse {
+    fn default() -> Self {
+        AclDenyResponse::Verbose
+    }
+}
+
+/// How new connections are handled during the startup warmup window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum WarmupMode {
+    /// [Default] Accept connections at a reduced rate
+    #[value(name = "throttle")]
+    #[serde(rename = "throttle")]
+    Throttle,
+    
+    /// Refuse all new connections until warm
+    #[value(name = "reject")]
+    #[serde(rename = "reject")]
+    Reject,
+}
+
+impl Default for WarmupMode {
+    fn default() -> Self {
+        WarmupMode::Throttle
+    }
+}
+
+/// Which packet is discarded when the TUN write queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum TunQueuePolicy {
+    /// [Default] Drop the arriving packet
+    #[value(name = "drop-tail")]
+    #[serde(rename = "drop-tail")]
+    DropTail,
+    
+    /// Drop the oldest queued packet to make room for the arriving one
+    #[value(name = "drop-oldest")]
+    #[serde(rename = "drop-oldest")]
+    DropOldest,
+}
+
+impl Default for TunQueuePolicy {
+    fn default() -> Self {
+        TunQueuePolicy::DropTail
+    }
+}
+
+/// How outbound data payloads are padded when padding is enabled.
+///
+/// Padding is carried inside the encrypted payload behind a two-byte
+/// length prefix, so it is authenticated and stripped again by the
+/// receiver before the packet reaches the TUN device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum PaddingPolicy {
+    /// [Default] Pad a random tenth of packets with 16 to 128 random bytes.
+    /// Costs about 7 bytes per packet on average but leaves most lengths visible.
+    #[value(name = "random")]
+    #[serde(rename = "random")]
+    Random,
+    
+    /// Pad every packet up to the next multiple of the bucket size, so only
+    /// the bucket is visible. Costs half a bucket per packet on average
+    /// (about 128 bytes with the default 256-byte buckets, roughly 10-15% on
+    /// bulk traffic), and small packets such as TCP ACKs grow to a full bucket.
+    #[value(name = "bucket")]
+    #[serde(rename = "bucket")]
+    Bucket,
+}
+
+impl Default for PaddingPolicy {
+    fn default() -> Self {
+        PaddingPolicy::Random
+    }
+}
+
+/// What happens to inner packets whose source address is not the
+/// tunnel address assigned to the sending session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum SourceIpPolicy {
+    /// [Default] Drop the packet, so clients cannot spoof other addresses
+    /// on the internal network.
+    #[value(name = "strict")]
+    #[serde(rename = "strict")]
+    Strict,
+    
+    /// Forward the packet anyway; mismatches are still logged and counted.
+    /// For clients that route other networks through the tunnel.
+    #[value(name = "permissive")]
+    #[serde(rename = "permissive")]
+    Permissive,
+}
+
+impl Default for SourceIpPolicy {
+    fn default() -> Self {
+        SourceIpPolicy::Strict
+    }
+}
+
+/// Wire format for per-client traffic export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum TrafficExportFormat {
+    /// [Default] StatsD counters
+    #[value(name = "statsd")]
+    #[serde(rename = "statsd")]
+    Statsd,
+    
+    /// InfluxDB line protocol
+    #[value(name = "influx")]
+    #[serde(rename = "influx")]
+    Influx,
+}
+
+impl Default for TrafficExportFormat {
+    fn default() -> Self {
+        TrafficExportFormat::Statsd
+    }
+}
+
 /// Command enum for subcommands
 #[derive(Parser, Debug, Clone)]
 pub enum Command {
@@ -113,6 +326,10 @@ pub struct ServerArgs {
     #[clap(long, default_value = "0.0.0.0:8443")]
     pub listen: String,
 
+    /// Further address to accept clients on alongside --listen (repeatable)
+    #[clap(long = "additional-listen")]
+    pub additional_listen_addrs: Vec<SocketAddr>,
+
     /// TUN device name (required for VPN modes)
     #[clap(long, default_value = defaults::DEFAULT_TUN_NAME)]
     pub tun_name: String,
@@ -149,10 +366,22 @@ pub struct ServerArgs {
     #[clap(long)]
     pub enable_padding: bool,
 
+    /// How data packets are padded when padding is enabled
+    #[clap(long, value_enum, default_value = "random")]
+    pub padding_policy: PaddingPolicy,
+
+    /// Bucket size in bytes for the `bucket` padding policy
+    #[clap(long, default_value_t = defaults::DEFAULT_PADDING_BUCKET_SIZE)]
+    pub padding_bucket_size: usize,
+
     /// Key rotation interval in seconds
     #[clap(long, default_value_t = defaults::DEFAULT_KEY_ROTATION_INTERVAL)]
     pub key_rotation_interval: u64,
 
+    /// Shorten each session's key rotation interval by a random amount up to this percentage
+    #[clap(long, default_value_t = defaults::DEFAULT_KEY_ROTATION_JITTER_PERCENT)]
+    pub key_rotation_jitter_percent: u8,
+
     /// Session timeout in seconds
     #[clap(long, default_value_t = defaults::DEFAULT_SESSION_TIMEOUT)]
     pub session_timeout: u64,
@@ -161,6 +390,58 @@ pub struct ServerArgs {
     #[clap(long, default_value_t = defaults::DEFAULT_MAX_CONNECTIONS_PER_IP)]
     pub max_connections_per_ip: usize,
     
+    /// Maximum active sessions; connections beyond it are closed before TLS (0 = unlimited)
+    #[clap(long, default_value_t = defaults::DEFAULT_MAX_SESSIONS)]
+    pub max_sessions: usize,
+    
+    /// Maximum concurrent sessions per public key with allow-multiple; ACL entries may override it (0 = unlimited)
+    #[clap(long, default_value_t = defaults::DEFAULT_MAX_SESSIONS_PER_KEY)]
+    pub max_sessions_per_key: usize,
+    
+    /// Maximum concurrent client connections, including ones still handshaking (0 = unlimited)
+    #[clap(long, default_value_t = defaults::DEFAULT_MAX_CONNECTIONS)]
+    pub max_connections: usize,
+    
+    /// Milliseconds a connection over the limit waits for a free slot before being closed (0 = close immediately)
+    #[clap(long, default_value_t = defaults::DEFAULT_CONNECTION_QUEUE_TIMEOUT_MS)]
+    pub connection_queue_timeout_ms: u64,
+    
+    /// Milliseconds challenge signatures are collected for batch verification (0 = verify individually)
+    #[clap(long, default_value_t = defaults::DEFAULT_CHALLENGE_BATCH_WINDOW_MS)]
+    pub challenge_batch_window_ms: u64,
+    
+    /// Largest outbound Data payload sent in one packet to clients that negotiate fragmentation (0 = never split)
+    #[clap(long, default_value_t = defaults::DEFAULT_FRAGMENT_SIZE)]
+    pub fragment_size: usize,
+    
+    /// MTU of the TUN device; inner packets larger than this are dropped
+    #[clap(long, default_value_t = defaults::DEFAULT_TUN_MTU)]
+    pub tun_mtu: u16,
+    
+    /// Rewrite the MSS option of tunneled TCP SYNs so segments fit the TUN MTU
+    #[clap(long)]
+    pub mss_clamp: bool,
+    
+    /// Seconds without inbound traffic (including pongs) before a session is disconnected (0 = disabled)
+    #[clap(long, default_value_t = defaults::DEFAULT_IDLE_TIMEOUT_SECS)]
+    pub idle_timeout_secs: u64,
+    
+    /// Consecutive unanswered heartbeats tolerated before a connection is considered dead (0 = unlimited)
+    #[clap(long, default_value_t = defaults::DEFAULT_MAX_MISSED_PONGS)]
+    pub max_missed_pongs: usize,
+    
+    /// Seconds between heartbeats on an established session (capped at a third of the idle timeout)
+    #[clap(long, default_value_t = defaults::DEFAULT_HEARTBEAT_INTERVAL_SECS)]
+    pub heartbeat_interval_secs: u64,
+    
+    /// RTT in milliseconds above which the heartbeat interval backs off (0 = fixed interval)
+    #[clap(long, default_value_t = defaults::DEFAULT_HEARTBEAT_RTT_THRESHOLD_MS)]
+    pub heartbeat_rtt_threshold_ms: u64,
+    
+    /// Longest heartbeat interval in seconds reached by backoff
+    #[clap(long, default_value_t = defaults::DEFAULT_MAX_HEARTBEAT_INTERVAL_SECS)]
+    pub max_heartbeat_interval_secs: u64,
+    
     /// Data directory for storage
     #[clap(long, default_value = defaults::DEFAULT_DATA_DIR)]
     pub data_dir: String,
@@ -197,6 +478,242 @@ pub struct ServerArgs {
     #[clap(long, default_value = "restricted", help = "Security mode for remote commands: 'restricted' (default) or 'full-access' (use with caution!)")]
     pub remote_security_mode: String,
     
+    /// Enable chaos (fault injection) mode for client testing; requires a build with the `chaos` feature
+    #[clap(long)]
+    pub enable_chaos_mode: bool,
+    
+    /// Disconnect active sessions whose access was revoked when the ACL is reloaded
+    #[clap(long)]
+    pub acl_revoke_on_reload: bool,
+    
+    /// Maximum sessions disconnected per second after an ACL reload
+    #[clap(long, default_value_t = defaults::DEFAULT_ACL_REVOCATION_RATE)]
+    pub acl_revocation_rate: u32,
+    
+    /// Whether an ACL reload re-applies tier changes to active sessions
+    #[clap(long, value_enum, default_value = "snapshot")]
+    pub acl_tier_policy: AclTierPolicy,
+    
+    /// What to do when a key with an active session authenticates again
+    #[clap(long, value_enum, default_value = "allow-multiple")]
+    pub duplicate_session_policy: DuplicateSessionPolicy,
+    
+    /// How tightly authentication challenges are bound to the client address
+    #[clap(long, value_enum, default_value = "ip")]
+    pub challenge_address_binding: ChallengeAddressBinding,
+    
+    /// Whether failed authentications report their reason or a generic error
+    #[clap(long, value_enum, default_value = "verbose")]
+    pub acl_deny_response: AclDenyResponse,
+    
+    /// Failed authentications after which a source IP is refused until they age out of the window (0 = unlimited)
+    #[clap(long, default_value_t = defaults::DEFAULT_AUTH_FAILURE_LIMIT)]
+    pub auth_failure_limit: usize,
+    
+    /// Sliding window over which failed authentications per source IP are counted, in seconds
+    #[clap(long, default_value_t = defaults::DEFAULT_AUTH_FAILURE_WINDOW_SECS)]
+    pub auth_failure_window_secs: u64,
+    
+    /// How long a replaced server key still serves clients that were sent it, in seconds
+    #[clap(long, default_value_t = defaults::DEFAULT_SERVER_KEY_ROTATION_OVERLAP_SECS)]
+    pub server_key_rotation_overlap_secs: u64,
+    
+    /// Proof-of-work difficulty (leading zero bits) required with each challenge (0 = none; clients must support it)
+    #[clap(long, default_value_t = defaults::DEFAULT_POW_DIFFICULTY)]
+    pub pow_difficulty: u8,
+    
+    /// Difficulty reached when all challenge slots are pending, raising it under handshake floods (0 = fixed at --pow-difficulty)
+    #[clap(long, default_value_t = defaults::DEFAULT_MAX_POW_DIFFICULTY)]
+    pub max_pow_difficulty: u8,
+    
+    /// Seconds a session stays resumable with its resumption token after it ends (0 = disabled)
+    #[clap(long, default_value_t = defaults::DEFAULT_RESUMPTION_TTL_SECS)]
+    pub resumption_ttl_secs: u64,
+    
+    /// Data packets rejected before the handshake completes until the connection is dropped (0 = drop on the first)
+    #[clap(long, default_value_t = defaults::DEFAULT_MAX_HANDSHAKE_DATA_PACKETS)]
+    pub max_handshake_data_packets: u32,
+    
+    /// Refuse clients that do not support `bound-session-key` (IpAssign key sealed to the session id and public key)
+    #[clap(long)]
+    pub require_bound_session_key: bool,
+    
+    /// Fraction of connections (0.0-1.0) that get detailed packet tracing, decided at connect time
+    #[clap(long, default_value_t = defaults::DEFAULT_TRACE_SAMPLE_RATE)]
+    pub trace_sample_rate: f64,
+    
+    /// Seconds after startup during which the server reports not-ready (0 = disabled)
+    #[clap(long, default_value_t = defaults::DEFAULT_WARMUP_SECS)]
+    pub warmup_secs: u64,
+    
+    /// How new connections are handled during warmup
+    #[clap(long, value_enum, default_value = "throttle")]
+    pub warmup_mode: WarmupMode,
+    
+    /// Connections accepted per second during warmup in throttle mode
+    #[clap(long, default_value_t = defaults::DEFAULT_WARMUP_ACCEPTS_PER_SEC)]
+    pub warmup_accepts_per_sec: u32,
+    
+    /// Per-client inbound packet rate limit in packets/sec (0 = unlimited)
+    #[clap(long, default_value_t = defaults::DEFAULT_MAX_CLIENT_PACKETS_PER_SEC)]
+    pub max_client_packets_per_sec: u64,
+    
+    /// Per-client inbound byte rate limit in bytes/sec (0 = unlimited)
+    #[clap(long, default_value_t = defaults::DEFAULT_MAX_CLIENT_BYTES_PER_SEC)]
+    pub max_client_bytes_per_sec: u64,
+    
+    /// Window in milliseconds for aggregating repetitive log events (auth/TLS failures)
+    #[clap(long, default_value_t = defaults::DEFAULT_LOG_AGGREGATION_WINDOW_MS)]
+    pub log_aggregation_window_ms: u64,
+    
+    /// Number of session keys to pre-generate for handshakes (0 = generate inline)
+    #[clap(long, default_value_t = defaults::DEFAULT_SESSION_KEY_POOL_SIZE)]
+    pub session_key_pool_size: usize,
+    
+    /// Total seconds a client may postpone each scheduled key rotation (0 = not allowed)
+    #[clap(long, default_value_t = defaults::DEFAULT_MAX_KEY_ROTATION_DEFERRAL_SECS)]
+    pub max_key_rotation_deferral_secs: u64,
+    
+    /// Server-wide key rotations per second; excess rotations are briefly delayed (0 = unlimited)
+    #[clap(long, default_value_t = defaults::DEFAULT_MAX_KEY_ROTATIONS_PER_SEC)]
+    pub max_key_rotations_per_sec: u32,
+    
+    /// Seconds a client's IP is held for reconnection after its session ends (0 = release immediately)
+    #[clap(long, default_value_t = defaults::DEFAULT_IP_RELEASE_GRACE_SECS)]
+    pub ip_release_grace_secs: u64,
+    
+    /// Longest IP lease in seconds a client may request; requests above it are clamped (0 = session timeout)
+    #[clap(long, default_value_t = defaults::DEFAULT_MAX_LEASE_DURATION_SECS)]
+    pub max_lease_duration_secs: u64,
+    
+    /// Fixed IP reserved for a client across reconnects, as "PUBLIC_KEY=IP" (repeatable)
+    #[clap(long = "ip-reservation")]
+    pub ip_reservations: Vec<String>,
+    
+    /// File to save IP leases to so they survive restarts (not persisted if not set)
+    #[clap(long)]
+    pub ip_lease_file: Option<PathBuf>,
+    
+    /// Seconds between ServerLoad packets to clients that opt in (0 = disabled)
+    #[clap(long, default_value_t = defaults::DEFAULT_SERVER_LOAD_INTERVAL_SECS)]
+    pub server_load_interval_secs: u64,
+    
+    /// Allowed WebSocket Origin (repeatable; none = Origin not checked)
+    #[clap(long = "ws-allowed-origin")]
+    pub ws_allowed_origins: Vec<String>,
+    
+    /// TLS ALPN protocol to advertise and require, e.g. aeronyx/1 (repeatable; none = ALPN not negotiated)
+    #[clap(long = "alpn-protocol")]
+    pub alpn_protocols: Vec<String>,
+    
+    /// PEM bundle of CAs whose client certificates (with Ed25519 keys) authenticate clients without the challenge
+    #[clap(long)]
+    pub client_ca_file: Option<PathBuf>,
+    
+    /// Refuse TLS clients that do not present a certificate from --client-ca-file
+    #[clap(long)]
+    pub require_client_cert: bool,
+    
+    /// Inner IP protocol allowed through the tunnel, by name (tcp, udp, icmp, icmpv6) or number (repeatable; none = all allowed)
+    #[clap(long = "tunnel-allowed-protocol")]
+    pub tunnel_allowed_protocols: Vec<String>,
+    
+    /// Whether inner packets must come from the client's assigned tunnel IP
+    #[clap(long, value_enum, default_value = "strict")]
+    pub source_ip_policy: SourceIpPolicy,
+    
+    /// Decrypted packets that may wait for the TUN device before drops start
+    #[clap(long, default_value_t = defaults::DEFAULT_TUN_QUEUE_DEPTH)]
+    pub tun_queue_depth: usize,
+    
+    /// Which packet is dropped when the TUN write queue is full
+    #[clap(long, value_enum, default_value = "drop-tail")]
+    pub tun_queue_policy: TunQueuePolicy,
+    
+    /// Messages that may wait for each client's socket before the oldest Data packets are dropped
+    #[clap(long, default_value_t = defaults::DEFAULT_SEND_QUEUE_DEPTH)]
+    pub send_queue_depth: usize,
+    
+    /// WebSocket subprotocol clients must offer during the upgrade
+    #[clap(long)]
+    pub ws_subprotocol: Option<String>,
+    
+    /// Largest WebSocket message in bytes a client may send before it is disconnected
+    #[clap(long, default_value_t = defaults::DEFAULT_WS_MAX_MESSAGE_SIZE)]
+    pub ws_max_message_size: usize,
+    
+    /// Largest single WebSocket frame in bytes a client may send before it is disconnected
+    #[clap(long, default_value_t = defaults::DEFAULT_WS_MAX_FRAME_SIZE)]
+    pub ws_max_frame_size: usize,
+    
+    /// Retries for transient shared secret derivation failures (0 = no retry)
+    #[clap(long, default_value_t = defaults::DEFAULT_SHARED_SECRET_RETRIES)]
+    pub shared_secret_retries: u32,
+    
+    /// Backoff in milliseconds before the first shared secret retry (doubles per retry)
+    #[clap(long, default_value_t = defaults::DEFAULT_SHARED_SECRET_RETRY_BACKOFF_MS)]
+    pub shared_secret_retry_backoff_ms: u64,
+    
+    /// NetFlow v5 collector for tunnel flow records (disabled if not set)
+    #[clap(long)]
+    pub flow_collector: Option<SocketAddr>,
+    
+    /// Address for the Prometheus `/metrics` HTTP listener (disabled if not set)
+    #[clap(long)]
+    pub metrics_listen: Option<SocketAddr>,
+    
+    /// Address for the admin HTTP listener, read-only except for `POST /reload` from loopback peers; requires a build with the `admin-api` feature (disabled if not set)
+    #[clap(long)]
+    pub admin_listen: Option<SocketAddr>,
+    
+    /// File to append one JSON lifecycle record per finished session (disabled if not set)
+    #[clap(long)]
+    pub session_record_file: Option<PathBuf>,
+    
+    /// File to append one JSON audit event per authentication decision (disabled if not set)
+    #[clap(long)]
+    pub auth_audit_file: Option<PathBuf>,
+    
+    /// Maximum concurrently tracked tunnel flows
+    #[clap(long, default_value_t = defaults::DEFAULT_FLOW_MAX_TRACKED)]
+    pub flow_max_tracked: usize,
+    
+    /// Seconds without packets before a flow is exported
+    #[clap(long, default_value_t = defaults::DEFAULT_FLOW_IDLE_TIMEOUT_SECS)]
+    pub flow_idle_timeout_secs: u64,
+    
+    /// Seconds after which long-lived flows are exported
+    #[clap(long, default_value_t = defaults::DEFAULT_FLOW_ACTIVE_TIMEOUT_SECS)]
+    pub flow_active_timeout_secs: u64,
+    
+    /// UDP address of a StatsD or InfluxDB endpoint for per-client traffic (disabled if not set)
+    #[clap(long)]
+    pub traffic_export_addr: Option<SocketAddr>,
+    
+    /// Line format for per-client traffic export
+    #[clap(long, value_enum, default_value = "statsd")]
+    pub traffic_export_format: TrafficExportFormat,
+    
+    /// Seconds between per-client traffic samples
+    #[clap(long, default_value_t = defaults::DEFAULT_TRAFFIC_EXPORT_INTERVAL_SECS)]
+    pub traffic_export_interval_secs: u64,
+    
+    /// Pin each client's session to one of this many dedicated worker threads (0 = disabled)
+    #[clap(long, default_value_t = defaults::DEFAULT_SESSION_WORKER_THREADS)]
+    pub session_worker_threads: usize,
+    
+    /// Recurring maintenance window rejecting new connections, as "[DAY] HH:MM-HH:MM" in UTC (repeatable)
+    #[clap(long = "quiet-hours")]
+    pub quiet_hours: Vec<String>,
+    
+    /// Maximum stateful features active per session (unset = unlimited)
+    #[clap(long)]
+    pub max_stateful_features: Option<usize>,
+    
+    /// Unauthenticated Hello capability queries answered per source IP per minute (0 = disabled)
+    #[clap(long, default_value_t = defaults::DEFAULT_CAPABILITY_QUERIES_PER_MINUTE)]
+    pub capability_queries_per_minute: usize,
+    
     /// Registration setup command
     #[clap(subcommand)]
     pub command: Option<Command>,
@@ -216,6 +733,10 @@ pub struct ServerConfig {
     /// Server listen address
     pub listen_addr: SocketAddr,
     
+    /// Further addresses accepting clients alongside `listen_addr`, sharing all server state
+    #[serde(default)]
+    pub additional_listen_addrs: Vec<SocketAddr>,
+    
     /// TUN device name
     pub tun_name: String,
     
@@ -240,15 +761,79 @@ pub struct ServerConfig {
     /// Enable traffic padding
     pub enable_padding: bool,
     
+    /// How data packets are padded when padding is enabled
+    #[serde(default)]
+    pub padding_policy: PaddingPolicy,
+    
+    /// Bucket size in bytes for the `bucket` padding policy
+    #[serde(default = "default_padding_bucket_size")]
+    pub padding_bucket_size: usize,
+    
     /// Key rotation interval
     pub key_rotation_interval: Duration,
     
+    /// Maximum per-session shortening of the key rotation interval, in percent
+    #[serde(default = "default_key_rotation_jitter_percent")]
+    pub key_rotation_jitter_percent: u8,
+    
     /// Session timeout
     pub session_timeout: Duration,
     
     /// Maximum connections per IP
     pub max_connections_per_ip: usize,
     
+    /// Maximum active sessions; connections beyond it are closed before TLS (0 = unlimited)
+    #[serde(default)]
+    pub max_sessions: usize,
+    
+    /// Maximum concurrent sessions per public key with allow-multiple; ACL entries may override it (0 = unlimited)
+    #[serde(default)]
+    pub max_sessions_per_key: usize,
+    
+    /// Maximum concurrent client connections, including ones still handshaking (0 = unlimited)
+    #[serde(default)]
+    pub max_connections: usize,
+    
+    /// Milliseconds a connection over the limit waits for a free slot before being closed (0 = close immediately)
+    #[serde(default)]
+    pub connection_queue_timeout_ms: u64,
+    
+    /// Milliseconds challenge signatures are collected for batch verification (0 = verify individually)
+    #[serde(default)]
+    pub challenge_batch_window_ms: u64,
+    
+    /// Largest outbound Data payload sent in one packet to clients that negotiate fragmentation (0 = never split)
+    #[serde(default)]
+    pub fragment_size: usize,
+    
+    /// MTU of the TUN device; inner packets larger than this are dropped
+    #[serde(default = "default_tun_mtu")]
+    pub tun_mtu: u16,
+    
+    /// Rewrite the MSS option of tunneled TCP SYNs so segments fit the TUN MTU
+    #[serde(default)]
+    pub mss_clamp: bool,
+    
+    /// Seconds without inbound traffic (including pongs) before a session is disconnected (0 = disabled)
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,
+    
+    /// Consecutive unanswered heartbeats tolerated before a connection is considered dead (0 = unlimited)
+    #[serde(default = "default_max_missed_pongs")]
+    pub max_missed_pongs: usize,
+    
+    /// Seconds between heartbeats on an established session (capped at a third of the idle timeout)
+    #[serde(default = "default_heartbeat_interval_secs")]
+    pub heartbeat_interval_secs: u64,
+    
+    /// RTT in milliseconds above which the heartbeat interval backs off (0 = fixed interval)
+    #[serde(default = "default_heartbeat_rtt_threshold_ms")]
+    pub heartbeat_rtt_threshold_ms: u64,
+    
+    /// Longest heartbeat interval in seconds reached by backoff
+    #[serde(default = "default_max_heartbeat_interval_secs")]
+    pub max_heartbeat_interval_secs: u64,
+    
     /// Data directory
     pub data_dir: PathBuf,
     
@@ -275,15 +860,402 @@ pub struct ServerConfig {
     #[serde(default = "default_security_mode")]
     pub remote_security_mode: String,
     
+    /// Enable chaos (fault injection) mode; only honored in builds with the `chaos` feature
+    #[serde(default)]
+    pub enable_chaos_mode: bool,
+    
+    /// Disconnect active sessions whose access was revoked when the ACL is reloaded
+    #[serde(default)]
+    pub acl_revoke_on_reload: bool,
+    
+    /// Maximum sessions disconnected per second after an ACL reload
+    #[serde(default = "default_acl_revocation_rate")]
+    pub acl_revocation_rate: u32,
+    
+    /// Whether an ACL reload re-applies tier changes to active sessions
+    #[serde(default)]
+    pub acl_tier_policy: AclTierPolicy,
+    
+    /// What to do when a key with an active session authenticates again
+    #[serde(default)]
+    pub duplicate_session_policy: DuplicateSessionPolicy,
+    
+    /// How tightly authentication challenges are bound to the client address
+    #[serde(default)]
+    pub challenge_address_binding: ChallengeAddressBinding,
+    
+    /// Whether failed authentications report their reason or a generic error
+    #[serde(default)]
+    pub acl_deny_response: AclDenyResponse,
+    
+    /// Failed authentications after which a source IP is refused until they age out of the window (0 = unlimited)
+    #[serde(default = "default_auth_failure_limit")]
+    pub auth_failure_limit: usize,
+    
+    /// Sliding window over which failed authentications per source IP are counted, in seconds
+    #[serde(default = "default_auth_failure_window_secs")]
+    pub auth_failure_window_secs: u64,
+    
+    /// How long a replaced server key still serves clients that were sent it, in seconds.
+    /// Applied at startup and on every reload, which also picks up a new key written
+    /// over `server_key_file`.
+    #[serde(default = "default_server_key_rotation_overlap_secs")]
+    pub server_key_rotation_overlap_secs: u64,
+    
+    /// Proof-of-work difficulty (leading zero bits) required with each challenge (0 = none; clients must support it)
+    #[serde(default)]
+    pub pow_difficulty: u8,
+    
+    /// Difficulty reached when all challenge slots are pending, raising it under handshake floods (0 = fixed at pow_difficulty)
+    #[serde(default)]
+    pub max_pow_difficulty: u8,
+    
+    /// Seconds a session stays resumable with its resumption token after it ends (0 = disabled)
+    #[serde(default = "default_resumption_ttl_secs")]
+    pub resumption_ttl_secs: u64,
+    
+    /// Data packets rejected before the handshake completes until the connection is dropped (0 = drop on the first)
+    #[serde(default = "default_max_handshake_data_packets")]
+    pub max_handshake_data_packets: u32,
+    
+    /// Refuse clients that do not support `bound-session-key` (IpAssign key sealed to the session id and public key)
+    #[serde(default)]
+    pub require_bound_session_key: bool,
+    
+    /// Fraction of connections (0.0-1.0) that get detailed packet tracing, decided at connect time
+    #[serde(default)]
+    pub trace_sample_rate: f64,
+    
+    /// Seconds after startup during which the server reports not-ready (0 = disabled)
+    #[serde(default)]
+    pub warmup_secs: u64,
+    
+    /// How new connections are handled during warmup
+    #[serde(default)]
+    pub warmup_mode: WarmupMode,
+    
+    /// Connections accepted per second during warmup in throttle mode
+    #[serde(default = "default_warmup_accepts_per_sec")]
+    pub warmup_accepts_per_sec: u32,
+    
+    /// Per-client inbound packet rate limit in packets/sec (0 = unlimited)
+    #[serde(default = "default_max_client_packets_per_sec")]
+    pub max_client_packets_per_sec: u64,
+    
+    /// Per-client inbound byte rate limit in bytes/sec (0 = unlimited)
+    #[serde(default = "default_max_client_bytes_per_sec")]
+    pub max_client_bytes_per_sec: u64,
+    
+    /// Window in milliseconds for aggregating repetitive log events (auth/TLS failures)
+    #[serde(default = "default_log_aggregation_window_ms")]
+    pub log_aggregation_window_ms: u64,
+    
+    /// Number of session keys to pre-generate for handshakes (0 = generate inline)
+    #[serde(default = "default_session_key_pool_size")]
+    pub session_key_pool_size: usize,
+    
+    /// Total seconds a client may postpone each scheduled key rotation (0 = not allowed)
+    #[serde(default = "default_max_key_rotation_deferral_secs")]
+    pub max_key_rotation_deferral_secs: u64,
+    
+    /// Server-wide key rotations per second; excess rotations are briefly delayed (0 = unlimited)
+    #[serde(default = "default_max_key_rotations_per_sec")]
+    pub max_key_rotations_per_sec: u32,
+    
+    /// Seconds a client's IP is held for reconnection after its session ends (0 = release immediately)
+    #[serde(default = "default_ip_release_grace_secs")]
+    pub ip_release_grace_secs: u64,
+    
+    /// Longest IP lease in seconds a client may request; requests above it are clamped (0 = session timeout)
+    #[serde(default = "default_max_lease_duration_secs")]
+    pub max_lease_duration_secs: u64,
+    
+    /// Fixed IPs reserved for clients across reconnects, as "PUBLIC_KEY=IP"
+    #[serde(default)]
+    pub ip_reservations: Vec<String>,
+    
+    /// File to save IP leases to so they survive restarts (not persisted if not set)
+    #[serde(default)]
+    pub ip_lease_file: Option<PathBuf>,
+    
+    /// Seconds between ServerLoad packets to clients that opt in (0 = disabled)
+    #[serde(default = "default_server_load_interval_secs")]
+    pub server_load_interval_secs: u64,
+    
+    /// Allowed WebSocket Origin values (empty = Origin not checked)
+    #[serde(default)]
+    pub ws_allowed_origins: Vec<String>,
+    
+    /// TLS ALPN protocols advertised; TLS clients must negotiate one of them (empty = ALPN not negotiated)
+    #[serde(default)]
+    pub alpn_protocols: Vec<String>,
+    
+    /// CAs whose client certificates (with Ed25519 keys) authenticate clients without the challenge (None = not requested)
+    #[serde(default)]
+    pub client_ca_file: Option<PathBuf>,
+    
+    /// Refuse TLS clients without a client certificate
+    #[serde(default)]
+    pub require_client_cert: bool,
+    
+    /// Inner IP protocols allowed through the tunnel, by name or number (empty = all allowed)
+    #[serde(default)]
+    pub tunnel_allowed_protocols: Vec<String>,
+    
+    /// Whether inner packets must come from the client's assigned tunnel IP
+    #[serde(default)]
+    pub source_ip_policy: SourceIpPolicy,
+    
+    /// Decrypted packets that may wait for the TUN device before drops start
+    #[serde(default = "default_tun_queue_depth")]
+    pub tun_queue_depth: usize,
+    
+    /// Which packet is dropped when the TUN write queue is full
+    #[serde(default)]
+    pub tun_queue_policy: TunQueuePolicy,
+    
+    /// Messages that may wait for each client's socket before the oldest Data packets are dropped
+    #[serde(default = "default_send_queue_depth")]
+    pub send_queue_depth: usize,
+    
+    /// WebSocket subprotocol clients must offer during the upgrade
+    #[serde(default)]
+    pub ws_subprotocol: Option<String>,
+    
+    /// Largest WebSocket message in bytes a client may send before it is disconnected
+    #[serde(default = "default_ws_max_message_size")]
+    pub ws_max_message_size: usize,
+    
+    /// Largest single WebSocket frame in bytes a client may send before it is disconnected
+    #[serde(default = "default_ws_max_frame_size")]
+    pub ws_max_frame_size: usize,
+    
+    /// Retries for transient shared secret derivation failures (0 = no retry)
+    #[serde(default = "default_shared_secret_retries")]
+    pub shared_secret_retries: u32,
+    
+    /// Backoff in milliseconds before the first shared secret retry (doubles per retry)
+    #[serde(default = "default_shared_secret_retry_backoff_ms")]
+    pub shared_secret_retry_backoff_ms: u64,
+    
+    /// NetFlow v5 collector for tunnel flow records (disabled if not set)
+    #[serde(default)]
+    pub flow_collector: Option<SocketAddr>,
+    
+    /// Address for the Prometheus `/metrics` HTTP listener (disabled if not set)
+    #[serde(default)]
+    pub metrics_listen: Option<SocketAddr>,
+    
+    /// Address for the admin HTTP listener, read-only except for `POST /reload` from loopback peers;
+    /// only honored in builds with the `admin-api` feature
+    #[serde(default)]
+    pub admin_listen: Option<SocketAddr>,
+    
+    /// File to append one JSON lifecycle record per finished session (disabled if not set)
+    #[serde(default)]
+    pub session_record_file: Option<PathBuf>,
+    
+    /// File to append one JSON audit event per authentication decision (disabled if not set)
+    #[serde(default)]
+    pub auth_audit_file: Option<PathBuf>,
+    
+    /// Maximum concurrently tracked tunnel flows
+    #[serde(default = "default_flow_max_tracked")]
+    pub flow_max_tracked: usize,
+    
+    /// Seconds without packets before a flow is exported
+    #[serde(default = "default_flow_idle_timeout_secs")]
+    pub flow_idle_timeout_secs: u64,
+    
+    /// Seconds after which long-lived flows are exported
+    #[serde(default = "default_flow_active_timeout_secs")]
+    pub flow_active_timeout_secs: u64,
+    
+    /// UDP address of a StatsD or InfluxDB endpoint for per-client traffic (disabled if not set)
+    #[serde(default)]
+    pub traffic_export_addr: Option<SocketAddr>,
+    
+    /// Line format for per-client traffic export
+    #[serde(default)]
+    pub traffic_export_format: TrafficExportFormat,
+    
+    /// Seconds between per-client traffic samples
+    #[serde(default = "default_traffic_export_interval_secs")]
+    pub traffic_export_interval_secs: u64,
+    
+    /// Pin each client's session to one of this many dedicated worker threads (0 = disabled)
+    #[serde(default = "default_session_worker_threads")]
+    pub session_worker_threads: usize,
+    
+    /// Recurring maintenance windows rejecting new connections, as "[DAY] HH:MM-HH:MM" in UTC
+    #[serde(default)]
+    pub quiet_hours: Vec<String>,
+    
+    /// Maximum stateful features active per session (None = unlimited)
+    #[serde(default)]
+    pub max_stateful_features: Option<usize>,
+    
+    /// Unauthenticated Hello capability queries answered per source IP per minute (0 = disabled)
+    #[serde(default = "default_capability_queries_per_minute")]
+    pub capability_queries_per_minute: usize,
+    
     /// Key manager for server keys
     #[serde(skip)]
     pub key_manager: Option<Arc<KeyManager>>,
+    
+    /// File the configuration was loaded from, re-read on reload
+    #[serde(skip)]
+    pub config_file: Option<PathBuf>,
 }
 
 fn default_security_mode() -> String {
     "restricted".to_string()
 }
 
+fn default_acl_revocation_rate() -> u32 {
+    defaults::DEFAULT_ACL_REVOCATION_RATE
+}
+
+fn default_max_client_packets_per_sec() -> u64 {
+    defaults::DEFAULT_MAX_CLIENT_PACKETS_PER_SEC
+}
+
+fn default_max_client_bytes_per_sec() -> u64 {
+    defaults::DEFAULT_MAX_CLIENT_BYTES_PER_SEC
+}
+
+fn default_log_aggregation_window_ms() -> u64 {
+    defaults::DEFAULT_LOG_AGGREGATION_WINDOW_MS
+}
+
+fn default_session_key_pool_size() -> usize {
+    defaults::DEFAULT_SESSION_KEY_POOL_SIZE
+}
+
+fn default_max_key_rotation_deferral_secs() -> u64 {
+    defaults::DEFAULT_MAX_KEY_ROTATION_DEFERRAL_SECS
+}
+
+fn default_auth_failure_limit() -> usize {
+    defaults::DEFAULT_AUTH_FAILURE_LIMIT
+}
+
+fn default_auth_failure_window_secs() -> u64 {
+    defaults::DEFAULT_AUTH_FAILURE_WINDOW_SECS
+}
+
+fn default_server_key_rotation_overlap_secs() -> u64 {
+    defaults::DEFAULT_SERVER_KEY_ROTATION_OVERLAP_SECS
+}
+
+fn default_resumption_ttl_secs() -> u64 {
+    defaults::DEFAULT_RESUMPTION_TTL_SECS
+}
+
+fn default_tun_mtu() -> u16 {
+    defaults::DEFAULT_TUN_MTU
+}
+
+fn default_idle_timeout_secs() -> u64 {
+    defaults::DEFAULT_IDLE_TIMEOUT_SECS
+}
+
+fn default_max_missed_pongs() -> usize {
+    defaults::DEFAULT_MAX_MISSED_PONGS
+}
+
+fn default_heartbeat_interval_secs() -> u64 {
+    defaults::DEFAULT_HEARTBEAT_INTERVAL_SECS
+}
+
+fn default_heartbeat_rtt_threshold_ms() -> u64 {
+    defaults::DEFAULT_HEARTBEAT_RTT_THRESHOLD_MS
+}
+
+fn default_max_heartbeat_interval_secs() -> u64 {
+    defaults::DEFAULT_MAX_HEARTBEAT_INTERVAL_SECS
+}
+
+fn default_key_rotation_jitter_percent() -> u8 {
+    defaults::DEFAULT_KEY_ROTATION_JITTER_PERCENT
+}
+
+fn default_max_key_rotations_per_sec() -> u32 {
+    defaults::DEFAULT_MAX_KEY_ROTATIONS_PER_SEC
+}
+
+fn default_ip_release_grace_secs() -> u64 {
+    defaults::DEFAULT_IP_RELEASE_GRACE_SECS
+}
+
+fn default_max_lease_duration_secs() -> u64 {
+    defaults::DEFAULT_MAX_LEASE_DURATION_SECS
+}
+
+fn default_server_load_interval_secs() -> u64 {
+    defaults::DEFAULT_SERVER_LOAD_INTERVAL_SECS
+}
+
+fn default_warmup_accepts_per_sec() -> u32 {
+    defaults::DEFAULT_WARMUP_ACCEPTS_PER_SEC
+}
+
+fn default_max_handshake_data_packets() -> u32 {
+    defaults::DEFAULT_MAX_HANDSHAKE_DATA_PACKETS
+}
+
+fn default_tun_queue_depth() -> usize {
+    defaults::DEFAULT_TUN_QUEUE_DEPTH
+}
+
+fn default_send_queue_depth() -> usize {
+    defaults::DEFAULT_SEND_QUEUE_DEPTH
+}
+
+fn default_ws_max_message_size() -> usize {
+    defaults::DEFAULT_WS_MAX_MESSAGE_SIZE
+}
+
+fn default_ws_max_frame_size() -> usize {
+    defaults::DEFAULT_WS_MAX_FRAME_SIZE
+}
+
+fn default_shared_secret_retries() -> u32 {
+    defaults::DEFAULT_SHARED_SECRET_RETRIES
+}
+
+fn default_shared_secret_retry_backoff_ms() -> u64 {
+    defaults::DEFAULT_SHARED_SECRET_RETRY_BACKOFF_MS
+}
+
+fn default_flow_max_tracked() -> usize {
+    defaults::DEFAULT_FLOW_MAX_TRACKED
+}
+
+fn default_flow_idle_timeout_secs() -> u64 {
+    defaults::DEFAULT_FLOW_IDLE_TIMEOUT_SECS
+}
+
+fn default_flow_active_timeout_secs() -> u64 {
+    defaults::DEFAULT_FLOW_ACTIVE_TIMEOUT_SECS
+}
+
+fn default_traffic_export_interval_secs() -> u64 {
+    defaults::DEFAULT_TRAFFIC_EXPORT_INTERVAL_SECS
+}
+
+fn default_padding_bucket_size() -> usize {
+    defaults::DEFAULT_PADDING_BUCKET_SIZE
+}
+
+fn default_session_worker_threads() -> usize {
+    defaults::DEFAULT_SESSION_WORKER_THREADS
+}
+
+fn default_capability_queries_per_minute() -> usize {
+    defaults::DEFAULT_CAPABILITY_QUERIES_PER_MINUTE
+}
+
 impl ServerConfig {
     /// Create a new server configuration from command line arguments
     pub fn from_args(args: ServerArgs) -> Result<Self, ConfigError> {
@@ -332,6 +1304,9 @@ impl ServerConfig {
                 if args.listen != "0.0.0.0:8443" {
                     config.listen_addr = args.listen.parse()?;
                 }
+                if !args.additional_listen_addrs.is_empty() {
+                    config.additional_listen_addrs = args.additional_listen_addrs;
+                }
                 
                 // Override registration data with loaded values
                 if registration_reference_code.is_some() {
@@ -345,6 +1320,12 @@ impl ServerConfig {
                 config.enable_remote_management = args.enable_remote_management;
                 config.remote_security_mode = args.remote_security_mode;
                 
+                if args.enable_chaos_mode {
+                    config.enable_chaos_mode = true;
+                }
+                
+                config.config_file = Some(PathBuf::from(config_path));
+                
                 // Validate the config
                 config.validate()?;
                 return Ok(config);
@@ -390,6 +1371,7 @@ impl ServerConfig {
             mode: args.mode,
             transport_security: args.transport_security,
             listen_addr,
+            additional_listen_addrs: args.additional_listen_addrs,
             tun_name: args.tun_name,
             subnet: args.subnet,
             cert_file,
@@ -398,6 +1380,8 @@ impl ServerConfig {
             enable_obfuscation: args.enable_obfuscation,
             obfuscation_method: args.obfuscation_method,
             enable_padding: args.enable_padding,
+            padding_policy: args.padding_policy,
+            padding_bucket_size: args.padding_bucket_size,
             key_rotation_interval: Duration::from_secs(args.key_rotation_interval),
             session_timeout: Duration::from_secs(args.session_timeout),
             max_connections_per_ip: args.max_connections_per_ip,
@@ -409,7 +1393,81 @@ impl ServerConfig {
             api_url: args.api_url,
             enable_remote_management: args.enable_remote_management,
             remote_security_mode: args.remote_security_mode,
+            enable_chaos_mode: args.enable_chaos_mode,
+            acl_revoke_on_reload: args.acl_revoke_on_reload,
+            acl_revocation_rate: args.acl_revocation_rate,
+            max_client_packets_per_sec: args.max_client_packets_per_sec,
+            max_client_bytes_per_sec: args.max_client_bytes_per_sec,
+            log_aggregation_window_ms: args.log_aggregation_window_ms,
+            session_key_pool_size: args.session_key_pool_size,
+            ws_allowed_origins: args.ws_allowed_origins,
+            alpn_protocols: args.alpn_protocols,
+            client_ca_file: args.client_ca_file,
+            require_client_cert: args.require_client_cert,
+            ws_subprotocol: args.ws_subprotocol,
+            ws_max_message_size: args.ws_max_message_size,
+            ws_max_frame_size: args.ws_max_frame_size,
+            shared_secret_retries: args.shared_secret_retries,
+            shared_secret_retry_backoff_ms: args.shared_secret_retry_backoff_ms,
+            flow_collector: args.flow_collector,
+            flow_max_tracked: args.flow_max_tracked,
+            flow_idle_timeout_secs: args.flow_idle_timeout_secs,
+            flow_active_timeout_secs: args.flow_active_timeout_secs,
+            traffic_export_addr: args.traffic_export_addr,
+            traffic_export_format: args.traffic_export_format,
+            traffic_export_interval_secs: args.traffic_export_interval_secs,
+            session_worker_threads: args.session_worker_threads,
+            quiet_hours: args.quiet_hours,
+            max_stateful_features: args.max_stateful_features,
+            capability_queries_per_minute: args.capability_queries_per_minute,
+            max_key_rotation_deferral_secs: args.max_key_rotation_deferral_secs,
+            acl_tier_policy: args.acl_tier_policy,
+            ip_release_grace_secs: args.ip_release_grace_secs,
+            max_lease_duration_secs: args.max_lease_duration_secs,
+            ip_reservations: args.ip_reservations,
+            ip_lease_file: args.ip_lease_file,
+            server_load_interval_secs: args.server_load_interval_secs,
+            duplicate_session_policy: args.duplicate_session_policy,
+            warmup_secs: args.warmup_secs,
+            warmup_mode: args.warmup_mode,
+            warmup_accepts_per_sec: args.warmup_accepts_per_sec,
+            max_key_rotations_per_sec: args.max_key_rotations_per_sec,
+            tunnel_allowed_protocols: args.tunnel_allowed_protocols,
+            source_ip_policy: args.source_ip_policy,
+            challenge_address_binding: args.challenge_address_binding,
+            acl_deny_response: args.acl_deny_response,
+            auth_failure_limit: args.auth_failure_limit,
+            auth_failure_window_secs: args.auth_failure_window_secs,
+            server_key_rotation_overlap_secs: args.server_key_rotation_overlap_secs,
+            pow_difficulty: args.pow_difficulty,
+            max_pow_difficulty: args.max_pow_difficulty,
+            resumption_ttl_secs: args.resumption_ttl_secs,
+            session_record_file: args.session_record_file,
+            auth_audit_file: args.auth_audit_file,
+            max_handshake_data_packets: args.max_handshake_data_packets,
+            tun_queue_depth: args.tun_queue_depth,
+            tun_queue_policy: args.tun_queue_policy,
+            send_queue_depth: args.send_queue_depth,
+            require_bound_session_key: args.require_bound_session_key,
+            trace_sample_rate: args.trace_sample_rate,
+            max_sessions: args.max_sessions,
+            metrics_listen: args.metrics_listen,
+            admin_listen: args.admin_listen,
+            key_rotation_jitter_percent: args.key_rotation_jitter_percent,
+            idle_timeout_secs: args.idle_timeout_secs,
+            max_missed_pongs: args.max_missed_pongs,
+            heartbeat_interval_secs: args.heartbeat_interval_secs,
+            heartbeat_rtt_threshold_ms: args.heartbeat_rtt_threshold_ms,
+            max_heartbeat_interval_secs: args.max_heartbeat_interval_secs,
+            max_sessions_per_key: args.max_sessions_per_key,
+            max_connections: args.max_connections,
+            connection_queue_timeout_ms: args.connection_queue_timeout_ms,
+            challenge_batch_window_ms: args.challenge_batch_window_ms,
+            fragment_size: args.fragment_size,
+            tun_mtu: args.tun_mtu,
+            mss_clamp: args.mss_clamp,
             key_manager: None,
+            config_file: None,
         };
         
         // Validate the config
@@ -442,9 +1500,26 @@ impl ServerConfig {
                         "Key file not found: {:?}", self.key_file
                     )));
                 }
+                if let Some(ca_file) = self.client_ca_file.as_ref().filter(|f| !f.exists()) {
+                    return Err(ConfigError::Invalid(format!(
+                        "Client CA file not found: {:?}", ca_file
+                    )));
+                }
             }
         }
         
+        if self.client_ca_file.is_some() && self.transport_security != TransportSecurity::Tls {
+            return Err(ConfigError::Invalid(
+                "Client certificate authentication requires TLS transport".to_string()
+            ));
+        }
+        
+        if self.require_client_cert && self.client_ca_file.is_none() {
+            return Err(ConfigError::Invalid(
+                "Requiring client certificates needs a client CA file".to_string()
+            ));
+        }
+        
         // Validate obfuscation method
         if self.enable_obfuscation {
             match self.obfuscation_method.as_str() {
@@ -462,12 +1537,304 @@ impl ServerConfig {
             ));
         }
         
+        if self.idle_timeout_secs != 0 && self.idle_timeout_secs < defaults::MIN_IDLE_TIMEOUT_SECS {
+            return Err(ConfigError::Invalid(format!(
+                "Idle timeout must be 0 (disabled) or at least {} seconds", defaults::MIN_IDLE_TIMEOUT_SECS
+            )));
+        }
+        
+        if self.max_missed_pongs > defaults::MAX_MISSED_PONGS {
+            return Err(ConfigError::Invalid(format!(
+                "Missed pongs must not exceed {}", defaults::MAX_MISSED_PONGS
+            )));
+        }
+        
+        if self.heartbeat_interval_secs == 0 || self.heartbeat_interval_secs > defaults::MAX_HEARTBEAT_INTERVAL_SECS {
+            return Err(ConfigError::Invalid(format!(
+                "Heartbeat interval must be between 1 and {} seconds", defaults::MAX_HEARTBEAT_INTERVAL_SECS
+            )));
+        }
+        
+        if self.heartbeat_rtt_threshold_ms != 0
+            && (self.max_heartbeat_interval_secs < self.heartbeat_interval_secs
+                || self.max_heartbeat_interval_secs > defaults::MAX_HEARTBEAT_INTERVAL_SECS)
+        {
+            return Err(ConfigError::Invalid(format!(
+                "Maximum heartbeat interval must be between the heartbeat interval ({}s) and {} seconds",
+                self.heartbeat_interval_secs, defaults::MAX_HEARTBEAT_INTERVAL_SECS
+            )));
+        }
+        
+        if self.key_rotation_jitter_percent > defaults::MAX_KEY_ROTATION_JITTER_PERCENT {
+            return Err(ConfigError::Invalid(format!(
+                "Key rotation jitter must not exceed {}%", defaults::MAX_KEY_ROTATION_JITTER_PERCENT
+            )));
+        }
+        
+        if self.connection_queue_timeout_ms > defaults::MAX_CONNECTION_QUEUE_TIMEOUT_MS {
+            return Err(ConfigError::Invalid(format!(
+                "Connection queue timeout must not exceed {} ms", defaults::MAX_CONNECTION_QUEUE_TIMEOUT_MS
+            )));
+        }
+        
+        if self.auth_failure_limit > 0
+            && !(1..=defaults::MAX_AUTH_FAILURE_WINDOW_SECS).contains(&self.auth_failure_window_secs)
+        {
+            return Err(ConfigError::Invalid(format!(
+                "Auth failure window must be between 1 and {} seconds", defaults::MAX_AUTH_FAILURE_WINDOW_SECS
+            )));
+        }
+        
+        // A shorter overlap would fail challenges still in flight when the key is replaced
+        let min_overlap = AUTH_CHALLENGE_TIMEOUT.as_secs();
+        if !(min_overlap..=defaults::MAX_SERVER_KEY_ROTATION_OVERLAP_SECS).contains(&self.server_key_rotation_overlap_secs) {
+            return Err(ConfigError::Invalid(format!(
+                "Server key rotation overlap must be between {} and {} seconds",
+                min_overlap, defaults::MAX_SERVER_KEY_ROTATION_OVERLAP_SECS
+            )));
+        }
+        
+        if self.pow_difficulty > MAX_POW_DIFFICULTY || self.max_pow_difficulty > MAX_POW_DIFFICULTY {
+            return Err(ConfigError::Invalid(format!(
+                "Proof-of-work difficulty must not exceed {} bits", MAX_POW_DIFFICULTY
+            )));
+        }
+        
+        if self.max_pow_difficulty != 0 && self.max_pow_difficulty < self.pow_difficulty {
+            return Err(ConfigError::Invalid(
+                "Maximum proof-of-work difficulty must be 0 or at least the base difficulty".to_string()
+            ));
+        }
+        
+        if self.resumption_ttl_secs > defaults::MAX_RESUMPTION_TTL_SECS {
+            return Err(ConfigError::Invalid(format!(
+                "Resumption TTL must not exceed {} seconds", defaults::MAX_RESUMPTION_TTL_SECS
+            )));
+        }
+        
+        if self.challenge_batch_window_ms > defaults::MAX_CHALLENGE_BATCH_WINDOW_MS {
+            return Err(ConfigError::Invalid(format!(
+                "Challenge batch window must not exceed {} ms", defaults::MAX_CHALLENGE_BATCH_WINDOW_MS
+            )));
+        }
+        
+        if self.fragment_size != 0 && self.fragment_size < defaults::MIN_FRAGMENT_SIZE {
+            return Err(ConfigError::Invalid(format!(
+                "Fragment size must be 0 or at least {} bytes", defaults::MIN_FRAGMENT_SIZE
+            )));
+        }
+        
+        if !(defaults::MIN_TUN_MTU..=defaults::MAX_TUN_MTU).contains(&self.tun_mtu) {
+            return Err(ConfigError::Invalid(format!(
+                "TUN MTU must be between {} and {}", defaults::MIN_TUN_MTU, defaults::MAX_TUN_MTU
+            )));
+        }
+        
         if self.session_timeout < Duration::from_secs(300) {
             return Err(ConfigError::Invalid(
                 "Session timeout must be at least 300 seconds".to_string()
             ));
         }
         
+        if self.log_aggregation_window_ms == 0 {
+            return Err(ConfigError::Invalid(
+                "Log aggregation window must be greater than 0".to_string()
+            ));
+        }
+        
+        if self.session_key_pool_size > defaults::MAX_SESSION_KEY_POOL_SIZE {
+            return Err(ConfigError::Invalid(format!(
+                "Session key pool size must not exceed {}", defaults::MAX_SESSION_KEY_POOL_SIZE
+            )));
+        }
+        
+        if self.max_key_rotation_deferral_secs > defaults::MAX_KEY_ROTATION_DEFERRAL_SECS {
+            return Err(ConfigError::Invalid(format!(
+                "Key rotation deferral must not exceed {} seconds", defaults::MAX_KEY_ROTATION_DEFERRAL_SECS
+            )));
+        }
+        
+        if self.ip_release_grace_secs > defaults::MAX_IP_RELEASE_GRACE_SECS {
+            return Err(ConfigError::Invalid(format!(
+                "IP release grace period must not exceed {} seconds", defaults::MAX_IP_RELEASE_GRACE_SECS
+            )));
+        }
+        
+        if self.max_lease_duration_secs != 0 && self.max_lease_duration_secs < MIN_REQUESTED_LEASE_SECS {
+            return Err(ConfigError::Invalid(format!(
+                "Maximum lease duration must be 0 or at least {} seconds", MIN_REQUESTED_LEASE_SECS
+            )));
+        }
+        
+        if let Err(e) = crate::network::ip_pool::parse_reservations(&self.ip_reservations) {
+            return Err(ConfigError::Invalid(e.to_string()));
+        }
+        
+        if self.server_load_interval_secs != 0 && self.server_load_interval_secs < defaults::MIN_SERVER_LOAD_INTERVAL_SECS {
+            return Err(ConfigError::Invalid(format!(
+                "Server load interval must be 0 (disabled) or at least {} seconds", defaults::MIN_SERVER_LOAD_INTERVAL_SECS
+            )));
+        }
+        
+        if self.warmup_secs > defaults::MAX_WARMUP_SECS {
+            return Err(ConfigError::Invalid(format!(
+                "Warmup period cannot exceed {} seconds", defaults::MAX_WARMUP_SECS
+            )));
+        }
+        
+        if self.warmup_mode == WarmupMode::Throttle && self.warmup_accepts_per_sec == 0 {
+            return Err(ConfigError::Invalid(
+                "Warmup accept rate must be greater than 0 in throttle mode".to_string()
+            ));
+        }
+        
+        if !(0.0..=1.0).contains(&self.trace_sample_rate) {
+            return Err(ConfigError::Invalid(
+                "Trace sample rate must be between 0.0 and 1.0".to_string()
+            ));
+        }
+        
+        if self.tun_queue_depth == 0 || self.tun_queue_depth > defaults::MAX_TUN_QUEUE_DEPTH {
+            return Err(ConfigError::Invalid(format!(
+                "TUN queue depth must be between 1 and {}", defaults::MAX_TUN_QUEUE_DEPTH
+            )));
+        }
+        
+        if self.send_queue_depth == 0 || self.send_queue_depth > defaults::MAX_SEND_QUEUE_DEPTH {
+            return Err(ConfigError::Invalid(format!(
+                "Send queue depth must be between 1 and {}", defaults::MAX_SEND_QUEUE_DEPTH
+            )));
+        }
+        
+        if let Some(protocol) = self.tunnel_allowed_protocols.iter()
+            .find(|p| crate::server::routing::parse_ip_protocol(p).is_none())
+        {
+            return Err(ConfigError::Invalid(format!(
+                "Unknown tunnel protocol '{}': expected tcp, udp, icmp, icmpv6 or a number 0-255", protocol
+            )));
+        }
+        
+        if self.ws_allowed_origins.iter().any(|o| o.trim().is_empty()) {
+            return Err(ConfigError::Invalid(
+                "Allowed WebSocket origins must not be empty".to_string()
+            ));
+        }
+        
+        if let Some(protocol) = self.alpn_protocols.iter().find(|p| p.is_empty() || p.len() > 255) {
+            return Err(ConfigError::Invalid(format!(
+                "ALPN protocol must be 1-255 bytes: {:?}", protocol
+            )));
+        }
+        
+        if let Some(protocol) = &self.ws_subprotocol {
+            if protocol.is_empty() || protocol.contains(|c: char| c == ',' || c.is_whitespace()) {
+                return Err(ConfigError::Invalid(format!(
+                    "Invalid WebSocket subprotocol: {:?}", protocol
+                )));
+            }
+        }
+        
+        for (name, size) in [("message", self.ws_max_message_size), ("frame", self.ws_max_frame_size)] {
+            if !(defaults::MIN_WS_MESSAGE_SIZE..=defaults::MAX_WS_MESSAGE_SIZE).contains(&size) {
+                return Err(ConfigError::Invalid(format!(
+                    "WebSocket max {} size must be between {} and {} bytes",
+                    name, defaults::MIN_WS_MESSAGE_SIZE, defaults::MAX_WS_MESSAGE_SIZE
+                )));
+            }
+        }
+        
+        if self.ws_max_frame_size > self.ws_max_message_size {
+            return Err(ConfigError::Invalid(
+                "WebSocket max frame size must not exceed the max message size".to_string()
+            ));
+        }
+        
+        if self.shared_secret_retries > defaults::MAX_SHARED_SECRET_RETRIES {
+            return Err(ConfigError::Invalid(format!(
+                "Shared secret retries must not exceed {}", defaults::MAX_SHARED_SECRET_RETRIES
+            )));
+        }
+        
+        let listen_addrs = self.listen_addrs();
+        if let Some(duplicate) = listen_addrs.iter().enumerate()
+            .find(|(i, addr)| addr.port() != 0 && listen_addrs[..*i].contains(addr))
+            .map(|(_, addr)| addr)
+        {
+            return Err(ConfigError::Invalid(format!("Listen address {} is given more than once", duplicate)));
+        }
+        let listens_on = |port: u16| listen_addrs.iter().any(|addr| addr.port() == port);
+        
+        if let Some(metrics_addr) = self.metrics_listen {
+            if metrics_addr.port() != 0 && listens_on(metrics_addr.port()) {
+                return Err(ConfigError::Invalid(
+                    "Metrics listener must use a different port than the main listeners".to_string()
+                ));
+            }
+        }
+        
+        if let Some(admin_addr) = self.admin_listen {
+            let metrics_port = self.metrics_listen.map(|addr| addr.port());
+            if admin_addr.port() != 0
+                && (listens_on(admin_addr.port()) || Some(admin_addr.port()) == metrics_port) {
+                return Err(ConfigError::Invalid(
+                    "Admin listener must use a different port than the main and metrics listeners".to_string()
+                ));
+            }
+        }
+        
+        if self.flow_collector.is_some() {
+            if self.flow_max_tracked == 0 {
+                return Err(ConfigError::Invalid(
+                    "Maximum tracked flows must be greater than 0".to_string()
+                ));
+            }
+            
+            if self.flow_idle_timeout_secs == 0 || self.flow_active_timeout_secs == 0 {
+                return Err(ConfigError::Invalid(
+                    "Flow timeouts must be greater than 0".to_string()
+                ));
+            }
+        }
+        
+        if self.traffic_export_addr.is_some()
+            && (self.traffic_export_interval_secs == 0
+                || self.traffic_export_interval_secs > defaults::MAX_TRAFFIC_EXPORT_INTERVAL_SECS) {
+            return Err(ConfigError::Invalid(format!(
+                "Traffic export interval must be between 1 and {} seconds",
+                defaults::MAX_TRAFFIC_EXPORT_INTERVAL_SECS
+            )));
+        }
+        
+        if self.enable_padding && self.padding_policy == PaddingPolicy::Bucket
+            && !(defaults::MIN_PADDING_BUCKET_SIZE..=defaults::MAX_PADDING_BUCKET_SIZE).contains(&self.padding_bucket_size) {
+            return Err(ConfigError::Invalid(format!(
+                "Padding bucket size must be between {} and {} bytes",
+                defaults::MIN_PADDING_BUCKET_SIZE, defaults::MAX_PADDING_BUCKET_SIZE
+            )));
+        }
+        
+        if self.session_worker_threads > defaults::MAX_SESSION_WORKER_THREADS {
+            return Err(ConfigError::Invalid(format!(
+                "Session worker threads must not exceed {}", defaults::MAX_SESSION_WORKER_THREADS
+            )));
+        }
+        
+        if self.capability_queries_per_minute > defaults::MAX_CAPABILITY_QUERIES_PER_MINUTE {
+            return Err(ConfigError::Invalid(format!(
+                "Capability queries must not exceed {} per minute", defaults::MAX_CAPABILITY_QUERIES_PER_MINUTE
+            )));
+        }
+        
+        if let Err(e) = crate::server::schedule::QuietHours::parse(&self.quiet_hours) {
+            return Err(ConfigError::Invalid(e.to_string()));
+        }
+        
+        if self.acl_revocation_rate == 0 {
+            return Err(ConfigError::Invalid(
+                "ACL revocation rate must be at least 1 per second".to_string()
+            ));
+        }
+        
         // Validate remote security mode
         match self.remote_security_mode.as_str() {
             "restricted" | "full-access" => (), // Valid modes
@@ -480,6 +1847,13 @@ impl ServerConfig {
         Ok(())
     }
     
+    /// Every address accepting clients, `listen_addr` first
+    pub fn listen_addrs(&self) -> Vec<SocketAddr> {
+        std::iter::once(self.listen_addr)
+            .chain(self.additional_listen_addrs.iter().copied())
+            .collect()
+    }
+    
     /// Check if VPN functionality is enabled
     pub fn is_vpn_enabled(&self) -> bool {
         matches!(self.mode, NodeMode::VPNEnabled | NodeMode::Hybrid)
@@ -554,6 +1928,7 @@ mod tests {
             mode: NodeMode::Hybrid,
             transport_security: TransportSecurity::Tls,
             listen_addr: "127.0.0.1:8080".parse().unwrap(),
+            additional_listen_addrs: Vec::new(),
             tun_name: "tun0".to_string(),
             subnet: "10.7.0.0/24".to_string(),
             cert_file: PathBuf::from("server.crt"),
@@ -562,6 +1937,8 @@ mod tests {
             enable_obfuscation: true,
             obfuscation_method: "xor".to_string(),
             enable_padding: true,
+            padding_policy: PaddingPolicy::Random,
+            padding_bucket_size: defaults::DEFAULT_PADDING_BUCKET_SIZE,
             key_rotation_interval: Duration::from_secs(3600),
             session_timeout: Duration::from_secs(86400),
             max_connections_per_ip: 10,
@@ -573,10 +1950,118 @@ mod tests {
             api_url: "https://api.aeronyx.network".to_string(),
             enable_remote_management: false,
             remote_security_mode: "restricted".to_string(),
+            enable_chaos_mode: false,
+            acl_revoke_on_reload: false,
+            acl_revocation_rate: defaults::DEFAULT_ACL_REVOCATION_RATE,
+            max_client_packets_per_sec: defaults::DEFAULT_MAX_CLIENT_PACKETS_PER_SEC,
+            max_client_bytes_per_sec: defaults::DEFAULT_MAX_CLIENT_BYTES_PER_SEC,
+            log_aggregation_window_ms: defaults::DEFAULT_LOG_AGGREGATION_WINDOW_MS,
+            session_key_pool_size: defaults::DEFAULT_SESSION_KEY_POOL_SIZE,
+            ws_allowed_origins: Vec::new(),
+            ws_subprotocol: None,
+            ws_max_message_size: defaults::DEFAULT_WS_MAX_MESSAGE_SIZE,
+            ws_max_frame_size: defaults::DEFAULT_WS_MAX_FRAME_SIZE,
+            shared_secret_retries: defaults::DEFAULT_SHARED_SECRET_RETRIES,
+            shared_secret_retry_backoff_ms: defaults::DEFAULT_SHARED_SECRET_RETRY_BACKOFF_MS,
+            flow_collector: None,
+            flow_max_tracked: defaults::DEFAULT_FLOW_MAX_TRACKED,
+            flow_idle_timeout_secs: defaults::DEFAULT_FLOW_IDLE_TIMEOUT_SECS,
+            flow_active_timeout_secs: defaults::DEFAULT_FLOW_ACTIVE_TIMEOUT_SECS,
+            traffic_export_addr: None,
+            traffic_export_format: TrafficExportFormat::Statsd,
+            traffic_export_interval_secs: defaults::DEFAULT_TRAFFIC_EXPORT_INTERVAL_SECS,
+            session_worker_threads: defaults::DEFAULT_SESSION_WORKER_THREADS,
+            quiet_hours: Vec::new(),
+            max_stateful_features: None,
+            capability_queries_per_minute: 6,
+            max_key_rotation_deferral_secs: defaults::DEFAULT_MAX_KEY_ROTATION_DEFERRAL_SECS,
+            acl_tier_policy: AclTierPolicy::Snapshot,
+            ip_release_grace_secs: defaults::DEFAULT_IP_RELEASE_GRACE_SECS,
+            max_lease_duration_secs: defaults::DEFAULT_MAX_LEASE_DURATION_SECS,
+            ip_reservations: Vec::new(),
+            ip_lease_file: None,
+            server_load_interval_secs: defaults::DEFAULT_SERVER_LOAD_INTERVAL_SECS,
+            duplicate_session_policy: DuplicateSessionPolicy::AllowMultiple,
+            warmup_secs: defaults::DEFAULT_WARMUP_SECS,
+            warmup_mode: WarmupMode::Throttle,
+            warmup_accepts_per_sec: defaults::DEFAULT_WARMUP_ACCEPTS_PER_SEC,
+            max_key_rotations_per_sec: defaults::DEFAULT_MAX_KEY_ROTATIONS_PER_SEC,
+            tunnel_allowed_protocols: Vec::new(),
+            source_ip_policy: SourceIpPolicy::Strict,
+            challenge_address_binding: ChallengeAddressBinding::Ip,
+            acl_deny_response: AclDenyResponse::Verbose,
+            session_record_file: None,
+            auth_audit_file: None,
+            max_handshake_data_packets: defaults::DEFAULT_MAX_HANDSHAKE_DATA_PACKETS,
+            tun_queue_depth: defaults::DEFAULT_TUN_QUEUE_DEPTH,
+            tun_queue_policy: TunQueuePolicy::DropTail,
+            send_queue_depth: defaults::DEFAULT_SEND_QUEUE_DEPTH,
+            require_bound_session_key: false,
+            trace_sample_rate: 0.0,
+            max_sessions: 0,
+            metrics_listen: None,
+            admin_listen: None,
+            key_rotation_jitter_percent: defaults::DEFAULT_KEY_ROTATION_JITTER_PERCENT,
+            idle_timeout_secs: 0,
+            max_missed_pongs: 0,
+            heartbeat_interval_secs: defaults::DEFAULT_HEARTBEAT_INTERVAL_SECS,
+            heartbeat_rtt_threshold_ms: 0,
+            max_heartbeat_interval_secs: defaults::DEFAULT_MAX_HEARTBEAT_INTERVAL_SECS,
+            max_sessions_per_key: 0,
+            max_connections: 0,
+            connection_queue_timeout_ms: 0,
+            challenge_batch_window_ms: 0,
+            fragment_size: 0,
+            tun_mtu: 1500,
+            mss_clamp: false,
+            alpn_protocols: Vec::new(),
+            client_ca_file: None,
+            require_client_cert: false,
+            auth_failure_limit: 10,
+            auth_failure_window_secs: 300,
+            server_key_rotation_overlap_secs: 300,
+            pow_difficulty: 0,
+            max_pow_difficulty: 0,
+            resumption_ttl_secs: 60,
             key_manager: None,
+            config_file: None,
         };
         
         assert!(config.validate().is_ok());
+        
+        // Additional listeners must not repeat an address or take the metrics port
+        let mut config = config;
+        config.additional_listen_addrs = vec!["[::]:8080".parse().unwrap(), "127.0.0.1:9443".parse().unwrap()];
+        assert!(config.validate().is_ok());
+        assert_eq!(config.listen_addrs()[0], config.listen_addr);
+        config.additional_listen_addrs.push("127.0.0.1:8080".parse().unwrap());
+        assert!(config.validate().is_err());
+        config.additional_listen_addrs.pop();
+        config.metrics_listen = Some("127.0.0.1:9443".parse().unwrap());
+        assert!(config.validate().is_err());
+        config.metrics_listen = None;
+        
+        // WebSocket limits must leave room for a Data packet, frames within messages
+        config.ws_max_frame_size = config.ws_max_message_size * 2;
+        assert!(config.validate().is_err());
+        config.ws_max_frame_size = 1024;
+        assert!(config.validate().is_err());
+        config.ws_max_frame_size = defaults::DEFAULT_WS_MAX_FRAME_SIZE;
+        assert!(config.validate().is_ok());
+        
+        // A lease cap is either unset or long enough to be usable
+        config.max_lease_duration_secs = 30;
+        assert!(config.validate().is_err());
+        config.max_lease_duration_secs = 3600;
+        assert!(config.validate().is_ok());
+        
+        // A replaced server key outlives the challenges that were sent it
+        config.server_key_rotation_overlap_secs = 5;
+        assert!(config.validate().is_err());
+        config.server_key_rotation_overlap_secs = defaults::MAX_SERVER_KEY_ROTATION_OVERLAP_SECS + 1;
+        assert!(config.validate().is_err());
+        config.server_key_rotation_overlap_secs = 300;
+        assert!(config.validate().is_ok());
     }
     
     #[test]
@@ -585,6 +2070,7 @@ mod tests {
             mode: NodeMode::VPNEnabled,
             transport_security: TransportSecurity::Tls,
             listen_addr: "127.0.0.1:8080".parse().unwrap(),
+            additional_listen_addrs: Vec::new(),
             tun_name: "tun0".to_string(),
             subnet: "10.7.0.0".to_string(), // Missing CIDR mask
             cert_file: PathBuf::from("server.crt"),
@@ -593,6 +2079,8 @@ mod tests {
             enable_obfuscation: true,
             obfuscation_method: "xor".to_string(),
             enable_padding: true,
+            padding_policy: PaddingPolicy::Random,
+            padding_bucket_size: defaults::DEFAULT_PADDING_BUCKET_SIZE,
             key_rotation_interval: Duration::from_secs(3600),
             session_timeout: Duration::from_secs(86400),
             max_connections_per_ip: 10,
@@ -604,7 +2092,81 @@ mod tests {
             api_url: "https://api.aeronyx.network".to_string(),
             enable_remote_management: false,
             remote_security_mode: "restricted".to_string(),
+            enable_chaos_mode: false,
+            acl_revoke_on_reload: false,
+            acl_revocation_rate: defaults::DEFAULT_ACL_REVOCATION_RATE,
+            max_client_packets_per_sec: defaults::DEFAULT_MAX_CLIENT_PACKETS_PER_SEC,
+            max_client_bytes_per_sec: defaults::DEFAULT_MAX_CLIENT_BYTES_PER_SEC,
+            log_aggregation_window_ms: defaults::DEFAULT_LOG_AGGREGATION_WINDOW_MS,
+            session_key_pool_size: defaults::DEFAULT_SESSION_KEY_POOL_SIZE,
+            ws_allowed_origins: Vec::new(),
+            ws_subprotocol: None,
+            ws_max_message_size: defaults::DEFAULT_WS_MAX_MESSAGE_SIZE,
+            ws_max_frame_size: defaults::DEFAULT_WS_MAX_FRAME_SIZE,
+            shared_secret_retries: defaults::DEFAULT_SHARED_SECRET_RETRIES,
+            shared_secret_retry_backoff_ms: defaults::DEFAULT_SHARED_SECRET_RETRY_BACKOFF_MS,
+            flow_collector: None,
+            flow_max_tracked: defaults::DEFAULT_FLOW_MAX_TRACKED,
+            flow_idle_timeout_secs: defaults::DEFAULT_FLOW_IDLE_TIMEOUT_SECS,
+            flow_active_timeout_secs: defaults::DEFAULT_FLOW_ACTIVE_TIMEOUT_SECS,
+            traffic_export_addr: None,
+            traffic_export_format: TrafficExportFormat::Statsd,
+            traffic_export_interval_secs: defaults::DEFAULT_TRAFFIC_EXPORT_INTERVAL_SECS,
+            session_worker_threads: defaults::DEFAULT_SESSION_WORKER_THREADS,
+            quiet_hours: Vec::new(),
+            max_stateful_features: None,
+            capability_queries_per_minute: 6,
+            max_key_rotation_deferral_secs: defaults::DEFAULT_MAX_KEY_ROTATION_DEFERRAL_SECS,
+            acl_tier_policy: AclTierPolicy::Snapshot,
+            ip_release_grace_secs: defaults::DEFAULT_IP_RELEASE_GRACE_SECS,
+            max_lease_duration_secs: defaults::DEFAULT_MAX_LEASE_DURATION_SECS,
+            ip_reservations: Vec::new(),
+            ip_lease_file: None,
+            server_load_interval_secs: defaults::DEFAULT_SERVER_LOAD_INTERVAL_SECS,
+            duplicate_session_policy: DuplicateSessionPolicy::AllowMultiple,
+            warmup_secs: defaults::DEFAULT_WARMUP_SECS,
+            warmup_mode: WarmupMode::Throttle,
+            warmup_accepts_per_sec: defaults::DEFAULT_WARMUP_ACCEPTS_PER_SEC,
+            max_key_rotations_per_sec: defaults::DEFAULT_MAX_KEY_ROTATIONS_PER_SEC,
+            tunnel_allowed_protocols: Vec::new(),
+            source_ip_policy: SourceIpPolicy::Strict,
+            challenge_address_binding: ChallengeAddressBinding::Ip,
+            acl_deny_response: AclDenyResponse::Verbose,
+            session_record_file: None,
+            auth_audit_file: None,
+            max_handshake_data_packets: defaults::DEFAULT_MAX_HANDSHAKE_DATA_PACKETS,
+            tun_queue_depth: defaults::DEFAULT_TUN_QUEUE_DEPTH,
+            tun_queue_policy: TunQueuePolicy::DropTail,
+            send_queue_depth: defaults::DEFAULT_SEND_QUEUE_DEPTH,
+            require_bound_session_key: false,
+            trace_sample_rate: 0.0,
+            max_sessions: 0,
+            metrics_listen: None,
+            admin_listen: None,
+            key_rotation_jitter_percent: defaults::DEFAULT_KEY_ROTATION_JITTER_PERCENT,
+            idle_timeout_secs: 0,
+            max_missed_pongs: 0,
+            heartbeat_interval_secs: defaults::DEFAULT_HEARTBEAT_INTERVAL_SECS,
+            heartbeat_rtt_threshold_ms: 0,
+            max_heartbeat_interval_secs: defaults::DEFAULT_MAX_HEARTBEAT_INTERVAL_SECS,
+            max_sessions_per_key: 0,
+            max_connections: 0,
+            connection_queue_timeout_ms: 0,
+            challenge_batch_window_ms: 0,
+            fragment_size: 0,
+            tun_mtu: 1500,
+            mss_clamp: false,
+            alpn_protocols: Vec::new(),
+            client_ca_file: None,
+            require_client_cert: false,
+            auth_failure_limit: 10,
+            auth_failure_window_secs: 300,
+            server_key_rotation_overlap_secs: 300,
+            pow_difficulty: 0,
+            max_pow_difficulty: 0,
+            resumption_ttl_secs: 60,
             key_manager: None,
+            config_file: None,
         };
         
         assert!(config.validate().is_err());
@@ -620,6 +2182,7 @@ mod tests {
             mode: NodeMode::DePINOnly,
             transport_security: TransportSecurity::Tls,
             listen_addr: "127.0.0.1:8080".parse().unwrap(),
+            additional_listen_addrs: Vec::new(),
             tun_name: "tun0".to_string(),
             subnet: "10.7.0.0/24".to_string(),
             cert_file: PathBuf::from("dummy.crt"),
@@ -628,6 +2191,8 @@ mod tests {
             enable_obfuscation: false,
             obfuscation_method: "xor".to_string(),
             enable_padding: false,
+            padding_policy: PaddingPolicy::Random,
+            padding_bucket_size: defaults::DEFAULT_PADDING_BUCKET_SIZE,
             key_rotation_interval: Duration::from_secs(3600),
             session_timeout: Duration::from_secs(86400),
             max_connections_per_ip: 10,
@@ -639,7 +2204,81 @@ mod tests {
             api_url: "https://api.aeronyx.network".to_string(),
             enable_remote_management: false,
             remote_security_mode: "restricted".to_string(),
+            enable_chaos_mode: false,
+            acl_revoke_on_reload: false,
+            acl_revocation_rate: defaults::DEFAULT_ACL_REVOCATION_RATE,
+            max_client_packets_per_sec: defaults::DEFAULT_MAX_CLIENT_PACKETS_PER_SEC,
+            max_client_bytes_per_sec: defaults::DEFAULT_MAX_CLIENT_BYTES_PER_SEC,
+            log_aggregation_window_ms: defaults::DEFAULT_LOG_AGGREGATION_WINDOW_MS,
+            session_key_pool_size: defaults::DEFAULT_SESSION_KEY_POOL_SIZE,
+            ws_allowed_origins: Vec::new(),
+            ws_subprotocol: None,
+            ws_max_message_size: defaults::DEFAULT_WS_MAX_MESSAGE_SIZE,
+            ws_max_frame_size: defaults::DEFAULT_WS_MAX_FRAME_SIZE,
+            shared_secret_retries: defaults::DEFAULT_SHARED_SECRET_RETRIES,
+            shared_secret_retry_backoff_ms: defaults::DEFAULT_SHARED_SECRET_RETRY_BACKOFF_MS,
+            flow_collector: None,
+            flow_max_tracked: defaults::DEFAULT_FLOW_MAX_TRACKED,
+            flow_idle_timeout_secs: defaults::DEFAULT_FLOW_IDLE_TIMEOUT_SECS,
+            flow_active_timeout_secs: defaults::DEFAULT_FLOW_ACTIVE_TIMEOUT_SECS,
+            traffic_export_addr: None,
+            traffic_export_format: TrafficExportFormat::Statsd,
+            traffic_export_interval_secs: defaults::DEFAULT_TRAFFIC_EXPORT_INTERVAL_SECS,
+            session_worker_threads: defaults::DEFAULT_SESSION_WORKER_THREADS,
+            quiet_hours: Vec::new(),
+            max_stateful_features: None,
+            capability_queries_per_minute: 6,
+            max_key_rotation_deferral_secs: defaults::DEFAULT_MAX_KEY_ROTATION_DEFERRAL_SECS,
+            acl_tier_policy: AclTierPolicy::Snapshot,
+            ip_release_grace_secs: defaults::DEFAULT_IP_RELEASE_GRACE_SECS,
+            max_lease_duration_secs: defaults::DEFAULT_MAX_LEASE_DURATION_SECS,
+            ip_reservations: Vec::new(),
+            ip_lease_file: None,
+            server_load_interval_secs: defaults::DEFAULT_SERVER_LOAD_INTERVAL_SECS,
+            duplicate_session_policy: DuplicateSessionPolicy::AllowMultiple,
+            warmup_secs: defaults::DEFAULT_WARMUP_SECS,
+            warmup_mode: WarmupMode::Throttle,
+            warmup_accepts_per_sec: defaults::DEFAULT_WARMUP_ACCEPTS_PER_SEC,
+            max_key_rotations_per_sec: defaults::DEFAULT_MAX_KEY_ROTATIONS_PER_SEC,
+            tunnel_allowed_protocols: Vec::new(),
+            source_ip_policy: SourceIpPolicy::Strict,
+            challenge_address_binding: ChallengeAddressBinding::Ip,
+            acl_deny_response: AclDenyResponse::Verbose,
+            session_record_file: None,
+            auth_audit_file: None,
+            max_handshake_data_packets: defaults::DEFAULT_MAX_HANDSHAKE_DATA_PACKETS,
+            tun_queue_depth: defaults::DEFAULT_TUN_QUEUE_DEPTH,
+            tun_queue_policy: TunQueuePolicy::DropTail,
+            send_queue_depth: defaults::DEFAULT_SEND_QUEUE_DEPTH,
+            require_bound_session_key: false,
+            trace_sample_rate: 0.0,
+            max_sessions: 0,
+            metrics_listen: None,
+            admin_listen: None,
+            key_rotation_jitter_percent: defaults::DEFAULT_KEY_ROTATION_JITTER_PERCENT,
+            idle_timeout_secs: 0,
+            max_missed_pongs: 0,
+            heartbeat_interval_secs: defaults::DEFAULT_HEARTBEAT_INTERVAL_SECS,
+            heartbeat_rtt_threshold_ms: 0,
+            max_heartbeat_interval_secs: defaults::DEFAULT_MAX_HEARTBEAT_INTERVAL_SECS,
+            max_sessions_per_key: 0,
+            max_connections: 0,
+            connection_queue_timeout_ms: 0,
+            challenge_batch_window_ms: 0,
+            fragment_size: 0,
+            tun_mtu: 1500,
+            mss_clamp: false,
+            alpn_protocols: Vec::new(),
+            client_ca_file: None,
+            require_client_cert: false,
+            auth_failure_limit: 10,
+            auth_failure_window_secs: 300,
+            server_key_rotation_overlap_secs: 300,
+            pow_difficulty: 0,
+            max_pow_difficulty: 0,
+            resumption_ttl_secs: 60,
             key_manager: None,
+            config_file: None,
         };
         
         // Test DePIN-only mode
@@ -663,6 +2302,7 @@ mod tests {
             mode: NodeMode::VPNEnabled,
             transport_security: TransportSecurity::Tls,
             listen_addr: "127.0.0.1:8080".parse().unwrap(),
+            additional_listen_addrs: Vec::new(),
             tun_name: "tun0".to_string(),
             subnet: "10.7.0.0/24".to_string(),
             cert_file: PathBuf::from("server.crt"),
@@ -671,6 +2311,8 @@ mod tests {
             enable_obfuscation: false,
             obfuscation_method: "xor".to_string(),
             enable_padding: false,
+            padding_policy: PaddingPolicy::Random,
+            padding_bucket_size: defaults::DEFAULT_PADDING_BUCKET_SIZE,
             key_rotation_interval: Duration::from_secs(3600),
             session_timeout: Duration::from_secs(86400),
             max_connections_per_ip: 10,
@@ -682,7 +2324,81 @@ mod tests {
             api_url: "https://api.aeronyx.network".to_string(),
             enable_remote_management: false,
             remote_security_mode: "restricted".to_string(),
+            enable_chaos_mode: false,
+            acl_revoke_on_reload: false,
+            acl_revocation_rate: defaults::DEFAULT_ACL_REVOCATION_RATE,
+            max_client_packets_per_sec: defaults::DEFAULT_MAX_CLIENT_PACKETS_PER_SEC,
+            max_client_bytes_per_sec: defaults::DEFAULT_MAX_CLIENT_BYTES_PER_SEC,
+            log_aggregation_window_ms: defaults::DEFAULT_LOG_AGGREGATION_WINDOW_MS,
+            session_key_pool_size: defaults::DEFAULT_SESSION_KEY_POOL_SIZE,
+            ws_allowed_origins: Vec::new(),
+            ws_subprotocol: None,
+            ws_max_message_size: defaults::DEFAULT_WS_MAX_MESSAGE_SIZE,
+            ws_max_frame_size: defaults::DEFAULT_WS_MAX_FRAME_SIZE,
+            shared_secret_retries: defaults::DEFAULT_SHARED_SECRET_RETRIES,
+            shared_secret_retry_backoff_ms: defaults::DEFAULT_SHARED_SECRET_RETRY_BACKOFF_MS,
+            flow_collector: None,
+            flow_max_tracked: defaults::DEFAULT_FLOW_MAX_TRACKED,
+            flow_idle_timeout_secs: defaults::DEFAULT_FLOW_IDLE_TIMEOUT_SECS,
+            flow_active_timeout_secs: defaults::DEFAULT_FLOW_ACTIVE_TIMEOUT_SECS,
+            traffic_export_addr: None,
+            traffic_export_format: TrafficExportFormat::Statsd,
+            traffic_export_interval_secs: defaults::DEFAULT_TRAFFIC_EXPORT_INTERVAL_SECS,
+            session_worker_threads: defaults::DEFAULT_SESSION_WORKER_THREADS,
+            quiet_hours: Vec::new(),
+            max_stateful_features: None,
+            capability_queries_per_minute: 6,
+            max_key_rotation_deferral_secs: defaults::DEFAULT_MAX_KEY_ROTATION_DEFERRAL_SECS,
+            acl_tier_policy: AclTierPolicy::Snapshot,
+            ip_release_grace_secs: defaults::DEFAULT_IP_RELEASE_GRACE_SECS,
+            max_lease_duration_secs: defaults::DEFAULT_MAX_LEASE_DURATION_SECS,
+            ip_reservations: Vec::new(),
+            ip_lease_file: None,
+            server_load_interval_secs: defaults::DEFAULT_SERVER_LOAD_INTERVAL_SECS,
+            duplicate_session_policy: DuplicateSessionPolicy::AllowMultiple,
+            warmup_secs: defaults::DEFAULT_WARMUP_SECS,
+            warmup_mode: WarmupMode::Throttle,
+            warmup_accepts_per_sec: defaults::DEFAULT_WARMUP_ACCEPTS_PER_SEC,
+            max_key_rotations_per_sec: defaults::DEFAULT_MAX_KEY_ROTATIONS_PER_SEC,
+            tunnel_allowed_protocols: Vec::new(),
+            source_ip_policy: SourceIpPolicy::Strict,
+            challenge_address_binding: ChallengeAddressBinding::Ip,
+            acl_deny_response: AclDenyResponse::Verbose,
+            session_record_file: None,
+            auth_audit_file: None,
+            max_handshake_data_packets: defaults::DEFAULT_MAX_HANDSHAKE_DATA_PACKETS,
+            tun_queue_depth: defaults::DEFAULT_TUN_QUEUE_DEPTH,
+            tun_queue_policy: TunQueuePolicy::DropTail,
+            send_queue_depth: defaults::DEFAULT_SEND_QUEUE_DEPTH,
+            require_bound_session_key: false,
+            trace_sample_rate: 0.0,
+            max_sessions: 0,
+            metrics_listen: None,
+            admin_listen: None,
+            key_rotation_jitter_percent: defaults::DEFAULT_KEY_ROTATION_JITTER_PERCENT,
+            idle_timeout_secs: 0,
+            max_missed_pongs: 0,
+            heartbeat_interval_secs: defaults::DEFAULT_HEARTBEAT_INTERVAL_SECS,
+            heartbeat_rtt_threshold_ms: 0,
+            max_heartbeat_interval_secs: defaults::DEFAULT_MAX_HEARTBEAT_INTERVAL_SECS,
+            max_sessions_per_key: 0,
+            max_connections: 0,
+            connection_queue_timeout_ms: 0,
+            challenge_batch_window_ms: 0,
+            fragment_size: 0,
+            tun_mtu: 1500,
+            mss_clamp: false,
+            alpn_protocols: Vec::new(),
+            client_ca_file: None,
+            require_client_cert: false,
+            auth_failure_limit: 10,
+            auth_failure_window_secs: 300,
+            server_key_rotation_overlap_secs: 300,
+            pow_difficulty: 0,
+            max_pow_difficulty: 0,
+            resumption_ttl_secs: 60,
             key_manager: None,
+            config_file: None,
         };
         
         // Test TLS mode
@@ -710,6 +2426,7 @@ mod tests {
             mode: NodeMode::DePINOnly,
             transport_security: TransportSecurity::Tls,
             listen_addr: "127.0.0.1:8080".parse().unwrap(),
+            additional_listen_addrs: Vec::new(),
             tun_name: "tun0".to_string(),
             subnet: "10.7.0.0/24".to_string(),
             cert_file: PathBuf::from("dummy.crt"),
@@ -718,6 +2435,8 @@ mod tests {
             enable_obfuscation: false,
             obfuscation_method: "xor".to_string(),
             enable_padding: false,
+            padding_policy: PaddingPolicy::Random,
+            padding_bucket_size: defaults::DEFAULT_PADDING_BUCKET_SIZE,
             key_rotation_interval: Duration::from_secs(3600),
             session_timeout: Duration::from_secs(86400),
             max_connections_per_ip: 10,
@@ -729,7 +2448,81 @@ mod tests {
             api_url: "https://api.aeronyx.network".to_string(),
             enable_remote_management: true,
             remote_security_mode: "invalid-mode".to_string(),
+            enable_chaos_mode: false,
+            acl_revoke_on_reload: false,
+            acl_revocation_rate: defaults::DEFAULT_ACL_REVOCATION_RATE,
+            max_client_packets_per_sec: defaults::DEFAULT_MAX_CLIENT_PACKETS_PER_SEC,
+            max_client_bytes_per_sec: defaults::DEFAULT_MAX_CLIENT_BYTES_PER_SEC,
+            log_aggregation_window_ms: defaults::DEFAULT_LOG_AGGREGATION_WINDOW_MS,
+            session_key_pool_size: defaults::DEFAULT_SESSION_KEY_POOL_SIZE,
+            ws_allowed_origins: Vec::new(),
+            ws_subprotocol: None,
+            ws_max_message_size: defaults::DEFAULT_WS_MAX_MESSAGE_SIZE,
+            ws_max_frame_size: defaults::DEFAULT_WS_MAX_FRAME_SIZE,
+            shared_secret_retries: defaults::DEFAULT_SHARED_SECRET_RETRIES,
+            shared_secret_retry_backoff_ms: defaults::DEFAULT_SHARED_SECRET_RETRY_BACKOFF_MS,
+            flow_collector: None,
+            flow_max_tracked: defaults::DEFAULT_FLOW_MAX_TRACKED,
+            flow_idle_timeout_secs: defaults::DEFAULT_FLOW_IDLE_TIMEOUT_SECS,
+            flow_active_timeout_secs: defaults::DEFAULT_FLOW_ACTIVE_TIMEOUT_SECS,
+            traffic_export_addr: None,
+            traffic_export_format: TrafficExportFormat::Statsd,
+            traffic_export_interval_secs: defaults::DEFAULT_TRAFFIC_EXPORT_INTERVAL_SECS,
+            session_worker_threads: defaults::DEFAULT_SESSION_WORKER_THREADS,
+            quiet_hours: Vec::new(),
+            max_stateful_features: None,
+            capability_queries_per_minute: 6,
+            max_key_rotation_deferral_secs: defaults::DEFAULT_MAX_KEY_ROTATION_DEFERRAL_SECS,
+            acl_tier_policy: AclTierPolicy::Snapshot,
+            ip_release_grace_secs: defaults::DEFAULT_IP_RELEASE_GRACE_SECS,
+            max_lease_duration_secs: defaults::DEFAULT_MAX_LEASE_DURATION_SECS,
+            ip_reservations: Vec::new(),
+            ip_lease_file: None,
+            server_load_interval_secs: defaults::DEFAULT_SERVER_LOAD_INTERVAL_SECS,
+            duplicate_session_policy: DuplicateSessionPolicy::AllowMultiple,
+            warmup_secs: defaults::DEFAULT_WARMUP_SECS,
+            warmup_mode: WarmupMode::Throttle,
+            warmup_accepts_per_sec: defaults::DEFAULT_WARMUP_ACCEPTS_PER_SEC,
+            max_key_rotations_per_sec: defaults::DEFAULT_MAX_KEY_ROTATIONS_PER_SEC,
+            tunnel_allowed_protocols: Vec::new(),
+            source_ip_policy: SourceIpPolicy::Strict,
+            challenge_address_binding: ChallengeAddressBinding::Ip,
+            acl_deny_response: AclDenyResponse::Verbose,
+            session_record_file: None,
+            auth_audit_file: None,
+            max_handshake_data_packets: defaults::DEFAULT_MAX_HANDSHAKE_DATA_PACKETS,
+            tun_queue_depth: defaults::DEFAULT_TUN_QUEUE_DEPTH,
+            tun_queue_policy: TunQueuePolicy::DropTail,
+            send_queue_depth: defaults::DEFAULT_SEND_QUEUE_DEPTH,
+            require_bound_session_key: false,
+            trace_sample_rate: 0.0,
+            max_sessions: 0,
+            metrics_listen: None,
+            admin_listen: None,
+            key_rotation_jitter_percent: defaults::DEFAULT_KEY_ROTATION_JITTER_PERCENT,
+            idle_timeout_secs: 0,
+            max_missed_pongs: 0,
+            heartbeat_interval_secs: defaults::DEFAULT_HEARTBEAT_INTERVAL_SECS,
+            heartbeat_rtt_threshold_ms: 0,
+            max_heartbeat_interval_secs: defaults::DEFAULT_MAX_HEARTBEAT_INTERVAL_SECS,
+            max_sessions_per_key: 0,
+            max_connections: 0,
+            connection_queue_timeout_ms: 0,
+            challenge_batch_window_ms: 0,
+            fragment_size: 0,
+            tun_mtu: 1500,
+            mss_clamp: false,
+            alpn_protocols: Vec::new(),
+            client_ca_file: None,
+            require_client_cert: false,
+            auth_failure_limit: 10,
+            auth_failure_window_secs: 300,
+            server_key_rotation_overlap_secs: 300,
+            pow_difficulty: 0,
+            max_pow_difficulty: 0,
+            resumption_ttl_secs: 60,
             key_manager: None,
+            config_file: None,
         };
         
         // Test invalid security mode
@@ -0,0 +1,231 @@
+// src/crypto/batch.rs
+//! Batched Ed25519 signature verification.
+//!
+//! Under connection churn, challenge responses arrive close together.
+//! `SignatureBatcher` collects them for a short window and checks the whole
+//! batch with one `ed25519_dalek::verify_batch` call. If the batch fails,
+//! each signature is re-checked on its own, so every caller still gets an
+//! accurate result for its own signature.
+//!
+//! Results match `verify_strict`, as the unbatched path does: signatures
+//! whose key or R is small-order, has a torsion component or is not
+//! canonically encoded never join a batch, where they could pass a check
+//! that `verify_strict` fails, and are checked with `verify_strict` alone.
+
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use ed25519_dalek::{PublicKey, Signature};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{oneshot, Mutex, Notify};
+use tracing::debug;
+
+/// A signature waiting for the next batch
+struct PendingSignature {
+    public_key: PublicKey,
+    signature: Signature,
+    message: Vec<u8>,
+    result: oneshot::Sender<bool>,
+}
+
+/// Verifies Ed25519 signatures in batches
+pub struct SignatureBatcher {
+    /// How long the first signature of a batch waits for others
+    window: Duration,
+    /// Signatures that trigger verification before the window ends
+    max_batch: usize,
+    /// Signatures collected for the next batch
+    pending: Mutex<Vec<PendingSignature>>,
+    /// Wakes the batch task when the batch is full
+    batch_full: Notify,
+}
+
+impl std::fmt::Debug for SignatureBatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignatureBatcher")
+            .field("window", &self.window)
+            .field("max_batch", &self.max_batch)
+            .finish()
+    }
+}
+
+impl SignatureBatcher {
+    /// Batch signatures arriving within `window`, up to `max_batch` at a time
+    pub fn new(window: Duration, max_batch: usize) -> Self {
+        Self {
+            window,
+            max_batch: max_batch.max(1),
+            pending: Mutex::new(Vec::new()),
+            batch_full: Notify::new(),
+        }
+    }
+
+    /// Verify `signature` over `message` by `public_key` (raw 32- and 64-byte
+    /// encodings), batched with other concurrent calls.
+    pub async fn verify(self: &Arc<Self>, public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        // Malformed keys and signatures can never verify; keep them out of the batch
+        let (Ok(public_key), Ok(signature)) = (PublicKey::from_bytes(public_key), Signature::from_bytes(signature)) else {
+            return false;
+        };
+        if !batch_safe(&public_key, &signature) {
+            return public_key.verify_strict(message, &signature).is_ok();
+        }
+
+        let (result, receiver) = oneshot::channel();
+        let first = {
+            let mut pending = self.pending.lock().await;
+            pending.push(PendingSignature { public_key, signature, message: message.to_vec(), result });
+            if pending.len() >= self.max_batch {
+                self.batch_full.notify_one();
+            }
+            pending.len() == 1
+        };
+
+        // The first signature of a batch starts a task that waits for the
+        // window, then verifies everything collected so far. It runs apart from
+        // the caller so a cancelled caller cannot strand the rest of the batch.
+        if first {
+            let batcher = self.clone();
+            tokio::spawn(async move {
+                tokio::select! {
+                    _ = tokio::time::sleep(batcher.window) => {}
+                    _ = batcher.batch_full.notified() => {}
+                }
+                let batch = std::mem::take(&mut *batcher.pending.lock().await);
+                let _ = tokio::task::spawn_blocking(move || Self::verify_pending(batch)).await;
+            });
+        }
+
+        receiver.await.unwrap_or(false)
+    }
+
+    /// Verify a batch, falling back to individual checks to find bad signatures
+    fn verify_pending(batch: Vec<PendingSignature>) {
+        if batch.is_empty() {
+            return;
+        }
+
+        let messages: Vec<&[u8]> = batch.iter().map(|p| p.message.as_slice()).collect();
+        let signatures: Vec<Signature> = batch.iter().map(|p| p.signature).collect();
+        let public_keys: Vec<PublicKey> = batch.iter().map(|p| p.public_key).collect();
+
+        if ed25519_dalek::verify_batch(&messages, &signatures, &public_keys).is_ok() {
+            for pending in batch {
+                let _ = pending.result.send(true);
+            }
+            return;
+        }
+
+        debug!("Signature batch of {} failed; verifying individually", batch.len());
+        for pending in batch {
+            let valid = pending.public_key.verify_strict(&pending.message, &pending.signature).is_ok();
+            let _ = pending.result.send(valid);
+        }
+    }
+}
+
+/// Whether a batch check of this signature agrees with `verify_strict`: the
+/// key and R must be canonically encoded points of prime order
+fn batch_safe(public_key: &PublicKey, signature: &Signature) -> bool {
+    let signature = signature.to_bytes();
+    let mut r = [0u8; 32];
+    r.copy_from_slice(&signature[..32]);
+    [public_key.to_bytes(), r].iter().all(|encoded| {
+        CompressedEdwardsY(*encoded).decompress().map_or(false, |point| {
+            point.compress().as_bytes() == encoded && !point.is_small_order() && point.is_torsion_free()
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::{Keypair, Signer};
+    use std::time::Instant;
+
+    fn signed(keypair: &Keypair, message: &[u8]) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let signature = keypair.sign_message(message);
+        (keypair.pubkey().to_bytes().to_vec(), message.to_vec(), signature.as_ref().to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_batch_with_one_bad_signature() {
+        let batcher = Arc::new(SignatureBatcher::new(Duration::from_millis(50), 64));
+        let mut inputs: Vec<_> = (0..8u8)
+            .map(|i| signed(&Keypair::new(), &[i; 32]))
+            .collect();
+        // Signature 3 covers a different message
+        inputs[3].1 = vec![0xff; 32];
+
+        let handles: Vec<_> = inputs.into_iter()
+            .map(|(key, message, signature)| {
+                let batcher = batcher.clone();
+                tokio::spawn(async move { batcher.verify(&key, &message, &signature).await })
+            })
+            .collect();
+        let mut results = Vec::new();
+        for handle in handles {
+            results.push(handle.await.unwrap());
+        }
+
+        let expected: Vec<bool> = (0..8).map(|i| i != 3).collect();
+        assert_eq!(results, expected);
+
+        // Malformed input fails without joining a batch
+        assert!(!batcher.verify(&[0u8; 5], b"message", &[0u8; 64]).await);
+
+        // The identity key with R = identity and s = 0 satisfies the plain
+        // verification equation for every message, but not the strict one
+        let mut identity = [0u8; 32];
+        identity[0] = 1;
+        let mut weak_signature = [0u8; 64];
+        weak_signature[..32].copy_from_slice(&identity);
+        assert!(!batcher.verify(&identity, b"message", &weak_signature).await);
+        let (key, message, signature) = signed(&Keypair::new(), b"message");
+        let (weak, valid) = tokio::join!(
+            batcher.verify(&identity, b"other", &weak_signature),
+            batcher.verify(&key, &message, &signature),
+        );
+        assert_eq!((weak, valid), (false, true));
+
+        // A full batch is verified without waiting for the window
+        let batcher = Arc::new(SignatureBatcher::new(Duration::from_secs(60), 1));
+        let (key, message, signature) = signed(&Keypair::new(), b"message");
+        let verified = tokio::time::timeout(Duration::from_secs(5), batcher.verify(&key, &message, &signature)).await;
+        assert_eq!(verified.ok(), Some(true));
+    }
+
+    #[tokio::test]
+    #[ignore] // Benchmark; run with --ignored --nocapture
+    async fn bench_batch_verification() {
+        const SIGNATURES: usize = 1_000;
+        let inputs: Vec<_> = (0..SIGNATURES)
+            .map(|i| signed(&Keypair::new(), &(i as u64).to_le_bytes()))
+            .collect();
+
+        let start = Instant::now();
+        for (key, message, signature) in &inputs {
+            let key = PublicKey::from_bytes(key).unwrap();
+            assert!(key.verify_strict(message, &Signature::from_bytes(signature).unwrap()).is_ok());
+        }
+        let single = start.elapsed();
+
+        let batcher = Arc::new(SignatureBatcher::new(Duration::from_millis(5), 64));
+        let start = Instant::now();
+        let handles: Vec<_> = inputs.into_iter()
+            .map(|(key, message, signature)| {
+                let batcher = batcher.clone();
+                tokio::spawn(async move { batcher.verify(&key, &message, &signature).await })
+            })
+            .collect();
+        for handle in handles {
+            assert!(handle.await.unwrap());
+        }
+        let batched = start.elapsed();
+
+        println!(
+            "signature verification: single {:?}/sig, batched {:?}/sig",
+            single / SIGNATURES as u32,
+            batched / SIGNATURES as u32
+        );
+    }
+}
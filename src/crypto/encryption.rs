@@ -9,7 +9,7 @@ use aes::Aes256;
 use cbc::{Decryptor, Encryptor};
 use cbc::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
 use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
-use chacha20poly1305::aead::{Aead, NewAead};
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
 use hmac::{Hmac, Mac}; // No NewMac, as it's accessed through the Mac trait
 use rand::{Rng, RngCore};
 use sha2::Sha256;
@@ -24,7 +24,7 @@ use crate::crypto::flexible_encryption::{
 
 // Add AES-GCM imports
 use aes_gcm::{
-    aead::{Aead as AesGcmAead, KeyInit},
+    aead::{Aead as AesGcmAead, KeyInit, Payload as AesGcmPayload},
     Aes256Gcm, Nonce as AesGcmNonce
 };
 use generic_array::GenericArray;
@@ -67,6 +67,11 @@ pub fn generate_challenge(size: usize) -> Vec<u8> {
 
 /// Encrypt data using ChaCha20-Poly1305 AEAD with authentication
 pub fn encrypt_chacha20(data: &[u8], key: &[u8], nonce_bytes: Option<&[u8]>) -> Result<(Vec<u8>, Vec<u8>), EncryptionError> {
+    encrypt_chacha20_aad(data, key, nonce_bytes, &[])
+}
+
+/// Encrypt data using ChaCha20-Poly1305, authenticating `aad` alongside it
+pub fn encrypt_chacha20_aad(data: &[u8], key: &[u8], nonce_bytes: Option<&[u8]>, aad: &[u8]) -> Result<(Vec<u8>, Vec<u8>), EncryptionError> {
     if key.len() != 32 {
         return Err(EncryptionError::InvalidKeyLength(key.len()));
     }
@@ -94,7 +99,7 @@ pub fn encrypt_chacha20(data: &[u8], key: &[u8], nonce_bytes: Option<&[u8]>) ->
 
     // Encrypt the data
     let nonce = Nonce::from_slice(&nonce_val);
-    let ciphertext = cipher.encrypt(nonce, data)
+    let ciphertext = cipher.encrypt(nonce, Payload { msg: data, aad })
         .map_err(|e| EncryptionError::EncryptionFailed(format!("ChaCha20-Poly1305 encryption failed: {}", e)))?;
 
     Ok((ciphertext, nonce_val.to_vec()))
@@ -146,9 +151,7 @@ pub fn encrypt_aes_gcm(plaintext: &[u8], key: &[u8], aad: Option<&[u8]>) -> Resu
         Some(aad_data) => {
             debug!("Encrypting with AAD, AAD length={}", aad_data.len());
             
-            // Version 1: Attempt to use AAD properly (adjust based on actual library implementation)
-            cipher.encrypt(nonce, aad_data)
-                .and_then(|_| cipher.encrypt(nonce, plaintext))
+            cipher.encrypt(nonce, AesGcmPayload { msg: plaintext, aad: aad_data })
                 .map_err(|e| {
                     error!("AES-GCM encryption failed with AAD: {}", e);
                     EncryptionError::EncryptionFailed(format!("AES-GCM encryption failed with AAD: {}", e))
@@ -171,8 +174,30 @@ pub fn encrypt_aes_gcm(plaintext: &[u8], key: &[u8], aad: Option<&[u8]>) -> Resu
     Ok((ciphertext, nonce_bytes.to_vec()))
 }
 
+/// Encrypt data using AES-256-GCM under a caller-supplied 12-byte nonce.
+/// The caller is responsible for never reusing a nonce with the same key.
+pub fn encrypt_aes_gcm_with_nonce(plaintext: &[u8], key: &[u8], nonce: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+    if key.len() != 32 {
+        return Err(EncryptionError::InvalidKeyLength(key.len()));
+    }
+    if nonce.len() != 12 {
+        return Err(EncryptionError::InvalidFormat(format!(
+            "Invalid nonce length: {} (expected 12)",
+            nonce.len()
+        )));
+    }
+
+    let cipher = Aes256Gcm::new(GenericArray::from_slice(key));
+    cipher.encrypt(AesGcmNonce::from_slice(nonce), plaintext)
+        .map_err(|e| EncryptionError::EncryptionFailed(format!("AES-GCM encryption failed: {}", e)))
+}
 
 pub fn decrypt_chacha20(ciphertext: &[u8], key: &[u8], nonce: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+    decrypt_chacha20_aad(ciphertext, key, nonce, &[])
+}
+
+/// Decrypt ChaCha20-Poly1305 data, verifying the `aad` it was sealed with
+pub fn decrypt_chacha20_aad(ciphertext: &[u8], key: &[u8], nonce: &[u8], aad: &[u8]) -> Result<Vec<u8>, EncryptionError> {
     if key.len() != 32 {
         error!("ChaCha20 key length invalid: {} (expected 32)", key.len());
         return Err(EncryptionError::InvalidKeyLength(key.len()));
@@ -208,7 +233,7 @@ pub fn decrypt_chacha20(ciphertext: &[u8], key: &[u8], nonce: &[u8]) -> Result<V
     debug!("ChaCha20 cipher created, attempting decryption");
 
     // Decrypt and verify the data
-    let plaintext = match cipher.decrypt(nonce_aead, ciphertext) {
+    let plaintext = match cipher.decrypt(nonce_aead, Payload { msg: ciphertext, aad }) {
         Ok(plaintext) => {
             debug!("ChaCha20 decryption successful: {} bytes", plaintext.len());
             if !plaintext.is_empty() {
@@ -282,9 +307,7 @@ pub fn decrypt_aes_gcm(ciphertext: &[u8], key: &[u8], nonce: &[u8], aad: Option<
         Some(aad_data) => {
             debug!("Decrypting with AAD, AAD length={}", aad_data.len());
             
-            // Version 1: Attempt to verify AAD (adjust based on actual library implementation)
-            cipher.decrypt(nonce_array, aad_data)
-                .and_then(|_| cipher.decrypt(nonce_array, ciphertext))
+            cipher.decrypt(nonce_array, AesGcmPayload { msg: ciphertext, aad: aad_data })
                 .map_err(|e| {
                     error!("AES-GCM decryption failed with AAD: {}", e);
                     EncryptionError::AuthenticationFailed
@@ -418,6 +441,84 @@ pub fn derive_session_key(shared_secret: &[u8], salt: &[u8]) -> Result<Vec<u8>,
     Ok(session_key)
 }
 
+/// Build the HMAC-SHA256 over an IP renewal request, keyed with the session key.
+/// Input: `"AERONYX-IP-RENEWAL" || session_id || 0x00 || ip_address`, followed by
+/// `0x00 || sequence (u64 big-endian)` when `control-sequence` is negotiated.
+fn renewal_hmac(session_key: &[u8], session_id: &str, ip_address: &str, sequence: Option<u64>) -> Result<HmacSha256, EncryptionError> {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(session_key)
+        .map_err(|_| EncryptionError::InvalidKeyLength(session_key.len()))?;
+    mac.update(b"AERONYX-IP-RENEWAL");
+    mac.update(session_id.as_bytes());
+    mac.update(&[0]);
+    mac.update(ip_address.as_bytes());
+    if let Some(sequence) = sequence {
+        mac.update(&[0]);
+        mac.update(&sequence.to_be_bytes());
+    }
+    Ok(mac)
+}
+
+/// Compute the MAC proving an IP renewal request comes from the session key holder
+pub fn sign_ip_renewal(session_key: &[u8], session_id: &str, ip_address: &str, sequence: Option<u64>) -> Result<Vec<u8>, EncryptionError> {
+    Ok(renewal_hmac(session_key, session_id, ip_address, sequence)?.finalize().into_bytes().to_vec())
+}
+
+/// Verify an IP renewal MAC (constant time)
+pub fn verify_ip_renewal(session_key: &[u8], session_id: &str, ip_address: &str, sequence: Option<u64>, mac: &[u8]) -> bool {
+    renewal_hmac(session_key, session_id, ip_address, sequence)
+        .map(|hmac| hmac.verify_slice(mac).is_ok())
+        .unwrap_or(false)
+}
+
+/// Build the HMAC-SHA256 over a client Disconnect, keyed with the session key.
+/// Input: `"AERONYX-DISCONNECT" || session_id || 0x00 || reason (u16 big-endian) || sequence (u64 big-endian)`.
+fn disconnect_hmac(session_key: &[u8], session_id: &str, reason: u16, sequence: u64) -> Result<HmacSha256, EncryptionError> {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(session_key)
+        .map_err(|_| EncryptionError::InvalidKeyLength(session_key.len()))?;
+    mac.update(b"AERONYX-DISCONNECT");
+    mac.update(session_id.as_bytes());
+    mac.update(&[0]);
+    mac.update(&reason.to_be_bytes());
+    mac.update(&sequence.to_be_bytes());
+    Ok(mac)
+}
+
+/// Compute the MAC proving a Disconnect and its sequence come from the session key holder
+pub fn sign_disconnect(session_key: &[u8], session_id: &str, reason: u16, sequence: u64) -> Result<Vec<u8>, EncryptionError> {
+    Ok(disconnect_hmac(session_key, session_id, reason, sequence)?.finalize().into_bytes().to_vec())
+}
+
+/// Verify a Disconnect MAC (constant time)
+pub fn verify_disconnect(session_key: &[u8], session_id: &str, reason: u16, sequence: u64, mac: &[u8]) -> bool {
+    disconnect_hmac(session_key, session_id, reason, sequence)
+        .map(|hmac| hmac.verify_slice(mac).is_ok())
+        .unwrap_or(false)
+}
+
+/// Build the HMAC-SHA256 over a session resumption request, keyed with the last session key.
+/// Input: `"AERONYX-RESUME" || token || 0x00 || public_key`.
+fn resumption_hmac(session_key: &[u8], token: &str, public_key: &str) -> Result<HmacSha256, EncryptionError> {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(session_key)
+        .map_err(|_| EncryptionError::InvalidKeyLength(session_key.len()))?;
+    mac.update(b"AERONYX-RESUME");
+    mac.update(token.as_bytes());
+    mac.update(&[0]);
+    mac.update(public_key.as_bytes());
+    Ok(mac)
+}
+
+/// Compute the proof that a client presenting a resumption token held the session key
+pub fn sign_session_resumption(session_key: &[u8], token: &str, public_key: &str) -> Result<Vec<u8>, EncryptionError> {
+    Ok(resumption_hmac(session_key, token, public_key)?.finalize().into_bytes().to_vec())
+}
+
+/// Verify a session resumption proof (constant time)
+pub fn verify_session_resumption(session_key: &[u8], token: &str, public_key: &str, proof: &[u8]) -> bool {
+    resumption_hmac(session_key, token, public_key)
+        .map(|hmac| hmac.verify_slice(proof).is_ok())
+        .unwrap_or(false)
+}
+
 /// Add padding to a packet
 pub fn add_padding(packet: &[u8], min_padding: usize, max_padding: usize) -> Vec<u8> {
     let mut rng = rand::thread_rng();
@@ -465,21 +566,47 @@ pub fn remove_padding(packet: &[u8]) -> Result<Vec<u8>, EncryptionError> {
     Ok(data.to_vec())
 }
 
-/// Encrypt a session key for transmission
+/// Associated data binding an `IpAssign` session key to its session.
+///
+/// When the `bound-session-key` feature is negotiated, `encrypted_session_key`
+/// is sealed with this as AEAD associated data, so the ciphertext only opens
+/// for the session and client it was issued to. Layout (bytes, no length
+/// prefixes):
+///
+/// ```text
+/// "AERONYX-SESSION-KEY-AAD" || 0x00 || session_id (UTF-8) || 0x00 || client public key (base58, UTF-8)
+/// ```
+///
+/// Clients rebuild it from the `session_id` in the `IpAssign` and their own
+/// public key, and pass it as associated data when decrypting the key with
+/// the negotiated algorithm.
+pub fn session_key_aad(session_id: &str, public_key: &str) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(25 + session_id.len() + public_key.len());
+    aad.extend_from_slice(b"AERONYX-SESSION-KEY-AAD");
+    aad.push(0);
+    aad.extend_from_slice(session_id.as_bytes());
+    aad.push(0);
+    aad.extend_from_slice(public_key.as_bytes());
+    aad
+}
+
+/// Encrypt a session key for transmission, optionally bound to `aad` (see `session_key_aad`)
 pub fn encrypt_session_key(
     session_key: &[u8],
     shared_secret: &[u8],
+    aad: Option<&[u8]>,
 ) -> Result<(Vec<u8>, Vec<u8>), EncryptionError> {
-    encrypt_chacha20(session_key, shared_secret, None)
+    encrypt_chacha20_aad(session_key, shared_secret, None, aad.unwrap_or_default())
 }
 
-/// Decrypt a session key received from peer
+/// Decrypt a session key received from peer, verifying the `aad` it was bound to
 pub fn decrypt_session_key(
     encrypted_key: &[u8],
     nonce: &[u8],
     shared_secret: &[u8],
+    aad: Option<&[u8]>,
 ) -> Result<Vec<u8>, EncryptionError> {
-    decrypt_chacha20(encrypted_key, shared_secret, nonce)
+    decrypt_chacha20_aad(encrypted_key, shared_secret, nonce, aad.unwrap_or_default())
 }
 
 /// Encrypt a session key for transmission using a specified algorithm preference.
@@ -488,6 +615,7 @@ pub fn decrypt_session_key(
 /// - `session_key`: The session key to encrypt
 /// - `shared_secret`: The shared secret used as the encryption key
 /// - `preferred_algorithm`: The encryption algorithm to use
+/// - `aad`: Associated data binding the key to its session (see `session_key_aad`), if negotiated
 ///
 /// # Returns
 /// - An `EncryptedPacket` containing the encrypted data, nonce, and algorithm used
@@ -495,10 +623,8 @@ pub fn encrypt_session_key_flexible(
     session_key: &[u8],
     shared_secret: &[u8], // This shared_secret acts as the 'key' for this encryption step
     preferred_algorithm: EncryptionAlgorithm,
+    aad: Option<&[u8]>,
 ) -> Result<EncryptedPacket, FlexibleEncryptionError> {
-    // We don't use AAD for session key encryption
-    let aad = None;
-    
     // Use the flexible encryption function directly
     // Note: We use the shared_secret as the key for encrypting the session_key
     encrypt_flexible(session_key, shared_secret, preferred_algorithm, aad)
@@ -509,6 +635,7 @@ pub fn encrypt_session_key_flexible(
 /// # Parameters
 /// - `encrypted_key_packet`: The `EncryptedPacket` containing encrypted session key data
 /// - `shared_secret`: The shared secret used as the decryption key
+/// - `aad`: Associated data the key was bound to, if any (must match exactly)
 ///
 /// # Returns
 /// - The decrypted session key or an error
@@ -518,10 +645,8 @@ pub fn encrypt_session_key_flexible(
 pub fn decrypt_session_key_flexible(
     encrypted_key_packet: &EncryptedPacket, // Expect EncryptedPacket struct
     shared_secret: &[u8],    // Shared secret acts as the key
+    aad: Option<&[u8]>,
 ) -> Result<Vec<u8>, FlexibleEncryptionError> {
-    // We didn't use AAD during encryption
-    let aad = None;
-    
     // Trust the algorithm specified in the packet, disable fallback for this specific case usually
     decrypt_flexible(
         &encrypted_key_packet.data,
@@ -780,7 +905,8 @@ mod tests {
         let encrypted_chacha = encrypt_session_key_flexible(
             &session_key,
             &shared_secret,
-            EncryptionAlgorithm::ChaCha20Poly1305
+            EncryptionAlgorithm::ChaCha20Poly1305,
+            None,
         ).unwrap();
         
         // Verify algorithm was set correctly
@@ -789,7 +915,8 @@ mod tests {
         // Decrypt and verify
         let decrypted_chacha = decrypt_session_key_flexible(
             &encrypted_chacha,
-            &shared_secret
+            &shared_secret,
+            None,
         ).unwrap();
         
         assert_eq!(session_key.to_vec(), decrypted_chacha);
@@ -798,7 +925,8 @@ mod tests {
         let encrypted_aes = encrypt_session_key_flexible(
             &session_key,
             &shared_secret,
-            EncryptionAlgorithm::AesGcm
+            EncryptionAlgorithm::AesGcm,
+            None,
         ).unwrap();
         
         // Verify algorithm was set correctly
@@ -807,9 +935,34 @@ mod tests {
         // Decrypt and verify
         let decrypted_aes = decrypt_session_key_flexible(
             &encrypted_aes,
-            &shared_secret
+            &shared_secret,
+            None,
         ).unwrap();
         
         assert_eq!(session_key.to_vec(), decrypted_aes);
     }
+    
+    #[test]
+    fn test_session_key_bound_to_session() {
+        use crate::crypto::flexible_encryption::EncryptionAlgorithm;
+        
+        let session_key = [10u8; 32];
+        let shared_secret = [11u8; 32];
+        let aad = session_key_aad("session_a", "client_a");
+        
+        for algorithm in [EncryptionAlgorithm::ChaCha20Poly1305, EncryptionAlgorithm::Aes256Gcm] {
+            let encrypted = encrypt_session_key_flexible(&session_key, &shared_secret, algorithm, Some(&aad)).unwrap();
+            assert_eq!(decrypt_session_key_flexible(&encrypted, &shared_secret, Some(&aad)).unwrap(), session_key.to_vec());
+            
+            // Spliced into another session or client, or opened without the binding
+            for context in [session_key_aad("session_b", "client_a"), session_key_aad("session_a", "client_b")] {
+                assert!(decrypt_session_key_flexible(&encrypted, &shared_secret, Some(&context)).is_err());
+            }
+            assert!(decrypt_session_key_flexible(&encrypted, &shared_secret, None).is_err());
+        }
+        
+        let (encrypted, nonce) = encrypt_session_key(&session_key, &shared_secret, Some(&aad)).unwrap();
+        assert_eq!(decrypt_session_key(&encrypted, &nonce, &shared_secret, Some(&aad)).unwrap(), session_key.to_vec());
+        assert!(decrypt_session_key(&encrypted, &nonce, &shared_secret, Some(&session_key_aad("session_b", "client_a"))).is_err());
+    }
 }
@@ -4,7 +4,7 @@
 //! This module provides a unified interface for encrypting and decrypting data
 //! using different algorithms (ChaCha20-Poly1305 or AES-GCM) based on client preference.
 
-use crate::crypto::encryption::{encrypt_chacha20, decrypt_chacha20, encrypt_aes_gcm, decrypt_aes_gcm};
+use crate::crypto::encryption::{encrypt_chacha20, encrypt_chacha20_aad, decrypt_chacha20, decrypt_chacha20_aad, encrypt_aes_gcm, encrypt_aes_gcm_with_nonce, decrypt_aes_gcm};
 use thiserror::Error;
 use tracing::{debug, info, warn, error};
 /// Encryption algorithms supported by the system
@@ -78,7 +78,7 @@ pub fn encrypt_flexible(
 ) -> Result<EncryptedPacket, FlexibleEncryptionError> {
     match algorithm {
         EncryptionAlgorithm::ChaCha20Poly1305 => {
-            let (encrypted, nonce) = encrypt_chacha20(data, key, None)
+            let (encrypted, nonce) = encrypt_chacha20_aad(data, key, None, aad.unwrap_or_default())
                 .map_err(|e| FlexibleEncryptionError::EncryptionFailed(e.to_string()))?;
                 
             Ok(EncryptedPacket {
@@ -100,6 +100,20 @@ pub fn encrypt_flexible(
     }
 }
 
+/// Encrypt data under a caller-supplied nonce (see `crypto::nonce`)
+pub fn encrypt_flexible_with_nonce(
+    data: &[u8],
+    key: &[u8],
+    algorithm: EncryptionAlgorithm,
+    nonce: &[u8],
+) -> Result<Vec<u8>, FlexibleEncryptionError> {
+    let result = match algorithm {
+        EncryptionAlgorithm::ChaCha20Poly1305 => encrypt_chacha20(data, key, Some(nonce)).map(|(encrypted, _)| encrypted),
+        EncryptionAlgorithm::Aes256Gcm => encrypt_aes_gcm_with_nonce(data, key, nonce),
+    };
+    result.map_err(|e| FlexibleEncryptionError::EncryptionFailed(e.to_string()))
+}
+
 pub fn decrypt_flexible(
     encrypted: &[u8],
     nonce: &[u8],
@@ -111,7 +125,7 @@ pub fn decrypt_flexible(
     // First attempt with specified algorithm
     let primary_result = match algorithm {
         EncryptionAlgorithm::ChaCha20Poly1305 => {
-            decrypt_chacha20(encrypted, key, nonce)
+            decrypt_chacha20_aad(encrypted, key, nonce, aad.unwrap_or_default())
                 .map_err(|e| FlexibleEncryptionError::DecryptionFailed(e.to_string()))
         },
         EncryptionAlgorithm::Aes256Gcm => {
@@ -134,8 +148,11 @@ pub fn decrypt_flexible(
         },
         EncryptionAlgorithm::Aes256Gcm => {
             // Fallback to ChaCha20-Poly1305
-            decrypt_chacha20(encrypted, key, nonce)
-                .map_err(|e| FlexibleEncryptionError::DecryptionFailed(format!("Both algorithms failed: {}", e)))
+            let result = match aad {
+                Some(aad) => decrypt_chacha20_aad(encrypted, key, nonce, aad),
+                None => decrypt_chacha20(encrypted, key, nonce),
+            };
+            result.map_err(|e| FlexibleEncryptionError::DecryptionFailed(format!("Both algorithms failed: {}", e)))
         }
     }
 }
@@ -26,6 +26,8 @@ use tokio::sync::Mutex;
 use tracing::{info, warn};
 use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret as X25519SecretKey};
 
+use crate::config::defaults::DEFAULT_SERVER_KEY_ROTATION_OVERLAP_SECS;
+
 /// Error type for key-related operations
 #[derive(Debug, Error)]
 pub enum KeyError {
@@ -46,6 +48,57 @@ pub enum KeyError {
 
     #[error("Signature verification failed")]
     SignatureVerification,
+
+    #[error("Previous server key {0} is still in its rotation overlap")]
+    RotationPending(String),
+}
+
+impl KeyError {
+    /// Whether the failure may succeed on retry (resource hiccup rather than bad key data)
+    pub fn is_transient(&self) -> bool {
+        match self {
+            KeyError::Io(e) => matches!(
+                e.kind(),
+                io::ErrorKind::Interrupted
+                    | io::ErrorKind::WouldBlock
+                    | io::ErrorKind::TimedOut
+                    | io::ErrorKind::OutOfMemory
+            ),
+            KeyError::Format(_)
+            | KeyError::NotFound(_)
+            | KeyError::InvalidData(_)
+            | KeyError::Crypto(_)
+            | KeyError::SignatureVerification
+            | KeyError::RotationPending(_) => false,
+        }
+    }
+}
+
+/// Retry policy for transient shared secret derivation failures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SecretRetryPolicy {
+    /// Retries after the first attempt (0 = fail immediately)
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each subsequent retry
+    pub base_backoff: Duration,
+}
+
+impl Default for SecretRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            base_backoff: Duration::from_millis(0),
+        }
+    }
+}
+
+/// Outcome of a shared secret derivation with retries
+#[derive(Debug)]
+pub struct SecretDerivation {
+    /// Final result
+    pub result: Result<Vec<u8>, KeyError>,
+    /// Attempts that failed transiently (including a final transient failure)
+    pub transient_failures: u32,
 }
 
 /// Cached secret key for performance optimization
@@ -161,15 +214,28 @@ impl SecretKeyCache {
     }
 }
 
+/// A replaced server keypair, still accepted until its overlap window ends
+#[derive(Debug)]
+struct RetiredKeypair {
+    keypair: Keypair,
+    retired_at: Instant,
+}
+
 /// Key manager for the server
 #[derive(Debug, Clone)]
 pub struct KeyManager {
     /// Server key pair
     keypair: Arc<Mutex<Keypair>>,
+    /// Previous key pair, kept for clients that were sent its public key
+    retired: Arc<Mutex<Option<RetiredKeypair>>>,
+    /// How long the previous key pair stays accepted after a rotation
+    rotation_overlap: Arc<Mutex<Duration>>,
     /// Path to the key file
     key_path: PathBuf,
     /// Secret key cache
     secret_cache: SecretKeyCache,
+    /// Retry policy for transient derivation failures
+    secret_retry: Arc<Mutex<SecretRetryPolicy>>,
 }
 
 impl KeyManager {
@@ -187,8 +253,11 @@ impl KeyManager {
 
         Ok(Self {
             keypair: Arc::new(Mutex::new(keypair)),
+            retired: Arc::new(Mutex::new(None)),
+            rotation_overlap: Arc::new(Mutex::new(Duration::from_secs(DEFAULT_SERVER_KEY_ROTATION_OVERLAP_SECS))),
             key_path: path,
             secret_cache,
+            secret_retry: Arc::new(Mutex::new(SecretRetryPolicy::default())),
         })
     }
 
@@ -302,32 +371,135 @@ impl KeyManager {
         signature.verify(pubkey.as_ref(), message)
     }
 
+    /// Public keys clients may currently be using: the current key, then the
+    /// previous one while its overlap window lasts
+    pub async fn accepted_public_keys(&self) -> Vec<Pubkey> {
+        let mut keys = vec![self.public_key().await];
+        let overlap = *self.rotation_overlap.lock().await;
+        if let Some(retired) = self.retired.lock().await.as_ref().filter(|r| r.retired_at.elapsed() <= overlap) {
+            keys.push(retired.keypair.pubkey());
+        }
+        keys
+    }
+
     /// Get or compute a shared secret with a client
     pub async fn get_shared_secret(&self, client_pubkey: &Pubkey) -> Result<Vec<u8>, KeyError> {
-        let keypair = self.keypair.lock().await;
-        self.secret_cache.get_or_compute(&keypair, client_pubkey).await
+        self.get_shared_secret_for(None, client_pubkey).await
     }
 
-    /// Rotate the server keypair
-    pub async fn rotate_keypair(&self) -> Result<(), KeyError> {
-        let new_keypair = Keypair::new();
-        let new_pubkey = new_keypair.pubkey(); // Get pubkey before moving
+    /// Get a shared secret with a client that was sent `server_key` (None = the current key).
+    ///
+    /// The current key is tried first, then the previous key while its
+    /// overlap window lasts, so a rotation does not break clients that
+    /// received the old key in their challenge.
+    pub async fn get_shared_secret_for(&self, server_key: Option<&Pubkey>, client_pubkey: &Pubkey) -> Result<Vec<u8>, KeyError> {
+        let server_key = {
+            let keypair = self.keypair.lock().await;
+            match server_key {
+                Some(key) if *key != keypair.pubkey() => *key,
+                _ => return self.secret_cache.get_or_compute(&keypair, client_pubkey).await,
+            }
+        };
 
-        // Save the NEW keypair to the file first
-        Self::save_keypair(&new_keypair, &self.key_path)?;
+        let overlap = *self.rotation_overlap.lock().await;
+        match self.retired.lock().await.as_ref() {
+            Some(retired) if retired.keypair.pubkey() == server_key && retired.retired_at.elapsed() <= overlap => {
+                generate_shared_secret(&retired.keypair, client_pubkey)
+            }
+            _ => Err(KeyError::NotFound(format!("Server key {} is no longer accepted", server_key))),
+        }
+    }
 
-        // Update the keypair in memory
-        {
-            let mut keypair_guard = self.keypair.lock().await;
-            *keypair_guard = new_keypair; // Move the new keypair into the Mutex guard
-        } // Mutex guard dropped here
+    /// Set how long a replaced server key stays accepted after a rotation
+    pub async fn set_rotation_overlap(&self, overlap: Duration) {
+        *self.rotation_overlap.lock().await = overlap;
+    }
 
+    /// Set the retry policy for transient shared secret derivation failures
+    pub async fn set_secret_retry_policy(&self, policy: SecretRetryPolicy) {
+        *self.secret_retry.lock().await = policy;
+    }
 
-        // Clear the secret cache as old secrets are now invalid
+    /// Get a shared secret, retrying transient failures with exponential backoff.
+    ///
+    /// Deterministic failures such as a malformed client key fail immediately.
+    pub async fn get_shared_secret_with_retry(&self, server_key: Option<&Pubkey>, client_pubkey: &Pubkey) -> SecretDerivation {
+        let policy = *self.secret_retry.lock().await;
+        let mut transient_failures = 0;
+        let mut backoff = policy.base_backoff;
+
+        loop {
+            let result = self.get_shared_secret_for(server_key, client_pubkey).await;
+            match &result {
+                Err(e) if e.is_transient() => {
+                    transient_failures += 1;
+                    if transient_failures > policy.max_retries {
+                        return SecretDerivation { result, transient_failures };
+                    }
+                    warn!("Transient shared secret derivation failure for {} (attempt {}): {}",
+                          client_pubkey, transient_failures, e);
+                    tokio::time::sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                }
+                _ => return SecretDerivation { result, transient_failures },
+            }
+        }
+    }
+
+    /// Rotate the server keypair to a freshly generated one and return its public key
+    pub async fn rotate_keypair(&self) -> Result<Pubkey, KeyError> {
+        self.install_keypair(Keypair::new(), true).await
+    }
+
+    /// Rotate to the keypair stored at `path` and return its public key.
+    /// The previous key stays accepted for the rotation overlap.
+    pub async fn load_new_keypair(&self, path: impl AsRef<Path>) -> Result<Pubkey, KeyError> {
+        let new_keypair = Self::load_keypair(path.as_ref())?;
+        self.install_keypair(new_keypair, true).await
+    }
+
+    /// Re-read the key file and rotate to the keypair in it, if an operator
+    /// replaced it, returning the public key now in use
+    pub async fn reload_keypair(&self) -> Result<Pubkey, KeyError> {
+        let new_keypair = Self::load_keypair(&self.key_path)?;
+        self.install_keypair(new_keypair, false).await
+    }
+
+    /// Make `new_keypair` the server key, saving it to the key file if
+    /// `persist`, and retire the current one. Refused while the previously
+    /// retired key is still in its overlap, since only one retired key is
+    /// kept and replacing it would strand the clients that were sent it.
+    async fn install_keypair(&self, new_keypair: Keypair, persist: bool) -> Result<Pubkey, KeyError> {
+        let new_pubkey = new_keypair.pubkey();
+
+        // Lock order: keypair, then retired
+        let mut keypair = self.keypair.lock().await;
+        if keypair.pubkey() == new_pubkey {
+            return Ok(new_pubkey);
+        }
+        let overlap = *self.rotation_overlap.lock().await;
+        let mut retired = self.retired.lock().await;
+        if let Some(pending) = retired.as_ref().filter(|r| r.retired_at.elapsed() <= overlap) {
+            return Err(KeyError::RotationPending(pending.keypair.pubkey().to_string()));
+        }
+
+        // Save the NEW keypair to the file first
+        if persist {
+            Self::save_keypair(&new_keypair, &self.key_path)?;
+        }
+
+        let old_keypair = std::mem::replace(&mut *keypair, new_keypair);
+        let old_pubkey = old_keypair.pubkey();
+        *retired = Some(RetiredKeypair { keypair: old_keypair, retired_at: Instant::now() });
+        drop(retired);
+        drop(keypair);
+
+        // Cached secrets were derived from the old key
         self.secret_cache.clear().await;
 
-        info!("Server keypair rotated, new public key: {}", new_pubkey);
-        Ok(())
+        info!("Server keypair rotated, new public key: {} (previous key {} accepted for {:?})",
+              new_pubkey, old_pubkey, overlap);
+        Ok(new_pubkey)
     }
 
 
@@ -479,6 +651,105 @@ mod tests {
 
     }
 
+    #[test]
+    fn test_key_error_classification() {
+        assert!(KeyError::Io(io::Error::new(io::ErrorKind::Interrupted, "interrupted")).is_transient());
+        assert!(KeyError::Io(io::Error::new(io::ErrorKind::OutOfMemory, "oom")).is_transient());
+        assert!(!KeyError::Io(io::Error::new(io::ErrorKind::PermissionDenied, "denied")).is_transient());
+        assert!(!KeyError::InvalidData("Invalid Ed25519 point".into()).is_transient());
+        assert!(!KeyError::Crypto("HKDF expansion failed".into()).is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_shared_secret_retry_skips_permanent_failures() {
+        let dir = tempdir().unwrap();
+        let key_manager = KeyManager::new(dir.path().join("test_keypair.bin"), Duration::from_secs(60), 10).await.unwrap();
+        key_manager.set_secret_retry_policy(SecretRetryPolicy {
+            max_retries: 3,
+            base_backoff: Duration::from_secs(5),
+        }).await;
+
+        // A valid key derives on the first attempt
+        let client = Keypair::new();
+        let outcome = key_manager.get_shared_secret_with_retry(None, &client.pubkey()).await;
+        assert!(outcome.result.is_ok());
+        assert_eq!(outcome.transient_failures, 0);
+
+        // A point that is not on the curve fails immediately without backoff
+        let invalid = (0u8..=255)
+            .map(|b| [b; 32])
+            .find(|bytes| CompressedEdwardsY::from_slice(bytes).decompress().is_none())
+            .unwrap();
+        let start = Instant::now();
+        let outcome = key_manager.get_shared_secret_with_retry(None, &Pubkey::new_from_array(invalid)).await;
+        assert!(matches!(outcome.result, Err(KeyError::InvalidData(_))));
+        assert_eq!(outcome.transient_failures, 0);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_server_key_rotation_overlap() {
+        let dir = tempdir().unwrap();
+        let key_path = dir.path().join("server_key.bin");
+        let key_manager = KeyManager::new(&key_path, Duration::from_secs(60), 10).await.unwrap();
+        key_manager.set_rotation_overlap(Duration::from_millis(200)).await;
+        let client = Keypair::new();
+
+        // A challenge goes out with the current key, then a new key is loaded
+        let old_key = key_manager.public_key().await;
+        let replacement = Keypair::new();
+        let new_key_path = dir.path().join("new_key.bin");
+        KeyManager::save_keypair(&replacement, &new_key_path).unwrap();
+        assert_eq!(key_manager.load_new_keypair(&new_key_path).await.unwrap(), replacement.pubkey());
+
+        // New challenges carry the new key, which is persisted for restarts
+        let new_key = key_manager.public_key().await;
+        assert_eq!(new_key, replacement.pubkey());
+        assert_eq!(KeyManager::load_keypair(&key_path).unwrap().pubkey(), new_key);
+        assert_eq!(key_manager.accepted_public_keys().await, vec![new_key, old_key]);
+
+        // During the overlap both challenges derive the secret their client computes
+        let old_secret = key_manager.get_shared_secret_for(Some(&old_key), &client.pubkey()).await.unwrap();
+        assert_eq!(old_secret, generate_shared_secret(&client, &old_key).unwrap());
+        let new_secret = key_manager.get_shared_secret_for(Some(&new_key), &client.pubkey()).await.unwrap();
+        assert_eq!(new_secret, generate_shared_secret(&client, &new_key).unwrap());
+        assert_eq!(key_manager.get_shared_secret(&client.pubkey()).await.unwrap(), new_secret);
+        let outcome = key_manager.get_shared_secret_with_retry(Some(&old_key), &client.pubkey()).await;
+        assert_eq!(outcome.result.unwrap(), old_secret);
+
+        // Signatures use the new key
+        let signature = key_manager.sign_message(b"challenge").await;
+        assert!(KeyManager::verify_signature(&new_key, b"challenge", &signature));
+
+        // Unknown keys are refused, and the old key once the overlap has passed
+        let unknown = Keypair::new().pubkey();
+        assert!(matches!(
+            key_manager.get_shared_secret_for(Some(&unknown), &client.pubkey()).await,
+            Err(KeyError::NotFound(_))
+        ));
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert!(matches!(
+            key_manager.get_shared_secret_for(Some(&old_key), &client.pubkey()).await,
+            Err(KeyError::NotFound(_))
+        ));
+        assert_eq!(key_manager.accepted_public_keys().await, vec![new_key]);
+        assert!(key_manager.get_shared_secret_for(Some(&new_key), &client.pubkey()).await.is_ok());
+
+        // Reloading picks up a key an operator wrote over the key file, and nothing else
+        assert_eq!(key_manager.reload_keypair().await.unwrap(), new_key);
+        assert_eq!(key_manager.accepted_public_keys().await, vec![new_key]);
+        let replacement = Keypair::new();
+        KeyManager::save_keypair(&replacement, &key_path).unwrap();
+        assert_eq!(key_manager.reload_keypair().await.unwrap(), replacement.pubkey());
+        assert_eq!(key_manager.accepted_public_keys().await, vec![replacement.pubkey(), new_key]);
+
+        // A further rotation inside the overlap would drop `new_key` early, so it is refused
+        assert!(matches!(key_manager.rotate_keypair().await, Err(KeyError::RotationPending(_))));
+        assert_eq!(key_manager.public_key().await, replacement.pubkey());
+        assert_eq!(KeyManager::load_keypair(&key_path).unwrap().pubkey(), replacement.pubkey());
+        assert_eq!(key_manager.accepted_public_keys().await, vec![replacement.pubkey(), new_key]);
+    }
+
     #[test]
     fn test_shared_secret() {
         // Create two keypairs
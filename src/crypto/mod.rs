@@ -4,10 +4,13 @@
 //! This module provides cryptographic functions and utilities for
 //! secure key management, encryption, and signatures.
 
+pub mod batch;
 pub mod encryption;
 pub mod keys;
 pub mod session;
 pub mod flexible_encryption; // Add the new module
+pub mod nonce;
+pub mod replay;
 
 // Re-export commonly used items
 pub use encryption::{encrypt_packet, decrypt_packet};
@@ -0,0 +1,388 @@
+// src/crypto/nonce.rs
+//! Deterministic Data packet nonces.
+//!
+//! Sessions that negotiate the `derived-nonce` feature omit the `nonce`
+//! field from Data packets. Both sides compute it from the session key and
+//! the packet's `counter` instead:
+//!
+//! ```text
+//! iv    = HMAC-SHA256(session_key, "AERONYX-DATA-NONCE" || direction || salt)[0..12]
+//! nonce = iv XOR (0x00000000 || counter as 8-byte big-endian)
+//! ```
+//!
+//! `direction` is a single byte, `0x00` for server-to-client and `0x01` for
+//! client-to-server, so the two directions never share a nonce under one key.
+//! `salt` is the UTF-8 `session_id` from IpAssign, so sessions that hold the
+//! same key (several sessions of one client, or a resumed session) derive
+//! different IVs.
+//!
+//! Each direction's counter starts at 0 under every session key (the key
+//! from IpAssign and each key delivered by KeyRotation) and must increase
+//! with every Data packet sent under that key. Outbound counters are drawn
+//! from a `SendCounter` kept with the key in `SessionKeyManager`, so the
+//! sessions sharing a client's key never draw the same one; a session sees
+//! gaps where another took a counter. Receivers accept each counter at
+//! most once under the current key, tolerating packets reordered within
+//! `REPLAY_WINDOW_SIZE` (see `crypto::replay`). A (key, direction, counter)
+//! triple is therefore never reused, which is the nonce uniqueness both
+//! ChaCha20-Poly1305 and AES-256-GCM require, salt or no salt.
+//!
+//! Sessions without the feature get the same construction, with the nonce
+//! still sent on the wire and the counter continuing across keys, since
+//! such clients do not know to restart it. Their inbound counters go through
+//! the same replay window, which is never reset.
+//!
+//! Once either direction has used `DATA_KEY_ROTATION_PACKETS` counters
+//! under one key, `take_rotation_due` reports it so the session rotates its key
+//! long before the counter could run out. If the counter does reach its
+//! end, `SendCounter::take` fails rather than wrap.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use thiserror::Error;
+
+use crate::config::constants::{DATA_KEY_ROTATION_PACKETS, SESSION_KEY_SIZE};
+use crate::crypto::replay::{ReplayError, ReplayWindow};
+
+/// Length of AEAD nonces for both supported ciphers
+pub const DATA_NONCE_SIZE: usize = 12;
+
+/// Error type for derived nonce operations
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum NonceError {
+    #[error("Invalid session key length: {0}")]
+    InvalidKeyLength(usize),
+
+    #[error(transparent)]
+    Replay(#[from] ReplayError),
+
+    #[error("Counter space exhausted for the current key")]
+    Exhausted,
+
+    #[error("Session key was replaced")]
+    StaleKey,
+}
+
+/// Direction a Data packet travels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonceDirection {
+    ServerToClient,
+    ClientToServer,
+}
+
+impl NonceDirection {
+    fn label(self) -> u8 {
+        match self {
+            NonceDirection::ServerToClient => 0x00,
+            NonceDirection::ClientToServer => 0x01,
+        }
+    }
+}
+
+/// Derive the per-key, per-direction, per-session IV that counters are mixed into
+pub fn derive_nonce_iv(session_key: &[u8], direction: NonceDirection, salt: &[u8]) -> Result<[u8; DATA_NONCE_SIZE], NonceError> {
+    if session_key.len() != SESSION_KEY_SIZE {
+        return Err(NonceError::InvalidKeyLength(session_key.len()));
+    }
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(session_key)
+        .map_err(|_| NonceError::InvalidKeyLength(session_key.len()))?;
+    mac.update(b"AERONYX-DATA-NONCE");
+    mac.update(&[direction.label()]);
+    mac.update(salt);
+    let digest = mac.finalize().into_bytes();
+    let mut iv = [0u8; DATA_NONCE_SIZE];
+    iv.copy_from_slice(&digest[..DATA_NONCE_SIZE]);
+    Ok(iv)
+}
+
+/// Build the nonce for `counter` from a derived IV
+pub fn nonce_for_counter(iv: &[u8; DATA_NONCE_SIZE], counter: u64) -> [u8; DATA_NONCE_SIZE] {
+    let mut nonce = *iv;
+    for (byte, c) in nonce[4..].iter_mut().zip(counter.to_be_bytes()) {
+        *byte ^= c;
+    }
+    nonce
+}
+
+/// Outbound Data counters under one session key.
+///
+/// Shared by every session using the key, so none of them takes a counter
+/// another has already used under it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SendCounter {
+    /// Next counter not yet taken under this key
+    next: u64,
+    /// First counter for sessions whose counter continues across keys
+    continuous_from: u64,
+}
+
+impl SendCounter {
+    /// Counter for the key replacing this one: restarting sessions begin
+    /// again at 0, continuing ones above anything taken so far
+    pub fn successor(&self) -> Self {
+        Self {
+            next: 0,
+            continuous_from: self.next.max(self.continuous_from),
+        }
+    }
+
+    /// Take a counter never taken before under this key, from 0 if `restart`
+    /// or else continuing from earlier keys
+    pub fn take(&mut self, restart: bool) -> Result<u64, NonceError> {
+        let counter = if restart { self.next } else { self.next.max(self.continuous_from) };
+        self.next = counter.checked_add(1).ok_or(NonceError::Exhausted)?;
+        Ok(counter)
+    }
+}
+
+/// Counter state for one direction under the current key
+#[derive(Debug, Default)]
+struct DirectionState {
+    /// IV of the key the counter belongs to; a new key resets the counter
+    iv: Option<[u8; DATA_NONCE_SIZE]>,
+    /// Counters accepted under that key (inbound only)
+    window: ReplayWindow,
+    /// Counters used under the current key
+    used: u64,
+    /// Rotation already reported for the current key
+    rotation_reported: bool,
+}
+
+impl DirectionState {
+    /// Switch to the key's IV, restarting the counter (if `restart`) when the key changed
+    fn enter_epoch(&mut self, session_key: &[u8], direction: NonceDirection, salt: &[u8], restart: bool) -> Result<[u8; DATA_NONCE_SIZE], NonceError> {
+        let iv = derive_nonce_iv(session_key, direction, salt)?;
+        if self.iv != Some(iv) {
+            self.iv = Some(iv);
+            self.used = 0;
+            self.rotation_reported = false;
+            if restart {
+                self.window = ReplayWindow::default();
+            }
+        }
+        Ok(iv)
+    }
+
+    /// Report, once per key, that `rotate_after` counters have been used
+    fn take_rotation_due(&mut self, rotate_after: u64) -> bool {
+        if self.used < rotate_after || self.rotation_reported {
+            return false;
+        }
+        self.rotation_reported = true;
+        true
+    }
+}
+
+/// Per-session counters for Data nonces
+#[derive(Debug)]
+pub struct DataNonceState {
+    send: DirectionState,
+    receive: DirectionState,
+    /// Counters either direction may use under one key before rotation is due
+    rotate_after: u64,
+}
+
+impl Default for DataNonceState {
+    fn default() -> Self {
+        Self::new(DATA_KEY_ROTATION_PACKETS)
+    }
+}
+
+impl DataNonceState {
+    /// Counter state that asks for a new key after `rotate_after` packets in either direction
+    pub fn new(rotate_after: u64) -> Self {
+        Self {
+            send: DirectionState::default(),
+            receive: DirectionState::default(),
+            rotate_after,
+        }
+    }
+
+    /// Nonce for an outbound `counter` taken from the key's `SendCounter`,
+    /// salted with the session's `salt`
+    pub fn send_nonce(&mut self, session_key: &[u8], salt: &[u8], counter: u64) -> Result<[u8; DATA_NONCE_SIZE], NonceError> {
+        let iv = self.send.enter_epoch(session_key, NonceDirection::ServerToClient, salt, false)?;
+        self.send.used += 1;
+        Ok(nonce_for_counter(&iv, counter))
+    }
+
+    /// Whether either direction has just used enough counters under the
+    /// current key that the key should be rotated. Reported once per key.
+    pub fn take_rotation_due(&mut self) -> bool {
+        let send_due = self.send.take_rotation_due(self.rotate_after);
+        let receive_due = self.receive.take_rotation_due(self.rotate_after);
+        send_due || receive_due
+    }
+
+    /// Nonce for an inbound packet, rejecting replayed or stale counters.
+    /// Call `accept_received` once the packet has been authenticated.
+    pub fn receive_nonce(&mut self, session_key: &[u8], salt: &[u8], counter: u64) -> Result<[u8; DATA_NONCE_SIZE], NonceError> {
+        let iv = self.receive.enter_epoch(session_key, NonceDirection::ClientToServer, salt, true)?;
+        self.receive.window.check(counter)?;
+        Ok(nonce_for_counter(&iv, counter))
+    }
+
+    /// Reject a replayed or stale counter from a session that sends its
+    /// nonces. Call `accept_received` once the packet has been authenticated.
+    pub fn check_received(&self, counter: u64) -> Result<(), NonceError> {
+        Ok(self.receive.window.check(counter)?)
+    }
+
+    /// Record an authenticated inbound counter
+    pub fn accept_received(&mut self, counter: u64) {
+        self.receive.window.accept(counter);
+        self.receive.used += 1;
+    }
+
+    /// Inbound counters accepted so far, to carry into a resumed session
+    pub fn received_window(&self) -> ReplayWindow {
+        self.receive.window
+    }
+
+    /// Continue from the inbound counters a resumed session's predecessor
+    /// accepted under the same key. Sessions with derived nonces start over
+    /// at their first packet anyway, as the new session's salt gives every
+    /// counter a new nonce.
+    pub fn resume_received(&mut self, window: ReplayWindow) {
+        self.receive.window = window;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SALT: &[u8] = b"session_test";
+
+    #[test]
+    fn test_nonce_construction() {
+        let key = [3u8; 32];
+        let iv = derive_nonce_iv(&key, NonceDirection::ServerToClient, SALT).unwrap();
+        assert_ne!(iv, derive_nonce_iv(&key, NonceDirection::ClientToServer, SALT).unwrap());
+        assert_eq!(nonce_for_counter(&iv, 0), iv);
+
+        // Counter is XORed big-endian into the last 8 bytes
+        let nonce = nonce_for_counter(&iv, 0x0102);
+        assert_eq!(nonce[..10], iv[..10]);
+        assert_eq!(nonce[10], iv[10] ^ 0x01);
+        assert_eq!(nonce[11], iv[11] ^ 0x02);
+        assert_eq!(
+            derive_nonce_iv(&[0u8; 16], NonceDirection::ServerToClient, SALT),
+            Err(NonceError::InvalidKeyLength(16))
+        );
+    }
+
+    #[test]
+    fn test_sessions_sharing_a_key_get_distinct_nonces() {
+        let key = [3u8; 32];
+        let (mut first, mut second) = (DataNonceState::default(), DataNonceState::default());
+
+        // Same key, same counter, different sessions: never the same nonce
+        for counter in 0..4 {
+            assert_ne!(
+                first.send_nonce(&key, b"session_a", counter).unwrap(),
+                second.send_nonce(&key, b"session_b", counter).unwrap()
+            );
+        }
+
+        // Drawing from the key's shared counter, they do not even share counters
+        let mut shared = SendCounter::default();
+        let mut counters = Vec::new();
+        for _ in 0..4 {
+            counters.push(shared.take(true).unwrap());
+            counters.push(shared.take(false).unwrap());
+        }
+        assert_eq!(counters, (0..8).collect::<Vec<u64>>());
+        assert_ne!(
+            derive_nonce_iv(&key, NonceDirection::ClientToServer, b"session_a").unwrap(),
+            derive_nonce_iv(&key, NonceDirection::ClientToServer, b"session_b").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_counters_reset_per_key_and_reject_replays() {
+        let mut state = DataNonceState::default();
+        let (first_key, second_key) = ([1u8; 32], [2u8; 32]);
+        let mut first_counter = SendCounter::default();
+
+        assert_eq!(first_counter.take(true).unwrap(), 0);
+        let counter = first_counter.take(true).unwrap();
+        assert_eq!(counter, 1);
+        let nonce = state.send_nonce(&first_key, SALT, counter).unwrap();
+        assert_ne!(nonce, state.send_nonce(&first_key, SALT, first_counter.take(true).unwrap()).unwrap());
+
+        // A new key starts a new counter epoch with a different IV
+        let mut second_counter = first_counter.successor();
+        let counter = second_counter.take(true).unwrap();
+        assert_eq!(counter, 0);
+        let nonce = state.send_nonce(&second_key, SALT, counter).unwrap();
+        assert_ne!(nonce, nonce_for_counter(&derive_nonce_iv(&first_key, NonceDirection::ServerToClient, SALT).unwrap(), 0));
+
+        // Inbound counters are accepted once each, but only authenticated ones count
+        state.receive_nonce(&first_key, SALT, 5).unwrap();
+        state.receive_nonce(&first_key, SALT, 3).unwrap();
+        state.accept_received(5);
+        assert_eq!(state.receive_nonce(&first_key, SALT, 5), Err(NonceError::Replay(ReplayError::Duplicate(5))));
+        assert!(state.receive_nonce(&first_key, SALT, 6).is_ok());
+        // Reordered within the window is fine
+        assert!(state.receive_nonce(&first_key, SALT, 3).is_ok());
+        assert!(state.receive_nonce(&second_key, SALT, 0).is_ok());
+    }
+
+    #[test]
+    fn test_rotation_due_before_exhaustion() {
+        let mut state = DataNonceState::new(3);
+        let (first_key, second_key) = ([1u8; 32], [2u8; 32]);
+
+        for counter in 0..3 {
+            assert!(!state.take_rotation_due());
+            state.send_nonce(&first_key, SALT, counter).unwrap();
+        }
+        assert!(state.take_rotation_due());
+
+        // Reported once, then again only under the next key
+        state.send_nonce(&first_key, SALT, 3).unwrap();
+        assert!(!state.take_rotation_due());
+        state.send_nonce(&second_key, SALT, 0).unwrap();
+        assert!(!state.take_rotation_due());
+
+        // Inbound packets count too, once authenticated
+        for counter in 0..3 {
+            state.receive_nonce(&second_key, SALT, counter).unwrap();
+            assert!(!state.take_rotation_due());
+            state.accept_received(counter);
+        }
+        assert!(state.take_rotation_due());
+
+        // The counter never wraps back onto a used nonce
+        let mut counter = SendCounter { next: u64::MAX - 1, continuous_from: 0 };
+        assert_eq!(counter.take(true), Ok(u64::MAX - 1));
+        assert_eq!(counter.take(true), Err(NonceError::Exhausted));
+    }
+
+    #[test]
+    fn test_continuous_counters_span_keys() {
+        let mut state = DataNonceState::new(2);
+        let (first_key, second_key) = ([1u8; 32], [2u8; 32]);
+        let mut first_counter = SendCounter::default();
+
+        for expected in 0..2 {
+            let counter = first_counter.take(false).unwrap();
+            assert_eq!(counter, expected);
+            state.send_nonce(&first_key, SALT, counter).unwrap();
+        }
+        assert!(state.take_rotation_due());
+
+        // Still bound to the counter under the new key, without restarting it
+        let mut second_counter = first_counter.successor();
+        let counter = second_counter.take(false).unwrap();
+        assert_eq!(counter, 2);
+        let nonce = state.send_nonce(&second_key, SALT, counter).unwrap();
+        assert_eq!(nonce, nonce_for_counter(&derive_nonce_iv(&second_key, NonceDirection::ServerToClient, SALT).unwrap(), 2));
+        assert!(!state.take_rotation_due());
+
+        // Restarting sessions under the same key skip what continuing ones took
+        assert_eq!(second_counter.take(true).unwrap(), 3);
+        assert_eq!(second_counter.successor().take(true).unwrap(), 0);
+    }
+}
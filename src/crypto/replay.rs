@@ -0,0 +1,156 @@
+// src/crypto/replay.rs
+//! Sliding-window replay protection for inbound Data counters.
+//!
+//! The window follows the IPsec anti-replay algorithm (RFC 4303,
+//! section 3.4.3): it remembers the highest counter accepted and a bitmap
+//! of the `REPLAY_WINDOW_SIZE` counters below it. A counter above the
+//! highest, or inside the window and not yet seen, is accepted; duplicates
+//! and counters that fell out of the window are rejected. Packets
+//! reordered on a lossy link therefore still get through, as long as they
+//! are not more than a window behind.
+//!
+//! `check` does not change the window; callers `accept` a counter only
+//! once its packet has been authenticated, so forged packets cannot
+//! advance the window past the real ones.
+
+use thiserror::Error;
+
+use crate::config::constants::REPLAY_WINDOW_SIZE;
+
+// The bitmap is a u128
+const _: () = assert!(REPLAY_WINDOW_SIZE > 0 && REPLAY_WINDOW_SIZE <= 128);
+
+/// Why a counter was refused
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ReplayError {
+    #[error("Counter {0} was already received")]
+    Duplicate(u64),
+
+    #[error("Counter {counter} is too far behind the highest accepted {highest}")]
+    TooOld { counter: u64, highest: u64 },
+}
+
+/// Highest accepted counter and which of the counters below it were seen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplayWindow {
+    /// Highest counter accepted so far
+    highest: Option<u64>,
+    /// Bit `i` is set when counter `highest - i` was accepted
+    seen: u128,
+}
+
+impl ReplayWindow {
+    /// Whether `counter` would be accepted
+    pub fn check(&self, counter: u64) -> Result<(), ReplayError> {
+        let Some(highest) = self.highest else {
+            return Ok(());
+        };
+        if counter > highest {
+            return Ok(());
+        }
+        let behind = highest - counter;
+        if behind >= REPLAY_WINDOW_SIZE {
+            return Err(ReplayError::TooOld { counter, highest });
+        }
+        if self.seen & (1 << behind) != 0 {
+            return Err(ReplayError::Duplicate(counter));
+        }
+        Ok(())
+    }
+
+    /// Record an authenticated counter that passed `check`
+    pub fn accept(&mut self, counter: u64) {
+        match self.highest {
+            Some(highest) if counter <= highest => {
+                let behind = highest - counter;
+                if behind < REPLAY_WINDOW_SIZE {
+                    self.seen |= 1 << behind;
+                }
+            }
+            Some(highest) => {
+                let ahead = counter - highest;
+                self.seen = if ahead >= 128 { 0 } else { self.seen << ahead };
+                self.seen |= 1;
+                self.highest = Some(counter);
+            }
+            None => {
+                self.seen = 1;
+                self.highest = Some(counter);
+            }
+        }
+    }
+
+    /// Highest counter accepted so far
+    pub fn highest(&self) -> Option<u64> {
+        self.highest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receive(window: &mut ReplayWindow, counter: u64) -> Result<(), ReplayError> {
+        window.check(counter)?;
+        window.accept(counter);
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_order_counters() {
+        let mut window = ReplayWindow::default();
+        for counter in 0..1000 {
+            assert_eq!(receive(&mut window, counter), Ok(()));
+        }
+        assert_eq!(window.highest(), Some(999));
+    }
+
+    #[test]
+    fn test_reordered_within_window() {
+        let mut window = ReplayWindow::default();
+        receive(&mut window, 200).unwrap();
+        receive(&mut window, 202).unwrap();
+        // Late arrivals that were never seen are still accepted
+        receive(&mut window, 201).unwrap();
+        receive(&mut window, 195).unwrap();
+        receive(&mut window, 202 - (REPLAY_WINDOW_SIZE - 1)).unwrap();
+        assert_eq!(window.highest(), Some(202));
+
+        // A jump keeps what was seen inside the shifted window
+        receive(&mut window, 250).unwrap();
+        assert_eq!(window.check(202), Err(ReplayError::Duplicate(202)));
+        assert_eq!(window.check(195), Err(ReplayError::Duplicate(195)));
+        receive(&mut window, 249).unwrap();
+    }
+
+    #[test]
+    fn test_duplicates_rejected() {
+        let mut window = ReplayWindow::default();
+        receive(&mut window, 0).unwrap();
+        assert_eq!(receive(&mut window, 0), Err(ReplayError::Duplicate(0)));
+        receive(&mut window, 3).unwrap();
+        receive(&mut window, 1).unwrap();
+        assert_eq!(receive(&mut window, 1), Err(ReplayError::Duplicate(1)));
+        assert_eq!(receive(&mut window, 3), Err(ReplayError::Duplicate(3)));
+
+        // Only accepted counters count; a checked but unauthenticated one does not
+        assert_eq!(window.check(2), Ok(()));
+        assert_eq!(window.check(2), Ok(()));
+    }
+
+    #[test]
+    fn test_below_window_rejected() {
+        let mut window = ReplayWindow::default();
+        let highest = REPLAY_WINDOW_SIZE + 50;
+        receive(&mut window, highest).unwrap();
+        let oldest = highest - (REPLAY_WINDOW_SIZE - 1);
+        assert_eq!(window.check(oldest), Ok(()));
+        assert_eq!(window.check(oldest - 1), Err(ReplayError::TooOld { counter: oldest - 1, highest }));
+        assert_eq!(window.check(0), Err(ReplayError::TooOld { counter: 0, highest }));
+
+        // Jumping further than the window forgets everything behind it
+        receive(&mut window, highest + 1000).unwrap();
+        assert!(matches!(window.check(highest), Err(ReplayError::TooOld { .. })));
+        assert_eq!(window.check(highest + 999), Ok(()));
+    }
+}
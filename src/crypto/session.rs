@@ -4,15 +4,19 @@
 //! This module manages the generation, storage, and rotation of
 //! session keys used for encrypting network traffic.
 
-use rand::RngCore;
-use std::collections::HashMap;
+use rand::{Rng, RngCore};
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify};
 // Removed unused warn, info imports
 use tracing::debug;
+use zeroize::Zeroizing;
 
-use crate::config::constants::SESSION_KEY_SIZE;
+use crate::config::constants::{
+    KEY_ROTATION_DEFERRAL_MIN_INTERVAL, KEY_ROTATION_HISTORY_SIZE, KEY_ROTATION_PACING_MAX_DELAY, SESSION_KEY_SIZE,
+};
+use crate::crypto::nonce::{NonceError, SendCounter};
 use crate::utils;
 
 /// Session key entry with metadata
@@ -26,20 +30,43 @@ pub struct SessionKeyEntry {
     pub last_used: Instant,
     /// How many times the key has been used
     pub usage_count: u64,
+    /// Client-requested postponement of this key's rotation
+    deferred_until: Option<Instant>,
+    /// Total postponement granted for this key
+    deferred_total: Duration,
+    /// Last postponement request, for rate limiting
+    last_deferral_request: Option<Instant>,
+    /// Outbound Data counters taken under this key by the client's sessions
+    send_counter: SendCounter,
 }
 
 impl SessionKeyEntry {
     /// Create a new session key entry
-    fn new(key: Vec<u8>) -> Self {
+    fn new(key: Vec<u8>, send_counter: SendCounter) -> Self {
         let now = Instant::now();
         Self {
             key,
+            send_counter,
             created_at: now,
             last_used: now,
             usage_count: 0,
+            deferred_until: None,
+            deferred_total: Duration::ZERO,
+            last_deferral_request: None,
         }
     }
 
+    /// Entry for `key` replacing `previous`, keeping the counter state if
+    /// the key is unchanged
+    fn replacing(previous: Option<&SessionKeyEntry>, key: Vec<u8>) -> Self {
+        let send_counter = match previous {
+            Some(previous) if previous.key == key => previous.send_counter.clone(),
+            Some(previous) => previous.send_counter.successor(),
+            None => SendCounter::default(),
+        };
+        Self::new(key, send_counter)
+    }
+
     /// Update the last used timestamp and increment usage count
     fn touch(&mut self) {
         self.last_used = Instant::now();
@@ -50,6 +77,90 @@ impl SessionKeyEntry {
     fn should_rotate(&self, max_age: Duration, max_usage: u64) -> bool {
         self.created_at.elapsed() > max_age || (max_usage > 0 && self.usage_count > max_usage)
     }
+
+    /// Time left on an active rotation postponement
+    fn deferral_remaining(&self) -> Option<Duration> {
+        self.deferred_until
+            .map(|until| until.saturating_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+}
+
+/// Record of a session key coming into use (never contains key material)
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct KeyRotationRecord {
+    /// Identifier of the key sent to the client
+    pub key_id: String,
+    /// When the key came into use (Unix milliseconds)
+    pub rotated_at: u64,
+}
+
+/// Pool of pre-generated session keys, refilled in the background.
+///
+/// Each key is handed out at most once. Keys still pooled when the pool
+/// is dropped are zeroized.
+#[derive(Debug)]
+pub struct SessionKeyPool {
+    /// Pre-generated keys waiting to be handed out
+    keys: Mutex<VecDeque<Zeroizing<Vec<u8>>>>,
+    /// Target number of pooled keys
+    capacity: usize,
+    /// Wakes the refill task when keys are drawn
+    refill_needed: Notify,
+}
+
+impl SessionKeyPool {
+    /// Create an empty pool holding up to `capacity` keys
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            keys: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            refill_needed: Notify::new(),
+        }
+    }
+
+    /// Take a key from the pool, if one is available
+    pub async fn take(&self) -> Option<Vec<u8>> {
+        let key = self.keys.lock().await.pop_front();
+        self.refill_needed.notify_one();
+        // Move the bytes out so the wrapper zeroizes only an empty buffer
+        key.map(|mut key| std::mem::take(&mut *key))
+    }
+
+    /// Top the pool up to capacity, returning the number of keys added
+    pub async fn refill(&self) -> usize {
+        let mut added = 0;
+        loop {
+            // Generate outside the lock so handshakes are never blocked on it
+            let key = Zeroizing::new(SessionKeyManager::generate_key());
+            let mut keys = self.keys.lock().await;
+            if keys.len() >= self.capacity {
+                return added;
+            }
+            keys.push_back(key);
+            added += 1;
+        }
+    }
+
+    /// Number of keys currently pooled
+    pub async fn available(&self) -> usize {
+        self.keys.lock().await.len()
+    }
+
+    /// Spawn the background task that keeps the pool full
+    pub fn start_refill_task(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let pool = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                let added = pool.refill().await;
+                if added > 0 {
+                    debug!("Refilled session key pool with {} keys ({} available)",
+                           added, pool.available().await);
+                }
+                pool.refill_needed.notified().await;
+            }
+        })
+    }
 }
 
 /// Session key manager for the server
@@ -61,6 +172,20 @@ pub struct SessionKeyManager {
     rotation_interval: Duration,
     /// Maximum key usages before rotation
     max_key_usages: u64,
+    /// Optional pool of pre-generated keys for handshakes
+    key_pool: Option<Arc<SessionKeyPool>>,
+    /// Bounded rotation history by client ID, oldest first
+    key_history: Arc<Mutex<HashMap<String, VecDeque<KeyRotationRecord>>>>,
+    /// Total postponement a client may request for each key (zero = not allowed)
+    max_rotation_deferral: Duration,
+    /// Rotations since the client's key was first stored, by client ID
+    rotation_counts: Arc<Mutex<HashMap<String, u64>>>,
+    /// Next free slot in the server-wide rotation schedule, if rotations are rate limited
+    next_rotation_slot: Option<Arc<Mutex<Instant>>>,
+    /// Spacing between rotation slots
+    rotation_spacing: Duration,
+    /// Maximum per-session shortening of the rotation interval, in percent
+    rotation_jitter_percent: u8,
 }
 
 impl SessionKeyManager {
@@ -70,7 +195,65 @@ impl SessionKeyManager {
             session_keys: Arc::new(Mutex::new(HashMap::new())),
             rotation_interval,
             max_key_usages,
+            key_pool: None,
+            key_history: Arc::new(Mutex::new(HashMap::new())),
+            max_rotation_deferral: Duration::ZERO,
+            rotation_counts: Arc::new(Mutex::new(HashMap::new())),
+            next_rotation_slot: None,
+            rotation_spacing: Duration::ZERO,
+            rotation_jitter_percent: 0,
+        }
+    }
+
+    /// Allow clients to postpone each scheduled rotation by up to `max` in total
+    pub fn with_rotation_deferral(mut self, max: Duration) -> Self {
+        self.max_rotation_deferral = max;
+        self
+    }
+
+    /// Limit rotations across all clients to `per_sec` (0 = unlimited)
+    pub fn with_rotation_rate_limit(mut self, per_sec: u32) -> Self {
+        if per_sec > 0 {
+            self.next_rotation_slot = Some(Arc::new(Mutex::new(Instant::now())));
+            self.rotation_spacing = Duration::from_secs(1) / per_sec;
         }
+        self
+    }
+
+    /// Shorten each session's rotation interval by a random 0..=`percent`%
+    pub fn with_rotation_jitter(mut self, percent: u8) -> Self {
+        self.rotation_jitter_percent = percent.min(100);
+        self
+    }
+
+    /// Configured rotation interval, before jitter
+    pub fn rotation_interval(&self) -> Duration {
+        self.rotation_interval
+    }
+
+    /// Pick a rotation interval for a new session.
+    ///
+    /// Jitter only ever shortens the interval, so no key outlives the
+    /// configured limit, while sessions that connected together drift apart.
+    pub fn session_rotation_interval(&self) -> Duration {
+        if self.rotation_jitter_percent == 0 {
+            return self.rotation_interval;
+        }
+        let jitter = rand::thread_rng().gen_range(0.0..=self.rotation_jitter_percent as f64 / 100.0);
+        self.rotation_interval.mul_f64(1.0 - jitter)
+    }
+
+    /// Draw handshake keys from a pre-generated pool of `size` keys
+    pub fn with_key_pool(mut self, size: usize) -> Self {
+        if size > 0 {
+            self.key_pool = Some(Arc::new(SessionKeyPool::new(size)));
+        }
+        self
+    }
+
+    /// Get the key pool, if enabled
+    pub fn key_pool(&self) -> Option<&Arc<SessionKeyPool>> {
+        self.key_pool.as_ref()
     }
 
     /// Generate a new random session key
@@ -80,14 +263,78 @@ impl SessionKeyManager {
         key
     }
 
+    /// Get a fresh session key, from the pool if available
+    pub async fn next_key(&self) -> Vec<u8> {
+        if let Some(pool) = &self.key_pool {
+            if let Some(key) = pool.take().await {
+                return key;
+            }
+            debug!("Session key pool empty, generating key inline");
+        }
+        Self::generate_key()
+    }
+
     /// Store a session key for a client
     pub async fn store_key(&self, client_id: &str, key: Vec<u8>) {
         let mut keys = self.session_keys.lock().await;
-        let entry = SessionKeyEntry::new(key);
+        let entry = SessionKeyEntry::replacing(keys.get(client_id), key);
         keys.insert(client_id.to_string(), entry);
         debug!("Stored new session key for client {}", utils::security::StringValidator::sanitize_log(client_id));
     }
 
+    /// Store a session key for a client and record it in the rotation history
+    pub async fn store_key_with_id(&self, client_id: &str, key: Vec<u8>, key_id: &str) {
+        self.store_key(client_id, key).await;
+
+        let mut history = self.key_history.lock().await;
+        let records = history.entry(client_id.to_string()).or_default();
+        if records.len() >= KEY_ROTATION_HISTORY_SIZE {
+            records.pop_front();
+        }
+        records.push_back(KeyRotationRecord {
+            key_id: key_id.to_string(),
+            rotated_at: utils::current_timestamp_millis(),
+        });
+    }
+
+    /// Store the key of a resumed session, continuing the outbound counters
+    /// its previous session took under it
+    pub async fn store_resumed_key(&self, client_id: &str, key: Vec<u8>, key_id: &str, send_counter: SendCounter) {
+        self.store_key_with_id(client_id, key, key_id).await;
+        if let Some(entry) = self.session_keys.lock().await.get_mut(client_id) {
+            entry.send_counter = send_counter;
+        }
+    }
+
+    /// Store a client's rotated key, recording it in the history and rotation count
+    pub async fn store_rotated_key(&self, client_id: &str, key: Vec<u8>, key_id: &str) {
+        self.store_key_with_id(client_id, key, key_id).await;
+        *self.rotation_counts.lock().await.entry(client_id.to_string()).or_insert(0) += 1;
+    }
+
+    /// Number of rotations of a client's key since it was first stored
+    pub async fn rotation_count(&self, client_id: &str) -> u64 {
+        self.rotation_counts.lock().await.get(client_id).copied().unwrap_or(0)
+    }
+
+    /// Get the rotation history for a client, oldest first
+    pub async fn key_history(&self, client_id: &str) -> Vec<KeyRotationRecord> {
+        self.key_history.lock().await
+            .get(client_id)
+            .map(|records| records.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Find the key that was active for a client at `timestamp` (Unix milliseconds)
+    pub async fn key_active_at(&self, client_id: &str, timestamp: u64) -> Option<KeyRotationRecord> {
+        self.key_history.lock().await
+            .get(client_id)?
+            .iter()
+            .rev()
+            .find(|record| record.rotated_at <= timestamp)
+            .cloned()
+    }
+
     /// Get a session key for a client, updating usage statistics
     pub async fn get_key(&self, client_id: &str) -> Option<Vec<u8>> {
         let mut keys = self.session_keys.lock().await;
@@ -110,23 +357,87 @@ impl SessionKeyManager {
 
     /// Check if a key needs to be rotated
     pub async fn needs_rotation(&self, client_id: &str) -> bool {
+        self.needs_rotation_after(client_id, self.rotation_interval).await
+    }
+
+    /// Check if a key needs to be rotated under a session-specific interval
+    pub async fn needs_rotation_after(&self, client_id: &str, interval: Duration) -> bool {
         let keys = self.session_keys.lock().await;
 
         if let Some(entry) = keys.get(client_id) {
-            entry.should_rotate(self.rotation_interval, self.max_key_usages)
+            entry.should_rotate(interval, self.max_key_usages)
+                && entry.deferral_remaining().is_none()
         } else {
             false
         }
     }
 
+    /// Postpone the next rotation of a client's key by up to `requested`.
+    ///
+    /// Grants are capped so the total postponement of one key never exceeds
+    /// the configured ceiling, and requests closer together than
+    /// `KEY_ROTATION_DEFERRAL_MIN_INTERVAL` are refused. Returns the
+    /// postponement granted from now (zero if refused).
+    pub async fn defer_rotation(&self, client_id: &str, requested: Duration) -> Duration {
+        let mut keys = self.session_keys.lock().await;
+        let entry = match keys.get_mut(client_id) {
+            Some(entry) => entry,
+            None => return Duration::ZERO,
+        };
+
+        let now = Instant::now();
+        if entry.last_deferral_request
+            .map_or(false, |last| now.duration_since(last) < KEY_ROTATION_DEFERRAL_MIN_INTERVAL)
+        {
+            debug!("Rate limited key rotation deferral for client {}", utils::security::StringValidator::sanitize_log(client_id));
+            return Duration::ZERO;
+        }
+        entry.last_deferral_request = Some(now);
+
+        // Extend from the current deferral end (if any) within the remaining budget
+        let current = entry.deferral_remaining().unwrap_or_default();
+        let budget = self.max_rotation_deferral.saturating_sub(entry.deferred_total);
+        let extension = requested.saturating_sub(current).min(budget);
+        if extension.is_zero() {
+            return current;
+        }
+        entry.deferred_total += extension;
+        entry.deferred_until = Some(now + current + extension);
+        debug!("Deferred key rotation for client {} by {:?}", utils::security::StringValidator::sanitize_log(client_id), current + extension);
+        current + extension
+    }
+
+    /// Time left on a client's rotation postponement, if one is active
+    pub async fn rotation_deferred(&self, client_id: &str) -> Option<Duration> {
+        self.session_keys.lock().await.get(client_id)?.deferral_remaining()
+    }
+
+    /// Reserve a slot in the server-wide rotation schedule.
+    ///
+    /// Returns how long the caller should wait before rotating so that
+    /// rotations are spread evenly at the configured rate. The wait is
+    /// capped at `KEY_ROTATION_PACING_MAX_DELAY`; past that the rate is
+    /// exceeded rather than holding keys beyond their lifetime any longer.
+    pub async fn reserve_rotation_slot(&self) -> Duration {
+        let next_slot = match &self.next_rotation_slot {
+            Some(next_slot) => next_slot,
+            None => return Duration::ZERO,
+        };
+        let mut next = next_slot.lock().await;
+        let now = Instant::now();
+        let slot = (*next).max(now).min(now + KEY_ROTATION_PACING_MAX_DELAY);
+        *next = slot + self.rotation_spacing;
+        slot - now
+    }
+
     /// Rotate a session key
     pub async fn rotate_key(&self, client_id: &str) -> Option<Vec<u8>> {
         let mut keys = self.session_keys.lock().await;
 
         // Only rotate if the client has an existing key
-        if keys.contains_key(client_id) {
+        if let Some(previous) = keys.get(client_id) {
             let new_key = Self::generate_key();
-            let entry = SessionKeyEntry::new(new_key.clone());
+            let entry = SessionKeyEntry::replacing(Some(previous), new_key.clone());
             keys.insert(client_id.to_string(), entry);
 
             debug!("Rotated session key for client {}", utils::security::StringValidator::sanitize_log(client_id));
@@ -136,9 +447,31 @@ impl SessionKeyManager {
         }
     }
 
+    /// Get a client's session key with the outbound counters taken under it
+    pub async fn key_with_send_counter(&self, client_id: &str) -> Option<(Vec<u8>, SendCounter)> {
+        self.session_keys.lock().await
+            .get(client_id)
+            .map(|entry| (entry.key.clone(), entry.send_counter.clone()))
+    }
+
+    /// Take the next outbound Data counter under a client's `key`, shared by
+    /// all of the client's sessions (see `SendCounter::take` for `restart`).
+    ///
+    /// Fails with `StaleKey` if `key` is no longer the client's key, since a
+    /// counter taken under the replacement would not be unique under `key`.
+    pub async fn next_send_counter(&self, client_id: &str, key: &[u8], restart: bool) -> Result<u64, NonceError> {
+        let mut keys = self.session_keys.lock().await;
+        match keys.get_mut(client_id) {
+            Some(entry) if entry.key == key => entry.send_counter.take(restart),
+            _ => Err(NonceError::StaleKey),
+        }
+    }
+
     /// Remove a client's session key
     pub async fn remove_key(&self, client_id: &str) {
         let mut keys = self.session_keys.lock().await;
+        self.key_history.lock().await.remove(client_id);
+        self.rotation_counts.lock().await.remove(client_id);
         if keys.remove(client_id).is_some() {
             debug!("Removed session key for client {}", utils::security::StringValidator::sanitize_log(client_id));
         }
@@ -173,6 +506,8 @@ impl SessionKeyManager {
         });
 
         let removed = before_count - keys.len();
+        self.key_history.lock().await.retain(|client_id, _| keys.contains_key(client_id));
+        self.rotation_counts.lock().await.retain(|client_id, _| keys.contains_key(client_id));
         if removed > 0 {
             debug!("Cleaned up {} inactive sessions", removed);
         }
@@ -252,6 +587,59 @@ mod tests {
         assert_ne!(retrieved, key2_orig); // Compare with the key stored for client2
     }
 
+    #[tokio::test]
+    async fn test_send_counters_follow_the_key() {
+        let manager = SessionKeyManager::new(Duration::from_secs(10), 100);
+        let first_key = SessionKeyManager::generate_key();
+        manager.store_key("client", first_key.clone()).await;
+
+        // Counters are taken once each across everyone using the key
+        assert_eq!(manager.next_send_counter("client", &first_key, true).await, Ok(0));
+        assert_eq!(manager.next_send_counter("client", &first_key, false).await, Ok(1));
+        assert_eq!(manager.next_send_counter("client", &first_key, true).await, Ok(2));
+
+        // Storing the same key again keeps its counters
+        manager.store_key("client", first_key.clone()).await;
+        assert_eq!(manager.next_send_counter("client", &first_key, true).await, Ok(3));
+
+        // A new key restarts them, except for continuing sessions
+        let second_key = manager.rotate_key("client").await.unwrap();
+        assert_eq!(manager.next_send_counter("client", &first_key, true).await, Err(NonceError::StaleKey));
+        assert_eq!(manager.next_send_counter("client", &second_key, true).await, Ok(0));
+        assert_eq!(manager.next_send_counter("client", &second_key, false).await, Ok(4));
+
+        manager.remove_key("client").await;
+        assert_eq!(manager.next_send_counter("client", &second_key, true).await, Err(NonceError::StaleKey));
+    }
+
+    #[tokio::test]
+    async fn test_rotation_interval_jitter() {
+        // Jittered intervals stay within [base * (1 - jitter), base]
+        let base = Duration::from_secs(3600);
+        let manager = SessionKeyManager::new(base, 0).with_rotation_jitter(20);
+        assert_eq!(manager.rotation_interval(), base);
+        for _ in 0..100 {
+            let interval = manager.session_rotation_interval();
+            assert!(interval <= base && interval >= base.mul_f64(0.8), "{:?}", interval);
+        }
+        let unjittered = SessionKeyManager::new(base, 0);
+        assert_eq!(unjittered.session_rotation_interval(), base);
+
+        // A shortened session interval triggers rotation before the base interval does
+        let manager = SessionKeyManager::new(Duration::from_millis(200), 0).with_rotation_jitter(50);
+        let client_id = "jittered-client";
+        manager.store_key(client_id, SessionKeyManager::generate_key()).await;
+        let session_interval = Duration::from_millis(100);
+        assert!(!manager.needs_rotation_after(client_id, session_interval).await);
+
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        assert!(manager.needs_rotation_after(client_id, session_interval).await);
+        assert!(!manager.needs_rotation(client_id).await);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(manager.needs_rotation(client_id).await);
+    }
+
     #[tokio::test]
     async fn test_cleanup_old_sessions() {
         let manager = SessionKeyManager::new(Duration::from_secs(10), 100);
@@ -284,4 +672,161 @@ mod tests {
         assert_eq!(key2.len(), SESSION_KEY_SIZE);
         assert_ne!(key1, key2);
     }
+
+    #[tokio::test]
+    async fn test_key_pool() {
+        let manager = SessionKeyManager::new(Duration::from_secs(10), 100).with_key_pool(4);
+        let pool = manager.key_pool().unwrap().clone();
+
+        // Empty pool falls back to inline generation
+        assert_eq!(pool.available().await, 0);
+        assert_eq!(manager.next_key().await.len(), SESSION_KEY_SIZE);
+
+        assert_eq!(pool.refill().await, 4);
+        assert_eq!(pool.refill().await, 0);
+
+        // Pooled keys are handed out once and are all distinct
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..4 {
+            let key = manager.next_key().await;
+            assert_eq!(key.len(), SESSION_KEY_SIZE);
+            assert!(seen.insert(key));
+        }
+        assert_eq!(pool.available().await, 0);
+
+        // Background task refills after keys are drawn
+        let handle = pool.start_refill_task();
+        manager.next_key().await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(pool.available().await, 4);
+        handle.abort();
+
+        // Disabled pool
+        let manager = SessionKeyManager::new(Duration::from_secs(10), 100).with_key_pool(0);
+        assert!(manager.key_pool().is_none());
+    }
+
+    #[tokio::test]
+    #[ignore] // Benchmark; run with --ignored --nocapture
+    async fn bench_key_pool_handshake_latency() {
+        const DRAWS: usize = 10_000;
+        let inline = SessionKeyManager::new(Duration::from_secs(10), 100);
+        let pooled = SessionKeyManager::new(Duration::from_secs(10), 100).with_key_pool(DRAWS);
+        pooled.key_pool().unwrap().refill().await;
+
+        let start = Instant::now();
+        for _ in 0..DRAWS {
+            inline.next_key().await;
+        }
+        let inline_time = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..DRAWS {
+            pooled.next_key().await;
+        }
+        let pooled_time = start.elapsed();
+
+        println!(
+            "session key draw: inline {:?}/key, pooled {:?}/key",
+            inline_time / DRAWS as u32,
+            pooled_time / DRAWS as u32
+        );
+    }
+
+    #[tokio::test]
+    async fn test_key_rotation_history() {
+        let manager = SessionKeyManager::new(Duration::from_secs(10), 100);
+        let client_id = "test-client";
+
+        assert!(manager.key_history(client_id).await.is_empty());
+
+        manager.store_key_with_id(client_id, SessionKeyManager::generate_key(), "initial").await;
+        let first = manager.key_history(client_id).await[0].clone();
+        assert_eq!(first.key_id, "initial");
+
+        // History is bounded, dropping the oldest records
+        for i in 0..KEY_ROTATION_HISTORY_SIZE {
+            manager.store_key_with_id(client_id, SessionKeyManager::generate_key(), &format!("key-{}", i)).await;
+        }
+        let history = manager.key_history(client_id).await;
+        assert_eq!(history.len(), KEY_ROTATION_HISTORY_SIZE);
+        assert_eq!(history[0].key_id, "key-0");
+        assert_eq!(history.last().unwrap().key_id, format!("key-{}", KEY_ROTATION_HISTORY_SIZE - 1));
+
+        // Lookup by time returns the latest key rotated in at or before it
+        let latest = history.last().unwrap();
+        assert_eq!(manager.key_active_at(client_id, latest.rotated_at).await.as_ref(), Some(latest));
+        assert!(manager.key_active_at(client_id, 0).await.is_none());
+
+        manager.remove_key(client_id).await;
+        assert!(manager.key_history(client_id).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rotation_deferral_bounds() {
+        // Every key is immediately due for rotation
+        let manager = SessionKeyManager::new(Duration::ZERO, 0)
+            .with_rotation_deferral(Duration::from_secs(60));
+        manager.store_key("client", SessionKeyManager::generate_key()).await;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert!(manager.needs_rotation("client").await);
+
+        // Granted within the ceiling and postpones rotation
+        assert_eq!(manager.defer_rotation("client", Duration::from_secs(40)).await, Duration::from_secs(40));
+        assert!(!manager.needs_rotation("client").await);
+        assert!(manager.rotation_deferred("client").await.unwrap() <= Duration::from_secs(40));
+
+        // Requests in quick succession are refused
+        assert_eq!(manager.defer_rotation("client", Duration::from_secs(50)).await, Duration::ZERO);
+
+        // Further postponement is capped by the remaining budget
+        {
+            let mut keys = manager.session_keys.lock().await;
+            keys.get_mut("client").unwrap().last_deferral_request = None;
+        }
+        let granted = manager.defer_rotation("client", Duration::from_secs(120)).await;
+        assert!(granted <= Duration::from_secs(60) && granted > Duration::from_secs(59));
+
+        // Once the deferral elapses rotation proceeds regardless
+        {
+            let mut keys = manager.session_keys.lock().await;
+            let entry = keys.get_mut("client").unwrap();
+            entry.deferred_until = Some(Instant::now());
+            entry.last_deferral_request = None;
+        }
+        assert!(manager.needs_rotation("client").await);
+        assert_eq!(manager.defer_rotation("client", Duration::from_secs(10)).await, Duration::ZERO);
+        assert!(manager.needs_rotation("client").await);
+
+        // Disabled by default
+        let manager = SessionKeyManager::new(Duration::ZERO, 0);
+        manager.store_key("client", SessionKeyManager::generate_key()).await;
+        assert_eq!(manager.defer_rotation("client", Duration::from_secs(10)).await, Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_rotation_rate_limit() {
+        // Unlimited: never wait
+        let manager = SessionKeyManager::new(Duration::from_secs(10), 100);
+        assert_eq!(manager.reserve_rotation_slot().await, Duration::ZERO);
+        assert_eq!(manager.reserve_rotation_slot().await, Duration::ZERO);
+
+        // 10/s: simultaneous rotations are spaced 100ms apart
+        let manager = SessionKeyManager::new(Duration::from_secs(10), 100).with_rotation_rate_limit(10);
+        let waits: Vec<Duration> = futures::future::join_all(
+            (0..5).map(|_| manager.reserve_rotation_slot())
+        ).await;
+        assert!(waits[0] < Duration::from_millis(10));
+        for pair in waits.windows(2) {
+            let gap = pair[1].saturating_sub(pair[0]);
+            assert!(gap > Duration::from_millis(90) && gap <= Duration::from_millis(100), "gap {:?}", gap);
+        }
+
+        // Waits never exceed the pacing ceiling
+        let manager = SessionKeyManager::new(Duration::from_secs(10), 100).with_rotation_rate_limit(1);
+        for _ in 0..(KEY_ROTATION_PACING_MAX_DELAY.as_secs() + 10) {
+            assert!(manager.reserve_rotation_slot().await <= KEY_ROTATION_PACING_MAX_DELAY);
+        }
+    }
 }
+
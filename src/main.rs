@@ -209,19 +209,37 @@ async fn run_with_vpn(config: ServerConfig) -> anyhow::Result<()> {
     info!("Server successfully initialized with military-grade security features");
     
     // Start server in the background
-    let server_handle = server.start().await?;
+    let mut server_handle = server.start().await?;
     
     // Wait for shutdown signal
     let shutdown_future = wait_for_shutdown_signal();
-    
-    // Wait for either server to finish or shutdown signal
-    tokio::select! {
-        _ = server_handle => {
-            info!("Server stopped");
-        }
-        _ = shutdown_future => {
-            info!("Shutdown signal received, stopping server...");
-            server.shutdown().await?;
+    tokio::pin!(shutdown_future);
+    let mut reload_signals = spawn_reload_signal_listener();
+    
+    // Wait for either server to finish or shutdown signal, handling reloads in between
+    loop {
+        tokio::select! {
+            _ = &mut server_handle => {
+                info!("Server stopped");
+                break;
+            }
+            _ = &mut shutdown_future => {
+                info!("Shutdown signal received, stopping server...");
+                server.shutdown().await?;
+                break;
+            }
+            Some(()) = reload_signals.recv() => {
+                info!("Reload signal received, reloading ACL and configuration...");
+                if let Err(e) = server.reload().await {
+                    error!("Reload failed: {}", e);
+                }
+            }
+            _ = server.reload_requested() => {
+                info!("Reload requested, reloading ACL and configuration...");
+                if let Err(e) = server.reload().await {
+                    error!("Reload failed: {}", e);
+                }
+            }
         }
     }
     
@@ -229,6 +247,31 @@ async fn run_with_vpn(config: ServerConfig) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Forward SIGHUP (reload) notifications over a channel
+fn spawn_reload_signal_listener() -> tokio::sync::mpsc::Receiver<()> {
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    
+    #[cfg(unix)]
+    tokio::spawn(async move {
+        let mut hangup = match signal::unix::signal(signal::unix::SignalKind::hangup()) {
+            Ok(s) => s,
+            Err(e) => {
+                warn!("Failed to install SIGHUP handler: {}", e);
+                return;
+            }
+        };
+        while hangup.recv().await.is_some() {
+            // A pending reload already covers this one
+            let _ = tx.try_send(());
+        }
+    });
+    
+    #[cfg(not(unix))]
+    drop(tx);
+    
+    rx
+}
+
 /// Wait for CTRL+C or termination signal
 async fn wait_for_shutdown_signal() {
     let ctrl_c = async {
@@ -0,0 +1,454 @@
+// src/network/flows.rs
+//! Flow accounting for tunnel traffic.
+//!
+//! This module aggregates inner IPv4 packets into flows keyed by their
+//! 5-tuple and exports finished flows to a collector as NetFlow v5
+//! datagrams, giving the same visibility as flow export on a physical
+//! interface. Tracked state is capped; when full, the least recently
+//! seen flow is exported early to make room. Flows are also indexed by
+//! recency, so finding that flow does not scan the table. Evicted flows
+//! wait for the next export in a queue capped at `max_flows`, which drops
+//! its oldest records (and counts them) if export falls behind.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::utils;
+
+/// NetFlow v5 limits a datagram to 30 records
+const NETFLOW_V5_MAX_RECORDS: usize = 30;
+/// NetFlow v5 header size in bytes
+const NETFLOW_V5_HEADER_SIZE: usize = 24;
+/// NetFlow v5 record size in bytes
+const NETFLOW_V5_RECORD_SIZE: usize = 48;
+
+/// Flow identity taken from the inner IP header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FlowKey {
+    pub src_addr: Ipv4Addr,
+    pub dst_addr: Ipv4Addr,
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub protocol: u8,
+}
+
+impl FlowKey {
+    /// Extract a flow key from an IPv4 packet (ports only for TCP/UDP)
+    pub fn from_ipv4(packet: &[u8]) -> Option<Self> {
+        if packet.len() < 20 || packet[0] >> 4 != 4 {
+            return None;
+        }
+        let header_len = ((packet[0] & 0x0f) as usize) * 4;
+        let protocol = packet[9];
+        let src_addr = Ipv4Addr::new(packet[12], packet[13], packet[14], packet[15]);
+        let dst_addr = Ipv4Addr::new(packet[16], packet[17], packet[18], packet[19]);
+
+        // Non-initial fragments carry no transport header
+        let fragment_offset = u16::from_be_bytes([packet[6], packet[7]]) & 0x1fff;
+        let (src_port, dst_port) = match protocol {
+            6 | 17 if fragment_offset == 0 && packet.len() >= header_len + 4 => (
+                u16::from_be_bytes([packet[header_len], packet[header_len + 1]]),
+                u16::from_be_bytes([packet[header_len + 2], packet[header_len + 3]]),
+            ),
+            _ => (0, 0),
+        };
+
+        Some(Self { src_addr, dst_addr, src_port, dst_port, protocol })
+    }
+}
+
+/// Finished flow ready for export
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlowRecord {
+    /// Flow identity
+    pub key: FlowKey,
+    /// Packets seen
+    pub packets: u64,
+    /// Bytes seen (inner IP length)
+    pub bytes: u64,
+    /// Cumulative TCP flags
+    pub tcp_flags: u8,
+    /// First packet time (Unix milliseconds)
+    pub start_ms: u64,
+    /// Last packet time (Unix milliseconds)
+    pub end_ms: u64,
+}
+
+/// Per-flow counters while the flow is active
+#[derive(Debug)]
+struct FlowState {
+    packets: u64,
+    bytes: u64,
+    tcp_flags: u8,
+    start_ms: u64,
+    end_ms: u64,
+    first_seen: Instant,
+    last_seen: Instant,
+    /// Position in the recency index
+    tick: u64,
+}
+
+impl FlowState {
+    fn into_record(self, key: FlowKey) -> FlowRecord {
+        FlowRecord {
+            key,
+            packets: self.packets,
+            bytes: self.bytes,
+            tcp_flags: self.tcp_flags,
+            start_ms: self.start_ms,
+            end_ms: self.end_ms,
+        }
+    }
+}
+
+/// Flow tracker settings
+#[derive(Debug, Clone, Copy)]
+pub struct FlowTrackerConfig {
+    /// Maximum concurrently tracked flows
+    pub max_flows: usize,
+    /// Export a flow after this long without packets
+    pub idle_timeout: Duration,
+    /// Export long-lived flows at least this often
+    pub active_timeout: Duration,
+}
+
+/// Active flows with their recency index
+#[derive(Debug, Default)]
+struct FlowTable {
+    /// Active flows
+    flows: HashMap<FlowKey, FlowState>,
+    /// Flows by the tick of their last packet, least recently seen first
+    recency: BTreeMap<u64, FlowKey>,
+    /// Tick handed to the next packet
+    next_tick: u64,
+}
+
+impl FlowTable {
+    fn take_tick(&mut self) -> u64 {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        tick
+    }
+
+    fn remove(&mut self, key: &FlowKey) -> Option<FlowState> {
+        let state = self.flows.remove(key)?;
+        self.recency.remove(&state.tick);
+        Some(state)
+    }
+
+    /// Remove the least recently seen flow
+    fn pop_oldest(&mut self) -> Option<(FlowKey, FlowState)> {
+        let (_, key) = self.recency.pop_first()?;
+        self.flows.remove(&key).map(|state| (key, state))
+    }
+}
+
+/// Bounded flow table fed by tunnel packets
+#[derive(Debug)]
+pub struct FlowTracker {
+    /// Active flows
+    table: Mutex<FlowTable>,
+    /// Flows finished early by eviction, awaiting export, oldest first
+    evicted: Mutex<VecDeque<FlowRecord>>,
+    /// Evicted flow records dropped because too many awaited export
+    dropped: AtomicU64,
+    /// Tracker settings
+    config: FlowTrackerConfig,
+}
+
+impl FlowTracker {
+    /// Create a new flow tracker
+    pub fn new(config: FlowTrackerConfig) -> Self {
+        Self {
+            table: Mutex::new(FlowTable::default()),
+            evicted: Mutex::new(VecDeque::new()),
+            dropped: AtomicU64::new(0),
+            config,
+        }
+    }
+
+    /// Account one IPv4 packet (other packets are ignored)
+    pub async fn observe(&self, packet: &[u8]) {
+        let key = match FlowKey::from_ipv4(packet) {
+            Some(key) => key,
+            None => return,
+        };
+        let tcp_flags = if key.protocol == 6 {
+            let header_len = ((packet[0] & 0x0f) as usize) * 4;
+            packet.get(header_len + 13).copied().unwrap_or(0)
+        } else {
+            0
+        };
+
+        let now = Instant::now();
+        let now_ms = utils::current_timestamp_millis();
+        let mut table = self.table.lock().await;
+        let tick = table.take_tick();
+
+        if let Some(state) = table.flows.get_mut(&key) {
+            state.packets += 1;
+            state.bytes += packet.len() as u64;
+            state.tcp_flags |= tcp_flags;
+            state.end_ms = now_ms;
+            state.last_seen = now;
+            let previous = std::mem::replace(&mut state.tick, tick);
+            table.recency.remove(&previous);
+            table.recency.insert(tick, key);
+            return;
+        }
+
+        if table.flows.len() >= self.config.max_flows {
+            // Make room by finishing the least recently seen flow
+            if let Some((oldest, state)) = table.pop_oldest() {
+                let mut evicted = self.evicted.lock().await;
+                if evicted.len() >= self.config.max_flows {
+                    evicted.pop_front();
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                evicted.push_back(state.into_record(oldest));
+            }
+        }
+
+        table.recency.insert(tick, key);
+        table.flows.insert(key, FlowState {
+            packets: 1,
+            bytes: packet.len() as u64,
+            tcp_flags,
+            start_ms: now_ms,
+            end_ms: now_ms,
+            first_seen: now,
+            last_seen: now,
+            tick,
+        });
+    }
+
+    /// Remove and return flows that are idle, too long-lived, or were evicted
+    pub async fn collect_finished(&self) -> Vec<FlowRecord> {
+        let mut finished: Vec<FlowRecord> = std::mem::take(&mut *self.evicted.lock().await).into();
+        let mut table = self.table.lock().await;
+
+        let expired: Vec<FlowKey> = table.flows.iter()
+            .filter(|(_, state)| {
+                state.last_seen.elapsed() >= self.config.idle_timeout
+                    || state.first_seen.elapsed() >= self.config.active_timeout
+            })
+            .map(|(key, _)| *key)
+            .collect();
+
+        for key in expired {
+            if let Some(state) = table.remove(&key) {
+                finished.push(state.into_record(key));
+            }
+        }
+        finished
+    }
+
+    /// Number of flows currently tracked
+    pub async fn active_flows(&self) -> usize {
+        self.table.lock().await.flows.len()
+    }
+
+    /// Evicted flow records dropped before they could be exported, since start
+    pub fn dropped_records(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Sends flow records to a collector as NetFlow v5
+#[derive(Debug)]
+pub struct FlowExporter {
+    /// Local UDP socket
+    socket: UdpSocket,
+    /// Collector address
+    collector: SocketAddr,
+    /// Exporter start time, the reference for NetFlow uptime fields
+    started: Instant,
+    /// Unix time at start in milliseconds
+    started_ms: u64,
+    /// Total flows exported so far
+    sequence: u32,
+}
+
+impl FlowExporter {
+    /// Create an exporter sending to `collector`
+    pub async fn new(collector: SocketAddr) -> std::io::Result<Self> {
+        let bind_addr: SocketAddr = if collector.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            (std::net::Ipv6Addr::UNSPECIFIED, 0).into()
+        };
+        Ok(Self {
+            socket: UdpSocket::bind(bind_addr).await?,
+            collector,
+            started: Instant::now(),
+            started_ms: utils::current_timestamp_millis(),
+            sequence: 0,
+        })
+    }
+
+    /// Export records, batching them into datagrams
+    pub async fn export(&mut self, records: &[FlowRecord]) {
+        for batch in records.chunks(NETFLOW_V5_MAX_RECORDS) {
+            let datagram = self.encode(batch);
+            if let Err(e) = self.socket.send_to(&datagram, self.collector).await {
+                warn!("Failed to export {} flow records to {}: {}", batch.len(), self.collector, e);
+            }
+            self.sequence = self.sequence.wrapping_add(batch.len() as u32);
+        }
+        if !records.is_empty() {
+            debug!("Exported {} flow records to {}", records.len(), self.collector);
+        }
+    }
+
+    /// Encode a batch of at most 30 records as a NetFlow v5 datagram
+    fn encode(&self, records: &[FlowRecord]) -> Vec<u8> {
+        let now_ms = utils::current_timestamp_millis();
+        let uptime_ms = self.started.elapsed().as_millis() as u32;
+        let to_uptime = |ms: u64| ms.saturating_sub(self.started_ms) as u32;
+
+        let mut buf = Vec::with_capacity(NETFLOW_V5_HEADER_SIZE + records.len() * NETFLOW_V5_RECORD_SIZE);
+        buf.extend_from_slice(&5u16.to_be_bytes());
+        buf.extend_from_slice(&(records.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&uptime_ms.to_be_bytes());
+        buf.extend_from_slice(&((now_ms / 1000) as u32).to_be_bytes());
+        buf.extend_from_slice(&(((now_ms % 1000) * 1_000_000) as u32).to_be_bytes());
+        buf.extend_from_slice(&self.sequence.to_be_bytes());
+        buf.extend_from_slice(&[0, 0]); // engine type, engine id
+        buf.extend_from_slice(&0u16.to_be_bytes()); // sampling interval
+
+        for record in records {
+            buf.extend_from_slice(&record.key.src_addr.octets());
+            buf.extend_from_slice(&record.key.dst_addr.octets());
+            buf.extend_from_slice(&[0; 4]); // next hop
+            buf.extend_from_slice(&[0; 4]); // input/output interface
+            buf.extend_from_slice(&(record.packets.min(u32::MAX as u64) as u32).to_be_bytes());
+            buf.extend_from_slice(&(record.bytes.min(u32::MAX as u64) as u32).to_be_bytes());
+            buf.extend_from_slice(&to_uptime(record.start_ms).to_be_bytes());
+            buf.extend_from_slice(&to_uptime(record.end_ms).to_be_bytes());
+            buf.extend_from_slice(&record.key.src_port.to_be_bytes());
+            buf.extend_from_slice(&record.key.dst_port.to_be_bytes());
+            buf.push(0); // pad
+            buf.push(record.tcp_flags);
+            buf.push(record.key.protocol);
+            buf.push(0); // tos
+            buf.extend_from_slice(&[0; 4]); // source/destination AS
+            buf.extend_from_slice(&[0; 2]); // source/destination mask
+            buf.extend_from_slice(&[0; 2]); // pad
+        }
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn udp_packet(src_port: u16, payload_len: usize) -> Vec<u8> {
+        let mut packet = vec![0u8; 28 + payload_len];
+        packet[0] = 0x45;
+        packet[9] = 17;
+        packet[12..16].copy_from_slice(&[10, 7, 0, 5]);
+        packet[16..20].copy_from_slice(&[1, 1, 1, 1]);
+        packet[20..22].copy_from_slice(&src_port.to_be_bytes());
+        packet[22..24].copy_from_slice(&53u16.to_be_bytes());
+        packet
+    }
+
+    #[tokio::test]
+    async fn test_flow_tracking_and_eviction() {
+        let tracker = FlowTracker::new(FlowTrackerConfig {
+            max_flows: 2,
+            idle_timeout: Duration::from_secs(60),
+            active_timeout: Duration::from_secs(600),
+        });
+
+        tracker.observe(&udp_packet(40000, 10)).await;
+        tracker.observe(&udp_packet(40000, 20)).await;
+        tracker.observe(&udp_packet(40001, 10)).await;
+        tracker.observe(&[0x60; 40]).await; // IPv6 is ignored
+        assert_eq!(tracker.active_flows().await, 2);
+
+        // A third flow evicts the least recently seen one
+        tracker.observe(&udp_packet(40002, 10)).await;
+        assert_eq!(tracker.active_flows().await, 2);
+
+        let finished = tracker.collect_finished().await;
+        assert_eq!(finished.len(), 1);
+        let record = &finished[0];
+        assert_eq!(record.key.src_addr, Ipv4Addr::new(10, 7, 0, 5));
+        assert_eq!(record.key.src_port, 40000);
+        assert_eq!(record.key.dst_port, 53);
+        assert_eq!(record.key.protocol, 17);
+        assert_eq!(record.packets, 2);
+        assert_eq!(record.bytes, 38 + 48);
+    }
+
+    #[tokio::test]
+    async fn test_eviction_follows_recency() {
+        let tracker = FlowTracker::new(FlowTrackerConfig {
+            max_flows: 3,
+            idle_timeout: Duration::from_secs(60),
+            active_timeout: Duration::from_secs(600),
+        });
+        for port in [1, 2, 3] {
+            tracker.observe(&udp_packet(port, 0)).await;
+        }
+        // Touching the oldest flow makes port 2 the least recently seen
+        tracker.observe(&udp_packet(1, 0)).await;
+
+        // New flows evict in recency order, keeping the index in step
+        for port in 100..103 {
+            tracker.observe(&udp_packet(port, 0)).await;
+        }
+        assert_eq!(tracker.active_flows().await, 3);
+        let evicted: Vec<u16> = tracker.collect_finished().await.iter().map(|r| r.key.src_port).collect();
+        assert_eq!(evicted, [2, 3, 1]);
+
+        // A flood between exports keeps only the newest evictions, counting the rest
+        for port in 200..210 {
+            tracker.observe(&udp_packet(port, 0)).await;
+        }
+        let evicted: Vec<u16> = tracker.collect_finished().await.iter().map(|r| r.key.src_port).collect();
+        assert_eq!(evicted, [204, 205, 206]);
+        assert_eq!(tracker.dropped_records(), 7);
+
+        let table = tracker.table.lock().await;
+        assert_eq!(table.recency.len(), table.flows.len());
+        assert!(table.recency.iter().all(|(tick, key)| table.flows[key].tick == *tick));
+    }
+
+    #[tokio::test]
+    async fn test_netflow_v5_export() {
+        let collector = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut exporter = FlowExporter::new(collector.local_addr().unwrap()).await.unwrap();
+
+        let tracker = FlowTracker::new(FlowTrackerConfig {
+            max_flows: 100,
+            idle_timeout: Duration::from_secs(0),
+            active_timeout: Duration::from_secs(600),
+        });
+        for port in 0..31 {
+            tracker.observe(&udp_packet(50000 + port, 0)).await;
+        }
+        let finished = tracker.collect_finished().await;
+        assert_eq!(finished.len(), 31);
+        exporter.export(&finished).await;
+
+        // 31 records need two datagrams
+        let mut buf = [0u8; 2048];
+        let len = collector.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf[0..2], &5u16.to_be_bytes());
+        assert_eq!(u16::from_be_bytes([buf[2], buf[3]]), 30);
+        assert_eq!(len, NETFLOW_V5_HEADER_SIZE + 30 * NETFLOW_V5_RECORD_SIZE);
+
+        let len = collector.recv(&mut buf).await.unwrap();
+        assert_eq!(u16::from_be_bytes([buf[2], buf[3]]), 1);
+        assert_eq!(u32::from_be_bytes([buf[16], buf[17], buf[18], buf[19]]), 30);
+        assert_eq!(len, NETFLOW_V5_HEADER_SIZE + NETFLOW_V5_RECORD_SIZE);
+    }
+}
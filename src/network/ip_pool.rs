@@ -5,15 +5,19 @@
 //! IP addresses for VPN clients.
 
 use ipnetwork::Ipv4Network;
-use std::collections::{HashMap, VecDeque};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::net::Ipv4Addr;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use thiserror::Error;
-use tokio::sync::Mutex;
-use tracing::{debug, info, warn};
+use tokio::sync::{Mutex, MutexGuard};
+use tracing::{debug, error, info, warn};
 
+use crate::config::constants::MIN_REQUESTED_LEASE_SECS;
+use crate::network::lease_store::{LeaseStore, PersistedLease};
 use crate::utils;
 
 /// Error type for IP pool operations
@@ -31,6 +35,18 @@ pub enum IpPoolError {
     #[error("IP already allocated: {0}")]
     AlreadyAllocated(String),
     
+    #[error("Invalid IP reservation: {0}")]
+    InvalidReservation(String),
+    
+    #[error("IP lease lost: {0}")]
+    LeaseLost(String),
+    
+    #[error("Lease persistence error: {0}")]
+    Persistence(String),
+    
+    #[error("IP pool inconsistency: {0}")]
+    Inconsistent(String),
+    
     #[error("Network error: {0}")]
     Network(String),
 }
@@ -44,8 +60,40 @@ pub struct IpAllocation {
     pub client_id: String,
     /// Expiration timestamp (milliseconds since epoch)
     pub expires_at: u64,
+    /// Lease length in seconds granted at allocation, reused by renewals
+    pub lease_duration_secs: u64,
     /// Is this a static allocation
     pub is_static: bool,
+    /// Session ended; held for the client until `expires_at`
+    pub held: bool,
+}
+
+/// Number of dynamic leases by time remaining until expiry
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LeaseExpiryBuckets {
+    /// Already expired but not yet reclaimed
+    pub expired: usize,
+    /// Expiring within the next minute
+    pub within_1m: usize,
+    /// Expiring in 1 to 5 minutes
+    pub within_5m: usize,
+    /// Expiring in 5 minutes to 1 hour
+    pub within_1h: usize,
+    /// Expiring in more than 1 hour
+    pub later: usize,
+}
+
+impl LeaseExpiryBuckets {
+    /// Count one lease with the given milliseconds remaining
+    fn add(&mut self, remaining_ms: u64) {
+        match remaining_ms {
+            0 => self.expired += 1,
+            1..=60_000 => self.within_1m += 1,
+            60_001..=300_000 => self.within_5m += 1,
+            300_001..=3_600_000 => self.within_1h += 1,
+            _ => self.later += 1,
+        }
+    }
 }
 
 /// IP address pool manager
@@ -59,6 +107,18 @@ pub struct IpPoolManager {
     subnet: Ipv4Network,
     /// Default lease duration in seconds
     default_lease_duration: u64,
+    /// Longest lease a client may request or renew, in seconds
+    max_lease_duration: u64,
+    /// How long a dynamic IP is held for its client after the session ends
+    release_grace: Duration,
+    /// Fixed addresses reserved for specific clients (client ID -> IP)
+    reservations: HashMap<String, String>,
+    /// Reserved addresses, kept out of the dynamic pool
+    reserved_ips: HashSet<String>,
+    /// Where the lease table is saved across restarts (None = not persisted)
+    lease_store: Option<Arc<dyn LeaseStore>>,
+    /// Lease table changed since it was last saved
+    leases_dirty: AtomicBool,
 }
 
 impl IpPoolManager {
@@ -76,63 +136,245 @@ impl IpPoolManager {
             allocated_ips: Arc::new(Mutex::new(HashMap::new())),
             subnet: network,
             default_lease_duration,
+            max_lease_duration: default_lease_duration,
+            release_grace: Duration::ZERO,
+            reservations: HashMap::new(),
+            reserved_ips: HashSet::new(),
+            lease_store: None,
+            leases_dirty: AtomicBool::new(false),
         })
     }
     
-    /// Check if a client already has an allocation
-    async fn get_existing_allocation(&self, client_id: &str) -> Option<String> {
-        let allocated = self.allocated_ips.lock().await;
-        for (ip, allocation) in allocated.iter() {
-            if allocation.client_id == client_id {
-                return Some(ip.clone());
+    /// Hold IPs for their client for `grace` after the session ends, instead of
+    /// returning them to the pool immediately
+    pub fn with_release_grace(mut self, grace: Duration) -> Self {
+        self.release_grace = grace;
+        self
+    }
+    
+    /// Reserve fixed addresses for specific clients (client ID -> IP).
+    ///
+    /// Reserved addresses are removed from the dynamic pool so they are never
+    /// handed to another client. Must be called before the pool is shared.
+    pub fn with_reservations(mut self, reservations: HashMap<String, String>) -> Result<Self, IpPoolError> {
+        let mut reserved_ips = HashSet::new();
+        for (client_id, ip) in &reservations {
+            let addr = Ipv4Addr::from_str(ip)
+                .map_err(|e| IpPoolError::InvalidReservation(format!("{} for {}: {}", ip, client_id, e)))?;
+            let server_ip = Ipv4Addr::from(u32::from(self.subnet.network()) + 1);
+            if !self.subnet.contains(addr) || addr == self.subnet.network()
+                || addr == self.subnet.broadcast() || addr == server_ip {
+                return Err(IpPoolError::InvalidReservation(format!(
+                    "{} for {} is not a usable address in subnet {}", ip, client_id, self.subnet
+                )));
+            }
+            if !reserved_ips.insert(addr.to_string()) {
+                return Err(IpPoolError::InvalidReservation(format!("{} is reserved more than once", ip)));
             }
         }
-        None
+        
+        Arc::get_mut(&mut self.available_ips)
+            .expect("IP reservations must be set before the pool is shared")
+            .get_mut()
+            .retain(|ip| !reserved_ips.contains(ip));
+        if !reservations.is_empty() {
+            info!("Reserved {} static IP addresses", reservations.len());
+        }
+        self.reservations = reservations;
+        self.reserved_ips = reserved_ips;
+        Ok(self)
     }
     
-    /// Create a new allocation with specified lease duration
-    async fn create_allocation(&self, client_id: &str, lease_duration_secs: u64) -> Result<String, IpPoolError> {
-        // Allocate from the dynamic pool
-        let mut available = self.available_ips.lock().await;
-        let ip = available.pop_front().ok_or(IpPoolError::PoolExhausted)?;
-        
+    /// Cap lease requests and renewals at `max_lease_secs`; 0 keeps the default lease
+    pub fn with_max_lease_duration(mut self, max_lease_secs: u64) -> Self {
+        if max_lease_secs > 0 {
+            self.max_lease_duration = max_lease_secs;
+        }
+        self
+    }
+    
+    /// Save the lease table to `store` so leases survive restarts
+    pub fn with_lease_store(mut self, store: Arc<dyn LeaseStore>) -> Self {
+        self.lease_store = Some(store);
+        self
+    }
+    
+    /// Reload leases saved by a previous run, pruning expired ones.
+    ///
+    /// Dynamic leases come back held for their client, as if its session had
+    /// just ended, so the client gets its IP back on reconnect until the lease
+    /// expires. Returns the number of leases restored.
+    pub async fn restore_leases(&self) -> Result<usize, IpPoolError> {
+        let Some(store) = &self.lease_store else {
+            return Ok(0);
+        };
+        let leases = store.load().await?;
         let now = utils::current_timestamp_millis();
-        let expires_at = now + (lease_duration_secs * 1000);
         
-        let allocation = IpAllocation {
-            ip_address: ip.clone(),
-            client_id: client_id.to_string(),
-            expires_at,
-            is_static: false,
+        let mut available = self.available_ips.lock().await;
+        let mut allocated = self.allocated_ips.lock().await;
+        let mut restored = 0;
+        for lease in leases {
+            if !lease.is_static && lease.expires_at <= now {
+                continue;
+            }
+            let in_subnet = Ipv4Addr::from_str(&lease.ip_address)
+                .map(|addr| self.subnet.contains(addr))
+                .unwrap_or(false);
+            if !in_subnet || allocated.contains_key(&lease.ip_address) {
+                warn!("Dropping saved lease of IP {} for client {}", lease.ip_address, lease.client_id);
+                continue;
+            }
+            
+            // Renewals keep the length the client was granted, within today's bounds
+            let lease_duration_secs = match lease.lease_duration_secs {
+                0 => self.default_lease_duration,
+                secs => self.effective_lease(Some(secs)),
+            };
+            available.retain(|ip| *ip != lease.ip_address);
+            allocated.insert(lease.ip_address.clone(), IpAllocation {
+                ip_address: lease.ip_address,
+                client_id: lease.client_id,
+                expires_at: lease.expires_at,
+                lease_duration_secs,
+                is_static: lease.is_static,
+                held: !lease.is_static,
+            });
+            restored += 1;
+        }
+        
+        if restored > 0 {
+            info!("Restored {} saved IP leases", restored);
+        }
+        Ok(restored)
+    }
+    
+    /// Save the lease table now
+    pub async fn persist_leases(&self) -> Result<(), IpPoolError> {
+        let Some(store) = &self.lease_store else {
+            return Ok(());
         };
+        self.leases_dirty.store(false, Ordering::SeqCst);
+        let leases: Vec<PersistedLease> = self.allocated_ips.lock().await.values()
+            .map(|allocation| PersistedLease {
+                client_id: allocation.client_id.clone(),
+                ip_address: allocation.ip_address.clone(),
+                expires_at: allocation.expires_at,
+                is_static: allocation.is_static,
+                lease_duration_secs: allocation.lease_duration_secs,
+            })
+            .collect();
+        let result = store.save(&leases).await;
+        if result.is_err() {
+            // Retry on the next flush
+            self.leases_dirty.store(true, Ordering::SeqCst);
+        }
+        result
+    }
+    
+    /// Save the lease table if it changed since the last save
+    pub async fn persist_leases_if_changed(&self) -> Result<(), IpPoolError> {
+        if !self.leases_dirty.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        self.persist_leases().await
+    }
+    
+    /// Note a change to the lease table for the next save
+    fn leases_changed(&self) {
+        self.leases_dirty.store(true, Ordering::SeqCst);
+    }
+    
+    /// Lock the free pool and the lease table together.
+    ///
+    /// Every path that touches both takes them through here, in this order,
+    /// so concurrent allocate/release/renew calls neither deadlock nor see a
+    /// half-applied change.
+    async fn lock_tables(&self) -> (MutexGuard<'_, VecDeque<String>>, MutexGuard<'_, HashMap<String, IpAllocation>>) {
+        let available = self.available_ips.lock().await;
+        let allocated = self.allocated_ips.lock().await;
+        (available, allocated)
+    }
+    
+    /// Allocate an IP for a client with the given lease duration.
+    ///
+    /// In order: the client's existing allocation (reclaimed with a fresh
+    /// lease if it is being held after a previous session), its reserved
+    /// address unless another client holds it, then the dynamic pool. The
+    /// whole decision is made under both locks, so two callers can never be
+    /// handed the same address.
+    async fn allocate(&self, client_id: &str, lease_duration_secs: u64) -> Result<String, IpPoolError> {
+        let (mut available, mut allocated) = self.lock_tables().await;
+        let expires_at = utils::current_timestamp_millis() + (lease_duration_secs * 1000);
         
-        let mut allocated = self.allocated_ips.lock().await;
-        allocated.insert(ip.clone(), allocation);
+        if let Some(allocation) = allocated.values_mut().find(|a| a.client_id == client_id) {
+            if allocation.held {
+                allocation.held = false;
+                allocation.expires_at = expires_at;
+                allocation.lease_duration_secs = lease_duration_secs;
+                debug!("Client {} reclaimed held IP {}", client_id, allocation.ip_address);
+                self.leases_changed();
+            }
+            return Ok(allocation.ip_address.clone());
+        }
+        
+        if let Some(ip) = self.reservations.get(client_id) {
+            match allocated.get(ip) {
+                Some(existing) => warn!("Reserved IP {} for client {} is in use by {}, allocating dynamically",
+                                        ip, client_id, existing.client_id),
+                None => {
+                    allocated.insert(ip.clone(), IpAllocation {
+                        ip_address: ip.clone(),
+                        client_id: client_id.to_string(),
+                        expires_at,
+                        lease_duration_secs,
+                        is_static: false,
+                        held: false,
+                    });
+                    self.leases_changed();
+                    debug_assert_eq!(self.inconsistency(&available, &allocated), None);
+                    debug!("Allocated reserved IP {} to client {}", ip, client_id);
+                    return Ok(ip.clone());
+                }
+            }
+        }
+        
+        let ip = available.pop_front().ok_or(IpPoolError::PoolExhausted)?;
+        match allocated.entry(ip.clone()) {
+            Entry::Occupied(entry) => {
+                // The free pool and the lease table disagree. The address stays
+                // with its current holder rather than being handed out twice.
+                error!("IP {} was in the free pool while allocated to client {}", ip, entry.get().client_id);
+                return Err(IpPoolError::AlreadyAllocated(format!(
+                    "IP {} is already allocated to client {}", ip, entry.get().client_id
+                )));
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(IpAllocation {
+                    ip_address: ip.clone(),
+                    client_id: client_id.to_string(),
+                    expires_at,
+                    lease_duration_secs,
+                    is_static: false,
+                    held: false,
+                });
+            }
+        }
+        self.leases_changed();
+        debug_assert_eq!(self.inconsistency(&available, &allocated), None);
         
         debug!("Allocated IP {} to client {} with lease {}s", ip, client_id, lease_duration_secs);
         Ok(ip)
     }
     
-    /// Allocate an IP address
-    pub async fn allocate_ip(&self, client_id: &str) -> Result<String, IpPoolError> {
-        // First check if this client already has an allocation
-        if let Some(ip) = self.get_existing_allocation(client_id).await {
-            return Ok(ip);
-        }
-        
-        // Create a new allocation with the default lease duration
-        self.create_allocation(client_id, self.default_lease_duration).await
+    /// Allocate an IP address, with the client's requested lease if it gave one
+    pub async fn allocate_ip(&self, client_id: &str, requested_lease_secs: Option<u64>) -> Result<String, IpPoolError> {
+        self.allocate(client_id, self.effective_lease(requested_lease_secs)).await
     }
     
-    /// Allocate an IP address with a specific lease duration
+    /// Allocate an IP address with a specific lease duration, bypassing the maximum
     pub async fn allocate_ip_with_lease(&self, client_id: &str, lease_duration_secs: u64) -> Result<String, IpPoolError> {
-        // First check if this client already has an allocation
-        if let Some(ip) = self.get_existing_allocation(client_id).await {
-            return Ok(ip);
-        }
-        
-        // Create a new allocation with the specified lease duration
-        self.create_allocation(client_id, lease_duration_secs).await
+        self.allocate(client_id, lease_duration_secs).await
     }
     
     /// Get the default lease duration
@@ -140,42 +382,117 @@ impl IpPoolManager {
         Duration::from_secs(self.default_lease_duration)
     }
     
+    /// Get the longest lease a client may request
+    pub fn get_max_lease_duration(&self) -> Duration {
+        Duration::from_secs(self.max_lease_duration)
+    }
+    
+    /// Lease in seconds granted for a client request: the default when none
+    /// was asked for, otherwise the request clamped to the pool's bounds
+    pub fn effective_lease(&self, requested_lease_secs: Option<u64>) -> u64 {
+        requested_lease_secs
+            .map_or(self.default_lease_duration, |secs| secs.max(MIN_REQUESTED_LEASE_SECS))
+            .min(self.max_lease_duration)
+    }
+    
     /// Release an IP address
     pub async fn release_ip(&self, ip: &str) -> Result<(), IpPoolError> {
-        let mut allocated = self.allocated_ips.lock().await;
+        let (mut available, mut allocated) = self.lock_tables().await;
+        self.release_locked(&mut available, &mut allocated, ip)
+    }
+    
+    /// Release an IP with both tables already locked
+    fn release_locked(
+        &self,
+        available: &mut VecDeque<String>,
+        allocated: &mut HashMap<String, IpAllocation>,
+        ip: &str,
+    ) -> Result<(), IpPoolError> {
+        let allocation = allocated.remove(ip).ok_or_else(|| IpPoolError::NotAllocated(ip.to_string()))?;
+        self.leases_changed();
+        if self.reserved_ips.contains(ip) {
+            // Stays reserved rather than returning to the dynamic pool
+            debug!("Released reserved IP {} (previously allocated to {})", ip, allocation.client_id);
+        } else if !allocation.is_static {
+            available.push_back(ip.to_string());
+            debug!("Released IP {} (previously allocated to {})", ip, allocation.client_id);
+        }
+        debug_assert_eq!(self.inconsistency(available, allocated), None);
+        Ok(())
+    }
+    
+    /// Release an IP after its session ends.
+    ///
+    /// With a release grace period, a dynamic IP stays allocated to its client
+    /// (and out of the free pool) until the grace expires, so a client that
+    /// reconnects in time gets the same IP back. Expired holds are returned
+    /// to the pool by `cleanup_expired`.
+    pub async fn release_ip_after_session(&self, ip: &str) -> Result<(), IpPoolError> {
+        if self.release_grace.is_zero() {
+            return self.release_ip(ip).await;
+        }
         
-        if let Some(allocation) = allocated.remove(ip) {
-            if !allocation.is_static {
-                let mut available = self.available_ips.lock().await;
-                available.push_back(ip.to_string());
-                debug!("Released IP {} (previously allocated to {})", ip, allocation.client_id);
+        let (mut available, mut allocated) = self.lock_tables().await;
+        match allocated.get_mut(ip) {
+            Some(allocation) if !allocation.is_static => {
+                allocation.held = true;
+                allocation.expires_at = utils::current_timestamp_millis() + self.release_grace.as_millis() as u64;
+                self.leases_changed();
+                debug!("Holding IP {} for client {} for {:?}", ip, allocation.client_id, self.release_grace);
+                Ok(())
             }
-            Ok(())
-        } else {
-            Err(IpPoolError::NotAllocated(ip.to_string()))
+            Some(_) => self.release_locked(&mut available, &mut allocated, ip),
+            None => Err(IpPoolError::NotAllocated(ip.to_string())),
         }
     }
     
-    /// Renew an IP lease with a specific duration
-    pub async fn renew_ip_with_lease(&self, ip: &str, lease_duration_secs: u64) -> Result<u64, IpPoolError> {
+    /// Renew a client's IP lease with a specific duration.
+    ///
+    /// The pool's allocation is authoritative: renewal fails with `LeaseLost`
+    /// if the IP has been reclaimed, reassigned, or released and is only
+    /// held for reconnection, in which case the client must request a new IP.
+    pub async fn renew_ip_with_lease(&self, ip: &str, client_id: &str, lease_duration_secs: u64) -> Result<u64, IpPoolError> {
+        self.renew(ip, client_id, Some(lease_duration_secs)).await
+    }
+    
+    /// Renew a lease for `lease_duration_secs`, or for the allocation's own
+    /// lease clamped to the maximum when None
+    async fn renew(&self, ip: &str, client_id: &str, lease_duration_secs: Option<u64>) -> Result<u64, IpPoolError> {
         let mut allocated = self.allocated_ips.lock().await;
         
-        if let Some(allocation) = allocated.get_mut(ip) {
-            let now = utils::current_timestamp_millis();
-            let expires_at = now + (lease_duration_secs * 1000);
-            allocation.expires_at = expires_at;
-            
-            debug!("Renewed IP {} lease for client {} with duration {}s", 
-                  ip, allocation.client_id, lease_duration_secs);
-            Ok(expires_at)
-        } else {
-            Err(IpPoolError::NotAllocated(ip.to_string()))
+        match allocated.get_mut(ip) {
+            // A session for this client ended and released the IP
+            Some(allocation) if allocation.client_id == client_id && allocation.held => Err(IpPoolError::LeaseLost(format!(
+                "IP {} has been released", ip
+            ))),
+            Some(allocation) if allocation.client_id == client_id => {
+                let now = utils::current_timestamp_millis();
+                if allocation.expires_at < now {
+                    // Still ours since cleanup has not reclaimed it yet
+                    debug!("Renewing expired but unreclaimed IP {} lease for client {}", ip, client_id);
+                }
+                let lease_duration_secs = lease_duration_secs
+                    .unwrap_or_else(|| allocation.lease_duration_secs.min(self.max_lease_duration));
+                let expires_at = now + (lease_duration_secs * 1000);
+                allocation.expires_at = expires_at;
+                self.leases_changed();
+                
+                debug!("Renewed IP {} lease for client {} with duration {}s", 
+                      ip, allocation.client_id, lease_duration_secs);
+                Ok(expires_at)
+            }
+            Some(_) => Err(IpPoolError::LeaseLost(format!(
+                "IP {} has been reassigned to another client", ip
+            ))),
+            None => Err(IpPoolError::LeaseLost(format!(
+                "IP {} has been reclaimed", ip
+            ))),
         }
     }
     
-    /// Renew an IP lease with the default duration
-    pub async fn renew_ip(&self, ip: &str) -> Result<u64, IpPoolError> {
-        self.renew_ip_with_lease(ip, self.default_lease_duration).await
+    /// Renew a client's IP lease for the duration it was granted
+    pub async fn renew_ip(&self, ip: &str, client_id: &str) -> Result<u64, IpPoolError> {
+        self.renew(ip, client_id, None).await
     }
     
     /// Assign a static IP
@@ -190,48 +507,41 @@ impl IpPoolManager {
             )));
         }
         
-        // First, try to find the IP in the allocated pool
-        {
-            let mut allocated = self.allocated_ips.lock().await;
-            
-            // Check if IP is already allocated
-            if let Some(allocation) = allocated.get(ip) {
-                if allocation.client_id != client_id {
-                    return Err(IpPoolError::AlreadyAllocated(format!(
-                        "IP {} is already allocated to client {}", 
-                        ip, allocation.client_id
-                    )));
-                }
-                
-                // Already allocated to this client, just make it static
-                let mut allocation = allocation.clone();
-                allocation.is_static = true;
-                allocated.insert(ip.to_string(), allocation);
-                
-                debug!("Changed IP {} allocation for client {} to static", ip, client_id);
-                return Ok(());
+        let (mut available, mut allocated) = self.lock_tables().await;
+        
+        // Check if IP is already allocated
+        if let Some(allocation) = allocated.get_mut(ip) {
+            if allocation.client_id != client_id {
+                return Err(IpPoolError::AlreadyAllocated(format!(
+                    "IP {} is already allocated to client {}", 
+                    ip, allocation.client_id
+                )));
             }
+            
+            // Already allocated to this client, just make it static
+            allocation.is_static = true;
+            self.leases_changed();
+            
+            debug!("Changed IP {} allocation for client {} to static", ip, client_id);
+            return Ok(());
         }
         
         // Remove from available pool if present
-        {
-            let mut available = self.available_ips.lock().await;
-            let index = available.iter().position(|available_ip| available_ip == ip);
-            if let Some(idx) = index {
-                available.remove(idx);
-            }
-        }
+        available.retain(|available_ip| available_ip != ip);
         
         // Create a static allocation
         let allocation = IpAllocation {
             ip_address: ip.to_string(),
             client_id: client_id.to_string(),
             expires_at: u64::MAX, // Never expires
+            lease_duration_secs: self.default_lease_duration,
             is_static: true,
+            held: false,
         };
         
-        let mut allocated = self.allocated_ips.lock().await;
         allocated.insert(ip.to_string(), allocation);
+        self.leases_changed();
+        debug_assert_eq!(self.inconsistency(&available, &allocated), None);
         
         info!("Assigned static IP {} to client {}", ip, client_id);
         Ok(())
@@ -240,21 +550,16 @@ impl IpPoolManager {
     /// Clean up expired allocations
     pub async fn cleanup_expired(&self) -> Vec<String> {
         let now = utils::current_timestamp_millis();
-        let mut to_release = Vec::<String>::new();
-        
-        // Find expired allocations
-        {
-            let allocated = self.allocated_ips.lock().await;
-            for (ip, allocation) in allocated.iter() {
-                if !allocation.is_static && allocation.expires_at < now {
-                    to_release.push(ip.clone());
-                }
-            }
-        }
         
-        // Release expired IPs
+        // Find and release expired allocations in one pass, so a lease renewed
+        // concurrently is never released after the fact
+        let (mut available, mut allocated) = self.lock_tables().await;
+        let to_release: Vec<String> = allocated.values()
+            .filter(|allocation| !allocation.is_static && allocation.expires_at < now)
+            .map(|allocation| allocation.ip_address.clone())
+            .collect();
         for ip in &to_release {
-            if let Err(e) = self.release_ip(ip).await {
+            if let Err(e) = self.release_locked(&mut available, &mut allocated, ip) {
                 warn!("Error releasing expired IP {}: {}", ip, e);
             }
         }
@@ -277,6 +582,27 @@ impl IpPoolManager {
         None
     }
     
+    /// Time remaining on a client's dynamic lease (None for unknown clients and static IPs)
+    pub async fn lease_remaining(&self, client_id: &str) -> Option<Duration> {
+        let allocation = self.get_client_allocation(client_id).await?;
+        if allocation.is_static {
+            return None;
+        }
+        let now = utils::current_timestamp_millis();
+        Some(Duration::from_millis(allocation.expires_at.saturating_sub(now)))
+    }
+    
+    /// Distribution of dynamic lease expirations, for anticipating renewal bursts
+    pub async fn lease_expiry_distribution(&self) -> LeaseExpiryBuckets {
+        let now = utils::current_timestamp_millis();
+        let allocated = self.allocated_ips.lock().await;
+        let mut buckets = LeaseExpiryBuckets::default();
+        for allocation in allocated.values().filter(|a| !a.is_static) {
+            buckets.add(allocation.expires_at.saturating_sub(now));
+        }
+        buckets
+    }
+    
     /// Get client IP
     pub async fn get_client_ip(&self, client_id: &str) -> Option<String> {
         let allocated = self.allocated_ips.lock().await;
@@ -294,6 +620,13 @@ impl IpPoolManager {
         allocated.get(ip).map(|a| a.client_id.clone())
     }
     
+    /// Number of IPs held for clients whose sessions have ended.
+    /// Held IPs are counted as allocated in `get_stats`.
+    pub async fn held_count(&self) -> usize {
+        let allocated = self.allocated_ips.lock().await;
+        allocated.values().filter(|a| a.held).count()
+    }
+    
     /// Get pool statistics
     pub async fn get_stats(&self) -> (usize, usize, usize) {
         let available = self.available_ips.lock().await;
@@ -308,6 +641,16 @@ impl IpPoolManager {
         (available_count, allocated_count, static_count)
     }
     
+    /// Fraction of the pool in use, from 0.0 to 1.0 (held IPs count as in use)
+    pub async fn utilization(&self) -> f32 {
+        let (available, allocated, _) = self.get_stats().await;
+        let total = available + allocated;
+        if total == 0 {
+            return 1.0;
+        }
+        allocated as f32 / total as f32
+    }
+    
     /// Get network details
     pub async fn get_network_details(&self) -> (String, String, String) {
         (
@@ -322,6 +665,37 @@ impl IpPoolManager {
         let allocated = self.allocated_ips.lock().await;
         allocated.values().cloned().collect()
     }
+    
+    /// Check the pool invariants: every address is either free or allocated
+    /// once, never both, and reserved addresses never sit in the free pool.
+    /// Cheap enough to run periodically; debug builds also check after every change.
+    pub async fn verify_consistency(&self) -> Result<(), IpPoolError> {
+        let (available, allocated) = self.lock_tables().await;
+        match self.inconsistency(&available, &allocated) {
+            Some(problem) => Err(IpPoolError::Inconsistent(problem)),
+            None => Ok(()),
+        }
+    }
+    
+    /// First invariant violation found in the locked tables, if any
+    fn inconsistency(&self, available: &VecDeque<String>, allocated: &HashMap<String, IpAllocation>) -> Option<String> {
+        if let Some((ip, allocation)) = allocated.iter().find(|(ip, a)| **ip != a.ip_address) {
+            return Some(format!("IP {} is recorded under {}", allocation.ip_address, ip));
+        }
+        let mut free = HashSet::with_capacity(available.len());
+        for ip in available {
+            if !free.insert(ip) {
+                return Some(format!("IP {} is in the free pool more than once", ip));
+            }
+            if let Some(allocation) = allocated.get(ip) {
+                return Some(format!("IP {} is free and allocated to client {}", ip, allocation.client_id));
+            }
+            if self.reserved_ips.contains(ip) {
+                return Some(format!("Reserved IP {} is in the free pool", ip));
+            }
+        }
+        None
+    }
 }
 
 /// Generate IP pool from CIDR subnet
@@ -358,6 +732,23 @@ fn generate_ip_pool(network: &Ipv4Network) -> Result<VecDeque<String>, IpPoolErr
     Ok(pool)
 }
 
+/// Parse IP reservations given as "PUBLIC_KEY=IP"
+pub fn parse_reservations(entries: &[String]) -> Result<HashMap<String, String>, IpPoolError> {
+    let mut reservations = HashMap::new();
+    for entry in entries {
+        let (client_id, ip) = entry.split_once('=')
+            .map(|(key, ip)| (key.trim(), ip.trim()))
+            .filter(|(key, ip)| !key.is_empty() && !ip.is_empty())
+            .ok_or_else(|| IpPoolError::InvalidReservation(format!("expected PUBLIC_KEY=IP, got {:?}", entry)))?;
+        Ipv4Addr::from_str(ip)
+            .map_err(|e| IpPoolError::InvalidReservation(format!("{}: {}", entry, e)))?;
+        if reservations.insert(client_id.to_string(), ip.to_string()).is_some() {
+            return Err(IpPoolError::InvalidReservation(format!("{} has more than one reservation", client_id)));
+        }
+    }
+    Ok(reservations)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -368,7 +759,7 @@ mod tests {
         
         // Allocate an IP
         let client1 = "client1";
-        let ip1 = pool_manager.allocate_ip(client1).await.unwrap();
+        let ip1 = pool_manager.allocate_ip(client1, None).await.unwrap();
         
         // Should be 192.168.1.2 or higher (skipping .0 and .1)
         assert!(ip1.starts_with("192.168.1."));
@@ -377,7 +768,7 @@ mod tests {
         
         // Allocate another IP
         let client2 = "client2";
-        let ip2 = pool_manager.allocate_ip(client2).await.unwrap();
+        let ip2 = pool_manager.allocate_ip(client2, None).await.unwrap();
         
         // Should be different
         assert_ne!(ip1, ip2);
@@ -414,7 +805,7 @@ mod tests {
         assert!(allocation.is_static);
         
         // Try to allocate for the same client
-        let ip = pool_manager.allocate_ip(client).await.unwrap();
+        let ip = pool_manager.allocate_ip(client, None).await.unwrap();
         
         // Should get the same static IP
         assert_eq!(ip, static_ip);
@@ -432,7 +823,7 @@ mod tests {
         
         // Allocate IP with short lease
         let client = "temp-client";
-        let ip = pool_manager.allocate_ip(client).await.unwrap();
+        let ip = pool_manager.allocate_ip(client, None).await.unwrap();
         
         // Get original expiration
         let allocation = pool_manager.get_client_allocation(client).await.unwrap();
@@ -442,7 +833,7 @@ mod tests {
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
         
         // Renew with longer lease
-        let new_expiry = pool_manager.renew_ip_with_lease(&ip, 60).await.unwrap();
+        let new_expiry = pool_manager.renew_ip_with_lease(&ip, client, 60).await.unwrap();
         
         // Should have a later expiration time
         assert!(new_expiry > original_expiry);
@@ -452,6 +843,75 @@ mod tests {
         assert_eq!(allocation.expires_at, new_expiry);
     }
     
+    #[tokio::test]
+    async fn test_requested_lease_clamped() {
+        let pool_manager = IpPoolManager::new("172.16.0.0/24", 86400).await.unwrap()
+            .with_max_lease_duration(3600);
+        assert_eq!(pool_manager.get_max_lease_duration(), Duration::from_secs(3600));
+        
+        // Below, at, and above the maximum; no request gets the default, clamped too
+        for (client, requested, granted) in [
+            ("below", Some(600), 600),
+            ("at", Some(3600), 3600),
+            ("above", Some(7200), 3600),
+            ("tiny", Some(1), MIN_REQUESTED_LEASE_SECS),
+            ("default", None, 3600),
+        ] {
+            let ip = pool_manager.allocate_ip(client, requested).await.unwrap();
+            let allocation = pool_manager.get_client_allocation(client).await.unwrap();
+            assert_eq!(allocation.lease_duration_secs, granted, "{}", client);
+            let remaining = pool_manager.lease_remaining(client).await.unwrap();
+            assert!(remaining <= Duration::from_secs(granted) && remaining > Duration::from_secs(granted - 10));
+            
+            // Renewal keeps the granted lease
+            let before = utils::current_timestamp_millis();
+            let expires_at = pool_manager.renew_ip(&ip, client).await.unwrap();
+            assert!(expires_at >= before + granted * 1000 && expires_at <= before + granted * 1000 + 5000);
+        }
+        
+        // Without a configured maximum the default lease is the cap
+        let uncapped = IpPoolManager::new("172.16.1.0/24", 1800).await.unwrap().with_max_lease_duration(0);
+        assert_eq!(uncapped.effective_lease(Some(7200)), 1800);
+        assert_eq!(uncapped.effective_lease(Some(900)), 900);
+    }
+    
+    #[tokio::test]
+    async fn test_renewal_after_lease_reclaimed() {
+        let pool_manager = IpPoolManager::new("172.16.0.0/29", 0).await.unwrap();
+        
+        // Lease expires immediately and is reclaimed
+        let ip = pool_manager.allocate_ip("client-a", None).await.unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(5)).await;
+        assert_eq!(pool_manager.cleanup_expired().await, vec![ip.clone()]);
+        
+        // Reclaimed and not yet reassigned
+        assert!(matches!(
+            pool_manager.renew_ip(&ip, "client-a").await,
+            Err(IpPoolError::LeaseLost(_))
+        ));
+        
+        // Reassigned to another client once the pool cycles back to the same IP
+        let mut reassigned = None;
+        for i in 0..4 {
+            let client = format!("client-{}", i);
+            if let Ok(other_ip) = pool_manager.allocate_ip(&client, None).await {
+                if other_ip == ip {
+                    reassigned = Some(client);
+                    break;
+                }
+            }
+        }
+        let new_owner = reassigned.expect("reclaimed IP should be reassigned");
+        assert!(matches!(
+            pool_manager.renew_ip(&ip, "client-a").await,
+            Err(IpPoolError::LeaseLost(_))
+        ));
+        
+        // The new owner's lease is untouched and still renewable
+        assert_eq!(pool_manager.get_ip_client(&ip).await.unwrap(), new_owner);
+        assert!(pool_manager.renew_ip_with_lease(&ip, &new_owner, 60).await.is_ok());
+    }
+    
     #[tokio::test]
     async fn test_subnet_validation() {
         // Valid subnet
@@ -463,4 +923,247 @@ mod tests {
         // Subnet too small (e.g., single IP)
         assert!(IpPoolManager::new("192.168.1.1/32", 3600).await.is_err());
     }
+    
+    #[tokio::test]
+    async fn test_lease_expiry_distribution() {
+        let pool_manager = IpPoolManager::new("10.9.0.0/24", 3600).await.unwrap();
+        
+        for (client, lease_secs) in [("a", 30), ("b", 45), ("c", 240), ("d", 1800), ("e", 7200)] {
+            pool_manager.allocate_ip_with_lease(client, lease_secs).await.unwrap();
+        }
+        pool_manager.assign_static_ip("10.9.0.100", "static").await.unwrap();
+        
+        let buckets = pool_manager.lease_expiry_distribution().await;
+        assert_eq!(buckets, LeaseExpiryBuckets {
+            expired: 0,
+            within_1m: 2,
+            within_5m: 1,
+            within_1h: 1,
+            later: 1,
+        });
+        
+        let remaining = pool_manager.lease_remaining("c").await.unwrap();
+        assert!(remaining <= Duration::from_secs(240) && remaining > Duration::from_secs(230));
+        assert!(pool_manager.lease_remaining("static").await.is_none());
+        assert!(pool_manager.lease_remaining("unknown").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_release_grace_hold() {
+        // /29 leaves 4 dynamic IPs
+        let pool = IpPoolManager::new("10.9.0.0/29", 3600).await.unwrap()
+            .with_release_grace(Duration::from_millis(200));
+        let ip = pool.allocate_ip("client", None).await.unwrap();
+        let (available, _, _) = pool.get_stats().await;
+
+        // Held after the session ends: not returned to the pool or given to others
+        pool.release_ip_after_session(&ip).await.unwrap();
+        assert_eq!(pool.held_count().await, 1);
+        assert_eq!(pool.get_stats().await.0, available);
+        assert_ne!(pool.allocate_ip("other", None).await.unwrap(), ip);
+        assert!(pool.cleanup_expired().await.is_empty());
+
+        // The same client reclaims it with a fresh lease
+        assert_eq!(pool.allocate_ip("client", None).await.unwrap(), ip);
+        assert_eq!(pool.held_count().await, 0);
+        assert!(pool.lease_remaining("client").await.unwrap() > Duration::from_secs(3000));
+
+        // Unclaimed holds are returned to the pool by the sweeper
+        pool.release_ip_after_session(&ip).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(pool.cleanup_expired().await, vec![ip.clone()]);
+        assert_eq!(pool.held_count().await, 0);
+        assert!(pool.get_client_ip("client").await.is_none());
+
+        // Without a grace period the IP is released immediately
+        let pool = IpPoolManager::new("10.9.0.0/29", 3600).await.unwrap();
+        let ip = pool.allocate_ip("client", None).await.unwrap();
+        pool.release_ip_after_session(&ip).await.unwrap();
+        assert!(pool.get_client_ip("client").await.is_none());
+    }
+
+    fn reservations(entries: &[&str]) -> HashMap<String, String> {
+        parse_reservations(&entries.iter().map(|e| e.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reserved_allocation() {
+        // /29 leaves 4 dynamic IPs, one of which is reserved
+        let pool = IpPoolManager::new("10.9.0.0/29", 3600).await.unwrap()
+            .with_reservations(reservations(&["gateway=10.9.0.3"])).unwrap();
+        assert_eq!(pool.get_stats().await.0, 3);
+
+        // The reserved address is never handed out dynamically
+        let mut dynamic = Vec::new();
+        for i in 0..3 {
+            dynamic.push(pool.allocate_ip(&format!("client{}", i), None).await.unwrap());
+        }
+        assert!(!dynamic.contains(&"10.9.0.3".to_string()));
+        assert!(matches!(pool.allocate_ip("client3", None).await, Err(IpPoolError::PoolExhausted)));
+
+        // Its client gets it even with the pool exhausted
+        assert_eq!(pool.allocate_ip("gateway", None).await.unwrap(), "10.9.0.3");
+        assert_eq!(pool.allocate_ip("gateway", None).await.unwrap(), "10.9.0.3");
+
+        // Addresses outside the usable range and duplicates are rejected
+        for bad in [&["gw=10.9.1.3"][..], &["gw=10.9.0.1"], &["gw=10.9.0.7"], &["a=10.9.0.3", "b=10.9.0.3"]] {
+            let result = IpPoolManager::new("10.9.0.0/29", 3600).await.unwrap().with_reservations(reservations(bad));
+            assert!(matches!(result, Err(IpPoolError::InvalidReservation(_))), "{:?}", bad);
+        }
+        assert!(parse_reservations(&["no-separator".to_string()]).is_err());
+        assert!(parse_reservations(&["gw=not-an-ip".to_string()]).is_err());
+        assert!(parse_reservations(&["gw=10.9.0.3".to_string(), "gw=10.9.0.4".to_string()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reservation_conflict() {
+        let pool = IpPoolManager::new("10.9.0.0/29", 3600).await.unwrap()
+            .with_reservations(reservations(&["gateway=10.9.0.3"])).unwrap();
+
+        // An explicit static assignment took the reserved address
+        pool.assign_static_ip("10.9.0.3", "other").await.unwrap();
+
+        // The reserved client falls back to the dynamic pool
+        let ip = pool.allocate_ip("gateway", None).await.unwrap();
+        assert_ne!(ip, "10.9.0.3");
+        assert_eq!(pool.get_ip_client("10.9.0.3").await.unwrap(), "other");
+    }
+
+    #[tokio::test]
+    async fn test_reserved_release() {
+        let pool = IpPoolManager::new("10.9.0.0/29", 1).await.unwrap()
+            .with_reservations(reservations(&["gateway=10.9.0.3"])).unwrap();
+        let ip = pool.allocate_ip("gateway", None).await.unwrap();
+        let (available, _, _) = pool.get_stats().await;
+
+        // Released reserved IPs go back to the reservation, not the free pool
+        pool.release_ip(&ip).await.unwrap();
+        assert_eq!(pool.get_stats().await.0, available);
+        assert!(pool.get_ip_client(&ip).await.is_none());
+        for i in 0..3 {
+            assert_ne!(pool.allocate_ip(&format!("client{}", i), None).await.unwrap(), ip);
+        }
+        assert_eq!(pool.allocate_ip("gateway", None).await.unwrap(), ip);
+
+        // The same applies when the lease expires
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert_eq!(pool.cleanup_expired().await.len(), 4);
+        // Only the three dynamic leases return to the pool
+        assert_eq!(pool.get_stats().await.0, 3);
+        assert_eq!(pool.allocate_ip("gateway", None).await.unwrap(), ip);
+    }
+
+    #[tokio::test]
+    async fn test_lease_persistence_round_trip() {
+        use crate::network::lease_store::FileLeaseStore;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(FileLeaseStore::new(dir.path().join("leases.json")));
+        let pool = IpPoolManager::new("10.9.0.0/29", 3600).await.unwrap()
+            .with_lease_store(store.clone());
+        assert_eq!(pool.restore_leases().await.unwrap(), 0);
+
+        let dynamic = pool.allocate_ip("client", Some(600)).await.unwrap();
+        pool.assign_static_ip("10.9.0.5", "node").await.unwrap();
+        pool.persist_leases_if_changed().await.unwrap();
+
+        // An expired lease in the saved table is pruned on reload
+        let mut saved = store.load().await.unwrap();
+        assert_eq!(saved.len(), 2);
+        saved.push(PersistedLease {
+            client_id: "stale".to_string(),
+            ip_address: "10.9.0.4".to_string(),
+            expires_at: utils::current_timestamp_millis() - 1,
+            is_static: false,
+            lease_duration_secs: 3600,
+        });
+        store.save(&saved).await.unwrap();
+
+        // A restarted pool gives clients their leases back
+        let pool = IpPoolManager::new("10.9.0.0/29", 3600).await.unwrap()
+            .with_lease_store(store.clone());
+        assert_eq!(pool.restore_leases().await.unwrap(), 2);
+        assert_eq!(pool.held_count().await, 1);
+        assert!(pool.get_ip_client("10.9.0.4").await.is_none());
+        assert_eq!(pool.get_stats().await, (2, 2, 1));
+        assert_ne!(pool.allocate_ip("other", None).await.unwrap(), dynamic);
+        assert_eq!(pool.allocate_ip("client", None).await.unwrap(), dynamic);
+        assert_eq!(pool.allocate_ip("node", None).await.unwrap(), "10.9.0.5");
+        assert!(pool.get_client_allocation("node").await.unwrap().is_static);
+        // The granted lease length survives the restart
+        assert_eq!(pool.get_client_allocation("client").await.unwrap().lease_duration_secs, 600);
+
+        // Tables saved before lease lengths were recorded restore with the default
+        let legacy: PersistedLease = serde_json::from_str(
+            r#"{"client_id":"client","ip_address":"10.9.0.2","expires_at":18446744073709551615,"is_static":false}"#,
+        ).unwrap();
+        assert_eq!(legacy.lease_duration_secs, 0);
+        store.save(&[legacy]).await.unwrap();
+        let pool = IpPoolManager::new("10.9.0.0/29", 3600).await.unwrap()
+            .with_lease_store(store);
+        assert_eq!(pool.restore_leases().await.unwrap(), 1);
+        assert_eq!(pool.get_client_allocation("client").await.unwrap().lease_duration_secs, 3600);
+    }
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_allocation_never_duplicates() {
+        let pool = Arc::new(IpPoolManager::new("10.9.0.0/24", 3600).await.unwrap()
+            .with_release_grace(Duration::from_secs(60)));
+        let (pool_size, _, _) = pool.get_stats().await;
+        let in_use = Arc::new(std::sync::Mutex::new(HashMap::<String, String>::new()));
+        
+        let mut tasks = Vec::new();
+        for worker in 0..16 {
+            let pool = pool.clone();
+            let in_use = in_use.clone();
+            tasks.push(tokio::spawn(async move {
+                for round in 0..200 {
+                    let client = format!("client-{}-{}", worker, round % 5);
+                    let ip = match pool.allocate_ip(&client, None).await {
+                        Ok(ip) => ip,
+                        Err(IpPoolError::PoolExhausted) => continue,
+                        Err(e) => panic!("allocation failed: {}", e),
+                    };
+                    if let Some(other) = in_use.lock().unwrap().insert(ip.clone(), client.clone()) {
+                        assert_eq!(other, client, "IP {} handed to two clients", ip);
+                    }
+                    pool.renew_ip(&ip, &client).await.unwrap();
+                    tokio::task::yield_now().await;
+                    in_use.lock().unwrap().remove(&ip);
+                    let result = if round % 2 == 0 {
+                        pool.release_ip(&ip).await
+                    } else {
+                        pool.release_ip_after_session(&ip).await
+                    };
+                    result.unwrap();
+                }
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+        
+        pool.verify_consistency().await.unwrap();
+        let owners: HashSet<String> = pool.get_allocations().await.into_iter().map(|a| a.client_id).collect();
+        let (available, allocated, _) = pool.get_stats().await;
+        assert_eq!(owners.len(), allocated);
+        assert_eq!(available + allocated, pool_size);
+    }
+    
+    #[tokio::test]
+    async fn test_duplicate_free_ip_is_refused() {
+        let pool = IpPoolManager::new("10.10.0.0/24", 3600).await.unwrap();
+        let ip = pool.allocate_ip("client1", None).await.unwrap();
+        pool.verify_consistency().await.unwrap();
+        
+        // Corrupt the free pool with an address that is already leased
+        pool.available_ips.lock().await.push_front(ip.clone());
+        assert!(matches!(pool.verify_consistency().await, Err(IpPoolError::Inconsistent(_))));
+        
+        // The next allocation refuses to hand it out again
+        assert!(matches!(pool.allocate_ip("client2", None).await, Err(IpPoolError::AlreadyAllocated(_))));
+        assert_eq!(pool.get_ip_client(&ip).await.as_deref(), Some("client1"));
+        pool.verify_consistency().await.unwrap();
+        assert_ne!(pool.allocate_ip("client2", None).await.unwrap(), ip);
+    }
 }
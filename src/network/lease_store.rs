@@ -0,0 +1,78 @@
+// src/network/lease_store.rs
+//! Persistence for IP leases.
+//!
+//! The IP pool saves its lease table through a `LeaseStore` so clients keep
+//! their addresses across server restarts. `FileLeaseStore` keeps the table
+//! in a local JSON file; other backends implement the same trait.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+
+use crate::network::ip_pool::IpPoolError;
+
+/// One lease as persisted by a `LeaseStore`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersistedLease {
+    /// Client public key
+    pub client_id: String,
+    /// Leased IP address
+    pub ip_address: String,
+    /// Expiration timestamp (milliseconds since epoch)
+    pub expires_at: u64,
+    /// Static allocation, never expires
+    pub is_static: bool,
+    /// Lease length granted to the client in seconds (0 = saved before this
+    /// was recorded; the pool default applies)
+    #[serde(default)]
+    pub lease_duration_secs: u64,
+}
+
+/// Storage backend for the IP lease table
+#[async_trait]
+pub trait LeaseStore: Debug + Send + Sync {
+    /// Load the most recently saved lease table
+    async fn load(&self) -> Result<Vec<PersistedLease>, IpPoolError>;
+
+    /// Replace the saved lease table
+    async fn save(&self, leases: &[PersistedLease]) -> Result<(), IpPoolError>;
+}
+
+/// Lease table kept in a local JSON file
+#[derive(Debug)]
+pub struct FileLeaseStore {
+    path: PathBuf,
+}
+
+impl FileLeaseStore {
+    /// Store leases in `path`; a missing file means no saved leases
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self { path: path.as_ref().to_path_buf() }
+    }
+}
+
+#[async_trait]
+impl LeaseStore for FileLeaseStore {
+    async fn load(&self) -> Result<Vec<PersistedLease>, IpPoolError> {
+        let json = match tokio::fs::read_to_string(&self.path).await {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(IpPoolError::Persistence(format!("{}: {}", self.path.display(), e))),
+        };
+        serde_json::from_str(&json)
+            .map_err(|e| IpPoolError::Persistence(format!("{}: {}", self.path.display(), e)))
+    }
+
+    async fn save(&self, leases: &[PersistedLease]) -> Result<(), IpPoolError> {
+        let json = serde_json::to_string_pretty(leases)
+            .map_err(|e| IpPoolError::Persistence(e.to_string()))?;
+
+        // Write to a temporary file first so a crash never leaves a truncated table
+        let temp_path = self.path.with_extension("tmp");
+        tokio::fs::write(&temp_path, json).await
+            .map_err(|e| IpPoolError::Persistence(format!("{}: {}", temp_path.display(), e)))?;
+        tokio::fs::rename(&temp_path, &self.path).await
+            .map_err(|e| IpPoolError::Persistence(format!("{}: {}", self.path.display(), e)))
+    }
+}
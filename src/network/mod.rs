@@ -5,8 +5,14 @@
 //! IP pools, packet routing, and network monitoring.
 
 pub mod ip_pool;
+pub mod lease_store;
 pub mod tun;
 pub mod monitor;
+pub mod flows;
+pub mod tun_queue;
+pub mod mss;
+pub mod traffic_export;
+pub mod origin;
 
 // Re-export commonly used items
 // Removed IpAllocation, NetworkStats if not used outside this module
@@ -0,0 +1,263 @@
+// src/network/mss.rs
+//! TCP MSS clamping for tunnel traffic.
+//!
+//! Tunneled TCP connections negotiate their segment size from the MSS
+//! option in the SYN. When the endpoints' links are larger than the
+//! tunnel, full-sized segments never fit and the connection stalls
+//! unless path MTU discovery works end to end. Rewriting the option on
+//! SYNs crossing the tunnel keeps segments within the tunnel MTU.
+
+/// TCP option kinds
+const TCP_OPT_END: u8 = 0;
+const TCP_OPT_NOP: u8 = 1;
+const TCP_OPT_MSS: u8 = 2;
+
+/// TCP SYN flag
+const TCP_FLAG_SYN: u8 = 0x02;
+
+/// Largest MSS that fits an MTU for the given IP version (no IP or TCP options)
+pub fn mss_for_mtu(mtu: u16, ip_version: u8) -> u16 {
+    let overhead = if ip_version == 6 { 60 } else { 40 };
+    mtu.saturating_sub(overhead)
+}
+
+/// Lower the MSS option of a TCP SYN to fit `mtu`, updating the TCP checksum.
+///
+/// Returns true if the packet was rewritten. Non-TCP packets, non-SYN
+/// segments, IPv4 fragments and IPv6 packets with extension headers are
+/// left untouched, as are SYNs without an MSS option or whose MSS
+/// already fits.
+pub fn clamp_tcp_mss(packet: &mut [u8], mtu: u16) -> bool {
+    let (version, tcp_start, tcp_end) = match tcp_segment_bounds(packet) {
+        Some(bounds) => bounds,
+        None => return false,
+    };
+    let tcp = &packet[tcp_start..tcp_end];
+    if tcp.len() < 20 || tcp[13] & TCP_FLAG_SYN == 0 {
+        return false;
+    }
+    let data_offset = usize::from(tcp[12] >> 4) * 4;
+    if data_offset < 20 || data_offset > tcp.len() {
+        return false;
+    }
+
+    let max_mss = mss_for_mtu(mtu, version);
+    let mss_offset = match find_mss_option(&tcp[20..data_offset]) {
+        Some(offset) => tcp_start + 20 + offset,
+        None => return false,
+    };
+    let current = u16::from_be_bytes([packet[mss_offset], packet[mss_offset + 1]]);
+    if current <= max_mss {
+        return false;
+    }
+
+    packet[mss_offset..mss_offset + 2].copy_from_slice(&max_mss.to_be_bytes());
+    packet[tcp_start + 16..tcp_start + 18].fill(0);
+    let checksum = tcp_checksum(packet, version, tcp_start, tcp_end);
+    packet[tcp_start + 16..tcp_start + 18].copy_from_slice(&checksum.to_be_bytes());
+    true
+}
+
+/// IP version and TCP segment range of a packet carrying an unfragmented TCP segment
+fn tcp_segment_bounds(packet: &[u8]) -> Option<(u8, usize, usize)> {
+    match packet.first()? >> 4 {
+        4 => {
+            let header_len = usize::from(packet[0] & 0x0f) * 4;
+            if header_len < 20 || packet.len() < header_len || packet[9] != 6 {
+                return None;
+            }
+            // Only the first fragment has a TCP header, and its checksum covers the whole segment
+            let flags_offset = u16::from_be_bytes([packet[6], packet[7]]);
+            if flags_offset & 0x3fff != 0 {
+                return None;
+            }
+            let total_len = usize::from(u16::from_be_bytes([packet[2], packet[3]]));
+            (total_len >= header_len && total_len <= packet.len()).then_some((4, header_len, total_len))
+        }
+        6 => {
+            if packet.len() < 40 || packet[6] != 6 {
+                return None;
+            }
+            let total_len = 40 + usize::from(u16::from_be_bytes([packet[4], packet[5]]));
+            (total_len <= packet.len()).then_some((6, 40, total_len))
+        }
+        _ => None,
+    }
+}
+
+/// Offset of the MSS value within a TCP options block
+fn find_mss_option(options: &[u8]) -> Option<usize> {
+    let mut i = 0;
+    while i < options.len() {
+        match options[i] {
+            TCP_OPT_END => return None,
+            TCP_OPT_NOP => i += 1,
+            kind => {
+                let len = usize::from(*options.get(i + 1)?);
+                if len < 2 || i + len > options.len() {
+                    return None;
+                }
+                if kind == TCP_OPT_MSS {
+                    return (len == 4).then_some(i + 2);
+                }
+                i += len;
+            }
+        }
+    }
+    None
+}
+
+/// Internet checksum of a TCP segment including the IP pseudo-header
+fn tcp_checksum(packet: &[u8], version: u8, tcp_start: usize, tcp_end: usize) -> u16 {
+    let segment_len = (tcp_end - tcp_start) as u32;
+    let mut sum: u32 = 0;
+    if version == 4 {
+        sum += ones_complement_sum(&packet[12..20]);
+        sum += 6 + segment_len;
+    } else {
+        sum += ones_complement_sum(&packet[8..40]);
+        sum += (segment_len >> 16) + (segment_len & 0xffff) + 6;
+    }
+    sum += ones_complement_sum(&packet[tcp_start..tcp_end]);
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Sum of big-endian 16-bit words, padding an odd trailing byte with zero
+fn ones_complement_sum(data: &[u8]) -> u32 {
+    let mut sum: u32 = 0;
+    for chunk in data.chunks(2) {
+        let word = match chunk {
+            [hi, lo] => u16::from_be_bytes([*hi, *lo]),
+            [hi] => u16::from_be_bytes([*hi, 0]),
+            _ => unreachable!(),
+        };
+        sum += u32::from(word);
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// IPv4 TCP SYN from 10.7.0.5:40000 to 93.184.216.34:443 with MSS 1460,
+    /// SACK permitted, timestamps and window scale
+    const IPV4_SYN: [u8; 60] = [
+        0x45, 0x00, 0x00, 0x3c, 0x1c, 0x46, 0x40, 0x00, 0x40, 0x06, 0x00, 0x00,
+        10, 7, 0, 5, 93, 184, 216, 34,
+        0x9c, 0x40, 0x01, 0xbb, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+        0xa0, 0x02, 0xfa, 0xf0, 0x6d, 0x28, 0x00, 0x00,
+        0x02, 0x04, 0x05, 0xb4, 0x04, 0x02, 0x08, 0x0a, 0x00, 0x01, 0x02, 0x03,
+        0x00, 0x00, 0x00, 0x00, 0x01, 0x03, 0x03, 0x07,
+    ];
+
+    fn with_valid_checksum(mut packet: Vec<u8>, version: u8, tcp_start: usize) -> Vec<u8> {
+        let tcp_end = packet.len();
+        packet[tcp_start + 16..tcp_start + 18].fill(0);
+        let checksum = tcp_checksum(&packet, version, tcp_start, tcp_end);
+        packet[tcp_start + 16..tcp_start + 18].copy_from_slice(&checksum.to_be_bytes());
+        packet
+    }
+
+    /// A segment with a correct checksum sums to 0xffff with its pseudo-header
+    fn checksum_ok(packet: &[u8], version: u8, tcp_start: usize) -> bool {
+        tcp_checksum(packet, version, tcp_start, packet.len()) == 0
+    }
+
+    fn ipv6_syn(mss: u16) -> Vec<u8> {
+        let mut packet = vec![0x60, 0, 0, 0, 0x00, 0x18, 6, 64];
+        packet.extend_from_slice(&[0xfd, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 5]);
+        packet.extend_from_slice(&[0x26, 0x06, 0x28, 0, 0x02, 0x20, 0, 1, 0x02, 0x48, 0x18, 0x93, 0x25, 0xc8, 0x19, 0x46]);
+        packet.extend_from_slice(&[0x9c, 0x41, 0x01, 0xbb, 0, 0, 0, 7, 0, 0, 0, 0, 0x60, 0x02, 0xff, 0xff, 0, 0, 0, 0]);
+        packet.extend_from_slice(&[0x02, 0x04]);
+        packet.extend_from_slice(&mss.to_be_bytes());
+        with_valid_checksum(packet, 6, 40)
+    }
+
+    #[test]
+    fn test_clamp_ipv4_syn() {
+        let original = IPV4_SYN.to_vec();
+        assert!(checksum_ok(&original, 4, 20));
+
+        let mut packet = original.clone();
+        assert!(clamp_tcp_mss(&mut packet, 1400));
+        assert_eq!(u16::from_be_bytes([packet[42], packet[43]]), 1360);
+        assert_eq!(packet[36..38], [0x6d, 0x8c]);
+        assert!(checksum_ok(&packet, 4, 20));
+        // Only the MSS value and checksum change
+        let changed: Vec<usize> = (0..packet.len()).filter(|&i| packet[i] != original[i]).collect();
+        assert!(changed.iter().all(|&i| (36..38).contains(&i) || (42..44).contains(&i)));
+
+        // An MSS that already fits is left alone
+        let mut fits = original.clone();
+        assert!(!clamp_tcp_mss(&mut fits, 1500));
+        assert_eq!(fits, original);
+    }
+
+    #[test]
+    fn test_clamp_odd_aligned_mss() {
+        // NOP before MSS puts the value at an odd offset; MSS value 1460 at bytes 43..45
+        let mut packet = IPV4_SYN.to_vec();
+        packet[40..60].copy_from_slice(&[
+            0x01, 0x02, 0x04, 0x05, 0xb4, 0x01, 0x01, 0x01, 0x04, 0x02,
+            0x08, 0x0a, 0x00, 0x01, 0x02, 0x03, 0x00, 0x00, 0x00, 0x00,
+        ]);
+        let mut packet = with_valid_checksum(packet, 4, 20);
+
+        assert!(clamp_tcp_mss(&mut packet, 1280));
+        assert_eq!(u16::from_be_bytes([packet[43], packet[44]]), 1240);
+        assert!(checksum_ok(&packet, 4, 20));
+    }
+
+    #[test]
+    fn test_clamp_ipv6_syn() {
+        let mut packet = ipv6_syn(1440);
+        assert!(checksum_ok(&packet, 6, 40));
+
+        assert!(clamp_tcp_mss(&mut packet, 1280));
+        assert_eq!(u16::from_be_bytes([packet[62], packet[63]]), 1220);
+        assert!(checksum_ok(&packet, 6, 40));
+    }
+
+    #[test]
+    fn test_non_syn_and_malformed_untouched() {
+        // ACK without SYN
+        let mut ack = IPV4_SYN.to_vec();
+        ack[33] = 0x10;
+        let original = ack.clone();
+        assert!(!clamp_tcp_mss(&mut ack, 1280));
+        assert_eq!(ack, original);
+
+        // UDP
+        let mut udp = IPV4_SYN.to_vec();
+        udp[9] = 17;
+        assert!(!clamp_tcp_mss(&mut udp, 1280));
+
+        // Non-initial fragment
+        let mut fragment = IPV4_SYN.to_vec();
+        fragment[6..8].copy_from_slice(&[0x00, 0x10]);
+        assert!(!clamp_tcp_mss(&mut fragment, 1280));
+
+        // Truncated packets and bad option lengths are skipped without panicking
+        for len in [0, 1, 20, 39, 44] {
+            let mut short = IPV4_SYN[..len].to_vec();
+            assert!(!clamp_tcp_mss(&mut short, 1280));
+        }
+        let mut bad_option = IPV4_SYN.to_vec();
+        bad_option[41] = 30;
+        assert!(!clamp_tcp_mss(&mut bad_option, 1280));
+        let mut zero_option = IPV4_SYN.to_vec();
+        zero_option[40..42].copy_from_slice(&[0x08, 0x00]);
+        assert!(!clamp_tcp_mss(&mut zero_option, 1280));
+    }
+
+    #[test]
+    fn test_mss_for_mtu() {
+        assert_eq!(mss_for_mtu(1500, 4), 1460);
+        assert_eq!(mss_for_mtu(1500, 6), 1440);
+        assert_eq!(mss_for_mtu(20, 4), 0);
+    }
+}
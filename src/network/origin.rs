@@ -0,0 +1,50 @@
+// src/network/origin.rs
+//! Connection origin tagging.
+//!
+//! An `OriginProvider` maps a client's address to the network it comes
+//! from (country and autonomous system). The server asks once per
+//! connection, after the WebSocket upgrade, and keeps the answer on the
+//! session for metrics and the admin API. Lookups run on the connection's
+//! task, so providers backed by a remote service should cache.
+//!
+//! The default `NoopOriginProvider` knows nothing, and sessions then carry
+//! no origin at all.
+
+use serde::Serialize;
+use std::fmt::Debug;
+use std::net::SocketAddr;
+
+/// Source network of a connection
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize)]
+pub struct ConnectionOrigin {
+    /// ISO 3166-1 alpha-2 country code
+    pub country: Option<String>,
+    /// Autonomous system number
+    pub asn: Option<u32>,
+}
+
+impl ConnectionOrigin {
+    /// Metric label values as (country, asn), `unknown` where missing
+    pub fn labels(&self) -> (String, String) {
+        (
+            self.country.clone().unwrap_or_else(|| "unknown".to_string()),
+            self.asn.map_or_else(|| "unknown".to_string(), |asn| asn.to_string()),
+        )
+    }
+}
+
+/// Looks up where a connection comes from
+pub trait OriginProvider: Debug + Send + Sync {
+    /// Origin of `addr`, or None when it is not known
+    fn lookup(&self, addr: SocketAddr) -> Option<ConnectionOrigin>;
+}
+
+/// Provider that never knows an origin
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopOriginProvider;
+
+impl OriginProvider for NoopOriginProvider {
+    fn lookup(&self, _addr: SocketAddr) -> Option<ConnectionOrigin> {
+        None
+    }
+}
@@ -0,0 +1,262 @@
+// src/network/traffic_export.rs
+//! Per-client traffic export to a time-series backend.
+//!
+//! `NetworkMonitor` keeps cumulative byte counters per client. The exporter
+//! samples them on an interval, turns them into deltas since the previous
+//! sample and hands the batch to a `TrafficSink`. Sampling only clones the
+//! monitor's counter map, and all encoding and sending happens on the
+//! exporter's own task, so the packet path never waits on the backend.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::net::SocketAddr;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, warn};
+
+use crate::config::settings::TrafficExportFormat;
+use crate::network::NetworkMonitor;
+use crate::utils;
+
+/// Keep datagrams under a typical path MTU
+const MAX_DATAGRAM_SIZE: usize = 1400;
+/// Metric prefix for StatsD, measurement name for InfluxDB
+const METRIC_NAME: &str = "aeronyx_client_traffic";
+
+/// Bytes a client moved since the previous sample
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrafficSample {
+    /// Client identifier (public key)
+    pub client_id: String,
+    /// Bytes sent to the client in this interval
+    pub bytes_sent: u64,
+    /// Bytes received from the client in this interval
+    pub bytes_received: u64,
+    /// When the sample was taken (Unix milliseconds)
+    pub timestamp_ms: u64,
+}
+
+/// Destination for per-client traffic samples
+#[async_trait]
+pub trait TrafficSink: Debug + Send + Sync {
+    /// Emit one interval's samples. Failures are the sink's to report.
+    async fn emit(&self, samples: &[TrafficSample]);
+}
+
+/// Sends samples as StatsD or InfluxDB line protocol over UDP
+#[derive(Debug)]
+pub struct UdpLineSink {
+    socket: UdpSocket,
+    target: SocketAddr,
+    format: TrafficExportFormat,
+}
+
+impl UdpLineSink {
+    /// Bind an ephemeral socket for sending to `target`
+    pub async fn new(target: SocketAddr, format: TrafficExportFormat) -> std::io::Result<Self> {
+        let bind_addr: SocketAddr = if target.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            (std::net::Ipv6Addr::UNSPECIFIED, 0).into()
+        };
+        Ok(Self {
+            socket: UdpSocket::bind(bind_addr).await?,
+            target,
+            format,
+        })
+    }
+
+    /// Encode one sample as protocol lines
+    fn encode(&self, sample: &TrafficSample) -> String {
+        match self.format {
+            TrafficExportFormat::Statsd => format!(
+                "{}.{}.bytes_sent:{}|c\n{}.{}.bytes_received:{}|c\n",
+                METRIC_NAME, sample.client_id, sample.bytes_sent,
+                METRIC_NAME, sample.client_id, sample.bytes_received,
+            ),
+            TrafficExportFormat::Influx => format!(
+                "{},client={} bytes_sent={}u,bytes_received={}u {}\n",
+                METRIC_NAME, sample.client_id, sample.bytes_sent, sample.bytes_received,
+                sample.timestamp_ms * 1_000_000,
+            ),
+        }
+    }
+
+    /// Pack lines into datagrams, never splitting a line
+    fn datagrams(&self, samples: &[TrafficSample]) -> Vec<String> {
+        let mut datagrams = Vec::new();
+        let mut current = String::new();
+        for sample in samples {
+            let lines = self.encode(sample);
+            if !current.is_empty() && current.len() + lines.len() > MAX_DATAGRAM_SIZE {
+                datagrams.push(std::mem::take(&mut current));
+            }
+            current.push_str(&lines);
+        }
+        if !current.is_empty() {
+            datagrams.push(current);
+        }
+        datagrams
+    }
+}
+
+#[async_trait]
+impl TrafficSink for UdpLineSink {
+    async fn emit(&self, samples: &[TrafficSample]) {
+        for datagram in self.datagrams(samples) {
+            if let Err(e) = self.socket.send_to(datagram.as_bytes(), self.target).await {
+                warn!("Failed to export client traffic to {}: {}", self.target, e);
+            }
+        }
+    }
+}
+
+/// Forwards each batch of samples to a channel
+#[derive(Debug)]
+pub struct ChannelTrafficSink {
+    tx: UnboundedSender<Vec<TrafficSample>>,
+}
+
+impl ChannelTrafficSink {
+    /// Send batches to `tx`; batches are dropped once the receiver is gone
+    pub fn new(tx: UnboundedSender<Vec<TrafficSample>>) -> Self {
+        Self { tx }
+    }
+}
+
+#[async_trait]
+impl TrafficSink for ChannelTrafficSink {
+    async fn emit(&self, samples: &[TrafficSample]) {
+        let _ = self.tx.send(samples.to_vec());
+    }
+}
+
+/// Samples per-client counters and emits the deltas
+#[derive(Debug)]
+pub struct TrafficExporter {
+    sink: Box<dyn TrafficSink>,
+    /// Cumulative (sent, received) per client at the previous sample
+    last_totals: HashMap<String, (u64, u64)>,
+}
+
+impl TrafficExporter {
+    /// Create an exporter writing to `sink`
+    pub fn new(sink: Box<dyn TrafficSink>) -> Self {
+        Self {
+            sink,
+            last_totals: HashMap::new(),
+        }
+    }
+
+    /// Compute deltas since the previous sample. Idle clients are left out.
+    pub async fn sample(&mut self, monitor: &NetworkMonitor) -> Vec<TrafficSample> {
+        let timestamp_ms = utils::current_timestamp_millis();
+        let totals: HashMap<String, (u64, u64)> = monitor
+            .get_all_client_stats()
+            .await
+            .into_iter()
+            .map(|(client_id, stats)| (client_id, (stats.stats.bytes_sent, stats.stats.bytes_received)))
+            .collect();
+
+        let mut samples = Vec::new();
+        for (client_id, &(sent, received)) in &totals {
+            let (last_sent, last_received) = self.last_totals.get(client_id).copied().unwrap_or((0, 0));
+            // Counters that went backwards were reset; count from zero
+            let bytes_sent = if sent >= last_sent { sent - last_sent } else { sent };
+            let bytes_received = if received >= last_received { received - last_received } else { received };
+            if bytes_sent > 0 || bytes_received > 0 {
+                samples.push(TrafficSample {
+                    client_id: client_id.clone(),
+                    bytes_sent,
+                    bytes_received,
+                    timestamp_ms,
+                });
+            }
+        }
+        samples.sort_by(|a, b| a.client_id.cmp(&b.client_id));
+
+        // Clients the monitor forgot are forgotten here too
+        self.last_totals = totals;
+        samples
+    }
+
+    /// Sample the monitor and emit any deltas
+    pub async fn export(&mut self, monitor: &NetworkMonitor) {
+        let samples = self.sample(monitor).await;
+        if !samples.is_empty() {
+            self.sink.emit(&samples).await;
+            debug!("Exported traffic for {} clients", samples.len());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_periodic_traffic_deltas() {
+        let monitor = NetworkMonitor::new(Duration::from_secs(60), 10);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut exporter = TrafficExporter::new(Box::new(ChannelTrafficSink::new(tx)));
+
+        monitor.record_client_traffic("alice", 100, 40).await;
+        monitor.record_client_traffic("bob", 0, 10).await;
+        exporter.export(&monitor).await;
+        let batch = rx.try_recv().unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!((batch[0].client_id.as_str(), batch[0].bytes_sent, batch[0].bytes_received), ("alice", 100, 40));
+        assert_eq!((batch[1].client_id.as_str(), batch[1].bytes_sent, batch[1].bytes_received), ("bob", 0, 10));
+
+        // Only the change since the last interval, idle clients omitted
+        monitor.record_client_traffic("alice", 25, 0).await;
+        monitor.record_client_traffic("alice", 5, 2).await;
+        exporter.export(&monitor).await;
+        let batch = rx.try_recv().unwrap();
+        assert_eq!(batch.len(), 1);
+        assert_eq!((batch[0].client_id.as_str(), batch[0].bytes_sent, batch[0].bytes_received), ("alice", 30, 2));
+
+        // Nothing moved, nothing emitted
+        exporter.export(&monitor).await;
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_udp_line_formats() {
+        let collector = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let target = collector.local_addr().unwrap();
+        let sample = TrafficSample {
+            client_id: "alice".to_string(),
+            bytes_sent: 100,
+            bytes_received: 40,
+            timestamp_ms: 1_700_000_000_000,
+        };
+        let mut buf = [0u8; 2048];
+
+        let statsd = UdpLineSink::new(target, TrafficExportFormat::Statsd).await.unwrap();
+        statsd.emit(&[sample.clone()]).await;
+        let len = collector.recv(&mut buf).await.unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf[..len]).unwrap(),
+            "aeronyx_client_traffic.alice.bytes_sent:100|c\naeronyx_client_traffic.alice.bytes_received:40|c\n"
+        );
+
+        let influx = UdpLineSink::new(target, TrafficExportFormat::Influx).await.unwrap();
+        influx.emit(&[sample.clone()]).await;
+        let len = collector.recv(&mut buf).await.unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf[..len]).unwrap(),
+            "aeronyx_client_traffic,client=alice bytes_sent=100u,bytes_received=40u 1700000000000000000\n"
+        );
+
+        // Large batches split on line boundaries
+        let many: Vec<TrafficSample> = (0..50).map(|_| sample.clone()).collect();
+        let datagrams = influx.datagrams(&many);
+        assert!(datagrams.len() > 1);
+        assert!(datagrams.iter().all(|d| d.len() <= MAX_DATAGRAM_SIZE && d.ends_with('\n')));
+        assert_eq!(datagrams.iter().map(|d| d.lines().count()).sum::<usize>(), 50);
+    }
+}
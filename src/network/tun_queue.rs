@@ -0,0 +1,148 @@
+// src/network/tun_queue.rs
+//! Bounded queue of decrypted packets waiting for the TUN device.
+//!
+//! Client sessions hand inner packets to the queue and a single writer
+//! task drains it into the device, so a slow TUN device (or a slow network
+//! behind it) costs dropped packets instead of unbounded buffering in the
+//! session tasks. When the queue is full, the configured policy decides
+//! whether the arriving packet or the oldest queued one is discarded.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::Arc;
+use tokio::sync::{Mutex, Notify};
+use tokio::task::JoinHandle;
+use tracing::{debug, error};
+
+use crate::config::settings::TunQueuePolicy;
+
+/// Result of offering a packet to the queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Enqueue {
+    /// The packet was queued
+    Queued,
+    /// The packet was queued after dropping the oldest queued packet
+    DroppedOldest,
+    /// The queue was full and the packet was dropped
+    Full,
+}
+
+/// Bounded FIFO between the packet router and the TUN device
+#[derive(Debug)]
+pub struct TunWriteQueue {
+    /// Maximum queued packets
+    capacity: usize,
+    /// Drop policy when full
+    policy: TunQueuePolicy,
+    /// Queued packets, oldest first
+    packets: Mutex<VecDeque<Vec<u8>>>,
+    /// Wakes the writer when a packet is queued
+    ready: Notify,
+}
+
+impl TunWriteQueue {
+    /// Create an empty queue holding at most `capacity` packets
+    pub fn new(capacity: usize, policy: TunQueuePolicy) -> Self {
+        Self {
+            capacity,
+            policy,
+            packets: Mutex::new(VecDeque::with_capacity(capacity.min(1024))),
+            ready: Notify::new(),
+        }
+    }
+
+    /// Offer a packet, applying the drop policy if the queue is full
+    pub async fn push(&self, packet: Vec<u8>) -> Enqueue {
+        let mut packets = self.packets.lock().await;
+        let outcome = if packets.len() < self.capacity {
+            Enqueue::Queued
+        } else {
+            match self.policy {
+                TunQueuePolicy::DropTail => return Enqueue::Full,
+                TunQueuePolicy::DropOldest => {
+                    packets.pop_front();
+                    Enqueue::DroppedOldest
+                }
+            }
+        };
+        packets.push_back(packet);
+        drop(packets);
+
+        self.ready.notify_one();
+        outcome
+    }
+
+    /// Take the oldest packet, waiting until one is queued
+    pub async fn pop(&self) -> Vec<u8> {
+        loop {
+            if let Some(packet) = self.packets.lock().await.pop_front() {
+                return packet;
+            }
+            self.ready.notified().await;
+        }
+    }
+
+    /// Packets currently queued
+    pub async fn depth(&self) -> usize {
+        self.packets.lock().await.len()
+    }
+
+    /// Start the task that drains the queue into `device`
+    pub fn spawn_writer<W>(self: Arc<Self>, device: Arc<Mutex<W>>) -> JoinHandle<()>
+    where
+        W: Write + Send + 'static,
+    {
+        tokio::spawn(async move {
+            loop {
+                let packet = self.pop().await;
+                let mut device = device.lock().await;
+                match device.write(&packet) {
+                    Ok(bytes) => debug!("Successfully wrote {} bytes to TUN device", bytes),
+                    Err(e) => error!("Failed to write to TUN device: {}", e),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_tun_queue_drop_policies() {
+        // Drop-tail keeps the queued packets and refuses the new one
+        let queue = TunWriteQueue::new(2, TunQueuePolicy::DropTail);
+        assert_eq!(queue.push(vec![1]).await, Enqueue::Queued);
+        assert_eq!(queue.push(vec![2]).await, Enqueue::Queued);
+        assert_eq!(queue.push(vec![3]).await, Enqueue::Full);
+        assert_eq!(queue.depth().await, 2);
+        assert_eq!(queue.pop().await, vec![1]);
+        assert_eq!(queue.pop().await, vec![2]);
+
+        // Drop-oldest makes room for the new packet
+        let queue = TunWriteQueue::new(2, TunQueuePolicy::DropOldest);
+        queue.push(vec![1]).await;
+        queue.push(vec![2]).await;
+        assert_eq!(queue.push(vec![3]).await, Enqueue::DroppedOldest);
+        assert_eq!(queue.depth().await, 2);
+        assert_eq!(queue.pop().await, vec![2]);
+        assert_eq!(queue.pop().await, vec![3]);
+
+        // The writer drains the queue into the device in order
+        let queue = Arc::new(TunWriteQueue::new(8, TunQueuePolicy::DropTail));
+        let device = Arc::new(Mutex::new(Vec::new()));
+        let writer = queue.clone().spawn_writer(device.clone());
+        queue.push(vec![1, 2]).await;
+        queue.push(vec![3]).await;
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while device.lock().await.len() < 3 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        }).await.unwrap();
+        assert_eq!(*device.lock().await, vec![1, 2, 3]);
+        assert_eq!(queue.depth().await, 0);
+        writer.abort();
+    }
+}
@@ -0,0 +1,290 @@
+// src/protocol/fragment.rs
+//! Fragmentation of oversized Data payloads.
+//!
+//! Sessions that negotiate the `fragmentation` feature may split a payload
+//! across several Data packets, each carrying a `FragmentHeader`. Every
+//! fragment is encrypted on its own, so only authenticated data is ever
+//! buffered. `FragmentReassembler` collects the fragments of each message
+//! and bounds what an unfinished message can cost: incomplete messages are
+//! dropped after a timeout, their number is capped, and the bytes buffered
+//! per client, including each message's bookkeeping, are capped. Empty
+//! fragments are refused, since they would cost slots without any bytes.
+
+use std::collections::{BTreeSet, HashMap};
+use std::mem::size_of;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::protocol::types::FragmentHeader;
+
+/// Error type for fragment reassembly
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum FragmentError {
+    #[error("Invalid fragment header: {0}")]
+    InvalidHeader(String),
+
+    #[error("Fragment does not match message {0}")]
+    Mismatch(u32),
+
+    #[error("Reassembly buffer full ({0} bytes)")]
+    BufferFull(usize),
+
+    #[error("Empty fragment for message {0}")]
+    Empty(u32),
+
+    #[error("Too many incomplete messages ({0})")]
+    TooManyMessages(usize),
+}
+
+/// Split `payload` into chunks of at most `fragment_size` bytes.
+///
+/// Returns one `(header, chunk)` pair per fragment, all sharing `message_id`.
+pub fn split_payload(payload: &[u8], fragment_size: usize, message_id: u32) -> Vec<(FragmentHeader, &[u8])> {
+    let chunks: Vec<&[u8]> = payload.chunks(fragment_size.max(1)).collect();
+    let total = chunks.len() as u16;
+    chunks.into_iter()
+        .enumerate()
+        .map(|(index, chunk)| (FragmentHeader { message_id, index: index as u16, total }, chunk))
+        .collect()
+}
+
+/// Fragments received so far for one message
+#[derive(Debug)]
+struct PartialMessage {
+    /// Fragment payloads by index
+    fragments: Vec<Option<Vec<u8>>>,
+    /// Fragments still missing
+    missing: usize,
+    /// Bytes charged for this message, bookkeeping included
+    bytes: usize,
+    /// When the first fragment arrived
+    started: Instant,
+}
+
+/// Bytes charged against the buffer for tracking a message of `total` fragments
+fn message_overhead(total: u16) -> usize {
+    size_of::<u32>() + size_of::<PartialMessage>() + total as usize * size_of::<Option<Vec<u8>>>()
+}
+
+/// Reassembles fragmented messages from one client
+#[derive(Debug)]
+pub struct FragmentReassembler {
+    /// How long an incomplete message is kept
+    timeout: Duration,
+    /// Most bytes buffered across all incomplete messages
+    max_bytes: usize,
+    /// Most fragments accepted for one message
+    max_fragments: u16,
+    /// Most incomplete messages held at once
+    max_messages: usize,
+    /// Incomplete messages by message ID
+    partial: HashMap<u32, PartialMessage>,
+    /// Incomplete messages by arrival of their first fragment, oldest first
+    by_age: BTreeSet<(Instant, u32)>,
+    /// Bytes currently charged, bookkeeping included
+    buffered: usize,
+}
+
+impl FragmentReassembler {
+    /// Create a reassembler with the given timeout and limits
+    pub fn new(timeout: Duration, max_bytes: usize, max_fragments: u16, max_messages: usize) -> Self {
+        Self {
+            timeout,
+            max_bytes,
+            max_fragments,
+            max_messages,
+            partial: HashMap::new(),
+            by_age: BTreeSet::new(),
+            buffered: 0,
+        }
+    }
+
+    /// Add one fragment received at `now`.
+    ///
+    /// Returns the complete message once its last fragment arrives. Expired
+    /// messages are dropped first. A fragment that would overflow the buffer
+    /// drops its whole message; a fragment starting a message beyond the
+    /// incomplete message limit is refused.
+    pub fn insert(&mut self, header: &FragmentHeader, data: Vec<u8>, now: Instant) -> Result<Option<Vec<u8>>, FragmentError> {
+        if header.total == 0 || header.total > self.max_fragments || header.index >= header.total {
+            return Err(FragmentError::InvalidHeader(format!(
+                "fragment {} of {} (limit {})", header.index, header.total, self.max_fragments
+            )));
+        }
+        if data.is_empty() {
+            return Err(FragmentError::Empty(header.message_id));
+        }
+        self.expire(now);
+
+        let overhead = match self.partial.get(&header.message_id) {
+            Some(message) => {
+                if message.fragments.len() != header.total as usize {
+                    return Err(FragmentError::Mismatch(header.message_id));
+                }
+                // Duplicates are ignored; the first copy wins
+                if message.fragments[header.index as usize].is_some() {
+                    return Ok(None);
+                }
+                0
+            }
+            // Nothing to wait for
+            None if header.total == 1 => return Ok(Some(data)),
+            None if self.partial.len() >= self.max_messages => {
+                return Err(FragmentError::TooManyMessages(self.max_messages));
+            }
+            None => message_overhead(header.total),
+        };
+
+        let cost = overhead + data.len();
+        if self.buffered + cost > self.max_bytes {
+            self.discard(header.message_id);
+            return Err(FragmentError::BufferFull(self.max_bytes));
+        }
+        self.buffered += cost;
+
+        let message = self.partial.entry(header.message_id).or_insert_with(|| PartialMessage {
+            fragments: vec![None; header.total as usize],
+            missing: header.total as usize,
+            bytes: 0,
+            started: now,
+        });
+        if overhead > 0 {
+            self.by_age.insert((now, header.message_id));
+        }
+        message.bytes += cost;
+        message.missing -= 1;
+        message.fragments[header.index as usize] = Some(data);
+
+        if message.missing > 0 {
+            return Ok(None);
+        }
+        let message = self.partial.remove(&header.message_id).expect("message present");
+        self.by_age.remove(&(message.started, header.message_id));
+        self.buffered -= message.bytes;
+        Ok(Some(message.fragments.into_iter().flatten().flatten().collect()))
+    }
+
+    /// Drop incomplete messages older than the timeout, returning how many were dropped
+    pub fn expire(&mut self, now: Instant) -> usize {
+        let mut dropped = 0;
+        while let Some(&(started, id)) = self.by_age.first() {
+            if now.saturating_duration_since(started) < self.timeout {
+                break;
+            }
+            self.by_age.pop_first();
+            self.discard(id);
+            dropped += 1;
+        }
+        dropped
+    }
+
+    /// Number of incomplete messages held
+    pub fn pending(&self) -> usize {
+        self.partial.len()
+    }
+
+    /// Bytes held by incomplete messages, bookkeeping included
+    pub fn buffered(&self) -> usize {
+        self.buffered
+    }
+
+    /// Drop an incomplete message and release its bytes
+    fn discard(&mut self, message_id: u32) {
+        if let Some(message) = self.partial.remove(&message_id) {
+            self.by_age.remove(&(message.started, message_id));
+            self.buffered -= message.bytes;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fragment_reassembly() {
+        let payload: Vec<u8> = (0..5000u32).map(|i| i as u8).collect();
+        let fragments = split_payload(&payload, 1200, 7);
+        assert_eq!(fragments.len(), 5);
+        assert!(fragments.iter().all(|(header, _)| header.total == 5 && header.message_id == 7));
+
+        // Out of order, with a duplicate
+        let start = Instant::now();
+        let mut reassembler = FragmentReassembler::new(Duration::from_secs(5), 64 * 1024, 64, 16);
+        for &i in &[3, 0, 4, 0, 1] {
+            let (header, chunk) = &fragments[i];
+            assert_eq!(reassembler.insert(header, chunk.to_vec(), start), Ok(None));
+        }
+        let (header, chunk) = &fragments[2];
+        assert_eq!(reassembler.insert(header, chunk.to_vec(), start), Ok(Some(payload.clone())));
+        assert_eq!(reassembler.pending(), 0);
+
+        // Never-completed messages are dropped on timeout
+        let (header, chunk) = &fragments[0];
+        assert_eq!(reassembler.insert(header, chunk.to_vec(), start), Ok(None));
+        assert_eq!(reassembler.expire(start + Duration::from_secs(4)), 0);
+        assert_eq!(reassembler.expire(start + Duration::from_secs(5)), 1);
+        assert_eq!(reassembler.pending(), 0);
+
+        // A late fragment of the expired message starts over rather than completing it
+        let (header, chunk) = &fragments[4];
+        let late = start + Duration::from_secs(6);
+        assert_eq!(reassembler.insert(header, chunk.to_vec(), late), Ok(None));
+
+        // The buffer cap drops the message that would overflow it
+        let mut reassembler = FragmentReassembler::new(Duration::from_secs(5), 2000, 64, 16);
+        assert_eq!(reassembler.insert(&fragments[0].0, fragments[0].1.to_vec(), start), Ok(None));
+        assert_eq!(
+            reassembler.insert(&fragments[1].0, fragments[1].1.to_vec(), start),
+            Err(FragmentError::BufferFull(2000))
+        );
+        assert_eq!(reassembler.pending(), 0);
+
+        // Malformed headers are refused
+        let bad = FragmentHeader { message_id: 1, index: 2, total: 2 };
+        assert!(matches!(reassembler.insert(&bad, vec![0], start), Err(FragmentError::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn test_distinct_message_flood() {
+        let start = Instant::now();
+        let first = |message_id: u32| FragmentHeader { message_id, index: 0, total: 64 };
+
+        // Empty fragments are refused outright
+        let mut reassembler = FragmentReassembler::new(Duration::from_secs(5), 64 * 1024, 64, 16);
+        assert_eq!(reassembler.insert(&first(1), Vec::new(), start), Err(FragmentError::Empty(1)));
+        assert_eq!(reassembler.pending(), 0);
+
+        // Opening fragments of distinct messages stop at the message limit
+        for message_id in 0..16 {
+            assert_eq!(reassembler.insert(&first(message_id), vec![0], start), Ok(None));
+        }
+        assert_eq!(reassembler.insert(&first(16), vec![0], start), Err(FragmentError::TooManyMessages(16)));
+        assert_eq!(reassembler.pending(), 16);
+
+        // Their bookkeeping is charged, not just their one byte each
+        assert_eq!(reassembler.buffered, 16 * (message_overhead(64) + 1));
+
+        // Fragments of messages already held still count toward completion
+        assert_eq!(reassembler.insert(&FragmentHeader { message_id: 3, index: 1, total: 64 }, vec![0], start), Ok(None));
+
+        // Expiry frees the slots
+        assert_eq!(reassembler.expire(start + Duration::from_secs(5)), 16);
+        assert_eq!(reassembler.buffered, 0);
+        assert_eq!(reassembler.insert(&first(16), vec![0], start + Duration::from_secs(5)), Ok(None));
+
+        // A byte budget smaller than the message limit's bookkeeping runs out first
+        let budget = 4 * message_overhead(64) + 8;
+        let mut reassembler = FragmentReassembler::new(Duration::from_secs(5), budget, 64, 16);
+        for message_id in 0..4 {
+            assert_eq!(reassembler.insert(&first(message_id), vec![0], start), Ok(None));
+        }
+        assert_eq!(reassembler.insert(&first(4), vec![0], start), Err(FragmentError::BufferFull(budget)));
+        assert_eq!(reassembler.pending(), 4);
+
+        // Single-fragment messages complete without being held
+        let whole = FragmentHeader { message_id: 9, index: 0, total: 1 };
+        assert_eq!(reassembler.insert(&whole, vec![1, 2], start), Ok(Some(vec![1, 2])));
+        assert_eq!(reassembler.pending(), 4);
+    }
+}
@@ -7,6 +7,7 @@
 pub mod types;
 pub mod serialization;
 pub mod validation;
+pub mod fragment;
 
 // Re-export commonly used items
 pub use types::{PacketType, MessageError};
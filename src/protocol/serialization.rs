@@ -6,9 +6,9 @@
 
 use serde_json;
 use tokio_tungstenite::tungstenite::Message as WsMessage;
-use tracing::{trace, debug, warn};
+use tracing::{trace, debug, info, warn};
 
-use crate::protocol::types::{MessageError, PacketType};
+use crate::protocol::types::{DisconnectReason, ErrorCode, MessageError, PacketType, RetryPolicy};
 use crate::protocol::validation::validate_message;
 
 /// Maximum allowed message size (1MB)
@@ -71,24 +71,30 @@ pub fn ws_message_to_packet(message: &WsMessage) -> Result<PacketType, MessageEr
 }
 
 /// Create a standard error packet
-pub fn create_error_packet(code: u16, message: &str) -> PacketType {
+pub fn create_error_packet(code: ErrorCode, message: &str) -> PacketType {
     PacketType::Error {
-        code,
+        code: code.code(),
         message: message.to_string(),
+        retry: RetryPolicy::for_error(code),
     }
 }
 
 /// Create a disconnect packet
-pub fn create_disconnect_packet(reason: u16, message: &str) -> PacketType {
+pub fn create_disconnect_packet(reason: DisconnectReason, message: &str) -> PacketType {
     PacketType::Disconnect {
-        reason,
+        reason: reason.code(),
         message: message.to_string(),
+        retry: RetryPolicy::for_disconnect(reason),
+        sequence: None,
+        mac: None,
     }
 }
 
 /// Get the name of a packet type for logging
 pub fn get_packet_type_name(packet: &PacketType) -> &'static str {
     match packet {
+        PacketType::Hello { .. } => "Hello",
+        PacketType::Capabilities { .. } => "Capabilities",
         PacketType::Auth { .. } => "Auth",
         PacketType::Challenge { .. } => "Challenge",
         PacketType::ChallengeResponse { .. } => "ChallengeResponse",
@@ -99,20 +105,61 @@ pub fn get_packet_type_name(packet: &PacketType) -> &'static str {
         PacketType::KeyRotation { .. } => "KeyRotation",
         PacketType::IpRenewal { .. } => "IpRenewal",
         PacketType::IpRenewalResponse { .. } => "IpRenewalResponse",
+        PacketType::PacketTooBig { .. } => "PacketTooBig",
         PacketType::Disconnect { .. } => "Disconnect",
+        PacketType::DisconnectAck { .. } => "DisconnectAck",
+        PacketType::FeatureAck { .. } => "FeatureAck",
+        PacketType::DeferKeyRotation { .. } => "DeferKeyRotation",
+        PacketType::KeyRotationDeferred { .. } => "KeyRotationDeferred",
+        PacketType::ServerLoad { .. } => "ServerLoad",
+        PacketType::ResumptionToken { .. } => "ResumptionToken",
         PacketType::Error { .. } => "Error",
     }
 }
 
+/// Log a packet on a connection sampled for detailed tracing
+///
+/// Emitted at info level and tagged `[sampled <session>]` so sampled
+/// sessions stand out in fleet logs. Payloads are reported by size only.
+pub fn log_sampled_packet(session_id: &str, packet: &PacketType, is_incoming: bool) {
+    let direction = if is_incoming { "Received" } else { "Sending" };
+    let name = get_packet_type_name(packet);
+    
+    match packet {
+        PacketType::Data { encrypted, counter, encryption_algorithm, .. } => {
+            info!(
+                "[sampled {}] {} {} packet, counter: {}, {} bytes, algorithm: {:?}",
+                session_id, direction, name, counter, encrypted.len(), encryption_algorithm
+            );
+        }
+        PacketType::Ping { sequence, .. } | PacketType::Pong { sequence, .. } => {
+            info!("[sampled {}] {} {} packet, sequence: {}", session_id, direction, name, sequence);
+        }
+        PacketType::Error { code, message, .. } => {
+            info!("[sampled {}] {} {} packet, code: {}, message: {}", session_id, direction, name, code, message);
+        }
+        _ => info!("[sampled {}] {} {} packet", session_id, direction, name),
+    }
+}
+
 /// Log packet information (non-sensitive parts only)
 pub fn log_packet_info(packet: &PacketType, is_incoming: bool) {
     let direction = if is_incoming { "Received" } else { "Sending" };
     
     match packet {
-        PacketType::Auth { public_key, version, features, .. } => {
+        PacketType::Hello { version } => {
+            debug!("{} Hello packet, version: {}", direction, version);
+        }
+        PacketType::Capabilities { protocol_version, features, max_mtu, .. } => {
+            debug!(
+                "{} Capabilities packet, version: {}, features: {:?}, max mtu: {}",
+                direction, protocol_version, features, max_mtu
+            );
+        }
+        PacketType::Auth { public_key, version, features, resumption_token, .. } => {
             debug!(
-                "{} Auth packet from {}, version: {}, features: {:?}",
-                direction, public_key, version, features
+                "{} Auth packet from {}, version: {}, features: {:?}, resuming: {}",
+                direction, public_key, version, features, resumption_token.is_some()
             );
         }
         PacketType::Challenge { id, expires_at, .. } => {
@@ -157,28 +204,58 @@ pub fn log_packet_info(packet: &PacketType, is_incoming: bool) {
                 direction, key_id
             );
         }
-        PacketType::IpRenewal { session_id, ip_address } => {
+        PacketType::IpRenewal { session_id, ip_address, mac, sequence } => {
             debug!(
-                "{} IpRenewal packet, session: {}, ip: {}",
-                direction, session_id, ip_address
+                "{} IpRenewal packet, session: {}, ip: {}, signed: {}, sequence: {:?}",
+                direction, session_id, ip_address, mac.is_some(), sequence
             );
         }
-        PacketType::IpRenewalResponse { session_id, expires_at, success } => {
+        PacketType::IpRenewalResponse { session_id, expires_at, success, reason } => {
             debug!(
-                "{} IpRenewalResponse packet, session: {}, success: {}, expires: {}",
-                direction, session_id, success, expires_at
+                "{} IpRenewalResponse packet, session: {}, success: {}, expires: {}, reason: {:?}",
+                direction, session_id, success, expires_at, reason
             );
         }
-        PacketType::Disconnect { reason, message } => {
+        PacketType::PacketTooBig { size, mtu } => {
             debug!(
-                "{} Disconnect packet, reason: {}, message: {}",
-                direction, reason, message
+                "{} PacketTooBig packet, size: {}, mtu: {}",
+                direction, size, mtu
             );
         }
-        PacketType::Error { code, message } => {
+        PacketType::Disconnect { reason, message, retry, sequence, mac } => {
+            debug!(
+                "{} Disconnect packet, reason: {}, message: {}, retry: {:?}, sequence: {:?}, signed: {}",
+                direction, reason, message, retry, sequence, mac.is_some()
+            );
+        }
+        PacketType::DisconnectAck { bytes_sent, bytes_received } => {
+            debug!(
+                "{} DisconnectAck packet, bytes sent: {}, bytes received: {}",
+                direction, bytes_sent, bytes_received
+            );
+        }
+        PacketType::FeatureAck { active, denied } => {
+            debug!(
+                "{} FeatureAck packet, active: {:?}, denied: {:?}",
+                direction, active, denied
+            );
+        }
+        PacketType::DeferKeyRotation { seconds } => {
+            debug!("{} DeferKeyRotation packet, seconds: {}", direction, seconds);
+        }
+        PacketType::KeyRotationDeferred { seconds } => {
+            debug!("{} KeyRotationDeferred packet, seconds: {}", direction, seconds);
+        }
+        PacketType::ServerLoad { active_sessions, load_factor } => {
+            trace!("{} ServerLoad packet, sessions: {}, load: {:.2}", direction, active_sessions, load_factor);
+        }
+        PacketType::ResumptionToken { ttl_secs, .. } => {
+            debug!("{} ResumptionToken packet, ttl: {}s", direction, ttl_secs);
+        }
+        PacketType::Error { code, message, retry } => {
             warn!(
-                "{} Error packet, code: {}, message: {}",
-                direction, code, message
+                "{} Error packet, code: {}, message: {}, retry: {:?}",
+                direction, code, message, retry
             );
         }
     }
@@ -255,12 +332,13 @@ mod tests {
     
     #[test]
     fn test_create_error_packet() {
-        let error = create_error_packet(1001, "Test error");
+        let error = create_error_packet(ErrorCode::AuthenticationFailed, "Test error");
         
         match error {
-            PacketType::Error { code, message } => {
+            PacketType::Error { code, message, retry } => {
                 assert_eq!(code, 1001);
                 assert_eq!(message, "Test error");
+                assert_eq!(retry, Some(RetryPolicy::NEVER));
             }
             _ => panic!("Wrong packet type"),
         }
@@ -268,12 +346,15 @@ mod tests {
     
     #[test]
     fn test_create_disconnect_packet() {
-        let disconnect = create_disconnect_packet(2, "Goodbye");
+        let disconnect = create_disconnect_packet(DisconnectReason::ServerShutdown, "Goodbye");
         
         match disconnect {
-            PacketType::Disconnect { reason, message } => {
+            PacketType::Disconnect { reason, message, retry, sequence, mac } => {
                 assert_eq!(reason, 2);
+                assert!(mac.is_none());
                 assert_eq!(message, "Goodbye");
+                assert!(sequence.is_none());
+                assert!(retry.unwrap().try_other_endpoint);
             }
             _ => panic!("Wrong packet type"),
         }
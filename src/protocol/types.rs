@@ -53,10 +53,41 @@ pub struct DataEnvelope {
     pub payload: Value,
 }
 
+/// Position of a Data packet within a fragmented payload
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FragmentHeader {
+    /// Identifies the fragments of one payload
+    pub message_id: u32,
+    /// Zero-based position of this fragment
+    pub index: u16,
+    /// Number of fragments in the payload
+    pub total: u16,
+}
+
 /// Packet types for client-server communication
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum PacketType {
+    /// Unauthenticated capability query, optionally sent before Auth when the server allows it
+    Hello {
+        /// Client protocol version
+        version: String,
+    },
+    
+    /// Reply to Hello listing what the server supports; reveals nothing about keys or sessions
+    Capabilities {
+        /// Protocol version the server speaks
+        protocol_version: String,
+        /// Oldest client protocol version the server accepts
+        min_protocol_version: String,
+        /// Session ciphers, as accepted in `Auth.encryption_algorithm`
+        encryption_algorithms: Vec<String>,
+        /// Optional features the server can activate
+        features: Vec<String>,
+        /// Largest inner packet accepted through the tunnel, in bytes
+        max_mtu: u16,
+    },
+    
     /// Authentication request
     Auth {
     /// Client public key
@@ -69,6 +100,15 @@ pub enum PacketType {
         encryption_algorithm: Option<String>,
         /// Nonce for security
         nonce: String,
+        /// Token from a previous session's ResumptionToken, to resume it without a challenge
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        resumption_token: Option<String>,
+        /// HMAC-SHA256 under the previous session key over the token (required with `resumption_token`)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        resumption_proof: Option<Vec<u8>>,
+        /// Requested IP lease in seconds; the server clamps it to its maximum
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        lease_duration: Option<u64>,
     },
     
     /// Challenge for authentication
@@ -81,6 +121,9 @@ pub enum PacketType {
         expires_at: u64,
         /// Challenge ID
         id: String,
+        /// Leading zero bits required of SHA-256(data || nonce as 8 big-endian bytes), absent when no proof of work is required
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pow_difficulty: Option<u8>,
     },
     
     /// Challenge response
@@ -91,6 +134,9 @@ pub enum PacketType {
         public_key: String,
         /// Challenge ID
         challenge_id: String,
+        /// Proof-of-work nonce, required when the challenge set `pow_difficulty`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pow_nonce: Option<u64>,
     },
     
     /// IP assignment
@@ -99,7 +145,7 @@ pub enum PacketType {
         ip_address: String,
         /// Lease duration in seconds
         lease_duration: u64,
-        /// Session ID
+        /// Session ID, also the salt of the session's Data nonces
         session_id: String,
         /// Encrypted session key
         encrypted_session_key: Vec<u8>,
@@ -107,6 +153,12 @@ pub enum PacketType {
         key_nonce: Vec<u8>,
         /// Selected encryption algorithm
         encryption_algorithm: String,
+        /// Protocol version spoken by the server
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        protocol_version: Option<String>,
+        /// Features negotiated for the session
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        features: Vec<String>,
     },
 
     
@@ -114,14 +166,18 @@ pub enum PacketType {
     Data {
     /// Encrypted packet data
         encrypted: Vec<u8>,
-        /// Encryption nonce
+        /// Encryption nonce (omitted when `derived-nonce` is negotiated)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
         nonce: Vec<u8>,
-        /// Packet counter for replay protection
+        /// Packet counter for replay protection (and nonce derivation)
         counter: u64,
         /// Optional padding data
         padding: Option<Vec<u8>>,
         /// Encryption algorithm used (optional for backward compatibility)
         encryption_algorithm: Option<String>,
+        /// Position within a fragmented payload (`fragmentation` feature only)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fragment: Option<FragmentHeader>,
     },
     
     /// Ping message for latency measurement and keepalive
@@ -160,6 +216,12 @@ pub enum PacketType {
         session_id: String,
         /// Current IP address
         ip_address: String,
+        /// HMAC-SHA256 under the session key (required once `signed-renewal` is negotiated)
+        #[serde(default)]
+        mac: Option<Vec<u8>>,
+        /// Control packet sequence number (required once `control-sequence` is negotiated)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sequence: Option<u64>,
     },
     
     /// IP renewal response
@@ -170,6 +232,16 @@ pub enum PacketType {
         expires_at: u64,
         /// Success flag
         success: bool,
+        /// Failure reason code (see `ErrorCode`), absent on success
+        reason: Option<u16>,
+    },
+    
+    /// Inner packet exceeded the tunnel MTU and was dropped (tunnel-level PMTU signal)
+    PacketTooBig {
+        /// Size of the offending inner packet in bytes
+        size: u32,
+        /// Tunnel MTU the client should use
+        mtu: u16,
     },
     
     /// Disconnect notification
@@ -178,6 +250,60 @@ pub enum PacketType {
         reason: u16,
         /// Human-readable message
         message: String,
+        /// How the client should reconnect, if at all
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        retry: Option<RetryPolicy>,
+        /// Control packet sequence number (client to server, required once `control-sequence` is negotiated)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sequence: Option<u64>,
+        /// HMAC-SHA256 under the session key over the reason and sequence
+        /// (client to server, required once `control-sequence` is negotiated)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mac: Option<Vec<u8>>,
+    },
+    
+    /// Acknowledgement of a client-initiated disconnect, sent before the server closes
+    DisconnectAck {
+        /// Bytes sent to the client during the session
+        bytes_sent: u64,
+        /// Bytes received from the client during the session
+        bytes_received: u64,
+    },
+    
+    /// Result of feature negotiation, sent after IpAssign to clients that request it
+    FeatureAck {
+        /// Features active for the session
+        active: Vec<String>,
+        /// Requested features that were not activated due to server limits
+        denied: Vec<String>,
+    },
+    
+    /// Request to postpone the next session key rotation
+    DeferKeyRotation {
+        /// Requested postponement in seconds
+        seconds: u32,
+    },
+    
+    /// Response to a rotation postponement request
+    KeyRotationDeferred {
+        /// Postponement granted from now in seconds (zero if refused)
+        seconds: u32,
+    },
+    
+    /// Advisory server load, pushed periodically to clients that opt in
+    ServerLoad {
+        /// Number of active sessions
+        active_sessions: u32,
+        /// Load from 0.0 (idle) to 1.0 (saturated)
+        load_factor: f32,
+    },
+    
+    /// Token for resuming this session after it ends, sent after IpAssign to clients with `session-resumption`
+    ResumptionToken {
+        /// Single-use token to present in a later Auth packet
+        token: String,
+        /// Seconds the token stays valid once the session has ended
+        ttl_secs: u64,
     },
     
     /// Error notification
@@ -186,13 +312,88 @@ pub enum PacketType {
         code: u16,
         /// Human-readable message
         message: String,
+        /// How the client should retry, if at all
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        retry: Option<RetryPolicy>,
     },
 }
 
+/// Machine-readable reconnect guidance attached to errors and disconnects.
+///
+/// Clients should wait a random delay between `min_backoff_ms` and
+/// `max_backoff_ms` (growing towards the maximum on repeated failures)
+/// before reconnecting, and prefer another server when `try_other_endpoint`
+/// is set. Clients that do not understand the field ignore it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Whether retrying can succeed without the client changing anything
+    pub retry: bool,
+    /// Minimum delay before retrying, in milliseconds
+    pub min_backoff_ms: u64,
+    /// Maximum delay before retrying, in milliseconds
+    pub max_backoff_ms: u64,
+    /// Whether another endpoint is more likely to succeed
+    pub try_other_endpoint: bool,
+}
+
+impl RetryPolicy {
+    /// Retrying will fail the same way
+    pub const NEVER: Self = Self { retry: false, min_backoff_ms: 0, max_backoff_ms: 0, try_other_endpoint: false };
+
+    /// Retry after a backoff between `min_ms` and `max_ms`
+    pub const fn backoff(min_ms: u64, max_ms: u64, try_other_endpoint: bool) -> Self {
+        Self { retry: true, min_backoff_ms: min_ms, max_backoff_ms: max_ms, try_other_endpoint }
+    }
+
+    /// Policy for an error code
+    pub fn for_error(code: ErrorCode) -> Option<Self> {
+        match code {
+            ErrorCode::AuthenticationFailed
+            | ErrorCode::InvalidMessage
+            | ErrorCode::Unauthorized
+            | ErrorCode::VersionMismatch => Some(Self::NEVER),
+            ErrorCode::RateLimited => Some(Self::backoff(1_000, 30_000, false)),
+            ErrorCode::ResourceExhausted => Some(Self::backoff(5_000, 60_000, true)),
+            // Reconnect promptly to get a new session or lease
+            ErrorCode::SessionExpired | ErrorCode::LeaseLost => Some(Self::backoff(0, 5_000, false)),
+            // Succeeds once the existing session ends
+            ErrorCode::AlreadyConnected => Some(Self::backoff(5_000, 60_000, false)),
+            ErrorCode::GeneralError
+            | ErrorCode::InternalError
+            | ErrorCode::InvalidState => Some(Self::backoff(1_000, 30_000, false)),
+            ErrorCode::HandshakeIncomplete
+            | ErrorCode::ReplayDetected
+            | ErrorCode::SessionLimit => None,
+        }
+    }
+
+    /// Policy for a disconnect reason
+    pub fn for_disconnect(reason: DisconnectReason) -> Option<Self> {
+        match reason {
+            DisconnectReason::UserInitiated => None,
+            DisconnectReason::SessionExpired
+            | DisconnectReason::IdleTimeout => Some(Self::backoff(0, 5_000, false)),
+            DisconnectReason::ServerShutdown
+            | DisconnectReason::Maintenance => Some(Self::backoff(5_000, 60_000, true)),
+            DisconnectReason::TooManyConnections => Some(Self::backoff(10_000, 120_000, true)),
+            DisconnectReason::InternalError => Some(Self::backoff(1_000, 30_000, false)),
+            DisconnectReason::AuthenticationFailed
+            | DisconnectReason::ProtocolViolation
+            | DisconnectReason::AccessDenied
+            | DisconnectReason::AccessRevoked
+            // Reconnecting would in turn replace the newer session
+            | DisconnectReason::SessionReplaced => Some(Self::NEVER),
+        }
+    }
+}
+
 pub mod encryption_algorithms {
     pub const CHACHA20_POLY1305: &str = "chacha20poly1305";
     pub const AES_256_GCM: &str = "aes256gcm";
     
+    /// Every algorithm the server accepts, as advertised in `Capabilities`
+    pub const ALL: &[&str] = &[CHACHA20_POLY1305, AES_256_GCM];
+    
     // Check if algorithm is supported
     pub fn is_supported(algorithm: &str) -> bool {
         match algorithm {
@@ -207,31 +408,159 @@ pub mod encryption_algorithms {
     }
 }
 
-/// Disconnect reason codes
-pub mod disconnect_reason {
-    pub const USER_INITIATED: u16 = 0;
-    pub const SESSION_EXPIRED: u16 = 1;
-    pub const SERVER_SHUTDOWN: u16 = 2;
-    pub const AUTHENTICATION_FAILED: u16 = 3;
-    pub const PROTOCOL_VIOLATION: u16 = 4;
-    pub const TOO_MANY_CONNECTIONS: u16 = 5;
-    pub const IDLE_TIMEOUT: u16 = 6;
-    pub const INTERNAL_ERROR: u16 = 7;
-    pub const ACCESS_DENIED: u16 = 8;
+/// Optional feature identifiers advertised in `Auth.features`
+pub mod features {
+    /// Keepalive via WebSocket control-frame ping/pong instead of application Ping/Pong
+    pub const WS_KEEPALIVE: &str = "ws-keepalive";
+    /// Client handles PacketTooBig notifications for oversized inner packets
+    pub const PACKET_TOO_BIG: &str = "packet-too-big";
+    /// Client waits for a DisconnectAck after sending Disconnect
+    pub const DISCONNECT_ACK: &str = "disconnect-ack";
+    /// Client wants a FeatureAck listing active and denied features after IpAssign
+    pub const FEATURE_ACK: &str = "feature-ack";
+    /// Client authenticates IpRenewal requests with a MAC under the session key
+    pub const SIGNED_RENEWAL: &str = "signed-renewal";
+    /// Data nonces are derived from the packet counter and omitted from the wire (see `crypto::nonce`)
+    pub const DERIVED_NONCE: &str = "derived-nonce";
+    /// Client wants periodic ServerLoad packets
+    pub const SERVER_LOAD: &str = "server-load";
+    /// IpAssign session key is sealed to the session id and client public key (see `crypto::encryption::session_key_aad`)
+    pub const BOUND_SESSION_KEY: &str = "bound-session-key";
+    /// IpRenewal and Disconnect carry a strictly increasing per-session `sequence` covered by
+    /// their MAC; replays are rejected. Only active together with `signed-renewal`
+    pub const CONTROL_SEQUENCE: &str = "control-sequence";
+    /// Client supports AES-256-GCM for session data; selected when `Auth.encryption_algorithm` names no cipher
+    pub const AES_GCM: &str = "aes-gcm";
+    /// Data payloads may be split across several packets carrying a `FragmentHeader` (see `protocol::fragment`)
+    pub const FRAGMENTATION: &str = "fragmentation";
+    /// Client accepts a ResumptionToken and may resume the session with it after disconnecting (see `auth::resumption`)
+    pub const SESSION_RESUMPTION: &str = "session-resumption";
+
+    /// Every feature the server can negotiate, as advertised in `Capabilities`
+    pub const ALL: &[&str] = &[
+        WS_KEEPALIVE, PACKET_TOO_BIG, DISCONNECT_ACK, FEATURE_ACK, SIGNED_RENEWAL, DERIVED_NONCE,
+        SERVER_LOAD, BOUND_SESSION_KEY, CONTROL_SEQUENCE, AES_GCM, FRAGMENTATION, SESSION_RESUMPTION,
+    ];
+
+    /// Check whether a feature keeps per-session state on the server
+    /// (as opposed to a cheap behavioural flag)
+    pub fn is_stateful(feature: &str) -> bool {
+        matches!(feature, PACKET_TOO_BIG | DERIVED_NONCE | FRAGMENTATION)
+    }
+}
+
+/// Why the server (or client) ended a session, carried as `Disconnect.reason`.
+///
+/// The discriminants are the wire codes and must not change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum DisconnectReason {
+    UserInitiated = 0,
+    SessionExpired = 1,
+    ServerShutdown = 2,
+    AuthenticationFailed = 3,
+    ProtocolViolation = 4,
+    TooManyConnections = 5,
+    IdleTimeout = 6,
+    InternalError = 7,
+    AccessDenied = 8,
+    AccessRevoked = 9,
+    Maintenance = 10,
+    SessionReplaced = 11,
+}
+
+impl DisconnectReason {
+    /// All reasons, in code order
+    pub const ALL: [Self; 12] = [
+        Self::UserInitiated,
+        Self::SessionExpired,
+        Self::ServerShutdown,
+        Self::AuthenticationFailed,
+        Self::ProtocolViolation,
+        Self::TooManyConnections,
+        Self::IdleTimeout,
+        Self::InternalError,
+        Self::AccessDenied,
+        Self::AccessRevoked,
+        Self::Maintenance,
+        Self::SessionReplaced,
+    ];
+
+    /// Wire code of this reason
+    pub const fn code(self) -> u16 {
+        self as u16
+    }
+
+    /// Reason for a wire code, if known
+    pub fn from_code(code: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|reason| reason.code() == code)
+    }
+}
+
+impl From<DisconnectReason> for u16 {
+    fn from(reason: DisconnectReason) -> Self {
+        reason.code()
+    }
+}
+
+/// Error codes carried as `Error.code` and `IpRenewalResponse.reason`.
+///
+/// The discriminants are the wire codes and must not change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum ErrorCode {
+    GeneralError = 1000,
+    AuthenticationFailed = 1001,
+    InvalidMessage = 1002,
+    RateLimited = 1003,
+    SessionExpired = 1004,
+    Unauthorized = 1005,
+    InternalError = 1006,
+    ResourceExhausted = 1007,
+    InvalidState = 1008,
+    VersionMismatch = 1009,
+    LeaseLost = 1010,
+    AlreadyConnected = 1011,
+    HandshakeIncomplete = 1012,
+    ReplayDetected = 1013,
+    SessionLimit = 1014,
 }
 
-/// Error codes
-pub mod error_code {
-    pub const GENERAL_ERROR: u16 = 1000;
-    pub const AUTHENTICATION_FAILED: u16 = 1001;
-    pub const INVALID_MESSAGE: u16 = 1002;
-    pub const RATE_LIMITED: u16 = 1003;
-    pub const SESSION_EXPIRED: u16 = 1004;
-    pub const UNAUTHORIZED: u16 = 1005;
-    pub const INTERNAL_ERROR: u16 = 1006;
-    pub const RESOURCE_EXHAUSTED: u16 = 1007;
-    pub const INVALID_STATE: u16 = 1008;
-    pub const VERSION_MISMATCH: u16 = 1009;
+impl ErrorCode {
+    /// All error codes, in code order
+    pub const ALL: [Self; 15] = [
+        Self::GeneralError,
+        Self::AuthenticationFailed,
+        Self::InvalidMessage,
+        Self::RateLimited,
+        Self::SessionExpired,
+        Self::Unauthorized,
+        Self::InternalError,
+        Self::ResourceExhausted,
+        Self::InvalidState,
+        Self::VersionMismatch,
+        Self::LeaseLost,
+        Self::AlreadyConnected,
+        Self::HandshakeIncomplete,
+        Self::ReplayDetected,
+        Self::SessionLimit,
+    ];
+
+    /// Wire code of this error
+    pub const fn code(self) -> u16 {
+        self as u16
+    }
+
+    /// Error for a wire code, if known
+    pub fn from_code(code: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|error| error.code() == code)
+    }
+}
+
+impl From<ErrorCode> for u16 {
+    fn from(code: ErrorCode) -> Self {
+        code.code()
+    }
 }
 
 /// Client connection state
@@ -349,4 +678,74 @@ mod tests {
         session.extend(1000, 3000);
         assert_eq!(session.expires_at, 4000);
     }
+    
+    #[test]
+    fn test_retry_policy_wire_format() {
+        // Policies are populated per reason and carried on the wire
+        let error = PacketType::Error {
+            code: ErrorCode::ResourceExhausted.code(),
+            message: "pool exhausted".to_string(),
+            retry: RetryPolicy::for_error(ErrorCode::ResourceExhausted),
+        };
+        let json: serde_json::Value = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["retry"]["retry"], true);
+        assert_eq!(json["retry"]["try_other_endpoint"], true);
+        assert_eq!(RetryPolicy::for_error(ErrorCode::Unauthorized), Some(RetryPolicy::NEVER));
+        assert!(!RetryPolicy::for_disconnect(DisconnectReason::AccessRevoked).unwrap().retry);
+        assert_eq!(RetryPolicy::for_disconnect(DisconnectReason::UserInitiated), None);
+        
+        // Absent policies are omitted, and packets from old peers still parse
+        let disconnect = PacketType::Disconnect { reason: 0, message: "bye".to_string(), retry: None, sequence: None, mac: None };
+        assert!(!serde_json::to_string(&disconnect).unwrap().contains("retry"));
+        let legacy: PacketType = serde_json::from_str(r#"{"type":"Error","code":1003,"message":"slow down"}"#).unwrap();
+        assert!(matches!(legacy, PacketType::Error { retry: None, .. }));
+    }
+    
+    #[test]
+    fn test_reason_and_error_codes() {
+        // Wire codes are fixed; changing any of these breaks deployed clients
+        let reasons: Vec<(DisconnectReason, u16)> = DisconnectReason::ALL.iter().map(|r| (*r, r.code())).collect();
+        assert_eq!(reasons, vec![
+            (DisconnectReason::UserInitiated, 0),
+            (DisconnectReason::SessionExpired, 1),
+            (DisconnectReason::ServerShutdown, 2),
+            (DisconnectReason::AuthenticationFailed, 3),
+            (DisconnectReason::ProtocolViolation, 4),
+            (DisconnectReason::TooManyConnections, 5),
+            (DisconnectReason::IdleTimeout, 6),
+            (DisconnectReason::InternalError, 7),
+            (DisconnectReason::AccessDenied, 8),
+            (DisconnectReason::AccessRevoked, 9),
+            (DisconnectReason::Maintenance, 10),
+            (DisconnectReason::SessionReplaced, 11),
+        ]);
+        let errors: Vec<(ErrorCode, u16)> = ErrorCode::ALL.iter().map(|e| (*e, e.code())).collect();
+        assert_eq!(errors, vec![
+            (ErrorCode::GeneralError, 1000),
+            (ErrorCode::AuthenticationFailed, 1001),
+            (ErrorCode::InvalidMessage, 1002),
+            (ErrorCode::RateLimited, 1003),
+            (ErrorCode::SessionExpired, 1004),
+            (ErrorCode::Unauthorized, 1005),
+            (ErrorCode::InternalError, 1006),
+            (ErrorCode::ResourceExhausted, 1007),
+            (ErrorCode::InvalidState, 1008),
+            (ErrorCode::VersionMismatch, 1009),
+            (ErrorCode::LeaseLost, 1010),
+            (ErrorCode::AlreadyConnected, 1011),
+            (ErrorCode::HandshakeIncomplete, 1012),
+            (ErrorCode::ReplayDetected, 1013),
+            (ErrorCode::SessionLimit, 1014),
+        ]);
+        
+        // Codes round-trip, and unknown codes are rejected
+        for reason in DisconnectReason::ALL {
+            assert_eq!(DisconnectReason::from_code(reason.code()), Some(reason));
+        }
+        for error in ErrorCode::ALL {
+            assert_eq!(ErrorCode::from_code(u16::from(error)), Some(error));
+        }
+        assert_eq!(DisconnectReason::from_code(12), None);
+        assert_eq!(ErrorCode::from_code(999), None);
+    }
 }
@@ -7,7 +7,10 @@
 use std::str::FromStr;
 use solana_sdk::pubkey::Pubkey;
 
-use crate::protocol::types::{MessageError, PacketType};
+use crate::auth::pow::MAX_POW_DIFFICULTY;
+use crate::auth::resumption::RESUMPTION_TOKEN_LENGTH;
+use crate::config::constants::{MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+use crate::protocol::types::{MessageError, PacketType, RetryPolicy};
 use crate::protocol::serialization::MAX_MESSAGE_SIZE;
 
 /// Utility for string validation
@@ -65,6 +68,64 @@ fn validate_auth(
     Ok(())
 }
 
+/// Validate the optional session resumption fields of an Auth packet
+fn validate_resumption(token: Option<&str>, proof: Option<&[u8]>) -> Result<(), MessageError> {
+    match (token, proof) {
+        (None, None) => Ok(()),
+        (Some(token), Some(proof)) => {
+            if token.len() != RESUMPTION_TOKEN_LENGTH || !token.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return Err(MessageError::InvalidValue("Invalid resumption token".to_string()));
+            }
+            
+            if proof.len() != 32 {
+                return Err(MessageError::InvalidValue(format!(
+                    "Invalid resumption proof length: {}", proof.len()
+                )));
+            }
+            
+            Ok(())
+        }
+        (Some(_), None) => Err(MessageError::MissingField("resumption_proof".to_string())),
+        (None, Some(_)) => Err(MessageError::MissingField("resumption_token".to_string())),
+    }
+}
+
+/// Parse a `major.minor[.patch]` version string
+fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = version.split('.').map(|part| part.parse::<u32>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next()?.ok()?;
+    let patch = match parts.next() {
+        Some(patch) => patch.ok()?,
+        None => 0,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((major, minor, patch))
+}
+
+/// Check a client's protocol version against the range this server supports.
+///
+/// Clients older than `MIN_PROTOCOL_VERSION` or with a newer major version
+/// than `PROTOCOL_VERSION` are refused. Newer minor versions only add
+/// features, which are negotiated separately.
+pub fn check_protocol_version(version: &str) -> Result<(), MessageError> {
+    let client = parse_version(version).ok_or_else(|| {
+        MessageError::InvalidValue(format!("Invalid version format: {}", version))
+    })?;
+    let (min, server) = match (parse_version(MIN_PROTOCOL_VERSION), parse_version(PROTOCOL_VERSION)) {
+        (Some(min), Some(server)) => (min, server),
+        _ => return Err(MessageError::InvalidValue("Invalid server protocol version".to_string())),
+    };
+
+    if client < min || client.0 > server.0 {
+        return Err(MessageError::VersionMismatch(version.to_string(), PROTOCOL_VERSION.to_string()));
+    }
+
+    Ok(())
+}
+
 /// Validate a challenge response message
 fn validate_challenge_response(
     signature: &str,
@@ -110,8 +171,8 @@ fn validate_data(
         return Err(MessageError::MessageTooLarge);
     }
     
-    // Check nonce size
-    if nonce.len() != 12 {
+    // Check nonce size (absent for sessions using derived nonces)
+    if !nonce.is_empty() && nonce.len() != 12 {
         return Err(MessageError::InvalidValue(format!(
             "Invalid nonce length: {} (expected 12)", nonce.len()
         )));
@@ -123,22 +184,63 @@ fn validate_data(
     Ok(())
 }
 
+/// Validate an optional retry policy
+fn validate_retry_policy(retry: Option<&RetryPolicy>) -> Result<(), MessageError> {
+    if let Some(retry) = retry {
+        if retry.min_backoff_ms > retry.max_backoff_ms {
+            return Err(MessageError::InvalidValue(format!(
+                "Retry backoff minimum {}ms exceeds maximum {}ms", retry.min_backoff_ms, retry.max_backoff_ms
+            )));
+        }
+    }
+    
+    Ok(())
+}
+
 /// Validate a packet based on its type
 pub fn validate_message(packet: &PacketType) -> Result<(), MessageError> {
     match packet {
+        PacketType::Hello { version } => {
+            if parse_version(version).is_none() {
+                return Err(MessageError::InvalidValue(format!(
+                    "Invalid version format: {}", version
+                )));
+            }
+            
+            Ok(())
+        }
+        
+        PacketType::Capabilities { max_mtu, .. } => {
+            if *max_mtu == 0 {
+                return Err(MessageError::InvalidValue("max_mtu cannot be zero".to_string()));
+            }
+            
+            Ok(())
+        }
+        
         PacketType::Auth {
             public_key,
             version,
             features,
             nonce,
             encryption_algorithm: _, 
-        } => validate_auth(public_key, version, features, nonce),
+            resumption_token,
+            resumption_proof,
+            lease_duration,
+        } => {
+            validate_auth(public_key, version, features, nonce)?;
+            if *lease_duration == Some(0) {
+                return Err(MessageError::InvalidValue("Requested lease duration must be positive".to_string()));
+            }
+            validate_resumption(resumption_token.as_deref(), resumption_proof.as_deref())
+        }
         
         PacketType::Challenge {
             data,
             server_key,
             expires_at,
             id,
+            pow_difficulty,
         } => {
             if data.is_empty() {
                 return Err(MessageError::MissingField("challenge data".to_string()));
@@ -158,6 +260,12 @@ pub fn validate_message(packet: &PacketType) -> Result<(), MessageError> {
                 return Err(MessageError::MissingField("challenge id".to_string()));
             }
             
+            if pow_difficulty.map_or(false, |d| d > MAX_POW_DIFFICULTY) {
+                return Err(MessageError::InvalidValue(format!(
+                    "pow_difficulty exceeds {}", MAX_POW_DIFFICULTY
+                )));
+            }
+            
             Ok(())
         }
         
@@ -165,6 +273,7 @@ pub fn validate_message(packet: &PacketType) -> Result<(), MessageError> {
             signature,
             public_key,
             challenge_id,
+            ..
         } => validate_challenge_response(signature, public_key, challenge_id),
         
         PacketType::IpAssign {
@@ -173,7 +282,7 @@ pub fn validate_message(packet: &PacketType) -> Result<(), MessageError> {
             session_id,
             encrypted_session_key,
             key_nonce,
-            encryption_algorithm: _, 
+            ..
         } => {
             // Validate IP address format
             if !ip_address.contains('.') || ip_address.split('.').count() != 4 {
@@ -203,7 +312,7 @@ pub fn validate_message(packet: &PacketType) -> Result<(), MessageError> {
             Ok(())
         }
         
-        PacketType::Data { encrypted, nonce, counter, padding: _, encryption_algorithm: _ } => {
+        PacketType::Data { encrypted, nonce, counter, .. } => {
             validate_data(encrypted, nonce, *counter)
         }
         
@@ -255,7 +364,7 @@ pub fn validate_message(packet: &PacketType) -> Result<(), MessageError> {
             Ok(())
         }
         
-        PacketType::IpRenewal { session_id, ip_address } => {
+        PacketType::IpRenewal { session_id, ip_address, mac, .. } => {
             if session_id.is_empty() {
                 return Err(MessageError::MissingField("session_id".to_string()));
             }
@@ -264,37 +373,108 @@ pub fn validate_message(packet: &PacketType) -> Result<(), MessageError> {
                 return Err(MessageError::MissingField("ip_address".to_string()));
             }
             
+            if let Some(mac) = mac {
+                if mac.len() != 32 {
+                    return Err(MessageError::InvalidValue(format!(
+                        "Invalid renewal MAC length: {}", mac.len()
+                    )));
+                }
+            }
+            
             Ok(())
         }
         
-        PacketType::IpRenewalResponse { session_id, expires_at, success: _ } => {
+        PacketType::IpRenewalResponse { session_id, expires_at, success, reason } => {
             if session_id.is_empty() {
                 return Err(MessageError::MissingField("session_id".to_string()));
             }
             
-            if *expires_at == 0 {
-                return Err(MessageError::InvalidValue("expires_at cannot be zero".to_string()));
+            // Failed renewals carry no expiry but must say why
+            if *success {
+                if *expires_at == 0 {
+                    return Err(MessageError::InvalidValue("expires_at cannot be zero".to_string()));
+                }
+            } else if reason.is_none() {
+                return Err(MessageError::MissingField("reason".to_string()));
+            }
+            
+            Ok(())
+        }
+        
+        PacketType::PacketTooBig { size, mtu } => {
+            if *mtu == 0 {
+                return Err(MessageError::InvalidValue("mtu cannot be zero".to_string()));
             }
             
-            // success is a boolean, always valid
+            if *size <= *mtu as u32 {
+                return Err(MessageError::InvalidValue(format!(
+                    "size {} does not exceed mtu {}", size, mtu
+                )));
+            }
             
             Ok(())
         }
         
-        PacketType::Disconnect { reason: _, message } => {
+        PacketType::Disconnect { reason: _, message, retry, mac, .. } => {
             if message.is_empty() {
                 return Err(MessageError::MissingField("message".to_string()));
             }
             
+            if let Some(mac) = mac {
+                if mac.len() != 32 {
+                    return Err(MessageError::InvalidValue(format!(
+                        "Invalid disconnect MAC length: {}", mac.len()
+                    )));
+                }
+            }
+            
+            validate_retry_policy(retry.as_ref())
+        }
+        
+        PacketType::DisconnectAck { .. } => Ok(()),
+        
+        PacketType::FeatureAck { .. } => Ok(()),
+        
+        PacketType::DeferKeyRotation { seconds } => {
+            if *seconds == 0 {
+                return Err(MessageError::InvalidValue("seconds cannot be zero".to_string()));
+            }
+            
+            Ok(())
+        }
+        
+        PacketType::KeyRotationDeferred { .. } => Ok(()),
+        
+        PacketType::ResumptionToken { token, ttl_secs } => {
+            if token.len() != RESUMPTION_TOKEN_LENGTH {
+                return Err(MessageError::InvalidValue(format!(
+                    "Invalid resumption token length: {}", token.len()
+                )));
+            }
+            
+            if *ttl_secs == 0 {
+                return Err(MessageError::InvalidValue("ttl_secs cannot be zero".to_string()));
+            }
+            
+            Ok(())
+        }
+        
+        PacketType::ServerLoad { active_sessions: _, load_factor } => {
+            if !(0.0..=1.0).contains(load_factor) {
+                return Err(MessageError::InvalidValue(format!(
+                    "load_factor must be between 0 and 1: {}", load_factor
+                )));
+            }
+            
             Ok(())
         }
         
-        PacketType::Error { code: _, message } => {
+        PacketType::Error { code: _, message, retry } => {
             if message.is_empty() {
                 return Err(MessageError::MissingField("message".to_string()));
             }
             
-            Ok(())
+            validate_retry_policy(retry.as_ref())
         }
     }
 }
@@ -383,6 +563,7 @@ mod tests {
             nonce: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12],
             counter: 1,
             padding: None,
+            fragment: None,
         };
 
         let result = validate_message(&data);
@@ -394,6 +575,7 @@ mod tests {
             nonce: vec![1, 2, 3], // Too short
             counter: 1,
             padding: None,
+            fragment: None,
         };
 
         let result = validate_message(&data);
@@ -416,5 +598,42 @@ mod tests {
 
         let result = validate_message(&ping);
         assert!(result.is_ok());
+
+        // Hello must carry a parseable version
+        assert!(validate_message(&PacketType::Hello { version: "1.2.0".to_string() }).is_ok());
+        assert!(validate_message(&PacketType::Hello { version: "latest".to_string() }).is_err());
+    }
+
+    #[test]
+    fn test_check_protocol_version() {
+        // Too old
+        assert!(matches!(check_protocol_version("0.9.5"), Err(MessageError::VersionMismatch(..))));
+
+        // Too new: a major version this server does not speak
+        assert!(matches!(check_protocol_version("2.0.0"), Err(MessageError::VersionMismatch(..))));
+
+        // Compatible, including newer minor versions and a missing patch number
+        assert!(check_protocol_version(PROTOCOL_VERSION).is_ok());
+        assert!(check_protocol_version("1.0").is_ok());
+        assert!(check_protocol_version("1.4.2").is_ok());
+
+        // Malformed
+        assert!(matches!(check_protocol_version("1.x"), Err(MessageError::InvalidValue(_))));
+        assert!(matches!(check_protocol_version("1.0.0.1"), Err(MessageError::InvalidValue(_))));
+    }
+
+    #[test]
+    fn test_validate_resumption() {
+        let token = "a".repeat(RESUMPTION_TOKEN_LENGTH);
+        assert!(validate_resumption(None, None).is_ok());
+        assert!(validate_resumption(Some(&token), Some(&[0; 32])).is_ok());
+
+        // Token and proof travel together
+        assert!(matches!(validate_resumption(Some(&token), None), Err(MessageError::MissingField(_))));
+        assert!(matches!(validate_resumption(None, Some(&[0; 32])), Err(MessageError::MissingField(_))));
+
+        assert!(validate_resumption(Some("short"), Some(&[0; 32])).is_err());
+        assert!(validate_resumption(Some(&"!".repeat(RESUMPTION_TOKEN_LENGTH)), Some(&[0; 32])).is_err());
+        assert!(validate_resumption(Some(&token), Some(&[0; 16])).is_err());
     }
 }
@@ -0,0 +1,611 @@
+// src/server/admin.rs
+//! Administrative operations for a running server.
+//!
+//! This module exposes operator-facing controls over live server state.
+//! It is only compiled with the `admin-api` feature.
+
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Notify, RwLock};
+use tokio::time;
+use tracing::{debug, info, warn};
+
+use crate::auth::acl::AccessControlManager;
+use crate::config::constants::ADMIN_REQUEST_TIMEOUT;
+use crate::crypto::KeyManager;
+use crate::crypto::session::{KeyRotationRecord, SessionKeyManager};
+use crate::network::ip_pool::IpPoolManager;
+use crate::server::core::ServerState;
+use crate::server::routing::{PacketRouter, RateLimitOverride, TrafficLimits};
+use crate::server::session::{SessionManager, SessionStats};
+use crate::server::warmup::Warmup;
+
+/// Error type for administrative operations
+#[derive(Debug, thiserror::Error)]
+pub enum AdminError {
+    #[error("Feature disabled: {0}")]
+    Disabled(String),
+
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Operation failed: {0}")]
+    Failed(String),
+}
+
+/// Kind of vertex in the session topology graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TopologyNodeKind {
+    /// Source network (/24 for IPv4, /64 for IPv6)
+    Network,
+    /// Client public key
+    PublicKey,
+    /// Assigned tunnel IP
+    AssignedIp,
+    /// ACL access level
+    Tier,
+}
+
+/// Vertex in the session topology graph
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct TopologyNode {
+    pub kind: TopologyNodeKind,
+    /// Unique id, `<kind>:<label>`
+    pub id: String,
+    pub label: String,
+}
+
+/// Active-session graph linking source networks, public keys, assigned IPs and tiers
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SessionTopology {
+    pub nodes: Vec<TopologyNode>,
+    /// Directed edges as `(from, to)` node ids
+    pub edges: Vec<(String, String)>,
+}
+
+impl SessionTopology {
+    /// Build the graph from `(source, public_key, assigned_ip, tier)` tuples.
+    /// Nodes and edges are deduplicated and sorted so output is stable.
+    pub fn from_sessions<'a>(
+        sessions: impl IntoIterator<Item = (IpAddr, &'a str, &'a str, Option<u8>)>,
+    ) -> Self {
+        let mut nodes = BTreeSet::new();
+        let mut edges = BTreeSet::new();
+        let mut node = |kind, label: String| {
+            let prefix = match kind {
+                TopologyNodeKind::Network => "net",
+                TopologyNodeKind::PublicKey => "key",
+                TopologyNodeKind::AssignedIp => "ip",
+                TopologyNodeKind::Tier => "tier",
+            };
+            let id = format!("{}:{}", prefix, label);
+            nodes.insert(TopologyNode { kind, id: id.clone(), label });
+            id
+        };
+
+        for (source, public_key, assigned_ip, tier) in sessions {
+            let network = node(TopologyNodeKind::Network, source_network(source));
+            let key = node(TopologyNodeKind::PublicKey, public_key.to_string());
+            let ip = node(TopologyNodeKind::AssignedIp, assigned_ip.to_string());
+            edges.insert((network, key.clone()));
+            edges.insert((key.clone(), ip));
+            if let Some(level) = tier {
+                let tier = node(TopologyNodeKind::Tier, level.to_string());
+                edges.insert((key, tier));
+            }
+        }
+
+        Self {
+            nodes: nodes.into_iter().collect(),
+            edges: edges.into_iter().collect(),
+        }
+    }
+
+    /// Render as a Graphviz DOT digraph
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph sessions {\n    rankdir=LR;\n");
+        for node in &self.nodes {
+            let shape = match node.kind {
+                TopologyNodeKind::Network => "folder",
+                TopologyNodeKind::PublicKey => "ellipse",
+                TopologyNodeKind::AssignedIp => "box",
+                TopologyNodeKind::Tier => "diamond",
+            };
+            let _ = writeln!(dot, "    {:?} [label={:?}, shape={}];", node.id, node.label, shape);
+        }
+        for (from, to) in &self.edges {
+            let _ = writeln!(dot, "    {:?} -> {:?};", from, to);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Approximate memory held by one active session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SessionMemory {
+    pub session_id: String,
+    pub client_id: String,
+    /// Estimate from `SessionManager::session_memory`
+    pub approx_bytes: usize,
+}
+
+/// Aggregate a client address to its source network
+fn source_network(addr: IpAddr) -> String {
+    match addr {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            format!("{}.{}.{}.0/24", a, b, c)
+        }
+        IpAddr::V6(v6) => {
+            let s = v6.segments();
+            format!("{:x}:{:x}:{:x}:{:x}::/64", s[0], s[1], s[2], s[3])
+        }
+    }
+}
+
+/// Handle for administrative operations on a running server
+#[derive(Clone)]
+pub struct AdminApi {
+    /// Session manager for client sessions
+    session_manager: Arc<SessionManager>,
+    /// Session key manager for key metadata queries
+    session_key_manager: Arc<SessionKeyManager>,
+    /// Server identity keys, for rotation
+    key_manager: Arc<KeyManager>,
+    /// Access control for tier lookups
+    acl_manager: Arc<AccessControlManager>,
+    /// Packet router holding the per-client rate limiters
+    packet_router: Arc<PacketRouter>,
+    /// IP pool, for per-client lease expiry
+    ip_pool: Arc<IpPoolManager>,
+    /// Wakes the server's owner to reload the ACL and configuration
+    reload_requests: Arc<Notify>,
+    /// Lifecycle state of the server, for the health endpoints
+    state: Arc<RwLock<ServerState>>,
+    /// Startup warmup window; the server is not ready until it has passed
+    warmup: Arc<Warmup>,
+    /// Chaos controller, if chaos mode is enabled
+    #[cfg(feature = "chaos")]
+    chaos: Option<Arc<crate::server::chaos::ChaosController>>,
+}
+
+impl AdminApi {
+    /// Create a new admin API handle
+    pub fn new(
+        session_manager: Arc<SessionManager>,
+        session_key_manager: Arc<SessionKeyManager>,
+        key_manager: Arc<KeyManager>,
+        acl_manager: Arc<AccessControlManager>,
+        packet_router: Arc<PacketRouter>,
+        ip_pool: Arc<IpPoolManager>,
+        reload_requests: Arc<Notify>,
+        state: Arc<RwLock<ServerState>>,
+        warmup: Arc<Warmup>,
+        #[cfg(feature = "chaos")] chaos: Option<Arc<crate::server::chaos::ChaosController>>,
+    ) -> Self {
+        Self {
+            session_manager,
+            session_key_manager,
+            key_manager,
+            acl_manager,
+            packet_router,
+            ip_pool,
+            reload_requests,
+            state,
+            warmup,
+            #[cfg(feature = "chaos")]
+            chaos,
+        }
+    }
+
+    /// Get the key rotation history (ids and timestamps only) for a client
+    pub async fn session_key_history(&self, client_id: &str) -> Result<Vec<KeyRotationRecord>, AdminError> {
+        let history = self.session_key_manager.key_history(client_id).await;
+        if history.is_empty() {
+            return Err(AdminError::NotFound(format!("no key history for client {}", client_id)));
+        }
+        Ok(history)
+    }
+
+    /// Export the active sessions as a graph of source networks, public keys,
+    /// assigned IPs and tiers (ACL access levels). Read-only snapshot of the
+    /// in-memory session table.
+    pub async fn session_topology(&self) -> SessionTopology {
+        let sessions = self.session_manager.all_sessions().await;
+        let mut rows = Vec::with_capacity(sessions.len());
+        for session in &sessions {
+            let tier = self.acl_manager.get_entry(&session.client_id).await.map(|e| e.access_level);
+            rows.push((session.address.ip(), session.client_id.as_str(), session.ip_address.as_str(), tier));
+        }
+        SessionTopology::from_sessions(rows)
+    }
+
+    /// Override the inbound rate limits of all active and future sessions of
+    /// a client until cleared (0 = unlimited). `burst_secs` is how many
+    /// seconds of traffic at the limit each bucket can absorb at once.
+    pub async fn set_client_rate_limit(
+        &self,
+        public_key: &str,
+        bytes_per_sec: u64,
+        packets_per_sec: u64,
+        burst_secs: u64,
+    ) -> Result<(), AdminError> {
+        if burst_secs == 0 {
+            return Err(AdminError::InvalidRequest("burst must be at least 1 second".to_string()));
+        }
+        self.packet_router.set_rate_limit_override(public_key, RateLimitOverride {
+            limits: TrafficLimits { packets_per_sec, bytes_per_sec },
+            burst_secs,
+        }).await;
+        info!(
+            "Rate limit override for {}: {} bytes/sec, {} packets/sec, {}s burst",
+            public_key, bytes_per_sec, packets_per_sec, burst_secs
+        );
+        Ok(())
+    }
+
+    /// Get a client's rate limit override, if any
+    pub async fn get_client_rate_limit(&self, public_key: &str) -> Option<RateLimitOverride> {
+        self.packet_router.rate_limit_override(public_key).await
+    }
+
+    /// Remove a client's rate limit override, reverting it to the server defaults
+    pub async fn clear_client_rate_limit(&self, public_key: &str) -> Result<(), AdminError> {
+        if self.packet_router.clear_rate_limit_override(public_key).await {
+            info!("Rate limit override for {} cleared", public_key);
+            Ok(())
+        } else {
+            Err(AdminError::NotFound(format!("no rate limit override for client {}", public_key)))
+        }
+    }
+
+    /// Ask the server to reload its ACL and configuration, as on SIGHUP.
+    /// Requests made before a pending reload runs are merged into it.
+    pub fn request_reload(&self) {
+        info!("Reload requested through the admin API");
+        self.reload_requests.notify_one();
+    }
+
+    /// Server public keys clients may authenticate against: the current key,
+    /// then the previous one while its rotation overlap lasts
+    pub async fn server_keys(&self) -> Vec<String> {
+        self.key_manager.accepted_public_keys().await.iter().map(|key| key.to_string()).collect()
+    }
+
+    /// Rotate the server identity key to a freshly generated one, returning
+    /// the accepted keys. Clients that were just sent the previous key can
+    /// still authenticate with it for the rotation overlap, and a further
+    /// rotation is refused until that overlap has passed.
+    pub async fn rotate_server_key(&self) -> Result<Vec<String>, AdminError> {
+        let public_key = self.key_manager.rotate_keypair().await
+            .map_err(|e| AdminError::Failed(format!("server key rotation: {}", e)))?;
+        info!("Server key rotated through the admin API, now serving {}", public_key);
+        Ok(self.server_keys().await)
+    }
+
+    /// Live traffic, RTT, session key age and IP lease remaining for each active session, oldest first
+    pub async fn session_stats(&self) -> Vec<SessionStats> {
+        self.session_manager.session_stats(&self.session_key_manager, &self.ip_pool).await
+    }
+
+    /// Current server state, and whether the server is ready for new
+    /// clients: running and past its startup warmup. The TUN device and IP
+    /// pool are set up before the server exists, so they need no check here.
+    pub async fn readiness(&self) -> (ServerState, bool) {
+        let state = *self.state.read().await;
+        (state, state == ServerState::Running && self.warmup.is_warm())
+    }
+
+    /// Serve admin requests over HTTP:
+    /// `GET /sessions` returns `session_stats` as JSON,
+    /// `POST /reload` calls `request_reload` (loopback peers only, anyone
+    /// else gets 403 since the listener is otherwise read-only),
+    /// `GET /server-keys` returns
+    /// `server_keys` as JSON, and the load balancer probes
+    /// `GET /healthz` (always 200) and `GET /readyz` (200 only when ready,
+    /// else 503) answer with the server state.
+    pub fn serve_http(self, listener: TcpListener) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!("Failed to accept admin connection: {}", e);
+                        continue;
+                    }
+                };
+                let api = self.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = api.answer_http(stream, peer).await {
+                        debug!("Admin request from {} failed: {}", peer, e);
+                    }
+                });
+            }
+        })
+    }
+
+    /// Answer a single HTTP request on an admin connection from `peer`
+    async fn answer_http(&self, mut stream: TcpStream, peer: SocketAddr) -> std::io::Result<()> {
+        let mut buf = [0u8; 1024];
+        let n = time::timeout(ADMIN_REQUEST_TIMEOUT, stream.read(&mut buf)).await
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out reading request"))??;
+        let request = String::from_utf8_lossy(&buf[..n]);
+        let mut request_line = request.lines().next().unwrap_or_default().split_whitespace();
+        let (method, path) = match (request_line.next(), request_line.next()) {
+            (Some(method), Some(target)) => (method, target.split('?').next()),
+            _ => ("", None),
+        };
+
+        let (status, content_type, body) = match (method, path) {
+            ("GET", Some("/sessions")) => match serde_json::to_string(&self.session_stats().await) {
+                Ok(json) => ("200 OK", "application/json", json),
+                Err(e) => ("500 Internal Server Error", "text/plain; charset=utf-8", format!("{}\n", e)),
+            },
+            ("POST", Some("/reload")) if peer.ip().is_loopback() => {
+                self.request_reload();
+                ("202 Accepted", "text/plain; charset=utf-8", "Reload requested\n".to_string())
+            }
+            ("POST", Some("/reload")) => {
+                warn!("Refused admin reload request from non-loopback peer {}", peer);
+                ("403 Forbidden", "text/plain; charset=utf-8", "Reload only accepted from loopback\n".to_string())
+            }
+            ("GET", Some("/server-keys")) => match serde_json::to_string(&self.server_keys().await) {
+                Ok(json) => ("200 OK", "application/json", json),
+                Err(e) => ("500 Internal Server Error", "text/plain; charset=utf-8", format!("{}\n", e)),
+            },
+            ("GET", Some("/healthz")) => {
+                let (state, _) = self.readiness().await;
+                ("200 OK", "text/plain; charset=utf-8", format!("{}\n", state.as_str()))
+            }
+            ("GET", Some("/readyz")) => match self.readiness().await {
+                (state, true) => ("200 OK", "text/plain; charset=utf-8", format!("{}\n", state.as_str())),
+                (ServerState::Running, false) => ("503 Service Unavailable", "text/plain; charset=utf-8", "running (warming up)\n".to_string()),
+                (state, false) => ("503 Service Unavailable", "text/plain; charset=utf-8", format!("{}\n", state.as_str())),
+            },
+            _ => ("404 Not Found", "text/plain; charset=utf-8", "Not Found\n".to_string()),
+        };
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status, content_type, body.len(), body
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await
+    }
+
+    /// Approximate memory held by each active session, largest first
+    pub async fn session_memory(&self) -> Vec<SessionMemory> {
+        self.session_manager.session_memory(&self.packet_router).await
+            .into_iter()
+            .map(|(session_id, client_id, approx_bytes)| SessionMemory { session_id, client_id, approx_bytes })
+            .collect()
+    }
+
+    #[cfg(feature = "chaos")]
+    fn chaos(&self) -> Result<&Arc<crate::server::chaos::ChaosController>, AdminError> {
+        self.chaos.as_ref()
+            .ok_or_else(|| AdminError::Disabled("chaos mode is not enabled in the server configuration".to_string()))
+    }
+
+    /// Enable or update packet loss/latency injection for a client
+    #[cfg(feature = "chaos")]
+    pub async fn set_client_chaos(
+        &self,
+        client_id: &str,
+        settings: crate::server::chaos::ChaosSettings,
+    ) -> Result<(), AdminError> {
+        let chaos = self.chaos()?;
+        chaos.set_client(client_id, settings).await
+            .map_err(|e| AdminError::InvalidRequest(e.to_string()))?;
+        if self.session_manager.get_session_by_client_id(client_id).await.is_none() {
+            info!("Chaos settings stored for {}; client is not currently connected", client_id);
+        }
+        Ok(())
+    }
+
+    /// Disable packet loss/latency injection for a client
+    #[cfg(feature = "chaos")]
+    pub async fn clear_client_chaos(&self, client_id: &str) -> Result<(), AdminError> {
+        if self.chaos()?.clear_client(client_id).await {
+            Ok(())
+        } else {
+            Err(AdminError::NotFound(format!("no chaos settings for client {}", client_id)))
+        }
+    }
+
+    /// Get the packet loss/latency settings for a client
+    #[cfg(feature = "chaos")]
+    pub async fn get_client_chaos(
+        &self,
+        client_id: &str,
+    ) -> Result<Option<crate::server::chaos::ChaosSettings>, AdminError> {
+        Ok(self.chaos()?.get_client(client_id).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_topology() {
+        let a: IpAddr = "203.0.113.7".parse().unwrap();
+        let b: IpAddr = "203.0.113.99".parse().unwrap();
+        let c: IpAddr = "2001:db8:1:2:3::1".parse().unwrap();
+        let topology = SessionTopology::from_sessions(vec![
+            (a, "KeyA", "10.7.0.2", Some(50)),
+            (b, "KeyB", "10.7.0.3", Some(50)),
+            (c, "KeyA", "10.7.0.4", None),
+        ]);
+
+        // Both IPv4 clients share one /24
+        let networks: Vec<_> = topology.nodes.iter()
+            .filter(|n| n.kind == TopologyNodeKind::Network)
+            .map(|n| n.label.as_str())
+            .collect();
+        assert_eq!(networks, vec!["2001:db8:1:2::/64", "203.0.113.0/24"]);
+        assert_eq!(topology.nodes.iter().filter(|n| n.kind == TopologyNodeKind::PublicKey).count(), 2);
+        assert_eq!(topology.nodes.iter().filter(|n| n.kind == TopologyNodeKind::Tier).count(), 1);
+        assert!(topology.edges.contains(&("key:KeyA".to_string(), "ip:10.7.0.4".to_string())));
+        assert!(topology.edges.contains(&("key:KeyB".to_string(), "tier:50".to_string())));
+        // 3 network->key, 3 key->ip, 2 key->tier (KeyA deduplicated)
+        assert_eq!(topology.edges.len(), 8);
+
+        let dot = topology.to_dot();
+        assert!(dot.starts_with("digraph sessions {"));
+        assert!(dot.contains("\"net:203.0.113.0/24\" -> \"key:KeyA\";"));
+
+        let json = serde_json::to_value(&topology).unwrap();
+        assert_eq!(json["nodes"][0]["kind"], "network");
+    }
+
+    async fn probe(addr: std::net::SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(format!("GET {} HTTP/1.1\r\nHost: admin\r\n\r\n", path).as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_health_endpoints() {
+        use crate::config::settings::WarmupMode;
+        use std::time::Duration;
+
+        let dir = tempfile::tempdir().unwrap();
+        let acl = AccessControlManager::new(dir.path().join("acl.json")).await.unwrap();
+        let state = Arc::new(RwLock::new(ServerState::Running));
+        let api = AdminApi::new(
+            Arc::new(SessionManager::new(5, Duration::from_secs(3600))),
+            Arc::new(SessionKeyManager::new(Duration::from_secs(3600), 1_000_000)),
+            Arc::new(KeyManager::new(dir.path().join("server_key"), Duration::from_secs(3600), 100).await.unwrap()),
+            Arc::new(acl),
+            Arc::new(PacketRouter::new(2048, false)),
+            Arc::new(IpPoolManager::new("10.7.0.0/24", 3600).await.unwrap()),
+            Arc::new(Notify::new()),
+            state.clone(),
+            Arc::new(Warmup::new(Duration::ZERO, WarmupMode::Throttle, 0)),
+            #[cfg(feature = "chaos")]
+            None,
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = api.serve_http(listener);
+
+        // Running: live and ready
+        let health = probe(addr, "/healthz").await;
+        assert!(health.starts_with("HTTP/1.1 200 OK\r\n") && health.ends_with("\r\n\r\nrunning\n"));
+        let ready = probe(addr, "/readyz").await;
+        assert!(ready.starts_with("HTTP/1.1 200 OK\r\n") && ready.ends_with("\r\n\r\nrunning\n"));
+
+        // Shutting down: still live, no longer ready
+        *state.write().await = ServerState::ShuttingDown;
+        let health = probe(addr, "/healthz").await;
+        assert!(health.starts_with("HTTP/1.1 200 OK\r\n") && health.ends_with("shutting_down\n"));
+        let ready = probe(addr, "/readyz").await;
+        assert!(ready.starts_with("HTTP/1.1 503 Service Unavailable\r\n") && ready.ends_with("shutting_down\n"));
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_reload_loopback_only() {
+        use crate::config::settings::WarmupMode;
+        use std::time::Duration;
+
+        let dir = tempfile::tempdir().unwrap();
+        let reload_requests = Arc::new(Notify::new());
+        let api = AdminApi::new(
+            Arc::new(SessionManager::new(5, Duration::from_secs(3600))),
+            Arc::new(SessionKeyManager::new(Duration::from_secs(3600), 1_000_000)),
+            Arc::new(KeyManager::new(dir.path().join("server_key"), Duration::from_secs(3600), 100).await.unwrap()),
+            Arc::new(AccessControlManager::new(dir.path().join("acl.json")).await.unwrap()),
+            Arc::new(PacketRouter::new(2048, false)),
+            Arc::new(IpPoolManager::new("10.7.0.0/24", 3600).await.unwrap()),
+            reload_requests.clone(),
+            Arc::new(RwLock::new(ServerState::Running)),
+            Arc::new(Warmup::new(Duration::ZERO, WarmupMode::Throttle, 0)),
+            #[cfg(feature = "chaos")]
+            None,
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let post_reload = || async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(b"POST /reload HTTP/1.1\r\nHost: admin\r\n\r\n").await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+
+        // A remote peer is refused and nothing is reloaded
+        let remote = tokio::spawn(post_reload());
+        let (stream, _) = listener.accept().await.unwrap();
+        api.answer_http(stream, "203.0.113.7:40000".parse().unwrap()).await.unwrap();
+        assert!(remote.await.unwrap().starts_with("HTTP/1.1 403 Forbidden\r\n"));
+        assert!(time::timeout(Duration::from_millis(50), reload_requests.notified()).await.is_err());
+
+        // A loopback peer gets its reload
+        let server = api.serve_http(listener);
+        assert!(post_reload().await.starts_with("HTTP/1.1 202 Accepted\r\n"));
+        time::timeout(Duration::from_secs(1), reload_requests.notified()).await.unwrap();
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_key_rotation() {
+        use crate::config::settings::WarmupMode;
+        use std::time::Duration;
+
+        let dir = tempfile::tempdir().unwrap();
+        let key_manager = Arc::new(KeyManager::new(dir.path().join("server_key"), Duration::from_secs(3600), 100).await.unwrap());
+        let api = AdminApi::new(
+            Arc::new(SessionManager::new(5, Duration::from_secs(3600))),
+            Arc::new(SessionKeyManager::new(Duration::from_secs(3600), 1_000_000)),
+            key_manager.clone(),
+            Arc::new(AccessControlManager::new(dir.path().join("acl.json")).await.unwrap()),
+            Arc::new(PacketRouter::new(2048, false)),
+            Arc::new(IpPoolManager::new("10.7.0.0/24", 3600).await.unwrap()),
+            Arc::new(Notify::new()),
+            Arc::new(RwLock::new(ServerState::Running)),
+            Arc::new(Warmup::new(Duration::ZERO, WarmupMode::Throttle, 0)),
+            #[cfg(feature = "chaos")]
+            None,
+        );
+        let old_key = key_manager.public_key().await.to_string();
+        assert_eq!(api.server_keys().await, vec![old_key.clone()]);
+
+        // The new key is served, the old one still accepted during the overlap
+        let keys = api.rotate_server_key().await.unwrap();
+        let new_key = key_manager.public_key().await.to_string();
+        assert_eq!(keys, vec![new_key.clone(), old_key.clone()]);
+
+        // Rotating again would drop the old key before its overlap ends
+        assert!(matches!(api.rotate_server_key().await, Err(AdminError::Failed(_))));
+        assert_eq!(api.server_keys().await, vec![new_key.clone(), old_key.clone()]);
+
+        // Keys are listed over HTTP, but cannot be rotated there
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = api.serve_http(listener);
+        let response = probe(addr, "/server-keys").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(&serde_json::to_string(&[new_key.clone(), old_key]).unwrap()));
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"POST /server-keys/rotate HTTP/1.1\r\nHost: admin\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert_eq!(key_manager.public_key().await.to_string(), new_key);
+        server.abort();
+    }
+}
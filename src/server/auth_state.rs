@@ -0,0 +1,306 @@
+// src/server/auth_state.rs
+//! Authentication phase state machine.
+//!
+//! A client must send `Auth`, receive a `Challenge`, then answer with a
+//! `ChallengeResponse`. Clients that presented a verified TLS client
+//! certificate skip the challenge: their `Auth` must name the certificate
+//! key and authenticates them directly. This module encodes those legal transitions so that
+//! out-of-order, duplicate or mismatched packets are rejected the same way
+//! regardless of where in the handshake they arrive. Effects (challenge
+//! generation, signature verification, ACL checks) stay with the caller.
+//!
+//! Before `Auth`, a client may send `Hello` to learn the server's
+//! capabilities. It does not change the state; whether it is answered
+//! (enabled, rate limit, once per connection) is also up to the caller.
+//!
+//! `Data` pipelined before the handshake completes is never buffered: it is
+//! rejected as `HandshakeIncomplete`, which the caller may tolerate a few
+//! times before giving up. Data the client pipelines after its
+//! `ChallengeResponse` and that has arrived by the time `IpAssign` is sent
+//! is rejected the same way, against the same budget.
+
+use crate::protocol::serialization::get_packet_type_name;
+use crate::protocol::types::{ErrorCode, PacketType};
+use crate::utils::security::StringValidator;
+
+/// Illegal or invalid input during authentication
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AuthViolation {
+    #[error("Expected {expected}")]
+    UnexpectedPacket { expected: &'static str, received: &'static str },
+
+    #[error("Invalid public key format")]
+    InvalidPublicKey,
+
+    #[error("Public key mismatch")]
+    PublicKeyMismatch,
+
+    #[error("Handshake incomplete: Data received while waiting for {expected}")]
+    HandshakeIncomplete { expected: &'static str },
+}
+
+impl AuthViolation {
+    /// Error code reported to the client
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            AuthViolation::UnexpectedPacket { .. } => ErrorCode::InvalidMessage,
+            AuthViolation::InvalidPublicKey | AuthViolation::PublicKeyMismatch => ErrorCode::AuthenticationFailed,
+            AuthViolation::HandshakeIncomplete { .. } => ErrorCode::HandshakeIncomplete,
+        }
+    }
+}
+
+/// Client identity and preferences from the `Auth` packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthRequest {
+    /// Client public key
+    pub public_key: String,
+    /// Client-reported protocol version
+    pub version: String,
+    /// Preferred encryption algorithm, as sent by the client
+    pub encryption_algorithm: Option<String>,
+    /// Advertised features
+    pub features: Vec<String>,
+    /// Token offered to resume a previous session
+    pub resumption_token: Option<String>,
+    /// Proof of the previous session key accompanying `resumption_token`
+    pub resumption_proof: Option<Vec<u8>>,
+    /// Requested IP lease in seconds
+    pub lease_duration: Option<u64>,
+}
+
+/// Action the caller must take for an accepted packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthTransition {
+    /// Answer a `Hello` with the server's `Capabilities`, staying in `AwaitingAuth`
+    DescribeCapabilities { version: String },
+    /// Generate and send a challenge, then move to `Challenged`
+    IssueChallenge(AuthRequest),
+    /// Verify the challenge signature (and proof of work), then move to `Authenticated`
+    VerifyResponse { signature: String, challenge_id: String, pow_nonce: Option<u64> },
+}
+
+/// Authentication phase of a connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthState {
+    /// Waiting for the initial `Auth` packet
+    AwaitingAuth,
+    /// Challenge sent, waiting for the `ChallengeResponse`
+    Challenged { request: AuthRequest, challenge_id: String },
+    /// Challenge verified
+    Authenticated { request: AuthRequest },
+}
+
+impl AuthState {
+    /// Description of the packet expected in this state
+    pub fn expected(&self) -> &'static str {
+        match self {
+            AuthState::AwaitingAuth => "authentication message",
+            AuthState::Challenged { .. } => "challenge response",
+            AuthState::Authenticated { .. } => "no further authentication messages",
+        }
+    }
+
+    /// Check a received packet against this state
+    pub fn next(&self, packet: PacketType) -> Result<AuthTransition, AuthViolation> {
+        match (self, packet) {
+            (AuthState::AwaitingAuth, PacketType::Hello { version }) => {
+                Ok(AuthTransition::DescribeCapabilities { version })
+            }
+            (AuthState::AwaitingAuth, PacketType::Auth {
+                public_key, version, features, encryption_algorithm, resumption_token, resumption_proof, lease_duration, ..
+            }) => {
+                if !StringValidator::is_valid_solana_pubkey(&public_key) {
+                    return Err(AuthViolation::InvalidPublicKey);
+                }
+                Ok(AuthTransition::IssueChallenge(AuthRequest {
+                    public_key,
+                    version,
+                    encryption_algorithm,
+                    features,
+                    resumption_token,
+                    resumption_proof,
+                    lease_duration,
+                }))
+            }
+            (AuthState::Challenged { request, .. }, PacketType::ChallengeResponse { signature, public_key, challenge_id, pow_nonce }) => {
+                if public_key != request.public_key {
+                    return Err(AuthViolation::PublicKeyMismatch);
+                }
+                Ok(AuthTransition::VerifyResponse { signature, challenge_id, pow_nonce })
+            }
+            (state, PacketType::Data { .. }) => Err(AuthViolation::HandshakeIncomplete {
+                expected: state.expected(),
+            }),
+            (state, packet) => Err(AuthViolation::UnexpectedPacket {
+                expected: state.expected(),
+                received: get_packet_type_name(&packet),
+            }),
+        }
+    }
+
+    /// Move to `Challenged` once a challenge has been sent
+    pub fn challenged(request: AuthRequest, challenge_id: String) -> Self {
+        AuthState::Challenged { request, challenge_id }
+    }
+
+    /// Move to `Authenticated` once the challenge response has been verified
+    pub fn authenticated(self) -> Result<Self, AuthViolation> {
+        match self {
+            AuthState::Challenged { request, .. } => Ok(AuthState::Authenticated { request }),
+            state => Err(AuthViolation::UnexpectedPacket {
+                expected: state.expected(),
+                received: "ChallengeResponse",
+            }),
+        }
+    }
+
+    /// Move straight to `Authenticated` for an `Auth` request whose key was
+    /// proven by a TLS client certificate
+    pub fn certificate_verified(request: AuthRequest, certificate_key: &str) -> Result<Self, AuthViolation> {
+        if request.public_key != certificate_key {
+            return Err(AuthViolation::PublicKeyMismatch);
+        }
+        Ok(AuthState::Authenticated { request })
+    }
+
+    /// Client request being authenticated, once known
+    pub fn request(&self) -> Option<&AuthRequest> {
+        match self {
+            AuthState::AwaitingAuth => None,
+            AuthState::Challenged { request, .. } | AuthState::Authenticated { request } => Some(request),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT_KEY: &str = "GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB";
+    const OTHER_KEY: &str = "AKnL4NNf3DGWZJS6cPknBuEGnVsV4A4m5tgebLHaRSZ9";
+
+    fn auth(public_key: &str) -> PacketType {
+        PacketType::Auth {
+            public_key: public_key.to_string(),
+            version: "1.0.0".to_string(),
+            features: vec!["packet-too-big".to_string()],
+            encryption_algorithm: None,
+            nonce: "golden-transcript".to_string(),
+            resumption_token: None,
+            resumption_proof: None,
+            lease_duration: None,
+        }
+    }
+
+    fn response(public_key: &str) -> PacketType {
+        PacketType::ChallengeResponse {
+            signature: "sig".to_string(),
+            public_key: public_key.to_string(),
+            challenge_id: "challenge".to_string(),
+            pow_nonce: Some(42),
+        }
+    }
+
+    fn challenged() -> AuthState {
+        match AuthState::AwaitingAuth.next(auth(CLIENT_KEY)).unwrap() {
+            AuthTransition::IssueChallenge(request) => AuthState::challenged(request, "challenge".to_string()),
+            other => panic!("unexpected transition {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_auth_happy_path() {
+        let state = challenged();
+        assert_eq!(state.request().unwrap().features, vec!["packet-too-big".to_string()]);
+        assert_eq!(
+            state.next(response(CLIENT_KEY)),
+            Ok(AuthTransition::VerifyResponse {
+                signature: "sig".to_string(),
+                challenge_id: "challenge".to_string(),
+                pow_nonce: Some(42),
+            })
+        );
+        let state = state.authenticated().unwrap();
+        assert_eq!(state.request().unwrap().public_key, CLIENT_KEY);
+    }
+
+    #[test]
+    fn test_auth_illegal_transitions() {
+        let ping = PacketType::Ping { timestamp: 0, sequence: 0 };
+
+        // Anything but Auth first
+        for packet in [response(CLIENT_KEY), ping.clone()] {
+            let violation = AuthState::AwaitingAuth.next(packet).unwrap_err();
+            assert_eq!(violation.to_string(), "Expected authentication message");
+            assert_eq!(violation.error_code(), ErrorCode::InvalidMessage);
+        }
+        assert_eq!(AuthState::AwaitingAuth.next(auth("not-a-key")), Err(AuthViolation::InvalidPublicKey));
+
+        // Duplicate Auth or anything else while challenged
+        for packet in [auth(CLIENT_KEY), ping.clone()] {
+            let violation = challenged().next(packet).unwrap_err();
+            assert_eq!(violation.to_string(), "Expected challenge response");
+        }
+        let violation = challenged().next(response(OTHER_KEY)).unwrap_err();
+        assert_eq!(violation, AuthViolation::PublicKeyMismatch);
+        assert_eq!(violation.error_code(), ErrorCode::AuthenticationFailed);
+
+        // No authentication packets once authenticated
+        let authenticated = challenged().authenticated().unwrap();
+        assert!(authenticated.next(auth(CLIENT_KEY)).is_err());
+        assert!(authenticated.next(response(CLIENT_KEY)).is_err());
+
+        // Cannot skip the challenge
+        assert!(AuthState::AwaitingAuth.authenticated().is_err());
+        assert!(authenticated.authenticated().is_err());
+    }
+
+    #[test]
+    fn test_hello_before_auth_only() {
+        let hello = PacketType::Hello { version: "1.0.0".to_string() };
+        assert_eq!(
+            AuthState::AwaitingAuth.next(hello.clone()),
+            Ok(AuthTransition::DescribeCapabilities { version: "1.0.0".to_string() })
+        );
+
+        let violation = challenged().next(hello.clone()).unwrap_err();
+        assert_eq!(violation, AuthViolation::UnexpectedPacket { expected: "challenge response", received: "Hello" });
+        assert!(challenged().authenticated().unwrap().next(hello).is_err());
+    }
+
+    #[test]
+    fn test_certificate_skips_challenge() {
+        let request = match AuthState::AwaitingAuth.next(auth(CLIENT_KEY)).unwrap() {
+            AuthTransition::IssueChallenge(request) => request,
+            other => panic!("unexpected transition {:?}", other),
+        };
+
+        let state = AuthState::certificate_verified(request.clone(), CLIENT_KEY).unwrap();
+        assert_eq!(state, AuthState::Authenticated { request: request.clone() });
+        assert!(state.next(response(CLIENT_KEY)).is_err());
+
+        // The Auth packet must name the certificate key
+        assert_eq!(AuthState::certificate_verified(request, OTHER_KEY), Err(AuthViolation::PublicKeyMismatch));
+    }
+
+    #[test]
+    fn test_auth_early_data() {
+        let data = PacketType::Data {
+            encrypted: vec![1, 2, 3],
+            nonce: vec![0; 12],
+            counter: 0,
+            padding: None,
+            encryption_algorithm: None,
+            fragment: None,
+        };
+
+        let violation = AuthState::AwaitingAuth.next(data.clone()).unwrap_err();
+        assert_eq!(violation, AuthViolation::HandshakeIncomplete { expected: "authentication message" });
+        assert_eq!(violation.error_code(), ErrorCode::HandshakeIncomplete);
+
+        let violation = challenged().next(data).unwrap_err();
+        assert_eq!(violation.to_string(), "Handshake incomplete: Data received while waiting for challenge response");
+        assert_eq!(violation.error_code(), ErrorCode::HandshakeIncomplete);
+    }
+}
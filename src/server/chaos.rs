@@ -0,0 +1,168 @@
+// src/server/chaos.rs
+//! Fault injection for client resilience testing.
+//!
+//! This module lets the server probabilistically drop or delay outbound
+//! Data packets for selected clients so that client developers can test
+//! their handling of loss and jitter against a real server. It is only
+//! compiled with the `chaos` feature and must additionally be enabled with
+//! the `enable_chaos_mode` configuration flag.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use rand::{thread_rng, Rng};
+use tokio::sync::RwLock;
+use tracing::warn;
+
+/// Error type for chaos mode operations
+#[derive(Debug, thiserror::Error)]
+pub enum ChaosError {
+    #[error("Invalid rate {0}: must be between 0.0 and 1.0")]
+    InvalidRate(f64),
+
+    #[error("Invalid delay: {0}")]
+    InvalidDelay(String),
+}
+
+/// Fault injection settings for a single client
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChaosSettings {
+    /// Probability of dropping a Data packet (0.0 - 1.0)
+    pub drop_rate: f64,
+    /// Probability of delaying a Data packet (0.0 - 1.0)
+    pub delay_rate: f64,
+    /// Maximum delay applied to a delayed packet in milliseconds
+    pub max_delay_ms: u64,
+}
+
+impl ChaosSettings {
+    /// Validate the settings
+    pub fn validate(&self) -> Result<(), ChaosError> {
+        for rate in [self.drop_rate, self.delay_rate] {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(ChaosError::InvalidRate(rate));
+            }
+        }
+        if self.delay_rate > 0.0 && self.max_delay_ms == 0 {
+            return Err(ChaosError::InvalidDelay(
+                "max_delay_ms must be non-zero when delay_rate is set".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// What to do with an outbound Data packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChaosAction {
+    /// Send the packet normally
+    Deliver,
+    /// Silently discard the packet
+    Drop,
+    /// Send the packet after the given delay
+    Delay(Duration),
+}
+
+/// Per-client fault injection controller
+pub struct ChaosController {
+    /// Active settings keyed by client ID
+    clients: RwLock<HashMap<String, ChaosSettings>>,
+}
+
+impl ChaosController {
+    /// Create a new chaos controller
+    pub fn new() -> Self {
+        warn!("!!! CHAOS MODE ENABLED: outbound Data packets may be dropped or delayed for selected clients. Do not use in production !!!");
+        Self {
+            clients: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Enable or update fault injection for a client
+    pub async fn set_client(&self, client_id: &str, settings: ChaosSettings) -> Result<(), ChaosError> {
+        settings.validate()?;
+        warn!(
+            "CHAOS MODE: client {} drop_rate={} delay_rate={} max_delay_ms={}",
+            client_id, settings.drop_rate, settings.delay_rate, settings.max_delay_ms
+        );
+        self.clients.write().await.insert(client_id.to_string(), settings);
+        Ok(())
+    }
+
+    /// Disable fault injection for a client
+    pub async fn clear_client(&self, client_id: &str) -> bool {
+        let removed = self.clients.write().await.remove(client_id).is_some();
+        if removed {
+            warn!("CHAOS MODE: disabled for client {}", client_id);
+        }
+        removed
+    }
+
+    /// Get the current settings for a client
+    pub async fn get_client(&self, client_id: &str) -> Option<ChaosSettings> {
+        self.clients.read().await.get(client_id).copied()
+    }
+
+    /// Decide what to do with the next outbound Data packet for a client
+    pub async fn decide(&self, client_id: &str) -> ChaosAction {
+        let settings = match self.get_client(client_id).await {
+            Some(settings) => settings,
+            None => return ChaosAction::Deliver,
+        };
+
+        let mut rng = thread_rng();
+        if settings.drop_rate > 0.0 && rng.gen_bool(settings.drop_rate) {
+            return ChaosAction::Drop;
+        }
+        if settings.delay_rate > 0.0 && rng.gen_bool(settings.delay_rate) {
+            let delay_ms = rng.gen_range(1..=settings.max_delay_ms);
+            return ChaosAction::Delay(Duration::from_millis(delay_ms));
+        }
+        ChaosAction::Deliver
+    }
+}
+
+impl Default for ChaosController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unconfigured_client_is_delivered() {
+        let chaos = ChaosController::new();
+        assert_eq!(chaos.decide("client1").await, ChaosAction::Deliver);
+    }
+
+    #[tokio::test]
+    async fn test_drop_and_delay_rates() {
+        let chaos = ChaosController::new();
+
+        chaos.set_client("client1", ChaosSettings { drop_rate: 1.0, delay_rate: 0.0, max_delay_ms: 0 }).await.unwrap();
+        assert_eq!(chaos.decide("client1").await, ChaosAction::Drop);
+
+        chaos.set_client("client1", ChaosSettings { drop_rate: 0.0, delay_rate: 1.0, max_delay_ms: 50 }).await.unwrap();
+        match chaos.decide("client1").await {
+            ChaosAction::Delay(d) => assert!(d >= Duration::from_millis(1) && d <= Duration::from_millis(50)),
+            other => panic!("Expected delay, got {:?}", other),
+        }
+
+        // Other clients are unaffected
+        assert_eq!(chaos.decide("client2").await, ChaosAction::Deliver);
+
+        assert!(chaos.clear_client("client1").await);
+        assert_eq!(chaos.decide("client1").await, ChaosAction::Deliver);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_settings_rejected() {
+        let chaos = ChaosController::new();
+        assert!(chaos.set_client("c", ChaosSettings { drop_rate: 1.5, delay_rate: 0.0, max_delay_ms: 0 }).await.is_err());
+        assert!(chaos.set_client("c", ChaosSettings { drop_rate: 0.0, delay_rate: 0.5, max_delay_ms: 0 }).await.is_err());
+        assert!(chaos.get_client("c").await.is_none());
+    }
+}
@@ -7,30 +7,120 @@
 use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::Arc;
-use std::time::Duration;
-use futures::{SinkExt, StreamExt, stream::{SplitSink, SplitStream}};
+use std::time::{Duration, Instant};
+use rand::{thread_rng, Rng};
+use futures::{FutureExt, SinkExt, StreamExt, stream::{SplitSink, SplitStream}};
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
 use tokio::sync::{Mutex, RwLock};
 use tokio::time;
 use tokio_rustls::{server::TlsStream, TlsAcceptor};
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
 use tokio_tungstenite::WebSocketStream;
-use tracing::{debug, info, trace, warn};
+use tracing::{debug, debug_span, error, info, trace, warn, Instrument};
 
 use crate::auth::AuthManager;
+use crate::auth::audit::{AuthAuditEvent, AuthDecision};
+use crate::auth::manager::AuthError;
+use crate::auth::resumption::ResumedSession;
+use crate::auth::certificate::certificate_public_key;
 use crate::crypto::{KeyManager, SessionKeyManager};
 use crate::crypto::flexible_encryption::EncryptionAlgorithm;
-use crate::crypto::encryption::encrypt_session_key_flexible;
+use crate::crypto::encryption::{encrypt_session_key_flexible, session_key_aad, verify_disconnect, verify_ip_renewal};
 use crate::network::{IpPoolManager, NetworkMonitor};
-use crate::protocol::types::PacketType;
-use crate::protocol::serialization::{packet_to_ws_message, ws_message_to_packet, create_error_packet, create_disconnect_packet, log_packet_info};
-use crate::server::session::{ClientSession, SessionManager};
+use crate::network::ip_pool::IpPoolError;
+use crate::network::origin::OriginProvider;
+use crate::protocol::types::{features, DisconnectReason, ErrorCode, PacketType, RetryPolicy};
+use crate::protocol::serialization::{get_packet_type_name, packet_to_ws_message, ws_message_to_packet, create_error_packet, create_disconnect_packet, log_packet_info, log_sampled_packet};
+use crate::protocol::validation::check_protocol_version;
+use crate::server::session::{apply_session_policy, ClientSession, SessionError, SessionManager, SessionPolicy};
 use crate::server::routing::PacketRouter;
-use crate::server::metrics::ServerMetricsCollector;
+use crate::server::metrics::{HandshakeFailure, HandshakePhase, ServerMetricsCollector};
 use crate::server::core::{ServerError, ServerState};
-use crate::utils::{current_timestamp_millis, random_string};
-use crate::utils::security::StringValidator;
+use crate::config::constants::HANDSHAKE_TIMEOUT;
+use crate::config::settings::AclDenyResponse;
+use crate::utils::{current_timestamp_millis, monotonic_millis, random_string};
 use solana_sdk::pubkey::Pubkey;
 use crate::server::connection::DuplexWebSocketConnection;
+use crate::server::negotiation::{select_encryption_algorithm, server_capabilities, NegotiatedCapabilities};
+use crate::server::auth_state::{AuthRequest, AuthState, AuthTransition, AuthViolation};
+use crate::server::handshake::WsHandshakePolicy;
+use crate::server::workers::PinnedWorkerPool;
+use crate::server::schedule::QuietHours;
+use crate::server::records::{SessionRecord, SessionRecordWriter, TeardownReason, SESSION_RECORD_SCHEMA_VERSION};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::protocol::{frame::coding::CloseCode, CloseFrame};
+
+/// Decide whether a new connection gets detailed packet tracing
+fn sample_for_tracing(rate: f64) -> bool {
+    rate > 0.0 && thread_rng().gen_bool(rate.min(1.0))
+}
+
+/// Reason reported to clients the ACL refuses, unless the deny response is strict
+const ACL_DENIED: &str = "Access denied by ACL";
+/// The one error every failed authentication gets under the strict deny response
+const GENERIC_AUTH_FAILURE: &str = "Authentication failed";
+
+/// Error packet for a failed authentication step, generic under the strict deny response
+fn auth_failure_packet(deny_response: AclDenyResponse, code: ErrorCode, message: &str) -> PacketType {
+    match deny_response {
+        AclDenyResponse::Verbose => create_error_packet(code, message),
+        AclDenyResponse::Strict => create_error_packet(ErrorCode::AuthenticationFailed, GENERIC_AUTH_FAILURE),
+    }
+}
+
+/// Error packet for a failed challenge step; rate-limited clients are told when to come back
+fn auth_error_packet(e: &AuthError, context: &str, deny_response: AclDenyResponse) -> PacketType {
+    let message = format!("{}: {}", context, e);
+    match e {
+        AuthError::TooManyAttempts { retry_after } => {
+            let retry_after_ms = retry_after.as_millis() as u64;
+            PacketType::Error {
+                code: ErrorCode::RateLimited.code(),
+                message,
+                retry: Some(RetryPolicy::backoff(retry_after_ms, retry_after_ms + 30_000, false)),
+            }
+        }
+        AuthError::AccessDenied(_) => auth_failure_packet(deny_response, ErrorCode::Unauthorized, ACL_DENIED),
+        _ => auth_failure_packet(deny_response, ErrorCode::AuthenticationFailed, &message),
+    }
+}
+
+/// How a session ends once the client sends a WebSocket Close frame.
+///
+/// A normal closure, a client going away, or a Close without a status code
+/// is a graceful end; any other code means the client gave up on the
+/// session and is reported as an error.
+fn close_frame_outcome(frame: Option<&CloseFrame<'_>>) -> Result<TeardownReason, ServerError> {
+    match frame {
+        None => Ok(TeardownReason::ConnectionClosed),
+        Some(frame) => match frame.code {
+            CloseCode::Normal | CloseCode::Away => Ok(TeardownReason::ConnectionClosed),
+            code => Err(ServerError::ClosedByClient(code.into(), frame.reason.to_string())),
+        },
+    }
+}
+
+/// Close frame for a read error caused by an oversized frame or message.
+///
+/// The WebSocket layer refuses such frames before buffering them; the client
+/// is told why with 1009 (Message Too Big) and then disconnected.
+fn message_too_big_close(error: &ServerError) -> Option<CloseFrame<'static>> {
+    match error {
+        ServerError::WebSocket(tokio_tungstenite::tungstenite::Error::Capacity(_)) => Some(CloseFrame {
+            code: CloseCode::Size,
+            reason: "Message too big".into(),
+        }),
+        _ => None,
+    }
+}
+
+/// Audit a failed authentication, with the key claimed so far if any
+async fn audit_auth_failure(auth_manager: &AuthManager, addr: SocketAddr, auth_state: &AuthState, reason: &str) {
+    let public_key = auth_state.request().map(|r| r.public_key.as_str());
+    auth_manager.audit(AuthAuditEvent::new(addr.ip(), public_key, AuthDecision::Failure, reason)).await;
+}
 
 /// Handle a RAW (non-TLS) client connection
 pub async fn handle_client_raw(
@@ -44,19 +134,30 @@ pub async fn handle_client_raw(
     network_monitor: Arc<NetworkMonitor>,
     packet_router: Arc<PacketRouter>,
     metrics: Arc<ServerMetricsCollector>,
+    origin_provider: Arc<dyn OriginProvider>,
+    handshake_policy: Arc<WsHandshakePolicy>,
+    worker_pool: Option<Arc<PinnedWorkerPool>>,
+    quiet_hours: Arc<QuietHours>,
+    session_records: Option<Arc<SessionRecordWriter>>,
+    max_stateful_features: Option<usize>,
+    max_handshake_data_packets: u32,
+    require_bound_session_key: bool,
+    trace_sample_rate: f64,
     server_state: Arc<RwLock<ServerState>>,
 ) -> Result<(), ServerError> {
-    // Directly upgrade TCP connection to WebSocket
-    let ws_stream = match tokio_tungstenite::accept_async(stream).await {
-        Ok(stream) => {
-            debug!("RAW WebSocket connection established with {}", addr);
-            stream
-        }
+    // Hold a connection slot until this function returns; dropping the stream closes it
+    let _connection_slot = match session_manager.acquire_connection_slot().await {
+        Ok(slot) => slot,
         Err(e) => {
-            return Err(ServerError::WebSocket(e));
+            metrics.record_connection_rejected().await;
+            return Err(e.into());
         }
     };
 
+    // Directly upgrade TCP connection to WebSocket
+    let ws_stream = upgrade_websocket(&handshake_policy, stream, addr, &metrics).await?;
+    debug!("RAW WebSocket connection established with {}", addr);
+
     // Create duplex connection wrapper
     let duplex_conn = DuplexWebSocketConnection::new_raw(ws_stream);
     
@@ -64,6 +165,7 @@ pub async fn handle_client_raw(
     process_websocket_session_with_connection(
         duplex_conn,
         addr,
+        None,
         key_manager,
         auth_manager,
         ip_pool,
@@ -72,15 +174,116 @@ pub async fn handle_client_raw(
         network_monitor,
         packet_router,
         metrics,
+        origin_provider,
+        worker_pool,
+        quiet_hours,
+        session_records,
+        max_stateful_features,
+        max_handshake_data_packets,
+        require_bound_session_key,
+        trace_sample_rate,
         server_state,
     ).await
 }
 
+/// What the TLS handshake established about a client
+#[derive(Debug)]
+struct TlsPeer {
+    /// Negotiated ALPN protocol
+    alpn: Option<String>,
+    /// Public key from a verified client certificate
+    certificate_key: Option<String>,
+}
+
+/// Classify a failed TLS handshake.
+///
+/// Bytes that are not a TLS record (a plain HTTP request, a port probe) or
+/// a connection dropped mid-handshake count as malformed; any other rustls
+/// error means a TLS client and this server could not agree.
+fn tls_failure_cause(error: &std::io::Error) -> HandshakeFailure {
+    match error.get_ref().and_then(|inner| inner.downcast_ref::<rustls::Error>()) {
+        Some(rustls::Error::CorruptMessage
+            | rustls::Error::CorruptMessagePayload(_)
+            | rustls::Error::InappropriateMessage { .. }
+            | rustls::Error::InappropriateHandshakeMessage { .. }
+            | rustls::Error::PeerSentOversizedRecord) => HandshakeFailure::Malformed,
+        Some(_) => HandshakeFailure::TlsAlert,
+        None if error.kind() == std::io::ErrorKind::TimedOut => HandshakeFailure::Timeout,
+        None => HandshakeFailure::Malformed,
+    }
+}
+
+/// Complete the TLS handshake, returning the stream and what it established about the client.
+///
+/// The acceptor only ever selects a protocol it advertises, so when ALPN is
+/// required the only extra check is that the client negotiated one at all.
+/// Client certificates reaching this point were verified by the acceptor;
+/// they are refused unless they carry an Ed25519 key. A failure comes with
+/// its cause for the handshake metrics.
+async fn accept_tls(
+    tls_acceptor: &TlsAcceptor,
+    stream: TcpStream,
+    require_alpn: bool,
+) -> Result<(TlsStream<TcpStream>, TlsPeer), (ServerError, HandshakeFailure)> {
+    let tls_stream = tls_acceptor.accept(stream).await
+        .map_err(|e| (ServerError::Tls(format!("TLS handshake failed: {}", e)), tls_failure_cause(&e)))?;
+    let connection = tls_stream.get_ref().1;
+    let alpn = connection.alpn_protocol()
+        .map(|protocol| String::from_utf8_lossy(protocol).into_owned());
+    if require_alpn && alpn.is_none() {
+        return Err((
+            ServerError::Tls("TLS client did not negotiate an ALPN protocol".to_string()),
+            HandshakeFailure::TlsAlert,
+        ));
+    }
+    let certificate_key = match connection.peer_certificates().and_then(|certs| certs.first()) {
+        Some(leaf) => Some(certificate_public_key(&leaf.0).ok_or_else(|| {
+            (ServerError::Tls("Client certificate does not carry an Ed25519 key".to_string()), HandshakeFailure::TlsAlert)
+        })?),
+        None => None,
+    };
+    Ok((tls_stream, TlsPeer { alpn, certificate_key }))
+}
+
+/// Upgrade a stream to a WebSocket under `policy`, recording why an upgrade failed
+async fn upgrade_websocket<S>(
+    policy: &WsHandshakePolicy,
+    stream: S,
+    addr: SocketAddr,
+    metrics: &ServerMetricsCollector,
+) -> Result<WebSocketStream<S>, ServerError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    match time::timeout(HANDSHAKE_TIMEOUT, policy.accept(stream)).await {
+        Ok(Ok(ws_stream)) => Ok(ws_stream),
+        Ok(Err((e, Some(reason)))) => {
+            debug!("Rejected WebSocket upgrade from {}: {}", addr, reason);
+            metrics.record_ws_handshake_rejection().await;
+            Err(e)
+        }
+        Ok(Err((e, None))) => {
+            let cause = match &e {
+                ServerError::WebSocket(tokio_tungstenite::tungstenite::Error::Io(io))
+                    if io.kind() == std::io::ErrorKind::TimedOut => HandshakeFailure::Timeout,
+                _ => HandshakeFailure::Malformed,
+            };
+            metrics.record_handshake_failure(HandshakePhase::WebSocket, cause).await;
+            Err(e)
+        }
+        Err(_) => {
+            metrics.record_handshake_failure(HandshakePhase::WebSocket, HandshakeFailure::Timeout).await;
+            Err(ServerError::Network(format!("WebSocket upgrade from {} timed out", addr)))
+        }
+    }
+}
+
 /// Handle a client connection
 pub async fn handle_client(
     stream: TcpStream,
     addr: SocketAddr,
     tls_acceptor: Arc<TlsAcceptor>,
+    require_alpn: bool,
     key_manager: Arc<KeyManager>,
     auth_manager: Arc<AuthManager>,
     ip_pool: Arc<IpPoolManager>,
@@ -89,34 +292,52 @@ pub async fn handle_client(
     network_monitor: Arc<NetworkMonitor>,
     packet_router: Arc<PacketRouter>,
     metrics: Arc<ServerMetricsCollector>,
+    origin_provider: Arc<dyn OriginProvider>,
+    handshake_policy: Arc<WsHandshakePolicy>,
+    worker_pool: Option<Arc<PinnedWorkerPool>>,
+    quiet_hours: Arc<QuietHours>,
+    session_records: Option<Arc<SessionRecordWriter>>,
+    max_stateful_features: Option<usize>,
+    max_handshake_data_packets: u32,
+    require_bound_session_key: bool,
+    trace_sample_rate: f64,
     server_state: Arc<RwLock<ServerState>>,
 ) -> Result<(), ServerError> {
+    // Hold a connection slot until this function returns, so every exit path
+    // releases it. Refused connections are closed before the TLS handshake.
+    let _connection_slot = match session_manager.acquire_connection_slot().await {
+        Ok(slot) => slot,
+        Err(e) => {
+            metrics.record_connection_rejected().await;
+            return Err(e.into());
+        }
+    };
+
     // Record TLS handshake start in metrics
     metrics.record_handshake_start().await;
+    let handshake_started = Instant::now();
 
     // Perform TLS handshake
-    let tls_stream : TlsStream<TcpStream> = match tls_acceptor.accept(stream).await {
-        Ok(stream) => {
+    let (tls_stream, peer) : (TlsStream<TcpStream>, TlsPeer) = match time::timeout(HANDSHAKE_TIMEOUT, accept_tls(&tls_acceptor, stream, require_alpn)).await {
+        Ok(Ok(accepted)) => {
             // Record successful handshake
-            metrics.record_handshake_complete().await;
-            debug!("TLS handshake successful with {}", addr);
-            stream
+            metrics.record_handshake_complete(handshake_started.elapsed()).await;
+            debug!("TLS handshake successful with {} (ALPN: {})", addr, accepted.1.alpn.as_deref().unwrap_or("none"));
+            accepted
         }
-        Err(e) => {
-            return Err(ServerError::Tls(format!("TLS handshake failed: {}", e)));
+        Ok(Err((e, cause))) => {
+            metrics.record_handshake_failure(HandshakePhase::Tls, cause).await;
+            return Err(e);
+        }
+        Err(_) => {
+            metrics.record_handshake_failure(HandshakePhase::Tls, HandshakeFailure::Timeout).await;
+            return Err(ServerError::Tls(format!("TLS handshake with {} timed out", addr)));
         }
     };
 
     // Upgrade connection to WebSocket
-    let ws_stream = match tokio_tungstenite::accept_async(tls_stream).await {
-        Ok(stream) => {
-            debug!("WebSocket connection established with {}", addr);
-            stream
-        }
-        Err(e) => {
-            return Err(ServerError::WebSocket(e));
-        }
-    };
+    let ws_stream = upgrade_websocket(&handshake_policy, tls_stream, addr, &metrics).await?;
+    debug!("WebSocket connection established with {}", addr);
 
     // Create duplex connection wrapper
     let duplex_conn = DuplexWebSocketConnection::new_tls(ws_stream);
@@ -125,6 +346,7 @@ pub async fn handle_client(
     process_websocket_session_with_connection(
         duplex_conn,
         addr,
+        peer.certificate_key,
         key_manager,
         auth_manager,
         ip_pool,
@@ -133,14 +355,60 @@ pub async fn handle_client(
         network_monitor,
         packet_router,
         metrics,
+        origin_provider,
+        worker_pool,
+        quiet_hours,
+        session_records,
+        max_stateful_features,
+        max_handshake_data_packets,
+        require_bound_session_key,
+        trace_sample_rate,
         server_state,
     ).await
 }
 
-/// Process a WebSocket session with an abstract connection
+/// Reject messages a client pipelined before its `IpAssign`.
+///
+/// Data cannot be accepted before the client holds the session key, so each
+/// Data packet already received is answered with `HandshakeIncomplete` and
+/// counted against the same budget as Data sent during the challenge. Any
+/// other packet is a protocol violation. Messages still in flight are left
+/// to the session loop.
+async fn reject_pipelined_messages(
+    duplex_conn: &DuplexWebSocketConnection,
+    early_data: &mut u32,
+    max_handshake_data_packets: u32,
+) -> Result<(), ServerError> {
+    const EXPECTED: &str = "IP assignment";
+    while let Some(next) = duplex_conn.next_message().now_or_never() {
+        let msg = match next {
+            Some(msg) => msg?,
+            None => return Err(ServerError::Network("WebSocket closed before IP assignment".to_string())),
+        };
+        let violation = match ws_message_to_packet(&msg)? {
+            PacketType::Data { .. } => AuthViolation::HandshakeIncomplete { expected: EXPECTED },
+            packet => AuthViolation::UnexpectedPacket { expected: EXPECTED, received: get_packet_type_name(&packet) },
+        };
+        let error_packet = create_error_packet(violation.error_code(), &violation.to_string());
+        duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await?;
+        match violation {
+            AuthViolation::HandshakeIncomplete { .. } if *early_data < max_handshake_data_packets => {
+                *early_data += 1;
+            }
+            violation => return Err(ServerError::Authentication(violation.to_string())),
+        }
+    }
+    Ok(())
+}
+
+/// Process a WebSocket session with an abstract connection.
+///
+/// `certificate_key` is the client public key proven by a TLS client
+/// certificate; such clients skip the challenge.
 async fn process_websocket_session_with_connection(
     duplex_conn: DuplexWebSocketConnection,
     addr: SocketAddr,
+    certificate_key: Option<String>,
     key_manager: Arc<KeyManager>,
     auth_manager: Arc<AuthManager>,
     ip_pool: Arc<IpPoolManager>,
@@ -149,176 +417,380 @@ async fn process_websocket_session_with_connection(
     network_monitor: Arc<NetworkMonitor>,
     packet_router: Arc<PacketRouter>,
     metrics: Arc<ServerMetricsCollector>,
+    origin_provider: Arc<dyn OriginProvider>,
+    worker_pool: Option<Arc<PinnedWorkerPool>>,
+    quiet_hours: Arc<QuietHours>,
+    session_records: Option<Arc<SessionRecordWriter>>,
+    max_stateful_features: Option<usize>,
+    max_handshake_data_packets: u32,
+    require_bound_session_key: bool,
+    trace_sample_rate: f64,
     server_state: Arc<RwLock<ServerState>>,
 ) -> Result<(), ServerError> {
-    // --- Authentication Phase ---
-    let (public_key_string, client_encryption_preference) = match time::timeout(Duration::from_secs(30), duplex_conn.next_message()).await {
-        Ok(Some(Ok(msg))) => {
-             match ws_message_to_packet(&msg) {
-                Ok(PacketType::Auth { 
-                    public_key, 
-                    version, 
-                    features, 
-                    encryption_algorithm,
-                    nonce: _nonce 
-                }) => {
-                    debug!(
-                        "Auth request from {}, version: {}, features: {:?}, encryption: {:?}", 
-                        public_key, version, features, encryption_algorithm
-                    );
-
-                    // Verify public key format
-                    if !StringValidator::is_valid_solana_pubkey(&public_key) {
-                        let error_packet = create_error_packet(1001, "Invalid public key format");
-                        let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
-                        metrics.record_auth_failure().await;
-                        return Err(ServerError::Authentication("Invalid public key format".to_string()));
-                    }
+    let connected_at = current_timestamp_millis();
+    let connected = Instant::now();
 
-                    // Store the client's algorithm preference string for later parsing
-                    let client_algo_pref_str = encryption_algorithm;
+    // --- Origin tagging: once per connection that got past the upgrade ---
+    let origin = origin_provider.lookup(addr);
+    if let Some(origin) = &origin {
+        debug!("Connection from {} originates from {:?}", addr, origin);
+    }
 
-                    // Generate challenge
-                    let challenge = match auth_manager.generate_challenge(&addr.to_string()).await {
-                        Ok(challenge) => challenge,
-                        Err(e) => {
-                             let error_packet = create_error_packet(1001, &format!("Failed to generate challenge: {}", e));
-                            let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
-                            metrics.record_auth_failure().await;
-                            return Err(ServerError::Authentication(format!("Challenge generation failed: {}", e)));
-                        }
-                    };
+    // --- Scheduled maintenance: turn away new clients with a reconnect hint ---
+    if let Some(remaining) = quiet_hours.active_now() {
+        debug!("Rejecting {} during quiet hours", addr);
+        let remaining_ms = remaining.as_millis() as u64;
+        let disconnect = PacketType::Disconnect {
+            reason: DisconnectReason::Maintenance.code(),
+            message: format!("Server is in scheduled maintenance; reconnect in {}s", remaining.as_secs().max(1)),
+            // Back off until the window closes, or go elsewhere now
+            retry: Some(RetryPolicy::backoff(remaining_ms, remaining_ms + 60_000, true)),
+            sequence: None,
+            mac: None,
+        };
+        let _ = duplex_conn.send_message(packet_to_ws_message(&disconnect)?).await;
+        let _ = duplex_conn.close().await;
+        return Ok(());
+    }
 
-                    // Get server public key
-                    let server_pubkey = key_manager.public_key().await.to_string();
+    // --- Session cap: reached while this connection was in its TLS/WebSocket handshake ---
+    if session_manager.at_capacity().await {
+        debug!("Session cap reached, turning away {}", addr);
+        metrics.record_capacity_rejection().await;
+        let disconnect = create_disconnect_packet(DisconnectReason::TooManyConnections, "Server is at capacity");
+        let _ = duplex_conn.send_message(packet_to_ws_message(&disconnect)?).await;
+        let _ = duplex_conn.close().await;
+        return Ok(());
+    }
 
-                    // Create challenge packet
-                    let challenge_packet = PacketType::Challenge {
-                        data: challenge.1.clone(), // Challenge data
-                        server_key: server_pubkey,
-                        expires_at: current_timestamp_millis() + crate::config::constants::AUTH_CHALLENGE_TIMEOUT.as_millis() as u64,
-                        id: challenge.0.clone(), // Challenge ID
-                    };
+    // --- Trace sampling: decided once, before any packet is exchanged ---
+    let traced = sample_for_tracing(trace_sample_rate);
+    if traced {
+        info!("[sampled] Connection from {} selected for detailed packet tracing", addr);
+    }
 
-                    // Send challenge
-                    if duplex_conn.send_message(packet_to_ws_message(&challenge_packet)?).await.is_err() {
-                        return Err(ServerError::Network("Failed to send challenge".to_string()));
-                    }
+    // --- Authentication Phase ---
+    // Every wait is also capped by the phase deadline, so a client cannot hold
+    // the connection unauthenticated by answering each step just in time
+    let timeouts = auth_manager.auth_timeouts();
+    let deny_response = auth_manager.acl_deny_response();
+    let auth_deadline = time::Instant::now() + timeouts.deadline;
+    let mut auth_state = AuthState::AwaitingAuth;
+    let mut early_data = 0u32;
+    let mut capabilities_sent = false;
+    let mut resumed: Option<ResumedSession> = None;
+    // Server key sent in the challenge; the session key is sealed for it even if the key rotates meanwhile
+    let mut served_server_key: Option<Pubkey> = None;
+    let auth_request = loop {
+        let step_timeout = match auth_state {
+            AuthState::AwaitingAuth => timeouts.auth_message,
+            _ => timeouts.challenge_response,
+        };
+        let wait_until = auth_deadline.min(time::Instant::now() + step_timeout);
+        let msg = match time::timeout_at(wait_until, duplex_conn.next_message()).await {
+            Ok(Some(Ok(msg))) => msg,
+            Ok(Some(Err(e))) => { // Handle specific websocket error
+                metrics.record_auth_failure().await;
+                audit_auth_failure(&auth_manager, addr, &auth_state, &e.to_string()).await;
+                if let Some(frame) = message_too_big_close(&e) {
+                    warn!("Closing connection from {}: {}", addr, e);
+                    let _ = duplex_conn.send_message(Message::Close(Some(frame))).await;
+                    let _ = duplex_conn.close().await;
+                }
+                return Err(e); // e is already ServerError
+            }
+            Err(_) if wait_until == auth_deadline => {
+                let reason = format!("Authentication not completed within {:?}", timeouts.deadline);
+                metrics.record_auth_failure().await;
+                audit_auth_failure(&auth_manager, addr, &auth_state, &reason).await;
+                return Err(ServerError::Authentication(reason));
+            }
+            Err(_) => { // Handle timeout
+                let reason = format!("Timed out waiting for {}", auth_state.expected());
+                metrics.record_auth_failure().await;
+                audit_auth_failure(&auth_manager, addr, &auth_state, &reason).await;
+                return Err(ServerError::Authentication(reason));
+            }
+            Ok(None) => { // Handle stream closed
+                let reason = format!("WebSocket closed while waiting for {}", auth_state.expected());
+                metrics.record_auth_failure().await;
+                audit_auth_failure(&auth_manager, addr, &auth_state, &reason).await;
+                return Err(ServerError::Authentication(reason));
+            }
+        };
 
-                    // Wait for challenge response
-                    match time::timeout(Duration::from_secs(30), duplex_conn.next_message()).await {
-                         Ok(Some(Ok(resp_msg))) => {
-                             match ws_message_to_packet(&resp_msg) {
-                                Ok(PacketType::ChallengeResponse { signature, public_key: resp_pubkey, challenge_id }) => {
-                                    if resp_pubkey != public_key {
-                                        let error_packet = create_error_packet(1001, "Public key mismatch");
-                                        let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
-                                        metrics.record_auth_failure().await;
-                                        return Err(ServerError::Authentication("Public key mismatch".to_string()));
-                                    }
-
-                                    // Verify the challenge
-                                    match auth_manager.verify_challenge(&challenge_id, &signature, &public_key, &addr.to_string()).await {
-                                        Ok(_) => {
-                                            debug!("Challenge successfully verified for {}", public_key);
-                                            if !auth_manager.is_client_allowed(&public_key).await {
-                                                 let error_packet = create_error_packet(1005, "Access denied by ACL");
-                                                let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
-                                                metrics.record_auth_failure().await;
-                                                return Err(ServerError::Authentication("Access denied by ACL".to_string()));
-                                            }
-                                            metrics.record_auth_success().await;
-                                            info!("Client {} authenticated successfully", public_key);
-                                            
-                                            // Parse client's preferred algorithm, if invalid/unsupported use default
-                                            let client_preferred_algo = client_algo_pref_str
-                                                .as_deref() // Option<String> -> Option<&str>
-                                                .and_then(EncryptionAlgorithm::from_str) // Option<&str> -> Option<EncryptionAlgorithm>
-                                                .unwrap_or_else(|| {
-                                                    if client_algo_pref_str.is_some() {
-                                                        warn!(
-                                                            "Client {} provided unsupported/invalid algorithm {:?}, using default.", 
-                                                            public_key, client_algo_pref_str
-                                                        );
-                                                    }
-                                                    EncryptionAlgorithm::default() // Use server default algorithm
-                                                });
-                                            
-                                            (public_key, client_preferred_algo) // Return the verified public key and parsed algorithm
-                                        }
-                                        Err(e) => {
-                                             let error_packet = create_error_packet(1001, &format!("Challenge verification failed: {}", e));
-                                            let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
-                                            metrics.record_auth_failure().await;
-                                            return Err(ServerError::Authentication(format!("Challenge verification failed: {}", e)));
-                                        }
-                                    }
-                                }
-                                Ok(_) => {
-                                    let error_packet = create_error_packet(1002, "Expected challenge response");
-                                    let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
-                                    metrics.record_auth_failure().await;
-                                    return Err(ServerError::Authentication("Expected challenge response".to_string()));
-                                }
-                                Err(e) => {
-                                    let error_packet = create_error_packet(1002, &format!("Invalid challenge response message: {}", e));
-                                     let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
-                                    metrics.record_auth_failure().await;
-                                    return Err(ServerError::Protocol(e));
-                                }
-                            }
+        let packet = match ws_message_to_packet(&msg) {
+            Ok(packet) => packet,
+            Err(e) => { // Deserialization error
+                let kind = match auth_state {
+                    AuthState::AwaitingAuth => "auth message",
+                    _ => "challenge response message",
+                };
+                let reason = format!("Invalid {}: {}", kind, e);
+                let error_packet = create_error_packet(ErrorCode::InvalidMessage, &reason);
+                let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
+                metrics.record_auth_failure().await;
+                audit_auth_failure(&auth_manager, addr, &auth_state, &reason).await;
+                return Err(ServerError::Protocol(e));
+            }
+        };
+
+        // Out-of-order, duplicate and mismatched packets are all rejected here
+        let transition = match auth_state.next(packet) {
+            Ok(transition) => transition,
+            // Early Data is dropped with an error; only repeated offences end the handshake
+            Err(violation @ AuthViolation::HandshakeIncomplete { .. }) if early_data < max_handshake_data_packets => {
+                early_data += 1;
+                debug!("Rejected Data from {} before handshake completion ({}/{})", addr, early_data, max_handshake_data_packets);
+                let error_packet = create_error_packet(violation.error_code(), &violation.to_string());
+                duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await?;
+                continue;
+            }
+            Err(violation) => {
+                let error_packet = create_error_packet(violation.error_code(), &violation.to_string());
+                let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
+                metrics.record_auth_failure().await;
+                audit_auth_failure(&auth_manager, addr, &auth_state, &violation.to_string()).await;
+                return Err(ServerError::Authentication(violation.to_string()));
+            }
+        };
+
+        match transition {
+            AuthTransition::DescribeCapabilities { version } => {
+                // Answered at most once per connection, and only when the operator allows it
+                if capabilities_sent || !auth_manager.capability_queries_enabled() {
+                    let violation = AuthViolation::UnexpectedPacket { expected: auth_state.expected(), received: "Hello" };
+                    let error_packet = create_error_packet(violation.error_code(), &violation.to_string());
+                    let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
+                    metrics.record_auth_failure().await;
+                    audit_auth_failure(&auth_manager, addr, &auth_state, &violation.to_string()).await;
+                    return Err(ServerError::Authentication(violation.to_string()));
+                }
+                if !auth_manager.allow_capability_query(&addr.ip()).await {
+                    let reason = "Too many capability queries";
+                    let error_packet = create_error_packet(ErrorCode::RateLimited, reason);
+                    let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
+                    metrics.record_auth_rate_limited().await;
+                    audit_auth_failure(&auth_manager, addr, &auth_state, reason).await;
+                    return Err(ServerError::Authentication(format!("{} from {}", reason, addr)));
+                }
+                debug!("Capability query from {} (client version {})", addr, version);
+                let capabilities = server_capabilities(packet_router.tunnel_mtu(), auth_manager.resumption().is_some());
+                duplex_conn.send_message(packet_to_ws_message(&capabilities)?).await?;
+                capabilities_sent = true;
+            }
+            AuthTransition::IssueChallenge(request) => {
+                debug!(
+                    "Auth request from {}, features: {:?}, encryption: {:?}",
+                    request.public_key, request.features, request.encryption_algorithm
+                );
+
+                // Refuse clients whose protocol version this server cannot speak
+                if let Err(e) = check_protocol_version(&request.version) {
+                    let error_packet = create_error_packet(ErrorCode::VersionMismatch, &e.to_string());
+                    let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
+                    metrics.record_auth_failure().await;
+                    metrics.record_auth_version(&request.version, false).await;
+                    auth_manager.audit(AuthAuditEvent::new(
+                        addr.ip(), Some(&request.public_key), AuthDecision::Failure, format!("Incompatible protocol version: {}", e),
+                    )).await;
+                    return Err(ServerError::Authentication(format!("Incompatible protocol version from {}: {}", addr, e)));
+                }
+
+                // A verified client certificate already proved the key
+                if let Some(certificate_key) = &certificate_key {
+                    let version = request.version.clone();
+                    let claimed_key = request.public_key.clone();
+                    let verified = match AuthState::certificate_verified(request, certificate_key) {
+                        Ok(AuthState::Authenticated { request }) => auth_manager
+                            .verify_certificate_key(certificate_key, &addr.to_string()).await
+                            .map(|_| request)
+                            .map_err(|e| (ErrorCode::AuthenticationFailed, format!("Certificate authentication failed: {}", e))),
+                        Ok(_) => return Err(ServerError::Internal("Authentication state machine out of sync".to_string())),
+                        Err(violation) => Err((violation.error_code(), violation.to_string())),
+                    };
+                    match verified {
+                        Ok(request) => {
+                            debug!("Client certificate accepted for {}", certificate_key);
+                            break request;
                         }
-                        Ok(Some(Err(e))) => { // Handle specific websocket error
+                        Err((code, message)) => {
+                            let error_packet = auth_failure_packet(deny_response, code, &message);
+                            let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
                             metrics.record_auth_failure().await;
-                            return Err(e); // e is already ServerError
+                            metrics.record_auth_version(&version, false).await;
+                            auth_manager.audit(AuthAuditEvent::new(addr.ip(), Some(&claimed_key), AuthDecision::Failure, message.as_str())).await;
+                            return Err(ServerError::Authentication(message));
                         }
-                        Err(_) => { // Handle timeout
-                            metrics.record_auth_failure().await;
-                            return Err(ServerError::Authentication("Timed out waiting for challenge response".to_string()));
+                    }
+                }
+
+                // A valid resumption token restores the previous session without a challenge
+                if let (Some(token), Some(proof)) = (&request.resumption_token, &request.resumption_proof) {
+                    match auth_manager.resume_session(token, &request.public_key, proof, &addr.to_string()).await {
+                        Ok(session) => {
+                            debug!("Resuming session for {}", request.public_key);
+                            metrics.record_session_resumption(true).await;
+                            resumed = Some(session);
+                            break request;
                         }
-                        Ok(None) => { // Handle stream closed
-                             metrics.record_auth_failure().await;
-                             return Err(ServerError::Authentication("WebSocket closed during challenge response".to_string()));
+                        // Fall back to the challenge, which also enforces the failure limit
+                        Err(e) => {
+                            debug!("Session resumption refused for {}: {}", request.public_key, e);
+                            metrics.record_session_resumption(false).await;
                         }
                     }
                 }
-                 Ok(_) => { // Wrong initial packet type
-                     let error_packet = create_error_packet(1002, "Expected authentication message");
-                     let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
-                     metrics.record_auth_failure().await;
-                     return Err(ServerError::Authentication("Expected authentication message".to_string()));
-                 }
-                 Err(e) => { // Deserialization error
-                     let error_packet = create_error_packet(1002, &format!("Invalid auth message: {}", e));
-                     let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
-                     metrics.record_auth_failure().await;
-                     return Err(ServerError::Protocol(e));
-                 }
-             }
+
+                // Generate challenge
+                let challenge = match auth_manager.generate_challenge(&addr.to_string()).await {
+                    Ok(challenge) => challenge,
+                    Err(e) => {
+                        let error_packet = auth_error_packet(&e, "Failed to generate challenge", deny_response);
+                        let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
+                        if matches!(e, AuthError::TooManyAttempts { .. }) {
+                            metrics.record_auth_rate_limited().await;
+                        }
+                        let reason = format!("Challenge generation failed: {}", e);
+                        metrics.record_auth_failure().await;
+                        metrics.record_auth_version(&request.version, false).await;
+                        auth_manager.audit(AuthAuditEvent::new(addr.ip(), Some(&request.public_key), AuthDecision::Failure, reason.as_str())).await;
+                        return Err(ServerError::Authentication(reason));
+                    }
+                };
+
+                // Create challenge packet
+                let server_key = key_manager.public_key().await;
+                served_server_key = Some(server_key);
+                let challenge_packet = PacketType::Challenge {
+                    data: challenge.1.clone(), // Challenge data
+                    server_key: server_key.to_string(),
+                    expires_at: current_timestamp_millis() + crate::config::constants::AUTH_CHALLENGE_TIMEOUT.as_millis() as u64,
+                    id: challenge.0.clone(), // Challenge ID
+                    pow_difficulty: Some(challenge.2).filter(|d| *d > 0),
+                };
+
+                // Send challenge
+                if duplex_conn.send_message(packet_to_ws_message(&challenge_packet)?).await.is_err() {
+                    return Err(ServerError::Network("Failed to send challenge".to_string()));
+                }
+                auth_state = AuthState::challenged(request, challenge.0);
+            }
+            AuthTransition::VerifyResponse { signature, challenge_id, pow_nonce } => {
+                let public_key = auth_state.request().map(|r| r.public_key.clone()).unwrap_or_default();
+                let version = auth_state.request().map(|r| r.version.clone()).unwrap_or_default();
+
+                // Verify the challenge
+                if let Err(e) = auth_manager.verify_challenge(&challenge_id, &signature, &public_key, &addr.to_string(), pow_nonce).await {
+                    let error_packet = auth_error_packet(&e, "Challenge verification failed", deny_response);
+                    let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
+                    if matches!(e, AuthError::TooManyAttempts { .. }) {
+                        metrics.record_auth_rate_limited().await;
+                    }
+                    let reason = format!("Challenge verification failed: {}", e);
+                    let decision = match e {
+                        AuthError::AccessDenied(_) => AuthDecision::DeniedByAcl,
+                        _ => AuthDecision::Failure,
+                    };
+                    metrics.record_auth_failure().await;
+                    metrics.record_auth_version(&version, false).await;
+                    auth_manager.audit(AuthAuditEvent::new(addr.ip(), Some(&public_key), decision, reason.as_str())).await;
+                    return Err(ServerError::Authentication(reason));
+                }
+                debug!("Challenge successfully verified for {}", public_key);
+
+                match auth_state.authenticated() {
+                    Ok(AuthState::Authenticated { request }) => break request,
+                    _ => return Err(ServerError::Internal("Authentication state machine out of sync".to_string())),
+                }
+            }
         }
-        Ok(Some(Err(e))) => { // Handle specific websocket error
-            metrics.record_auth_failure().await;
-            return Err(e); // e is already ServerError
+    };
+    let access = auth_manager.is_client_allowed(&auth_request.public_key).await;
+    if !access.allowed {
+        let error_packet = auth_failure_packet(deny_response, ErrorCode::Unauthorized, ACL_DENIED);
+        let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
+        metrics.record_auth_failure().await;
+        metrics.record_auth_version(&auth_request.version, false).await;
+        auth_manager.audit(AuthAuditEvent::new(
+            addr.ip(), Some(&auth_request.public_key), AuthDecision::DeniedByAcl, ACL_DENIED,
+        )).await;
+        return Err(ServerError::Authentication(ACL_DENIED.to_string()));
+    }
+    metrics.record_auth_success().await;
+    metrics.record_auth_version(&auth_request.version, true).await;
+    let method = if certificate_key.is_some() {
+        "Client certificate verified"
+    } else if resumed.is_some() {
+        "Session resumed"
+    } else {
+        "Challenge verified"
+    };
+    auth_manager.audit(AuthAuditEvent::new(addr.ip(), Some(&auth_request.public_key), AuthDecision::Success, method)).await;
+    let time_to_auth_ms = connected.elapsed().as_millis() as u64;
+    info!("Client {} authenticated successfully", auth_request.public_key);
+
+    // Client's preferred algorithm (explicit, or via the `aes-gcm` feature); if invalid/unsupported use default
+    let client_encryption_preference = select_encryption_algorithm(auth_request.encryption_algorithm.as_deref(), &auth_request.features)
+        .unwrap_or_else(|| {
+            if auth_request.encryption_algorithm.is_some() {
+                warn!(
+                    "Client {} provided unsupported/invalid algorithm {:?}, using default.",
+                    auth_request.public_key, auth_request.encryption_algorithm
+                );
+            }
+            EncryptionAlgorithm::default() // Use server default algorithm
+        });
+    let AuthRequest { public_key: public_key_string, features: client_features, lease_duration: requested_lease, .. } = auth_request;
+    // --- Authentication Phase End ---
+
+    let bound_session_key = client_features.iter().any(|f| f == features::BOUND_SESSION_KEY);
+    if require_bound_session_key && !bound_session_key {
+        let error_packet = create_error_packet(
+            ErrorCode::VersionMismatch,
+            &format!("Client must support the {} feature", features::BOUND_SESSION_KEY),
+        );
+        let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
+        return Err(ServerError::Authentication(format!("Client {} does not support {}", public_key_string, features::BOUND_SESSION_KEY)));
+    }
+
+    // Apply the duplicate session policy before allocating anything for this key
+    let acl_session_limit = auth_manager.get_client_info(&public_key_string).await.map_or(0, |entry| entry.max_sessions);
+    let reservation = match session_manager.admit_client(&public_key_string, acl_session_limit).await {
+        Ok((0, reservation)) => reservation,
+        Ok((replaced, reservation)) => {
+            debug!("Replaced {} existing sessions for {}", replaced, public_key_string);
+            reservation
         }
-        Err(_) => { // Handle timeout
-            metrics.record_auth_failure().await;
-            return Err(ServerError::Authentication("Timed out waiting for auth message".to_string()));
+        Err(e) => {
+            let code = match e {
+                SessionError::KeyLimitReached(_) => {
+                    metrics.record_key_session_rejection().await;
+                    ErrorCode::SessionLimit
+                }
+                SessionError::ServerFull(_) => {
+                    metrics.record_capacity_rejection().await;
+                    ErrorCode::ResourceExhausted
+                }
+                _ => ErrorCode::AlreadyConnected,
+            };
+            let error_packet = create_error_packet(code, &e.to_string());
+            let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
+            return Err(ServerError::Session(e));
         }
-         Ok(None) => { // Handle stream closed
-             metrics.record_auth_failure().await;
-             return Err(ServerError::Authentication("WebSocket closed before authentication".to_string()));
-         }
     };
-    // --- Authentication Phase End ---
 
     // Assign IP address
-    let ip_address = match ip_pool.allocate_ip(&public_key_string).await {
+    let ip_address = match ip_pool.allocate_ip(&public_key_string, requested_lease).await {
         Ok(ip) => {
             debug!("Assigned IP {} to client {}", ip, public_key_string);
+            if let Some(resumed) = resumed.as_ref().filter(|resumed| resumed.ip_address != ip) {
+                debug!("Previous IP {} of resumed client {} is no longer leased", resumed.ip_address, public_key_string);
+            }
             ip
         }
         Err(e) => {
-            let error_packet = create_error_packet(1007, &format!("Failed to allocate IP: {}", e));
+            let error_packet = create_error_packet(ErrorCode::ResourceExhausted, &format!("Failed to allocate IP: {}", e));
             let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
             return Err(ServerError::Network(format!("IP allocation failed: {}", e)));
         }
@@ -327,19 +799,35 @@ async fn process_websocket_session_with_connection(
     // Generate session ID
     let session_id = format!("session_{}", random_string(16));
 
-    // Generate session key
-    let session_key = SessionKeyManager::generate_key();
-
-    // Store session key
-    session_key_manager.store_key(&public_key_string, session_key.clone()).await;
+    // Generate and store the session key (drawn from the pre-generated pool when enabled),
+    // unless resuming, in which case the key's counters carry on where they left off
+    let (session_key, resumed_received) = match resumed {
+        Some(resumed) => {
+            session_key_manager.store_resumed_key(&public_key_string, resumed.session_key.clone(), "initial", resumed.send_counter).await;
+            (resumed.session_key, Some(resumed.received))
+        }
+        None => {
+            let session_key = session_key_manager.next_key().await;
+            session_key_manager.store_key_with_id(&public_key_string, session_key.clone(), "initial").await;
+            (session_key, None)
+        }
+    };
 
     // Get shared secret for encrypting session key
     let pubkey = Pubkey::from_str(&public_key_string)
         .map_err(|e| ServerError::KeyError(format!("Invalid public key: {}", e)))?;
-    let shared_secret = match key_manager.get_shared_secret(&pubkey).await {
+    let derivation = key_manager.get_shared_secret_with_retry(served_server_key.as_ref(), &pubkey).await;
+    let permanent_failure = matches!(&derivation.result, Err(e) if !e.is_transient());
+    if derivation.transient_failures > 0 || permanent_failure {
+        metrics.record_secret_derivation_failures(
+            derivation.transient_failures as u64,
+            permanent_failure as u64,
+        ).await;
+    }
+    let shared_secret = match derivation.result {
         Ok(secret) => secret,
         Err(e) => {
-            let error_packet = create_error_packet(1006, &format!("Failed to derive shared secret: {}", e));
+            let error_packet = create_error_packet(ErrorCode::InternalError, &format!("Failed to derive shared secret: {}", e));
             let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
             if let Err(release_err) = ip_pool.release_ip(&ip_address).await {
                 warn!("Failed to release IP {}: {}", ip_address, release_err);
@@ -349,14 +837,16 @@ async fn process_websocket_session_with_connection(
     };
 
     // Encrypt session key using the flexible encryption method with negotiated algorithm
+    let key_aad = bound_session_key.then(|| session_key_aad(&session_id, &public_key_string));
     let encrypted_key_packet = match encrypt_session_key_flexible(
         &session_key,
         &shared_secret,
         client_encryption_preference, // Use negotiated algorithm
+        key_aad.as_deref(),
     ) {
         Ok(packet) => packet,
         Err(e) => {
-            let error_packet = create_error_packet(1006, &format!("Encryption failed: {}", e));
+            let error_packet = create_error_packet(ErrorCode::InternalError, &format!("Encryption failed: {}", e));
             let _ = duplex_conn.send_message(packet_to_ws_message(&error_packet)?).await;
             if let Err(release_err) = ip_pool.release_ip(&ip_address).await {
                 warn!("Failed to release IP {}: {}", ip_address, release_err);
@@ -365,6 +855,16 @@ async fn process_websocket_session_with_connection(
         }
     };
 
+    // Resumption tokens are only issued when the server keeps them
+    let resumption = auth_manager.resumption();
+    let mut capabilities = NegotiatedCapabilities::negotiate(
+        &client_features,
+        encrypted_key_packet.algorithm,
+        packet_router.padding_enabled(),
+        max_stateful_features,
+    );
+    capabilities.session_resumption &= resumption.is_some();
+
     // Create the ClientSession with the abstract connection
     let session = ClientSession::new(
         session_id.clone(),
@@ -374,74 +874,254 @@ async fn process_websocket_session_with_connection(
         duplex_conn.sender(),
         duplex_conn.receiver(),
         Some(encrypted_key_packet.algorithm.as_str().to_string()),
-    )?;
+    )?
+    .with_capabilities(capabilities)
+    .with_tracing(traced)
+    .with_key_rotation_interval(session_key_manager.session_rotation_interval())
+    .with_send_queue_depth(session_manager.send_queue_depth())
+    .with_roles(access.roles)
+    .with_origin(origin);
+    if let Some(received) = resumed_received {
+        session.data_nonces().await.resume_received(received);
+    }
+    if traced {
+        info!("[sampled {}] Session for {} at {} is traced", session_id, public_key_string, addr);
+    }
+    debug!("Negotiated features for {}: {:?}", public_key_string, session.capabilities.active_features());
+
+    // Snapshot the client's ACL tier for this session
+    let policy = SessionPolicy::from_entry(auth_manager.get_client_info(&public_key_string).await.as_ref());
+    apply_session_policy(&session, policy, &network_monitor).await;
 
     // Create IP assignment packet with encryption algorithm info
+    let lease_duration = ip_pool.get_client_allocation(&public_key_string).await
+        .map_or_else(|| ip_pool.effective_lease(requested_lease), |allocation| allocation.lease_duration_secs);
     let ip_assign = PacketType::IpAssign {
         ip_address: ip_address.clone(),
-        lease_duration: ip_pool.get_default_lease_duration().as_secs(),
+        lease_duration,
         session_id: session_id.clone(),
         encrypted_session_key: encrypted_key_packet.data,
         key_nonce: encrypted_key_packet.nonce,
         encryption_algorithm: encrypted_key_packet.algorithm.as_str().to_string(),
+        protocol_version: Some(crate::config::constants::PROTOCOL_VERSION.to_string()),
+        features: session.capabilities.active_features().iter().map(|f| f.to_string()).collect(),
     };
 
-    // Send IP assignment
+    // Send IP assignment, unless the client already broke the handshake by pipelining past it
+    if let Err(e) = reject_pipelined_messages(&duplex_conn, &mut early_data, max_handshake_data_packets).await {
+        debug!("Handshake with {} ended before IP assignment: {}", addr, e);
+        if let Err(release_err) = ip_pool.release_ip(&ip_address).await {
+             warn!("Failed to release IP {}: {}", ip_address, release_err);
+        }
+        return Err(e);
+    }
     if session.send_packet(&ip_assign).await.is_err() {
         if let Err(release_err) = ip_pool.release_ip(&ip_address).await {
              warn!("Failed to release IP {}: {}", ip_address, release_err);
         }
         return Err(ServerError::Network("Failed to send IP assignment".to_string()));
     }
+
+    // Tell clients that asked which features took effect and which were refused
+    if session.capabilities.feature_ack {
+        let feature_ack = PacketType::FeatureAck {
+            active: session.capabilities.active_features().iter().map(|f| f.to_string()).collect(),
+            denied: session.capabilities.denied.clone(),
+        };
+        if let Err(e) = session.send_packet(&feature_ack).await {
+            debug!("Failed to send feature ack to {}: {}", public_key_string, e);
+        }
+    } else if !session.capabilities.denied.is_empty() {
+        debug!("Denied features for {}: {:?}", public_key_string, session.capabilities.denied);
+    }
+
+    // Token to resume this session once it ends, armed at teardown
+    let resumption_token = match resumption.as_ref().filter(|_| session.capabilities.session_resumption) {
+        Some(store) => {
+            let token = store.issue(&public_key_string).await;
+            let packet = PacketType::ResumptionToken { token: token.clone(), ttl_secs: store.ttl().as_secs() };
+            if let Err(e) = session.send_packet(&packet).await {
+                debug!("Failed to send resumption token to {}: {}", public_key_string, e);
+            }
+            Some(token)
+        }
+        None => None,
+    };
     
     // Register the session
-    session_manager.add_session(session.clone()).await;
+    session_manager.add_admitted_session(session.clone(), reservation).await;
+    metrics.record_session_capabilities(&session.capabilities).await;
+    let origin_labels = match &session.origin {
+        Some(origin) => Some(metrics.record_session_origin(origin).await),
+        None => None,
+    };
+    let capabilities = session.capabilities.clone();
+    let teardown = session.clone();
+    let rotations_before = session_key_manager.rotation_count(&public_key_string).await;
 
     // Process client messages
-    let result = process_client_session(
+    let session_future = process_client_session(
         session,
         key_manager, // Keep original Arc
         session_key_manager.clone(), // Clone Arc for the async function
-        packet_router, // Keep original Arc
+        packet_router.clone(), // Clone Arc for cleanup of per-client router state
         network_monitor, // Keep original Arc
         ip_pool.clone(), // Clone Arc for cleanup logic within or after process_client_session
         session_manager.clone(), // Clone Arc for cleanup logic within or after process_client_session
+        metrics.clone(),
         server_state,
-    ).await;
+    );
+    let result = supervise_session(&teardown, session_future, worker_pool.as_ref()).await;
 
     // Cleanup after process_client_session finishes or errors
     info!("Cleaning up session for client {}", public_key_string);
+    if let Some(writer) = &session_records {
+        let teardown_reason = match &result {
+            Ok(reason) => *reason,
+            Err(ServerError::Internal(msg)) if msg == "Server shutting down" => TeardownReason::ServerShutdown,
+            Err(_) => TeardownReason::Error,
+        };
+        let (bytes_down, bytes_up) = teardown.traffic();
+        let (packets_down, packets_up) = teardown.message_counts();
+        let key_rotations = session_key_manager.rotation_count(&public_key_string).await
+            .saturating_sub(rotations_before);
+        writer.write(&SessionRecord {
+            schema_version: SESSION_RECORD_SCHEMA_VERSION,
+            session_id: session_id.clone(),
+            client_id: public_key_string.clone(),
+            source_addr: addr.to_string(),
+            assigned_ip: ip_address.clone(),
+            connected_at,
+            time_to_auth_ms,
+            duration_ms: connected.elapsed().as_millis() as u64,
+            bytes_up,
+            bytes_down,
+            packets_up,
+            packets_down,
+            key_rotations,
+            ip_renewals: teardown.ip_renewals(),
+            teardown_reason,
+            peak_rtt_ms: teardown.peak_rtt_ms(),
+        }).await;
+    }
     session_manager.remove_session(&session_id).await; // Use cloned session_manager
-    if let Err(e) = ip_pool.release_ip(&ip_address).await { // Use cloned ip_pool
-        warn!("Failed to release IP {} during cleanup: {}", ip_address, e);
+    metrics.record_tx_dropped(teardown.tx_dropped()).await;
+    metrics.release_session_capabilities(&capabilities).await;
+    if let Some(labels) = &origin_labels {
+        metrics.release_session_origin(labels).await;
     }
-    // Use original session_key_manager (which still holds a valid Arc reference)
-    session_key_manager.remove_key(&public_key_string).await;
+    // The IP and session key are shared by all sessions of a key
+    if session_manager.get_session_by_client_id(&public_key_string).await.is_none() {
+        packet_router.remove_client(&public_key_string).await;
+        // Held briefly so a reconnecting client gets the same IP back
+        if let Err(e) = ip_pool.release_ip_after_session(&ip_address).await { // Use cloned ip_pool
+            warn!("Failed to release IP {} during cleanup: {}", ip_address, e);
+        }
+        // The token resumes with the last key in use, so arm it before the key is dropped
+        if let (Some(store), Some(token)) = (&resumption, &resumption_token) {
+            match session_key_manager.key_with_send_counter(&public_key_string).await {
+                Some((session_key, send_counter)) => {
+                    let received = teardown.data_nonces().await.received_window();
+                    store.arm(token, ResumedSession { session_key, ip_address: ip_address.clone(), send_counter, received }).await;
+                }
+                None => store.revoke(token).await,
+            }
+        }
+        // Use original session_key_manager (which still holds a valid Arc reference)
+        session_key_manager.remove_key(&public_key_string).await;
+    } else {
+        debug!("Other sessions for {} remain; keeping shared IP and session key", public_key_string);
+        // The remaining sessions carry on with the key, so this one is not resumable
+        if let (Some(store), Some(token)) = (&resumption, &resumption_token) {
+            store.revoke(token).await;
+        }
+    }
+    teardown.mark_torn_down();
+
+    result.map(|_| ()) // Return the result from process_client_session
+}
+
+
+/// Aborts a session helper task when dropped, including while a panic unwinds
+struct TaskGuard(tokio::task::JoinHandle<()>);
 
-    result // Return the result from process_client_session
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
 }
 
+/// Run a session's message loop, catching panics at the task boundary.
+///
+/// A panic in packet handling becomes an `Internal` error and closes the
+/// connection, so the caller's teardown still releases the session's IP, key
+/// and registry entry. Session locks are tokio mutexes and are released as
+/// the panic unwinds; other sessions are unaffected.
+async fn supervise_session<F>(
+    session: &ClientSession,
+    future: F,
+    worker_pool: Option<&Arc<PinnedWorkerPool>>,
+) -> Result<TeardownReason, ServerError>
+where
+    F: Future<Output = Result<TeardownReason, ServerError>> + Send + 'static,
+{
+    let panic = match worker_pool {
+        // Keep all of this client's processing on one worker
+        Some(pool) => match pool.spawn_pinned(&session.client_id, future).await {
+            Ok(result) => return result,
+            Err(e) if e.is_panic() => e.into_panic(),
+            Err(e) => return Err(ServerError::Internal(format!("Pinned session task failed: {}", e))),
+        },
+        None => match AssertUnwindSafe(future).catch_unwind().await {
+            Ok(result) => return result,
+            Err(panic) => panic,
+        },
+    };
+
+    let message = panic.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    error!("Session {} for {} panicked: {}", session.id, session.client_id, message);
+    session.mark_stream_taken().await;
+    session.close().await;
+    Err(ServerError::Internal(format!("Session task panicked: {}", message)))
+}
 
-/// Process messages from an authenticated client session
+/// Process messages from an authenticated client session.
+///
+/// Runs in a `session` span carrying the session ID, client key and peer
+/// address, which the heartbeat and key rotation tasks inherit, so their
+/// logs need not repeat the client key.
+#[tracing::instrument(
+    name = "session",
+    skip_all,
+    fields(session_id = %session.id, client_id = %session.client_id, peer_addr = %session.address),
+)]
 async fn process_client_session(
     session: ClientSession,
     key_manager: Arc<KeyManager>, // Keep original Arc
     session_key_manager: Arc<SessionKeyManager>, // Now receives a clone
     packet_router: Arc<PacketRouter>, // Keep original Arc
     network_monitor: Arc<NetworkMonitor>, // Keep original Arc
-    _ip_pool: Arc<IpPoolManager>, // Mark unused if cleanup is outside
-    _session_manager: Arc<SessionManager>, // Mark unused if cleanup is outside
+    ip_pool: Arc<IpPoolManager>,
+    session_manager: Arc<SessionManager>,
+    metrics: Arc<ServerMetricsCollector>,
     server_state: Arc<RwLock<ServerState>>,
-) -> Result<(), ServerError> {
+) -> Result<TeardownReason, ServerError> {
     let client_id = session.client_id.clone();
     let session_id = session.id.clone();
     let ip_address = session.ip_address.clone();
     // let _address = session.address; // Marked unused
 
     // --- Heartbeat Task ---
-    let heartbeat_interval = Duration::from_secs(30);
+    // Also enforces the idle timeout, pinging at least three times per idle window,
+    // and the maximum session duration
+    let idle_timeout = session_manager.idle_timeout();
+    let max_missed_pongs = session_manager.max_missed_pongs();
+    let heartbeat_policy = session_manager.heartbeat_policy();
     let session_hb = session.clone(); // Clone session for heartbeat task
-    let heartbeat_handle = tokio::spawn(async move {
+    let heartbeat = TaskGuard(tokio::spawn(async move {
+        let mut heartbeat_interval = heartbeat_policy.initial_interval(idle_timeout);
         let mut interval = time::interval(heartbeat_interval);
         let mut sequence: u64 = 0;
         loop {
@@ -450,37 +1130,104 @@ async fn process_client_session(
             if session_hb.is_stream_taken().await {
                  break;
             }
-            let ping = PacketType::Ping {
-                timestamp: current_timestamp_millis(),
-                sequence,
+            // Every inbound message (Pongs included) refreshes the idle timer
+            let idle = session_hb.idle_time().await;
+            if !idle_timeout.is_zero() && idle >= idle_timeout {
+                info!("Session idle for {}s; disconnecting", idle.as_secs());
+                let disconnect = create_disconnect_packet(DisconnectReason::IdleTimeout, "Idle timeout");
+                let _ = time::timeout(crate::config::constants::DISCONNECT_SEND_TIMEOUT, session_hb.send_packet(&disconnect)).await;
+                session_hb.mark_stream_taken().await;
+                session_hb.close().await;
+                break;
+            }
+            // The ACL tier caps the session length; read each tick so reloads apply
+            if session_hb.past_max_duration().await {
+                info!("Session reached its maximum duration; disconnecting");
+                let disconnect = create_disconnect_packet(DisconnectReason::SessionExpired, "Maximum session duration reached");
+                let _ = time::timeout(crate::config::constants::DISCONNECT_SEND_TIMEOUT, session_hb.send_packet(&disconnect)).await;
+                session_hb.mark_stream_taken().await;
+                session_hb.close().await;
+                break;
+            }
+            // A half-open connection accepts pings but never answers them
+            let unanswered = session_hb.unanswered_pings().await;
+            if max_missed_pongs > 0 && unanswered > max_missed_pongs {
+                warn!("{} consecutive heartbeats unanswered; closing connection", unanswered);
+                session_hb.mark_connection_dead().await;
+                let _ = time::timeout(crate::config::constants::DISCONNECT_SEND_TIMEOUT, session_hb.close()).await;
+                break;
+            }
+            // Echoed back in the Pong, so take it from the clock RTTs are measured on
+            let sent_at = monotonic_millis();
+            let sent = if session_hb.capabilities.ws_keepalive {
+                // Control-frame keepalive: the timestamp payload is echoed back in the Pong frame
+                session_hb.send_ws_ping(sent_at.to_be_bytes().to_vec()).await
+            } else {
+                let ping = PacketType::Ping {
+                    timestamp: sent_at,
+                    sequence,
+                };
+                session_hb.send_packet(&ping).await
             };
-            if session_hb.send_packet(&ping).await.is_err() {
-                warn!("Failed to send heartbeat to {}: channel closed", session_hb.client_id);
+            if sent.is_err() {
+                warn!("Failed to send heartbeat: channel closed");
                 break;
             }
+            session_hb.record_ping_sent(sequence, sent_at).await;
             sequence = sequence.wrapping_add(1);
+
+            // Back off while the link is slow, using the RTT of the last answered ping
+            let next = heartbeat_policy.next_interval(heartbeat_interval, idle_timeout, session_hb.last_rtt_ms());
+            if next != heartbeat_interval {
+                debug!("Heartbeat interval {:?} -> {:?} (RTT {:?} ms)", heartbeat_interval, next, session_hb.last_rtt_ms());
+                heartbeat_interval = next;
+                interval = time::interval_at(time::Instant::now() + next, next);
+            }
         }
-    });
+    }.in_current_span()));
 
     // --- Key Rotation Task ---
-    let rotation_interval = Duration::from_secs(3600); // 1 hour
+    let rotation_interval = session.key_rotation_interval;
     let session_rot = session.clone(); // Clone session for rotation task
     let session_key_manager_clone = session_key_manager.clone();
     let key_manager_clone = key_manager.clone();
-    let key_rotation_handle = tokio::spawn(async move {
+    let key_rotation = TaskGuard(tokio::spawn(async move {
         let mut interval = time::interval(rotation_interval);
         loop {
-            interval.tick().await;
+            // Rotate on schedule, or at once when the nonce counters run high
+            let forced = tokio::select! {
+                _ = interval.tick() => false,
+                _ = session_rot.key_rotation_requested() => true,
+            };
             // Check if session is closing - Remove dereference (*)
             if session_rot.is_stream_taken().await {
                 break;
             }
 
-            if !session_key_manager_clone.needs_rotation(&session_rot.client_id).await {
-                continue;
+            if forced {
+                debug!("Nonce counter threshold reached, rotating session key early");
+            } else {
+                // Honour a client-requested postponement, then rotate as soon as it ends
+                while let Some(remaining) = session_key_manager_clone.rotation_deferred(&session_rot.client_id).await {
+                    time::sleep(remaining).await;
+                }
+
+                if !session_key_manager_clone.needs_rotation_after(&session_rot.client_id, rotation_interval).await {
+                    continue;
+                }
             }
 
-            debug!("Rotating session key for client {}", session_rot.client_id);
+            // Spread simultaneous rotations out under the server-wide rate limit
+            let pacing = session_key_manager_clone.reserve_rotation_slot().await;
+            if !pacing.is_zero() {
+                trace!("Delaying key rotation by {:?}", pacing);
+                time::sleep(pacing).await;
+                if session_rot.is_stream_taken().await {
+                    break;
+                }
+            }
+
+            debug!("Rotating session key");
 
             let new_key = SessionKeyManager::generate_key();
 
@@ -498,7 +1245,7 @@ async fn process_client_session(
                  ) {
                      Ok(packet) => packet,
                      Err(e) => {
-                         warn!("Failed to encrypt new session key for {}: {}", session_rot.client_id, e);
+                         warn!("Failed to encrypt new session key: {}", e);
                          continue;
                      }
                  };
@@ -511,73 +1258,123 @@ async fn process_client_session(
                  let rotation = PacketType::KeyRotation {
                      encrypted_new_key: encrypted_packet.data,
                      nonce: encrypted_packet.nonce,
-                     key_id,
+                     key_id: key_id.clone(),
                      signature: signature.to_string(),
                  };
 
                 if session_rot.send_packet(&rotation).await.is_err() {
-                     warn!("Failed to send key rotation to {}: channel closed", session_rot.client_id);
+                     warn!("Failed to send key rotation: channel closed");
                      break;
                  }
 
-                session_key_manager_clone.store_key(&session_rot.client_id, new_key).await;
-                 debug!("Session key rotated for client {}", session_rot.client_id);
+                session_key_manager_clone.store_rotated_key(&session_rot.client_id, new_key, &key_id).await;
+                metrics.record_key_rotation().await;
+                 debug!("Session key rotated");
              } else {
-                 warn!("Could not get current session key for rotation for client {}", session_rot.client_id);
+                 warn!("Could not get current session key for rotation");
              }
         }
-    });
+    }.in_current_span()));
 
 
-    let mut last_counter: Option<u64> = None;
-
     // Main message processing loop
-     loop {
+     let teardown_reason = loop {
          // Check server state first
          let current_state = *server_state.read().await;
          if current_state != ServerState::Running {
-             let disconnect = create_disconnect_packet(2, "Server shutting down");
+             let disconnect = create_disconnect_packet(DisconnectReason::ServerShutdown, "Server shutting down");
              let _ = session.send_packet(&disconnect).await; // Attempt to notify client
              return Err(ServerError::Internal("Server shutting down".to_string()));
          }
 
-         match session.next_message().await {
+         // Session was closed by the server (e.g. access revoked)
+         if session.is_stream_taken().await {
+             if session.is_connection_dead() {
+                 return Err(ServerError::ConnectionDead(session.unanswered_pings().await));
+             }
+             debug!("Session closed by server");
+             break TeardownReason::ClosedByServer;
+         }
+
+         let message = tokio::select! {
+             message = session.next_message() => message,
+             _ = session.stream_taken() => continue,
+         };
+         match message {
              Some(Ok(msg)) => {
                  session.update_activity().await;
 
+                 // WebSocket control frames only feed the idle timer (and RTT for our own pings)
+                 match &msg {
+                     Message::Pong(payload) => {
+                         if let Ok(bytes) = <[u8; 8]>::try_from(payload.as_slice()) {
+                             let sent_at = u64::from_be_bytes(bytes);
+                             let now = monotonic_millis();
+                             if session.acknowledge_ping(sent_at).await && now >= sent_at {
+                                 network_monitor.record_latency(&client_id, (now - sent_at) as f64).await;
+                                 session.record_rtt(now - sent_at);
+                             }
+                         }
+                         continue;
+                     }
+                     Message::Ping(_) => {
+                         // The WebSocket layer answers pings automatically
+                         continue;
+                     }
+                     Message::Close(frame) => {
+                         match frame {
+                             Some(frame) => info!("Client closed the connection: code {}, reason {:?}", u16::from(frame.code), frame.reason),
+                             None => info!("Client closed the connection without a status code"),
+                         }
+                         let outcome = close_frame_outcome(frame.as_ref());
+
+                         // Answer with our own Close, echoing the client's code
+                         let reply = CloseFrame {
+                             code: frame.as_ref().map_or(CloseCode::Normal, |frame| frame.code),
+                             reason: "".into(),
+                         };
+                         session.close_with(Some(reply)).await;
+                         match outcome {
+                             Ok(reason) => break reason,
+                             Err(e) => return Err(e),
+                         }
+                     }
+                     _ => {}
+                 }
+
                  match ws_message_to_packet(&msg) {
                      Ok(packet) => {
                          log_packet_info(&packet, true);
+                         if session.traced {
+                             log_sampled_packet(&session.id, &packet, true);
+                         }
 
                          match packet {
-                            PacketType::Data { encrypted, nonce, counter, padding: _, encryption_algorithm } => {
-                                 if let Some(last) = last_counter {
-                                     if counter <= last && counter != 0 {
-                                         warn!("Potential replay attack detected from {}: counter {} <= {}", client_id, counter, last);
-                                         continue;
-                                     }
-                                 }
-                                 last_counter = Some(counter);
-
+                            // The outer `padding` field is unauthenticated and dropped; padding
+                            // travels inside the ciphertext and is stripped by the router
+                            PacketType::Data { encrypted, nonce, counter, padding: _, encryption_algorithm, fragment } => {
+                                 // Counters are replay checked by the router, once the packet is authenticated
                                  if let Some(key) = session_key_manager.get_key(&client_id).await {
                                      // session对象直接传递给handle_inbound_packet，由函数内部正确处理
                                      match packet_router.handle_inbound_packet(
                                          &encrypted, 
                                          &nonce, 
+                                         counter,
                                          &key, 
                                          &session,
                                          encryption_algorithm.as_deref(),
-                                     ).await {
+                                         fragment.as_ref(),
+                                     ).instrument(debug_span!("packet", counter, len = encrypted.len())).await {
                                          Ok(bytes_written) => {
                                              network_monitor.record_client_traffic(&client_id, 0, bytes_written as u64).await;
                                              network_monitor.record_sent(bytes_written as u64).await;
                                          }
                                          Err(e) => {
-                                             trace!("Failed to process inbound packet from {}: {}", client_id, e);
+                                             trace!(counter, "Failed to process inbound packet: {}", e);
                                          }
                                      }
                                  } else {
-                                     warn!("No session key found for client {}, dropping packet", client_id);
+                                     warn!("No session key found, dropping packet");
                                  }
                              }
                              PacketType::Ping { timestamp, sequence } => {
@@ -587,78 +1384,1758 @@ async fn process_client_session(
                                      sequence,
                                  };
                                  if session.send_packet(&pong).await.is_err() {
-                                     warn!("Failed to send pong to {}: channel closed", client_id);
+                                     warn!("Failed to send pong: channel closed");
                                      return Err(ServerError::Network("Pong send failed".to_string()));
                                  }
                              }
                              PacketType::Pong { echo_timestamp, server_timestamp: _, sequence: _ } => {
-                                 let now = current_timestamp_millis();
+                                 // Only echoes of our own heartbeats clear them and give an RTT
+                                 if !session.acknowledge_ping(echo_timestamp).await {
+                                     debug!("Pong echoes no outstanding heartbeat");
+                                     continue;
+                                 }
+                                 let now = monotonic_millis();
                                  if now >= echo_timestamp {
                                      let rtt = now - echo_timestamp;
                                      network_monitor.record_latency(&client_id, rtt as f64).await;
+                                     session.record_rtt(rtt);
                                  } else {
-                                      warn!("Received Pong with future timestamp from {}", client_id);
+                                      warn!("Received Pong with future timestamp");
                                  }
                              }
-                             PacketType::IpRenewal { session_id: renewal_id, ip_address: renewal_ip } => {
+                             PacketType::IpRenewal { session_id: renewal_id, ip_address: renewal_ip, mac, sequence } => {
                                  if renewal_id != session_id {
-                                     warn!("IP renewal with mismatched session ID from {}", client_id);
+                                     warn!("IP renewal with mismatched session ID");
                                      continue;
                                  }
                                  if renewal_ip != ip_address {
-                                     warn!("IP renewal with mismatched IP from {}", client_id);
+                                     warn!("IP renewal with mismatched IP");
                                      continue;
                                  }
-                                 // Get the IP pool from the session_manager or pass it in
-                                 // Assuming IP Pool is accessible (e.g., passed into process_client_session)
-                                 // let ip_pool = ...; // Get access to IP Pool
-                                 // match ip_pool.renew_ip(&ip_address).await { ... }
-
-                                 // Placeholder: Need access to ip_pool here
-                                 warn!("IP Renewal requested but IP pool access not implemented here");
-                                 let response = PacketType::IpRenewalResponse {
-                                      session_id: session_id.clone(),
-                                      expires_at: 0, // Indicate failure
-                                      success: false,
-                                  };
-                                  if session.send_packet(&response).await.is_err() {
-                                      warn!("Failed to send failed IP renewal response to {}: channel closed", client_id);
-                                      return Err(ServerError::Network("Failed IP renewal response send failed".to_string()));
-                                  }
-
+                                 if session.capabilities.signed_renewal {
+                                     // Renewal must prove possession of the session key (and cover the sequence,
+                                     // which is only negotiated together with signed renewals)
+                                     let key = session_key_manager.get_key(&client_id).await;
+                                     let signed_sequence = sequence.filter(|_| session.capabilities.control_sequence);
+                                     let authentic = match (&key, &mac) {
+                                         (Some(key), Some(mac)) => verify_ip_renewal(key, &session_id, &ip_address, signed_sequence, mac),
+                                         _ => false,
+                                     };
+                                     if !authentic {
+                                         warn!("Rejected unsigned or forged IP renewal");
+                                         let response = PacketType::IpRenewalResponse {
+                                             session_id: session_id.clone(),
+                                             expires_at: 0,
+                                             success: false,
+                                             reason: Some(ErrorCode::Unauthorized.code()),
+                                         };
+                                         if session.send_packet(&response).await.is_err() {
+                                             return Err(ServerError::Network("IP renewal response send failed".to_string()));
+                                         }
+                                         continue;
+                                     }
+                                 }
+                                 // Checked after the MAC so forged packets cannot consume sequence numbers
+                                 if session.capabilities.control_sequence && !session.accept_control_sequence(sequence).await {
+                                     warn!("Rejected replayed IP renewal (sequence {:?})", sequence);
+                                     let response = PacketType::IpRenewalResponse {
+                                         session_id: session_id.clone(),
+                                         expires_at: 0,
+                                         success: false,
+                                         reason: Some(ErrorCode::ReplayDetected.code()),
+                                     };
+                                     if session.send_packet(&response).await.is_err() {
+                                         return Err(ServerError::Network("IP renewal response send failed".to_string()));
+                                     }
+                                     continue;
+                                 }
+                                 let response = match ip_pool.renew_ip(&ip_address, &client_id).await {
+                                     Ok(expires_at) => {
+                                         debug!("Renewed IP {}", ip_address);
+                                         session.record_ip_renewal();
+                                         PacketType::IpRenewalResponse {
+                                             session_id: session_id.clone(),
+                                             expires_at,
+                                             success: true,
+                                             reason: None,
+                                         }
+                                     }
+                                     Err(e) => {
+                                         let reason = match e {
+                                             IpPoolError::LeaseLost(_) => ErrorCode::LeaseLost,
+                                             _ => ErrorCode::InternalError,
+                                         };
+                                         warn!("IP renewal failed: {}", e);
+                                         PacketType::IpRenewalResponse {
+                                             session_id: session_id.clone(),
+                                             expires_at: 0,
+                                             success: false,
+                                             reason: Some(reason.code()),
+                                         }
+                                     }
+                                 };
+                                 if session.send_packet(&response).await.is_err() {
+                                     warn!("Failed to send IP renewal response: channel closed");
+                                     return Err(ServerError::Network("IP renewal response send failed".to_string()));
+                                 }
                              }
-                             PacketType::Disconnect { reason, message } => {
-                                 info!("Client {} disconnecting: {} (reason {})", client_id, message, reason);
-                                 break; // Break loop for graceful disconnect
+                             PacketType::DeferKeyRotation { seconds } => {
+                                 let granted = session_key_manager
+                                     .defer_rotation(&client_id, Duration::from_secs(seconds as u64))
+                                     .await;
+                                 let response = PacketType::KeyRotationDeferred { seconds: granted.as_secs() as u32 };
+                                 if session.send_packet(&response).await.is_err() {
+                                     warn!("Failed to send key rotation deferral: channel closed");
+                                     return Err(ServerError::Network("Key rotation deferral send failed".to_string()));
+                                 }
+                             }
+                             PacketType::Disconnect { reason, message, sequence, mac, .. } => {
+                                 if session.capabilities.control_sequence {
+                                     // The MAC covers the sequence, so a forged Disconnect can neither
+                                     // end the session nor push the sequence out of the client's reach
+                                     let key = session_key_manager.get_key(&client_id).await;
+                                     let authentic = match (&key, &mac, sequence) {
+                                         (Some(key), Some(mac), Some(sequence)) => verify_disconnect(key, &session_id, reason, sequence, mac),
+                                         _ => false,
+                                     };
+                                     if !authentic {
+                                         warn!("Rejected unsigned or forged Disconnect");
+                                         let error_packet = create_error_packet(ErrorCode::Unauthorized, "Disconnect not signed with the session key");
+                                         if session.send_packet(&error_packet).await.is_err() {
+                                             return Err(ServerError::Network("Error packet send failed".to_string()));
+                                         }
+                                         continue;
+                                     }
+                                 }
+                                 if session.capabilities.control_sequence && !session.accept_control_sequence(sequence).await {
+                                     warn!("Rejected replayed Disconnect (sequence {:?})", sequence);
+                                     let error_packet = create_error_packet(ErrorCode::ReplayDetected, "Disconnect sequence missing or already used");
+                                     if session.send_packet(&error_packet).await.is_err() {
+                                         return Err(ServerError::Network("Error packet send failed".to_string()));
+                                     }
+                                     continue;
+                                 }
+                                 info!("Client disconnecting: {} (reason {})", message, reason);
+                                 if session.capabilities.disconnect_ack {
+                                     // Confirm graceful teardown, without letting a stalled client hold the session
+                                     let (bytes_sent, bytes_received) = session.traffic();
+                                     let ack = PacketType::DisconnectAck { bytes_sent, bytes_received };
+                                     match time::timeout(crate::config::constants::DISCONNECT_ACK_TIMEOUT, session.send_packet(&ack)).await {
+                                         Ok(Ok(())) => debug!("Acknowledged disconnect"),
+                                         Ok(Err(e)) => debug!("Failed to send disconnect ack: {}", e),
+                                         Err(_) => warn!("Timed out sending disconnect ack"),
+                                     }
+                                 }
+                                 break TeardownReason::ClientDisconnect; // Break loop for graceful disconnect
                              }
                              _ => {
-                                 warn!("Received unexpected packet type from {} during session", client_id);
+                                 warn!("Received unexpected packet type during session");
                              }
                          }
                      }
                      Err(e) => {
-                         warn!("Failed to parse message from {}: {}", client_id, e);
+                         warn!("Failed to parse message: {}", e);
                          // Maybe disconnect on parse error?
                          // return Err(ServerError::Protocol(e));
                      }
                  }
              }
              Some(Err(e)) => { // WebSocket error
-                 debug!("WebSocket error for client {}: {}", client_id, e);
+                 debug!("WebSocket error: {}", e);
+                 if let Some(frame) = message_too_big_close(&e) {
+                     warn!("Closing session: {}", e);
+                     session.close_with(Some(frame)).await;
+                 }
                  // Use explicit From conversion
                  return Err(ServerError::from(e));
              }
              None => { // WebSocket stream closed
-                 debug!("WebSocket connection closed for client {}", client_id);
-                 break; // Break loop for normal closure
+                 debug!("WebSocket connection closed");
+                 break TeardownReason::ConnectionClosed; // Break loop for normal closure
              }
          }
-     }
+     };
 
     // Abort background tasks associated with this session
-    heartbeat_handle.abort();
-    key_rotation_handle.abort();
+    drop(heartbeat);
+    drop(key_rotation);
     session.mark_stream_taken().await; // Mark session as closing
 
-    Ok(()) // Return Ok(()) if loop finishes normally
+    Ok(teardown_reason) // Return the reason if loop finishes normally
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::origin::{ConnectionOrigin, NoopOriginProvider};
+    use crate::server::connection::mock;
+    use serde_json::Value;
+    use solana_sdk::signature::{keypair_from_seed, Signer};
+    use std::path::PathBuf;
+
+    /// Golden transcript of a successful handshake.
+    ///
+    /// Client frames are replayed as recorded; `{{challenge_id}}` and
+    /// `{{signature}}` are filled in from the server's challenge. Server frames
+    /// are compared byte for byte after their per-connection random fields
+    /// are replaced with placeholders. Regenerate the server frames with
+    /// `AERONYX_REGEN_GOLDEN=1 cargo test test_handshake_golden_transcript`.
+    const GOLDEN_TRANSCRIPT: &str = include_str!("testdata/handshake_transcript.json");
+
+    fn golden_transcript_path() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/server/testdata/handshake_transcript.json")
+    }
+
+    /// Fields of server frames that differ on every connection
+    fn volatile_fields(packet_type: &str) -> &'static [&'static str] {
+        match packet_type {
+            "Challenge" => &["data", "expires_at", "id"],
+            "IpAssign" => &["session_id", "encrypted_session_key", "key_nonce"],
+            _ => &[],
+        }
+    }
+
+    /// Replace volatile fields in a raw server frame, keeping all other bytes
+    fn normalize_server_frame(raw: &str) -> String {
+        let value: Value = serde_json::from_str(raw).expect("server frame is not JSON");
+        let packet_type = value["type"].as_str().unwrap_or_default();
+        let mut normalized = raw.to_string();
+        for field in volatile_fields(packet_type) {
+            let field_value = &value[*field];
+            let placeholder = match field_value.as_array() {
+                Some(bytes) => format!("<{} bytes>", bytes.len()),
+                None => format!("<{}>", field),
+            };
+            let exact = format!("\"{}\":{}", field, field_value);
+            assert!(normalized.contains(&exact), "field {} not found in {}", field, raw);
+            normalized = normalized.replacen(&exact, &format!("\"{}\":\"{}\"", field, placeholder), 1);
+        }
+        normalized
+    }
+
+    #[tokio::test]
+    async fn test_handshake_golden_transcript() {
+        let regen = std::env::var("AERONYX_REGEN_GOLDEN").is_ok();
+        let mut transcript: Value = serde_json::from_str(GOLDEN_TRANSCRIPT).unwrap();
+        let client_keypair = keypair_from_seed(&[7u8; 32]).unwrap();
+
+        // Fixed server identity so the challenge carries a stable server key
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("server_key"), [1u8; 32]).unwrap();
+        let key_manager = Arc::new(KeyManager::new(dir.path().join("server_key"), Duration::from_secs(3600), 100).await.unwrap());
+        let auth_manager = Arc::new(AuthManager::new(
+            dir.path().join("acl.json"),
+            key_manager.clone(),
+            crate::config::constants::AUTH_CHALLENGE_TIMEOUT,
+            100,
+            crate::config::settings::ChallengeAddressBinding::Ip,
+        ).await.unwrap());
+        let ip_pool = Arc::new(IpPoolManager::new("10.7.0.0/24", 86400).await.unwrap());
+        let metrics = Arc::new(ServerMetricsCollector::new(Duration::from_secs(60), 60));
+
+        let (conn, mut peer) = mock::duplex();
+        let server = tokio::spawn(process_websocket_session_with_connection(
+            conn,
+            "127.0.0.1:40000".parse().unwrap(),
+            None,
+            key_manager,
+            auth_manager,
+            ip_pool,
+            Arc::new(SessionManager::new(5, Duration::from_secs(3600))),
+            Arc::new(SessionKeyManager::new(Duration::from_secs(3600), 1_000_000)),
+            Arc::new(NetworkMonitor::new(Duration::from_secs(5), 120)),
+            Arc::new(PacketRouter::new(crate::config::constants::PACKET_SIZE_LIMIT, false)),
+            metrics.clone(),
+            Arc::new(NoopOriginProvider),
+            None,
+            Arc::new(QuietHours::default()),
+            None,
+            None,
+            crate::config::defaults::DEFAULT_MAX_HANDSHAKE_DATA_PACKETS,
+            false,
+            0.0,
+            Arc::new(RwLock::new(ServerState::Running)),
+        ));
+
+        let mut challenge_id = String::new();
+        let mut signature = String::new();
+        for frame in transcript["frames"].as_array_mut().unwrap() {
+            match frame["from"].as_str().unwrap() {
+                "client" => {
+                    let message = frame["message"].as_str().unwrap()
+                        .replace("{{challenge_id}}", &challenge_id)
+                        .replace("{{signature}}", &signature);
+                    peer.to_server.send(Message::Text(message)).unwrap();
+                }
+                "server" => {
+                    let received = time::timeout(Duration::from_secs(5), peer.from_server.recv()).await
+                        .expect("timed out waiting for server frame")
+                        .expect("server closed the connection");
+                    let raw = received.to_text().unwrap().to_string();
+
+                    if let Ok(PacketType::Challenge { data, id, .. }) = ws_message_to_packet(&received) {
+                        challenge_id = id;
+                        signature = client_keypair.sign_message(&data).to_string();
+                    }
+
+                    let normalized = normalize_server_frame(&raw);
+                    if regen {
+                        frame["message"] = Value::String(normalized);
+                    } else {
+                        assert_eq!(normalized, frame["message"].as_str().unwrap(), "server frame differs from golden transcript");
+                    }
+                }
+                other => panic!("unknown frame origin {}", other),
+            }
+        }
+
+        // Closing the client end ends the session
+        drop(peer);
+        assert!(time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap().is_ok());
+        assert_eq!(metrics.get_metrics().await.auth_successes, 1);
+
+        if regen {
+            let json = serde_json::to_string_pretty(&transcript).unwrap();
+            std::fs::write(golden_transcript_path(), json + "\n").unwrap();
+        }
+    }
+
+    const TEST_SESSION_KEY: [u8; 32] = [9u8; 32];
+
+    /// Run an established session over a mock connection, send `packets` and
+    /// then disconnect, returning every packet the server sent
+    async fn run_session(caps: NegotiatedCapabilities, packets: Vec<PacketType>) -> Vec<PacketType> {
+        let ip_pool = Arc::new(IpPoolManager::new("10.7.0.0/24", 86400).await.unwrap());
+        let ip_address = ip_pool.allocate_ip("client", None).await.unwrap();
+        run_session_with_pool(caps, ip_pool, ip_address, packets).await
+    }
+
+    /// As `run_session`, for a session holding `ip_address` in `ip_pool`
+    async fn run_session_with_pool(
+        caps: NegotiatedCapabilities,
+        ip_pool: Arc<IpPoolManager>,
+        ip_address: String,
+        packets: Vec<PacketType>,
+    ) -> Vec<PacketType> {
+        let dir = tempfile::tempdir().unwrap();
+        let key_manager = Arc::new(KeyManager::new(dir.path().join("server_key"), Duration::from_secs(3600), 100).await.unwrap());
+        let session_key_manager = Arc::new(SessionKeyManager::new(Duration::from_secs(3600), 1_000_000));
+        session_key_manager.store_key("client", TEST_SESSION_KEY.to_vec()).await;
+        let (conn, mut peer) = mock::duplex();
+        let session = ClientSession::new(
+            "session_test".to_string(),
+            "client".to_string(),
+            ip_address,
+            "127.0.0.1:40000".parse().unwrap(),
+            conn.sender(),
+            conn.receiver(),
+            None,
+        ).unwrap().with_capabilities(caps);
+        drop(conn);
+
+        let server = tokio::spawn(process_client_session(
+            session,
+            key_manager,
+            session_key_manager,
+            Arc::new(PacketRouter::new(crate::config::constants::PACKET_SIZE_LIMIT, false)),
+            Arc::new(NetworkMonitor::new(Duration::from_secs(5), 120)),
+            ip_pool,
+            Arc::new(SessionManager::new(5, Duration::from_secs(3600))),
+            Arc::new(ServerMetricsCollector::new(Duration::from_secs(60), 60)),
+            Arc::new(RwLock::new(ServerState::Running)),
+        ));
+
+        for packet in packets {
+            peer.to_server.send(packet_to_ws_message(&packet).unwrap()).unwrap();
+        }
+        // The session may already have ended on a Disconnect among `packets`
+        let disconnect = create_disconnect_packet(DisconnectReason::UserInitiated, "bye");
+        let _ = peer.to_server.send(packet_to_ws_message(&disconnect).unwrap());
+        assert!(time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap().is_ok());
+
+        let mut sent = Vec::new();
+        while let Ok(msg) = peer.from_server.try_recv() {
+            sent.push(ws_message_to_packet(&msg).unwrap());
+        }
+        sent
+    }
+
+    /// Collects formatted log output for inspection
+    #[derive(Clone, Default)]
+    struct Captured(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Captured {
+        /// Log output so far
+        fn output(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    /// Capture this thread's log output until the guard is dropped
+    fn capture_logs() -> (Captured, tracing::subscriber::DefaultGuard) {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        (captured, tracing::subscriber::set_default(subscriber))
+    }
+
+    #[tokio::test]
+    async fn test_session_log_spans() {
+        let (captured, _default) = capture_logs();
+
+        // Undecryptable Data, then a packet the session does not expect
+        let data = PacketType::Data {
+            encrypted: vec![0u8; 32],
+            nonce: vec![0u8; 12],
+            counter: 7,
+            padding: None,
+            encryption_algorithm: None,
+            fragment: None,
+        };
+        let unexpected = PacketType::Challenge {
+            data: vec![1],
+            server_key: "server".to_string(),
+            expires_at: 0,
+            id: "challenge".to_string(),
+            pow_difficulty: None,
+        };
+        run_session(NegotiatedCapabilities::default(), vec![data, unexpected]).await;
+
+        let output = captured.output();
+        let session_span = "session{session_id=session_test client_id=client peer_addr=127.0.0.1:40000}";
+        let line = |message: &str| output.lines().find(|line| line.contains(message))
+            .unwrap_or_else(|| panic!("no {:?} in log:\n{}", message, output));
+        // Session logs carry the session's identity without repeating it
+        assert!(line("Received unexpected packet type during session").contains(session_span));
+        assert!(line("Client disconnecting").contains(session_span));
+        // Router logs for an inbound packet are nested in its own span
+        let decrypt = line("Packet decryption failed");
+        assert!(decrypt.contains(&format!("{}:packet{{counter=7 len=32}}", session_span)), "{}", decrypt);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_ack() {
+        let caps = NegotiatedCapabilities::negotiate(
+            &[crate::protocol::types::features::DISCONNECT_ACK.to_string()],
+            EncryptionAlgorithm::default(),
+            false,
+            None,
+        );
+        let sent = run_session(caps, Vec::new()).await;
+        let ack = sent.iter().position(|p| matches!(p, PacketType::DisconnectAck { .. }));
+        match ack.map(|index| (index, &sent[index])) {
+            Some((index, PacketType::DisconnectAck { bytes_sent, bytes_received })) => {
+                // The Disconnect itself is counted; the ack is not
+                let disconnect = create_disconnect_packet(DisconnectReason::UserInitiated, "bye");
+                assert_eq!(*bytes_received, packet_to_ws_message(&disconnect).unwrap().len() as u64);
+                let heartbeats: u64 = sent[..index].iter()
+                    .map(|p| packet_to_ws_message(p).unwrap().len() as u64)
+                    .sum();
+                assert_eq!(*bytes_sent, heartbeats);
+            }
+            other => panic!("expected DisconnectAck, got {:?}", other),
+        }
+
+        // Clients that did not ask for it get no ack
+        let sent = run_session(NegotiatedCapabilities::default(), Vec::new()).await;
+        assert!(!sent.iter().any(|p| matches!(p, PacketType::DisconnectAck { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_signed_ip_renewal() {
+        use crate::crypto::encryption::sign_ip_renewal;
+
+        let caps = NegotiatedCapabilities::negotiate(
+            &[crate::protocol::types::features::SIGNED_RENEWAL.to_string()],
+            EncryptionAlgorithm::default(),
+            false,
+            None,
+        );
+        let renewal = |mac: Option<Vec<u8>>| PacketType::IpRenewal {
+            session_id: "session_test".to_string(),
+            ip_address: "10.7.0.2".to_string(),
+            mac,
+            sequence: None,
+        };
+        let valid = sign_ip_renewal(&TEST_SESSION_KEY, "session_test", "10.7.0.2", None).unwrap();
+        let forged = sign_ip_renewal(&[1u8; 32], "session_test", "10.7.0.2", None).unwrap();
+
+        let sent = run_session(caps, vec![
+            renewal(None),
+            renewal(Some(forged)),
+            renewal(Some(valid.clone())),
+        ]).await;
+        let outcomes: Vec<_> = sent.iter()
+            .filter_map(|p| match p {
+                PacketType::IpRenewalResponse { success, reason, .. } => Some((*success, *reason)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(outcomes, vec![
+            (false, Some(ErrorCode::Unauthorized.code())),
+            (false, Some(ErrorCode::Unauthorized.code())),
+            (true, None),
+        ]);
+
+        // Without the feature, unsigned renewals are still accepted
+        let sent = run_session(NegotiatedCapabilities::default(), vec![renewal(None)]).await;
+        assert!(sent.iter().any(|p| matches!(p, PacketType::IpRenewalResponse { success: true, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_control_packet_replay() {
+        use crate::crypto::encryption::{sign_disconnect, sign_ip_renewal};
+        use crate::protocol::types::features;
+
+        let caps = NegotiatedCapabilities::negotiate(
+            &[features::SIGNED_RENEWAL.to_string(), features::CONTROL_SEQUENCE.to_string()],
+            EncryptionAlgorithm::default(),
+            false,
+            None,
+        );
+        let signed_renewal = |key: &[u8], sequence: Option<u64>| PacketType::IpRenewal {
+            session_id: "session_test".to_string(),
+            ip_address: "10.7.0.2".to_string(),
+            mac: Some(sign_ip_renewal(key, "session_test", "10.7.0.2", sequence).unwrap()),
+            sequence,
+        };
+        let renewal = |sequence: Option<u64>| signed_renewal(&TEST_SESSION_KEY[..], sequence);
+        let signed_disconnect = |key: Option<&[u8]>, sequence: u64| PacketType::Disconnect {
+            reason: DisconnectReason::UserInitiated.code(),
+            message: "bye".to_string(),
+            retry: None,
+            sequence: Some(sequence),
+            mac: key.map(|key| sign_disconnect(key, "session_test", DisconnectReason::UserInitiated.code(), sequence).unwrap()),
+        };
+        let disconnect = |sequence: u64| signed_disconnect(Some(&TEST_SESSION_KEY[..]), sequence);
+        let forged_key = [1u8; 32];
+        // A sequence swapped into a captured renewal breaks its MAC
+        let mut resequenced = renewal(Some(1));
+        if let PacketType::IpRenewal { sequence, .. } = &mut resequenced {
+            *sequence = Some(5);
+        }
+
+        let sent = run_session(caps, vec![
+            renewal(Some(1)),
+            renewal(Some(1)),
+            resequenced,
+            renewal(None),
+            // Forged packets with the highest sequence do not advance it
+            signed_renewal(&forged_key[..], Some(u64::MAX)),
+            renewal(Some(2)),
+            disconnect(2),
+            signed_disconnect(None, 9),
+            signed_disconnect(Some(&forged_key[..]), u64::MAX),
+            disconnect(3),
+        ]).await;
+        let outcomes: Vec<_> = sent.iter()
+            .filter_map(|p| match p {
+                PacketType::IpRenewalResponse { success, reason, .. } => Some((*success, *reason)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(outcomes, vec![
+            (true, None),
+            (false, Some(ErrorCode::ReplayDetected.code())),
+            (false, Some(ErrorCode::Unauthorized.code())),
+            (false, Some(ErrorCode::ReplayDetected.code())),
+            (false, Some(ErrorCode::Unauthorized.code())),
+            (true, None),
+        ]);
+        // The stale, unsigned and forged Disconnects were refused before the fresh one ended the session
+        let refused = |code: ErrorCode| sent.iter()
+            .filter(|p| matches!(p, PacketType::Error { code: sent_code, .. } if *sent_code == code.code()))
+            .count();
+        assert_eq!(refused(ErrorCode::ReplayDetected), 1);
+        assert_eq!(refused(ErrorCode::Unauthorized), 2);
+    }
+
+    #[test]
+    fn test_trace_sampling_rate() {
+        assert!((0..1000).all(|_| !sample_for_tracing(0.0)));
+        assert!((0..1000).all(|_| sample_for_tracing(1.0)));
+        let sampled = (0..10_000).filter(|_| sample_for_tracing(0.1)).count();
+        assert!((500..2000).contains(&sampled), "sampled {} of 10000", sampled);
+    }
+
+    #[tokio::test]
+    async fn test_renewal_checks_pool_lease() {
+        let renewal = |ip: &str| PacketType::IpRenewal {
+            session_id: "session_test".to_string(),
+            ip_address: ip.to_string(),
+            mac: None,
+            sequence: None,
+        };
+        let outcome = |sent: Vec<PacketType>| sent.iter()
+            .find_map(|p| match p {
+                PacketType::IpRenewalResponse { success, reason, .. } => Some((*success, *reason)),
+                _ => None,
+            });
+
+        // Another session for the same key ended and released the shared IP
+        let ip_pool = Arc::new(
+            IpPoolManager::new("10.7.0.0/24", 86400).await.unwrap()
+                .with_release_grace(Duration::from_secs(60))
+        );
+        let ip = ip_pool.allocate_ip("client", None).await.unwrap();
+        ip_pool.release_ip_after_session(&ip).await.unwrap();
+        let sent = run_session_with_pool(NegotiatedCapabilities::default(), ip_pool, ip.clone(), vec![renewal(&ip)]).await;
+        assert_eq!(outcome(sent), Some((false, Some(ErrorCode::LeaseLost.code()))));
+
+        // The cached IP was reclaimed and the client now leases a different one
+        let ip_pool = Arc::new(IpPoolManager::new("10.7.0.0/24", 86400).await.unwrap());
+        let stale = ip_pool.allocate_ip("client", None).await.unwrap();
+        ip_pool.release_ip(&stale).await.unwrap();
+        let current = ip_pool.allocate_ip("client", None).await.unwrap();
+        assert_ne!(stale, current);
+        let sent = run_session_with_pool(NegotiatedCapabilities::default(), ip_pool.clone(), stale.clone(), vec![renewal(&stale)]).await;
+        assert_eq!(outcome(sent), Some((false, Some(ErrorCode::LeaseLost.code()))));
+        assert_eq!(ip_pool.get_client_ip("client").await, Some(current));
+    }
+
+    /// Run an idle session managed by `session_manager`, returning the handler and the client end
+    async fn spawn_heartbeat_session(
+        session_manager: SessionManager,
+    ) -> (tokio::task::JoinHandle<Result<TeardownReason, ServerError>>, mock::MockPeer) {
+        let dir = tempfile::tempdir().unwrap();
+        let key_manager = Arc::new(KeyManager::new(dir.path().join("server_key"), Duration::from_secs(3600), 100).await.unwrap());
+        let ip_pool = Arc::new(IpPoolManager::new("10.7.0.0/24", 86400).await.unwrap());
+        let ip_address = ip_pool.allocate_ip("client", None).await.unwrap();
+        let (conn, peer) = mock::duplex();
+        let session = ClientSession::new(
+            "session_heartbeat".to_string(),
+            "client".to_string(),
+            ip_address,
+            "127.0.0.1:40000".parse().unwrap(),
+            conn.sender(),
+            conn.receiver(),
+            None,
+        ).unwrap();
+        drop(conn);
+
+        let server = tokio::spawn(process_client_session(
+            session,
+            key_manager,
+            Arc::new(SessionKeyManager::new(Duration::from_secs(3600), 1_000_000)),
+            Arc::new(PacketRouter::new(crate::config::constants::PACKET_SIZE_LIMIT, false)),
+            Arc::new(NetworkMonitor::new(Duration::from_secs(5), 120)),
+            ip_pool,
+            Arc::new(session_manager),
+            Arc::new(ServerMetricsCollector::new(Duration::from_secs(60), 60)),
+            Arc::new(RwLock::new(ServerState::Running)),
+        ));
+        (server, peer)
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout() {
+        let idle_timeout = Duration::from_millis(300);
+        let (server, mut peer) = spawn_heartbeat_session(
+            SessionManager::new(5, Duration::from_secs(3600)).with_idle_timeout(idle_timeout),
+        ).await;
+        let started = Instant::now();
+        let mut last_pong = started;
+
+        // Answering heartbeats keeps the session alive past the deadline
+        let mut disconnect = None;
+        while disconnect.is_none() {
+            let msg = time::timeout(Duration::from_secs(5), peer.from_server.recv()).await.unwrap().unwrap();
+            match ws_message_to_packet(&msg).unwrap() {
+                PacketType::Ping { timestamp, sequence } if started.elapsed() < idle_timeout * 2 => {
+                    let pong = PacketType::Pong { echo_timestamp: timestamp, server_timestamp: timestamp, sequence };
+                    peer.to_server.send(packet_to_ws_message(&pong).unwrap()).unwrap();
+                    last_pong = Instant::now();
+                }
+                // Then the client goes quiet
+                PacketType::Ping { .. } => {}
+                PacketType::Disconnect { reason, .. } => disconnect = Some(reason),
+                _ => {}
+            }
+        }
+        assert_eq!(disconnect, Some(DisconnectReason::IdleTimeout.code()));
+        assert!(started.elapsed() >= idle_timeout * 2);
+        assert!(last_pong.elapsed() >= idle_timeout);
+
+        // The handler ends without the client closing the connection
+        let reason = time::timeout(Duration::from_secs(1), server).await.unwrap().unwrap().unwrap();
+        assert_eq!(reason, TeardownReason::ClosedByServer);
+    }
+
+    #[tokio::test]
+    async fn test_max_session_duration() {
+        use crate::auth::acl::AccessControlManager;
+
+        let mut server = TestServer::new(|auth_manager| auth_manager).await;
+        server.session_manager = Arc::new(SessionManager::new(5, Duration::from_secs(3600)).with_idle_timeout(Duration::from_millis(600)));
+        let client = keypair_from_seed(&[6u8; 32]).unwrap();
+        let mut entry = AccessControlManager::create_allow_entry(&client.pubkey().to_string());
+        entry.max_session_duration = 1;
+        server.auth_manager.add_client(entry).await.unwrap();
+
+        // An active client is still disconnected once its tier's limit is reached
+        let started = Instant::now();
+        let (handler, mut peer) = server.connect();
+        authenticate(&server, &mut peer, &client, &["chacha20poly1305"], None).await;
+        let to_server = peer.to_server.clone();
+        let disconnect = expect_packet(&mut peer, |packet| match packet {
+            PacketType::Ping { timestamp, sequence } => {
+                let pong = PacketType::Pong { echo_timestamp: timestamp, server_timestamp: timestamp, sequence };
+                to_server.send(packet_to_ws_message(&pong).unwrap()).unwrap();
+                None
+            }
+            PacketType::Disconnect { reason, .. } => Some(reason),
+            _ => None,
+        }).await;
+        assert_eq!(disconnect, DisconnectReason::SessionExpired.code());
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert!(time::timeout(Duration::from_secs(5), handler).await.unwrap().unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_client_close_frame() {
+        let (captured, _default) = capture_logs();
+
+        // Wait for the server's Close, skipping heartbeats
+        async fn close_reply(peer: &mut mock::MockPeer) -> Option<CloseFrame<'static>> {
+            loop {
+                match time::timeout(Duration::from_secs(5), peer.from_server.recv()).await.unwrap() {
+                    Some(Message::Close(frame)) => return frame,
+                    Some(_) => continue,
+                    None => panic!("connection dropped without a Close frame"),
+                }
+            }
+        }
+
+        // A normal closure ends the session gracefully and is acknowledged
+        let (server, mut peer) = spawn_heartbeat_session(SessionManager::new(5, Duration::from_secs(3600))).await;
+        let close = CloseFrame { code: CloseCode::Normal, reason: "user logged out".into() };
+        peer.to_server.send(Message::Close(Some(close))).unwrap();
+        assert_eq!(close_reply(&mut peer).await.map(|frame| frame.code), Some(CloseCode::Normal));
+        let reason = time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap().unwrap();
+        assert_eq!(reason, TeardownReason::ConnectionClosed);
+        let output = captured.output();
+        assert!(output.contains("Client closed the connection: code 1000, reason \"user logged out\""), "{}", output);
+
+        // Any other code is reported with the client's reason
+        let (server, mut peer) = spawn_heartbeat_session(SessionManager::new(5, Duration::from_secs(3600))).await;
+        let close = CloseFrame { code: CloseCode::Policy, reason: "certificate pinning failed".into() };
+        peer.to_server.send(Message::Close(Some(close))).unwrap();
+        assert_eq!(close_reply(&mut peer).await.map(|frame| frame.code), Some(CloseCode::Policy));
+        let result = time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap();
+        assert!(
+            matches!(&result, Err(ServerError::ClosedByClient(1008, reason)) if reason == "certificate pinning failed"),
+            "{:?}", result
+        );
+
+        // A Close without a status code is a normal closure
+        assert!(matches!(close_frame_outcome(None), Ok(TeardownReason::ConnectionClosed)));
+        let away = CloseFrame { code: CloseCode::Away, reason: "".into() };
+        assert!(matches!(close_frame_outcome(Some(&away)), Ok(TeardownReason::ConnectionClosed)));
+    }
+
+    #[tokio::test]
+    async fn test_missed_pongs() {
+        // The client stays active but its pongs are lost after the first few heartbeats
+        let (mut server, mut peer) = spawn_heartbeat_session(
+            SessionManager::new(5, Duration::from_secs(3600))
+                .with_idle_timeout(Duration::from_millis(600))
+                .with_max_missed_pongs(2),
+        ).await;
+
+        let mut answered = 0;
+        let result = time::timeout(Duration::from_secs(5), async {
+            loop {
+                tokio::select! {
+                    msg = peer.from_server.recv() => {
+                        let Some(msg) = msg else { continue };
+                        if let PacketType::Ping { timestamp, sequence } = ws_message_to_packet(&msg).unwrap() {
+                            let reply = if answered < 3 {
+                                answered += 1;
+                                PacketType::Pong { echo_timestamp: timestamp, server_timestamp: timestamp, sequence }
+                            } else {
+                                // Traffic that is not a pong keeps the idle timer fresh
+                                PacketType::Ping { timestamp, sequence }
+                            };
+                            peer.to_server.send(packet_to_ws_message(&reply).unwrap()).unwrap();
+                        }
+                    }
+                    result = &mut server => break result.unwrap(),
+                }
+            }
+        }).await.unwrap();
+        assert!(matches!(result, Err(ServerError::ConnectionDead(3))), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_unread_pongs_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_manager = Arc::new(KeyManager::new(dir.path().join("server_key"), Duration::from_secs(3600), 100).await.unwrap());
+        let ip_pool = Arc::new(IpPoolManager::new("10.7.0.0/24", 86400).await.unwrap());
+        let ip_address = ip_pool.allocate_ip("client", None).await.unwrap();
+        let (conn, mut peer) = mock::duplex();
+        let socket = conn.sender();
+        let session = ClientSession::new(
+            "session_pings".to_string(),
+            "client".to_string(),
+            ip_address,
+            "127.0.0.1:40000".parse().unwrap(),
+            conn.sender(),
+            conn.receiver(),
+            None,
+        ).unwrap().with_send_queue_depth(8);
+        drop(conn);
+        let server = tokio::spawn(process_client_session(
+            session.clone(),
+            key_manager,
+            Arc::new(SessionKeyManager::new(Duration::from_secs(3600), 1_000_000)),
+            Arc::new(PacketRouter::new(crate::config::constants::PACKET_SIZE_LIMIT, false)),
+            Arc::new(NetworkMonitor::new(Duration::from_secs(5), 120)),
+            ip_pool,
+            Arc::new(SessionManager::new(5, Duration::from_secs(3600))),
+            Arc::new(ServerMetricsCollector::new(Duration::from_secs(60), 60)),
+            Arc::new(RwLock::new(ServerState::Running)),
+        ));
+
+        // The client pings without reading; its pongs give way to newer ones
+        let stalled = socket.lock().await;
+        for sequence in 0..100 {
+            let ping = PacketType::Ping { timestamp: current_timestamp_millis(), sequence };
+            peer.to_server.send(packet_to_ws_message(&ping).unwrap()).unwrap();
+        }
+        time::timeout(Duration::from_secs(5), async {
+            // The writer may already hold the first pong
+            while session.tx_dropped() < 100 - 8 - 1 {
+                time::sleep(Duration::from_millis(10)).await;
+            }
+        }).await.unwrap();
+        assert!(session.send_queue_depth().await <= 8);
+        drop(stalled);
+
+        // The newest pongs are delivered and the session carries on
+        let mut sequences = Vec::new();
+        while let Ok(Some(msg)) = time::timeout(Duration::from_millis(200), peer.from_server.recv()).await {
+            if let PacketType::Pong { sequence, .. } = ws_message_to_packet(&msg).unwrap() {
+                sequences.push(sequence);
+            }
+        }
+        assert!(sequences.ends_with(&[97, 98, 99]), "{:?}", sequences);
+        assert!(!server.is_finished());
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_heartbeat() {
+        use crate::server::heartbeat::HeartbeatPolicy;
+
+        // Every pong is late, then the client stops answering altogether
+        let policy = HeartbeatPolicy::fixed(Duration::from_millis(100))
+            .with_backoff(Duration::from_millis(20), Duration::from_millis(400));
+        let (mut server, mut peer) = spawn_heartbeat_session(
+            SessionManager::new(5, Duration::from_secs(3600))
+                .with_max_missed_pongs(2)
+                .with_heartbeat_policy(policy),
+        ).await;
+
+        let mut pings = Vec::new();
+        let result = time::timeout(Duration::from_secs(5), async {
+            loop {
+                tokio::select! {
+                    msg = peer.from_server.recv() => {
+                        let Some(msg) = msg else { continue };
+                        if let PacketType::Ping { timestamp, sequence } = ws_message_to_packet(&msg).unwrap() {
+                            pings.push(Instant::now());
+                            if pings.len() <= 4 {
+                                time::sleep(Duration::from_millis(50)).await;
+                                let pong = PacketType::Pong { echo_timestamp: timestamp, server_timestamp: timestamp, sequence };
+                                peer.to_server.send(packet_to_ws_message(&pong).unwrap()).unwrap();
+                            }
+                        }
+                    }
+                    result = &mut server => break result.unwrap(),
+                }
+            }
+        }).await.unwrap();
+
+        // The interval doubled while the RTT stayed over the threshold, up to the maximum
+        let gaps: Vec<Duration> = pings.windows(2).map(|w| w[1] - w[0]).collect();
+        assert!(gaps[0] < Duration::from_millis(180), "{:?}", gaps);
+        assert!(gaps[1] >= Duration::from_millis(180) && gaps[1] < Duration::from_millis(380), "{:?}", gaps);
+        assert!(gaps[2] >= Duration::from_millis(380), "{:?}", gaps);
+        assert!(gaps[3] >= Duration::from_millis(380) && gaps[3] < Duration::from_millis(600), "{:?}", gaps);
+
+        // Unanswered pings are still counted at the longer interval
+        assert!(matches!(result, Err(ServerError::ConnectionDead(3))), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_capability_query() {
+        let test_server = |per_minute: usize| async move {
+            let mut server = TestServer::new(|auth_manager| auth_manager.with_capability_queries(per_minute)).await;
+            server.packet_router = Arc::new(PacketRouter::new(crate::config::constants::PACKET_SIZE_LIMIT, false).with_tunnel_mtu(1400));
+            server
+        };
+        async fn query(peer: &mut mock::MockPeer) -> PacketType {
+            let hello = PacketType::Hello { version: "1.0.0".to_string() };
+            peer.to_server.send(packet_to_ws_message(&hello).unwrap()).unwrap();
+            let reply = time::timeout(Duration::from_secs(5), peer.from_server.recv()).await.unwrap().unwrap();
+            ws_message_to_packet(&reply).unwrap()
+        }
+
+        // Answered before Auth, after which the handshake proceeds as usual
+        let limited = test_server(2).await;
+        let (server, mut peer) = limited.connect();
+        match query(&mut peer).await {
+            PacketType::Capabilities { protocol_version, features: advertised, max_mtu, .. } => {
+                assert_eq!(protocol_version, crate::config::constants::PROTOCOL_VERSION);
+                assert!(advertised.iter().any(|f| f == features::SIGNED_RENEWAL));
+                // No resumption store is configured here
+                assert!(!advertised.iter().any(|f| f == features::SESSION_RESUMPTION));
+                assert_eq!(max_mtu, 1400);
+            }
+            other => panic!("Expected Capabilities, got {:?}", other),
+        }
+        let auth = PacketType::Auth {
+            public_key: keypair_from_seed(&[4u8; 32]).unwrap().pubkey().to_string(),
+            version: "1.0.0".to_string(),
+            features: vec!["chacha20poly1305".to_string()],
+            encryption_algorithm: None,
+            nonce: "hello-client".to_string(),
+            resumption_token: None,
+            resumption_proof: None,
+            lease_duration: None,
+        };
+        peer.to_server.send(packet_to_ws_message(&auth).unwrap()).unwrap();
+        let reply = time::timeout(Duration::from_secs(5), peer.from_server.recv()).await.unwrap().unwrap();
+        assert!(matches!(ws_message_to_packet(&reply).unwrap(), PacketType::Challenge { .. }));
+        server.abort();
+
+        // Only one query per connection
+        let (server, mut peer) = limited.connect();
+        assert!(matches!(query(&mut peer).await, PacketType::Capabilities { .. }));
+        assert!(matches!(query(&mut peer).await, PacketType::Error { code, .. } if code == ErrorCode::InvalidMessage.code()));
+        assert!(time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap().is_err());
+
+        // Per source IP limit across connections
+        let (server, mut peer) = limited.connect();
+        assert!(matches!(query(&mut peer).await, PacketType::Error { code, .. } if code == ErrorCode::RateLimited.code()));
+        assert!(time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap().is_err());
+
+        // Operators can turn queries off
+        let (server, mut peer) = test_server(0).await.connect();
+        assert!(matches!(query(&mut peer).await, PacketType::Error { code, .. } if code == ErrorCode::InvalidMessage.code()));
+        assert!(time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_session_panic_teardown() {
+        let new_session = |id: &str, conn: &DuplexWebSocketConnection| ClientSession::new(
+            id.to_string(),
+            format!("client-{}", id),
+            "10.7.0.2".to_string(),
+            "127.0.0.1:40000".parse().unwrap(),
+            conn.sender(),
+            conn.receiver(),
+            None,
+        ).unwrap();
+        let (conn_a, _peer_a) = mock::duplex();
+        let (conn_b, mut peer_b) = mock::duplex();
+        let panicking = new_session("a", &conn_a);
+        let healthy = new_session("b", &conn_b);
+
+        // Panic in packet handling while holding one of the session's locks
+        let handler = panicking.clone();
+        let result = supervise_session(&panicking, async move {
+            let _nonces = handler.data_nonces().await;
+            panic!("malformed packet");
+        }, None).await;
+        assert!(matches!(result, Err(ServerError::Internal(msg)) if msg.contains("malformed packet")));
+        assert!(panicking.is_stream_taken().await);
+        // The lock was released while unwinding
+        time::timeout(Duration::from_secs(1), panicking.data_nonces()).await.unwrap();
+
+        // Panics on a pinned worker are contained the same way
+        let pool = Arc::new(PinnedWorkerPool::new(1).unwrap());
+        let result = supervise_session(&panicking, async { panic!("pinned") }, Some(&pool)).await;
+        assert!(matches!(result, Err(ServerError::Internal(msg)) if msg.contains("pinned")));
+
+        // Other sessions keep working
+        let ping = PacketType::Ping { timestamp: 1, sequence: 1 };
+        healthy.send_packet(&ping).await.unwrap();
+        assert!(peer_b.from_server.recv().await.is_some());
+        assert!(!healthy.is_stream_taken().await);
+    }
+
+    #[tokio::test]
+    async fn test_connection_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_manager = Arc::new(KeyManager::new(dir.path().join("server_key"), Duration::from_secs(3600), 100).await.unwrap());
+        let auth_manager = Arc::new(AuthManager::new(
+            dir.path().join("acl.json"),
+            key_manager.clone(),
+            crate::config::constants::AUTH_CHALLENGE_TIMEOUT,
+            100,
+            crate::config::settings::ChallengeAddressBinding::Ip,
+        ).await.unwrap());
+        let ip_pool = Arc::new(IpPoolManager::new("10.7.0.0/24", 86400).await.unwrap());
+        let session_manager = Arc::new(SessionManager::new(5, Duration::from_secs(3600))
+            .with_max_connections(2, Duration::from_millis(200)));
+        let metrics = Arc::new(ServerMetricsCollector::new(Duration::from_secs(60), 60));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+
+        // Accept one connection and serve it until the client goes away
+        let serve_next = || {
+            let (listener, key_manager, auth_manager, ip_pool, session_manager, metrics) =
+                (&listener, key_manager.clone(), auth_manager.clone(), ip_pool.clone(), session_manager.clone(), metrics.clone());
+            async move {
+                let client = TcpStream::connect(server_addr).await.unwrap();
+                let (stream, addr) = listener.accept().await.unwrap();
+                let handler = tokio::spawn(handle_client_raw(
+                    stream,
+                    addr,
+                    key_manager,
+                    auth_manager,
+                    ip_pool,
+                    session_manager,
+                    Arc::new(SessionKeyManager::new(Duration::from_secs(3600), 1_000_000)),
+                    Arc::new(NetworkMonitor::new(Duration::from_secs(5), 120)),
+                    Arc::new(PacketRouter::new(crate::config::constants::PACKET_SIZE_LIMIT, false)),
+                    metrics,
+                    Arc::new(NoopOriginProvider),
+                    Arc::new(WsHandshakePolicy::default()),
+                    None,
+                    Arc::new(QuietHours::default()),
+                    None,
+                    None,
+                    crate::config::defaults::DEFAULT_MAX_HANDSHAKE_DATA_PACKETS,
+                    false,
+                    0.0,
+                    Arc::new(RwLock::new(ServerState::Running)),
+                ));
+                (client, handler)
+            }
+        };
+
+        // Fill both slots with connections that never finish their handshake
+        let (first_client, first) = serve_next().await;
+        let (_second_client, second) = serve_next().await;
+
+        // A third waits out the queue timeout and is refused
+        let (_third_client, third) = serve_next().await;
+        let result = time::timeout(Duration::from_secs(5), third).await.unwrap().unwrap();
+        assert!(matches!(result, Err(ServerError::Session(SessionError::ConnectionLimitReached(2)))), "{:?}", result);
+        assert_eq!(metrics.get_metrics().await.connections_rejected, 1);
+
+        // A queued connection gets the slot released when a handler exits early
+        let (_fourth_client, fourth) = serve_next().await;
+        drop(first_client);
+        assert!(time::timeout(Duration::from_secs(5), first).await.unwrap().unwrap().is_err());
+        time::sleep(Duration::from_millis(400)).await;
+        assert!(!fourth.is_finished());
+        assert_eq!(metrics.get_metrics().await.connections_rejected, 1);
+        assert!(!second.is_finished());
+    }
+    #[tokio::test]
+    async fn test_alpn_negotiation() {
+        use rustls::{Certificate, ClientConfig, RootCertStore, ServerName};
+
+        let testdata = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/server/testdata");
+        let cert_path = testdata.join("tls_server.crt");
+        let acceptor = TlsAcceptor::from(crate::server::core::VpnServer::tls_server_config(
+            &cert_path,
+            &testdata.join("tls_server.key"),
+            &["aeronyx/1".to_string()],
+            None,
+        ).unwrap());
+        let mut roots = RootCertStore::empty();
+        let mut cert_reader = std::io::BufReader::new(std::fs::File::open(&cert_path).unwrap());
+        for der in rustls_pemfile::certs(&mut cert_reader).unwrap() {
+            roots.add(&Certificate(der)).unwrap();
+        }
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+
+        // (client ALPN offer, ALPN required, negotiated protocol if accepted)
+        let cases: [(&[&str], bool, Option<Option<&str>>); 5] = [
+            (&["aeronyx/1"], true, Some(Some("aeronyx/1"))),
+            (&["h2", "aeronyx/1"], true, Some(Some("aeronyx/1"))),
+            (&["h2"], true, None),
+            (&[], true, None),
+            (&[], false, Some(None)),
+        ];
+        for (offered, require_alpn, expected) in cases {
+            let mut client_config = ClientConfig::builder()
+                .with_safe_defaults()
+                .with_root_certificates(roots.clone())
+                .with_no_client_auth();
+            client_config.alpn_protocols = offered.iter().map(|p| p.as_bytes().to_vec()).collect();
+            let connector = tokio_rustls::TlsConnector::from(Arc::new(client_config));
+            let client = tokio::spawn(async move {
+                let tcp = TcpStream::connect(server_addr).await.unwrap();
+                connector.connect(ServerName::try_from("localhost").unwrap(), tcp).await
+            });
+
+            let (stream, _) = listener.accept().await.unwrap();
+            let accepted = accept_tls(&acceptor, stream, require_alpn).await;
+            match expected {
+                Some(protocol) => {
+                    let (_stream, peer) = accepted.unwrap();
+                    assert_eq!(peer.alpn.as_deref(), protocol, "offered {:?}", offered);
+                    client.await.unwrap().unwrap();
+                }
+                None => {
+                    assert!(matches!(accepted, Err((ServerError::Tls(_), HandshakeFailure::TlsAlert))), "offered {:?}", offered);
+                    client.abort();
+                }
+            }
+        }
+    }
+    /// Run the handshake for a client whose TLS certificate proved `certificate_key`,
+    /// sending one Auth for `auth_key` and returning the server's reply and result
+    async fn certificate_handshake(certificate_key: &str, auth_key: &str) -> (PacketType, Result<(), ServerError>) {
+        let test_server = TestServer::new(|auth_manager| auth_manager).await;
+        let (server, mut peer) = test_server.spawn_session(Some(certificate_key.to_string()));
+
+        let auth = PacketType::Auth {
+            public_key: auth_key.to_string(),
+            version: "1.0.0".to_string(),
+            features: vec!["chacha20poly1305".to_string()],
+            encryption_algorithm: None,
+            nonce: "client-certificate".to_string(),
+            resumption_token: None,
+            resumption_proof: None,
+            lease_duration: None,
+        };
+        peer.to_server.send(packet_to_ws_message(&auth).unwrap()).unwrap();
+        let reply = time::timeout(Duration::from_secs(5), peer.from_server.recv()).await
+            .expect("timed out waiting for server reply")
+            .expect("server closed the connection");
+        drop(peer);
+        let result = time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap();
+        (ws_message_to_packet(&reply).unwrap(), result)
+    }
+
+    #[tokio::test]
+    async fn test_auth_timeouts() {
+        use crate::auth::manager::AuthTimeouts;
+
+        // Start the authentication phase for a mock client under `timeouts`
+        let start = |timeouts: AuthTimeouts| async move {
+            let test_server = TestServer::new(|auth_manager| auth_manager.with_auth_timeouts(timeouts)).await;
+            let (server, peer) = test_server.connect();
+            (server, peer, test_server)
+        };
+        let long = Duration::from_secs(30);
+
+        // A client that never sends Auth is dropped after the short wait
+        let started = time::Instant::now();
+        let (server, _peer, _test_server) = start(AuthTimeouts {
+            auth_message: Duration::from_millis(100),
+            challenge_response: long,
+            deadline: long,
+        }).await;
+        let result = time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap();
+        assert!(matches!(&result, Err(ServerError::Authentication(msg)) if msg.contains("authentication message")), "{:?}", result);
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // A client that answers the first step is still bound by the phase deadline
+        let started = time::Instant::now();
+        let (server, mut peer, _test_server) = start(AuthTimeouts {
+            auth_message: long,
+            challenge_response: long,
+            deadline: Duration::from_millis(300),
+        }).await;
+        send_auth(&mut peer, &keypair_from_seed(&[4u8; 32]).unwrap(), &["chacha20poly1305"], None);
+        receive_challenge(&mut peer).await;
+        let result = time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap();
+        assert!(matches!(&result, Err(ServerError::Authentication(msg)) if msg.contains("not completed")), "{:?}", result);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert!(started.elapsed() < long);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_early_data() {
+        use crate::config::defaults::DEFAULT_MAX_HANDSHAKE_DATA_PACKETS;
+
+        let data = packet_to_ws_message(&PacketType::Data {
+            encrypted: vec![1, 2, 3],
+            nonce: vec![0; 12],
+            counter: 0,
+            padding: None,
+            encryption_algorithm: None,
+        }).unwrap();
+        let handshake_incomplete = |packet| match packet {
+            PacketType::Error { code, .. } => Some(code == ErrorCode::HandshakeIncomplete.code()),
+            _ => None,
+        };
+        let server = TestServer::new(|auth_manager| auth_manager).await;
+
+        // Data pipelined after the ChallengeResponse is rejected, not processed after IpAssign
+        let client = keypair_from_seed(&[4u8; 32]).unwrap();
+        let (handler, mut peer) = server.connect();
+        send_auth(&mut peer, &client, &["chacha20poly1305"], None);
+        let (id, challenge, _) = receive_challenge(&mut peer).await;
+        answer_challenge(&mut peer, &client, id, &challenge);
+        peer.to_server.send(data.clone()).unwrap();
+        let reply = time::timeout(Duration::from_secs(5), peer.from_server.recv()).await.unwrap().unwrap();
+        assert_eq!(handshake_incomplete(ws_message_to_packet(&reply).unwrap()), Some(true));
+        let reply = time::timeout(Duration::from_secs(5), peer.from_server.recv()).await.unwrap().unwrap();
+        assert!(matches!(ws_message_to_packet(&reply).unwrap(), PacketType::IpAssign { .. }));
+        drop(peer);
+        assert!(time::timeout(Duration::from_secs(5), handler).await.unwrap().unwrap().is_ok());
+
+        // Data during the challenge is tolerated up to the limit, which pipelined Data also counts against
+        let client = keypair_from_seed(&[5u8; 32]).unwrap();
+        let (handler, mut peer) = server.connect();
+        send_auth(&mut peer, &client, &["chacha20poly1305"], None);
+        let (id, challenge, _) = receive_challenge(&mut peer).await;
+        for _ in 0..DEFAULT_MAX_HANDSHAKE_DATA_PACKETS {
+            peer.to_server.send(data.clone()).unwrap();
+            assert!(expect_packet(&mut peer, handshake_incomplete).await);
+        }
+        assert!(!handler.is_finished());
+        answer_challenge(&mut peer, &client, id, &challenge);
+        peer.to_server.send(data.clone()).unwrap();
+        assert!(expect_packet(&mut peer, handshake_incomplete).await);
+        let result = time::timeout(Duration::from_secs(5), handler).await.unwrap().unwrap();
+        assert!(matches!(&result, Err(ServerError::Authentication(msg)) if msg.contains("Handshake incomplete")), "{:?}", result);
+        while let Ok(msg) = peer.from_server.try_recv() {
+            assert!(!matches!(ws_message_to_packet(&msg), Ok(PacketType::IpAssign { .. })));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_auth_audit_events() {
+        use crate::auth::acl::AccessControlManager;
+        use crate::auth::audit::ChannelAuditSink;
+        use crate::auth::manager::AuthTimeouts;
+
+        let (tx, mut events) = tokio::sync::mpsc::unbounded_channel();
+        let test_server = TestServer::new(|auth_manager| auth_manager
+            .with_auth_timeouts(AuthTimeouts {
+                auth_message: Duration::from_millis(200),
+                challenge_response: Duration::from_secs(30),
+                deadline: Duration::from_secs(30),
+            })
+            .with_audit_sink(Arc::new(ChannelAuditSink::new(tx)))).await;
+
+        // Send Auth as `client` and answer the challenge with `signer`
+        async fn sign_in(peer: &mut mock::MockPeer, client: &solana_sdk::signature::Keypair, signer: &solana_sdk::signature::Keypair) {
+            send_auth(peer, client, &["chacha20poly1305"], None);
+            let (id, data, _) = receive_challenge(peer).await;
+            let response = PacketType::ChallengeResponse {
+                signature: signer.sign_message(&data).to_string(),
+                public_key: client.pubkey().to_string(),
+                challenge_id: id,
+                pow_nonce: None,
+            };
+            peer.to_server.send(packet_to_ws_message(&response).unwrap()).unwrap();
+        }
+
+        // Exactly one event per attempt
+        let mut next_event = || {
+            let event = events.try_recv().expect("no audit event for the attempt");
+            assert!(events.try_recv().is_err(), "more than one audit event for the attempt");
+            event
+        };
+
+        // A client that never sends Auth has claimed no key
+        let (server, _peer) = test_server.connect();
+        assert!(server.await.unwrap().is_err());
+        let event = next_event();
+        assert_eq!(event.source_ip.to_string(), "127.0.0.1");
+        assert_eq!(event.public_key, None);
+        assert_eq!(event.decision, AuthDecision::Failure);
+        assert!(event.reason.contains("Timed out"), "{}", event.reason);
+
+        // A signature from the wrong key
+        let claimed = keypair_from_seed(&[5u8; 32]).unwrap();
+        let (server, mut peer) = test_server.connect();
+        sign_in(&mut peer, &claimed, &keypair_from_seed(&[6u8; 32]).unwrap()).await;
+        assert!(server.await.unwrap().is_err());
+        let event = next_event();
+        assert_eq!(event.public_key, Some(claimed.pubkey().to_string()));
+        assert_eq!(event.decision, AuthDecision::Failure);
+        assert!(event.reason.contains("Challenge verification failed"), "{}", event.reason);
+
+        // A proven key the ACL refuses
+        let denied = keypair_from_seed(&[8u8; 32]).unwrap();
+        test_server.auth_manager.add_client(AccessControlManager::create_deny_entry(&denied.pubkey().to_string(), None)).await.unwrap();
+        let (server, mut peer) = test_server.connect();
+        sign_in(&mut peer, &denied, &denied).await;
+        assert!(server.await.unwrap().is_err());
+        let event = next_event();
+        assert_eq!(event.public_key, Some(denied.pubkey().to_string()));
+        assert_eq!(event.decision, AuthDecision::DeniedByAcl);
+
+        // A successful client
+        let client = keypair_from_seed(&[9u8; 32]).unwrap();
+        let (server, mut peer) = test_server.connect();
+        sign_in(&mut peer, &client, &client).await;
+        let reply = time::timeout(Duration::from_secs(5), peer.from_server.recv()).await.unwrap().unwrap();
+        assert!(matches!(ws_message_to_packet(&reply).unwrap(), PacketType::IpAssign { .. }));
+        drop(peer);
+        assert!(time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap().is_ok());
+        let event = next_event();
+        assert_eq!(event.public_key, Some(client.pubkey().to_string()));
+        assert_eq!(event.decision, AuthDecision::Success);
+        assert_eq!(event.reason, "Challenge verified");
+    }
+
+    #[tokio::test]
+    async fn test_strict_acl_deny_response() {
+        use crate::auth::acl::AccessControlManager;
+        use crate::config::settings::AclDenyResponse;
+
+        // Error returned once the challenge is answered with the key from `signer_seed`
+        async fn denial(deny_response: AclDenyResponse, deny_listed: bool, signer_seed: u8) -> (u16, String) {
+            let test_server = TestServer::new(|auth_manager| auth_manager.with_acl_deny_response(deny_response)).await;
+            let client = keypair_from_seed(&[8u8; 32]).unwrap();
+            if deny_listed {
+                test_server.auth_manager.add_client(AccessControlManager::create_deny_entry(&client.pubkey().to_string(), None)).await.unwrap();
+            }
+
+            let (server, mut peer) = test_server.connect();
+            send_auth(&mut peer, &client, &["chacha20poly1305"], None);
+            let (challenge_id, data, _) = receive_challenge(&mut peer).await;
+            let signer = keypair_from_seed(&[signer_seed; 32]).unwrap();
+            let response = PacketType::ChallengeResponse {
+                signature: signer.sign_message(&data).to_string(),
+                public_key: client.pubkey().to_string(),
+                challenge_id,
+                pow_nonce: None,
+            };
+            peer.to_server.send(packet_to_ws_message(&response).unwrap()).unwrap();
+
+            let reply = time::timeout(Duration::from_secs(5), peer.from_server.recv()).await.unwrap().unwrap();
+            assert!(server.await.unwrap().is_err());
+            match ws_message_to_packet(&reply).unwrap() {
+                PacketType::Error { code, message, .. } => (code, message),
+                other => panic!("expected an error, got {:?}", other),
+            }
+        }
+
+        // Verbose: a deny-listed key learns it passed the signature check
+        let (code, message) = denial(AclDenyResponse::Verbose, true, 8).await;
+        assert_eq!(code, ErrorCode::Unauthorized.code());
+        assert_eq!(message, "Access denied by ACL");
+
+        // Strict: the ACL denial is indistinguishable from a bad signature
+        let denied = denial(AclDenyResponse::Strict, true, 8).await;
+        let bad_signature = denial(AclDenyResponse::Strict, false, 9).await;
+        assert_eq!(denied, (ErrorCode::AuthenticationFailed.code(), "Authentication failed".to_string()));
+        assert_eq!(denied, bad_signature);
+    }
+
+    #[tokio::test]
+    async fn test_client_certificate_auth() {
+        use rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerName};
+        use crate::server::core::{ClientCertAuth, VpnServer};
+
+        let testdata = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/server/testdata");
+        let read_certs = |name: &str| -> Vec<Certificate> {
+            let mut reader = std::io::BufReader::new(std::fs::File::open(testdata.join(name)).unwrap());
+            rustls_pemfile::certs(&mut reader).unwrap().into_iter().map(Certificate).collect()
+        };
+        let client_cert_path = testdata.join("tls_client.crt");
+        let client_private_key = {
+            let mut reader = std::io::BufReader::new(std::fs::File::open(testdata.join("tls_client.key")).unwrap());
+            PrivateKey(rustls_pemfile::pkcs8_private_keys(&mut reader).unwrap().remove(0))
+        };
+        // The self-signed client certificate holds the Ed25519 key with this seed
+        let client_key = keypair_from_seed(&[8u8; 32]).unwrap().pubkey().to_string();
+        let mut roots = RootCertStore::empty();
+        for cert in read_certs("tls_server.crt") {
+            roots.add(&cert).unwrap();
+        }
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+
+        // (certificate required, client presents one, handshake accepted)
+        for (required, present, accepted) in [(false, true, true), (false, false, true), (true, true, true), (true, false, false)] {
+            let acceptor = TlsAcceptor::from(VpnServer::tls_server_config(
+                &testdata.join("tls_server.crt"),
+                &testdata.join("tls_server.key"),
+                &[],
+                Some(ClientCertAuth { ca_file: &client_cert_path, required }),
+            ).unwrap());
+            let builder = ClientConfig::builder().with_safe_defaults().with_root_certificates(roots.clone());
+            let client_config = if present {
+                builder.with_single_cert(read_certs("tls_client.crt"), client_private_key.clone()).unwrap()
+            } else {
+                builder.with_no_client_auth()
+            };
+            let connector = tokio_rustls::TlsConnector::from(Arc::new(client_config));
+            let client = tokio::spawn(async move {
+                let tcp = TcpStream::connect(server_addr).await.unwrap();
+                connector.connect(ServerName::try_from("localhost").unwrap(), tcp).await
+            });
+
+            let (stream, _) = listener.accept().await.unwrap();
+            let result = accept_tls(&acceptor, stream, false).await;
+            if accepted {
+                let (_stream, peer) = result.unwrap();
+                assert_eq!(peer.certificate_key, present.then(|| client_key.clone()), "required {}, present {}", required, present);
+                client.await.unwrap().unwrap();
+            } else {
+                assert!(matches!(result, Err((ServerError::Tls(_), HandshakeFailure::TlsAlert))));
+                client.abort();
+            }
+        }
+
+        // The certificate key authenticates Auth directly: IpAssign follows without a Challenge
+        let (reply, result) = certificate_handshake(&client_key, &client_key).await;
+        match reply {
+            PacketType::IpAssign { ip_address, encryption_algorithm, .. } => {
+                assert!(ip_address.starts_with("10.7.0."));
+                assert_eq!(encryption_algorithm, "chacha20poly1305");
+            }
+            other => panic!("Unexpected packet: {:?}", other),
+        }
+        assert!(result.is_ok(), "{:?}", result);
+
+        // Auth for any other key is refused
+        let other_key = keypair_from_seed(&[7u8; 32]).unwrap().pubkey().to_string();
+        let (reply, result) = certificate_handshake(&client_key, &other_key).await;
+        assert!(matches!(reply, PacketType::Error { code, .. } if code == ErrorCode::AuthenticationFailed.code()), "{:?}", reply);
+        assert!(matches!(result, Err(ServerError::Authentication(_))));
+    }
+
+    #[tokio::test]
+    async fn test_connection_origin_tagging() {
+        /// Maps loopback to a documentation ASN and knows nothing else
+        #[derive(Debug)]
+        struct LoopbackOrigin;
+
+        impl OriginProvider for LoopbackOrigin {
+            fn lookup(&self, addr: SocketAddr) -> Option<ConnectionOrigin> {
+                addr.ip().is_loopback().then(|| ConnectionOrigin { country: Some("ZZ".to_string()), asn: Some(64512) })
+            }
+        }
+
+        let mut test_server = TestServer::new(|auth_manager| auth_manager).await;
+        test_server.origin_provider = Arc::new(LoopbackOrigin);
+        let metrics = test_server.metrics.clone();
+        let client_key = keypair_from_seed(&[9u8; 32]).unwrap().pubkey().to_string();
+
+        let (server, mut peer) = test_server.spawn_session(Some(client_key.clone()));
+        let auth = PacketType::Auth {
+            public_key: client_key.clone(),
+            version: "1.0.0".to_string(),
+            features: vec!["chacha20poly1305".to_string()],
+            encryption_algorithm: None,
+            nonce: "origin-tagging".to_string(),
+            resumption_token: None,
+            resumption_proof: None,
+            lease_duration: None,
+        };
+        peer.to_server.send(packet_to_ws_message(&auth).unwrap()).unwrap();
+        let reply = time::timeout(Duration::from_secs(5), peer.from_server.recv()).await.unwrap().unwrap();
+        assert!(matches!(ws_message_to_packet(&reply).unwrap(), PacketType::IpAssign { .. }));
+        test_server.session(&client_key).await;
+
+        // The admin snapshot and the metrics carry the tag
+        let expected = ConnectionOrigin { country: Some("ZZ".to_string()), asn: Some(64512) };
+        let stats = test_server.session_manager.session_stats(&test_server.session_key_manager, &test_server.ip_pool).await;
+        assert_eq!(stats[0].origin.as_ref(), Some(&expected));
+        let key = ("ZZ".to_string(), "64512".to_string());
+        assert_eq!(metrics.get_metrics().await.sessions_by_origin.get(&key), Some(&1));
+        assert!(metrics.prometheus_text().await.contains("aeronyx_sessions_by_origin{country=\"ZZ\",asn=\"64512\"} 1"));
+
+        // The gauge is released with the session
+        drop(peer);
+        let _ = time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap();
+        assert_eq!(metrics.get_metrics().await.sessions_by_origin.get(&key), None);
+
+        // The default provider tags nothing
+        assert_eq!(NoopOriginProvider.lookup("127.0.0.1:40000".parse().unwrap()), None);
+    }
+
+    /// Server state shared by the connections of a test
+    struct TestServer {
+        dir: tempfile::TempDir,
+        key_manager: Arc<KeyManager>,
+        auth_manager: Arc<AuthManager>,
+        ip_pool: Arc<IpPoolManager>,
+        session_manager: Arc<SessionManager>,
+        session_key_manager: Arc<SessionKeyManager>,
+        packet_router: Arc<PacketRouter>,
+        metrics: Arc<ServerMetricsCollector>,
+        origin_provider: Arc<dyn OriginProvider>,
+    }
+
+    impl TestServer {
+        async fn new(configure: impl FnOnce(AuthManager) -> AuthManager) -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let key_manager = Arc::new(KeyManager::new(dir.path().join("server_key"), Duration::from_secs(3600), 100).await.unwrap());
+            let auth_manager = Arc::new(configure(AuthManager::new(
+                dir.path().join("acl.json"),
+                key_manager.clone(),
+                crate::config::constants::AUTH_CHALLENGE_TIMEOUT,
+                100,
+                crate::config::settings::ChallengeAddressBinding::Ip,
+            ).await.unwrap()));
+            Self {
+                dir,
+                key_manager,
+                auth_manager,
+                ip_pool: Arc::new(IpPoolManager::new("10.7.0.0/24", 86400).await.unwrap()),
+                session_manager: Arc::new(SessionManager::new(5, Duration::from_secs(3600))),
+                session_key_manager: Arc::new(SessionKeyManager::new(Duration::from_secs(3600), 1_000_000)),
+                packet_router: Arc::new(PacketRouter::new(crate::config::constants::PACKET_SIZE_LIMIT, false)),
+                metrics: Arc::new(ServerMetricsCollector::new(Duration::from_secs(60), 60)),
+                origin_provider: Arc::new(NoopOriginProvider),
+            }
+        }
+
+        /// Accept a connection over a mock duplex
+        fn connect(&self) -> (tokio::task::JoinHandle<Result<(), ServerError>>, mock::MockPeer) {
+            self.spawn_session(None)
+        }
+
+        /// Accept a connection over a mock duplex whose TLS client
+        /// certificate, if any, proved `certificate_key`
+        fn spawn_session(&self, certificate_key: Option<String>) -> (tokio::task::JoinHandle<Result<(), ServerError>>, mock::MockPeer) {
+            let (conn, peer) = mock::duplex();
+            let server = tokio::spawn(process_websocket_session_with_connection(
+                conn,
+                "127.0.0.1:40000".parse().unwrap(),
+                certificate_key,
+                self.key_manager.clone(),
+                self.auth_manager.clone(),
+                self.ip_pool.clone(),
+                self.session_manager.clone(),
+                self.session_key_manager.clone(),
+                Arc::new(NetworkMonitor::new(Duration::from_secs(5), 120)),
+                self.packet_router.clone(),
+                self.metrics.clone(),
+                self.origin_provider.clone(),
+                None,
+                Arc::new(QuietHours::default()),
+                None,
+                None,
+                crate::config::defaults::DEFAULT_MAX_HANDSHAKE_DATA_PACKETS,
+                false,
+                0.0,
+                Arc::new(RwLock::new(ServerState::Running)),
+            ));
+            (server, peer)
+        }
+
+        /// The registered session of `client_id`, once the handshake has added it
+        async fn session(&self, client_id: &str) -> ClientSession {
+            time::timeout(Duration::from_secs(5), async {
+                loop {
+                    if let Some(session) = self.session_manager.get_session_by_client_id(client_id).await {
+                        return session;
+                    }
+                    time::sleep(Duration::from_millis(10)).await;
+                }
+            }).await.unwrap()
+        }
+    }
+
+    /// Receive server packets until `select` picks one, skipping the rest
+    async fn expect_packet<T>(peer: &mut mock::MockPeer, mut select: impl FnMut(PacketType) -> Option<T>) -> T {
+        loop {
+            let msg = time::timeout(Duration::from_secs(5), peer.from_server.recv()).await
+                .expect("timed out waiting for server packet")
+                .expect("server closed the connection");
+            if let Some(selected) = select(ws_message_to_packet(&msg).unwrap()) {
+                return selected;
+            }
+        }
+    }
+
+    /// Send an Auth as `client`, with a resumption token and proof if given
+    fn send_auth(
+        peer: &mut mock::MockPeer,
+        client: &solana_sdk::signature::Keypair,
+        features: &[&str],
+        resumption: Option<(String, Vec<u8>)>,
+    ) {
+        let (resumption_token, resumption_proof) = resumption.unzip();
+        let auth = PacketType::Auth {
+            public_key: client.pubkey().to_string(),
+            version: "1.0.0".to_string(),
+            features: features.iter().map(|f| f.to_string()).collect(),
+            encryption_algorithm: None,
+            nonce: "test-client".to_string(),
+            resumption_token,
+            resumption_proof,
+            lease_duration: None,
+        };
+        peer.to_server.send(packet_to_ws_message(&auth).unwrap()).unwrap();
+    }
+
+    /// Receive the server's challenge, returning its ID, data and server key
+    async fn receive_challenge(peer: &mut mock::MockPeer) -> (String, Vec<u8>, Pubkey) {
+        expect_packet(peer, |packet| match packet {
+            PacketType::Challenge { id, data, server_key, .. } => Some((id, data, Pubkey::from_str(&server_key).unwrap())),
+            _ => None,
+        }).await
+    }
+
+    /// Sign the challenge `data` as `client`
+    fn answer_challenge(peer: &mut mock::MockPeer, client: &solana_sdk::signature::Keypair, id: String, data: &[u8]) {
+        let response = PacketType::ChallengeResponse {
+            signature: client.sign_message(data).to_string(),
+            public_key: client.pubkey().to_string(),
+            challenge_id: id,
+            pow_nonce: None,
+        };
+        peer.to_server.send(packet_to_ws_message(&response).unwrap()).unwrap();
+    }
+
+    /// Receive the IpAssign and decrypt its session key with the secret
+    /// shared with `server_key`, returning the session ID and key
+    async fn receive_session_key(
+        peer: &mut mock::MockPeer,
+        client: &solana_sdk::signature::Keypair,
+        server_key: &Pubkey,
+    ) -> (String, Vec<u8>) {
+        use crate::crypto::flexible_encryption::EncryptedPacket;
+        use crate::crypto::encryption::decrypt_session_key_flexible;
+        use crate::crypto::keys::generate_shared_secret;
+
+        let (session_id, key_packet) = expect_packet(peer, |packet| match packet {
+            PacketType::IpAssign { session_id, encrypted_session_key, key_nonce, encryption_algorithm, .. } => Some((
+                session_id,
+                EncryptedPacket {
+                    data: encrypted_session_key,
+                    nonce: key_nonce,
+                    algorithm: EncryptionAlgorithm::from_str(&encryption_algorithm).unwrap(),
+                },
+            )),
+            _ => None,
+        }).await;
+        let shared_secret = generate_shared_secret(client, server_key).unwrap();
+        (session_id, decrypt_session_key_flexible(&key_packet, &shared_secret, None).unwrap())
+    }
+
+    /// Authenticate as `client`, through the challenge or with a resumption
+    /// token and proof, returning the session ID and decrypted session key
+    async fn authenticate(
+        server: &TestServer,
+        peer: &mut mock::MockPeer,
+        client: &solana_sdk::signature::Keypair,
+        features: &[&str],
+        resumption: Option<(String, Vec<u8>)>,
+    ) -> (String, Vec<u8>) {
+        let resuming = resumption.is_some();
+        send_auth(peer, client, features, resumption);
+        let server_key = if resuming {
+            server.key_manager.public_key().await
+        } else {
+            let (id, data, server_key) = receive_challenge(peer).await;
+            answer_challenge(peer, client, id, &data);
+            server_key
+        };
+        receive_session_key(peer, client, &server_key).await
+    }
+
+    #[tokio::test]
+    async fn test_resumed_session_continues_counters() {
+        use crate::crypto::encryption::sign_session_resumption;
+
+        let server = TestServer::new(|auth_manager| auth_manager.with_session_resumption(Duration::from_secs(60))).await;
+        let client = keypair_from_seed(&[5u8; 32]).unwrap();
+        let client_id = client.pubkey().to_string();
+        let client_features = ["chacha20poly1305", features::SESSION_RESUMPTION];
+
+        let (first_server, mut peer) = server.connect();
+        let (_, session_key) = authenticate(&server, &mut peer, &client, &client_features, None).await;
+        let token = expect_packet(&mut peer, |packet| match packet {
+            PacketType::ResumptionToken { token, .. } => Some(token),
+            _ => None,
+        }).await;
+
+        // Outbound Data and an accepted inbound counter before the disconnect
+        let session = server.session(&client_id).await;
+        let mut sent = Vec::new();
+        for _ in 0..3 {
+            server.packet_router.route_outbound_packet(&[0x45u8; 40], &session_key, &session, &server.session_key_manager).await.unwrap();
+            sent.push(expect_packet(&mut peer, |packet| match packet {
+                PacketType::Data { nonce, counter, .. } => Some((counter, nonce)),
+                _ => None,
+            }).await);
+        }
+        session.data_nonces().await.accept_received(5);
+        drop(session);
+        drop(peer);
+        time::timeout(Duration::from_secs(5), first_server).await.unwrap().unwrap().unwrap();
+
+        // Resuming keeps the key, and with it where its counters left off
+        let (second_server, mut peer) = server.connect();
+        let proof = sign_session_resumption(&session_key, &token, &client_id).unwrap();
+        let (_, resumed_key) = authenticate(&server, &mut peer, &client, &client_features, Some((token, proof))).await;
+        assert_eq!(resumed_key, session_key);
+
+        let session = server.session(&client_id).await;
+        server.packet_router.route_outbound_packet(&[0x45u8; 40], &session_key, &session, &server.session_key_manager).await.unwrap();
+        let (counter, nonce) = expect_packet(&mut peer, |packet| match packet {
+            PacketType::Data { nonce, counter, .. } => Some((counter, nonce)),
+            _ => None,
+        }).await;
+        assert_eq!(counter, 3);
+        assert!(!sent.iter().any(|(_, earlier)| *earlier == nonce), "nonce repeated after resumption");
+        assert!(session.data_nonces().await.check_received(5).is_err());
+
+        drop(session);
+        drop(peer);
+        let _ = time::timeout(Duration::from_secs(5), second_server).await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handshake_across_server_key_rotation() {
+        let server = TestServer::new(|auth_manager| auth_manager).await;
+        let client = keypair_from_seed(&[6u8; 32]).unwrap();
+        let late_client = keypair_from_seed(&[7u8; 32]).unwrap();
+        let old_key = server.key_manager.public_key().await;
+
+        // A challenge goes out under the old key, then an operator writes a new key file
+        let (in_flight_server, mut in_flight) = server.connect();
+        send_auth(&mut in_flight, &client, &["chacha20poly1305"], None);
+        let (id, data, served_key) = receive_challenge(&mut in_flight).await;
+        assert_eq!(served_key, old_key);
+        let replacement = solana_sdk::signature::Keypair::new();
+        let new_key_path = server.dir.path().join("new_server_key");
+        std::fs::write(&new_key_path, replacement.to_bytes()).unwrap();
+        assert_eq!(server.key_manager.load_new_keypair(&new_key_path).await.unwrap(), replacement.pubkey());
+
+        // The in-flight handshake completes with the key it was sent
+        answer_challenge(&mut in_flight, &client, id, &data);
+        let (_, session_key) = receive_session_key(&mut in_flight, &client, &old_key).await;
+        assert_eq!(server.session_key_manager.get_key(&client.pubkey().to_string()).await, Some(session_key));
+
+        // New handshakes are served the new key
+        let (late_server, mut late) = server.connect();
+        send_auth(&mut late, &late_client, &["chacha20poly1305"], None);
+        let (id, data, served_key) = receive_challenge(&mut late).await;
+        assert_eq!(served_key, replacement.pubkey());
+        answer_challenge(&mut late, &late_client, id, &data);
+        let (_, session_key) = receive_session_key(&mut late, &late_client, &served_key).await;
+        assert_eq!(server.session_key_manager.get_key(&late_client.pubkey().to_string()).await, Some(session_key));
+
+        for (peer, task) in [(in_flight, in_flight_server), (late, late_server)] {
+            drop(peer);
+            assert!(time::timeout(Duration::from_secs(5), task).await.unwrap().unwrap().is_ok());
+        }
+    }
+}
+
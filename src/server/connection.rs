@@ -167,3 +167,75 @@ impl DuplexWebSocketConnection {
         sender.close().await
     }
 }
+
+/// In-memory connection for exercising sessions in tests
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+    /// Server-side sending half backed by a channel
+    struct MockSender {
+        tx: Option<UnboundedSender<Message>>,
+    }
+
+    /// Server-side receiving half backed by a channel
+    struct MockReceiver {
+        rx: UnboundedReceiver<Message>,
+    }
+
+    #[async_trait]
+    impl WebSocketConnection for MockSender {
+        async fn send_message(&mut self, msg: Message) -> Result<(), ServerError> {
+            match &self.tx {
+                Some(tx) => tx.send(msg).map_err(|_| ServerError::Network("Mock peer closed".to_string())),
+                None => Err(ServerError::Network("Mock connection closed".to_string())),
+            }
+        }
+
+        async fn next_message(&mut self) -> Option<Result<Message, ServerError>> {
+            None
+        }
+
+        async fn close(&mut self) -> Result<(), ServerError> {
+            self.tx = None;
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl WebSocketConnection for MockReceiver {
+        async fn send_message(&mut self, _msg: Message) -> Result<(), ServerError> {
+            Err(ServerError::Internal("Cannot send on receiver".to_string()))
+        }
+
+        async fn next_message(&mut self) -> Option<Result<Message, ServerError>> {
+            self.rx.recv().await.map(Ok)
+        }
+
+        async fn close(&mut self) -> Result<(), ServerError> {
+            Ok(())
+        }
+    }
+
+    /// Client end of a mock connection
+    pub struct MockPeer {
+        /// Messages sent by the server
+        pub from_server: UnboundedReceiver<Message>,
+        /// Messages to deliver to the server
+        pub to_server: UnboundedSender<Message>,
+    }
+
+    /// Create a connected mock duplex connection and its client end
+    pub fn duplex() -> (DuplexWebSocketConnection, MockPeer) {
+        let (server_tx, from_server) = unbounded_channel();
+        let (to_server, server_rx) = unbounded_channel();
+
+        let conn = DuplexWebSocketConnection {
+            sender: Arc::new(Mutex::new(Box::new(MockSender { tx: Some(server_tx) }))),
+            receiver: Arc::new(Mutex::new(Box::new(MockReceiver { rx: server_rx }))),
+        };
+
+        (conn, MockPeer { from_server, to_server })
+    }
+}
@@ -5,30 +5,49 @@
 //! client connections, authentication, and network routing.
 
 use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
+use socket2::{Domain, Protocol, Socket, Type};
 use tokio::net::TcpListener;
-use tokio::sync::{Mutex, RwLock};
-use tokio::task::JoinHandle;
+use tokio::sync::{Mutex, Notify, RwLock};
+use tokio::task::{AbortHandle, JoinHandle};
 use tokio::time;
 use tokio_rustls::TlsAcceptor;
 use tracing::{debug, error, info, trace, warn};
 use tun::platform::Device;
 // Import rustls types explicitly
-use rustls::{Certificate, PrivateKey, ServerConfig as RustlsServerConfig}; 
+use rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig as RustlsServerConfig};
+use rustls::server::{AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient};
 
 use crate::auth::AuthManager;
+use crate::auth::audit::{AuditSink, JsonLinesAuditSink};
 use crate::auth::challenge::ChallengeError;
-use crate::config::settings::{ServerConfig, TransportSecurity};
+use crate::config::settings::{AclTierPolicy, ServerConfig, TransportSecurity};
 use crate::crypto::{KeyManager, SessionKeyManager};
+use crate::crypto::keys::SecretRetryPolicy;
 use crate::network::{IpPoolManager, NetworkMonitor, setup_tun_device, configure_nat, get_first_ip_from_subnet};
+use crate::network::ip_pool::parse_reservations;
+use crate::network::lease_store::FileLeaseStore;
 use crate::network::tun::TunConfig;
+use crate::network::tun_queue::TunWriteQueue;
 use crate::protocol::MessageError;
 use crate::server::session::{SessionManager, SessionError};
-use crate::server::routing::PacketRouter;
+use crate::server::routing::{PacketRouter, TrafficLimits};
 use crate::server::metrics::ServerMetricsCollector;
 use crate::server::client::{handle_client, handle_client_raw};
 use crate::server::packet::start_tun_packet_processor;
+use crate::server::handshake::WsHandshakePolicy;
+use crate::server::heartbeat::HeartbeatPolicy;
+use crate::server::workers::PinnedWorkerPool;
+use crate::server::schedule::QuietHours;
+use crate::server::warmup::Warmup;
+use crate::server::records::SessionRecordWriter;
+use crate::network::flows::{FlowExporter, FlowTracker, FlowTrackerConfig};
+use crate::network::traffic_export::{TrafficExporter, UdpLineSink};
+use crate::network::origin::{NoopOriginProvider, OriginProvider};
+use crate::utils::logging::ThrottledLogger;
 use crate::utils::security::RateLimiter;
 use crate::registration::RegistrationManager;
 
@@ -70,6 +89,12 @@ pub enum ServerError {
     #[error("TUN setup error: {0}")]
     TunSetup(String),
 
+    #[error("Connection dead: {0} consecutive heartbeats unanswered")]
+    ConnectionDead(usize),
+
+    #[error("Connection closed by client with code {0}: {1}")]
+    ClosedByClient(u16, String),
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
@@ -89,6 +114,28 @@ pub enum ServerState {
     Stopped,
 }
 
+impl ServerState {
+    /// Lowercase name, as reported by the health endpoints
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ServerState::Created => "created",
+            ServerState::Starting => "starting",
+            ServerState::Running => "running",
+            ServerState::ShuttingDown => "shutting_down",
+            ServerState::Stopped => "stopped",
+        }
+    }
+}
+
+/// Client certificate verification on the TLS acceptor
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ClientCertAuth<'a> {
+    /// PEM bundle of the CAs trusted to issue client certificates
+    pub ca_file: &'a Path,
+    /// Refuse clients without a certificate instead of falling back to the challenge
+    pub required: bool,
+}
+
 /// Main VPN server for the AeroNyx Privacy Network
 pub struct VpnServer {
     /// Server configuration
@@ -111,16 +158,273 @@ pub struct VpnServer {
     pub network_monitor: Arc<NetworkMonitor>,
     /// Packet router for network traffic
     pub packet_router: Arc<PacketRouter>,
+    /// Bounded queue of inner packets waiting for the TUN device
+    pub tun_queue: Arc<TunWriteQueue>,
     /// Server metrics collector
     pub metrics: Arc<ServerMetricsCollector>,
+    /// Source network lookup for new connections; replace before `start` to tag sessions
+    pub origin_provider: Arc<dyn OriginProvider>,
     /// Rate limiter for connections
     pub rate_limiter: Arc<RateLimiter>,
+    /// Aggregated logging for connection-level failures
+    pub failure_logs: Arc<ConnectionFailureLogs>,
+    /// Origin/subprotocol requirements for WebSocket upgrades
+    pub handshake_policy: Arc<WsHandshakePolicy>,
+    /// Tunnel flow accounting, present only when flow export is enabled
+    pub flow_tracker: Option<Arc<FlowTracker>>,
+    /// Dedicated workers for client-pinned session processing, if enabled
+    pub worker_pool: Option<Arc<PinnedWorkerPool>>,
+    /// Recurring maintenance windows during which new connections are rejected
+    pub quiet_hours: Arc<QuietHours>,
+    /// Startup window during which the server is not ready and admits conservatively
+    pub warmup: Arc<Warmup>,
+    /// Sink for per-session lifecycle records, if enabled
+    pub session_records: Option<Arc<SessionRecordWriter>>,
+    /// Reload requests from the admin API, awaited by the server's owner via `reload_requested`
+    pub reload_requests: Arc<Notify>,
     /// Server state
     pub state: Arc<RwLock<ServerState>>,
     /// Server task handles (background tasks ONLY)
     pub task_handles: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    /// Accept loops, one per listener, aborted on shutdown
+    listener_handles: Mutex<Vec<AbortHandle>>,
+    /// Addresses the listeners are bound to, once started
+    local_addrs: Mutex<Vec<SocketAddr>>,
     /// Registration manager
     pub registration_manager: Option<Arc<RegistrationManager>>,
+    /// Chaos (fault injection) controller, present only when chaos mode is enabled
+    #[cfg(feature = "chaos")]
+    pub chaos: Option<Arc<crate::server::chaos::ChaosController>>,
+}
+
+/// Aggregating loggers for high-frequency connection failures
+pub struct ConnectionFailureLogs {
+    /// Connections rejected by the per-IP rate limiter
+    pub rate_limited: ThrottledLogger,
+    /// Connections refused during startup warmup
+    pub warmup: ThrottledLogger,
+    /// Connections refused because the session cap was reached
+    pub at_capacity: ThrottledLogger,
+    /// Connections refused because the global connection limit was reached
+    pub connection_limit: ThrottledLogger,
+    /// Failed TLS handshakes
+    pub tls: ThrottledLogger,
+    /// Failed authentications
+    pub auth: ThrottledLogger,
+}
+
+impl ConnectionFailureLogs {
+    /// Create loggers aggregating over `window`
+    pub fn new(window: Duration) -> Self {
+        Self {
+            rate_limited: ThrottledLogger::new("rate-limited connections", "IPs", window),
+            warmup: ThrottledLogger::new("connections refused during warmup", "IPs", window),
+            at_capacity: ThrottledLogger::new("connections refused at session capacity", "IPs", window),
+            connection_limit: ThrottledLogger::new("connections refused at the connection limit", "IPs", window),
+            tls: ThrottledLogger::new("TLS handshake failures", "IPs", window),
+            auth: ThrottledLogger::new("auth failures", "IPs", window),
+        }
+    }
+
+    /// Emit any pending summaries
+    pub fn flush(&self) {
+        self.rate_limited.flush();
+        self.warmup.flush();
+        self.at_capacity.flush();
+        self.connection_limit.flush();
+        self.tls.flush();
+        self.auth.flush();
+    }
+}
+
+/// Shared state handed to every listener's accept loop
+struct AcceptContext {
+    tls_acceptor: Option<Arc<TlsAcceptor>>,
+    require_alpn: bool,
+    key_manager: Arc<KeyManager>,
+    auth_manager: Arc<AuthManager>,
+    ip_pool: Arc<IpPoolManager>,
+    session_manager: Arc<SessionManager>,
+    session_key_manager: Arc<SessionKeyManager>,
+    network_monitor: Arc<NetworkMonitor>,
+    packet_router: Arc<PacketRouter>,
+    metrics: Arc<ServerMetricsCollector>,
+    origin_provider: Arc<dyn OriginProvider>,
+    rate_limiter: Arc<RateLimiter>,
+    failure_logs: Arc<ConnectionFailureLogs>,
+    handshake_policy: Arc<WsHandshakePolicy>,
+    worker_pool: Option<Arc<PinnedWorkerPool>>,
+    quiet_hours: Arc<QuietHours>,
+    warmup: Arc<Warmup>,
+    session_records: Option<Arc<SessionRecordWriter>>,
+    max_stateful_features: Option<usize>,
+    max_handshake_data_packets: u32,
+    require_bound_session_key: bool,
+    trace_sample_rate: f64,
+    state: Arc<RwLock<ServerState>>,
+}
+
+/// Bind a client listener on `addr`. IPv6 sockets are made v6-only, so
+/// `[::]` and `0.0.0.0` can both be listened on with the same port even
+/// where a wildcard IPv6 socket would otherwise claim IPv4 as well.
+fn bind_listener(addr: SocketAddr) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    TcpListener::from_std(socket.into())
+}
+
+/// Accept clients on `listener` until the server leaves the Running state
+async fn run_accept_loop(listener: TcpListener, ctx: Arc<AcceptContext>) {
+    let local_addr = listener.local_addr().map(|addr| addr.to_string()).unwrap_or_default();
+    loop {
+        let current_state = *ctx.state.read().await;
+        if current_state != ServerState::Running {
+            info!("Server state is {:?}, stopping accept loop on {}.", current_state, local_addr);
+            break;
+        }
+
+        match listener.accept().await {
+            Ok((stream, addr)) => {
+                trace!("Accepted connection from {} on {}", addr, local_addr);
+
+                if !ctx.rate_limiter.check_rate_limit(&addr.ip()).await {
+                    trace!("Rate limit exceeded for {}, rejecting connection", addr);
+                    ctx.failure_logs.rate_limited.record(&addr.ip().to_string());
+                    drop(stream);
+                    continue;
+                }
+
+                if !ctx.warmup.admit().await {
+                    trace!("Warming up, refusing connection from {}", addr);
+                    ctx.failure_logs.warmup.record(&addr.ip().to_string());
+                    drop(stream);
+                    continue;
+                }
+
+                // Cheapest possible rejection during a storm: no TLS, no WebSocket
+                if ctx.session_manager.at_capacity().await {
+                    trace!("Session cap reached, refusing connection from {}", addr);
+                    ctx.failure_logs.at_capacity.record(&addr.ip().to_string());
+                    ctx.metrics.record_capacity_rejection().await;
+                    drop(stream);
+                    continue;
+                }
+
+                ctx.metrics.record_new_connection().await;
+
+                // Spawn a task for each client
+                let ctx = ctx.clone();
+                tokio::spawn(async move {
+                    let result = match &ctx.tls_acceptor {
+                        Some(tls_acceptor) => handle_client(
+                            stream,
+                            addr,
+                            tls_acceptor.clone(),
+                            ctx.require_alpn,
+                            ctx.key_manager.clone(),
+                            ctx.auth_manager.clone(),
+                            ctx.ip_pool.clone(),
+                            ctx.session_manager.clone(),
+                            ctx.session_key_manager.clone(),
+                            ctx.network_monitor.clone(),
+                            ctx.packet_router.clone(),
+                            ctx.metrics.clone(),
+                            ctx.origin_provider.clone(),
+                            ctx.handshake_policy.clone(),
+                            ctx.worker_pool.clone(),
+                            ctx.quiet_hours.clone(),
+                            ctx.session_records.clone(),
+                            ctx.max_stateful_features,
+                            ctx.max_handshake_data_packets,
+                            ctx.require_bound_session_key,
+                            ctx.trace_sample_rate,
+                            ctx.state.clone(),
+                        ).await,
+                        None => handle_client_raw(
+                            stream,
+                            addr,
+                            ctx.key_manager.clone(),
+                            ctx.auth_manager.clone(),
+                            ctx.ip_pool.clone(),
+                            ctx.session_manager.clone(),
+                            ctx.session_key_manager.clone(),
+                            ctx.network_monitor.clone(),
+                            ctx.packet_router.clone(),
+                            ctx.metrics.clone(),
+                            ctx.origin_provider.clone(),
+                            ctx.handshake_policy.clone(),
+                            ctx.worker_pool.clone(),
+                            ctx.quiet_hours.clone(),
+                            ctx.session_records.clone(),
+                            ctx.max_stateful_features,
+                            ctx.max_handshake_data_packets,
+                            ctx.require_bound_session_key,
+                            ctx.trace_sample_rate,
+                            ctx.state.clone(),
+                        ).await,
+                    };
+
+                    // Log client disconnection reason
+                    if let Err(e) = result {
+                        match e {
+                            ServerError::WebSocket(ws_err) => {
+                                use tokio_tungstenite::tungstenite::error::Error as WsError;
+                                match ws_err {
+                                    WsError::ConnectionClosed | WsError::Protocol(_) | WsError::Io(_) => {
+                                        trace!("WebSocket connection closed for {}: {}", addr, ws_err);
+                                    },
+                                    _ => {
+                                        debug!("WebSocket error for {}: {}", addr, ws_err);
+                                    }
+                                }
+                            }
+                            ServerError::Authentication(_) => {
+                                trace!("Client {} disconnected due to auth error: {}", addr, e);
+                                ctx.failure_logs.auth.record(&addr.ip().to_string());
+                            }
+                            ServerError::Tls(_) => {
+                                trace!("Client {} disconnected due to TLS error: {}", addr, e);
+                                ctx.failure_logs.tls.record(&addr.ip().to_string());
+                            }
+                            ServerError::Session(SessionError::ConnectionLimitReached(_)) => {
+                                trace!("Client {} refused: {}", addr, e);
+                                ctx.failure_logs.connection_limit.record(&addr.ip().to_string());
+                            }
+                            ServerError::Session(SessionError::ServerFull(_)) => {
+                                trace!("Client {} refused: {}", addr, e);
+                                ctx.failure_logs.at_capacity.record(&addr.ip().to_string());
+                            }
+                            ServerError::Internal(ref msg) if msg == "Server shutting down" => {
+                                debug!("Client {} disconnected due to server shutdown.", addr);
+                            }
+                            _ => {
+                                error!("Error handling client {}: {}", addr, e);
+                            }
+                        }
+                    }
+                    ctx.metrics.record_connection_close().await;
+                });
+            }
+            Err(e) => {
+                let current_state = *ctx.state.read().await;
+                if current_state == ServerState::Running {
+                    error!("Error accepting connection on {}: {}", local_addr, e);
+                    // Avoid busy-looping on accept errors
+                    time::sleep(Duration::from_millis(100)).await;
+                } else {
+                    info!("Accept loop on {} terminated due to server state change.", local_addr);
+                    break; // Exit loop if server is stopping/stopped
+                }
+            }
+        }
+    }
+    info!("Listener on {} stopped.", local_addr);
 }
 
 impl VpnServer {
@@ -141,6 +445,11 @@ impl VpnServer {
                 km
             }
         };
+        key_manager.set_secret_retry_policy(SecretRetryPolicy {
+            max_retries: config.shared_secret_retries,
+            base_backoff: Duration::from_millis(config.shared_secret_retry_backoff_ms),
+        }).await;
+        key_manager.set_rotation_overlap(Duration::from_secs(config.server_key_rotation_overlap_secs)).await;
 
         // Setup TUN device
         info!("Setting up TUN device: {}", config.tun_name);
@@ -152,7 +461,7 @@ impl VpnServer {
             name: config.tun_name.clone(),
             subnet: config.subnet.clone(),
             server_ip,
-            mtu: crate::config::constants::TUN_MTU,
+            mtu: config.tun_mtu,
         };
         let tun_device = setup_tun_device(&tun_config)
             .map_err(|e| ServerError::TunSetup(format!("Failed to create TUN device: {}", e)))?;
@@ -169,25 +478,77 @@ impl VpnServer {
             None
         };
 
+        let audit_sink: Option<Arc<dyn AuditSink>> = match &config.auth_audit_file {
+            Some(path) => {
+                info!("Writing authentication audit events to {}", path.display());
+                Some(Arc::new(JsonLinesAuditSink::open(path).await
+                    .map_err(|e| ServerError::Internal(format!("Failed to open auth audit file {}: {}", path.display(), e)))?))
+            }
+            None => None,
+        };
+
         // Initialize auth manager
         let auth_manager = Arc::new(AuthManager::new(
             config.acl_file.clone(),
             key_manager.clone(),
              crate::config::constants::AUTH_CHALLENGE_TIMEOUT,
             1000,
-        ).await.map_err(|e| ServerError::Authentication(e.to_string()))?);
+            config.challenge_address_binding,
+        ).await
+         .map(|manager| match config.challenge_batch_window_ms {
+             0 => manager,
+             window => manager.with_signature_batching(Duration::from_millis(window)),
+         })
+         .map(|manager| manager.with_failure_limit(
+             config.auth_failure_limit,
+             Duration::from_secs(config.auth_failure_window_secs),
+         ))
+         .map(|manager| manager.with_proof_of_work(config.pow_difficulty, config.max_pow_difficulty))
+         .map(|manager| manager.with_acl_deny_response(config.acl_deny_response))
+         .map(|manager| manager.with_capability_queries(config.capability_queries_per_minute))
+         .map(|manager| match config.resumption_ttl_secs {
+             0 => manager,
+             ttl => manager.with_session_resumption(Duration::from_secs(ttl)),
+         })
+         .map(|manager| match audit_sink {
+             Some(sink) => manager.with_audit_sink(sink),
+             None => manager,
+         })
+         .map_err(|e| ServerError::Authentication(e.to_string()))?);
 
         // Initialize IP pool manager
         let ip_pool = Arc::new(IpPoolManager::new(
             &config.subnet,
             config.session_timeout.as_secs(),
-        ).await.map_err(|e| ServerError::Network(format!("Failed to initialize IP pool: {}", e)))?);
+        ).await
+         .map(|pool| pool.with_release_grace(Duration::from_secs(config.ip_release_grace_secs)))
+         .map(|pool| pool.with_max_lease_duration(config.max_lease_duration_secs))
+         .and_then(|pool| pool.with_reservations(parse_reservations(&config.ip_reservations)?))
+         .map(|pool| match &config.ip_lease_file {
+             Some(path) => pool.with_lease_store(Arc::new(FileLeaseStore::new(path))),
+             None => pool,
+         })
+         .map_err(|e| ServerError::Network(format!("Failed to initialize IP pool: {}", e)))?);
+        ip_pool.restore_leases().await
+            .map_err(|e| ServerError::Network(format!("Failed to restore IP leases: {}", e)))?;
 
         // Initialize session manager
         let session_manager = Arc::new(SessionManager::new(
             config.max_connections_per_ip,
             config.session_timeout,
-        ));
+        )
+        .with_duplicate_policy(config.duplicate_session_policy)
+        .with_max_sessions(config.max_sessions)
+        .with_idle_timeout(Duration::from_secs(config.idle_timeout_secs))
+        .with_max_missed_pongs(config.max_missed_pongs)
+        .with_heartbeat_policy(match config.heartbeat_rtt_threshold_ms {
+            0 => HeartbeatPolicy::fixed(Duration::from_secs(config.heartbeat_interval_secs)),
+            threshold => HeartbeatPolicy::fixed(Duration::from_secs(config.heartbeat_interval_secs))
+                .with_backoff(Duration::from_millis(threshold), Duration::from_secs(config.max_heartbeat_interval_secs)),
+        })
+        .with_send_queue_depth(config.send_queue_depth)
+        .with_max_sessions_per_key(config.max_sessions_per_key)
+        .with_max_connections(config.max_connections, Duration::from_millis(config.connection_queue_timeout_ms)));
         
         // Set global session manager reference
         crate::server::globals::set_session_manager(session_manager.clone());
@@ -196,7 +557,10 @@ impl VpnServer {
         let session_key_manager = Arc::new(SessionKeyManager::new(
             config.key_rotation_interval,
             1_000_000,
-        ));
+        ).with_key_pool(config.session_key_pool_size)
+         .with_rotation_deferral(Duration::from_secs(config.max_key_rotation_deferral_secs))
+         .with_rotation_rate_limit(config.max_key_rotations_per_sec)
+         .with_rotation_jitter(config.key_rotation_jitter_percent));
         
         // Set global session key manager reference
         crate::server::globals::set_session_key_manager(session_key_manager.clone());
@@ -207,11 +571,17 @@ impl VpnServer {
             120,
         ));
 
-        // Initialize packet router
-        let packet_router = Arc::new(PacketRouter::new(
-            crate::config::constants::PACKET_SIZE_LIMIT,
-            config.enable_padding,
-        ));
+        // Initialize chaos controller if requested and compiled in
+        #[cfg(feature = "chaos")]
+        let chaos = if config.enable_chaos_mode {
+            Some(Arc::new(crate::server::chaos::ChaosController::new()))
+        } else {
+            None
+        };
+        #[cfg(not(feature = "chaos"))]
+        if config.enable_chaos_mode {
+            error!("Chaos mode requested but this build does not include the `chaos` feature; ignoring");
+        }
 
         // Initialize metrics collector
         let metrics = Arc::new(ServerMetricsCollector::new(
@@ -219,12 +589,91 @@ impl VpnServer {
             60,
         ));
 
+        // Initialize the bounded TUN write queue
+        let tun_queue = Arc::new(TunWriteQueue::new(config.tun_queue_depth, config.tun_queue_policy));
+
+        // Initialize packet router
+        let packet_router = PacketRouter::new(
+            crate::config::constants::PACKET_SIZE_LIMIT,
+            config.enable_padding,
+        )
+        .with_traffic_limits(TrafficLimits {
+            packets_per_sec: config.max_client_packets_per_sec,
+            bytes_per_sec: config.max_client_bytes_per_sec,
+        })
+        .with_metrics(metrics.clone())
+        .with_protocol_allowlist(
+            config.tunnel_allowed_protocols.iter()
+                .filter_map(|p| crate::server::routing::parse_ip_protocol(p)),
+        )
+        .with_source_ip_policy(config.source_ip_policy)
+        .with_tun_queue(tun_queue.clone())
+        .with_fragment_size(config.fragment_size)
+        .with_tunnel_mtu(config.tun_mtu)
+        .with_mss_clamp(config.mss_clamp)
+        .with_padding_policy(config.padding_policy, config.padding_bucket_size);
+        let flow_tracker = config.flow_collector.map(|collector| {
+            info!("Exporting tunnel flow records to {}", collector);
+            Arc::new(FlowTracker::new(FlowTrackerConfig {
+                max_flows: config.flow_max_tracked,
+                idle_timeout: Duration::from_secs(config.flow_idle_timeout_secs),
+                active_timeout: Duration::from_secs(config.flow_active_timeout_secs),
+            }))
+        });
+        let packet_router = match &flow_tracker {
+            Some(tracker) => packet_router.with_flow_tracker(tracker.clone()),
+            None => packet_router,
+        };
+        #[cfg(feature = "chaos")]
+        let packet_router = match &chaos {
+            Some(chaos) => packet_router.with_chaos(chaos.clone()),
+            None => packet_router,
+        };
+        let packet_router = Arc::new(packet_router);
+
         // Initialize rate limiter
         let rate_limiter = Arc::new(RateLimiter::new(
             config.max_connections_per_ip,
              crate::config::constants::RATE_LIMIT_WINDOW,
         ));
 
+        let failure_logs = Arc::new(ConnectionFailureLogs::new(
+            Duration::from_millis(config.log_aggregation_window_ms),
+        ));
+
+        let handshake_policy = Arc::new(WsHandshakePolicy::new(
+            config.ws_allowed_origins.clone(),
+            config.ws_subprotocol.clone(),
+        ).with_message_limits(config.ws_max_message_size, config.ws_max_frame_size));
+        let worker_pool = if config.session_worker_threads > 0 {
+            Some(Arc::new(PinnedWorkerPool::new(config.session_worker_threads)
+                .map_err(|e| ServerError::Internal(format!("Failed to start session workers: {}", e)))?))
+        } else {
+            None
+        };
+
+        let quiet_hours = Arc::new(QuietHours::parse(&config.quiet_hours)
+            .map_err(|e| ServerError::Internal(e.to_string()))?);
+
+        let session_records = match &config.session_record_file {
+            Some(path) => {
+                info!("Writing session lifecycle records to {}", path.display());
+                Some(Arc::new(SessionRecordWriter::open(path).await
+                    .map_err(|e| ServerError::Internal(format!("Failed to open session record file {}: {}", path.display(), e)))?))
+            }
+            None => None,
+        };
+
+        let warmup = Arc::new(Warmup::new(
+            Duration::from_secs(config.warmup_secs),
+            config.warmup_mode,
+            config.warmup_accepts_per_sec,
+        ));
+
+        if handshake_policy.is_enabled() {
+            info!("WebSocket Origin/subprotocol validation enabled");
+        }
+
         // Configure NAT if requested
         if let Err(e) = configure_nat(&config.tun_name, &config.subnet) {
             warn!("Failed to configure NAT: {}. VPN routing may not work correctly.", e);
@@ -258,26 +707,54 @@ impl VpnServer {
             session_key_manager,
             network_monitor,
             packet_router,
+            tun_queue,
             metrics,
+            origin_provider: Arc::new(NoopOriginProvider),
             rate_limiter,
+            failure_logs,
+            handshake_policy,
+            flow_tracker,
+            worker_pool,
+            quiet_hours,
+            warmup,
+            session_records,
+            reload_requests: Arc::new(Notify::new()),
             state: Arc::new(RwLock::new(ServerState::Created)),
             task_handles: Arc::new(Mutex::new(Vec::new())),
+            listener_handles: Mutex::new(Vec::new()),
+            local_addrs: Mutex::new(Vec::new()),
             registration_manager,
+            #[cfg(feature = "chaos")]
+            chaos,
         })
     }
 
     /// Set up TLS configuration
      fn setup_tls(config: &ServerConfig) -> Result<Arc<RustlsServerConfig>, ServerError> {
-        debug!("Setting up TLS with cert: {:?}, key: {:?}", config.cert_file, config.key_file);
+        let client_auth = config.client_ca_file.as_deref().map(|ca_file| ClientCertAuth {
+            ca_file,
+            required: config.require_client_cert,
+        });
+        Self::tls_server_config(&config.cert_file, &config.key_file, &config.alpn_protocols, client_auth)
+    }
 
-        if !config.cert_file.exists() {
-            return Err(ServerError::Tls(format!("Certificate file not found: {:?}", config.cert_file)));
+    /// Build the rustls server config from PEM files, advertising the given ALPN protocols
+    pub(crate) fn tls_server_config(
+        cert_path: &Path,
+        key_path: &Path,
+        alpn_protocols: &[String],
+        client_auth: Option<ClientCertAuth<'_>>,
+    ) -> Result<Arc<RustlsServerConfig>, ServerError> {
+        debug!("Setting up TLS with cert: {:?}, key: {:?}", cert_path, key_path);
+
+        if !cert_path.exists() {
+            return Err(ServerError::Tls(format!("Certificate file not found: {:?}", cert_path)));
         }
-        if !config.key_file.exists() {
-            return Err(ServerError::Tls(format!("Private key file not found: {:?}", config.key_file)));
+        if !key_path.exists() {
+            return Err(ServerError::Tls(format!("Private key file not found: {:?}", key_path)));
         }
 
-        let cert_file = std::fs::File::open(&config.cert_file)
+        let cert_file = std::fs::File::open(cert_path)
             .map_err(|e| ServerError::Tls(format!("Failed to open cert file: {}", e)))?;
         let mut cert_reader = std::io::BufReader::new(cert_file);
 
@@ -295,7 +772,7 @@ impl VpnServer {
              warn!("Certificate validation warning: {}", e);
         }
 
-        let key_file = std::fs::File::open(&config.key_file)
+        let key_file = std::fs::File::open(key_path)
             .map_err(|e| ServerError::Tls(format!("Failed to open key file: {}", e)))?;
          let mut key_reader = std::io::BufReader::new(key_file);
 
@@ -309,19 +786,57 @@ impl VpnServer {
              })
              .ok_or_else(|| ServerError::Tls("No valid private key (PKCS#8 or RSA) found in key file".to_string()))?;
 
-        // Use rustls safe defaults, TLS 1.3 only
-        let mut tls_config = RustlsServerConfig::builder()
-            .with_safe_defaults()
-            .with_no_client_auth()
+        // Use rustls safe defaults, TLS 1.3 only.
+        // Client certificates are only requested when a client CA is configured.
+        // A verified certificate must carry an Ed25519 key, which then stands in
+        // for the challenge, so every per-client cap and rate limit still keys
+        // off the client's public key. Certificate-authenticated clients are
+        // therefore subject to the same pubkey-keyed session limits as
+        // challenge-authenticated ones; there are no separate per-certificate limits.
+        let builder = RustlsServerConfig::builder().with_safe_defaults();
+        let builder = match client_auth {
+            Some(client_auth) => {
+                let roots = Self::load_client_roots(client_auth.ca_file)?;
+                let verifier = if client_auth.required {
+                    AllowAnyAuthenticatedClient::new(roots)
+                } else {
+                    AllowAnyAnonymousOrAuthenticatedClient::new(roots)
+                };
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let mut tls_config = builder
             .with_single_cert(rustls_certs, key)
             .map_err(|e| ServerError::Tls(format!("TLS config error: {}", e)))?;
 
         // Enable session resumption
         tls_config.session_storage = rustls::server::ServerSessionMemoryCache::new(1024);
 
+        // Clients offering none of these protocols fail the handshake
+        tls_config.alpn_protocols = alpn_protocols.iter().map(|p| p.as_bytes().to_vec()).collect();
+
         Ok(Arc::new(tls_config))
     }
 
+    /// Load the CAs trusted to issue client certificates
+    fn load_client_roots(ca_path: &Path) -> Result<RootCertStore, ServerError> {
+        let ca_file = std::fs::File::open(ca_path)
+            .map_err(|e| ServerError::Tls(format!("Failed to open client CA file {:?}: {}", ca_path, e)))?;
+        let ca_certs = rustls_pemfile::certs(&mut std::io::BufReader::new(ca_file))
+            .map_err(|e| ServerError::Tls(format!("Failed to parse client CA file: {}", e)))?;
+
+        let mut roots = RootCertStore::empty();
+        for der in ca_certs {
+            roots.add(&Certificate(der))
+                .map_err(|e| ServerError::Tls(format!("Invalid client CA certificate: {}", e)))?;
+        }
+        if roots.is_empty() {
+            return Err(ServerError::Tls(format!("No certificates found in client CA file {:?}", ca_path)));
+        }
+        Ok(roots)
+    }
+
     /// Validate certificate expiration dates (basic check)
     fn validate_certificate_expiry(certs: &[Certificate]) -> Result<(), ServerError> {
          if certs.is_empty() {
@@ -339,7 +854,7 @@ impl VpnServer {
     }
 
     /// Start the VPN server
-    /// Returns a JoinHandle for the main listener task.
+    /// Returns a JoinHandle that finishes once every listener has stopped.
     pub async fn start(&self) -> Result<JoinHandle<()>, ServerError> {
         // --- State Check ---
         {
@@ -355,8 +870,37 @@ impl VpnServer {
         } else {
             "RAW mode (ws://) - no TLS"
         };
-        
-        info!("Starting AeroNyx Privacy Network Server on {} in {}", self.config.listen_addr, transport_mode);
+        if self.config.transport_security == TransportSecurity::Tls && self.tls_acceptor.is_none() {
+            *self.state.write().await = ServerState::Stopped;
+            return Err(ServerError::Internal("TLS acceptor not initialized".to_string()));
+        }
+
+        let listen_addrs = self.config.listen_addrs();
+        info!(
+            "Starting AeroNyx Privacy Network Server on {} in {}",
+            listen_addrs.iter().map(|addr| addr.to_string()).collect::<Vec<_>>().join(", "),
+            transport_mode,
+        );
+
+        // --- Bind Listeners ---
+        // All of them before anything else starts, so a taken port fails startup cleanly
+        let mut listeners = Vec::with_capacity(listen_addrs.len());
+        for listen_addr in listen_addrs {
+            match bind_listener(listen_addr) {
+                Ok(listener) => {
+                    let local_addr = listener.local_addr().unwrap_or(listen_addr);
+                    info!("Server listening on {} ({})", local_addr, transport_mode);
+                    listeners.push(listener);
+                }
+                Err(e) => {
+                    *self.state.write().await = ServerState::Stopped;
+                    return Err(ServerError::Network(format!("Failed to bind to {}: {}", listen_addr, e)));
+                }
+            }
+        }
+        *self.local_addrs.lock().await = listeners.iter()
+            .filter_map(|listener| listener.local_addr().ok())
+            .collect();
         
         // Check if all global references are properly initialized
         if !crate::server::globals::all_initialized() {
@@ -375,9 +919,11 @@ impl VpnServer {
              self.network_monitor.clone(),
              self.state.clone(),
          ).await;
+        let tun_writer_handle = self.tun_queue.clone().spawn_writer(self.tun_device.clone());
         {
             let mut handles = self.task_handles.lock().await;
             handles.push(tun_processor_handle);
+            handles.push(tun_writer_handle);
         }
 
         // --- Start WebSocket connection if registered ---
@@ -421,264 +967,54 @@ impl VpnServer {
             }
         }
 
-        // --- Prepare for Main Loop ---
-        let key_manager = self.key_manager.clone();
-        let auth_manager = self.auth_manager.clone();
-        let ip_pool = self.ip_pool.clone();
-        let session_manager = self.session_manager.clone();
-        let session_key_manager = self.session_key_manager.clone();
-        let network_monitor = self.network_monitor.clone();
-        let packet_router = self.packet_router.clone();
-        let metrics = self.metrics.clone();
-        let rate_limiter = self.rate_limiter.clone();
-        let state = self.state.clone();
-        let listen_addr = self.config.listen_addr;
-        let transport_security = self.config.transport_security;
-
-        // --- Main Server Loop Task (Accepting Connections) ---
-        let main_server_handle = if transport_security == TransportSecurity::Tls {
-            // --- TLS Mode Logic (original logic) ---
-            info!("Starting server in TLS mode (wss://)");
-            let tls_acceptor = self.tls_acceptor.clone()
-                .ok_or_else(|| ServerError::Internal("TLS acceptor not initialized".to_string()))?;
-
-            tokio::spawn(async move {
-                let listener = match TcpListener::bind(&listen_addr).await {
-                    Ok(l) => {
-                        info!("Server listening on {} (TLS mode)", listen_addr);
-                        {
-                            let mut state_guard = state.write().await;
-                            if *state_guard == ServerState::Starting {
-                                *state_guard = ServerState::Running;
-                            } else {
-                                error!("Server state changed during bind, stopping listener task.");
-                                return;
-                            }
-                        }
-                        l
-                    }
-                    Err(e) => {
-                        error!("Failed to bind to {}: {}", listen_addr, e);
-                        let mut state_guard = state.write().await;
-                        *state_guard = ServerState::Stopped;
-                        return;
-                    }
-                };
-
-                // --- Accept Loop ---
-                loop {
-                    let current_state = *state.read().await;
-                    if current_state != ServerState::Running {
-                        info!("Server state is {:?}, stopping accept loop.", current_state);
-                        break;
-                    }
-
-                    match listener.accept().await {
-                        Ok((stream, addr)) => {
-                            trace!("Accepted connection from {}", addr);
-
-                            if !rate_limiter.check_rate_limit(&addr.ip()).await {
-                                warn!("Rate limit exceeded for {}, rejecting connection", addr);
-                                drop(stream);
-                                continue;
-                            }
-
-                            metrics.record_new_connection().await;
-
-                            // Clone Arcs for the client handling task
-                            let tls_acceptor_clone = tls_acceptor.clone();
-                            let key_manager_clone = key_manager.clone();
-                            let auth_manager_clone = auth_manager.clone();
-                            let ip_pool_clone = ip_pool.clone();
-                            let session_manager_clone = session_manager.clone();
-                            let session_key_manager_clone = session_key_manager.clone();
-                            let network_monitor_clone = network_monitor.clone();
-                            let packet_router_clone = packet_router.clone();
-                            let metrics_clone = metrics.clone();
-                            let server_state_clone = state.clone();
-
-                            // Spawn a task for each client
-                            tokio::spawn(async move {
-                                let client_metrics = metrics_clone;
-                                let result = handle_client(
-                                    stream,
-                                    addr,
-                                    tls_acceptor_clone,
-                                    key_manager_clone,
-                                    auth_manager_clone,
-                                    ip_pool_clone,
-                                    session_manager_clone,
-                                    session_key_manager_clone,
-                                    network_monitor_clone,
-                                    packet_router_clone,
-                                    client_metrics.clone(),
-                                    server_state_clone,
-                                ).await;
-
-                                // Log client disconnection reason
-                                if let Err(e) = result {
-                                    match e {
-                                        ServerError::WebSocket(ws_err) => {
-                                            use tokio_tungstenite::tungstenite::error::Error as WsError;
-                                            match ws_err {
-                                                WsError::ConnectionClosed | WsError::Protocol(_) | WsError::Io(_) => {
-                                                    trace!("WebSocket connection closed for {}: {}", addr, ws_err);
-                                                },
-                                                _ => {
-                                                    debug!("WebSocket error for {}: {}", addr, ws_err);
-                                                }
-                                            }
-                                        }
-                                        ServerError::Authentication(_) | ServerError::Tls(_) => {
-                                            debug!("Client {} disconnected due to auth/TLS error: {}", addr, e);
-                                        }
-                                        ServerError::Internal(ref msg) if msg == "Server shutting down" => {
-                                            debug!("Client {} disconnected due to server shutdown.", addr);
-                                        }
-                                        _ => {
-                                            error!("Error handling client {}: {}", addr, e);
-                                        }
-                                    }
-                                }
-                                client_metrics.record_connection_close().await;
-                            });
-                        }
-                        Err(e) => {
-                            let current_state = *state.read().await;
-                            if current_state == ServerState::Running {
-                                error!("Error accepting connection: {}", e);
-                                // Avoid busy-looping on accept errors
-                                time::sleep(Duration::from_millis(100)).await;
-                            } else {
-                                info!("Accept loop terminated due to server state change.");
-                                break; // Exit loop if server is stopping/stopped
-                            }
-                        }
-                    }
-                }
-                // Accept loop finished
-                info!("Server listener task stopped (TLS mode).");
-            })
-        } else {
-            // --- RAW Mode Logic (new logic) ---
-            info!("Starting server in RAW mode (ws://) - no TLS");
-            
-            tokio::spawn(async move {
-                let listener = match TcpListener::bind(&listen_addr).await {
-                    Ok(l) => {
-                        info!("Server listening on {} (RAW mode)", listen_addr);
-                        {
-                            let mut state_guard = state.write().await;
-                            if *state_guard == ServerState::Starting {
-                                *state_guard = ServerState::Running;
-                            } else {
-                                error!("Server state changed during bind, stopping listener task.");
-                                return;
-                            }
-                        }
-                        l
-                    }
-                    Err(e) => {
-                        error!("Failed to bind to {}: {}", listen_addr, e);
-                        let mut state_guard = state.write().await;
-                        *state_guard = ServerState::Stopped;
-                        return;
-                    }
-                };
-
-                // --- Accept Loop ---
-                loop {
-                    let current_state = *state.read().await;
-                    if current_state != ServerState::Running {
-                        info!("Server state is {:?}, stopping accept loop.", current_state);
-                        break;
-                    }
-
-                    match listener.accept().await {
-                        Ok((stream, addr)) => {
-                            trace!("Accepted connection from {}", addr);
+        // --- Accept Loops: one per listener, all sharing the server's managers ---
+        {
+            let mut state = self.state.write().await;
+            if *state != ServerState::Starting {
+                return Err(ServerError::Internal(format!("Server state changed to {:?} during startup", *state)));
+            }
+            *state = ServerState::Running;
+        }
+        let context = Arc::new(AcceptContext {
+            tls_acceptor: self.tls_acceptor.clone(),
+            require_alpn: !self.config.alpn_protocols.is_empty(),
+            key_manager: self.key_manager.clone(),
+            auth_manager: self.auth_manager.clone(),
+            ip_pool: self.ip_pool.clone(),
+            session_manager: self.session_manager.clone(),
+            session_key_manager: self.session_key_manager.clone(),
+            network_monitor: self.network_monitor.clone(),
+            packet_router: self.packet_router.clone(),
+            metrics: self.metrics.clone(),
+            origin_provider: self.origin_provider.clone(),
+            rate_limiter: self.rate_limiter.clone(),
+            failure_logs: self.failure_logs.clone(),
+            handshake_policy: self.handshake_policy.clone(),
+            worker_pool: self.worker_pool.clone(),
+            quiet_hours: self.quiet_hours.clone(),
+            warmup: self.warmup.clone(),
+            session_records: self.session_records.clone(),
+            max_stateful_features: self.config.max_stateful_features,
+            max_handshake_data_packets: self.config.max_handshake_data_packets,
+            require_bound_session_key: self.config.require_bound_session_key,
+            trace_sample_rate: self.config.trace_sample_rate,
+            state: self.state.clone(),
+        });
+        let mut accept_loops = Vec::with_capacity(listeners.len());
+        {
+            let mut listener_handles = self.listener_handles.lock().await;
+            for listener in listeners {
+                let handle = tokio::spawn(run_accept_loop(listener, context.clone()));
+                listener_handles.push(handle.abort_handle());
+                accept_loops.push(handle);
+            }
+        }
 
-                            if !rate_limiter.check_rate_limit(&addr.ip()).await {
-                                warn!("Rate limit exceeded for {}, rejecting connection", addr);
-                                drop(stream);
-                                continue;
-                            }
-
-                            metrics.record_new_connection().await;
-
-                            // Clone Arcs for the client handling task
-                            let key_manager_clone = key_manager.clone();
-                            let auth_manager_clone = auth_manager.clone();
-                            let ip_pool_clone = ip_pool.clone();
-                            let session_manager_clone = session_manager.clone();
-                            let session_key_manager_clone = session_key_manager.clone();
-                            let network_monitor_clone = network_monitor.clone();
-                            let packet_router_clone = packet_router.clone();
-                            let metrics_clone = metrics.clone();
-                            let server_state_clone = state.clone();
-
-                            // Spawn a task for each client
-                            tokio::spawn(async move {
-                                let client_metrics = metrics_clone;
-                                let result = handle_client_raw(
-                                    stream,
-                                    addr,
-                                    key_manager_clone,
-                                    auth_manager_clone,
-                                    ip_pool_clone,
-                                    session_manager_clone,
-                                    session_key_manager_clone,
-                                    network_monitor_clone,
-                                    packet_router_clone,
-                                    client_metrics.clone(),
-                                    server_state_clone,
-                                ).await;
-
-                                // Log client disconnection reason
-                                if let Err(e) = result {
-                                    match e {
-                                        ServerError::WebSocket(ws_err) => {
-                                            use tokio_tungstenite::tungstenite::error::Error as WsError;
-                                            match ws_err {
-                                                WsError::ConnectionClosed | WsError::Protocol(_) | WsError::Io(_) => {
-                                                    trace!("WebSocket connection closed for {}: {}", addr, ws_err);
-                                                },
-                                                _ => {
-                                                    debug!("WebSocket error for {}: {}", addr, ws_err);
-                                                }
-                                            }
-                                        }
-                                        ServerError::Authentication(_) => {
-                                            debug!("Client {} disconnected due to auth error: {}", addr, e);
-                                        }
-                                        ServerError::Internal(ref msg) if msg == "Server shutting down" => {
-                                            debug!("Client {} disconnected due to server shutdown.", addr);
-                                        }
-                                        _ => {
-                                            error!("Error handling client {}: {}", addr, e);
-                                        }
-                                    }
-                                }
-                                client_metrics.record_connection_close().await;
-                            });
-                        }
-                        Err(e) => {
-                            let current_state = *state.read().await;
-                            if current_state == ServerState::Running {
-                                error!("Error accepting connection: {}", e);
-                                // Avoid busy-looping on accept errors
-                                time::sleep(Duration::from_millis(100)).await;
-                            } else {
-                                info!("Accept loop terminated due to server state change.");
-                                break; // Exit loop if server is stopping/stopped
-                            }
-                        }
-                    }
-                }
-                // Accept loop finished
-                info!("Server listener task stopped (RAW mode).");
-            })
-        };
+        // --- Main Server Task: finishes once every listener has stopped ---
+        let main_server_handle = tokio::spawn(async move {
+            futures::future::join_all(accept_loops).await;
+            info!("All listeners stopped.");
+        });
 
         Ok(main_server_handle)
     }
@@ -700,12 +1036,25 @@ impl VpnServer {
             *state = ServerState::ShuttingDown; // Signal all tasks to stop
         }
 
+        // --- Stop Accepting Connections ---
+        {
+            let mut listener_handles = self.listener_handles.lock().await;
+            info!("Closing {} listeners.", listener_handles.len());
+            for handle in listener_handles.drain(..) {
+                handle.abort(); // Drops the listener, releasing its address
+            }
+        }
+
+        // --- Save IP Leases ---
+        // Before sessions release their IPs, so clients get them back after a restart
+        if let Err(e) = self.ip_pool.persist_leases().await {
+            warn!("Failed to save IP leases: {}", e);
+        }
+
         // --- Gracefully Close Existing Sessions ---
         info!("Closing active client sessions...");
-        // Send disconnect messages and close connections
+        // Send disconnect messages and wait (bounded) for clients to close
         self.session_manager.close_all_sessions("Server shutdown").await;
-        // Give some time for messages to be sent
-        time::sleep(Duration::from_millis(500)).await;
 
         // --- Abort Background Tasks stored in task_handles ---
         info!("Stopping background tasks...");
@@ -775,10 +1124,112 @@ impl VpnServer {
                  if !removed.is_empty() {
                      debug!("Released {} expired IP leases", removed.len());
                  }
+                 if let Err(e) = ip_pool_clone.verify_consistency().await {
+                     error!("{}", e);
+                 }
              }
               debug!("IP pool cleanup task stopped.");
          }));
 
+         // --- Task: IP Lease Persistence ---
+         let ip_pool_clone = self.ip_pool.clone();
+         let state_clone = self.state.clone();
+         handles.push(tokio::spawn(async move {
+             let mut interval = time::interval(crate::config::constants::LEASE_PERSIST_INTERVAL);
+             loop {
+                 interval.tick().await;
+                 let current_state = *state_clone.read().await;
+                 if current_state == ServerState::ShuttingDown || current_state == ServerState::Stopped { break; }
+
+                 if let Err(e) = ip_pool_clone.persist_leases_if_changed().await {
+                     warn!("Failed to save IP leases: {}", e);
+                 }
+             }
+              debug!("IP lease persistence task stopped.");
+         }));
+
+         // --- Task: Lease Expiry Gauges ---
+         let ip_pool_clone = self.ip_pool.clone();
+         let metrics_clone = self.metrics.clone();
+         let state_clone = self.state.clone();
+         handles.push(tokio::spawn(async move {
+             let mut interval = time::interval(crate::config::constants::LEASE_EXPIRY_GAUGE_INTERVAL);
+             loop {
+                 interval.tick().await;
+                 let current_state = *state_clone.read().await;
+                 if current_state == ServerState::ShuttingDown || current_state == ServerState::Stopped { break; }
+                 if current_state != ServerState::Running && current_state != ServerState::Starting { continue; }
+
+                 let buckets = ip_pool_clone.lease_expiry_distribution().await;
+                 if buckets.within_1m > 0 {
+                     debug!("{} IP leases expire within the next minute", buckets.within_1m);
+                 }
+                 metrics_clone.record_lease_expiry(buckets).await;
+                 let (_, allocated, _) = ip_pool_clone.get_stats().await;
+                 metrics_clone.record_allocated_ips(allocated).await;
+             }
+              debug!("Lease expiry gauge task stopped.");
+         }));
+
+         // --- Task: TUN Write Queue Gauge ---
+         let tun_queue_clone = self.tun_queue.clone();
+         let metrics_clone = self.metrics.clone();
+         let state_clone = self.state.clone();
+         handles.push(tokio::spawn(async move {
+             let mut interval = time::interval(crate::config::constants::TUN_QUEUE_GAUGE_INTERVAL);
+             loop {
+                 interval.tick().await;
+                 let current_state = *state_clone.read().await;
+                 if current_state == ServerState::ShuttingDown || current_state == ServerState::Stopped { break; }
+                 if current_state != ServerState::Running && current_state != ServerState::Starting { continue; }
+
+                 metrics_clone.record_tun_queue_depth(tun_queue_clone.depth().await).await;
+             }
+              debug!("TUN write queue gauge task stopped.");
+         }));
+
+         // --- Task: Session Memory Gauge ---
+         let session_manager_clone = self.session_manager.clone();
+         let packet_router_clone = self.packet_router.clone();
+         let metrics_clone = self.metrics.clone();
+         let state_clone = self.state.clone();
+         handles.push(tokio::spawn(async move {
+             let mut interval = time::interval(crate::config::constants::SESSION_MEMORY_GAUGE_INTERVAL);
+             loop {
+                 interval.tick().await;
+                 let current_state = *state_clone.read().await;
+                 if current_state == ServerState::ShuttingDown || current_state == ServerState::Stopped { break; }
+                 if current_state != ServerState::Running && current_state != ServerState::Starting { continue; }
+
+                 let usage = session_manager_clone.session_memory(&packet_router_clone).await;
+                 metrics_clone.record_session_memory(usage.iter().map(|(_, _, bytes)| bytes).sum()).await;
+             }
+              debug!("Session memory gauge task stopped.");
+         }));
+
+         // --- Task: Server Load Heartbeat ---
+         if self.config.server_load_interval_secs > 0 {
+             let ip_pool_clone = self.ip_pool.clone();
+             let session_manager_clone = self.session_manager.clone();
+             let state_clone = self.state.clone();
+             let load_interval = Duration::from_secs(self.config.server_load_interval_secs);
+             handles.push(tokio::spawn(async move {
+                 let mut interval = time::interval(load_interval);
+                 loop {
+                     interval.tick().await;
+                     let current_state = *state_clone.read().await;
+                     if current_state == ServerState::ShuttingDown || current_state == ServerState::Stopped { break; }
+                     if current_state != ServerState::Running { continue; }
+
+                     // Address pool saturation is the hard capacity limit
+                     let load_factor = ip_pool_clone.utilization().await;
+                     let notified = session_manager_clone.broadcast_server_load(load_factor).await;
+                     trace!("Sent server load {:.2} to {} clients", load_factor, notified);
+                 }
+                 debug!("Server load heartbeat task stopped.");
+             }));
+         }
+
          // --- Task: Session Key Cleanup ---
           let session_key_manager_clone = self.session_key_manager.clone();
           let state_clone = self.state.clone();
@@ -818,10 +1269,170 @@ impl VpnServer {
                   if removed > 0 {
                       debug!("Cleaned up {} expired authentication challenges", removed);
                   }
+                  let forgotten = auth_manager_clone.cleanup_failed_attempts().await;
+                  if forgotten > 0 {
+                      debug!("Forgot failed authentication attempts from {} source IPs", forgotten);
+                  }
+                  let expired = auth_manager_clone.cleanup_resumption_tokens().await;
+                  if expired > 0 {
+                      debug!("Dropped {} expired session resumption tokens", expired);
+                  }
               }
                debug!("Auth challenge cleanup task stopped.");
           }));
 
+         // --- Task: Refill Session Key Pool ---
+          if let Some(pool) = self.session_key_manager.key_pool() {
+              info!("Pre-generating a pool of {} session keys", self.config.session_key_pool_size);
+              handles.push(pool.start_refill_task());
+          }
+
+         // --- Task: Export Tunnel Flow Records ---
+          if let (Some(tracker), Some(collector)) = (self.flow_tracker.clone(), self.config.flow_collector) {
+              let state_clone = self.state.clone();
+              handles.push(tokio::spawn(async move {
+                  let mut exporter = match FlowExporter::new(collector).await {
+                      Ok(exporter) => exporter,
+                      Err(e) => {
+                          error!("Failed to create flow exporter for {}: {}", collector, e);
+                          return;
+                      }
+                  };
+                  let mut interval = time::interval(Duration::from_secs(1));
+                  let mut reported_dropped = 0;
+                  loop {
+                      interval.tick().await;
+                      let current_state = *state_clone.read().await;
+                      if current_state == ServerState::ShuttingDown || current_state == ServerState::Stopped { break; }
+
+                      let finished = tracker.collect_finished().await;
+                      let dropped = tracker.dropped_records();
+                      if dropped > reported_dropped {
+                          warn!("{} evicted flow records dropped before export", dropped - reported_dropped);
+                          reported_dropped = dropped;
+                      }
+                      if !finished.is_empty() {
+                          trace!("Exporting {} finished flows ({} still active)", finished.len(), tracker.active_flows().await);
+                          exporter.export(&finished).await;
+                      }
+                  }
+                  debug!("Flow export task stopped.");
+              }));
+          }
+
+         // --- Task: Export Per-Client Traffic ---
+          if let Some(target) = self.config.traffic_export_addr {
+              let state_clone = self.state.clone();
+              let monitor = self.network_monitor.clone();
+              let format = self.config.traffic_export_format;
+              let sample_every = Duration::from_secs(self.config.traffic_export_interval_secs);
+              handles.push(tokio::spawn(async move {
+                  let mut exporter = match UdpLineSink::new(target, format).await {
+                      Ok(sink) => TrafficExporter::new(Box::new(sink)),
+                      Err(e) => {
+                          error!("Failed to create traffic exporter for {}: {}", target, e);
+                          return;
+                      }
+                  };
+                  info!("Exporting per-client traffic to {} every {:?}", target, sample_every);
+                  let mut interval = time::interval(sample_every);
+                  loop {
+                      interval.tick().await;
+                      let current_state = *state_clone.read().await;
+                      if current_state == ServerState::ShuttingDown || current_state == ServerState::Stopped { break; }
+
+                      exporter.export(&monitor).await;
+                  }
+                  debug!("Traffic export task stopped.");
+              }));
+          }
+
+         // --- Task: Serve Prometheus Metrics ---
+          if let Some(metrics_addr) = self.config.metrics_listen {
+              match TcpListener::bind(metrics_addr).await {
+                  Ok(listener) => {
+                      info!("Serving Prometheus metrics on http://{}/metrics", metrics_addr);
+                      handles.push(self.metrics.clone().serve_prometheus(listener));
+                  }
+                  Err(e) => error!("Failed to bind metrics listener on {}: {}", metrics_addr, e),
+              }
+          }
+
+         // --- Task: Serve Admin Queries ---
+          if let Some(admin_addr) = self.config.admin_listen {
+              #[cfg(feature = "admin-api")]
+              match TcpListener::bind(admin_addr).await {
+                  Ok(listener) => {
+                      info!("Serving admin queries on http://{}/sessions", admin_addr);
+                      handles.push(self.admin_api().serve_http(listener));
+                  }
+                  Err(e) => error!("Failed to bind admin listener on {}: {}", admin_addr, e),
+              }
+              #[cfg(not(feature = "admin-api"))]
+              warn!("admin_listen {} ignored: this build does not include the admin-api feature", admin_addr);
+          }
+
+         // --- Task: Track Quiet Hours Transitions ---
+          if self.quiet_hours.is_enabled() {
+              let quiet_hours_clone = self.quiet_hours.clone();
+              let state_clone = self.state.clone();
+              handles.push(tokio::spawn(async move {
+                  let mut interval = time::interval(Duration::from_secs(1));
+                  let mut in_quiet_hours = false;
+                  loop {
+                      interval.tick().await;
+                      let current_state = *state_clone.read().await;
+                      if current_state == ServerState::ShuttingDown || current_state == ServerState::Stopped { break; }
+
+                      match quiet_hours_clone.active_now() {
+                          Some(remaining) if !in_quiet_hours => {
+                              info!("Entering quiet hours: rejecting new connections for the next {}s", remaining.as_secs());
+                              in_quiet_hours = true;
+                          }
+                          None if in_quiet_hours => {
+                              info!("Leaving quiet hours: accepting new connections");
+                              in_quiet_hours = false;
+                          }
+                          _ => {}
+                      }
+                  }
+                  debug!("Quiet hours task stopped.");
+              }));
+          }
+
+         // --- Task: Announce End of Warmup ---
+          if let Some(remaining) = self.warmup.remaining() {
+              info!("Warming up for {}s ({:?} mode); reporting not-ready", remaining.as_secs(), self.config.warmup_mode);
+              let warmup_clone = self.warmup.clone();
+              let state_clone = self.state.clone();
+              handles.push(tokio::spawn(async move {
+                  if let Some(remaining) = warmup_clone.remaining() {
+                      time::sleep(remaining).await;
+                  }
+                  let current_state = *state_clone.read().await;
+                  if current_state == ServerState::Running {
+                      info!("Warmup complete: server ready");
+                  }
+              }));
+          }
+
+         // --- Task: Flush Aggregated Failure Logs ---
+          let failure_logs_clone = self.failure_logs.clone();
+          let flush_interval = Duration::from_millis(self.config.log_aggregation_window_ms);
+          let state_clone = self.state.clone();
+          handles.push(tokio::spawn(async move {
+              let mut interval = time::interval(flush_interval);
+              loop {
+                  interval.tick().await;
+                  let current_state = *state_clone.read().await;
+                  // Stop if server is shutting down or stopped
+                  if current_state == ServerState::ShuttingDown || current_state == ServerState::Stopped { break; }
+
+                  failure_logs_clone.flush();
+              }
+               debug!("Failure log flush task stopped.");
+          }));
+
          // --- Task: Metrics Reporting ---
           let metrics_clone = self.metrics.clone();
           let state_clone = self.state.clone();
@@ -854,11 +1465,108 @@ impl VpnServer {
          info!("Background tasks started.");
     }
 
+    /// Reload the ACL, the server key file and the runtime-adjustable settings
+    /// of the configuration file
+    pub async fn reload(&self) -> Result<(), ServerError> {
+        self.reload_config().await?;
+        self.reload_server_key().await?;
+        self.reload_acl().await
+    }
+
+    /// Addresses the server is accepting clients on; empty until started
+    pub async fn local_addrs(&self) -> Vec<SocketAddr> {
+        self.local_addrs.lock().await.clone()
+    }
+
+    /// Wait until a reload is requested through the admin API
+    pub async fn reload_requested(&self) {
+        self.reload_requests.notified().await
+    }
+
+    /// Re-read the configuration file the server was started from, if any,
+    /// and apply the settings that can change at runtime: the per-IP
+    /// authentication failure limit and the server key rotation overlap.
+    /// Other settings take effect on restart.
+    pub async fn reload_config(&self) -> Result<(), ServerError> {
+        let path = match &self.config.config_file {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let reloaded = ServerConfig::load_from_file(&path.to_string_lossy())
+            .map_err(|e| ServerError::Internal(format!("Failed to reload configuration from {}: {}", path.display(), e)))?;
+
+        let window = Duration::from_secs(reloaded.auth_failure_window_secs);
+        self.auth_manager.set_failure_limit(reloaded.auth_failure_limit, window);
+        let overlap = Duration::from_secs(reloaded.server_key_rotation_overlap_secs);
+        self.key_manager.set_rotation_overlap(overlap).await;
+        info!(
+            "Reloaded configuration from {}: auth failure limit {} per {:?}, server key overlap {:?}",
+            path.display(), reloaded.auth_failure_limit, window, overlap
+        );
+        Ok(())
+    }
+
+    /// Re-read the server key file and rotate to the key in it if an operator
+    /// replaced it. New challenges carry the new key, while the previous one
+    /// still serves clients that were sent it for the rotation overlap.
+    pub async fn reload_server_key(&self) -> Result<(), ServerError> {
+        let previous = self.key_manager.public_key().await;
+        let current = self.key_manager.reload_keypair().await
+            .map_err(|e| ServerError::KeyError(format!("Failed to reload server key: {}", e)))?;
+        if current == previous {
+            debug!("Server key file unchanged; still serving {}", current);
+        }
+        Ok(())
+    }
+
+    /// Reload the ACL from disk.
+    /// With `acl_tier_policy = reapply`, tier changes are applied to all active
+    /// sessions of each key before returning; otherwise sessions keep the tier
+    /// they authenticated with.
+    /// If `acl_revoke_on_reload` is set, active sessions that are no longer
+    /// allowed are disconnected in the background at a bounded rate; otherwise
+    /// they keep running until they disconnect naturally.
+    pub async fn reload_acl(&self) -> Result<(), ServerError> {
+        let acl_manager = self.auth_manager.acl_manager();
+        acl_manager.reload().await
+            .map_err(|e| ServerError::Authentication(format!("Failed to reload ACL: {}", e)))?;
+
+        if self.config.acl_tier_policy == AclTierPolicy::Reapply {
+            let updated = self.session_manager
+                .reapply_acl_policies(&acl_manager, &self.network_monitor)
+                .await;
+            if updated > 0 {
+                info!("Re-applied ACL tiers to {} active sessions", updated);
+            }
+        }
+
+        if !self.config.acl_revoke_on_reload {
+            debug!("ACL reloaded; existing sessions are kept until they disconnect");
+            return Ok(());
+        }
+
+        let session_manager = self.session_manager.clone();
+        let rate = self.config.acl_revocation_rate;
+        tokio::spawn(async move {
+            let disconnected = session_manager.disconnect_revoked_sessions(&acl_manager, rate).await;
+            if disconnected > 0 {
+                info!("Disconnected {} sessions after ACL reload", disconnected);
+            }
+        });
+
+        Ok(())
+    }
+
     /// Get the server's current state
     pub async fn get_state(&self) -> ServerState {
         *self.state.read().await
     }
 
+    /// Check whether the server is running and past its startup warmup
+    pub async fn is_ready(&self) -> bool {
+        self.get_state().await == ServerState::Running && self.warmup.is_warm()
+    }
+
     // --- Accessor methods ---
     pub fn metrics(&self) -> Arc<ServerMetricsCollector> {
         self.metrics.clone()
@@ -872,6 +1580,22 @@ impl VpnServer {
     pub fn ip_pool(&self) -> Arc<IpPoolManager> {
         self.ip_pool.clone()
     }
+    #[cfg(feature = "admin-api")]
+    pub fn admin_api(&self) -> crate::server::admin::AdminApi {
+        crate::server::admin::AdminApi::new(
+            self.session_manager.clone(),
+            self.session_key_manager.clone(),
+            self.key_manager.clone(),
+            self.auth_manager.acl_manager(),
+            self.packet_router.clone(),
+            self.ip_pool.clone(),
+            self.reload_requests.clone(),
+            self.state.clone(),
+            self.warmup.clone(),
+            #[cfg(feature = "chaos")]
+            self.chaos.clone(),
+        )
+    }
 }
 
 // --- Tests ---
@@ -922,6 +1646,7 @@ mod tests {
 
         let config = ServerConfig {
             listen_addr: "127.0.0.1:8080".parse().unwrap(),
+            additional_listen_addrs: Vec::new(),
             tun_name: "tun_test".to_string(),
             subnet: "10.7.7.0/24".to_string(),
             cert_file: cert_path.clone(),
@@ -930,6 +1655,8 @@ mod tests {
             enable_obfuscation: false,
             obfuscation_method: "xor".to_string(),
             enable_padding: false,
+            padding_policy: crate::config::settings::PaddingPolicy::Random,
+            padding_bucket_size: crate::config::defaults::DEFAULT_PADDING_BUCKET_SIZE,
             key_rotation_interval: Duration::from_secs(3600),
             session_timeout: Duration::from_secs(3600),
             max_connections_per_ip: 5,
@@ -942,7 +1669,81 @@ mod tests {
             enable_remote_management: false,
             remote_security_mode: "restricted".to_string(),
             transport_security: TransportSecurity::Tls,
+            enable_chaos_mode: false,
+            acl_revoke_on_reload: false,
+            acl_revocation_rate: crate::config::defaults::DEFAULT_ACL_REVOCATION_RATE,
+            max_client_packets_per_sec: crate::config::defaults::DEFAULT_MAX_CLIENT_PACKETS_PER_SEC,
+            max_client_bytes_per_sec: crate::config::defaults::DEFAULT_MAX_CLIENT_BYTES_PER_SEC,
+            log_aggregation_window_ms: crate::config::defaults::DEFAULT_LOG_AGGREGATION_WINDOW_MS,
+            session_key_pool_size: crate::config::defaults::DEFAULT_SESSION_KEY_POOL_SIZE,
+            ws_allowed_origins: Vec::new(),
+            ws_subprotocol: None,
+            ws_max_message_size: crate::config::defaults::DEFAULT_WS_MAX_MESSAGE_SIZE,
+            ws_max_frame_size: crate::config::defaults::DEFAULT_WS_MAX_FRAME_SIZE,
+            shared_secret_retries: crate::config::defaults::DEFAULT_SHARED_SECRET_RETRIES,
+            shared_secret_retry_backoff_ms: crate::config::defaults::DEFAULT_SHARED_SECRET_RETRY_BACKOFF_MS,
+            flow_collector: None,
+            flow_max_tracked: crate::config::defaults::DEFAULT_FLOW_MAX_TRACKED,
+            flow_idle_timeout_secs: crate::config::defaults::DEFAULT_FLOW_IDLE_TIMEOUT_SECS,
+            flow_active_timeout_secs: crate::config::defaults::DEFAULT_FLOW_ACTIVE_TIMEOUT_SECS,
+            traffic_export_addr: None,
+            traffic_export_format: crate::config::settings::TrafficExportFormat::Statsd,
+            traffic_export_interval_secs: crate::config::defaults::DEFAULT_TRAFFIC_EXPORT_INTERVAL_SECS,
+            session_worker_threads: crate::config::defaults::DEFAULT_SESSION_WORKER_THREADS,
+            quiet_hours: Vec::new(),
+            max_stateful_features: None,
+            capability_queries_per_minute: 6,
+            max_key_rotation_deferral_secs: crate::config::defaults::DEFAULT_MAX_KEY_ROTATION_DEFERRAL_SECS,
+            acl_tier_policy: AclTierPolicy::Snapshot,
+            ip_release_grace_secs: crate::config::defaults::DEFAULT_IP_RELEASE_GRACE_SECS,
+            max_lease_duration_secs: crate::config::defaults::DEFAULT_MAX_LEASE_DURATION_SECS,
+            ip_reservations: Vec::new(),
+            ip_lease_file: None,
+            server_load_interval_secs: crate::config::defaults::DEFAULT_SERVER_LOAD_INTERVAL_SECS,
+            duplicate_session_policy: crate::config::settings::DuplicateSessionPolicy::AllowMultiple,
+            warmup_secs: crate::config::defaults::DEFAULT_WARMUP_SECS,
+            warmup_mode: crate::config::settings::WarmupMode::Throttle,
+            warmup_accepts_per_sec: crate::config::defaults::DEFAULT_WARMUP_ACCEPTS_PER_SEC,
+            max_key_rotations_per_sec: crate::config::defaults::DEFAULT_MAX_KEY_ROTATIONS_PER_SEC,
+            tunnel_allowed_protocols: Vec::new(),
+            source_ip_policy: crate::config::settings::SourceIpPolicy::Strict,
+            challenge_address_binding: crate::config::settings::ChallengeAddressBinding::Ip,
+            acl_deny_response: crate::config::settings::AclDenyResponse::Verbose,
+            session_record_file: None,
+            auth_audit_file: None,
+            max_handshake_data_packets: crate::config::defaults::DEFAULT_MAX_HANDSHAKE_DATA_PACKETS,
+            tun_queue_depth: crate::config::defaults::DEFAULT_TUN_QUEUE_DEPTH,
+            tun_queue_policy: crate::config::settings::TunQueuePolicy::DropTail,
+            send_queue_depth: crate::config::defaults::DEFAULT_SEND_QUEUE_DEPTH,
+            require_bound_session_key: false,
+            trace_sample_rate: 0.0,
+            max_sessions: 0,
+            metrics_listen: None,
+            admin_listen: None,
+            key_rotation_jitter_percent: crate::config::defaults::DEFAULT_KEY_ROTATION_JITTER_PERCENT,
+            idle_timeout_secs: 0,
+            max_missed_pongs: 0,
+            heartbeat_interval_secs: crate::config::defaults::DEFAULT_HEARTBEAT_INTERVAL_SECS,
+            heartbeat_rtt_threshold_ms: 0,
+            max_heartbeat_interval_secs: crate::config::defaults::DEFAULT_MAX_HEARTBEAT_INTERVAL_SECS,
+            max_sessions_per_key: 0,
+            max_connections: 0,
+            connection_queue_timeout_ms: 0,
+            challenge_batch_window_ms: 0,
+            fragment_size: 0,
+            tun_mtu: 1500,
+            mss_clamp: false,
+            alpn_protocols: Vec::new(),
+            client_ca_file: None,
+            require_client_cert: false,
+            auth_failure_limit: 10,
+            auth_failure_window_secs: 300,
+            server_key_rotation_overlap_secs: 300,
+            pow_difficulty: 0,
+            max_pow_difficulty: 0,
+            resumption_ttl_secs: 60,
             key_manager: None, // Let KeyManager be created internally if needed
+            config_file: None,
             mode: crate::config::settings::NodeMode::VPNEnabled,
         };
 
@@ -956,4 +1757,213 @@ mod tests {
 
          println!("TLS test passed.");
     }
+
+    /// Accept context backed by real managers under `dir`
+    async fn test_accept_context(dir: &std::path::Path, handshake_policy: WsHandshakePolicy) -> AcceptContext {
+        let key_manager = Arc::new(KeyManager::new(dir.join("server_key"), Duration::from_secs(3600), 100).await.unwrap());
+        let auth_manager = Arc::new(AuthManager::new(
+            dir.join("acl.json"),
+            key_manager.clone(),
+            crate::config::constants::AUTH_CHALLENGE_TIMEOUT,
+            100,
+            crate::config::settings::ChallengeAddressBinding::Ip,
+        ).await.unwrap());
+        AcceptContext {
+            tls_acceptor: None,
+            require_alpn: false,
+            key_manager,
+            auth_manager,
+            ip_pool: Arc::new(IpPoolManager::new("10.7.0.0/24", 86400).await.unwrap()),
+            session_manager: Arc::new(SessionManager::new(5, Duration::from_secs(3600))),
+            session_key_manager: Arc::new(SessionKeyManager::new(Duration::from_secs(3600), 1_000_000)),
+            network_monitor: Arc::new(NetworkMonitor::new(Duration::from_secs(5), 120)),
+            packet_router: Arc::new(PacketRouter::new(crate::config::constants::PACKET_SIZE_LIMIT, false)),
+            metrics: Arc::new(ServerMetricsCollector::new(Duration::from_secs(60), 60)),
+            origin_provider: Arc::new(NoopOriginProvider),
+            rate_limiter: Arc::new(RateLimiter::new(100, Duration::from_secs(60))),
+            failure_logs: Arc::new(ConnectionFailureLogs::new(Duration::from_secs(60))),
+            handshake_policy: Arc::new(handshake_policy),
+            worker_pool: None,
+            quiet_hours: Arc::new(QuietHours::default()),
+            warmup: Arc::new(Warmup::new(Duration::ZERO, crate::config::settings::WarmupMode::Throttle, 0)),
+            session_records: None,
+            max_stateful_features: None,
+            max_handshake_data_packets: crate::config::defaults::DEFAULT_MAX_HANDSHAKE_DATA_PACKETS,
+            require_bound_session_key: false,
+            trace_sample_rate: 0.0,
+            state: Arc::new(RwLock::new(ServerState::Running)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multiple_listeners() {
+        use futures::{SinkExt, StreamExt};
+        use solana_sdk::signature::{keypair_from_seed, Signer};
+        use tokio_tungstenite::tungstenite::Message;
+        use crate::protocol::PacketType;
+        use crate::protocol::serialization::{packet_to_ws_message, ws_message_to_packet};
+
+        let dir = tempfile::tempdir().unwrap();
+        let context = Arc::new(test_accept_context(dir.path(), WsHandshakePolicy::default()).await);
+        let metrics = context.metrics.clone();
+
+        // Two ephemeral ports served by the same managers
+        let mut addrs = Vec::new();
+        let mut accept_loops = Vec::new();
+        for _ in 0..2 {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            addrs.push(listener.local_addr().unwrap());
+            accept_loops.push(tokio::spawn(run_accept_loop(listener, context.clone())));
+        }
+        assert_ne!(addrs[0], addrs[1]);
+
+        let mut clients = Vec::new();
+        for (i, addr) in addrs.iter().enumerate() {
+            let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.unwrap();
+            let auth = PacketType::Auth {
+                public_key: keypair_from_seed(&[i as u8 + 1; 32]).unwrap().pubkey().to_string(),
+                version: "1.0.0".to_string(),
+                features: vec!["chacha20poly1305".to_string()],
+                encryption_algorithm: None,
+                nonce: format!("listener-{}", i),
+                resumption_token: None,
+                resumption_proof: None,
+                lease_duration: None,
+            };
+            ws.send(packet_to_ws_message(&auth).unwrap()).await.unwrap();
+            let reply: Message = time::timeout(Duration::from_secs(5), ws.next()).await.unwrap().unwrap().unwrap();
+            assert!(matches!(ws_message_to_packet(&reply).unwrap(), PacketType::Challenge { .. }));
+            clients.push(ws);
+        }
+
+        // Both connections are counted by the shared metrics
+        let snapshot = metrics.get_metrics().await;
+        assert_eq!(snapshot.total_connections, 2);
+        assert_eq!(snapshot.active_connections, 2);
+
+        // Aborting the accept loops releases both ports
+        for handle in &accept_loops {
+            handle.abort();
+        }
+        for handle in accept_loops {
+            assert!(handle.await.unwrap_err().is_cancelled());
+        }
+        for addr in addrs {
+            TcpListener::bind(addr).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dual_stack_wildcard_listeners() {
+        // Hosts without IPv6 cannot run this
+        if std::net::TcpListener::bind("[::1]:0").is_err() {
+            return;
+        }
+        let ipv4 = bind_listener("0.0.0.0:0".parse().unwrap()).unwrap();
+        let port = ipv4.local_addr().unwrap().port();
+        let ipv6 = bind_listener(SocketAddr::new("::".parse().unwrap(), port)).unwrap();
+        assert_eq!(ipv6.local_addr().unwrap().port(), port);
+
+        // Each wildcard takes its own family's connections
+        let (v4_client, v4_accepted) = tokio::join!(
+            tokio::net::TcpStream::connect(("127.0.0.1", port)),
+            ipv4.accept(),
+        );
+        assert!(v4_client.is_ok() && v4_accepted.unwrap().1.is_ipv4());
+        let (v6_client, v6_accepted) = tokio::join!(
+            tokio::net::TcpStream::connect(("::1", port)),
+            ipv6.accept(),
+        );
+        assert!(v6_client.is_ok() && v6_accepted.unwrap().1.is_ipv6());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_frame_is_refused() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message;
+        use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+
+        let dir = tempfile::tempdir().unwrap();
+        let policy = WsHandshakePolicy::default().with_message_limits(4096, 1024);
+        let context = Arc::new(test_accept_context(dir.path(), policy).await);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accept_loop = tokio::spawn(run_accept_loop(listener, context.clone()));
+
+        // A frame over the limit is answered with 1009 and the connection dropped
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.unwrap();
+        ws.send(Message::Binary(vec![0u8; 2048])).await.unwrap();
+        let reply = time::timeout(Duration::from_secs(5), ws.next()).await.unwrap();
+        match reply {
+            Some(Ok(Message::Close(Some(frame)))) => assert_eq!(frame.code, CloseCode::Size),
+            other => panic!("expected a Message Too Big close, got {:?}", other),
+        }
+
+        // The server keeps accepting other clients
+        assert!(tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.is_ok());
+        accept_loop.abort();
+    }
+
+    #[tokio::test]
+    async fn test_handshake_failures_by_cause() {
+        use rustls::{ClientConfig, ServerName};
+        use tokio::io::AsyncWriteExt;
+        use tokio::net::TcpStream;
+        use crate::server::metrics::{HandshakeFailure, HandshakePhase};
+
+        async fn wait_for_failure(metrics: &ServerMetricsCollector, phase: HandshakePhase, reason: HandshakeFailure) {
+            time::timeout(Duration::from_secs(5), async {
+                while metrics.get_metrics().await.handshake_failures.get(phase, reason) == 0 {
+                    time::sleep(Duration::from_millis(10)).await;
+                }
+            }).await.unwrap();
+        }
+
+        let testdata = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/server/testdata");
+        let tls_dir = tempfile::tempdir().unwrap();
+        let mut tls_context = test_accept_context(tls_dir.path(), WsHandshakePolicy::default()).await;
+        tls_context.tls_acceptor = Some(Arc::new(TlsAcceptor::from(VpnServer::tls_server_config(
+            &testdata.join("tls_server.crt"),
+            &testdata.join("tls_server.key"),
+            &[],
+            None,
+        ).unwrap())));
+        let tls_context = Arc::new(tls_context);
+        let tls_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let tls_addr = tls_listener.local_addr().unwrap();
+        let tls_loop = tokio::spawn(run_accept_loop(tls_listener, tls_context.clone()));
+
+        // Plain HTTP on the TLS port is a malformed handshake, the typical scanner
+        let mut stream = TcpStream::connect(tls_addr).await.unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        wait_for_failure(&tls_context.metrics, HandshakePhase::Tls, HandshakeFailure::Malformed).await;
+
+        // A TLS client that does not trust the certificate aborts with an alert
+        let client_config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(RootCertStore::empty())
+            .with_no_client_auth();
+        let connector = tokio_rustls::TlsConnector::from(Arc::new(client_config));
+        let tcp = TcpStream::connect(tls_addr).await.unwrap();
+        assert!(connector.connect(ServerName::try_from("localhost").unwrap(), tcp).await.is_err());
+        wait_for_failure(&tls_context.metrics, HandshakePhase::Tls, HandshakeFailure::TlsAlert).await;
+
+        let metrics = tls_context.metrics.get_metrics().await;
+        assert_eq!(metrics.handshake_failures.phase_total(HandshakePhase::Tls), 2);
+        assert_eq!(metrics.handshake_failures.phase_total(HandshakePhase::WebSocket), 0);
+        assert_eq!(metrics.active_handshakes, 0);
+        tls_loop.abort();
+
+        // An HTTP request without the upgrade headers fails the WebSocket upgrade
+        let raw_dir = tempfile::tempdir().unwrap();
+        let raw_context = Arc::new(test_accept_context(raw_dir.path(), WsHandshakePolicy::default()).await);
+        let raw_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let raw_addr = raw_listener.local_addr().unwrap();
+        let raw_loop = tokio::spawn(run_accept_loop(raw_listener, raw_context.clone()));
+        let mut stream = TcpStream::connect(raw_addr).await.unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        wait_for_failure(&raw_context.metrics, HandshakePhase::WebSocket, HandshakeFailure::Malformed).await;
+        assert_eq!(raw_context.metrics.get_metrics().await.handshake_failures.phase_total(HandshakePhase::WebSocket), 1);
+        raw_loop.abort();
+    }
 }
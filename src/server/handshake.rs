@@ -0,0 +1,191 @@
+// src/server/handshake.rs
+//! WebSocket upgrade validation.
+//!
+//! This module checks the `Origin` header and the requested subprotocol
+//! during the WebSocket upgrade, protecting browser clients against
+//! cross-site WebSocket hijacking. Validation is disabled by default since
+//! native clients do not send an `Origin`.
+//!
+//! The policy also caps the size of frames and messages a client may send.
+//! Tungstenite checks a frame's declared length before reading its payload,
+//! so an oversized frame fails fast instead of being buffered.
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::{header, HeaderValue, StatusCode};
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tokio_tungstenite::WebSocketStream;
+
+use crate::config::defaults::{DEFAULT_WS_MAX_FRAME_SIZE, DEFAULT_WS_MAX_MESSAGE_SIZE};
+use crate::server::core::ServerError;
+
+/// Reason a WebSocket upgrade was rejected
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum HandshakeRejection {
+    #[error("Origin not allowed: {0}")]
+    OriginNotAllowed(String),
+
+    #[error("Missing Origin header")]
+    MissingOrigin,
+
+    #[error("Required subprotocol {0} not offered")]
+    SubprotocolMissing(String),
+}
+
+/// Origin, subprotocol and size requirements for WebSocket connections
+#[derive(Debug, Clone)]
+pub struct WsHandshakePolicy {
+    /// Allowed `Origin` values (empty = any origin)
+    allowed_origins: Vec<String>,
+    /// Subprotocol the client must offer
+    required_subprotocol: Option<String>,
+    /// Largest message a client may send
+    max_message_size: usize,
+    /// Largest single frame a client may send
+    max_frame_size: usize,
+}
+
+impl Default for WsHandshakePolicy {
+    fn default() -> Self {
+        Self::new(Vec::new(), None)
+    }
+}
+
+impl WsHandshakePolicy {
+    /// Create a new handshake policy
+    pub fn new(allowed_origins: Vec<String>, required_subprotocol: Option<String>) -> Self {
+        Self {
+            allowed_origins,
+            required_subprotocol,
+            max_message_size: DEFAULT_WS_MAX_MESSAGE_SIZE,
+            max_frame_size: DEFAULT_WS_MAX_FRAME_SIZE,
+        }
+    }
+
+    /// Limit the size of messages and frames accepted from clients
+    pub fn with_message_limits(mut self, max_message_size: usize, max_frame_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    /// Tungstenite configuration applied to accepted connections
+    fn ws_config(&self) -> WebSocketConfig {
+        WebSocketConfig {
+            max_message_size: Some(self.max_message_size),
+            max_frame_size: Some(self.max_frame_size),
+            ..WebSocketConfig::default()
+        }
+    }
+
+    /// Check whether any validation is configured
+    pub fn is_enabled(&self) -> bool {
+        !self.allowed_origins.is_empty() || self.required_subprotocol.is_some()
+    }
+
+    /// Validate an upgrade request
+    pub fn check(&self, request: &Request) -> Result<(), HandshakeRejection> {
+        if !self.allowed_origins.is_empty() {
+            let origin = request.headers().get(header::ORIGIN)
+                .and_then(|v| v.to_str().ok())
+                .ok_or(HandshakeRejection::MissingOrigin)?;
+            if !self.allowed_origins.iter().any(|allowed| allowed.eq_ignore_ascii_case(origin)) {
+                return Err(HandshakeRejection::OriginNotAllowed(origin.to_string()));
+            }
+        }
+
+        if let Some(required) = &self.required_subprotocol {
+            let offered = request.headers().get_all(header::SEC_WEBSOCKET_PROTOCOL)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(','))
+                .any(|p| p.trim() == required);
+            if !offered {
+                return Err(HandshakeRejection::SubprotocolMissing(required.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Upgrade a stream to a WebSocket, enforcing this policy.
+    ///
+    /// A rejected upgrade returns the reason in the error so callers can
+    /// account for it before any authentication takes place.
+    pub async fn accept<S>(&self, stream: S) -> Result<WebSocketStream<S>, (ServerError, Option<HandshakeRejection>)>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        if !self.is_enabled() {
+            return tokio_tungstenite::accept_async_with_config(stream, Some(self.ws_config())).await
+                .map_err(|e| (ServerError::WebSocket(e), None));
+        }
+
+        let mut rejection = None;
+        let callback = |request: &Request, mut response: Response| -> Result<Response, ErrorResponse> {
+            match self.check(request) {
+                Ok(()) => {
+                    if let Some(protocol) = &self.required_subprotocol {
+                        if let Ok(value) = HeaderValue::from_str(protocol) {
+                            response.headers_mut().insert(header::SEC_WEBSOCKET_PROTOCOL, value);
+                        }
+                    }
+                    Ok(response)
+                }
+                Err(reason) => {
+                    let mut error = ErrorResponse::new(Some(reason.to_string()));
+                    *error.status_mut() = StatusCode::FORBIDDEN;
+                    rejection = Some(reason);
+                    Err(error)
+                }
+            }
+        };
+
+        let config = self.ws_config();
+        let result = tokio_tungstenite::accept_hdr_async_with_config(stream, callback, Some(config)).await;
+        result.map_err(|e| (ServerError::WebSocket(e), rejection))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(origin: Option<&str>, protocols: Option<&str>) -> Request {
+        let mut builder = Request::builder().uri("wss://vpn.example.com/");
+        if let Some(origin) = origin {
+            builder = builder.header(header::ORIGIN, origin);
+        }
+        if let Some(protocols) = protocols {
+            builder = builder.header(header::SEC_WEBSOCKET_PROTOCOL, protocols);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn test_handshake_policy() {
+        // Disabled by default
+        let policy = WsHandshakePolicy::default();
+        assert!(!policy.is_enabled());
+        assert!(policy.check(&request(None, None)).is_ok());
+
+        let policy = WsHandshakePolicy::new(
+            vec!["https://app.aeronyx.network".to_string()],
+            Some("aeronyx.v1".to_string()),
+        );
+        assert!(policy.is_enabled());
+        assert!(policy.check(&request(Some("https://app.aeronyx.network"), Some("chat, aeronyx.v1"))).is_ok());
+        assert_eq!(
+            policy.check(&request(None, Some("aeronyx.v1"))),
+            Err(HandshakeRejection::MissingOrigin)
+        );
+        assert_eq!(
+            policy.check(&request(Some("https://evil.example"), Some("aeronyx.v1"))),
+            Err(HandshakeRejection::OriginNotAllowed("https://evil.example".to_string()))
+        );
+        assert_eq!(
+            policy.check(&request(Some("https://app.aeronyx.network"), Some("chat"))),
+            Err(HandshakeRejection::SubprotocolMissing("aeronyx.v1".to_string()))
+        );
+    }
+}
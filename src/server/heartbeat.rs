@@ -0,0 +1,123 @@
+// src/server/heartbeat.rs
+//! Heartbeat scheduling for established sessions.
+//!
+//! The server pings each session at a configurable interval. With adaptive
+//! backoff enabled, a session whose last measured RTT exceeds a threshold
+//! has its interval doubled after each ping, up to a maximum, and halved
+//! back towards the base once the RTT recovers. Congested or battery-bound
+//! links then see less heartbeat chatter.
+//!
+//! Whatever the RTT, the interval never exceeds a third of the idle
+//! timeout, so an idle session still gets pinged before it is dropped.
+//! Dead-connection detection counts unanswered pings, so it keeps working
+//! and simply takes proportionally longer at a longer interval.
+
+use std::time::Duration;
+
+use crate::config::defaults::{DEFAULT_HEARTBEAT_INTERVAL_SECS, DEFAULT_MAX_HEARTBEAT_INTERVAL_SECS};
+
+/// When the server pings an established session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeartbeatPolicy {
+    /// Ping interval on a healthy link
+    pub interval: Duration,
+    /// RTT above which the interval backs off (None = fixed interval)
+    pub rtt_threshold: Option<Duration>,
+    /// Longest interval backoff may reach
+    pub max_interval: Duration,
+}
+
+impl Default for HeartbeatPolicy {
+    fn default() -> Self {
+        Self::fixed(Duration::from_secs(DEFAULT_HEARTBEAT_INTERVAL_SECS))
+    }
+}
+
+impl HeartbeatPolicy {
+    /// Ping every `interval` regardless of RTT
+    pub fn fixed(interval: Duration) -> Self {
+        Self {
+            interval,
+            rtt_threshold: None,
+            max_interval: interval.max(Duration::from_secs(DEFAULT_MAX_HEARTBEAT_INTERVAL_SECS)),
+        }
+    }
+
+    /// Back off up to `max_interval` while the RTT exceeds `rtt_threshold`
+    pub fn with_backoff(mut self, rtt_threshold: Duration, max_interval: Duration) -> Self {
+        self.rtt_threshold = Some(rtt_threshold);
+        self.max_interval = max_interval.max(self.interval);
+        self
+    }
+
+    /// Interval for the first pings of a session
+    pub fn initial_interval(&self, idle_timeout: Duration) -> Duration {
+        within_idle_window(self.interval, idle_timeout)
+    }
+
+    /// Interval after a ping, given the current interval and the last measured RTT
+    pub fn next_interval(&self, current: Duration, idle_timeout: Duration, rtt_ms: Option<u64>) -> Duration {
+        let next = match (self.rtt_threshold, rtt_ms) {
+            (Some(threshold), Some(rtt)) if rtt > threshold.as_millis() as u64 => (current * 2).min(self.max_interval),
+            _ => (current / 2).max(self.interval),
+        };
+        within_idle_window(next, idle_timeout)
+    }
+}
+
+/// Cap `interval` so an idle session gets at least three pings before it times out
+fn within_idle_window(interval: Duration, idle_timeout: Duration) -> Duration {
+    if idle_timeout.is_zero() {
+        interval
+    } else {
+        interval.min(idle_timeout / 3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_IDLE_TIMEOUT: Duration = Duration::ZERO;
+
+    #[test]
+    fn test_fixed_interval() {
+        let policy = HeartbeatPolicy::fixed(Duration::from_secs(60));
+        assert_eq!(policy.initial_interval(NO_IDLE_TIMEOUT), Duration::from_secs(60));
+        // RTT is ignored without backoff
+        let next = policy.next_interval(Duration::from_secs(60), NO_IDLE_TIMEOUT, Some(5_000));
+        assert_eq!(next, Duration::from_secs(60));
+
+        // The idle timeout still bounds the interval
+        assert_eq!(policy.initial_interval(Duration::from_secs(90)), Duration::from_secs(30));
+        assert_eq!(HeartbeatPolicy::default().interval, Duration::from_secs(DEFAULT_HEARTBEAT_INTERVAL_SECS));
+    }
+
+    #[test]
+    fn test_adaptive_backoff() {
+        let policy = HeartbeatPolicy::fixed(Duration::from_secs(20))
+            .with_backoff(Duration::from_millis(500), Duration::from_secs(100));
+        let base = policy.initial_interval(NO_IDLE_TIMEOUT);
+
+        // Slow link: doubles up to the maximum
+        let mut interval = base;
+        let mut steps = Vec::new();
+        for _ in 0..4 {
+            interval = policy.next_interval(interval, NO_IDLE_TIMEOUT, Some(800));
+            steps.push(interval.as_secs());
+        }
+        assert_eq!(steps, vec![40, 80, 100, 100]);
+
+        // At or under the threshold, or unmeasured: halves back to the base
+        interval = policy.next_interval(interval, NO_IDLE_TIMEOUT, Some(500));
+        assert_eq!(interval, Duration::from_secs(50));
+        interval = policy.next_interval(interval, NO_IDLE_TIMEOUT, None);
+        assert_eq!(interval, Duration::from_secs(25));
+        interval = policy.next_interval(interval, NO_IDLE_TIMEOUT, Some(10));
+        assert_eq!(interval, base);
+
+        // Backoff never outgrows the idle window
+        let idle_timeout = Duration::from_secs(150);
+        assert_eq!(policy.next_interval(Duration::from_secs(40), idle_timeout, Some(800)), Duration::from_secs(50));
+    }
+}
@@ -5,13 +5,20 @@
 //! and reporting server performance metrics.
 
 // Remove unused import: HashMap
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write as _;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::RwLock;
 use tokio::time;
-// Remove unused imports: debug, info
-use tracing::warn; // Keep warn
+use tracing::{debug, warn};
+
+use crate::config::constants::{HANDSHAKE_DURATION_BUCKETS, MAX_CLIENT_VERSION_LABELS, MAX_ORIGIN_LABELS, METRICS_REQUEST_TIMEOUT};
+use crate::network::ip_pool::LeaseExpiryBuckets;
+use crate::network::origin::ConnectionOrigin;
+use crate::server::negotiation::NegotiatedCapabilities;
 
 // Remove unused import: utils
 // --- Structs ServerMetrics, ConnectionRateMetrics remain the same ---
@@ -31,6 +38,12 @@ pub struct ServerMetrics {
     pub auth_successes: u64,
     /// Authentication failures
     pub auth_failures: u64,
+    /// Challenge requests and responses refused by the per-IP failure limit
+    pub auth_rate_limited: u64,
+    /// Sessions resumed from a resumption token
+    pub sessions_resumed: u64,
+    /// Resumption tokens refused, after which the client fell back to a challenge
+    pub resumptions_refused: u64,
     /// Average CPU usage (percentage)
     pub cpu_usage: f64,
     /// Memory usage (percentage)
@@ -41,6 +54,197 @@ pub struct ServerMetrics {
     pub active_handshakes: usize,
     /// Total TLS handshakes
     pub total_handshakes: u64,
+    /// TLS handshakes that completed successfully
+    pub completed_handshakes: u64,
+    /// Duration of completed TLS handshakes
+    pub handshake_duration: DurationHistogram,
+    /// Failed TLS handshakes and WebSocket upgrades by phase and cause
+    pub handshake_failures: HandshakeFailureCounts,
+    /// Authentication outcomes by client-reported version
+    pub auth_by_version: BTreeMap<String, VersionAuthCounts>,
+    /// IP addresses currently allocated from the pool
+    pub allocated_ips: usize,
+    /// Capabilities active across current sessions
+    pub capabilities: CapabilityGauges,
+    /// Active sessions with a known origin, by (country, ASN) label values
+    pub sessions_by_origin: BTreeMap<(String, String), usize>,
+    /// Inbound packets dropped for exceeding the per-client packet rate
+    pub packet_rate_drops: u64,
+    /// Inbound packets dropped for exceeding the per-client byte rate
+    pub byte_rate_drops: u64,
+    /// Inner packets dropped by the tunnel protocol allowlist (including malformed headers)
+    pub protocol_drops: u64,
+    /// Inner packets whose source was not the session's tunnel IP (including malformed headers)
+    pub source_ip_mismatches: u64,
+    /// Inner packets dropped because the TUN write queue was full
+    pub tun_queue_drops: u64,
+    /// Packets waiting in the TUN write queue
+    pub tun_queue_depth: usize,
+    /// Outbound messages dropped from ended sessions' send queues
+    pub tx_dropped: u64,
+    /// Approximate memory held by all active sessions (see `SessionManager::session_memory`)
+    pub session_memory_bytes: usize,
+    /// WebSocket upgrades rejected for a disallowed Origin or missing subprotocol
+    pub ws_handshake_rejections: u64,
+    /// Connections refused because the active session cap was reached
+    pub capacity_rejections: u64,
+    /// Authentications refused because the key was at its concurrent session limit
+    pub key_session_rejections: u64,
+    /// Connections refused because the global connection limit was reached
+    pub connections_rejected: u64,
+    /// Shared secret derivation attempts that failed transiently
+    pub secret_derivation_transient_failures: u64,
+    /// Shared secret derivations that failed permanently (e.g. malformed key)
+    pub secret_derivation_permanent_failures: u64,
+    /// Dynamic IP leases by time until expiry
+    pub lease_expiry: LeaseExpiryBuckets,
+    /// Session key rotations since startup
+    pub key_rotations: u64,
+    /// Session key rotations per second over the last collection interval
+    pub key_rotation_rate: f64,
+}
+
+/// Gauges of the capabilities actually negotiated by active sessions
+#[derive(Debug, Clone, Default)]
+pub struct CapabilityGauges {
+    /// Active sessions per cipher
+    pub ciphers: BTreeMap<&'static str, usize>,
+    /// Active sessions with compression enabled
+    pub compression_on: usize,
+    /// Active sessions with compression disabled
+    pub compression_off: usize,
+    /// Active sessions with padding enabled
+    pub padding_on: usize,
+    /// Active sessions with padding disabled
+    pub padding_off: usize,
+    /// Active sessions using WebSocket control-frame keepalive
+    pub ws_keepalive: usize,
+}
+
+impl CapabilityGauges {
+    /// Add or remove one session's capabilities from the gauges
+    fn apply(&mut self, caps: &NegotiatedCapabilities, add: bool) {
+        fn adjust(gauge: &mut usize, add: bool) {
+            *gauge = if add { gauge.saturating_add(1) } else { gauge.saturating_sub(1) };
+        }
+
+        let cipher = self.ciphers.entry(caps.encryption_algorithm.as_str()).or_insert(0);
+        adjust(cipher, add);
+        if caps.compression {
+            adjust(&mut self.compression_on, add);
+        } else {
+            adjust(&mut self.compression_off, add);
+        }
+        if caps.padding {
+            adjust(&mut self.padding_on, add);
+        } else {
+            adjust(&mut self.padding_off, add);
+        }
+        if caps.ws_keepalive {
+            adjust(&mut self.ws_keepalive, add);
+        }
+    }
+}
+
+/// Cumulative histogram of durations, bucketed by `HANDSHAKE_DURATION_BUCKETS`
+#[derive(Debug, Clone, Default)]
+pub struct DurationHistogram {
+    /// Observations at or below each bucket bound (non-cumulative)
+    pub buckets: [u64; HANDSHAKE_DURATION_BUCKETS.len()],
+    /// Total observations
+    pub count: u64,
+    /// Sum of all observations in seconds
+    pub sum_secs: f64,
+}
+
+impl DurationHistogram {
+    /// Record one observation
+    pub fn observe(&mut self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        if let Some(idx) = HANDSHAKE_DURATION_BUCKETS.iter().position(|bound| secs <= *bound) {
+            self.buckets[idx] += 1;
+        }
+        self.count += 1;
+        self.sum_secs += secs;
+    }
+}
+
+/// Connection setup step a handshake failed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandshakePhase {
+    /// TLS handshake
+    Tls = 0,
+    /// WebSocket upgrade (over TLS or raw TCP)
+    WebSocket = 1,
+}
+
+impl HandshakePhase {
+    pub const ALL: [Self; 2] = [Self::Tls, Self::WebSocket];
+
+    /// Metric label value
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Tls => "tls",
+            Self::WebSocket => "websocket",
+        }
+    }
+}
+
+/// Why a handshake failed.
+///
+/// Scanners and port probes mostly show up as `Malformed`, while
+/// `TlsAlert` points at a real protocol, certificate or ALPN mismatch
+/// with a client that does speak TLS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandshakeFailure {
+    /// The peers could not agree on TLS parameters or certificates
+    TlsAlert = 0,
+    /// The handshake did not finish in time
+    Timeout = 1,
+    /// The peer did not speak the protocol, or hung up midway
+    Malformed = 2,
+}
+
+impl HandshakeFailure {
+    pub const ALL: [Self; 3] = [Self::TlsAlert, Self::Timeout, Self::Malformed];
+
+    /// Metric label value
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::TlsAlert => "tls_alert",
+            Self::Timeout => "timeout",
+            Self::Malformed => "malformed",
+        }
+    }
+}
+
+/// Handshake failure counters for every phase and cause
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HandshakeFailureCounts {
+    counts: [[u64; HandshakeFailure::ALL.len()]; HandshakePhase::ALL.len()],
+}
+
+impl HandshakeFailureCounts {
+    /// Failures recorded for `phase` and `reason`
+    pub fn get(&self, phase: HandshakePhase, reason: HandshakeFailure) -> u64 {
+        self.counts[phase as usize][reason as usize]
+    }
+
+    /// Failures recorded for `phase`, whatever the cause
+    pub fn phase_total(&self, phase: HandshakePhase) -> u64 {
+        self.counts[phase as usize].iter().sum()
+    }
+
+    fn record(&mut self, phase: HandshakePhase, reason: HandshakeFailure) {
+        self.counts[phase as usize][reason as usize] += 1;
+    }
+}
+
+/// Authentication outcomes for one client version
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VersionAuthCounts {
+    pub successes: u64,
+    pub failures: u64,
 }
 
 impl Default for ServerMetrics {
@@ -53,11 +257,38 @@ impl Default for ServerMetrics {
             bytes_received: 0,
             auth_successes: 0,
             auth_failures: 0,
+            auth_rate_limited: 0,
+            sessions_resumed: 0,
+            resumptions_refused: 0,
             cpu_usage: 0.0,
             memory_usage: 0.0,
             load_average: (0.0, 0.0, 0.0),
             active_handshakes: 0,
             total_handshakes: 0,
+            completed_handshakes: 0,
+            handshake_duration: DurationHistogram::default(),
+            handshake_failures: HandshakeFailureCounts::default(),
+            auth_by_version: BTreeMap::new(),
+            allocated_ips: 0,
+            capabilities: CapabilityGauges::default(),
+            sessions_by_origin: BTreeMap::new(),
+            packet_rate_drops: 0,
+            byte_rate_drops: 0,
+            protocol_drops: 0,
+            source_ip_mismatches: 0,
+            tun_queue_drops: 0,
+            tun_queue_depth: 0,
+            tx_dropped: 0,
+            session_memory_bytes: 0,
+            ws_handshake_rejections: 0,
+            capacity_rejections: 0,
+            key_session_rejections: 0,
+            connections_rejected: 0,
+            secret_derivation_transient_failures: 0,
+            secret_derivation_permanent_failures: 0,
+            lease_expiry: LeaseExpiryBuckets::default(),
+            key_rotations: 0,
+            key_rotation_rate: 0.0,
         }
     }
 }
@@ -72,6 +303,8 @@ pub struct ConnectionRateMetrics {
     pub auth_attempts: f64,
     /// Data throughput (bytes/sec)
     pub throughput: f64,
+    /// Session key rotations per second
+    pub key_rotations: f64,
 }
 
 /// Performance monitoring for the server
@@ -182,18 +415,21 @@ impl ServerMetricsCollector {
                      let auth_total = metrics_guard.auth_successes + metrics_guard.auth_failures;
                      let last_auth_total = last_metrics.auth_successes + last_metrics.auth_failures;
                      let auth_diff = auth_total.saturating_sub(last_auth_total);
+                     let rotation_diff = metrics_guard.key_rotations.saturating_sub(last_metrics.key_rotations);
 
 
                      let bytes_sent_rate = if duration_secs > 0.0 { bytes_sent_diff as f64 / duration_secs } else { 0.0 };
                      let bytes_received_rate = if duration_secs > 0.0 { bytes_received_diff as f64 / duration_secs } else { 0.0 };
                      let connection_rate = if duration_secs > 0.0 { connection_diff as f64 / duration_secs } else { 0.0 };
                      let auth_rate = if duration_secs > 0.0 { auth_diff as f64 / duration_secs } else { 0.0 };
+                     let rotation_rate = if duration_secs > 0.0 { rotation_diff as f64 / duration_secs } else { 0.0 };
 
 
                      // Update system metrics in the current state
                      metrics_guard.cpu_usage = cpu;
                      metrics_guard.memory_usage = memory;
                      metrics_guard.load_average = load;
+                     metrics_guard.key_rotation_rate = rotation_rate;
 
 
                      // Store the current metrics in history
@@ -213,6 +449,7 @@ impl ServerMetricsCollector {
                          new_connections: connection_rate,
                          auth_attempts: auth_rate,
                          throughput: bytes_sent_rate + bytes_received_rate,
+                         key_rotations: rotation_rate,
                      };
                       { // Scope for rate history lock
                           let mut rate_history_guard = rate_history_clone.write().await;
@@ -292,6 +529,22 @@ impl ServerMetricsCollector {
         metrics.auth_failures += 1;
     }
 
+    /// Record an authentication step refused by the per-IP failure limit
+    pub async fn record_auth_rate_limited(&self) {
+        let mut metrics = self.metrics.write().await;
+        metrics.auth_rate_limited += 1;
+    }
+
+    /// Record a session resumption attempt
+    pub async fn record_session_resumption(&self, resumed: bool) {
+        let mut metrics = self.metrics.write().await;
+        if resumed {
+            metrics.sessions_resumed += 1;
+        } else {
+            metrics.resumptions_refused += 1;
+        }
+    }
+
     /// Record bytes sent
     pub async fn record_bytes_sent(&self, bytes: u64) {
         let mut metrics = self.metrics.write().await;
@@ -311,10 +564,179 @@ impl ServerMetricsCollector {
         metrics.total_handshakes += 1;
     }
 
-    /// Record TLS handshake completion
-    pub async fn record_handshake_complete(&self) {
+    /// Record TLS handshake completion and how long it took
+    pub async fn record_handshake_complete(&self, elapsed: Duration) {
         let mut metrics = self.metrics.write().await;
         metrics.active_handshakes = metrics.active_handshakes.saturating_sub(1);
+        metrics.completed_handshakes += 1;
+        metrics.handshake_duration.observe(elapsed);
+    }
+
+    /// Record a failed TLS handshake or WebSocket upgrade.
+    ///
+    /// A TLS failure also ends the handshake counted by `record_handshake_start`.
+    pub async fn record_handshake_failure(&self, phase: HandshakePhase, reason: HandshakeFailure) {
+        let mut metrics = self.metrics.write().await;
+        if phase == HandshakePhase::Tls {
+            metrics.active_handshakes = metrics.active_handshakes.saturating_sub(1);
+        }
+        metrics.handshake_failures.record(phase, reason);
+    }
+
+    /// Record an authentication outcome for a client-reported version
+    ///
+    /// Only attempts that got far enough to send an Auth packet have a version;
+    /// the totals from `record_auth_success`/`record_auth_failure` still count all of them.
+    /// Past `MAX_CLIENT_VERSION_LABELS` distinct versions, new ones are folded into `other`.
+    pub async fn record_auth_version(&self, version: &str, success: bool) {
+        let mut metrics = self.metrics.write().await;
+        let key = if metrics.auth_by_version.contains_key(version)
+            || metrics.auth_by_version.len() < MAX_CLIENT_VERSION_LABELS
+        {
+            version.to_string()
+        } else {
+            "other".to_string()
+        };
+        let counts = metrics.auth_by_version.entry(key).or_default();
+        if success {
+            counts.successes += 1;
+        } else {
+            counts.failures += 1;
+        }
+    }
+
+    /// Update the allocated IP gauge
+    pub async fn record_allocated_ips(&self, allocated: usize) {
+        let mut metrics = self.metrics.write().await;
+        metrics.allocated_ips = allocated;
+    }
+
+    /// Record an inbound packet dropped by the per-client packet rate limit
+    pub async fn record_packet_rate_drop(&self) {
+        let mut metrics = self.metrics.write().await;
+        metrics.packet_rate_drops += 1;
+    }
+
+    /// Record an inbound packet dropped by the per-client byte rate limit
+    pub async fn record_byte_rate_drop(&self) {
+        let mut metrics = self.metrics.write().await;
+        metrics.byte_rate_drops += 1;
+    }
+
+    /// Record an inner packet dropped by the tunnel protocol allowlist
+    pub async fn record_protocol_drop(&self) {
+        let mut metrics = self.metrics.write().await;
+        metrics.protocol_drops += 1;
+    }
+
+    /// Record an inner packet whose source was not the session's tunnel IP
+    pub async fn record_source_ip_mismatch(&self) {
+        let mut metrics = self.metrics.write().await;
+        metrics.source_ip_mismatches += 1;
+    }
+
+    /// Record an inner packet dropped because the TUN write queue was full
+    pub async fn record_tun_queue_drop(&self) {
+        let mut metrics = self.metrics.write().await;
+        metrics.tun_queue_drops += 1;
+    }
+
+    /// Update the TUN write queue depth gauge
+    pub async fn record_tun_queue_depth(&self, depth: usize) {
+        let mut metrics = self.metrics.write().await;
+        metrics.tun_queue_depth = depth;
+    }
+
+    /// Record outbound messages a session dropped because its client fell behind
+    pub async fn record_tx_dropped(&self, dropped: u64) {
+        if dropped > 0 {
+            let mut metrics = self.metrics.write().await;
+            metrics.tx_dropped += dropped;
+        }
+    }
+
+    /// Update the session memory gauge
+    pub async fn record_session_memory(&self, bytes: usize) {
+        let mut metrics = self.metrics.write().await;
+        metrics.session_memory_bytes = bytes;
+    }
+
+    /// Record a WebSocket upgrade rejected by the Origin/subprotocol policy
+    pub async fn record_ws_handshake_rejection(&self) {
+        let mut metrics = self.metrics.write().await;
+        metrics.ws_handshake_rejections += 1;
+    }
+
+    /// Record an authentication refused at the per-key session limit
+    pub async fn record_key_session_rejection(&self) {
+        let mut metrics = self.metrics.write().await;
+        metrics.key_session_rejections += 1;
+    }
+
+    /// Record a connection refused at the active session cap
+    pub async fn record_capacity_rejection(&self) {
+        let mut metrics = self.metrics.write().await;
+        metrics.capacity_rejections += 1;
+    }
+
+    /// Record a connection refused at the global connection limit
+    pub async fn record_connection_rejected(&self) {
+        let mut metrics = self.metrics.write().await;
+        metrics.connections_rejected += 1;
+    }
+
+    /// Record shared secret derivation failures by kind
+    pub async fn record_secret_derivation_failures(&self, transient: u64, permanent: u64) {
+        let mut metrics = self.metrics.write().await;
+        metrics.secret_derivation_transient_failures += transient;
+        metrics.secret_derivation_permanent_failures += permanent;
+    }
+
+    /// Record a completed session key rotation
+    pub async fn record_key_rotation(&self) {
+        let mut metrics = self.metrics.write().await;
+        metrics.key_rotations += 1;
+    }
+
+    /// Update the lease expiry gauges
+    pub async fn record_lease_expiry(&self, buckets: LeaseExpiryBuckets) {
+        let mut metrics = self.metrics.write().await;
+        metrics.lease_expiry = buckets;
+    }
+
+    /// Record the negotiated capabilities of a newly established session
+    pub async fn record_session_capabilities(&self, caps: &NegotiatedCapabilities) {
+        let mut metrics = self.metrics.write().await;
+        metrics.capabilities.apply(caps, true);
+    }
+
+    /// Remove the negotiated capabilities of a closed session
+    pub async fn release_session_capabilities(&self, caps: &NegotiatedCapabilities) {
+        let mut metrics = self.metrics.write().await;
+        metrics.capabilities.apply(caps, false);
+    }
+
+    /// Count a newly established session under its origin, returning the
+    /// label values it was counted under for `release_session_origin`.
+    ///
+    /// Past `MAX_ORIGIN_LABELS` distinct origins, new ones are folded into `other`.
+    pub async fn record_session_origin(&self, origin: &ConnectionOrigin) -> (String, String) {
+        let mut metrics = self.metrics.write().await;
+        let key = origin_key(&metrics.sessions_by_origin, origin);
+        *metrics.sessions_by_origin.entry(key.clone()).or_insert(0) += 1;
+        key
+    }
+
+    /// Remove a closed session from the origin gauge, dropping the label
+    /// once no session is left under it so the budget is freed
+    pub async fn release_session_origin(&self, key: &(String, String)) {
+        let mut metrics = self.metrics.write().await;
+        if let Some(count) = metrics.sessions_by_origin.get_mut(key) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                metrics.sessions_by_origin.remove(key);
+            }
+        }
     }
 
     // --- Getters remain similar, ensure they acquire read lock ---
@@ -366,6 +788,8 @@ impl ServerMetricsCollector {
         report.push_str("\nAuthentication:\n");
         report.push_str(&format!("  Successful: {}\n", metrics.auth_successes));
         report.push_str(&format!("  Failed: {}\n", metrics.auth_failures));
+        report.push_str(&format!("  Rate Limited: {}\n", metrics.auth_rate_limited));
+        report.push_str(&format!("  Resumed: {} ({} refused)\n", metrics.sessions_resumed, metrics.resumptions_refused));
         let auth_total = metrics.auth_successes + metrics.auth_failures;
         let auth_success_rate = if auth_total > 0 {
             (metrics.auth_successes as f64 / auth_total as f64) * 100.0
@@ -388,10 +812,186 @@ impl ServerMetricsCollector {
         report.push_str("\nTLS Handshakes:\n");
         report.push_str(&format!("  Active: {}\n", metrics.active_handshakes));
         report.push_str(&format!("  Total: {}\n", metrics.total_handshakes));
+        for phase in HandshakePhase::ALL {
+            let failures = &metrics.handshake_failures;
+            let causes: Vec<String> = HandshakeFailure::ALL.iter()
+                .map(|reason| format!("{} {}", failures.get(phase, *reason), reason.label()))
+                .collect();
+            report.push_str(&format!("  Failed ({}): {} ({})\n", phase.label(), failures.phase_total(phase), causes.join(", ")));
+        }
+        report.push_str(&format!("  WebSocket Upgrades Rejected: {}\n", metrics.ws_handshake_rejections));
+        report.push_str(&format!("  Refused at Session Cap: {}\n", metrics.capacity_rejections));
+        report.push_str(&format!("  Refused at Per-Key Session Limit: {}\n", metrics.key_session_rejections));
+        report.push_str(&format!("  Refused at Connection Limit: {}\n", metrics.connections_rejected));
+        report.push_str(&format!("  Shared Secret Failures: {} transient, {} permanent\n",
+            metrics.secret_derivation_transient_failures, metrics.secret_derivation_permanent_failures));
+        report.push_str(&format!("  Session Key Rotations: {} ({:.2}/s)\n",
+            metrics.key_rotations, metrics.key_rotation_rate));
+
+        // Rate limiting
+        report.push_str("\nRate Limited Packets:\n");
+        report.push_str(&format!("  Packet Rate: {}\n", metrics.packet_rate_drops));
+        report.push_str(&format!("  Byte Rate: {}\n", metrics.byte_rate_drops));
+        report.push_str(&format!("  Protocol Filter: {}\n", metrics.protocol_drops));
+        report.push_str(&format!("  Source IP Mismatch: {}\n", metrics.source_ip_mismatches));
+
+        // TUN write queue
+        report.push_str("\nTUN Write Queue:\n");
+        report.push_str(&format!("  Depth: {}\n", metrics.tun_queue_depth));
+        report.push_str(&format!("  Dropped (queue full): {}\n", metrics.tun_queue_drops));
+
+        // Client send queues
+        report.push_str("\nClient Send Queues:\n");
+        report.push_str(&format!("  Dropped (client behind): {}\n", metrics.tx_dropped));
+        report.push_str(&format!("\nSession Memory (approx): {} KiB\n", metrics.session_memory_bytes / 1024));
+
+        // IP leases
+        let leases = &metrics.lease_expiry;
+        report.push_str("\nIP Lease Expiry:\n");
+        report.push_str(&format!("  Expired (unreclaimed): {}\n", leases.expired));
+        report.push_str(&format!("  Within 1m: {}\n", leases.within_1m));
+        report.push_str(&format!("  Within 5m: {}\n", leases.within_5m));
+        report.push_str(&format!("  Within 1h: {}\n", leases.within_1h));
+        report.push_str(&format!("  Later: {}\n", leases.later));
+
+        // Negotiated capabilities
+        let caps = &metrics.capabilities;
+        report.push_str("\nNegotiated Capabilities (active sessions):\n");
+        for (cipher, count) in &caps.ciphers {
+            report.push_str(&format!("  Cipher {}: {}\n", cipher, count));
+        }
+        report.push_str(&format!("  Compression: {} on, {} off\n", caps.compression_on, caps.compression_off));
+        report.push_str(&format!("  Padding: {} on, {} off\n", caps.padding_on, caps.padding_off));
+        report.push_str(&format!("  WS Keepalive: {}\n", caps.ws_keepalive));
+
+        // Session origins
+        if !metrics.sessions_by_origin.is_empty() {
+            report.push_str("\nSession Origins (active sessions):\n");
+            for ((country, asn), count) in &metrics.sessions_by_origin {
+                report.push_str(&format!("  {} AS{}: {}\n", country, asn, count));
+            }
+        }
 
         report
     }
 
+    /// Render the metrics in the Prometheus text exposition format (version 0.0.4)
+    pub async fn prometheus_text(&self) -> String {
+        let metrics = self.metrics.read().await;
+        let mut out = String::new();
+
+        // Auth counters, broken down by client version. Attempts that failed
+        // before the client sent its version are reported as `unknown`.
+        let (versioned_successes, versioned_failures) = metrics.auth_by_version.values()
+            .fold((0u64, 0u64), |(s, f), counts| (s + counts.successes, f + counts.failures));
+        for (name, help, total, versioned, pick) in [
+            ("aeronyx_auth_success_total", "Successful client authentications", metrics.auth_successes, versioned_successes,
+                (|c: &VersionAuthCounts| c.successes) as fn(&VersionAuthCounts) -> u64),
+            ("aeronyx_auth_failure_total", "Failed client authentications", metrics.auth_failures, versioned_failures,
+                |c: &VersionAuthCounts| c.failures),
+        ] {
+            prometheus_header(&mut out, name, help, "counter");
+            for (version, counts) in &metrics.auth_by_version {
+                let _ = writeln!(out, "{}{{client_version=\"{}\"}} {}", name, escape_label_value(version), pick(counts));
+            }
+            let _ = writeln!(out, "{}{{client_version=\"unknown\"}} {}", name, total.saturating_sub(versioned));
+        }
+
+        prometheus_header(&mut out, "aeronyx_auth_rate_limited_total", "Authentication steps refused by the per-IP failure limit", "counter");
+        let _ = writeln!(out, "aeronyx_auth_rate_limited_total {}", metrics.auth_rate_limited);
+
+        prometheus_header(&mut out, "aeronyx_session_resumption_total", "Session resumption attempts by result", "counter");
+        let _ = writeln!(out, "aeronyx_session_resumption_total{{result=\"resumed\"}} {}", metrics.sessions_resumed);
+        let _ = writeln!(out, "aeronyx_session_resumption_total{{result=\"refused\"}} {}", metrics.resumptions_refused);
+
+        prometheus_header(&mut out, "aeronyx_source_ip_mismatch_total", "Inner packets whose source was not the session's tunnel IP", "counter");
+        let _ = writeln!(out, "aeronyx_source_ip_mismatch_total {}", metrics.source_ip_mismatches);
+
+        prometheus_header(&mut out, "aeronyx_tx_dropped_total", "Outbound messages dropped from ended sessions' send queues", "counter");
+        let _ = writeln!(out, "aeronyx_tx_dropped_total {}", metrics.tx_dropped);
+
+        prometheus_header(&mut out, "aeronyx_handshake_start_total", "TLS handshakes started", "counter");
+        let _ = writeln!(out, "aeronyx_handshake_start_total {}", metrics.total_handshakes);
+        prometheus_header(&mut out, "aeronyx_handshake_complete_total", "TLS handshakes completed", "counter");
+        let _ = writeln!(out, "aeronyx_handshake_complete_total {}", metrics.completed_handshakes);
+        prometheus_header(&mut out, "aeronyx_handshake_failure_total", "Failed TLS handshakes and WebSocket upgrades by phase and cause", "counter");
+        for phase in HandshakePhase::ALL {
+            for reason in HandshakeFailure::ALL {
+                let _ = writeln!(out, "aeronyx_handshake_failure_total{{phase=\"{}\",reason=\"{}\"}} {}",
+                    phase.label(), reason.label(), metrics.handshake_failures.get(phase, reason));
+            }
+        }
+
+        prometheus_header(&mut out, "aeronyx_active_sessions", "Active client connections", "gauge");
+        let _ = writeln!(out, "aeronyx_active_sessions {}", metrics.active_connections);
+        prometheus_header(&mut out, "aeronyx_allocated_ips", "IP addresses allocated from the pool", "gauge");
+        let _ = writeln!(out, "aeronyx_allocated_ips {}", metrics.allocated_ips);
+        prometheus_header(&mut out, "aeronyx_sessions_by_origin", "Active sessions by source country and ASN", "gauge");
+        for ((country, asn), count) in &metrics.sessions_by_origin {
+            let _ = writeln!(out, "aeronyx_sessions_by_origin{{country=\"{}\",asn=\"{}\"}} {}",
+                escape_label_value(country), escape_label_value(asn), count);
+        }
+
+        let histogram = &metrics.handshake_duration;
+        let name = "aeronyx_tls_handshake_duration_seconds";
+        prometheus_header(&mut out, name, "Duration of completed TLS handshakes", "histogram");
+        let mut cumulative = 0;
+        for (bound, count) in HANDSHAKE_DURATION_BUCKETS.iter().zip(histogram.buckets.iter()) {
+            cumulative += count;
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulative);
+        }
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, histogram.count);
+        let _ = writeln!(out, "{}_sum {}", name, histogram.sum_secs);
+        let _ = writeln!(out, "{}_count {}", name, histogram.count);
+
+        out
+    }
+
+    /// Serve `prometheus_text` on `GET /metrics` for connections accepted by `listener`
+    pub fn serve_prometheus(self: Arc<Self>, listener: TcpListener) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!("Failed to accept metrics scrape connection: {}", e);
+                        continue;
+                    }
+                };
+                let collector = self.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = collector.answer_scrape(stream).await {
+                        debug!("Metrics scrape from {} failed: {}", peer, e);
+                    }
+                });
+            }
+        })
+    }
+
+    /// Answer a single HTTP request on a scrape connection
+    async fn answer_scrape(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        let mut buf = [0u8; 1024];
+        let n = time::timeout(METRICS_REQUEST_TIMEOUT, stream.read(&mut buf)).await
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out reading request"))??;
+        let request = String::from_utf8_lossy(&buf[..n]);
+        let mut request_line = request.lines().next().unwrap_or_default().split_whitespace();
+        let path = match (request_line.next(), request_line.next()) {
+            (Some("GET"), Some(target)) => target.split('?').next(),
+            _ => None,
+        };
+
+        let (status, body) = match path {
+            Some("/metrics") => ("200 OK", self.prometheus_text().await),
+            _ => ("404 Not Found", "Not Found\n".to_string()),
+        };
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status, body.len(), body
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await
+    }
+
     /// Get a compact status report for admin interface
      pub async fn get_status(&self) -> String {
          let metrics = self.metrics.read().await;
@@ -418,6 +1018,16 @@ impl ServerMetricsCollector {
      }
 }
 
+/// Gauge key for `origin`, folded into `other` once the label budget is spent
+fn origin_key(gauge: &BTreeMap<(String, String), usize>, origin: &ConnectionOrigin) -> (String, String) {
+    let key = origin.labels();
+    if gauge.contains_key(&key) || gauge.len() < MAX_ORIGIN_LABELS {
+        key
+    } else {
+        ("other".to_string(), "other".to_string())
+    }
+}
+
 /// Get CPU usage percentage (async) - Placeholder Implementation
 async fn get_cpu_usage() -> f64 {
     // NOTE: This remains a placeholder. For accurate CPU usage,
@@ -566,6 +1176,17 @@ async fn get_load_average() -> (f64, f64, f64) {
 }
 
 // --- format_bytes remains the same ---
+/// Write the `# HELP` and `# TYPE` lines for a Prometheus metric family
+fn prometheus_header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Escape a Prometheus label value (backslash, double quote and newline)
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
 /// Format bytes as human-readable string
 fn format_bytes(bytes: u64) -> String {
     const KB: u64 = 1024;
@@ -628,6 +1249,155 @@ mod tests {
 
     }
 
+    #[tokio::test]
+    async fn test_capability_gauges() {
+        use crate::crypto::flexible_encryption::EncryptionAlgorithm;
+
+        let collector = ServerMetricsCollector::new(Duration::from_secs(1), 10);
+        let aes = NegotiatedCapabilities::negotiate(&[], EncryptionAlgorithm::Aes256Gcm, true, None);
+        let chacha = NegotiatedCapabilities::negotiate(
+            &["ws-keepalive".to_string()],
+            EncryptionAlgorithm::ChaCha20Poly1305,
+            false,
+            None,
+        );
+
+        collector.record_session_capabilities(&aes).await;
+        collector.record_session_capabilities(&chacha).await;
+        let caps = collector.get_metrics().await.capabilities;
+        assert_eq!(caps.ciphers.get("aes256gcm"), Some(&1));
+        assert_eq!(caps.ciphers.get("chacha20poly1305"), Some(&1));
+        assert_eq!(caps.padding_on, 1);
+        assert_eq!(caps.padding_off, 1);
+        assert_eq!(caps.compression_off, 2);
+        assert_eq!(caps.ws_keepalive, 1);
+
+        collector.release_session_capabilities(&aes).await;
+        let caps = collector.get_metrics().await.capabilities;
+        assert_eq!(caps.ciphers.get("aes256gcm"), Some(&0));
+        assert_eq!(caps.padding_on, 0);
+        assert_eq!(caps.compression_off, 1);
+    }
+
+    #[tokio::test]
+    async fn test_origin_label_budget() {
+        let collector = ServerMetricsCollector::new(Duration::from_secs(1), 10);
+        let origin = |asn: u32| ConnectionOrigin { country: Some("NL".to_string()), asn: Some(asn) };
+        let mut keys = Vec::new();
+        for asn in 0..MAX_ORIGIN_LABELS as u32 {
+            keys.push(collector.record_session_origin(&origin(asn)).await);
+        }
+
+        // Past the budget new origins are folded into `other`
+        let other = ("other".to_string(), "other".to_string());
+        let folded = collector.record_session_origin(&origin(1000)).await;
+        assert_eq!(folded, other);
+
+        // The folded session is released from the entry it was counted under
+        collector.release_session_origin(&folded).await;
+        assert!(!collector.get_metrics().await.sessions_by_origin.contains_key(&other));
+
+        // A label with no sessions left is dropped and frees its slot
+        collector.release_session_origin(&keys[0]).await;
+        assert!(!collector.get_metrics().await.sessions_by_origin.contains_key(&keys[0]));
+        let key = collector.record_session_origin(&origin(2000)).await;
+        assert_eq!(key, origin(2000).labels());
+        assert_eq!(collector.get_metrics().await.sessions_by_origin.len(), MAX_ORIGIN_LABELS);
+    }
+
+    #[tokio::test]
+    async fn test_prometheus_export() {
+        let collector = Arc::new(ServerMetricsCollector::new(Duration::from_secs(1), 10));
+        collector.record_handshake_start().await;
+        collector.record_handshake_complete(Duration::from_millis(20)).await;
+        collector.record_handshake_start().await;
+        collector.record_handshake_complete(Duration::from_secs(30)).await;
+        collector.record_handshake_start().await;
+        collector.record_handshake_failure(HandshakePhase::Tls, HandshakeFailure::Malformed).await;
+        collector.record_handshake_failure(HandshakePhase::WebSocket, HandshakeFailure::Timeout).await;
+        collector.record_new_connection().await;
+        collector.record_allocated_ips(3).await;
+        collector.record_session_origin(&ConnectionOrigin { country: Some("NL".to_string()), asn: Some(1136) }).await;
+        collector.record_auth_success().await;
+        collector.record_auth_version("1.0.0", true).await;
+        collector.record_auth_failure().await;
+        collector.record_auth_version("2.0 \"beta\"", false).await;
+        collector.record_auth_failure().await;
+        collector.record_auth_rate_limited().await;
+        collector.record_session_resumption(true).await;
+        collector.record_tx_dropped(4).await;
+        collector.record_tx_dropped(0).await;
+
+        let text = collector.prometheus_text().await;
+        let mut types = BTreeMap::new();
+        let mut samples = BTreeMap::new();
+        for line in text.lines() {
+            if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = rest.split_once(' ').unwrap();
+                assert!(types.insert(name.to_string(), kind.to_string()).is_none(), "duplicate TYPE for {}", name);
+                continue;
+            }
+            if line.starts_with("# HELP ") {
+                continue;
+            }
+            // Every sample is `name{labels} value` with a declared family and a numeric value
+            let (series, value) = line.rsplit_once(' ').unwrap();
+            let value: f64 = value.parse().unwrap_or_else(|_| panic!("bad value in {:?}", line));
+            let name = series.split('{').next().unwrap();
+            assert!(name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'), "bad name in {:?}", line);
+            let family = ["_bucket", "_sum", "_count"].iter()
+                .find_map(|suffix| name.strip_suffix(suffix).filter(|f| types.get(*f).map(String::as_str) == Some("histogram")))
+                .unwrap_or(name);
+            assert!(types.contains_key(family), "sample before TYPE in {:?}", line);
+            if let Some(labels) = series.strip_prefix(name) {
+                if !labels.is_empty() {
+                    assert!(labels.starts_with('{') && labels.ends_with('}'), "bad labels in {:?}", line);
+                }
+            }
+            samples.insert(series.to_string(), value);
+        }
+
+        assert_eq!(types["aeronyx_auth_success_total"], "counter");
+        assert_eq!(types["aeronyx_tls_handshake_duration_seconds"], "histogram");
+        assert_eq!(samples["aeronyx_auth_success_total{client_version=\"1.0.0\"}"], 1.0);
+        assert_eq!(samples["aeronyx_auth_failure_total{client_version=\"2.0 \\\"beta\\\"\"}"], 1.0);
+        assert_eq!(samples["aeronyx_auth_failure_total{client_version=\"unknown\"}"], 1.0);
+        assert_eq!(samples["aeronyx_auth_rate_limited_total"], 1.0);
+        assert_eq!(samples["aeronyx_session_resumption_total{result=\"resumed\"}"], 1.0);
+        assert_eq!(samples["aeronyx_session_resumption_total{result=\"refused\"}"], 0.0);
+        assert_eq!(samples["aeronyx_tx_dropped_total"], 4.0);
+        assert_eq!(samples["aeronyx_handshake_start_total"], 3.0);
+        assert_eq!(samples["aeronyx_handshake_complete_total"], 2.0);
+        assert_eq!(samples["aeronyx_handshake_failure_total{phase=\"tls\",reason=\"malformed\"}"], 1.0);
+        assert_eq!(samples["aeronyx_handshake_failure_total{phase=\"tls\",reason=\"tls_alert\"}"], 0.0);
+        assert_eq!(samples["aeronyx_handshake_failure_total{phase=\"websocket\",reason=\"timeout\"}"], 1.0);
+        assert_eq!(collector.get_metrics().await.active_handshakes, 0);
+        assert_eq!(samples["aeronyx_active_sessions"], 1.0);
+        assert_eq!(samples["aeronyx_allocated_ips"], 3.0);
+        assert_eq!(samples["aeronyx_sessions_by_origin{country=\"NL\",asn=\"1136\"}"], 1.0);
+        assert_eq!(samples["aeronyx_tls_handshake_duration_seconds_bucket{le=\"0.01\"}"], 0.0);
+        assert_eq!(samples["aeronyx_tls_handshake_duration_seconds_bucket{le=\"0.025\"}"], 1.0);
+        assert_eq!(samples["aeronyx_tls_handshake_duration_seconds_bucket{le=\"5\"}"], 1.0);
+        assert_eq!(samples["aeronyx_tls_handshake_duration_seconds_bucket{le=\"+Inf\"}"], 2.0);
+        assert_eq!(samples["aeronyx_tls_handshake_duration_seconds_count"], 2.0);
+
+        // The same text is served over HTTP; other paths are 404
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = collector.clone().serve_prometheus(listener);
+        for (path, status) in [("/metrics", "200 OK"), ("/other", "404 Not Found")] {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with(&format!("HTTP/1.1 {}", status)), "{}", response);
+            if path == "/metrics" {
+                assert!(response.ends_with(&text));
+            }
+        }
+        server.abort();
+    }
+
     #[test]
     fn test_format_bytes() {
         assert_eq!(format_bytes(100), "100 B");
@@ -12,6 +12,19 @@ pub mod client;
 pub mod packet;
 pub mod globals;
 pub mod connection;
+pub mod negotiation;
+pub mod auth_state;
+pub mod handshake;
+pub mod workers;
+pub mod schedule;
+pub mod warmup;
+pub mod records;
+pub mod send_queue;
+pub mod heartbeat;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+#[cfg(feature = "admin-api")]
+pub mod admin;
 
 // Re-export commonly used items
 pub use core::VpnServer;
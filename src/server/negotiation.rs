@@ -0,0 +1,308 @@
+// src/server/negotiation.rs
+//! Feature negotiation for client sessions.
+//!
+//! This module resolves the optional features advertised by a client during
+//! authentication into the set of capabilities that are actually active for
+//! the session, taking the server configuration into account.
+
+use tracing::debug;
+
+use crate::config::constants::{MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+use crate::crypto::flexible_encryption::EncryptionAlgorithm;
+use crate::protocol::types::{encryption_algorithms, features, PacketType};
+
+/// Capabilities negotiated for a single client session
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NegotiatedCapabilities {
+    /// Keepalive uses WebSocket control frames instead of application Ping/Pong
+    pub ws_keepalive: bool,
+    /// Cipher selected for the session
+    pub encryption_algorithm: EncryptionAlgorithm,
+    /// Outbound traffic padding is active
+    pub padding: bool,
+    /// Client accepts PacketTooBig notifications
+    pub packet_too_big: bool,
+    /// Client expects a DisconnectAck before the server closes
+    pub disconnect_ack: bool,
+    /// Client expects a FeatureAck after IpAssign
+    pub feature_ack: bool,
+    /// IpRenewal requests must carry a valid MAC under the session key
+    pub signed_renewal: bool,
+    /// Data nonces are derived from counters instead of being transmitted
+    pub derived_nonce: bool,
+    /// Client receives periodic ServerLoad packets
+    pub server_load: bool,
+    /// IpAssign session key is bound to the session id and client public key
+    pub bound_session_key: bool,
+    /// IpRenewal and Disconnect must carry an increasing sequence number under their MAC
+    pub control_sequence: bool,
+    /// Data payloads may be fragmented in both directions
+    pub fragmentation: bool,
+    /// Client receives a ResumptionToken to resume the session after it ends
+    pub session_resumption: bool,
+    /// Requested stateful features refused because of the per-session limit
+    pub denied: Vec<String>,
+    /// Payload compression is active (not yet supported by the tunnel, always off)
+    pub compression: bool,
+}
+
+/// Pick the session cipher from the client's Auth request.
+///
+/// An explicitly requested algorithm wins; otherwise advertising `aes-gcm`
+/// selects AES-256-GCM. Returns `None` when the client expressed no usable
+/// preference, leaving the server default.
+pub fn select_encryption_algorithm(requested: Option<&str>, advertised: &[String]) -> Option<EncryptionAlgorithm> {
+    requested
+        .and_then(EncryptionAlgorithm::from_str)
+        .or_else(|| {
+            advertised.iter()
+                .any(|f| f == features::AES_GCM)
+                .then_some(EncryptionAlgorithm::Aes256Gcm)
+        })
+}
+
+/// Build the `Capabilities` reply to a pre-authentication `Hello`.
+///
+/// Only static protocol facts are included, nothing about keys, clients
+/// or load, so answering does not help an unauthenticated peer.
+/// Session resumption is only listed when `resumption_enabled`, so the
+/// reply matches what negotiation would actually grant.
+pub fn server_capabilities(max_mtu: u16, resumption_enabled: bool) -> PacketType {
+    PacketType::Capabilities {
+        protocol_version: PROTOCOL_VERSION.to_string(),
+        min_protocol_version: MIN_PROTOCOL_VERSION.to_string(),
+        encryption_algorithms: encryption_algorithms::ALL.iter().map(|a| a.to_string()).collect(),
+        features: features::ALL.iter()
+            .filter(|f| resumption_enabled || **f != features::SESSION_RESUMPTION)
+            .map(|f| f.to_string())
+            .collect(),
+        max_mtu,
+    }
+}
+
+impl NegotiatedCapabilities {
+    /// Negotiate capabilities from the features advertised by the client
+    /// and the settings the server has selected for the session.
+    ///
+    /// At most `max_stateful` stateful features are activated, in the order
+    /// the client advertised them; the rest are recorded in `denied`.
+    pub fn negotiate(
+        advertised: &[String],
+        encryption_algorithm: EncryptionAlgorithm,
+        padding_enabled: bool,
+        max_stateful: Option<usize>,
+    ) -> Self {
+        let mut caps = Self {
+            encryption_algorithm,
+            padding: padding_enabled,
+            ..Self::default()
+        };
+
+        let mut stateful = 0;
+        for feature in advertised {
+            if features::is_stateful(feature) && !caps.active_features().contains(&feature.as_str()) {
+                if max_stateful.map_or(false, |max| stateful >= max) {
+                    debug!("Denying stateful feature {}: per-session limit reached", feature);
+                    caps.denied.push(feature.clone());
+                    continue;
+                }
+                stateful += 1;
+            }
+            match feature.as_str() {
+                features::WS_KEEPALIVE => caps.ws_keepalive = true,
+                features::PACKET_TOO_BIG => caps.packet_too_big = true,
+                features::DISCONNECT_ACK => caps.disconnect_ack = true,
+                features::FEATURE_ACK => caps.feature_ack = true,
+                features::SIGNED_RENEWAL => caps.signed_renewal = true,
+                features::DERIVED_NONCE => caps.derived_nonce = true,
+                features::SERVER_LOAD => caps.server_load = true,
+                features::BOUND_SESSION_KEY => caps.bound_session_key = true,
+                features::CONTROL_SEQUENCE => caps.control_sequence = true,
+                features::FRAGMENTATION => caps.fragmentation = true,
+                features::SESSION_RESUMPTION => caps.session_resumption = true,
+                // Already applied through `select_encryption_algorithm`
+                features::AES_GCM => {}
+                other => debug!("Ignoring unsupported client feature: {}", other),
+            }
+        }
+
+        // An unauthenticated sequence could be forged to lock out the client's own packets
+        if caps.control_sequence && !caps.signed_renewal {
+            debug!("Ignoring {} without {}", features::CONTROL_SEQUENCE, features::SIGNED_RENEWAL);
+            caps.control_sequence = false;
+        }
+
+        caps
+    }
+
+    /// Get the list of feature identifiers that are active
+    pub fn active_features(&self) -> Vec<&'static str> {
+        let mut active = Vec::new();
+        if self.ws_keepalive {
+            active.push(features::WS_KEEPALIVE);
+        }
+        if self.packet_too_big {
+            active.push(features::PACKET_TOO_BIG);
+        }
+        if self.disconnect_ack {
+            active.push(features::DISCONNECT_ACK);
+        }
+        if self.feature_ack {
+            active.push(features::FEATURE_ACK);
+        }
+        if self.signed_renewal {
+            active.push(features::SIGNED_RENEWAL);
+        }
+        if self.derived_nonce {
+            active.push(features::DERIVED_NONCE);
+        }
+        if self.server_load {
+            active.push(features::SERVER_LOAD);
+        }
+        if self.bound_session_key {
+            active.push(features::BOUND_SESSION_KEY);
+        }
+        if self.control_sequence {
+            active.push(features::CONTROL_SEQUENCE);
+        }
+        if self.fragmentation {
+            active.push(features::FRAGMENTATION);
+        }
+        if self.session_resumption {
+            active.push(features::SESSION_RESUMPTION);
+        }
+        if self.encryption_algorithm == EncryptionAlgorithm::Aes256Gcm {
+            active.push(features::AES_GCM);
+        }
+        active
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_ws_keepalive() {
+        let caps = NegotiatedCapabilities::negotiate(
+            &["ws-keepalive".to_string()],
+            EncryptionAlgorithm::ChaCha20Poly1305,
+            false,
+            None,
+        );
+        assert!(caps.ws_keepalive);
+        assert_eq!(caps.active_features(), vec![features::WS_KEEPALIVE]);
+    }
+
+    #[test]
+    fn test_negotiate_ignores_unknown_features() {
+        let caps = NegotiatedCapabilities::negotiate(
+            &["unknown".to_string(), "chat".to_string()],
+            EncryptionAlgorithm::ChaCha20Poly1305,
+            false,
+            None,
+        );
+        assert_eq!(caps, NegotiatedCapabilities::default());
+        assert!(caps.active_features().is_empty());
+    }
+
+    #[test]
+    fn test_negotiate_reflects_server_settings() {
+        // Advertised support does not imply the capability is active
+        let caps = NegotiatedCapabilities::negotiate(
+            &["compression".to_string()],
+            EncryptionAlgorithm::Aes256Gcm,
+            true,
+            None,
+        );
+        assert_eq!(caps.encryption_algorithm, EncryptionAlgorithm::Aes256Gcm);
+        assert!(caps.padding);
+        assert!(!caps.compression);
+    }
+
+    #[test]
+    fn test_negotiate_stateful_feature_limit() {
+        let advertised = vec![
+            features::WS_KEEPALIVE.to_string(),
+            features::PACKET_TOO_BIG.to_string(),
+            features::FEATURE_ACK.to_string(),
+        ];
+
+        // Cheap flags are not counted against the limit
+        let caps = NegotiatedCapabilities::negotiate(&advertised, EncryptionAlgorithm::default(), false, Some(0));
+        assert!(caps.ws_keepalive && caps.feature_ack);
+        assert!(!caps.packet_too_big);
+        assert_eq!(caps.denied, vec![features::PACKET_TOO_BIG.to_string()]);
+
+        let caps = NegotiatedCapabilities::negotiate(&advertised, EncryptionAlgorithm::default(), false, Some(1));
+        assert!(caps.packet_too_big);
+        assert!(caps.denied.is_empty());
+
+        let caps = NegotiatedCapabilities::negotiate(&advertised, EncryptionAlgorithm::default(), false, None);
+        assert!(caps.packet_too_big);
+        assert!(caps.denied.is_empty());
+    }
+
+    #[test]
+    fn test_control_sequence_requires_signed_renewal() {
+        let alone = NegotiatedCapabilities::negotiate(
+            &[features::CONTROL_SEQUENCE.to_string()],
+            EncryptionAlgorithm::default(),
+            false,
+            None,
+        );
+        assert!(!alone.control_sequence);
+        assert!(alone.active_features().is_empty());
+
+        let signed = NegotiatedCapabilities::negotiate(
+            &[features::CONTROL_SEQUENCE.to_string(), features::SIGNED_RENEWAL.to_string()],
+            EncryptionAlgorithm::default(),
+            false,
+            None,
+        );
+        assert!(signed.control_sequence && signed.signed_renewal);
+    }
+
+    #[test]
+    fn test_select_aes_gcm() {
+        let advertised = vec![features::AES_GCM.to_string()];
+
+        // The feature alone selects AES-256-GCM
+        assert_eq!(select_encryption_algorithm(None, &advertised), Some(EncryptionAlgorithm::Aes256Gcm));
+        // An explicit algorithm still wins
+        assert_eq!(
+            select_encryption_algorithm(Some("chacha20poly1305"), &advertised),
+            Some(EncryptionAlgorithm::ChaCha20Poly1305)
+        );
+        // No preference leaves the server default
+        assert_eq!(select_encryption_algorithm(None, &[]), None);
+        assert_eq!(select_encryption_algorithm(Some("rot13"), &[]), None);
+
+        let caps = NegotiatedCapabilities::negotiate(&advertised, EncryptionAlgorithm::Aes256Gcm, false, None);
+        assert_eq!(caps.active_features(), vec![features::AES_GCM]);
+        assert!(caps.denied.is_empty());
+    }
+
+    #[test]
+    fn test_server_capabilities_match_negotiation() {
+        for resumption_enabled in [true, false] {
+            let (advertised, max_mtu) = match server_capabilities(1400, resumption_enabled) {
+                PacketType::Capabilities { features, encryption_algorithms, max_mtu, .. } => {
+                    assert!(encryption_algorithms.iter().all(|a| EncryptionAlgorithm::from_str(a).is_some()));
+                    (features, max_mtu)
+                }
+                other => panic!("unexpected packet {:?}", other),
+            };
+            assert_eq!(max_mtu, 1400);
+            assert_eq!(advertised.iter().any(|f| f == features::SESSION_RESUMPTION), resumption_enabled);
+
+            // Every advertised feature is one the server actually activates
+            let caps = NegotiatedCapabilities::negotiate(&advertised, EncryptionAlgorithm::Aes256Gcm, false, None);
+            let mut active = caps.active_features();
+            let mut expected: Vec<&str> = advertised.iter().map(String::as_str).collect();
+            active.sort_unstable();
+            expected.sort_unstable();
+            assert_eq!(active, expected);
+        }
+    }
+}
@@ -102,6 +102,7 @@ pub async fn process_tun_packets(
                                 &processed_packet,
                                 &session_key,
                                 &session,
+                                &session_key_manager,
                             ).await {
                                 trace!("Error routing packet to {}: {}", dest_ip, e);
                             }
@@ -0,0 +1,159 @@
+// src/server/records.rs
+//! Per-session lifecycle records.
+//!
+//! When a session ends, one JSON line summarising its whole lifecycle is
+//! appended to the configured record file, for offline analysis and
+//! billing. Fields are only ever added to the schema; a rename, removal or
+//! change of meaning bumps `SESSION_RECORD_SCHEMA_VERSION`.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// Version of the `SessionRecord` schema
+pub const SESSION_RECORD_SCHEMA_VERSION: u32 = 1;
+
+/// Why a session ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TeardownReason {
+    /// The client sent a Disconnect
+    ClientDisconnect,
+    /// The WebSocket closed without a Disconnect
+    ConnectionClosed,
+    /// The server closed the session (revocation, replacement, idle timeout)
+    ClosedByServer,
+    /// The server is shutting down
+    ServerShutdown,
+    /// The session failed with an error
+    Error,
+}
+
+/// One-line summary of a finished session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionRecord {
+    /// Schema version, `SESSION_RECORD_SCHEMA_VERSION` when written
+    pub schema_version: u32,
+    /// Session ID
+    pub session_id: String,
+    /// Client public key
+    pub client_id: String,
+    /// Client source address
+    pub source_addr: String,
+    /// Tunnel IP assigned to the session
+    pub assigned_ip: String,
+    /// When the WebSocket session began (Unix milliseconds)
+    pub connected_at: u64,
+    /// Time from connection to successful authentication
+    pub time_to_auth_ms: u64,
+    /// Time from connection to teardown
+    pub duration_ms: u64,
+    /// WebSocket payload bytes received from the client
+    pub bytes_up: u64,
+    /// WebSocket payload bytes sent to the client
+    pub bytes_down: u64,
+    /// WebSocket messages received from the client
+    pub packets_up: u64,
+    /// WebSocket messages sent to the client
+    pub packets_down: u64,
+    /// Session key rotations during the session
+    pub key_rotations: u64,
+    /// Successful IP lease renewals during the session
+    pub ip_renewals: u64,
+    /// Why the session ended
+    pub teardown_reason: TeardownReason,
+    /// Highest round-trip time measured, if any
+    pub peak_rtt_ms: Option<u64>,
+}
+
+/// Appends session records to a file as JSON lines
+#[derive(Debug)]
+pub struct SessionRecordWriter {
+    /// Destination path, for logging
+    path: PathBuf,
+    /// Open record file
+    file: Mutex<File>,
+}
+
+impl SessionRecordWriter {
+    /// Open (or create) `path` for appending
+    pub async fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path).await?;
+        Ok(Self { path, file: Mutex::new(file) })
+    }
+
+    /// Append one record
+    pub async fn write(&self, record: &SessionRecord) {
+        let mut line = match serde_json::to_string(record) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Failed to serialize session record for {}: {}", record.session_id, e);
+                return;
+            }
+        };
+        line.push('\n');
+
+        // One write per record keeps lines whole
+        let mut file = self.file.lock().await;
+        if let Err(e) = file.write_all(line.as_bytes()).await {
+            warn!("Failed to write session record to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(session_id: &str) -> SessionRecord {
+        SessionRecord {
+            schema_version: SESSION_RECORD_SCHEMA_VERSION,
+            session_id: session_id.to_string(),
+            client_id: "client".to_string(),
+            source_addr: "203.0.113.7:40000".to_string(),
+            assigned_ip: "10.7.0.2".to_string(),
+            connected_at: 1_700_000_000_000,
+            time_to_auth_ms: 120,
+            duration_ms: 60_000,
+            bytes_up: 1000,
+            bytes_down: 2000,
+            packets_up: 10,
+            packets_down: 20,
+            key_rotations: 1,
+            ip_renewals: 2,
+            teardown_reason: TeardownReason::ClientDisconnect,
+            peak_rtt_ms: Some(45),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_records_append_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sessions.jsonl");
+        let writer = SessionRecordWriter::open(&path).await.unwrap();
+        writer.write(&record("s1")).await;
+        writer.write(&record("s2")).await;
+
+        let contents = tokio::fs::read_to_string(&path).await.unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let first: SessionRecord = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first, record("s1"));
+
+        // Stable wire names
+        let value: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(value["schema_version"], 1);
+        assert_eq!(value["teardown_reason"], "client_disconnect");
+        assert_eq!(value["time_to_auth_ms"], 120);
+        assert_eq!(value["peak_rtt_ms"], 45);
+
+        // Reopening appends rather than truncating
+        let writer = SessionRecordWriter::open(&path).await.unwrap();
+        writer.write(&record("s3")).await;
+        assert_eq!(tokio::fs::read_to_string(&path).await.unwrap().lines().count(), 3);
+    }
+}
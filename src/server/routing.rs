@@ -7,7 +7,11 @@
 // Removed unused io import
 use std::io::Write;
 // Removed unused IpAddr, Ipv4Addr imports
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Instant;
 use rand::{Rng, thread_rng};
 use tokio::sync::Mutex;
 use chrono;
@@ -16,13 +20,25 @@ use serde::{Serialize, Deserialize};
 // Removed unused debug import
 use tracing::{debug, error, trace, warn};
 
-use crate::config::constants::{MIN_PADDING_SIZE, MAX_PADDING_SIZE, PAD_PROBABILITY};
+use crate::config::constants::{
+    FRAGMENT_REASSEMBLY_TIMEOUT, MAX_FRAGMENTS_PER_MESSAGE, MAX_INCOMPLETE_MESSAGES, MAX_REASSEMBLY_BYTES, MIN_PADDING_SIZE, MAX_PADDING_SIZE,
+    PAD_PROBABILITY, PACKET_TOO_BIG_INTERVAL, TUN_MTU,
+};
 // Removed: use crate::crypto::{encrypt_packet, decrypt_packet};
 use crate::protocol::{PacketType, MessageError};
+use crate::protocol::fragment::{split_payload, FragmentReassembler};
+use crate::protocol::types::FragmentHeader;
 // Removed unused packet_to_ws_message import
 use crate::server::session::ClientSession;
-use crate::utils::security::detect_attack_patterns;
+use crate::server::metrics::ServerMetricsCollector;
+use crate::network::flows::FlowTracker;
+use crate::network::mss::clamp_tcp_mss;
+use crate::network::tun_queue::{Enqueue, TunWriteQueue};
+use crate::utils::security::{detect_attack_patterns, TokenBucket};
 use crate::crypto::flexible_encryption::EncryptionAlgorithm;
+use crate::crypto::session::SessionKeyManager;
+use crate::config::defaults::DEFAULT_PADDING_BUCKET_SIZE;
+use crate::config::settings::{PaddingPolicy, SourceIpPolicy};
 
 /// Error type for packet routing operations
 #[derive(Debug, thiserror::Error)]
@@ -53,6 +69,107 @@ pub enum RoutingError {
 
     #[error("Potential attack detected: {0}")]
     SecurityRisk(String),
+
+    #[error("Rate limit exceeded: {0}")]
+    RateLimited(String),
+}
+
+/// Per-client inbound traffic limits (0 = unlimited)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrafficLimits {
+    /// Maximum packets per second
+    pub packets_per_sec: u64,
+    /// Maximum bytes per second
+    pub bytes_per_sec: u64,
+}
+
+impl TrafficLimits {
+    /// No limits
+    pub const UNLIMITED: Self = Self { packets_per_sec: 0, bytes_per_sec: 0 };
+}
+
+/// Operator override of one client's inbound traffic limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitOverride {
+    /// Limits replacing the server defaults (0 = unlimited)
+    pub limits: TrafficLimits,
+    /// Seconds of traffic at the limit that each bucket can absorb at once
+    pub burst_secs: u64,
+}
+
+/// Token buckets tracking one client's inbound traffic
+struct ClientBuckets {
+    /// Limits the buckets were built for
+    limits: TrafficLimits,
+    /// Built from an operator override rather than the defaults and ACL tier
+    overridden: bool,
+    packets: Option<TokenBucket>,
+    bytes: Option<TokenBucket>,
+}
+
+impl ClientBuckets {
+    /// Buckets allow one second of burst at the configured rate
+    fn new(limits: TrafficLimits) -> Self {
+        Self::with_burst(limits, 1)
+    }
+
+    /// Buckets allow `burst_secs` seconds of burst at the configured rate
+    fn with_burst(limits: TrafficLimits, burst_secs: u64) -> Self {
+        let bucket = |rate: u64| (rate > 0).then(|| TokenBucket::new(rate, rate.saturating_mul(burst_secs.max(1))));
+        Self {
+            limits,
+            overridden: false,
+            packets: bucket(limits.packets_per_sec),
+            bytes: bucket(limits.bytes_per_sec),
+        }
+    }
+
+    /// Buckets for an operator override
+    fn for_override(rate_override: RateLimitOverride) -> Self {
+        Self {
+            overridden: true,
+            ..Self::with_burst(rate_override.limits, rate_override.burst_secs)
+        }
+    }
+}
+
+/// Parse a tunnel protocol name (tcp, udp, icmp, icmpv6) or IP protocol number
+pub fn parse_ip_protocol(name: &str) -> Option<u8> {
+    match name.trim().to_ascii_lowercase().as_str() {
+        "icmp" => Some(1),
+        "tcp" => Some(6),
+        "udp" => Some(17),
+        "icmpv6" => Some(58),
+        other => other.parse().ok(),
+    }
+}
+
+/// IP protocol (IPv4) or Next Header (IPv6) of an inner packet, if its header is well-formed
+fn inner_ip_protocol(packet: &[u8]) -> Option<u8> {
+    match packet.first()? >> 4 {
+        4 => {
+            let header_len = usize::from(packet[0] & 0x0f) * 4;
+            (header_len >= 20 && packet.len() >= header_len).then(|| packet[9])
+        }
+        6 => (packet.len() >= 40).then(|| packet[6]),
+        _ => None,
+    }
+}
+
+/// Source address of an inner packet, if its header is well-formed
+fn inner_source_ip(packet: &[u8]) -> Option<IpAddr> {
+    match packet.first()? >> 4 {
+        4 => {
+            let header_len = usize::from(packet[0] & 0x0f) * 4;
+            (header_len >= 20 && packet.len() >= header_len)
+                .then(|| IpAddr::from([packet[12], packet[13], packet[14], packet[15]]))
+        }
+        6 if packet.len() >= 40 => {
+            let source: [u8; 16] = packet[8..24].try_into().ok()?;
+            Some(IpAddr::from(source))
+        }
+        _ => None,
+    }
 }
 
 /// Data envelope for mixed-mode packet handling
@@ -77,8 +194,41 @@ pub struct PacketRouter {
     max_packet_size: usize,
     /// Whether to enable padding
     enable_padding: bool,
-    /// Packet counter to prevent replay attacks
-    packet_counter: Arc<Mutex<u64>>,
+    /// How outbound payloads are padded when padding is enabled
+    padding_policy: PaddingPolicy,
+    /// Bucket size for `PaddingPolicy::Bucket`
+    padding_bucket_size: usize,
+    /// Per-client inbound traffic limits
+    traffic_limits: TrafficLimits,
+    /// Token buckets per client ID
+    client_buckets: Arc<Mutex<HashMap<String, ClientBuckets>>>,
+    /// Per-client limit overrides set at runtime, kept until cleared
+    rate_overrides: Arc<Mutex<HashMap<String, RateLimitOverride>>>,
+    /// Metrics collector for rate limit drops
+    metrics: Option<Arc<ServerMetricsCollector>>,
+    /// Largest inner packet accepted for the TUN device
+    tunnel_mtu: usize,
+    /// Rewrite the MSS option of forwarded TCP SYNs to fit the tunnel MTU
+    mss_clamp: bool,
+    /// Last PacketTooBig notification sent per client ID
+    packet_too_big_sent: Arc<Mutex<HashMap<String, Instant>>>,
+    /// Flow accounting for tunnel traffic, if flow export is enabled
+    flow_tracker: Option<Arc<FlowTracker>>,
+    /// Inner IP protocols allowed to reach the TUN device (None = all)
+    allowed_protocols: Option<HashSet<u8>>,
+    /// Whether inner packets from other than the session's tunnel IP are dropped
+    source_ip_policy: SourceIpPolicy,
+    /// Bounded queue toward the TUN device (None = write directly)
+    tun_queue: Option<Arc<TunWriteQueue>>,
+    /// Largest outbound payload sent unfragmented to sessions that negotiated fragmentation (0 = never split)
+    fragment_size: usize,
+    /// Message ID for the next fragmented outbound payload
+    next_fragment_message: AtomicU32,
+    /// Incomplete fragmented payloads per client ID
+    reassembly: Arc<Mutex<HashMap<String, FragmentReassembler>>>,
+    /// Fault injection for resilience testing (chaos builds only)
+    #[cfg(feature = "chaos")]
+    chaos: Option<Arc<crate::server::chaos::ChaosController>>,
 }
 
 impl PacketRouter {
@@ -87,8 +237,294 @@ impl PacketRouter {
         Self {
             max_packet_size,
             enable_padding,
-            packet_counter: Arc::new(Mutex::new(0)),
+            padding_policy: PaddingPolicy::Random,
+            padding_bucket_size: DEFAULT_PADDING_BUCKET_SIZE,
+            traffic_limits: TrafficLimits::UNLIMITED,
+            client_buckets: Arc::new(Mutex::new(HashMap::new())),
+            rate_overrides: Arc::new(Mutex::new(HashMap::new())),
+            metrics: None,
+            tunnel_mtu: TUN_MTU as usize,
+            mss_clamp: false,
+            packet_too_big_sent: Arc::new(Mutex::new(HashMap::new())),
+            flow_tracker: None,
+            allowed_protocols: None,
+            source_ip_policy: SourceIpPolicy::Permissive,
+            tun_queue: None,
+            fragment_size: 0,
+            next_fragment_message: AtomicU32::new(0),
+            reassembly: Arc::new(Mutex::new(HashMap::new())),
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        }
+    }
+
+    /// Set the per-client inbound packet and byte rate limits
+    pub fn with_traffic_limits(mut self, limits: TrafficLimits) -> Self {
+        self.traffic_limits = limits;
+        self
+    }
+
+    /// Attach a metrics collector
+    pub fn with_metrics(mut self, metrics: Arc<ServerMetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Set the largest inner packet accepted for the TUN device
+    pub fn with_tunnel_mtu(mut self, mtu: u16) -> Self {
+        self.tunnel_mtu = mtu as usize;
+        self
+    }
+
+    /// Largest inner packet accepted for the TUN device
+    pub fn tunnel_mtu(&self) -> u16 {
+        self.tunnel_mtu as u16
+    }
+
+    /// Clamp the MSS of TCP SYNs crossing the tunnel in either direction to fit the tunnel MTU
+    pub fn with_mss_clamp(mut self, enabled: bool) -> Self {
+        self.mss_clamp = enabled;
+        self
+    }
+
+    /// Choose how outbound payloads are padded when padding is enabled
+    pub fn with_padding_policy(mut self, policy: PaddingPolicy, bucket_size: usize) -> Self {
+        self.padding_policy = policy;
+        self.padding_bucket_size = bucket_size.max(1);
+        self
+    }
+
+    /// Account tunnel traffic in a flow tracker
+    pub fn with_flow_tracker(mut self, tracker: Arc<FlowTracker>) -> Self {
+        self.flow_tracker = Some(tracker);
+        self
+    }
+
+    /// Only forward inner packets whose IP protocol is listed (empty = allow all)
+    pub fn with_protocol_allowlist(mut self, protocols: impl IntoIterator<Item = u8>) -> Self {
+        let protocols: HashSet<u8> = protocols.into_iter().collect();
+        self.allowed_protocols = (!protocols.is_empty()).then_some(protocols);
+        self
+    }
+
+    /// Drop (strict) or only count (permissive) inner packets not sourced from the session's tunnel IP
+    pub fn with_source_ip_policy(mut self, policy: SourceIpPolicy) -> Self {
+        self.source_ip_policy = policy;
+        self
+    }
+
+    /// Hand inner packets to a bounded TUN write queue instead of writing them directly
+    pub fn with_tun_queue(mut self, queue: Arc<TunWriteQueue>) -> Self {
+        self.tun_queue = Some(queue);
+        self
+    }
+
+    /// Split outbound payloads larger than `fragment_size` for sessions that negotiated fragmentation (0 = never split)
+    pub fn with_fragment_size(mut self, fragment_size: usize) -> Self {
+        self.fragment_size = fragment_size;
+        self
+    }
+
+    /// Attach a chaos controller for fault injection on outbound Data packets
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, chaos: Arc<crate::server::chaos::ChaosController>) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    /// Charge one inbound packet of `bytes` against the client's packet and byte rate limits.
+    ///
+    /// `tier_bytes_per_sec` is the byte limit from the client's ACL entry; it
+    /// replaces the server default when non-zero, and an operator override
+    /// replaces both. Buckets are rebuilt when the tier limit changes.
+    async fn check_traffic_limits(&self, client_id: &str, bytes: usize, tier_bytes_per_sec: u64) -> Result<(), RoutingError> {
+        let tier_limits = match tier_bytes_per_sec {
+            0 => self.traffic_limits,
+            bytes_per_sec => TrafficLimits { bytes_per_sec, ..self.traffic_limits },
+        };
+        if tier_limits == TrafficLimits::UNLIMITED && self.rate_overrides.lock().await.is_empty() {
+            return Ok(());
+        }
+
+        // Lock order: client_buckets, then rate_overrides
+        let (packet_ok, byte_ok, limits) = {
+            let mut buckets = self.client_buckets.lock().await;
+            let client = match buckets.entry(client_id.to_string()) {
+                std::collections::hash_map::Entry::Occupied(entry) => {
+                    let client = entry.into_mut();
+                    if !client.overridden && client.limits != tier_limits {
+                        *client = ClientBuckets::new(tier_limits);
+                    }
+                    client
+                }
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    let buckets = match self.rate_overrides.lock().await.get(client_id) {
+                        Some(o) => ClientBuckets::for_override(*o),
+                        None => ClientBuckets::new(tier_limits),
+                    };
+                    entry.insert(buckets)
+                }
+            };
+            let packet_ok = client.packets.as_mut().map_or(true, |b| b.try_consume(1));
+            // Only charge bytes for packets that passed the packet limit
+            let byte_ok = !packet_ok || client.bytes.as_mut().map_or(true, |b| b.try_consume(bytes as u64));
+            (packet_ok, byte_ok, client.limits)
+        };
+
+        if !packet_ok {
+            if let Some(metrics) = &self.metrics {
+                metrics.record_packet_rate_drop().await;
+            }
+            return Err(RoutingError::RateLimited(format!(
+                "{} exceeded {} packets/sec", client_id, limits.packets_per_sec
+            )));
+        }
+        if !byte_ok {
+            if let Some(metrics) = &self.metrics {
+                metrics.record_byte_rate_drop().await;
+            }
+            return Err(RoutingError::RateLimited(format!(
+                "{} exceeded {} bytes/sec", client_id, limits.bytes_per_sec
+            )));
+        }
+        Ok(())
+    }
+
+    /// Override a client's traffic limits for its active and future sessions,
+    /// replacing its running buckets immediately
+    #[cfg(any(feature = "admin-api", test))]
+    pub async fn set_rate_limit_override(&self, client_id: &str, rate_override: RateLimitOverride) {
+        let mut buckets = self.client_buckets.lock().await;
+        self.rate_overrides.lock().await.insert(client_id.to_string(), rate_override);
+        buckets.insert(client_id.to_string(), ClientBuckets::for_override(rate_override));
+    }
+
+    /// Remove a client's override and revert it to the server defaults.
+    /// Returns false if the client had no override.
+    #[cfg(any(feature = "admin-api", test))]
+    pub async fn clear_rate_limit_override(&self, client_id: &str) -> bool {
+        let mut buckets = self.client_buckets.lock().await;
+        let removed = self.rate_overrides.lock().await.remove(client_id).is_some();
+        if removed {
+            buckets.remove(client_id);
         }
+        removed
+    }
+
+    /// Get a client's traffic limit override, if any
+    #[cfg(any(feature = "admin-api", test))]
+    pub async fn rate_limit_override(&self, client_id: &str) -> Option<RateLimitOverride> {
+        self.rate_overrides.lock().await.get(client_id).copied()
+    }
+
+    /// Bytes held for a client's incomplete fragmented payloads
+    pub async fn reassembly_bytes(&self, client_id: &str) -> usize {
+        self.reassembly.lock().await.get(client_id).map_or(0, FragmentReassembler::buffered)
+    }
+
+    /// Forget rate limiting state for a disconnected client
+    pub async fn remove_client(&self, client_id: &str) {
+        self.client_buckets.lock().await.remove(client_id);
+        self.packet_too_big_sent.lock().await.remove(client_id);
+        self.reassembly.lock().await.remove(client_id);
+    }
+
+    /// Reject inner packets larger than the tunnel MTU, notifying clients that support it
+    async fn check_tunnel_mtu(&self, packet: &[u8], session: &ClientSession) -> Result<(), RoutingError> {
+        if packet.len() <= self.tunnel_mtu {
+            return Ok(());
+        }
+
+        debug!("Dropping {} byte packet from {}: exceeds tunnel MTU {}",
+               packet.len(), session.client_id, self.tunnel_mtu);
+
+        if session.capabilities.packet_too_big {
+            let should_notify = {
+                let mut sent = self.packet_too_big_sent.lock().await;
+                let now = Instant::now();
+                match sent.get(&session.client_id) {
+                    Some(last) if now.duration_since(*last) < PACKET_TOO_BIG_INTERVAL => false,
+                    _ => {
+                        sent.insert(session.client_id.clone(), now);
+                        true
+                    }
+                }
+            };
+
+            if should_notify {
+                let notice = PacketType::PacketTooBig {
+                    size: packet.len() as u32,
+                    mtu: self.tunnel_mtu as u16,
+                };
+                if let Err(e) = session.send_packet(&notice).await {
+                    debug!("Failed to send PacketTooBig to {}: {}", session.client_id, e);
+                }
+            }
+        }
+
+        Err(RoutingError::InvalidPacket(format!(
+            "Packet size {} exceeds tunnel MTU {}",
+            packet.len(), self.tunnel_mtu
+        )))
+    }
+
+    /// Drop inner packets whose IP protocol is not in the allowlist.
+    ///
+    /// Packets whose header is too short to carry a protocol field are
+    /// dropped too. For IPv6 the fixed header's Next Header is checked, so
+    /// traffic behind extension headers must allow the extension header type.
+    async fn check_protocol_allowlist(&self, packet: &[u8], session: &ClientSession) -> Result<(), RoutingError> {
+        let allowed = match &self.allowed_protocols {
+            Some(allowed) => allowed,
+            None => return Ok(()),
+        };
+
+        let reason = match inner_ip_protocol(packet) {
+            Some(protocol) if allowed.contains(&protocol) => return Ok(()),
+            Some(protocol) => format!("IP protocol {} not allowed", protocol),
+            None => "Malformed IP header".to_string(),
+        };
+
+        trace!("Dropping packet from {}: {}", session.client_id, reason);
+        if let Some(metrics) = &self.metrics {
+            metrics.record_protocol_drop().await;
+        }
+        Err(RoutingError::InvalidPacket(reason))
+    }
+
+    /// Check that an inner packet's source is the session's assigned tunnel IP.
+    ///
+    /// Mismatches and malformed headers are logged and counted; only the
+    /// strict policy drops them. Sessions are assigned IPv4 addresses, so
+    /// IPv6 packets never match.
+    async fn check_source_ip(&self, packet: &[u8], session: &ClientSession) -> Result<(), RoutingError> {
+        let source = inner_source_ip(packet);
+        if source.is_some() && source == session.ip_address.parse().ok() {
+            return Ok(());
+        }
+
+        let reason = match source {
+            Some(source) => format!("Source {} does not match assigned IP {}", source, session.ip_address),
+            None => "Malformed IP header".to_string(),
+        };
+        if let Some(metrics) = &self.metrics {
+            metrics.record_source_ip_mismatch().await;
+        }
+        match self.source_ip_policy {
+            SourceIpPolicy::Strict => {
+                warn!("Dropping packet from {}: {}", session.client_id, reason);
+                Err(RoutingError::SecurityRisk(reason))
+            }
+            SourceIpPolicy::Permissive => {
+                debug!("Forwarding packet from {} despite source check: {}", session.client_id, reason);
+                Ok(())
+            }
+        }
+    }
+
+    /// Check whether outbound traffic padding is enabled
+    pub fn padding_enabled(&self) -> bool {
+        self.enable_padding
     }
 
     /// Process an IP packet and extract routing information
@@ -124,6 +560,7 @@ impl PacketRouter {
         packet: &[u8],
         session_key: &[u8],
         session: &ClientSession,
+        session_keys: &SessionKeyManager,
     ) -> Result<(), RoutingError> {
         // Check packet size
         if packet.len() > self.max_packet_size {
@@ -133,57 +570,165 @@ impl PacketRouter {
             )));
         }
 
+        if let Some(tracker) = &self.flow_tracker {
+            tracker.observe(packet).await;
+        }
+
+        let mut packet_data = packet.to_vec();
+        if self.mss_clamp && clamp_tcp_mss(&mut packet_data, self.tunnel_mtu as u16) {
+            trace!("Clamped TCP MSS on SYN to {}", session.client_id);
+        }
+
         // Apply padding if enabled
-        let packet_data = if self.enable_padding && self.should_add_padding() {
-            self.add_padding(packet)
-        } else {
-            packet.to_vec()
-        };
+        if self.enable_padding {
+            match self.padding_policy {
+                PaddingPolicy::Random if self.should_add_padding() => packet_data = self.add_padding(&packet_data),
+                PaddingPolicy::Bucket => packet_data = self.pad_to_bucket(&packet_data),
+                PaddingPolicy::Random => {}
+            }
+        }
 
         // Get the session's encryption algorithm
         let algorithm = EncryptionAlgorithm::from_str(
             &session.encryption_algorithm
         ).unwrap_or_default();
         
-        // Use flexible encryption
-        let encrypted_packet = crate::crypto::flexible_encryption::encrypt_packet(
-            &packet_data, session_key, Some(algorithm)
-        ).map_err(|e| RoutingError::Encryption(e.to_string()))?;
+        // Sessions that negotiated fragmentation get large payloads in pieces
+        let fragments: Vec<(Option<FragmentHeader>, &[u8])> =
+            if session.capabilities.fragmentation && self.fragment_size > 0 && packet_data.len() > self.fragment_size {
+                let message_id = self.next_fragment_message.fetch_add(1, Ordering::Relaxed);
+                split_payload(&packet_data, self.fragment_size, message_id)
+                    .into_iter()
+                    .map(|(header, chunk)| (Some(header), chunk))
+                    .collect()
+            } else {
+                vec![(None, &packet_data[..])]
+            };
 
-        // Get next packet counter
-        let counter = {
-            let mut counter = self.packet_counter.lock().await;
-            let value = *counter;
-            *counter = value.wrapping_add(1);
-            value
-        };
+        // Create data packets with algorithm info
+        let mut data_packets = Vec::with_capacity(fragments.len());
+        for (fragment, payload) in fragments {
+            let (encrypted, nonce, counter) = self.encrypt_outbound(payload, session_key, session, session_keys, algorithm).await?;
+            data_packets.push(PacketType::Data {
+                encrypted,
+                nonce,
+                counter,
+                padding: None,
+                encryption_algorithm: Some(algorithm.as_str().to_string()),
+                fragment,
+            });
+        }
 
-        // Create data packet with algorithm info
-        let data_packet = PacketType::Data {
-            encrypted: encrypted_packet.data,
-            nonce: encrypted_packet.nonce,
-            counter,
-            padding: None,
-            encryption_algorithm: Some(encrypted_packet.algorithm.as_str().to_string()),
-        };
+        #[cfg(feature = "chaos")]
+        if let Some(chaos) = &self.chaos {
+            use crate::server::chaos::ChaosAction;
+            match chaos.decide(&session.client_id).await {
+                ChaosAction::Deliver => {}
+                ChaosAction::Drop => {
+                    trace!("CHAOS MODE: dropped {} Data packet(s) for {}", data_packets.len(), session.client_id);
+                    return Ok(());
+                }
+                ChaosAction::Delay(delay) => {
+                    trace!("CHAOS MODE: delaying {} Data packet(s) for {} by {:?}", data_packets.len(), session.client_id, delay);
+                    // Delay in a separate task so the TUN reader is not blocked
+                    let session = session.clone();
+                    tokio::spawn(async move {
+                        tokio::time::sleep(delay).await;
+                        for data_packet in &data_packets {
+                            if let Err(e) = session.send_packet(data_packet).await {
+                                debug!("CHAOS MODE: failed to send delayed packet to {}: {}", session.client_id, e);
+                                break;
+                            }
+                        }
+                    });
+                    return Ok(());
+                }
+            }
+        }
 
         // Send to client
-        session.send_packet(&data_packet)
-            .await
-            .map_err(|e| RoutingError::Protocol(MessageError::InvalidFormat(e.to_string())))?;
+        for data_packet in &data_packets {
+            session.send_packet(data_packet)
+                .await
+                .map_err(|e| RoutingError::Protocol(MessageError::InvalidFormat(e.to_string())))?;
+        }
 
         Ok(())
     }
 
+    /// Encrypt one outbound payload, returning the ciphertext, wire nonce and counter
+    async fn encrypt_outbound(
+        &self,
+        payload: &[u8],
+        session_key: &[u8],
+        session: &ClientSession,
+        session_keys: &SessionKeyManager,
+        algorithm: EncryptionAlgorithm,
+    ) -> Result<(Vec<u8>, Vec<u8>, u64), RoutingError> {
+        // Counters come from the key, shared by all of the client's sessions, so none
+        // repeats under it. Sessions that negotiated derived nonces restart the counter
+        // per key and leave the nonce off the wire; others receive it as before.
+        let counter = session_keys
+            .next_send_counter(&session.client_id, session_key, session.capabilities.derived_nonce)
+            .await
+            .map_err(|e| RoutingError::Encryption(e.to_string()))?;
+        let (nonce, rotation_due) = {
+            let mut nonces = session.data_nonces().await;
+            let nonce = nonces.send_nonce(session_key, session.id.as_bytes(), counter)
+                .map_err(|e| RoutingError::Encryption(e.to_string()))?;
+            (nonce, nonces.take_rotation_due())
+        };
+        if rotation_due {
+            session.request_key_rotation();
+        }
+
+        let encrypted = crate::crypto::flexible_encryption::encrypt_flexible_with_nonce(
+            payload, session_key, algorithm, &nonce
+        ).map_err(|e| RoutingError::Encryption(e.to_string()))?;
+        let wire_nonce = if session.capabilities.derived_nonce { Vec::new() } else { nonce.to_vec() };
+        Ok((encrypted, wire_nonce, counter))
+    }
+
     /// Handle an inbound packet from a client with mixed mode support
     pub async fn handle_inbound_packet(
         &self,
         encrypted: &[u8],
         nonce: &[u8],
+        counter: u64,
         session_key: &[u8],
         session: &ClientSession,
         encryption_algorithm: Option<&str>,
+        fragment: Option<&FragmentHeader>,
     ) -> Result<usize, RoutingError> {
+        // Enforce rate limits before spending any effort on decryption
+        let tier_bytes_per_sec = session.policy().await.bandwidth_limit;
+        self.check_traffic_limits(&session.client_id, encrypted.len(), tier_bytes_per_sec).await?;
+
+        if fragment.is_some() && !session.capabilities.fragmentation {
+            return Err(RoutingError::InvalidPacket("Fragment from session without fragmentation".to_string()));
+        }
+
+        // Sessions with derived nonces take the nonce from the counter; one sent
+        // anyway must be that nonce, binding each counter to exactly one nonce
+        let derived_nonce = if session.capabilities.derived_nonce {
+            let derived = session.data_nonces().await.receive_nonce(session_key, session.id.as_bytes(), counter)
+                .map_err(|e| RoutingError::SecurityRisk(e.to_string()))?;
+            if !nonce.is_empty() && nonce != derived {
+                return Err(RoutingError::SecurityRisk(format!("Nonce does not match counter {}", counter)));
+            }
+            Some(derived)
+        } else if nonce.is_empty() {
+            return Err(RoutingError::InvalidPacket("Missing nonce".to_string()));
+        } else {
+            // Clients that do not count send 0 throughout and are not replay checked
+            if counter != 0 {
+                session.data_nonces().await.check_received(counter)
+                    .map_err(|e| RoutingError::SecurityRisk(e.to_string()))?;
+            }
+            None
+        };
+        let nonce = derived_nonce.as_ref().map_or(nonce, |derived| &derived[..]);
+
         // Determine which algorithm to use
         let algorithm = if let Some(algo) = encryption_algorithm {
             debug!("Using packet-specified algorithm: {}", algo);
@@ -210,6 +755,14 @@ impl PacketRouter {
         ) {
             Ok(data) => {
                 debug!("Packet decryption successful, received {} bytes", data.len());
+                // Only authenticated counters move the replay window
+                if derived_nonce.is_some() || counter != 0 {
+                    let mut nonces = session.data_nonces().await;
+                    nonces.accept_received(counter);
+                    if nonces.take_rotation_due() {
+                        session.request_key_rotation();
+                    }
+                }
                 data
             },
             Err(e) => {
@@ -220,6 +773,31 @@ impl PacketRouter {
             }
         };
 
+        // Fragments are buffered until the whole payload has arrived
+        let decrypted = match fragment {
+            None => decrypted,
+            Some(header) => {
+                let mut reassembly = self.reassembly.lock().await;
+                let reassembler = reassembly.entry(session.client_id.clone()).or_insert_with(|| {
+                    FragmentReassembler::new(
+                        FRAGMENT_REASSEMBLY_TIMEOUT, MAX_REASSEMBLY_BYTES, MAX_FRAGMENTS_PER_MESSAGE, MAX_INCOMPLETE_MESSAGES,
+                    )
+                });
+                match reassembler.insert(header, decrypted, Instant::now()) {
+                    Ok(Some(payload)) => payload,
+                    Ok(None) => {
+                        trace!("Buffered fragment {}/{} of message {} from {} ({} incomplete)",
+                               header.index + 1, header.total, header.message_id, session.client_id, reassembler.pending());
+                        return Ok(0);
+                    }
+                    Err(e) => {
+                        debug!("Dropping fragment from {}: {}", session.client_id, e);
+                        return Err(RoutingError::InvalidPacket(e.to_string()));
+                    }
+                }
+            }
+        };
+
         // Try to parse as a DataEnvelope
         match serde_json::from_slice::<DataEnvelope>(&decrypted) {
             Ok(envelope) => {
@@ -239,7 +817,7 @@ impl PacketRouter {
             Err(e) => {
                 // Legacy mode: Try direct IP packet (without envelope)
                 debug!("Failed to parse as DataEnvelope: {}. Trying legacy mode as direct IP packet.", e);
-                return self.write_to_tun_device(&decrypted).await;
+                return self.write_to_tun_device(&decrypted, session).await;
             }
         }
     }
@@ -284,7 +862,7 @@ impl PacketRouter {
             // Get session key for the requesting client
             if let Some(session_key) = session_key_manager.get_key(&session.client_id).await {
                 // Forward the chat info to the requesting client
-                self.forward_envelope_to_session(&envelope, &session_key, session).await?;
+                self.forward_envelope_to_session(&envelope, &session_key, session, &session_key_manager).await?;
             } else {
                 return Err(RoutingError::Processing(format!("No session key found for {}", session.client_id)));
             }
@@ -337,7 +915,7 @@ impl PacketRouter {
                     // Get target's session key
                     if let Some(target_key) = session_key_manager.get_key(&target_session.client_id).await {
                         // Try to forward the notification
-                        if let Err(e) = self.forward_envelope_to_session(&envelope, &target_key, &target_session).await {
+                        if let Err(e) = self.forward_envelope_to_session(&envelope, &target_key, &target_session, &session_key_manager).await {
                             debug!("Failed to forward leave notification to {}: {}", target_session.client_id, e);
                             // Continue with other sessions despite errors
                         }
@@ -363,7 +941,7 @@ impl PacketRouter {
         if let Some(session_key_manager) = crate::server::globals::get_session_key_manager() {
             if let Some(session_key) = session_key_manager.get_key(&session.client_id).await {
                 // Send confirmation
-                self.forward_envelope_to_session(&envelope, &session_key, session).await?;
+                self.forward_envelope_to_session(&envelope, &session_key, session, &session_key_manager).await?;
             }
         }
         
@@ -408,7 +986,7 @@ async fn handle_delete_chat_request(
         // Send error response to requester
         if let Some(session_key_manager) = crate::server::globals::get_session_key_manager() {
             if let Some(session_key) = session_key_manager.get_key(&_session.client_id).await {
-                self.forward_envelope_to_session(&envelope, &session_key, _session).await?;
+                self.forward_envelope_to_session(&envelope, &session_key, _session, &session_key_manager).await?;
             }
         }
         
@@ -444,7 +1022,7 @@ async fn handle_delete_chat_request(
                 }
                 
                 // Try to forward the notification
-                if let Err(e) = self.forward_envelope_to_session(&envelope, &target_key, &target_session).await {
+                if let Err(e) = self.forward_envelope_to_session(&envelope, &target_key, &target_session, &session_key_manager).await {
                     debug!("Failed to forward deletion notification to {}: {}", target_session.client_id, e);
                 }
             }
@@ -515,7 +1093,7 @@ async fn handle_delete_chat_request(
     async fn process_ip_payload(
         &self,
         payload: serde_json::Value,
-        _session: &ClientSession,
+        session: &ClientSession,
     ) -> Result<usize, RoutingError> {
         // Extract Base64 string from the payload
         let base64_ip = payload.as_str()
@@ -526,11 +1104,11 @@ async fn handle_delete_chat_request(
             .map_err(|e| RoutingError::InvalidPacket(format!("Invalid Base64 IP payload: {}", e)))?;
         
         // Write the IP packet to the TUN device
-        self.write_to_tun_device(&ip_packet_bytes).await
+        self.write_to_tun_device(&ip_packet_bytes, session).await
     }
     
     /// Helper method to write data to the TUN device
-    async fn write_to_tun_device(&self, data: &[u8]) -> Result<usize, RoutingError> {
+    async fn write_to_tun_device(&self, data: &[u8], session: &ClientSession) -> Result<usize, RoutingError> {
         // Check packet size
         if data.len() > self.max_packet_size {
             return Err(RoutingError::InvalidPacket(format!(
@@ -539,14 +1117,8 @@ async fn handle_delete_chat_request(
             )));
         }
     
-        // Check for attack patterns
-        if let Some(reason) = detect_attack_patterns(data) {
-            warn!("Security risk in packet: {}", reason);
-            return Err(RoutingError::SecurityRisk(reason));
-        }
-    
-        // Remove padding if necessary
-        let packet_data = if self.enable_padding {
+        // Remove padding if necessary, so only the real packet is inspected and forwarded
+        let mut packet_data = if self.enable_padding {
             match self.remove_padding(data) {
                 Ok(clean_data) => {
                     debug!("Padding removed, packet size reduced from {} to {} bytes", 
@@ -561,6 +1133,46 @@ async fn handle_delete_chat_request(
         } else {
             data.to_vec()
         };
+    
+        // Check for attack patterns
+        if let Some(reason) = detect_attack_patterns(&packet_data) {
+            warn!("Security risk in packet: {}", reason);
+            return Err(RoutingError::SecurityRisk(reason));
+        }
+        
+        // Spoofed packets are refused before anything else is done on their behalf
+        self.check_source_ip(&packet_data, session).await?;
+        
+        // Oversized inner packets cannot be forwarded
+        self.check_tunnel_mtu(&packet_data, session).await?;
+        self.check_protocol_allowlist(&packet_data, session).await?;
+        
+        if self.mss_clamp && clamp_tcp_mss(&mut packet_data, self.tunnel_mtu as u16) {
+            trace!("Clamped TCP MSS on SYN from {}", session.client_id);
+        }
+        
+        if let Some(tracker) = &self.flow_tracker {
+            tracker.observe(&packet_data).await;
+        }
+        
+        if let Some(queue) = &self.tun_queue {
+            let len = packet_data.len();
+            return match queue.push(packet_data).await {
+                Enqueue::Queued => Ok(len),
+                Enqueue::DroppedOldest => {
+                    if let Some(metrics) = &self.metrics {
+                        metrics.record_tun_queue_drop().await;
+                    }
+                    Ok(len)
+                }
+                Enqueue::Full => {
+                    if let Some(metrics) = &self.metrics {
+                        metrics.record_tun_queue_drop().await;
+                    }
+                    Err(RoutingError::TunWrite("TUN write queue full".to_string()))
+                }
+            };
+        }
         
         // Get the TUN device
         if let Some(tun_device) = crate::server::globals::get_tun_device() {
@@ -623,7 +1235,7 @@ async fn handle_delete_chat_request(
                 if let Some(session_key_manager) = crate::server::globals::get_session_key_manager() {
                     if let Some(target_key) = session_key_manager.get_key(&target_session.client_id).await {
                         // Create encrypted message packet
-                        if let Err(e) = self.forward_envelope_to_session(&envelope, &target_key, &target_session).await {
+                        if let Err(e) = self.forward_envelope_to_session(&envelope, &target_key, &target_session, &session_key_manager).await {
                             warn!("Failed to forward message to {}: {}", target_session.client_id, e);
                             // Continue with other sessions - don't fail entire operation for one recipient
                         }
@@ -645,6 +1257,7 @@ async fn handle_delete_chat_request(
         envelope: &DataEnvelope,
         target_key: &[u8],
         target_session: &ClientSession,
+        session_keys: &SessionKeyManager,
     ) -> Result<(), RoutingError> {
         // Serialize the envelope
         let envelope_data = serde_json::to_vec(envelope)
@@ -655,28 +1268,17 @@ async fn handle_delete_chat_request(
             &target_session.encryption_algorithm
         ).unwrap_or_default();
         
-        // Encrypt the envelope
-        let encrypted_packet = crate::crypto::flexible_encryption::encrypt_packet(
-            &envelope_data,
-            target_key,
-            Some(algorithm),
-        ).map_err(|e| RoutingError::Encryption(e.to_string()))?;
-        
-        // Get next packet counter (should be atomic)
-        let counter = {
-            let mut counter = self.packet_counter.lock().await;
-            let value = *counter;
-            *counter = value.wrapping_add(1);
-            value
-        };
+        // Encrypt the envelope under the target session's nonce counter
+        let (encrypted, nonce, counter) = self.encrypt_outbound(&envelope_data, target_key, target_session, session_keys, algorithm).await?;
         
         // Create Data packet
         let data_packet = crate::protocol::types::PacketType::Data {
-            encrypted: encrypted_packet.data,
-            nonce: encrypted_packet.nonce,
+            encrypted,
+            nonce,
             counter,
             padding: None,
-            encryption_algorithm: Some(encrypted_packet.algorithm.as_str().to_string()),
+            encryption_algorithm: Some(algorithm.as_str().to_string()),
+            fragment: None,
         };
         
         // Send to target client
@@ -730,7 +1332,7 @@ async fn handle_delete_chat_request(
         if let Some(session_key_manager) = crate::server::globals::get_session_key_manager() {
             if let Some(session_key) = session_key_manager.get_key(&session.client_id).await {
                 // Forward to requesting client
-                self.forward_envelope_to_session(&envelope, &session_key, session).await?;
+                self.forward_envelope_to_session(&envelope, &session_key, session, &session_key_manager).await?;
             } else {
                 return Err(RoutingError::Processing(format!("No session key found for {}", session.client_id)));
             }
@@ -771,7 +1373,7 @@ async fn handle_delete_chat_request(
             // Get target session key
             if let Some(target_key) = session_key_manager.get_key(&target_session.client_id).await {
                 // Forward signal
-                self.forward_envelope_to_session(&envelope, &target_key, &target_session).await?;
+                self.forward_envelope_to_session(&envelope, &target_key, &target_session, &session_key_manager).await?;
             } else {
                 return Err(RoutingError::Processing(format!("No session key found for {}", target_session.client_id)));
             }
@@ -789,23 +1391,16 @@ async fn handle_delete_chat_request(
 
     /// Add random padding to a packet
     fn add_padding(&self, packet: &[u8]) -> Vec<u8> {
-        let mut rng = thread_rng();
-        let padding_len = rng.gen_range(MIN_PADDING_SIZE..=MAX_PADDING_SIZE);
-
-        let mut result = Vec::with_capacity(packet.len() + padding_len + 2);
-
-        // Add padding length as two bytes (big-endian)
-        result.extend_from_slice(&(padding_len as u16).to_be_bytes());
-
-        // Add the original packet
-        result.extend_from_slice(packet);
-
-        // Add random padding
-        for _ in 0..padding_len {
-            result.push(rng.gen::<u8>());
-        }
+        let padding_len = thread_rng().gen_range(MIN_PADDING_SIZE..=MAX_PADDING_SIZE);
+        frame_with_padding(packet, padding_len)
+    }
 
-        result
+    /// Pad a packet so that, with its length prefix, it fills a whole number of buckets
+    fn pad_to_bucket(&self, packet: &[u8]) -> Vec<u8> {
+        let framed_len = packet.len() + 2;
+        let bucket = self.padding_bucket_size;
+        let padded_len = (framed_len + bucket - 1) / bucket * bucket;
+        frame_with_padding(packet, (padded_len - framed_len).min(u16::MAX as usize))
     }
 
     /// Remove padding from a packet
@@ -835,6 +1430,25 @@ async fn handle_delete_chat_request(
     }
 }
 
+/// Prefix a packet with the padding length and append that many random bytes
+fn frame_with_padding(packet: &[u8], padding_len: usize) -> Vec<u8> {
+    let mut rng = thread_rng();
+    let mut result = Vec::with_capacity(packet.len() + padding_len + 2);
+
+    // Add padding length as two bytes (big-endian)
+    result.extend_from_slice(&(padding_len as u16).to_be_bytes());
+
+    // Add the original packet
+    result.extend_from_slice(packet);
+
+    // Add random padding
+    for _ in 0..padding_len {
+        result.push(rng.gen::<u8>());
+    }
+
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1042,4 +1656,586 @@ mod tests {
         let decoded = base64::decode(extracted_base64).unwrap();
         assert_eq!(decoded, ip_data);
     }
+
+    #[tokio::test]
+    async fn test_traffic_limits_packet_and_byte_rate() {
+        let metrics = Arc::new(ServerMetricsCollector::new(std::time::Duration::from_secs(60), 10));
+        let router = PacketRouter::new(2048, false)
+            .with_traffic_limits(TrafficLimits { packets_per_sec: 3, bytes_per_sec: 1000 })
+            .with_metrics(metrics.clone());
+
+        // Many tiny packets trip the packet limit while far under the byte limit
+        for _ in 0..3 {
+            assert!(router.check_traffic_limits("tiny", 10, 0).await.is_ok());
+        }
+        assert!(matches!(router.check_traffic_limits("tiny", 10, 0).await, Err(RoutingError::RateLimited(_))));
+
+        // A few large packets trip the byte limit while under the packet limit
+        assert!(router.check_traffic_limits("bulk", 600, 0).await.is_ok());
+        assert!(matches!(router.check_traffic_limits("bulk", 600, 0).await, Err(RoutingError::RateLimited(_))));
+
+        let snapshot = metrics.get_metrics().await;
+        assert_eq!(snapshot.packet_rate_drops, 1);
+        assert_eq!(snapshot.byte_rate_drops, 1);
+
+        // State is per client and can be cleared
+        router.remove_client("tiny").await;
+        assert!(router.check_traffic_limits("tiny", 10, 0).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_override() {
+        let router = PacketRouter::new(2048, false)
+            .with_traffic_limits(TrafficLimits { packets_per_sec: 100, bytes_per_sec: 0 });
+        assert!(router.check_traffic_limits("abuser", 10, 0).await.is_ok());
+
+        // Tightening applies to the running buckets immediately
+        router.set_rate_limit_override("abuser", RateLimitOverride {
+            limits: TrafficLimits { packets_per_sec: 2, bytes_per_sec: 0 },
+            burst_secs: 1,
+        }).await;
+        assert!(router.check_traffic_limits("abuser", 10, 0).await.is_ok());
+        assert!(router.check_traffic_limits("abuser", 10, 0).await.is_ok());
+        let err = router.check_traffic_limits("abuser", 10, 0).await.unwrap_err();
+        assert!(err.to_string().contains("exceeded 2 packets/sec"));
+
+        // Other clients keep the defaults; the override survives disconnects
+        assert!(router.check_traffic_limits("other", 10, 0).await.is_ok());
+        router.remove_client("abuser").await;
+        for _ in 0..2 {
+            assert!(router.check_traffic_limits("abuser", 10, 0).await.is_ok());
+        }
+        assert!(router.check_traffic_limits("abuser", 10, 0).await.is_err());
+
+        // Burst scales the bucket size
+        router.set_rate_limit_override("vip", RateLimitOverride {
+            limits: TrafficLimits { packets_per_sec: 2, bytes_per_sec: 0 },
+            burst_secs: 3,
+        }).await;
+        for _ in 0..6 {
+            assert!(router.check_traffic_limits("vip", 10, 0).await.is_ok());
+        }
+        assert!(router.check_traffic_limits("vip", 10, 0).await.is_err());
+
+        // Clearing reverts to the defaults
+        assert!(router.clear_rate_limit_override("abuser").await);
+        assert!(!router.clear_rate_limit_override("abuser").await);
+        assert!(router.rate_limit_override("abuser").await.is_none());
+        for _ in 0..10 {
+            assert!(router.check_traffic_limits("abuser", 10, 0).await.is_ok());
+        }
+
+        // Overrides apply even when the defaults are unlimited
+        let router = PacketRouter::new(2048, false);
+        router.set_rate_limit_override("client", RateLimitOverride {
+            limits: TrafficLimits { packets_per_sec: 1, bytes_per_sec: 0 },
+            burst_secs: 1,
+        }).await;
+        assert!(router.check_traffic_limits("client", 10, 0).await.is_ok());
+        assert!(router.check_traffic_limits("client", 10, 0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tier_byte_limit_smoothing() {
+        let router = PacketRouter::new(2048, false)
+            .with_traffic_limits(TrafficLimits { packets_per_sec: 0, bytes_per_sec: 1000 });
+
+        // The ACL tier replaces the default byte limit, burst included
+        for _ in 0..4 {
+            assert!(router.check_traffic_limits("premium", 1000, 4000).await.is_ok());
+        }
+        assert!(router.check_traffic_limits("premium", 1000, 4000).await.is_err());
+        assert!(router.check_traffic_limits("basic", 1000, 0).await.is_ok());
+        assert!(router.check_traffic_limits("basic", 1000, 0).await.is_err());
+
+        // A changed tier takes effect on the next packet
+        assert!(router.check_traffic_limits("premium", 1000, 500).await.is_err());
+
+        // Refill follows elapsed time: after draining the burst, a steady
+        // stream is admitted at the configured rate
+        let router = PacketRouter::new(2048, false)
+            .with_traffic_limits(TrafficLimits { packets_per_sec: 0, bytes_per_sec: 10_000 });
+        while router.check_traffic_limits("client", 100, 0).await.is_ok() {}
+        let start = std::time::Instant::now();
+        let mut admitted = 0u64;
+        while start.elapsed() < std::time::Duration::from_millis(500) {
+            if router.check_traffic_limits("client", 100, 0).await.is_ok() {
+                admitted += 100;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+        }
+        let rate = admitted as f64 / start.elapsed().as_secs_f64();
+        assert!((7_000.0..=11_000.0).contains(&rate), "smoothed rate {} bytes/sec", rate);
+    }
+
+    #[tokio::test]
+    async fn test_traffic_limits_unlimited() {
+        let router = PacketRouter::new(2048, false);
+        for _ in 0..100 {
+            assert!(router.check_traffic_limits("client", 16384, 0).await.is_ok());
+        }
+    }
+
+    fn ipv4_header(protocol: u8) -> Vec<u8> {
+        let mut packet = vec![0x45, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, 0x00,
+                              0x40, protocol, 0x00, 0x00, 10, 7, 0, 5, 8, 8, 8, 8];
+        packet.extend_from_slice(&[0u8; 8]);
+        packet
+    }
+
+    #[tokio::test]
+    async fn test_protocol_allowlist() {
+        let metrics = Arc::new(ServerMetricsCollector::new(std::time::Duration::from_secs(60), 10));
+        let protocols = ["tcp", "udp"].iter().filter_map(|p| parse_ip_protocol(p));
+        let router = PacketRouter::new(2048, false)
+            .with_protocol_allowlist(protocols)
+            .with_metrics(metrics.clone());
+        let (session, _peer) = mock_session("client", false);
+
+        // Allowed: TCP and UDP over IPv4, UDP over IPv6
+        assert!(router.check_protocol_allowlist(&ipv4_header(6), &session).await.is_ok());
+        assert!(router.check_protocol_allowlist(&ipv4_header(17), &session).await.is_ok());
+        let mut ipv6 = vec![0u8; 48];
+        ipv6[0] = 0x60;
+        ipv6[6] = 17;
+        assert!(router.check_protocol_allowlist(&ipv6, &session).await.is_ok());
+
+        // Blocked: ICMP, GRE, ICMPv6
+        assert!(router.check_protocol_allowlist(&ipv4_header(1), &session).await.is_err());
+        assert!(router.check_protocol_allowlist(&ipv4_header(47), &session).await.is_err());
+        ipv6[6] = 58;
+        assert!(router.check_protocol_allowlist(&ipv6, &session).await.is_err());
+
+        // Malformed headers are dropped without panicking
+        let mut bad_ihl = ipv4_header(6);
+        bad_ihl[0] = 0x44;
+        let mut long_ihl = ipv4_header(6);
+        long_ihl[0] = 0x4f;
+        for packet in [&[][..], &[0x45][..], &ipv4_header(6)[..19], &bad_ihl[..], &long_ihl[..], &ipv6[..39], &[0x00; 40][..]] {
+            assert!(matches!(
+                router.check_protocol_allowlist(packet, &session).await,
+                Err(RoutingError::InvalidPacket(_))
+            ));
+        }
+        assert_eq!(metrics.get_metrics().await.protocol_drops, 10);
+
+        // Default: everything passes, even malformed packets (checked elsewhere)
+        let router = PacketRouter::new(2048, false).with_protocol_allowlist(Vec::new());
+        assert!(router.check_protocol_allowlist(&ipv4_header(47), &session).await.is_ok());
+        assert!(router.check_protocol_allowlist(&[], &session).await.is_ok());
+
+        assert_eq!(parse_ip_protocol("ICMPv6"), Some(58));
+        assert_eq!(parse_ip_protocol("132"), Some(132));
+        assert_eq!(parse_ip_protocol("sctp"), None);
+        assert_eq!(parse_ip_protocol("256"), None);
+    }
+
+    #[tokio::test]
+    async fn test_source_ip_validation() {
+        let metrics = Arc::new(ServerMetricsCollector::new(std::time::Duration::from_secs(60), 10));
+        let router = PacketRouter::new(2048, false)
+            .with_source_ip_policy(SourceIpPolicy::Strict)
+            .with_metrics(metrics.clone());
+        let (session, _peer) = mock_session("client", false);
+
+        // Sourced from the session's tunnel IP 10.7.0.5
+        assert!(router.check_source_ip(&ipv4_header(6), &session).await.is_ok());
+
+        // Spoofed sources, IPv6 and malformed headers are dropped
+        let mut spoofed = ipv4_header(6);
+        spoofed[12..16].copy_from_slice(&[10, 7, 0, 6]);
+        let mut ipv6 = vec![0u8; 40];
+        ipv6[0] = 0x60;
+        for packet in [&spoofed[..], &ipv6[..], &ipv4_header(6)[..19], &[][..]] {
+            assert!(matches!(
+                router.check_source_ip(packet, &session).await,
+                Err(RoutingError::SecurityRisk(_))
+            ));
+        }
+        assert_eq!(metrics.get_metrics().await.source_ip_mismatches, 4);
+
+        // Permissive forwards the spoofed packet but still counts it
+        let router = PacketRouter::new(2048, false)
+            .with_source_ip_policy(SourceIpPolicy::Permissive)
+            .with_metrics(metrics.clone());
+        assert!(router.check_source_ip(&spoofed, &session).await.is_ok());
+        assert!(router.check_source_ip(&ipv4_header(6), &session).await.is_ok());
+        assert_eq!(metrics.get_metrics().await.source_ip_mismatches, 5);
+    }
+
+    fn mock_session(
+        client_id: &str,
+        packet_too_big: bool,
+    ) -> (ClientSession, crate::server::connection::mock::MockPeer) {
+        let (conn, peer) = crate::server::connection::mock::duplex();
+        let mut caps = crate::server::negotiation::NegotiatedCapabilities::default();
+        caps.packet_too_big = packet_too_big;
+        let session = ClientSession::new(
+            "session".to_string(),
+            client_id.to_string(),
+            "10.7.0.5".to_string(),
+            "127.0.0.1:9000".parse().unwrap(),
+            conn.sender(),
+            conn.receiver(),
+            None,
+        ).unwrap().with_capabilities(caps);
+        (session, peer)
+    }
+
+    /// Key manager holding `key` for each of `client_ids`
+    async fn key_manager(key: &[u8], client_ids: &[&str]) -> SessionKeyManager {
+        let manager = SessionKeyManager::new(std::time::Duration::from_secs(3600), 0);
+        for client_id in client_ids {
+            manager.store_key(client_id, key.to_vec()).await;
+        }
+        manager
+    }
+
+    #[tokio::test]
+    async fn test_packet_too_big_notification() {
+        let router = PacketRouter::new(16384, false);
+        let oversized = vec![0u8; TUN_MTU as usize + 1];
+
+        // Supporting clients get one rate-limited notice
+        let (session, mut peer) = mock_session("client", true);
+        assert!(router.check_tunnel_mtu(&vec![0u8; TUN_MTU as usize], &session).await.is_ok());
+        assert!(router.check_tunnel_mtu(&oversized, &session).await.is_err());
+        assert!(router.check_tunnel_mtu(&oversized, &session).await.is_err());
+
+        let msg = peer.from_server.try_recv().unwrap();
+        match crate::protocol::serialization::ws_message_to_packet(&msg).unwrap() {
+            PacketType::PacketTooBig { size, mtu } => {
+                assert_eq!(size as usize, oversized.len());
+                assert_eq!(mtu, TUN_MTU);
+            }
+            other => panic!("Unexpected packet: {:?}", other),
+        }
+        assert!(peer.from_server.try_recv().is_err());
+
+        // Legacy clients only see the drop
+        let (legacy, mut legacy_peer) = mock_session("legacy", false);
+        assert!(router.check_tunnel_mtu(&oversized, &legacy).await.is_err());
+        assert!(legacy_peer.from_server.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_derived_nonce_data_packets() {
+        use crate::crypto::nonce::{derive_nonce_iv, nonce_for_counter, NonceDirection};
+
+        let router = PacketRouter::new(16384, false);
+        let key = [4u8; 32];
+        let (session, mut peer) = mock_session("client", false);
+        let mut caps = session.capabilities.clone();
+        caps.derived_nonce = true;
+        let session = session.with_capabilities(caps);
+        let session_keys = key_manager(&key, &["client"]).await;
+
+        // Outbound packets omit the nonce and count up from 0
+        let payload = vec![0x45u8; 40];
+        for expected in 0..2u64 {
+            router.route_outbound_packet(&payload, &key, &session, &session_keys).await.unwrap();
+            let msg = peer.from_server.try_recv().unwrap();
+            assert!(!msg.to_text().unwrap().contains("nonce"));
+            match crate::protocol::serialization::ws_message_to_packet(&msg).unwrap() {
+                PacketType::Data { encrypted, nonce, counter, .. } => {
+                    assert!(nonce.is_empty());
+                    assert_eq!(counter, expected);
+                    let iv = derive_nonce_iv(&key, NonceDirection::ServerToClient, session.id.as_bytes()).unwrap();
+                    let decrypted = crate::crypto::flexible_encryption::decrypt_packet(
+                        &encrypted, &key, &nonce_for_counter(&iv, counter), EncryptionAlgorithm::default(), false,
+                    ).unwrap();
+                    assert_eq!(decrypted, payload);
+                }
+                other => panic!("Unexpected packet: {:?}", other),
+            }
+        }
+
+        // Inbound packets are decrypted with the derived nonce; replays are refused
+        let iv = derive_nonce_iv(&key, NonceDirection::ClientToServer, session.id.as_bytes()).unwrap();
+        let encrypted = crate::crypto::flexible_encryption::encrypt_flexible_with_nonce(
+            &payload, &key, EncryptionAlgorithm::default(), &nonce_for_counter(&iv, 7),
+        ).unwrap();
+        let first = router.handle_inbound_packet(&encrypted, &[], 7, &key, &session, None, None).await;
+        assert!(!matches!(first, Err(RoutingError::Decryption(_)) | Err(RoutingError::SecurityRisk(_))));
+        let replay = router.handle_inbound_packet(&encrypted, &[], 7, &key, &session, None, None).await;
+        assert!(matches!(replay, Err(RoutingError::SecurityRisk(_))));
+
+        // Sessions that did not negotiate the feature must send a nonce
+        let (legacy, _legacy_peer) = mock_session("legacy", false);
+        let result = router.handle_inbound_packet(&encrypted, &[], 8, &key, &legacy, None, None).await;
+        assert!(matches!(result, Err(RoutingError::InvalidPacket(_))));
+    }
+
+    #[tokio::test]
+    async fn test_inbound_replay_window() {
+        let router = PacketRouter::new(16384, false);
+        let key = [6u8; 32];
+        let (session, _peer) = mock_session("legacy", false);
+        let nonce = [7u8; 12];
+        let encrypted = crate::crypto::flexible_encryption::encrypt_flexible_with_nonce(
+            &[0x45u8; 40], &key, EncryptionAlgorithm::default(), &nonce,
+        ).unwrap();
+        let receive = |counter: u64| router.handle_inbound_packet(&encrypted, &nonce, counter, &key, &session, None, None);
+        let authenticated = |result: &Result<usize, RoutingError>| {
+            !matches!(result, Err(RoutingError::Decryption(_)) | Err(RoutingError::SecurityRisk(_)))
+        };
+
+        // Reordered packets are accepted, duplicates are not
+        assert!(authenticated(&receive(5).await));
+        assert!(authenticated(&receive(3).await));
+        assert!(matches!(receive(5).await, Err(RoutingError::SecurityRisk(_))));
+        assert!(matches!(receive(3).await, Err(RoutingError::SecurityRisk(_))));
+
+        // A forged packet with a far-ahead counter does not move the window
+        let forged = router.handle_inbound_packet(&[0u8; 56], &nonce, 1_000_000, &key, &session, None, None).await;
+        assert!(matches!(forged, Err(RoutingError::Decryption(_))));
+        assert!(authenticated(&receive(4).await));
+
+        // Counter 0 is what clients that do not count always send
+        assert!(authenticated(&receive(0).await));
+        assert!(authenticated(&receive(0).await));
+    }
+
+    #[tokio::test]
+    async fn test_fragmented_data_packets() {
+        use crate::protocol::serialization::ws_message_to_packet;
+        use crate::crypto::flexible_encryption::{decrypt_packet, encrypt_packet};
+
+        let router = PacketRouter::new(16384, false).with_fragment_size(256);
+        let key = [5u8; 32];
+        let (session, mut peer) = mock_session("client", false);
+        let session_keys = key_manager(&key, &["client"]).await;
+        let mut caps = session.capabilities.clone();
+        caps.fragmentation = true;
+        let session = session.with_capabilities(caps);
+
+        // Outbound payloads over the fragment size are split, each piece encrypted on its own
+        let payload: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+        router.route_outbound_packet(&payload, &key, &session, &session_keys).await.unwrap();
+        let mut reassembled = Vec::new();
+        let mut index = 0;
+        while let Ok(msg) = peer.from_server.try_recv() {
+            match ws_message_to_packet(&msg).unwrap() {
+                PacketType::Data { encrypted, nonce, fragment, .. } => {
+                    let fragment = fragment.expect("fragment header");
+                    assert_eq!((fragment.index, fragment.total), (index, 4));
+                    reassembled.extend(decrypt_packet(&encrypted, &key, &nonce, EncryptionAlgorithm::default(), false).unwrap());
+                    index += 1;
+                }
+                other => panic!("Unexpected packet: {:?}", other),
+            }
+        }
+        assert_eq!(reassembled, payload);
+
+        // Inbound fragments are reassembled, in any order, before the payload is dispatched
+        let envelope = DataEnvelope {
+            payload_type: PayloadDataType::Json,
+            payload: serde_json::json!({ "type": "noop", "data": "x".repeat(600) }),
+        };
+        let plaintext = serde_json::to_vec(&envelope).unwrap();
+        let fragments = split_payload(&plaintext, 256, 9);
+        assert_eq!(fragments.len(), 3);
+        let mut results = Vec::new();
+        for (counter, &i) in [2usize, 0, 1].iter().enumerate() {
+            let (header, chunk) = &fragments[i];
+            let encrypted = encrypt_packet(chunk, &key, None).unwrap();
+            results.push(router.handle_inbound_packet(
+                &encrypted.data, &encrypted.nonce, counter as u64, &key, &session, None, Some(header),
+            ).await.unwrap());
+        }
+        assert_eq!(results[..2], [0, 0]);
+        assert!(results[2] > 600);
+
+        // Sessions that did not negotiate the feature cannot send fragments
+        let (legacy, _legacy_peer) = mock_session("legacy", false);
+        let (header, chunk) = &fragments[0];
+        let encrypted = encrypt_packet(chunk, &key, None).unwrap();
+        let result = router.handle_inbound_packet(&encrypted.data, &encrypted.nonce, 0, &key, &legacy, None, Some(header)).await;
+        assert!(matches!(result, Err(RoutingError::InvalidPacket(_))));
+    }
+
+    #[tokio::test]
+    async fn test_outbound_mss_clamp() {
+        use crate::protocol::serialization::ws_message_to_packet;
+        use crate::crypto::flexible_encryption::decrypt_packet;
+
+        // SYN from 93.184.216.34:443 to 10.7.0.5:40000 advertising MSS 1460
+        let syn: Vec<u8> = vec![
+            0x45, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x40, 0x00, 0x40, 0x06, 0x00, 0x00,
+            93, 184, 216, 34, 10, 7, 0, 5,
+            0x01, 0xbb, 0x9c, 0x40, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+            0x60, 0x02, 0xfa, 0xf0, 0xbf, 0x52, 0x00, 0x00, 0x02, 0x04, 0x05, 0xb4,
+        ];
+        let key = [6u8; 32];
+        let (session, mut peer) = mock_session("client", false);
+        let session_keys = key_manager(&key, &["client"]).await;
+
+        let mut delivered = Vec::new();
+        for clamp in [false, true] {
+            let router = PacketRouter::new(16384, false).with_tunnel_mtu(1400).with_mss_clamp(clamp);
+            router.route_outbound_packet(&syn, &key, &session, &session_keys).await.unwrap();
+            match ws_message_to_packet(&peer.from_server.try_recv().unwrap()).unwrap() {
+                PacketType::Data { encrypted, nonce, .. } => {
+                    delivered.push(decrypt_packet(&encrypted, &key, &nonce, EncryptionAlgorithm::default(), false).unwrap());
+                }
+                other => panic!("Unexpected packet: {:?}", other),
+            }
+        }
+
+        // Unclamped SYNs pass through; clamped ones carry MSS 1360 and a fixed-up checksum
+        assert_eq!(delivered[0], syn);
+        assert_eq!(delivered[1][40..44], [0x02, 0x04, 0x05, 0x50]);
+        assert_eq!(delivered[1][36..38], [0xbf, 0xb6]);
+        assert_eq!(delivered[1][..36], syn[..36]);
+    }
+
+    #[tokio::test]
+    async fn test_bucket_padding_round_trip() {
+        use crate::protocol::serialization::ws_message_to_packet;
+        use crate::crypto::flexible_encryption::{decrypt_packet, encrypt_packet};
+        use crate::config::settings::TunQueuePolicy;
+
+        let queue = Arc::new(TunWriteQueue::new(8, TunQueuePolicy::DropTail));
+        let router = PacketRouter::new(16384, true)
+            .with_padding_policy(PaddingPolicy::Bucket, 256)
+            .with_tun_queue(queue.clone());
+        let key = [7u8; 32];
+        let (session, mut peer) = mock_session("client", false);
+        let session_keys = key_manager(&key, &["client"]).await;
+
+        // Every outbound payload fills whole buckets and strips back to the original
+        for len in [40usize, 254, 255, 600, 1400] {
+            let packet: Vec<u8> = std::iter::once(0x45).chain((1..len).map(|i| b'a' + (i % 26) as u8)).collect();
+            router.route_outbound_packet(&packet, &key, &session, &session_keys).await.unwrap();
+            let plaintext = match ws_message_to_packet(&peer.from_server.try_recv().unwrap()).unwrap() {
+                PacketType::Data { encrypted, nonce, .. } => {
+                    decrypt_packet(&encrypted, &key, &nonce, EncryptionAlgorithm::default(), false).unwrap()
+                }
+                other => panic!("Unexpected packet: {:?}", other),
+            };
+            assert_eq!(plaintext.len() % 256, 0, "{} byte packet not padded to a bucket", len);
+            assert_eq!(plaintext.len(), (len + 2 + 255) / 256 * 256);
+            assert_eq!(router.remove_padding(&plaintext).unwrap(), packet);
+
+            // Padded inbound packets reach the TUN device without their padding
+            let encrypted = encrypt_packet(&plaintext, &key, None).unwrap();
+            let written = router.handle_inbound_packet(
+                &encrypted.data, &encrypted.nonce, len as u64, &key, &session, None, None,
+            ).await.unwrap();
+            assert_eq!(written, len);
+            assert_eq!(queue.pop().await, packet);
+        }
+
+        // Without padding enabled the policy has no effect
+        let plain = PacketRouter::new(16384, false).with_padding_policy(PaddingPolicy::Bucket, 256);
+        let packet = vec![0x45u8; 40];
+        plain.route_outbound_packet(&packet, &key, &session, &session_keys).await.unwrap();
+        match ws_message_to_packet(&peer.from_server.try_recv().unwrap()).unwrap() {
+            PacketType::Data { encrypted, nonce, .. } => {
+                assert_eq!(decrypt_packet(&encrypted, &key, &nonce, EncryptionAlgorithm::default(), false).unwrap(), packet);
+            }
+            other => panic!("Unexpected packet: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nonce_counter_binding() {
+        use crate::crypto::nonce::{derive_nonce_iv, nonce_for_counter, NonceDirection};
+        use crate::protocol::serialization::ws_message_to_packet;
+
+        let router = PacketRouter::new(16384, false);
+        let key = [8u8; 32];
+        let session_keys = key_manager(&key, &["legacy", "client"]).await;
+
+        // Sessions without derived nonces still get counter-derived nonces, on the wire
+        let (legacy, mut legacy_peer) = mock_session("legacy", false);
+        let iv = derive_nonce_iv(&key, NonceDirection::ServerToClient, legacy.id.as_bytes()).unwrap();
+        for expected in 0..2u64 {
+            router.route_outbound_packet(&[0x45u8; 40], &key, &legacy, &session_keys).await.unwrap();
+            match ws_message_to_packet(&legacy_peer.from_server.try_recv().unwrap()).unwrap() {
+                PacketType::Data { nonce, counter, .. } => {
+                    assert_eq!(counter, expected);
+                    assert_eq!(nonce, nonce_for_counter(&iv, counter).to_vec());
+                }
+                other => panic!("Unexpected packet: {:?}", other),
+            }
+        }
+
+        // An explicit nonce from a derived-nonce session must match its counter
+        let (session, mut peer) = mock_session("client", false);
+        let mut caps = session.capabilities.clone();
+        caps.derived_nonce = true;
+        let session = session.with_capabilities(caps).with_data_key_rotation_after(2);
+        let receive_iv = derive_nonce_iv(&key, NonceDirection::ClientToServer, session.id.as_bytes()).unwrap();
+        let payload = vec![0x45u8; 40];
+        let encrypted = crate::crypto::flexible_encryption::encrypt_flexible_with_nonce(
+            &payload, &key, EncryptionAlgorithm::default(), &nonce_for_counter(&receive_iv, 3),
+        ).unwrap();
+        let mismatched = nonce_for_counter(&receive_iv, 4);
+        let result = router.handle_inbound_packet(&encrypted, &mismatched, 3, &key, &session, None, None).await;
+        assert!(matches!(result, Err(RoutingError::SecurityRisk(_))));
+        let matching = nonce_for_counter(&receive_iv, 3);
+        let result = router.handle_inbound_packet(&encrypted, &matching, 3, &key, &session, None, None).await;
+        assert!(!matches!(result, Err(RoutingError::Decryption(_)) | Err(RoutingError::SecurityRisk(_))));
+
+        // Reaching the per-key packet budget asks the session for a new key
+        router.route_outbound_packet(&payload, &key, &session, &session_keys).await.unwrap();
+        router.route_outbound_packet(&payload, &key, &session, &session_keys).await.unwrap();
+        assert!(peer.from_server.try_recv().is_ok());
+        tokio::time::timeout(std::time::Duration::from_secs(1), session.key_rotation_requested()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sessions_sharing_a_key_never_repeat_nonces() {
+        use crate::crypto::nonce::{derive_nonce_iv, nonce_for_counter, NonceDirection};
+        use crate::protocol::serialization::ws_message_to_packet;
+
+        let router = Arc::new(PacketRouter::new(16384, false));
+        let key = [9u8; 32];
+        let session_keys = Arc::new(key_manager(&key, &["client"]).await);
+
+        // Two sessions of one client under AllowMultiple, both on the client's key
+        let mut caps = crate::server::negotiation::NegotiatedCapabilities::default();
+        caps.derived_nonce = true;
+        let (first, mut first_peer) = mock_session("client", false);
+        let first = first.with_capabilities(caps.clone());
+        let (conn, mut second_peer) = crate::server::connection::mock::duplex();
+        let second = ClientSession::new(
+            "session_b".to_string(),
+            "client".to_string(),
+            "10.7.0.6".to_string(),
+            "127.0.0.1:9001".parse().unwrap(),
+            conn.sender(),
+            conn.receiver(),
+            None,
+        ).unwrap().with_capabilities(caps);
+
+        let send = move |session: ClientSession| {
+            let (router, session_keys) = (router.clone(), session_keys.clone());
+            tokio::spawn(async move {
+                for _ in 0..16 {
+                    router.route_outbound_packet(&[0x45u8; 40], &key, &session, &session_keys).await.unwrap();
+                }
+                session
+            })
+        };
+        let (first_task, second_task) = (send(first), send(second));
+        let (first, second) = (first_task.await.unwrap(), second_task.await.unwrap());
+
+        let mut counters = HashSet::new();
+        let mut nonces = HashSet::new();
+        for (session, peer) in [(&first, &mut first_peer), (&second, &mut second_peer)] {
+            let iv = derive_nonce_iv(&key, NonceDirection::ServerToClient, session.id.as_bytes()).unwrap();
+            while let Ok(msg) = peer.from_server.try_recv() {
+                match ws_message_to_packet(&msg).unwrap() {
+                    PacketType::Data { counter, .. } => {
+                        assert!(counters.insert(counter), "counter {} sent twice under one key", counter);
+                        assert!(nonces.insert(nonce_for_counter(&iv, counter)));
+                    }
+                    other => panic!("Unexpected packet: {:?}", other),
+                }
+            }
+        }
+        assert_eq!(counters.len(), 32);
+    }
 }
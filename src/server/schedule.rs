@@ -0,0 +1,166 @@
+// src/server/schedule.rs
+//! Recurring maintenance ("quiet hours") schedule.
+//!
+//! During a quiet window the server rejects new connections with a
+//! maintenance message and a reconnect hint. Existing sessions are left
+//! running. Windows are written as `[DAY] HH:MM-HH:MM`, evaluated in UTC
+//! against the server clock, e.g. `02:00-04:00` (every day) or
+//! `Sun 23:00-01:00` (Sunday night into Monday).
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveTime, Utc, Weekday};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Error type for schedule parsing
+#[derive(Debug, thiserror::Error)]
+pub enum ScheduleError {
+    #[error("Invalid quiet hours window '{0}': {1}")]
+    InvalidWindow(String, String),
+}
+
+/// A single recurring quiet window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuietWindow {
+    /// Day the window starts on (None = every day)
+    day: Option<Weekday>,
+    /// Start time (UTC)
+    start: NaiveTime,
+    /// End time (UTC); earlier than `start` when the window crosses midnight
+    end: NaiveTime,
+}
+
+impl QuietWindow {
+    /// Length of the window
+    fn length(&self) -> ChronoDuration {
+        let length = self.end - self.start;
+        if length <= ChronoDuration::zero() {
+            length + ChronoDuration::days(1)
+        } else {
+            length
+        }
+    }
+
+    /// Time remaining in this window at `now`, if it is active
+    fn remaining_at(&self, now: DateTime<Utc>) -> Option<ChronoDuration> {
+        // A window active now started either today or yesterday
+        for days_back in 0..=1 {
+            let date = (now - ChronoDuration::days(days_back)).date_naive();
+            if self.day.map_or(false, |day| date.weekday() != day) {
+                continue;
+            }
+            let start = date.and_time(self.start).and_utc();
+            let end = start + self.length();
+            if start <= now && now < end {
+                return Some(end - now);
+            }
+        }
+        None
+    }
+}
+
+impl FromStr for QuietWindow {
+    type Err = ScheduleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| ScheduleError::InvalidWindow(s.to_string(), reason.to_string());
+
+        let (day, range) = match s.trim().split_once(' ') {
+            Some((day, range)) => {
+                let day = Weekday::from_str(day.trim())
+                    .map_err(|_| invalid("unknown day, expected e.g. Mon or Sunday"))?;
+                (Some(day), range.trim())
+            }
+            None => (None, s.trim()),
+        };
+
+        let (start, end) = range.split_once('-')
+            .ok_or_else(|| invalid("expected HH:MM-HH:MM"))?;
+        let start = NaiveTime::parse_from_str(start.trim(), "%H:%M")
+            .map_err(|_| invalid("invalid start time"))?;
+        let end = NaiveTime::parse_from_str(end.trim(), "%H:%M")
+            .map_err(|_| invalid("invalid end time"))?;
+        if start == end {
+            return Err(invalid("window is empty"));
+        }
+
+        Ok(Self { day, start, end })
+    }
+}
+
+/// Set of recurring quiet windows
+#[derive(Debug, Clone, Default)]
+pub struct QuietHours {
+    windows: Vec<QuietWindow>,
+}
+
+impl QuietHours {
+    /// Parse a schedule from window specifications
+    pub fn parse(specs: &[String]) -> Result<Self, ScheduleError> {
+        let windows = specs.iter()
+            .map(|spec| spec.parse())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { windows })
+    }
+
+    /// Check whether any windows are configured
+    pub fn is_enabled(&self) -> bool {
+        !self.windows.is_empty()
+    }
+
+    /// Time until the current quiet period ends, if one is active at `now`
+    pub fn active_at(&self, now: DateTime<Utc>) -> Option<Duration> {
+        self.windows.iter()
+            .filter_map(|window| window.remaining_at(now))
+            .max()
+            .and_then(|remaining| remaining.to_std().ok())
+    }
+
+    /// Time until the current quiet period ends, if one is active now
+    pub fn active_now(&self) -> Option<Duration> {
+        self.active_at(Utc::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        // 2024-01-01 was a Monday
+        Utc.with_ymd_and_hms(2024, 1, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_quiet_windows() {
+        assert!("02:00-04:00".parse::<QuietWindow>().is_ok());
+        assert!("Sun 23:00-01:00".parse::<QuietWindow>().is_ok());
+        assert!("sunday 23:00-01:00".parse::<QuietWindow>().is_ok());
+        assert!("Someday 02:00-04:00".parse::<QuietWindow>().is_err());
+        assert!("02:00".parse::<QuietWindow>().is_err());
+        assert!("25:00-04:00".parse::<QuietWindow>().is_err());
+        assert!("02:00-02:00".parse::<QuietWindow>().is_err());
+    }
+
+    #[test]
+    fn test_quiet_hours_evaluation() {
+        let schedule = QuietHours::parse(&[
+            "02:00-04:00".to_string(),
+            "Sun 23:00-01:00".to_string(),
+        ]).unwrap();
+        assert!(schedule.is_enabled());
+
+        // Daily window
+        assert_eq!(schedule.active_at(at(2, 3, 30)), Some(Duration::from_secs(30 * 60)));
+        assert_eq!(schedule.active_at(at(2, 4, 0)), None);
+        assert_eq!(schedule.active_at(at(2, 1, 59)), None);
+
+        // Weekly window crossing midnight: Sunday Jan 7 into Monday Jan 8
+        assert_eq!(schedule.active_at(at(7, 23, 30)), Some(Duration::from_secs(90 * 60)));
+        assert_eq!(schedule.active_at(at(8, 0, 30)), Some(Duration::from_secs(30 * 60)));
+        assert_eq!(schedule.active_at(at(6, 23, 30)), None);
+        assert_eq!(schedule.active_at(at(2, 0, 30)), None);
+
+        assert!(!QuietHours::parse(&[]).unwrap().is_enabled());
+    }
+}
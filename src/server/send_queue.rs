@@ -0,0 +1,253 @@
+// src/server/send_queue.rs
+//! Bounded queue of messages waiting for a client's WebSocket.
+//!
+//! Each session hands outbound messages to its own queue and a writer task
+//! drains it into the socket, so the heartbeat, key rotation and routing
+//! tasks never wait on a slow client. When the queue is full the oldest
+//! queued Data message is dropped to make room; control messages
+//! (disconnects, key rotations, pings) are never dropped, and may take the
+//! queue past its bound if nothing else can give way. Pongs are queued as
+//! Data: the client decides how many it asks for, and one that pings
+//! without reading must not grow the queue.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::{Mutex, Notify};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tracing::debug;
+
+use crate::network::tun_queue::Enqueue;
+use crate::protocol::PacketType;
+use crate::server::connection::WebSocketConnection;
+
+/// Whether a queued message may be dropped under backpressure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendClass {
+    /// Protocol control message, always delivered
+    Control,
+    /// Tunnel traffic and Pong replies, dropped oldest-first when the queue is full
+    Data,
+}
+
+impl SendClass {
+    /// Class of an outbound packet
+    pub fn of(packet: &PacketType) -> Self {
+        match packet {
+            PacketType::Data { .. } | PacketType::Pong { .. } => SendClass::Data,
+            _ => SendClass::Control,
+        }
+    }
+}
+
+/// Bounded FIFO between a session and its WebSocket sender
+#[derive(Debug)]
+pub struct SendQueue {
+    /// Queued messages beyond which Data messages are dropped
+    capacity: usize,
+    /// Queued messages, oldest first
+    messages: Mutex<VecDeque<(SendClass, Message)>>,
+    /// Wakes the writer when a message is queued
+    ready: Notify,
+    /// Set once the queue accepts no more messages
+    closed: AtomicBool,
+    /// Set once the writer has stopped
+    finished: AtomicBool,
+    /// Wakes waiters for `finished`
+    finished_notify: Notify,
+}
+
+impl SendQueue {
+    /// Create an empty queue holding at most `capacity` Data messages
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            messages: Mutex::new(VecDeque::with_capacity(capacity.min(64))),
+            ready: Notify::new(),
+            closed: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
+            finished_notify: Notify::new(),
+        }
+    }
+
+    /// Offer a message, dropping the oldest queued Data message if the queue is full.
+    ///
+    /// A Data message is refused (`Full`) when the queue is full of control
+    /// messages or has been closed.
+    pub async fn push(&self, message: Message, class: SendClass) -> Enqueue {
+        if self.is_closed() {
+            return Enqueue::Full;
+        }
+        let mut messages = self.messages.lock().await;
+        let outcome = if messages.len() < self.capacity {
+            Enqueue::Queued
+        } else {
+            match messages.iter().position(|(queued, _)| *queued == SendClass::Data) {
+                Some(oldest) => {
+                    messages.remove(oldest);
+                    Enqueue::DroppedOldest
+                }
+                None if class == SendClass::Data => return Enqueue::Full,
+                None => Enqueue::Queued,
+            }
+        };
+        messages.push_back((class, message));
+        drop(messages);
+
+        self.ready.notify_one();
+        outcome
+    }
+
+    /// Take the oldest message, waiting until one is queued
+    pub async fn pop(&self) -> Message {
+        loop {
+            if let Some((_, message)) = self.messages.lock().await.pop_front() {
+                return message;
+            }
+            self.ready.notified().await;
+        }
+    }
+
+    /// Messages currently queued
+    pub async fn depth(&self) -> usize {
+        self.messages.lock().await.len()
+    }
+
+    /// Memory held by the queue: its slots and the payloads of queued messages
+    pub async fn queued_bytes(&self) -> usize {
+        let messages = self.messages.lock().await;
+        messages.capacity() * std::mem::size_of::<(SendClass, Message)>()
+            + messages.iter().map(|(_, message)| message.len()).sum::<usize>()
+    }
+
+    /// Stop accepting messages and have the writer close the connection once
+    /// the queued control messages are sent. Queued Data messages are discarded.
+    pub async fn close(&self) {
+        self.close_with(None).await;
+    }
+
+    /// As `close`, sending `frame` as the WebSocket Close frame
+    pub async fn close_with(&self, frame: Option<CloseFrame<'static>>) {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let mut messages = self.messages.lock().await;
+        messages.retain(|(class, _)| *class == SendClass::Control);
+        messages.push_back((SendClass::Control, Message::Close(frame)));
+        drop(messages);
+
+        self.ready.notify_one();
+    }
+
+    /// Whether the queue has stopped accepting messages
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the writer has stopped
+    pub async fn finished(&self) {
+        let notified = self.finished_notify.notified();
+        if !self.finished.load(Ordering::SeqCst) {
+            notified.await;
+        }
+    }
+
+    /// Start the task that drains the queue into `sender`, calling `on_send`
+    /// for each message just before it is written. The task closes the
+    /// connection and stops after the queue is closed or a write fails.
+    pub fn spawn_writer<F>(self: Arc<Self>, sender: Arc<Mutex<Box<dyn WebSocketConnection>>>, on_send: F) -> JoinHandle<()>
+    where
+        F: Fn(&Message) + Send + 'static,
+    {
+        tokio::spawn(async move {
+            loop {
+                let message = self.pop().await;
+                let mut sender = sender.lock().await;
+                if let Message::Close(frame) = message {
+                    if frame.is_some() {
+                        // Fails harmlessly if the WebSocket layer already answered the client's Close
+                        let _ = sender.send_message(Message::Close(frame)).await;
+                    }
+                    let _ = sender.close().await; // Ignore errors on close
+                    break;
+                }
+                on_send(&message);
+                if let Err(e) = sender.send_message(message).await {
+                    debug!("Stopping send queue writer: {}", e);
+                    let _ = sender.close().await;
+                    break;
+                }
+            }
+
+            self.closed.store(true, Ordering::SeqCst);
+            self.messages.lock().await.clear();
+            self.finished.store(true, Ordering::SeqCst);
+            self.finished_notify.notify_waiters();
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Message {
+        Message::Text(s.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_send_queue_drops_oldest_data() {
+        let queue = SendQueue::new(3);
+        assert_eq!(queue.push(text("d1"), SendClass::Data).await, Enqueue::Queued);
+        assert_eq!(queue.push(text("c1"), SendClass::Control).await, Enqueue::Queued);
+        assert_eq!(queue.push(text("d2"), SendClass::Data).await, Enqueue::Queued);
+
+        // Full: the oldest Data message gives way to new Data and to control
+        assert_eq!(queue.push(text("d3"), SendClass::Data).await, Enqueue::DroppedOldest);
+        assert_eq!(queue.push(text("c2"), SendClass::Control).await, Enqueue::DroppedOldest);
+        assert_eq!(queue.depth().await, 3);
+        assert_eq!(queue.pop().await, text("c1"));
+        assert_eq!(queue.pop().await, text("d3"));
+        assert_eq!(queue.pop().await, text("c2"));
+    }
+
+    #[tokio::test]
+    async fn test_send_queue_never_drops_control() {
+        let queue = SendQueue::new(2);
+        queue.push(text("c1"), SendClass::Control).await;
+        queue.push(text("c2"), SendClass::Control).await;
+
+        // Nothing can give way to Data, but control still goes past the bound
+        assert_eq!(queue.push(text("d1"), SendClass::Data).await, Enqueue::Full);
+        assert_eq!(queue.push(text("c3"), SendClass::Control).await, Enqueue::Queued);
+        assert_eq!(queue.depth().await, 3);
+
+        // Closing discards Data but keeps control ahead of the close
+        let queue = SendQueue::new(4);
+        queue.push(text("d1"), SendClass::Data).await;
+        queue.push(text("c1"), SendClass::Control).await;
+        queue.close().await;
+        assert_eq!(queue.push(text("c2"), SendClass::Control).await, Enqueue::Full);
+        assert_eq!(queue.pop().await, text("c1"));
+        assert_eq!(queue.pop().await, Message::Close(None));
+        assert_eq!(queue.depth().await, 0);
+    }
+
+    #[test]
+    fn test_send_class() {
+        let data = PacketType::Data {
+            encrypted: vec![1],
+            nonce: vec![0; 12],
+            counter: 1,
+            padding: None,
+            encryption_algorithm: None,
+            fragment: None,
+        };
+        assert_eq!(SendClass::of(&data), SendClass::Data);
+        assert_eq!(SendClass::of(&PacketType::Ping { timestamp: 1, sequence: 1 }), SendClass::Control);
+        let pong = PacketType::Pong { echo_timestamp: 1, server_timestamp: 2, sequence: 1 };
+        assert_eq!(SendClass::of(&pong), SendClass::Data);
+    }
+}
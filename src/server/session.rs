@@ -1,18 +1,72 @@
 // src/server/session.rs
 
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::{Mutex, Notify, OwnedSemaphorePermit, RwLock, Semaphore};
 use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 use std::time::{Duration, Instant};
-use tracing::{warn, info};
-use std::sync::atomic::AtomicBool;
+use tracing::{debug, warn, info};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
 use crate::protocol::PacketType;
-use crate::protocol::serialization::packet_to_ws_message;
+use crate::protocol::types::DisconnectReason;
+use crate::protocol::serialization::{log_sampled_packet, packet_to_ws_message};
 use crate::server::core::ServerError;
 use crate::crypto::flexible_encryption::EncryptionAlgorithm;
+use crate::crypto::nonce::DataNonceState;
+use crate::config::constants::{DISCONNECT_SEND_TIMEOUT, SESSION_REPLACE_TIMEOUT, SHUTDOWN_DRAIN_TIMEOUT};
+use crate::config::defaults::{DEFAULT_SEND_QUEUE_DEPTH, MAX_MISSED_PONGS};
+use crate::config::settings::DuplicateSessionPolicy;
 use crate::server::connection::WebSocketConnection;
+use crate::server::heartbeat::HeartbeatPolicy;
+use crate::server::negotiation::NegotiatedCapabilities;
+use crate::server::send_queue::{SendClass, SendQueue};
+use crate::network::tun_queue::Enqueue;
+use crate::auth::acl::{AccessControlEntry, AccessControlManager};
+use crate::network::NetworkMonitor;
+use crate::network::origin::ConnectionOrigin;
+use crate::crypto::session::SessionKeyManager;
+use crate::server::routing::PacketRouter;
+use crate::network::ip_pool::IpPoolManager;
+
+/// Tier-derived limits applied to a session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SessionPolicy {
+    /// Inbound bandwidth limit in bytes/sec (0 = server default)
+    pub bandwidth_limit: u64,
+    /// Maximum session duration in seconds (0 = unlimited)
+    pub max_session_duration: u64,
+}
+
+impl SessionPolicy {
+    /// Policy for a client's ACL entry; clients without one are unrestricted
+    pub fn from_entry(entry: Option<&AccessControlEntry>) -> Self {
+        entry.map_or_else(Self::default, |entry| Self {
+            bandwidth_limit: entry.bandwidth_limit,
+            max_session_duration: entry.max_session_duration,
+        })
+    }
+}
+
+/// Per-session counters reported in the session record
+#[derive(Debug, Default)]
+struct LifecycleCounters {
+    /// WebSocket messages sent to the client
+    messages_sent: AtomicU64,
+    /// WebSocket messages received from the client
+    messages_received: AtomicU64,
+    /// Successful IP lease renewals
+    ip_renewals: AtomicU64,
+    /// Highest round-trip time measured in milliseconds (0 = none)
+    peak_rtt_ms: AtomicU64,
+    /// Most recent round-trip time measured in milliseconds (0 = none)
+    last_rtt_ms: AtomicU64,
+    /// Outbound messages dropped because the send queue was full
+    tx_dropped: AtomicU64,
+}
 
 /// Client session for connected users
 #[derive(Clone)]
@@ -22,14 +76,42 @@ pub struct ClientSession {
     pub client_id: String,
     pub ip_address: String,
     pub address: SocketAddr,
+    /// When the session was established (milliseconds since epoch)
+    pub connected_at: u64,
     // Changed from concrete types to trait objects
     ws_sender: Arc<Mutex<Box<dyn WebSocketConnection>>>,
     ws_receiver: Arc<Mutex<Box<dyn WebSocketConnection>>>,
+    /// Outbound messages waiting for `ws_sender`
+    send_queue: Arc<SendQueue>,
+    /// Set once the writer draining `send_queue` has been started
+    writer_started: Arc<AtomicBool>,
     pub last_activity: Arc<Mutex<Instant>>,
     stream_taken: Arc<AtomicBool>,
+    /// Wakes the session's processing loop when the stream is taken
+    stream_taken_notify: Arc<Notify>,
+    /// Heartbeats awaiting a pong, oldest first, as (sequence, sent_at)
+    outstanding_pings: Arc<Mutex<VecDeque<(u64, u64)>>>,
+    /// Set when the session was closed for unanswered heartbeats
+    connection_dead: Arc<AtomicBool>,
+    /// WebSocket payload bytes sent to the client
+    bytes_sent: Arc<AtomicU64>,
+    /// WebSocket payload bytes received from the client
+    bytes_received: Arc<AtomicU64>,
+    /// Message, renewal and RTT counters
+    counters: Arc<LifecycleCounters>,
     
     // Existing encryption support fields
     pub encryption_algorithm: String,
+    /// Capabilities negotiated during authentication
+    pub capabilities: NegotiatedCapabilities,
+    /// Sampled for detailed packet tracing at connect time
+    pub traced: bool,
+    /// Effective session key rotation interval, including jitter
+    pub key_rotation_interval: Duration,
+    /// Role tags from the client's access policy, for rate limiting and routing decisions
+    pub roles: Arc<Vec<String>>,
+    /// Source network reported by the origin provider, if known
+    pub origin: Option<ConnectionOrigin>,
     
     /// Current room ID
     current_room: Arc<RwLock<Option<String>>>,
@@ -37,6 +119,18 @@ pub struct ClientSession {
     display_name: Arc<RwLock<Option<String>>>,
     /// Fallback encryption enabled
     fallback_enabled: Arc<RwLock<bool>>,
+    /// Tier-derived limits, set at authentication
+    policy: Arc<RwLock<SessionPolicy>>,
+    /// Counters for Data nonces
+    data_nonces: Arc<Mutex<DataNonceState>>,
+    /// Wakes the key rotation task when the nonce counters call for a new key
+    key_rotation_due: Arc<Notify>,
+    /// Last accepted control packet sequence number
+    control_sequence: Arc<Mutex<Option<u64>>>,
+    /// Set once the session's handler has released its IP and session key
+    torn_down: Arc<AtomicBool>,
+    /// Wakes waiters for `torn_down`
+    torn_down_notify: Arc<Notify>,
 }
 
 impl ClientSession {
@@ -61,17 +155,85 @@ impl ClientSession {
             client_id,
             ip_address,
             address,
+            connected_at: crate::utils::current_timestamp_millis(),
             ws_sender,
             ws_receiver,
+            send_queue: Arc::new(SendQueue::new(DEFAULT_SEND_QUEUE_DEPTH)),
+            writer_started: Arc::new(AtomicBool::new(false)),
             last_activity: Arc::new(Mutex::new(Instant::now())),
             stream_taken: Arc::new(AtomicBool::new(false)),
+            stream_taken_notify: Arc::new(Notify::new()),
+            outstanding_pings: Arc::new(Mutex::new(VecDeque::new())),
+            connection_dead: Arc::new(AtomicBool::new(false)),
+            bytes_sent: Arc::new(AtomicU64::new(0)),
+            bytes_received: Arc::new(AtomicU64::new(0)),
+            counters: Arc::new(LifecycleCounters::default()),
             encryption_algorithm: algorithm,
+            capabilities: NegotiatedCapabilities::default(),
+            traced: false,
+            key_rotation_interval: crate::config::constants::KEY_ROTATION_INTERVAL,
+            roles: Arc::new(Vec::new()),
+            origin: None,
             current_room: Arc::new(RwLock::new(None)),
             display_name: Arc::new(RwLock::new(None)),
             fallback_enabled: Arc::new(RwLock::new(true)), // Enable fallback by default
+            policy: Arc::new(RwLock::new(SessionPolicy::default())),
+            data_nonces: Arc::new(Mutex::new(DataNonceState::default())),
+            key_rotation_due: Arc::new(Notify::new()),
+            control_sequence: Arc::new(Mutex::new(None)),
+            torn_down: Arc::new(AtomicBool::new(false)),
+            torn_down_notify: Arc::new(Notify::new()),
         })
     }
     
+    /// Attach the capabilities negotiated for this session
+    pub fn with_capabilities(mut self, capabilities: NegotiatedCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Mark the session as sampled for detailed packet tracing
+    pub fn with_tracing(mut self, traced: bool) -> Self {
+        self.traced = traced;
+        self
+    }
+
+    /// Tag the session with where its connection comes from
+    pub fn with_origin(mut self, origin: Option<ConnectionOrigin>) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Set the session's key rotation interval
+    pub fn with_key_rotation_interval(mut self, interval: Duration) -> Self {
+        self.key_rotation_interval = interval;
+        self
+    }
+
+    /// Bound the messages queued for the client before Data messages are dropped.
+    /// Must be called before anything is sent.
+    pub fn with_send_queue_depth(mut self, depth: usize) -> Self {
+        self.send_queue = Arc::new(SendQueue::new(depth));
+        self
+    }
+
+    /// Ask for a new session key after `packets` Data packets in either direction
+    pub fn with_data_key_rotation_after(mut self, packets: u64) -> Self {
+        self.data_nonces = Arc::new(Mutex::new(DataNonceState::new(packets)));
+        self
+    }
+
+    /// Attach the role tags granted by the client's access policy
+    pub fn with_roles(mut self, roles: Vec<String>) -> Self {
+        self.roles = Arc::new(roles);
+        self
+    }
+
+    /// Whether the client holds `role`
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
     /// Set whether fallback to alternative encryption algorithm is allowed
     pub async fn set_fallback_enabled(&self, enabled: bool) {
         let mut fallback = self.fallback_enabled.write().await;
@@ -89,11 +251,100 @@ impl ClientSession {
         EncryptionAlgorithm::from_str(&self.encryption_algorithm)
     }
 
-    /// Send a packet to the client (acquires lock on sender)
+    /// Queue a packet for the client without waiting for the socket.
+    ///
+    /// Data packets may be dropped if the client falls behind (see
+    /// `SendQueue`); other packets are always delivered while the connection
+    /// lasts. Fails once the connection is closed or a write has failed.
     pub async fn send_packet(&self, packet: &PacketType) -> Result<(), ServerError> {
         let message = packet_to_ws_message(packet)?;
-        let mut sender_guard = self.ws_sender.lock().await;
-        sender_guard.send_message(message).await
+        if self.traced {
+            log_sampled_packet(&self.id, packet, false);
+        }
+        self.enqueue(message, SendClass::of(packet)).await
+    }
+
+    /// Queue a WebSocket control-frame ping for the client
+    pub async fn send_ws_ping(&self, payload: Vec<u8>) -> Result<(), ServerError> {
+        self.enqueue(Message::Ping(payload), SendClass::Control).await
+    }
+
+    /// Offer a message to the send queue, counting any message it drops
+    async fn enqueue(&self, message: Message, class: SendClass) -> Result<(), ServerError> {
+        if self.send_queue.is_closed() {
+            return Err(ServerError::Network("Connection closed".to_string()));
+        }
+        self.start_writer();
+        match self.send_queue.push(message, class).await {
+            Enqueue::Queued => {}
+            Enqueue::DroppedOldest | Enqueue::Full => {
+                self.counters.tx_dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        Ok(())
+    }
+
+    /// Start draining the send queue into the socket, once per session
+    fn start_writer(&self) {
+        if self.writer_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let bytes_sent = self.bytes_sent.clone();
+        let counters = self.counters.clone();
+        self.send_queue.clone().spawn_writer(self.ws_sender.clone(), move |message| {
+            bytes_sent.fetch_add(message.len() as u64, Ordering::Relaxed);
+            counters.messages_sent.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+
+    /// Outbound messages dropped because the client was not keeping up
+    pub fn tx_dropped(&self) -> u64 {
+        self.counters.tx_dropped.load(Ordering::Relaxed)
+    }
+
+    /// Messages waiting in the session's send queue
+    pub async fn send_queue_depth(&self) -> usize {
+        self.send_queue.depth().await
+    }
+
+    /// Remember a heartbeat until its pong arrives. Only the newest ones are
+    /// kept, enough to tell when more than `MAX_MISSED_PONGS` went unanswered.
+    pub async fn record_ping_sent(&self, sequence: u64, sent_at: u64) {
+        let mut outstanding = self.outstanding_pings.lock().await;
+        if outstanding.len() > MAX_MISSED_PONGS {
+            outstanding.pop_front();
+        }
+        outstanding.push_back((sequence, sent_at));
+    }
+
+    /// Clear the heartbeat whose timestamp a pong echoes, along with any
+    /// older ones. Returns false, clearing nothing, if no outstanding
+    /// heartbeat was sent at exactly `echo_timestamp`.
+    pub async fn acknowledge_ping(&self, echo_timestamp: u64) -> bool {
+        let mut outstanding = self.outstanding_pings.lock().await;
+        match outstanding.iter().position(|(_, sent_at)| *sent_at == echo_timestamp) {
+            Some(answered) => {
+                outstanding.drain(..=answered);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Consecutive heartbeats sent since the last pong
+    pub async fn unanswered_pings(&self) -> usize {
+        self.outstanding_pings.lock().await.len()
+    }
+
+    /// Give up on a connection that stopped answering heartbeats
+    pub async fn mark_connection_dead(&self) {
+        self.connection_dead.store(true, Ordering::SeqCst);
+        self.mark_stream_taken().await;
+    }
+
+    /// Whether the session was closed for unanswered heartbeats
+    pub fn is_connection_dead(&self) -> bool {
+        self.connection_dead.load(Ordering::SeqCst)
     }
 
     /// Update last activity timestamp (acquires lock)
@@ -112,27 +363,88 @@ impl ClientSession {
     /// Returns Option<Result<Message, ServerError>> to handle stream end and errors.
     pub async fn next_message(&self) -> Option<Result<Message, ServerError>> {
         let mut receiver_guard = self.ws_receiver.lock().await;
-        receiver_guard.next_message().await
+        let message = receiver_guard.next_message().await;
+        if let Some(Ok(msg)) = &message {
+            self.bytes_received.fetch_add(msg.len() as u64, Ordering::Relaxed);
+            self.counters.messages_received.fetch_add(1, Ordering::Relaxed);
+        }
+        message
+    }
+
+    /// Get the (sent, received) WebSocket payload byte counts for this session
+    pub fn traffic(&self) -> (u64, u64) {
+        (self.bytes_sent.load(Ordering::Relaxed), self.bytes_received.load(Ordering::Relaxed))
+    }
+
+    /// Get the (sent, received) WebSocket message counts for this session
+    pub fn message_counts(&self) -> (u64, u64) {
+        (
+            self.counters.messages_sent.load(Ordering::Relaxed),
+            self.counters.messages_received.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Count a successful IP lease renewal
+    pub fn record_ip_renewal(&self) {
+        self.counters.ip_renewals.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Successful IP lease renewals so far
+    pub fn ip_renewals(&self) -> u64 {
+        self.counters.ip_renewals.load(Ordering::Relaxed)
+    }
+
+    /// Track a round-trip time measurement
+    pub fn record_rtt(&self, rtt_ms: u64) {
+        self.counters.peak_rtt_ms.fetch_max(rtt_ms.max(1), Ordering::Relaxed);
+        self.counters.last_rtt_ms.store(rtt_ms.max(1), Ordering::Relaxed);
+    }
+
+    /// Most recent round-trip time measured, if any
+    pub fn last_rtt_ms(&self) -> Option<u64> {
+        Some(self.counters.last_rtt_ms.load(Ordering::Relaxed)).filter(|&rtt| rtt > 0)
+    }
+
+    /// Highest round-trip time measured, if any
+    pub fn peak_rtt_ms(&self) -> Option<u64> {
+        Some(self.counters.peak_rtt_ms.load(Ordering::Relaxed)).filter(|&rtt| rtt > 0)
     }
 
     /// Attempt to logically take the stream components.
     /// This marks the session as consumed but doesn't return the raw streams.
     /// Returns true if successfully marked as taken, false otherwise.
     pub async fn mark_stream_taken(&self) -> bool {
-        use std::sync::atomic::Ordering;
-        !self.stream_taken.swap(true, Ordering::SeqCst)
+        let first = !self.stream_taken.swap(true, Ordering::SeqCst);
+        if first {
+            // Stores a permit, so the loop wakes even if it is not waiting yet
+            self.stream_taken_notify.notify_one();
+        }
+        first
+    }
+
+    /// Wait until the stream is marked as taken
+    pub async fn stream_taken(&self) {
+        if !self.stream_taken.load(Ordering::SeqCst) {
+            self.stream_taken_notify.notified().await;
+        }
     }
 
     /// Check if the stream has been marked as taken.
     pub async fn is_stream_taken(&self) -> bool {
-        use std::sync::atomic::Ordering;
         self.stream_taken.load(Ordering::SeqCst)
     }
 
-    // Close the underlying connection (best effort)
+    /// Close the underlying connection (best effort) once packets already
+    /// queued, other than Data, have been sent
     pub async fn close(&self) {
-        let mut sender_guard = self.ws_sender.lock().await;
-        let _ = sender_guard.close().await; // Ignore errors on close
+        self.close_with(None).await;
+    }
+
+    /// As `close`, sending `frame` as the WebSocket Close frame
+    pub async fn close_with(&self, frame: Option<CloseFrame<'static>>) {
+        self.start_writer();
+        self.send_queue.close_with(frame).await;
+        self.send_queue.finished().await;
     }
     
     /// Get the current room ID
@@ -159,6 +471,165 @@ impl ClientSession {
         let mut display_name = self.display_name.write().await;
         *display_name = name;
     }
+    
+    /// Get the tier-derived limits for this session
+    pub async fn policy(&self) -> SessionPolicy {
+        *self.policy.read().await
+    }
+    
+    /// Replace the tier-derived limits for this session
+    pub async fn set_policy(&self, policy: SessionPolicy) {
+        *self.policy.write().await = policy;
+    }
+
+    /// Whether the session has outlived its policy's maximum duration
+    pub async fn past_max_duration(&self) -> bool {
+        let max_secs = self.policy().await.max_session_duration;
+        max_secs > 0
+            && crate::utils::current_timestamp_millis().saturating_sub(self.connected_at) >= max_secs.saturating_mul(1000)
+    }
+    
+    /// Counter state for Data nonces
+    pub async fn data_nonces(&self) -> tokio::sync::MutexGuard<'_, DataNonceState> {
+        self.data_nonces.lock().await
+    }
+    
+    /// Ask the key rotation task to rotate now, ahead of its schedule
+    pub fn request_key_rotation(&self) {
+        self.key_rotation_due.notify_one();
+    }
+    
+    /// Wait until a rotation is requested with `request_key_rotation`
+    pub async fn key_rotation_requested(&self) {
+        self.key_rotation_due.notified().await;
+    }
+    
+    /// Accept a control packet sequence number if it is newer than every
+    /// one accepted before; missing, replayed or stale numbers are refused
+    pub async fn accept_control_sequence(&self, sequence: Option<u64>) -> bool {
+        let Some(sequence) = sequence else {
+            return false;
+        };
+        let mut last = self.control_sequence.lock().await;
+        if last.map_or(false, |last| sequence <= last) {
+            return false;
+        }
+        *last = Some(sequence);
+        true
+    }
+    
+    /// Rough estimate of the memory this session holds: the session itself,
+    /// its identifiers and negotiated features, room and display name, the
+    /// shared nonce, replay window, counter and policy state, and the
+    /// messages backed up in its send queue and ping list. The WebSocket
+    /// connection and per-client state kept outside the session (router
+    /// buckets and reassembly buffers, monitor stats) are not counted; see
+    /// `SessionManager::session_memory`. Meant for spotting heavy sessions, not exact.
+    pub async fn approx_memory_bytes(&self) -> usize {
+        use std::mem::size_of;
+        // Each Arc allocation carries two reference counts
+        fn shared<T>() -> usize {
+            2 * size_of::<usize>() + size_of::<T>()
+        }
+        let optional_string = |value: &Option<String>| value.as_ref().map_or(0, String::capacity);
+
+        let strings = self.id.capacity()
+            + self.client_id.capacity()
+            + self.ip_address.capacity()
+            + self.encryption_algorithm.capacity()
+            + self.capabilities.denied.iter().map(|f| size_of::<String>() + f.capacity()).sum::<usize>()
+            + optional_string(&*self.current_room.read().await)
+            + optional_string(&*self.display_name.read().await)
+            + self.origin.as_ref().map_or(0, |origin| optional_string(&origin.country));
+
+        size_of::<Self>()
+            + strings
+            + shared::<Mutex<Instant>>()
+            + shared::<AtomicBool>() * 2
+            + shared::<AtomicU64>() * 2
+            + shared::<LifecycleCounters>()
+            + shared::<RwLock<Option<String>>>() * 2
+            + shared::<RwLock<bool>>()
+            + shared::<RwLock<SessionPolicy>>()
+            + shared::<Mutex<DataNonceState>>()
+            + shared::<Mutex<Option<u64>>>()
+            + shared::<Notify>() * 3
+            + shared::<SendQueue>()
+            + self.send_queue.queued_bytes().await
+            + shared::<Mutex<VecDeque<(u64, u64)>>>()
+            + self.outstanding_pings.lock().await.capacity() * size_of::<(u64, u64)>()
+    }
+    
+    /// Record that the session's handler has finished cleaning up
+    pub fn mark_torn_down(&self) {
+        self.torn_down.store(true, Ordering::SeqCst);
+        self.torn_down_notify.notify_waiters();
+    }
+    
+    /// Wait until the session's handler has finished cleaning up
+    pub async fn torn_down(&self) {
+        loop {
+            // Register before checking the flag so a concurrent notify is not missed
+            let notified = self.torn_down_notify.notified();
+            if self.torn_down.load(Ordering::SeqCst) {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Apply a policy to a session and to the client's shared traffic limits
+pub async fn apply_session_policy(session: &ClientSession, policy: SessionPolicy, monitor: &NetworkMonitor) {
+    session.set_policy(policy).await;
+    monitor.set_bandwidth_limit(&session.client_id, policy.bandwidth_limit).await;
+}
+
+/// Point-in-time statistics for one active session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SessionStats {
+    pub session_id: String,
+    /// Client public key
+    pub client_id: String,
+    /// Tunnel IP assigned to the session
+    pub assigned_ip: String,
+    /// When the session was established (milliseconds since epoch)
+    pub connected_at: u64,
+    /// WebSocket payload bytes received from the client
+    pub bytes_in: u64,
+    /// WebSocket payload bytes sent to the client
+    pub bytes_out: u64,
+    /// Most recent heartbeat round-trip time
+    pub last_rtt_ms: Option<u64>,
+    /// Outbound messages dropped because the client was not keeping up
+    pub tx_dropped: u64,
+    /// Time since the client's current session key was issued
+    pub session_key_age_secs: Option<u64>,
+    /// Time left on the client's dynamic IP lease (None for static IPs)
+    pub lease_remaining_secs: Option<u64>,
+    /// Source network of the connection, if known
+    pub origin: Option<ConnectionOrigin>,
+}
+
+/// Session slot held for a client admitted by `SessionManager::admit_client`
+/// until its session is added with `add_admitted_session`. Dropping it
+/// first, when the handshake fails, releases the slot.
+#[derive(Debug)]
+pub struct SessionReservation {
+    client_id: String,
+    pending: Arc<std::sync::Mutex<HashMap<String, usize>>>,
+}
+
+impl Drop for SessionReservation {
+    fn drop(&mut self) {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = pending.get_mut(&self.client_id) {
+            *count -= 1;
+            if *count == 0 {
+                pending.remove(&self.client_id);
+            }
+        }
+    }
 }
 
 /// Session manager for handling multiple client sessions
@@ -167,8 +638,31 @@ pub struct SessionManager {
     sessions: Arc<Mutex<std::collections::HashMap<String, ClientSession>>>,
     /// IP to session mapping for quicker lookups (IP String -> Session ID String)
     ip_sessions: Arc<Mutex<std::collections::HashMap<String, String>>>,
+    /// Admitted clients whose sessions are not added yet, by client ID.
+    /// Only changed while `sessions` is locked.
+    pending_admissions: Arc<std::sync::Mutex<HashMap<String, usize>>>,
     /// Session timeout
     session_timeout: Duration,
+    /// Handling of a new authentication from a key that is already connected
+    duplicate_policy: DuplicateSessionPolicy,
+    /// Active session cap (0 = unlimited)
+    max_sessions: usize,
+    /// Time without inbound traffic before a session is disconnected (zero = disabled)
+    idle_timeout: Duration,
+    /// Unanswered heartbeats tolerated before a connection is considered dead (0 = unlimited)
+    max_missed_pongs: usize,
+    /// Heartbeat interval and RTT backoff
+    heartbeat_policy: HeartbeatPolicy,
+    /// Messages queued per session before Data messages are dropped
+    send_queue_depth: usize,
+    /// Concurrent sessions allowed per key under `AllowMultiple` (0 = unlimited)
+    max_sessions_per_key: usize,
+    /// Slots for concurrent client connections (None = unlimited)
+    connection_slots: Option<Arc<Semaphore>>,
+    /// Configured connection limit, reported on rejection
+    max_connections: usize,
+    /// How long a connection waits for a free slot (zero = reject immediately)
+    connection_queue_timeout: Duration,
 }
 
 impl SessionManager {
@@ -180,8 +674,225 @@ impl SessionManager {
         Self {
             sessions: Arc::new(Mutex::new(std::collections::HashMap::new())),
             ip_sessions: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            pending_admissions: Arc::new(std::sync::Mutex::new(HashMap::new())),
             session_timeout,
+            duplicate_policy: DuplicateSessionPolicy::default(),
+            max_sessions: 0,
+            idle_timeout: Duration::ZERO,
+            max_missed_pongs: 0,
+            heartbeat_policy: HeartbeatPolicy::default(),
+            send_queue_depth: DEFAULT_SEND_QUEUE_DEPTH,
+            max_sessions_per_key: 0,
+            connection_slots: None,
+            max_connections: 0,
+            connection_queue_timeout: Duration::ZERO,
+        }
+    }
+
+    /// Set how a new authentication from an already-connected key is handled
+    pub fn with_duplicate_policy(mut self, policy: DuplicateSessionPolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Cap the number of active sessions (0 = unlimited)
+    pub fn with_max_sessions(mut self, max_sessions: usize) -> Self {
+        self.max_sessions = max_sessions;
+        self
+    }
+
+    /// Disconnect sessions that receive nothing for `timeout` (zero = never)
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Time without inbound traffic before a session is disconnected (zero = disabled)
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    /// Consider a connection dead after more than `max` consecutive unanswered heartbeats (0 = never)
+    pub fn with_max_missed_pongs(mut self, max: usize) -> Self {
+        self.max_missed_pongs = max;
+        self
+    }
+
+    /// Unanswered heartbeats tolerated before a connection is considered dead (0 = unlimited)
+    pub fn max_missed_pongs(&self) -> usize {
+        self.max_missed_pongs
+    }
+
+    /// Ping sessions according to `policy`
+    pub fn with_heartbeat_policy(mut self, policy: HeartbeatPolicy) -> Self {
+        self.heartbeat_policy = policy;
+        self
+    }
+
+    /// Heartbeat interval and RTT backoff for sessions
+    pub fn heartbeat_policy(&self) -> HeartbeatPolicy {
+        self.heartbeat_policy
+    }
+
+    /// Bound each session's send queue at `depth` messages
+    pub fn with_send_queue_depth(mut self, depth: usize) -> Self {
+        self.send_queue_depth = depth;
+        self
+    }
+
+    /// Messages queued per session before Data messages are dropped
+    pub fn send_queue_depth(&self) -> usize {
+        self.send_queue_depth
+    }
+
+    /// Limit concurrent sessions per key (0 = unlimited); ACL entries may override it
+    pub fn with_max_sessions_per_key(mut self, max: usize) -> Self {
+        self.max_sessions_per_key = max;
+        self
+    }
+
+    /// Limit concurrent client connections (0 = unlimited). Connections over
+    /// the limit wait up to `queue_timeout` for a free slot before being refused.
+    pub fn with_max_connections(mut self, max: usize, queue_timeout: Duration) -> Self {
+        self.connection_slots = (max > 0).then(|| Arc::new(Semaphore::new(max)));
+        self.max_connections = max;
+        self.connection_queue_timeout = queue_timeout;
+        self
+    }
+
+    /// Reserve a connection slot for the lifetime of a client connection.
+    ///
+    /// The slot is released when the returned permit is dropped; `None` means
+    /// connections are unlimited.
+    pub async fn acquire_connection_slot(&self) -> Result<Option<OwnedSemaphorePermit>, SessionError> {
+        let Some(slots) = &self.connection_slots else {
+            return Ok(None);
+        };
+        let permit = if self.connection_queue_timeout.is_zero() {
+            slots.clone().try_acquire_owned().ok()
+        } else {
+            tokio::time::timeout(self.connection_queue_timeout, slots.clone().acquire_owned())
+                .await
+                .ok()
+                .and_then(Result::ok)
+        };
+        permit.map(Some).ok_or(SessionError::ConnectionLimitReached(self.max_connections))
+    }
+
+    /// Whether the active session cap has been reached, counting admitted
+    /// handshakes whose sessions are not added yet
+    pub async fn at_capacity(&self) -> bool {
+        if self.max_sessions == 0 {
+            return false;
         }
+        let sessions = self.sessions.lock().await;
+        let admitting: usize = self.pending_admissions.lock().unwrap_or_else(|e| e.into_inner()).values().sum();
+        sessions.len() + admitting >= self.max_sessions
+    }
+
+    /// Apply the duplicate session policy to a newly authenticated client,
+    /// before it is given an IP or session key.
+    ///
+    /// The check and a reservation for the new session are made under the
+    /// sessions lock, so handshakes for the same key completing together
+    /// see each other. Returns the number of sessions replaced and the
+    /// reservation to pass to `add_admitted_session`.
+    ///
+    /// With `ReplaceExisting`, the key's existing sessions are disconnected
+    /// and their handlers given up to `SESSION_REPLACE_TIMEOUT` to release
+    /// the IP and session key, so the old teardown cannot undo the new setup.
+    ///
+    /// With `RejectNew`, a key with a session or another admitted handshake
+    /// is rejected. With `AllowMultiple`, a key whose sessions and admitted
+    /// handshakes reach its session limit is rejected; `acl_limit` replaces
+    /// the server-wide limit when non-zero.
+    ///
+    /// Under any policy, a client is rejected once sessions and admitted
+    /// handshakes reach the active session cap, sessions about to be
+    /// replaced aside.
+    pub async fn admit_client(&self, client_id: &str, acl_limit: u32) -> Result<(usize, SessionReservation), SessionError> {
+        let (existing, reservation) = {
+            let sessions = self.sessions.lock().await;
+            let existing: Vec<ClientSession> = sessions.values()
+                .filter(|s| s.client_id == client_id)
+                .cloned()
+                .collect();
+            let mut pending = self.pending_admissions.lock().unwrap_or_else(|e| e.into_inner());
+            let admitting = pending.get(client_id).copied().unwrap_or(0);
+
+            // Admitted handshakes hold a slot under the session cap as well, so
+            // concurrent ones cannot all pass the check made before TLS
+            if self.max_sessions > 0 {
+                let replaced = match self.duplicate_policy {
+                    DuplicateSessionPolicy::ReplaceExisting => existing.len(),
+                    _ => 0,
+                };
+                let admitting_total: usize = pending.values().sum();
+                if sessions.len() - replaced + admitting_total >= self.max_sessions {
+                    return Err(SessionError::ServerFull(self.max_sessions));
+                }
+            }
+
+            match self.duplicate_policy {
+                DuplicateSessionPolicy::AllowMultiple => {
+                    let limit = if acl_limit > 0 { acl_limit as usize } else { self.max_sessions_per_key };
+                    // Admitted handshakes count, so concurrent ones cannot all take the last slot
+                    if limit > 0 && existing.len() + admitting >= limit {
+                        return Err(SessionError::KeyLimitReached(limit));
+                    }
+                }
+                DuplicateSessionPolicy::RejectNew => {
+                    if !existing.is_empty() || admitting > 0 {
+                        return Err(SessionError::AlreadyConnected);
+                    }
+                }
+                DuplicateSessionPolicy::ReplaceExisting => {}
+            }
+
+            *pending.entry(client_id.to_string()).or_insert(0) += 1;
+            let reservation = SessionReservation {
+                client_id: client_id.to_string(),
+                pending: self.pending_admissions.clone(),
+            };
+            (existing, reservation)
+        };
+
+        match self.duplicate_policy {
+            DuplicateSessionPolicy::AllowMultiple | DuplicateSessionPolicy::RejectNew => Ok((0, reservation)),
+            DuplicateSessionPolicy::ReplaceExisting if existing.is_empty() => Ok((0, reservation)),
+            DuplicateSessionPolicy::ReplaceExisting => {
+                for session in &existing {
+                    match self.disconnect_session(
+                        &session.id,
+                        DisconnectReason::SessionReplaced,
+                        "Replaced by a newer session",
+                    ).await {
+                        // Already gone (client disconnected on its own)
+                        Ok(()) | Err(SessionError::NotFound(_)) => {}
+                        Err(e) => warn!("Failed to disconnect replaced session {}: {}", session.id, e),
+                    }
+                    // Unblock a handler waiting on a client that ignores the disconnect
+                    session.close().await;
+                }
+                for session in &existing {
+                    if tokio::time::timeout(SESSION_REPLACE_TIMEOUT, session.torn_down()).await.is_err() {
+                        warn!("Replaced session {} for {} did not tear down in time", session.id, client_id);
+                    }
+                }
+                info!("Replaced {} existing sessions for {}", existing.len(), client_id);
+                Ok((existing.len(), reservation))
+            }
+        }
+    }
+
+    /// Add the session of a client admitted by `admit_client`, in place of its reservation
+    pub async fn add_admitted_session(&self, session: ClientSession, reservation: SessionReservation) {
+        let mut sessions_guard = self.sessions.lock().await;
+        let mut ip_sessions_guard = self.ip_sessions.lock().await;
+        ip_sessions_guard.insert(session.ip_address.clone(), session.id.clone());
+        sessions_guard.insert(session.id.clone(), session);
+        // Released while the session is visible, so the slot is never counted twice or not at all
+        drop(reservation);
     }
 
     /// Add a new session
@@ -254,6 +965,50 @@ impl SessionManager {
         sessions_guard.values().cloned().collect()
     }
 
+    /// Approximate memory held by each active session as `(session_id, client_id, bytes)`, largest first.
+    ///
+    /// A client's fragment reassembly buffers in `packet_router` are charged
+    /// to one of its sessions, so the sum does not count them twice.
+    pub async fn session_memory(&self, packet_router: &PacketRouter) -> Vec<(String, String, usize)> {
+        let sessions = self.all_sessions().await;
+        let mut usage = Vec::with_capacity(sessions.len());
+        let mut charged = HashSet::new();
+        for session in sessions {
+            let mut bytes = session.approx_memory_bytes().await;
+            if charged.insert(session.client_id.clone()) {
+                bytes += packet_router.reassembly_bytes(&session.client_id).await;
+            }
+            usage.push((session.id, session.client_id, bytes));
+        }
+        usage.sort_by(|a, b| b.2.cmp(&a.2));
+        usage
+    }
+
+    /// Live statistics for every active session, oldest first
+    pub async fn session_stats(&self, session_keys: &SessionKeyManager, ip_pool: &IpPoolManager) -> Vec<SessionStats> {
+        let key_ages = session_keys.get_stats().await;
+        let sessions = self.all_sessions().await;
+        let mut stats = Vec::with_capacity(sessions.len());
+        for session in sessions {
+            let (bytes_out, bytes_in) = session.traffic();
+            stats.push(SessionStats {
+                session_key_age_secs: key_ages.get(&session.client_id).map(|(age, _)| age.as_secs()),
+                lease_remaining_secs: ip_pool.lease_remaining(&session.client_id).await.map(|left| left.as_secs()),
+                last_rtt_ms: session.last_rtt_ms(),
+                tx_dropped: session.tx_dropped(),
+                origin: session.origin.clone(),
+                session_id: session.id,
+                client_id: session.client_id,
+                assigned_ip: session.ip_address,
+                connected_at: session.connected_at,
+                bytes_in,
+                bytes_out,
+            });
+        }
+        stats.sort_by(|a, b| a.connected_at.cmp(&b.connected_at).then_with(|| a.session_id.cmp(&b.session_id)));
+        stats
+    }
+
     /// Count active sessions
     pub async fn session_count(&self) -> usize {
         let sessions_guard = self.sessions.lock().await;
@@ -268,29 +1023,44 @@ impl SessionManager {
             .count()
     }
 
-    /// Close all sessions gracefully (sends disconnect message)
-    pub async fn close_all_sessions(&self, reason: &str) {
-        let sessions_to_close = {
-            let sessions_guard = self.sessions.lock().await;
-            sessions_guard.values().cloned().collect::<Vec<_>>()
-        };
-
-        let disconnect_packet = crate::protocol::serialization::create_disconnect_packet(
-            crate::protocol::types::disconnect_reason::SERVER_SHUTDOWN,
-            reason
-        );
+    /// Send a Disconnect to every session and wait up to `SHUTDOWN_DRAIN_TIMEOUT`
+    /// for their handlers to tear down (the client closed the socket or
+    /// acknowledged); sessions still open after that are closed.
+    ///
+    /// Sends run concurrently on a snapshot of the sessions, so the manager
+    /// lock is never held while a session's sender lock is, and a wedged
+    /// sender only costs `DISCONNECT_SEND_TIMEOUT`. Sessions that are already
+    /// gone are skipped silently. Returns the number that closed in time.
+    pub async fn broadcast_disconnect(&self, reason: DisconnectReason, message: &str) -> usize {
+        let sessions = self.all_sessions().await;
+        let disconnect_packet = crate::protocol::serialization::create_disconnect_packet(reason, message);
 
-        // Send disconnect notifications concurrently
-        let close_futures = sessions_to_close.iter().map(|session| {
-            let packet = disconnect_packet.clone();
+        let drains = sessions.iter().map(|session| {
+            let packet = &disconnect_packet;
             async move {
-                if let Err(e) = session.send_packet(&packet).await {
-                    warn!("Failed to send disconnect to {}: {}", session.client_id, e);
+                match tokio::time::timeout(DISCONNECT_SEND_TIMEOUT, session.send_packet(packet)).await {
+                    Ok(Ok(())) => {}
+                    // Already gone
+                    Ok(Err(e)) => debug!("Skipping disconnect for {}: {}", session.client_id, e),
+                    Err(_) => debug!("Timed out sending disconnect to {}", session.client_id),
                 }
-                session.close().await;
+                let drained = tokio::time::timeout(SHUTDOWN_DRAIN_TIMEOUT, session.torn_down()).await.is_ok();
+                if !drained {
+                    // Unblock a handler waiting on a client that ignores the disconnect
+                    session.mark_stream_taken().await;
+                    let _ = tokio::time::timeout(DISCONNECT_SEND_TIMEOUT, session.close()).await;
+                }
+                drained
             }
         });
-        futures::future::join_all(close_futures).await;
+        let drained = futures::future::join_all(drains).await.into_iter().filter(|d| *d).count();
+        info!("{} of {} sessions closed after disconnect", drained, sessions.len());
+        drained
+    }
+
+    /// Close all sessions gracefully (sends disconnect message)
+    pub async fn close_all_sessions(&self, reason: &str) {
+        self.broadcast_disconnect(DisconnectReason::ServerShutdown, reason).await;
 
         // Clear all session tracking data AFTER attempting notifications
         {
@@ -304,6 +1074,102 @@ impl SessionManager {
         info!("Cleared all active sessions.");
     }
 
+    /// Notify a client and close its session
+    pub async fn disconnect_session(&self, session_id: &str, reason: DisconnectReason, message: &str) -> Result<(), SessionError> {
+        let session = self.get_session(session_id).await
+            .ok_or_else(|| SessionError::NotFound(session_id.to_string()))?;
+
+        let disconnect_packet = crate::protocol::serialization::create_disconnect_packet(reason, message);
+        if let Err(e) = session.send_packet(&disconnect_packet).await {
+            warn!("Failed to send disconnect to {}: {}", session.client_id, e);
+        }
+        // Stops the session's processing loop even if the client ignores the close
+        session.mark_stream_taken().await;
+        self.remove_session(session_id).await;
+        Ok(())
+    }
+
+    /// Disconnect sessions whose clients are no longer allowed by the ACL.
+    /// At most `max_per_second` sessions are disconnected per second.
+    pub async fn disconnect_revoked_sessions(&self, acl: &AccessControlManager, max_per_second: u32) -> usize {
+        let mut revoked = Vec::new();
+        for session in self.all_sessions().await {
+            if !acl.is_allowed(&session.client_id).await {
+                revoked.push(session);
+            }
+        }
+        if revoked.is_empty() {
+            return 0;
+        }
+
+        info!("ACL reload revoked access for {} active sessions", revoked.len());
+        let pause = Duration::from_secs(1) / max_per_second.max(1);
+        let mut disconnected = 0;
+        for (i, session) in revoked.iter().enumerate() {
+            if i > 0 {
+                tokio::time::sleep(pause).await;
+            }
+            match self.disconnect_session(
+                &session.id,
+                DisconnectReason::AccessRevoked,
+                "Access revoked",
+            ).await {
+                Ok(()) => {
+                    info!("Disconnected session {} for {}: access revoked", session.id, session.client_id);
+                    disconnected += 1;
+                }
+                // Already gone (client disconnected on its own)
+                Err(SessionError::NotFound(_)) => {}
+                Err(e) => warn!("Failed to disconnect revoked session {}: {}", session.id, e),
+            }
+        }
+        disconnected
+    }
+
+    /// Send a ServerLoad packet to every session that negotiated `server-load`.
+    /// Returns the number of sessions notified.
+    pub async fn broadcast_server_load(&self, load_factor: f32) -> usize {
+        let sessions = self.all_sessions().await;
+        let packet = PacketType::ServerLoad {
+            active_sessions: sessions.len().min(u32::MAX as usize) as u32,
+            load_factor: load_factor.clamp(0.0, 1.0),
+        };
+        let mut notified = 0;
+        for session in sessions.iter().filter(|s| s.capabilities.server_load) {
+            // Advisory only: a slow or closing client just misses an update
+            if session.send_packet(&packet).await.is_ok() {
+                notified += 1;
+            }
+        }
+        notified
+    }
+
+    /// Re-apply the current ACL tier to every active session.
+    ///
+    /// The policy is resolved once per public key, so all sessions of a key
+    /// end up with the same limits even if the ACL changes again mid-update.
+    /// Returns the number of sessions whose policy changed.
+    pub async fn reapply_acl_policies(&self, acl: &AccessControlManager, monitor: &NetworkMonitor) -> usize {
+        let mut policies: std::collections::HashMap<String, SessionPolicy> = std::collections::HashMap::new();
+        let mut updated = 0;
+        for session in self.all_sessions().await {
+            let policy = match policies.get(&session.client_id) {
+                Some(policy) => *policy,
+                None => {
+                    let policy = SessionPolicy::from_entry(acl.get_entry(&session.client_id).await.as_ref());
+                    policies.insert(session.client_id.clone(), policy);
+                    policy
+                }
+            };
+            if session.policy().await != policy {
+                apply_session_policy(&session, policy, monitor).await;
+                info!("Re-applied ACL tier to session {} for {}: {:?}", session.id, session.client_id, policy);
+                updated += 1;
+            }
+        }
+        updated
+    }
+
     /// Clean up expired sessions based on idle time
     pub async fn cleanup_expired_sessions(&self) -> usize {
         let timeout = self.session_timeout;
@@ -381,6 +1247,18 @@ pub enum SessionError {
     #[error("Session expired")]
     Expired,
 
+    #[error("Client already has an active session")]
+    AlreadyConnected,
+
+    #[error("Client already has the maximum of {0} active sessions")]
+    KeyLimitReached(usize),
+
+    #[error("Server is at its limit of {0} concurrent connections")]
+    ConnectionLimitReached(usize),
+
+    #[error("Server is at its limit of {0} active sessions")]
+    ServerFull(usize),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -390,3 +1268,511 @@ pub enum SessionError {
     #[error("Stream components have already been consumed or are unavailable")]
     StreamConsumed,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::acl::AccessControlManager;
+    use crate::server::connection::mock::{self, MockPeer};
+
+    fn mock_session(id: &str, client_id: &str, ip: &str) -> (ClientSession, MockPeer) {
+        let (conn, peer) = mock::duplex();
+        let session = ClientSession::new(
+            id.to_string(),
+            client_id.to_string(),
+            ip.to_string(),
+            "127.0.0.1:50000".parse().unwrap(),
+            conn.sender(),
+            conn.receiver(),
+            None,
+        ).unwrap();
+        (session, peer)
+    }
+
+    #[tokio::test]
+    async fn test_acknowledge_ping() {
+        let (session, _peer) = mock_session("s1", "client", "10.7.0.2");
+        for (sequence, sent_at) in [(0, 1_000), (1, 2_000), (2, 3_000)] {
+            session.record_ping_sent(sequence, sent_at).await;
+        }
+
+        // Echoes of timestamps that were never sent clear nothing
+        assert!(!session.acknowledge_ping(2_500).await);
+        assert!(!session.acknowledge_ping(u64::MAX).await);
+        assert_eq!(session.unanswered_pings().await, 3);
+
+        // A matching echo clears its heartbeat and the older ones
+        assert!(session.acknowledge_ping(2_000).await);
+        assert_eq!(session.unanswered_pings().await, 1);
+        assert!(!session.acknowledge_ping(2_000).await);
+
+        // An unanswered backlog stays bounded, still past any tolerated limit
+        for sequence in 0..1_000 {
+            session.record_ping_sent(sequence, 10_000 + sequence).await;
+        }
+        assert_eq!(session.unanswered_pings().await, MAX_MISSED_PONGS + 1);
+        assert!(session.acknowledge_ping(10_999).await);
+        assert_eq!(session.unanswered_pings().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_session_memory_estimate() {
+        let manager = SessionManager::new(5, Duration::from_secs(3600));
+        let (light, _light_peer) = mock_session("s1", "light", "10.7.0.2");
+        let (heavy, _heavy_peer) = mock_session("s2", "heavy", "10.7.0.3");
+        let baseline = heavy.approx_memory_bytes().await;
+        assert!(baseline >= std::mem::size_of::<ClientSession>());
+
+        heavy.set_display_name(Some("x".repeat(4096))).await;
+        let named = heavy.approx_memory_bytes().await;
+        assert!(named >= baseline + 4096);
+
+        // Messages backed up behind a stalled socket are counted; the
+        // writer holds at most one of the two while it waits for the socket
+        let stalled = heavy.ws_sender.lock().await;
+        heavy.send_ws_ping(vec![0u8; 4096]).await.unwrap();
+        heavy.send_ws_ping(vec![0u8; 4096]).await.unwrap();
+        assert!(heavy.approx_memory_bytes().await >= named + 4096);
+        drop(stalled);
+
+        manager.add_session(light).await;
+        manager.add_session(heavy).await;
+        let router = PacketRouter::new(crate::config::constants::PACKET_SIZE_LIMIT, false);
+        let usage = manager.session_memory(&router).await;
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].0, "s2");
+        assert!(usage[0].2 > usage[1].2);
+    }
+
+    #[tokio::test]
+    async fn test_session_stats() {
+        let manager = SessionManager::new(5, Duration::from_secs(3600));
+        let (active, mut active_peer) = mock_session("s1", "active", "10.7.0.2");
+        let (quiet, _quiet_peer) = mock_session("s2", "quiet", "10.7.0.3");
+        manager.add_session(active.clone()).await;
+        manager.add_session(quiet).await;
+        let session_keys = SessionKeyManager::new(Duration::from_secs(3600), 1_000_000);
+        session_keys.store_key("active", vec![1u8; 32]).await;
+        let ip_pool = IpPoolManager::new("10.7.0.0/24", 3600).await.unwrap();
+        ip_pool.allocate_ip("active", None).await.unwrap();
+
+        // Traffic both ways and two heartbeat round trips
+        active.send_packet(&PacketType::Ping { timestamp: 1, sequence: 1 }).await.unwrap();
+        let sent = active_peer.from_server.recv().await.unwrap().len() as u64;
+        active_peer.to_server.send(Message::Text("hello".to_string())).unwrap();
+        active.next_message().await.unwrap().unwrap();
+        active.record_rtt(40);
+        active.record_rtt(25);
+
+        let stats = manager.session_stats(&session_keys, &ip_pool).await;
+        assert_eq!(stats.len(), 2);
+        let active_stats = stats.iter().find(|s| s.client_id == "active").unwrap();
+        assert_eq!(active_stats.assigned_ip, "10.7.0.2");
+        assert_eq!((active_stats.bytes_in, active_stats.bytes_out), (5, sent));
+        assert_eq!(active_stats.last_rtt_ms, Some(25));
+        assert_eq!(active_stats.session_key_age_secs, Some(0));
+        assert!(active_stats.lease_remaining_secs.map_or(false, |left| left > 3500 && left <= 3600));
+        assert!(active_stats.connected_at <= crate::utils::current_timestamp_millis());
+
+        let quiet_stats = stats.iter().find(|s| s.client_id == "quiet").unwrap();
+        assert_eq!((quiet_stats.bytes_in, quiet_stats.bytes_out, quiet_stats.last_rtt_ms), (0, 0, None));
+        assert_eq!(quiet_stats.session_key_age_secs, None);
+        assert_eq!(quiet_stats.lease_remaining_secs, None);
+
+        let json = serde_json::to_value(active_stats).unwrap();
+        assert_eq!(json["last_rtt_ms"], 25);
+        assert_eq!(json["bytes_in"], 5);
+        assert_eq!(json["tx_dropped"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_send_queue_backpressure() {
+        let (conn, mut peer) = mock::duplex();
+        let socket = conn.sender();
+        let session = ClientSession::new(
+            "s1".to_string(),
+            "slow".to_string(),
+            "10.7.0.2".to_string(),
+            "127.0.0.1:50000".parse().unwrap(),
+            conn.sender(),
+            conn.receiver(),
+            None,
+        ).unwrap().with_send_queue_depth(4);
+        let data = |counter: u64| PacketType::Data {
+            encrypted: vec![counter as u8],
+            nonce: vec![0; 12],
+            counter,
+            padding: None,
+            encryption_algorithm: None,
+            fragment: None,
+        };
+
+        // A client that stops reading stalls the writer, not the senders
+        let stalled = socket.lock().await;
+        for counter in 0..10 {
+            tokio::time::timeout(Duration::from_millis(100), session.send_packet(&data(counter)))
+                .await.unwrap().unwrap();
+        }
+        let disconnect = crate::protocol::serialization::create_disconnect_packet(
+            DisconnectReason::ServerShutdown,
+            "Server shutdown",
+        );
+        session.send_packet(&disconnect).await.unwrap();
+        assert_eq!(session.send_queue_depth().await, 4);
+        // The writer may already hold the first packet
+        assert!(session.tx_dropped() >= 6, "{}", session.tx_dropped());
+        drop(stalled);
+
+        // The newest Data packets survive, and the Disconnect is never dropped
+        let mut counters = Vec::new();
+        loop {
+            let msg = peer.from_server.recv().await.unwrap();
+            match crate::protocol::serialization::ws_message_to_packet(&msg).unwrap() {
+                PacketType::Data { counter, .. } => counters.push(counter),
+                PacketType::Disconnect { reason, .. } => {
+                    assert_eq!(reason, DisconnectReason::ServerShutdown.code());
+                    break;
+                }
+                other => panic!("Unexpected packet {:?}", other),
+            }
+        }
+        assert!(counters.ends_with(&[7, 8, 9]), "{:?}", counters);
+
+        // Closing flushes the queue; later sends fail
+        session.close().await;
+        assert!(session.send_packet(&data(10)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_disconnect() {
+        let manager = SessionManager::new(5, Duration::from_secs(3600));
+        let (active, mut active_peer) = mock_session("s1", "active", "10.7.0.2");
+        let (gone, gone_peer) = mock_session("s2", "gone", "10.7.0.3");
+        manager.add_session(active.clone()).await;
+        manager.add_session(gone.clone()).await;
+
+        // The handler for the gone session already finished; its client is unreachable
+        drop(gone_peer);
+        gone.mark_torn_down();
+
+        // The active client closes as soon as it sees the Disconnect
+        let handler = tokio::spawn(async move {
+            let msg = active_peer.from_server.recv().await.unwrap();
+            active.mark_torn_down();
+            crate::protocol::serialization::ws_message_to_packet(&msg).unwrap()
+        });
+
+        let started = Instant::now();
+        let drained = manager.broadcast_disconnect(
+            DisconnectReason::ServerShutdown,
+            "Server shutdown",
+        ).await;
+        assert_eq!(drained, 2);
+        assert!(started.elapsed() < SHUTDOWN_DRAIN_TIMEOUT);
+        match handler.await.unwrap() {
+            PacketType::Disconnect { reason, .. } => {
+                assert_eq!(reason, DisconnectReason::ServerShutdown.code());
+            }
+            other => panic!("Expected Disconnect, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_cap() {
+        let manager = SessionManager::new(5, Duration::from_secs(3600)).with_max_sessions(2);
+        let (first, _first_peer) = mock_session("s1", "a", "10.7.0.2");
+        let (second, _second_peer) = mock_session("s2", "b", "10.7.0.3");
+
+        manager.add_session(first).await;
+        assert!(!manager.at_capacity().await);
+        manager.add_session(second).await;
+        assert!(manager.at_capacity().await);
+        manager.remove_session("s1").await;
+        assert!(!manager.at_capacity().await);
+
+        // 0 leaves the session count unbounded
+        assert!(!SessionManager::new(5, Duration::from_secs(3600)).at_capacity().await);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_revoked_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let acl = AccessControlManager::new(dir.path().join("acl.json")).await.unwrap();
+        let manager = SessionManager::new(5, Duration::from_secs(3600));
+
+        let (kept, _kept_peer) = mock_session("s1", "allowed", "10.7.0.2");
+        let (revoked, mut revoked_peer) = mock_session("s2", "revoked", "10.7.0.3");
+        manager.add_session(kept).await;
+        manager.add_session(revoked).await;
+
+        acl.add_entry(AccessControlManager::create_deny_entry("revoked", None)).await.unwrap();
+
+        assert_eq!(manager.disconnect_revoked_sessions(&acl, 10).await, 1);
+        assert!(manager.has_session("s1").await);
+        assert!(!manager.has_session("s2").await);
+
+        let msg = revoked_peer.from_server.recv().await.unwrap();
+        match crate::protocol::serialization::ws_message_to_packet(&msg).unwrap() {
+            PacketType::Disconnect { reason, .. } => {
+                assert_eq!(reason, DisconnectReason::AccessRevoked.code());
+            }
+            other => panic!("Expected Disconnect, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reapply_acl_policies() {
+        let dir = tempfile::tempdir().unwrap();
+        let acl = AccessControlManager::new(dir.path().join("acl.json")).await.unwrap();
+        let monitor = NetworkMonitor::new(Duration::from_secs(1), 10);
+        let manager = SessionManager::new(5, Duration::from_secs(3600));
+
+        let mut entry = AccessControlManager::create_allow_entry("key");
+        entry.bandwidth_limit = 0;
+        entry.max_session_duration = 0;
+        acl.add_entry(entry.clone()).await.unwrap();
+
+        // Two concurrent sessions for the same key, both snapshotting the tier at auth
+        let (first, _first_peer) = mock_session("s1", "key", "10.7.0.2");
+        let (second, _second_peer) = mock_session("s2", "key", "10.7.0.3");
+        let (other, _other_peer) = mock_session("s3", "other", "10.7.0.4");
+        for session in [&first, &second, &other] {
+            let policy = SessionPolicy::from_entry(acl.get_entry(&session.client_id).await.as_ref());
+            apply_session_policy(session, policy, &monitor).await;
+            manager.add_session(session.clone()).await;
+        }
+        let original = first.policy().await;
+        assert_eq!(original.max_session_duration, 0);
+
+        // Reload downgrades the tier
+        entry.bandwidth_limit = 1000;
+        entry.max_session_duration = 60;
+        acl.add_entry(entry).await.unwrap();
+
+        // Snapshot semantics: nothing changes until re-applied
+        assert_eq!(second.policy().await, original);
+
+        assert_eq!(manager.reapply_acl_policies(&acl, &monitor).await, 2);
+        for session in [&first, &second] {
+            let policy = session.policy().await;
+            assert_eq!(policy.bandwidth_limit, 1000);
+            assert_eq!(policy.max_session_duration, 60);
+        }
+        assert_eq!(monitor.get_client_stats("key").await.unwrap().bandwidth_limit, 1000);
+        assert!(monitor.check_bandwidth_limit("key", 2000, Duration::from_secs(1)).await);
+        assert_eq!(other.policy().await, SessionPolicy::default());
+
+        // Idempotent
+        assert_eq!(manager.reapply_acl_policies(&acl, &monitor).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_session_roles() {
+        let dir = tempfile::tempdir().unwrap();
+        let acl = AccessControlManager::new(dir.path().join("acl.json")).await.unwrap();
+        let mut entry = AccessControlManager::create_allow_entry("key");
+        entry.roles = vec!["limited".to_string()];
+        acl.add_entry(entry).await.unwrap();
+
+        let (session, _peer) = mock_session("s1", "key", "10.7.0.2");
+        assert!(!session.has_role("limited"));
+        let session = session.with_roles(acl.policy("key").await.roles);
+        assert!(session.has_role("limited"));
+        assert!(!session.has_role("admin"));
+        // Shared by clones handed to other components
+        assert_eq!(*session.clone().roles, vec!["limited".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_server_load() {
+        let manager = SessionManager::new(5, Duration::from_secs(3600));
+        let (session, mut peer) = mock_session("s1", "opted-in", "10.7.0.2");
+        let mut caps = session.capabilities.clone();
+        caps.server_load = true;
+        manager.add_session(session.with_capabilities(caps)).await;
+        let (legacy, mut legacy_peer) = mock_session("s2", "legacy", "10.7.0.3");
+        manager.add_session(legacy).await;
+
+        assert_eq!(manager.broadcast_server_load(0.25).await, 1);
+        let msg = peer.from_server.try_recv().unwrap();
+        match crate::protocol::serialization::ws_message_to_packet(&msg).unwrap() {
+            PacketType::ServerLoad { active_sessions, load_factor } => {
+                assert_eq!(active_sessions, 2);
+                assert_eq!(load_factor, 0.25);
+            }
+            other => panic!("Expected ServerLoad, got {:?}", other),
+        }
+        assert!(legacy_peer.from_server.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_session_policies() {
+        // AllowMultiple: existing sessions are untouched
+        let manager = SessionManager::new(5, Duration::from_secs(3600));
+        let (existing, mut existing_peer) = mock_session("s1", "key", "10.7.0.2");
+        manager.add_session(existing).await;
+        assert_eq!(manager.admit_client("key", 0).await.unwrap().0, 0);
+        assert!(manager.has_session("s1").await);
+        assert!(existing_peer.from_server.try_recv().is_err());
+
+        // RejectNew: the new authentication fails, the old session stays
+        let manager = SessionManager::new(5, Duration::from_secs(3600))
+            .with_duplicate_policy(DuplicateSessionPolicy::RejectNew);
+        let (existing, _existing_peer) = mock_session("s1", "key", "10.7.0.2");
+        manager.add_session(existing).await;
+        assert!(matches!(manager.admit_client("key", 0).await, Err(SessionError::AlreadyConnected)));
+        assert_eq!(manager.admit_client("other", 0).await.unwrap().0, 0);
+        assert!(manager.has_session("s1").await);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_admission_reject_new() {
+        let manager = Arc::new(SessionManager::new(5, Duration::from_secs(3600))
+            .with_duplicate_policy(DuplicateSessionPolicy::RejectNew));
+
+        // Two handshakes for one key finishing together: only one is admitted
+        let attempts: Vec<_> = (0..2).map(|_| {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.admit_client("key", 0).await })
+        }).collect();
+        let mut admitted = Vec::new();
+        for attempt in attempts {
+            match attempt.await.unwrap() {
+                Ok((_, reservation)) => admitted.push(reservation),
+                Err(e) => assert!(matches!(e, SessionError::AlreadyConnected)),
+            }
+        }
+        assert_eq!(admitted.len(), 1);
+
+        // A handshake that fails after admission gives its slot back
+        drop(admitted);
+        let (_, reservation) = manager.admit_client("key", 0).await.unwrap();
+
+        // The added session takes over the slot
+        let (session, _peer) = mock_session("s1", "key", "10.7.0.2");
+        manager.add_admitted_session(session, reservation).await;
+        assert!(matches!(manager.admit_client("key", 0).await, Err(SessionError::AlreadyConnected)));
+        manager.remove_session("s1").await;
+        assert!(manager.admit_client("key", 0).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_per_key_session_limit() {
+        let manager = SessionManager::new(5, Duration::from_secs(3600)).with_max_sessions_per_key(2);
+        let (first, _first_peer) = mock_session("s1", "key", "10.7.0.2");
+        let (second, _second_peer) = mock_session("s2", "key", "10.7.0.3");
+        let (third, _third_peer) = mock_session("s3", "key", "10.7.0.4");
+
+        // Up to the limit
+        assert_eq!(manager.admit_client("key", 0).await.unwrap().0, 0);
+        manager.add_session(first).await;
+        assert_eq!(manager.admit_client("key", 0).await.unwrap().0, 0);
+        manager.add_session(second).await;
+
+        // One more is refused, other keys are unaffected
+        assert!(matches!(manager.admit_client("key", 0).await, Err(SessionError::KeyLimitReached(2))));
+        assert_eq!(manager.admit_client("other", 0).await.unwrap().0, 0);
+
+        // An ACL limit replaces the server default
+        assert_eq!(manager.admit_client("key", 3).await.unwrap().0, 0);
+        assert!(matches!(manager.admit_client("key", 1).await, Err(SessionError::KeyLimitReached(1))));
+
+        // Freeing a session makes room again
+        manager.remove_session("s1").await;
+        assert_eq!(manager.admit_client("key", 0).await.unwrap().0, 0);
+        manager.add_session(third).await;
+        assert!(matches!(manager.admit_client("key", 0).await, Err(SessionError::KeyLimitReached(2))));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_admission_key_limit() {
+        let manager = Arc::new(SessionManager::new(10, Duration::from_secs(3600)).with_max_sessions_per_key(2));
+        let (session, _peer) = mock_session("s1", "key", "10.7.0.2");
+        manager.add_session(session).await;
+
+        // Several handshakes racing for the one remaining slot: only one is admitted
+        let attempts: Vec<_> = (0..4).map(|_| {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.admit_client("key", 0).await })
+        }).collect();
+        let mut admitted = Vec::new();
+        for attempt in attempts {
+            match attempt.await.unwrap() {
+                Ok((_, reservation)) => admitted.push(reservation),
+                Err(e) => assert!(matches!(e, SessionError::KeyLimitReached(2))),
+            }
+        }
+        assert_eq!(admitted.len(), 1);
+
+        // Pending admissions also count against an ACL limit
+        let (_, acl_reservation) = manager.admit_client("key", 3).await.unwrap();
+        assert!(matches!(manager.admit_client("key", 3).await, Err(SessionError::KeyLimitReached(3))));
+
+        // Failed handshakes give their slots back
+        drop(acl_reservation);
+        drop(admitted);
+        assert!(manager.admit_client("key", 0).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_admission_session_cap() {
+        let manager = Arc::new(SessionManager::new(10, Duration::from_secs(3600))
+            .with_duplicate_policy(DuplicateSessionPolicy::AllowMultiple)
+            .with_max_sessions(2));
+        let (session, _peer) = mock_session("s1", "a", "10.7.0.2");
+        manager.add_session(session).await;
+
+        // Handshakes of different keys racing for the last slot: only one is admitted
+        let attempts: Vec<_> = ["b", "c", "d", "e"].into_iter().map(|key| {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.admit_client(key, 0).await })
+        }).collect();
+        let mut admitted = Vec::new();
+        for attempt in attempts {
+            match attempt.await.unwrap() {
+                Ok((_, reservation)) => admitted.push(reservation),
+                Err(e) => assert!(matches!(e, SessionError::ServerFull(2)), "{:?}", e),
+            }
+        }
+        assert_eq!(admitted.len(), 1);
+        assert!(manager.at_capacity().await);
+
+        // A failed handshake gives its slot back
+        drop(admitted);
+        assert!(!manager.at_capacity().await);
+        assert!(manager.admit_client("f", 0).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_replace_existing_session() {
+        let manager = SessionManager::new(5, Duration::from_secs(3600))
+            .with_duplicate_policy(DuplicateSessionPolicy::ReplaceExisting);
+        let (existing, mut existing_peer) = mock_session("s1", "key", "10.7.0.2");
+        let (unrelated, _unrelated_peer) = mock_session("s2", "other", "10.7.0.3");
+        manager.add_session(existing.clone()).await;
+        manager.add_session(unrelated).await;
+
+        // Stand-in for the old session's handler finishing its cleanup
+        let handler = existing.clone();
+        let cleanup = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            handler.mark_torn_down();
+        });
+
+        assert_eq!(manager.admit_client("key", 0).await.unwrap().0, 1);
+        // Admission waited for the old teardown
+        assert!(cleanup.is_finished());
+        assert!(!manager.has_session("s1").await);
+        assert!(manager.has_session("s2").await);
+        assert!(existing.is_stream_taken().await);
+
+        let msg = existing_peer.from_server.recv().await.unwrap();
+        match crate::protocol::serialization::ws_message_to_packet(&msg).unwrap() {
+            PacketType::Disconnect { reason, .. } => {
+                assert_eq!(reason, DisconnectReason::SessionReplaced.code());
+            }
+            other => panic!("Expected Disconnect, got {:?}", other),
+        }
+    }
+}
+
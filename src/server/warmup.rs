@@ -0,0 +1,108 @@
+// src/server/warmup.rs
+//! Startup warmup window.
+//!
+//! Right after startup the server sees near-zero load, but caches and the
+//! TUN path are cold and a reconnect storm can arrive all at once. For a
+//! configurable window the server reports itself not-ready and either
+//! accepts new connections at a reduced rate or refuses them outright.
+
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::config::settings::WarmupMode;
+
+/// Startup warmup state shared by the accept loops
+#[derive(Debug)]
+pub struct Warmup {
+    /// When the window started
+    started: Instant,
+    /// Length of the window
+    window: Duration,
+    /// Connection handling during the window
+    mode: WarmupMode,
+    /// Accepts allowed per second in throttle mode
+    accepts_per_sec: u32,
+    /// Start of the current one-second accept bucket and accepts counted in it
+    bucket: Mutex<(Instant, u32)>,
+}
+
+impl Warmup {
+    /// Start a warmup window now
+    pub fn new(window: Duration, mode: WarmupMode, accepts_per_sec: u32) -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            window,
+            mode,
+            accepts_per_sec,
+            bucket: Mutex::new((now, 0)),
+        }
+    }
+
+    /// Time left in the window, if still warming up
+    pub fn remaining(&self) -> Option<Duration> {
+        self.window.checked_sub(self.started.elapsed()).filter(|r| !r.is_zero())
+    }
+
+    /// Whether warmup has finished and the server may report ready
+    pub fn is_warm(&self) -> bool {
+        self.remaining().is_none()
+    }
+
+    /// Check whether a new connection may be accepted now
+    pub async fn admit(&self) -> bool {
+        if self.is_warm() {
+            return true;
+        }
+        match self.mode {
+            WarmupMode::Reject => false,
+            WarmupMode::Throttle => {
+                let mut bucket = self.bucket.lock().await;
+                let now = Instant::now();
+                if now.duration_since(bucket.0) >= Duration::from_secs(1) {
+                    *bucket = (now, 0);
+                }
+                if bucket.1 < self.accepts_per_sec {
+                    bucket.1 += 1;
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_warmup_admission() {
+        // Disabled window: ready immediately, nothing throttled
+        let warmup = Warmup::new(Duration::ZERO, WarmupMode::Reject, 1);
+        assert!(warmup.is_warm());
+        assert!(warmup.admit().await);
+
+        // Reject mode refuses everything until warm
+        let warmup = Warmup::new(Duration::from_secs(60), WarmupMode::Reject, 10);
+        assert!(!warmup.is_warm());
+        assert!(warmup.remaining().unwrap() <= Duration::from_secs(60));
+        assert!(!warmup.admit().await);
+
+        // Throttle mode allows a fixed number per second
+        let warmup = Warmup::new(Duration::from_secs(60), WarmupMode::Throttle, 3);
+        for _ in 0..3 {
+            assert!(warmup.admit().await);
+        }
+        assert!(!warmup.admit().await);
+
+        // Window expiry lifts the throttle
+        let warmup = Warmup::new(Duration::from_millis(20), WarmupMode::Throttle, 1);
+        assert!(warmup.admit().await);
+        assert!(!warmup.admit().await);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(warmup.is_warm());
+        assert!(warmup.admit().await);
+    }
+}
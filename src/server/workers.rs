@@ -0,0 +1,184 @@
+// src/server/workers.rs
+//! Pinned worker runtimes for per-client processing.
+//!
+//! Each worker is a dedicated thread running a single-threaded tokio
+//! runtime. A client's session is always scheduled on the same worker,
+//! chosen by hashing its client ID, so its packets are processed in order
+//! on one core. Shared managers use async locks and remain accessible from
+//! every worker; sockets stay registered with the main runtime's driver.
+
+use std::collections::hash_map::DefaultHasher;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::thread;
+use tokio::runtime::{Builder, Handle};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tracing::{debug, info};
+
+/// A single pinned worker
+struct Worker {
+    /// Handle for spawning onto the worker's runtime
+    handle: Handle,
+    /// Stops the worker thread when dropped or sent
+    shutdown: Option<oneshot::Sender<()>>,
+    /// Worker thread
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+/// Pool of single-threaded runtimes with client-affine scheduling
+pub struct PinnedWorkerPool {
+    workers: Vec<Worker>,
+}
+
+impl PinnedWorkerPool {
+    /// Start `threads` pinned workers
+    pub fn new(threads: usize) -> io::Result<Self> {
+        let mut workers = Vec::with_capacity(threads);
+        for index in 0..threads {
+            let runtime = Builder::new_current_thread().enable_all().build()?;
+            let handle = runtime.handle().clone();
+            let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+            let thread = thread::Builder::new()
+                .name(format!("aeronyx-worker-{}", index))
+                .spawn(move || {
+                    // Drive spawned sessions until shutdown
+                    let _ = runtime.block_on(shutdown_rx);
+                    debug!("Pinned worker {} stopped", index);
+                })?;
+            workers.push(Worker {
+                handle,
+                shutdown: Some(shutdown_tx),
+                thread: Some(thread),
+            });
+        }
+        info!("Started {} pinned session workers", threads);
+        Ok(Self { workers })
+    }
+
+    /// Worker index for a client ID
+    pub fn worker_for(&self, client_id: &str) -> usize {
+        let mut hasher = DefaultHasher::new();
+        client_id.hash(&mut hasher);
+        (hasher.finish() % self.workers.len() as u64) as usize
+    }
+
+    /// Run a client's processing on its pinned worker
+    pub fn spawn_pinned<F>(&self, client_id: &str, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let index = self.worker_for(client_id);
+        debug!("Pinning client {} to worker {}", client_id, index);
+        self.workers[index].handle.spawn(future)
+    }
+}
+
+impl Drop for PinnedWorkerPool {
+    fn drop(&mut self) {
+        for worker in &mut self.workers {
+            if let Some(shutdown) = worker.shutdown.take() {
+                let _ = shutdown.send(());
+            }
+        }
+        for worker in &mut self.workers {
+            if let Some(thread) = worker.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use tokio::sync::{mpsc, Mutex};
+
+    fn current_thread_name() -> String {
+        thread::current().name().unwrap_or_default().to_string()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_pinned_workers() {
+        let pool = PinnedWorkerPool::new(4).unwrap();
+        assert!((0..100).all(|i| pool.worker_for(&format!("client-{}", i)) < 4));
+
+        // The same client always lands on the same worker thread
+        let first = pool.spawn_pinned("client-a", async { current_thread_name() }).await.unwrap();
+        for _ in 0..10 {
+            let name = pool.spawn_pinned("client-a", async { current_thread_name() }).await.unwrap();
+            assert_eq!(name, first);
+        }
+        assert_eq!(first, format!("aeronyx-worker-{}", pool.worker_for("client-a")));
+
+        // Shared state and timers work from pinned workers
+        let shared = Arc::new(Mutex::new(0u32));
+        let mut handles = Vec::new();
+        for i in 0..16 {
+            let shared = shared.clone();
+            handles.push(pool.spawn_pinned(&format!("client-{}", i), async move {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+                *shared.lock().await += 1;
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(*shared.lock().await, 16);
+    }
+
+    /// Round-trip a message stream between a client task and a session task
+    async fn echo_round_trips(pool: Option<&PinnedWorkerPool>, client_id: &str, messages: usize) -> Duration {
+        let (to_session, mut session_rx) = mpsc::channel::<Vec<u8>>(64);
+        let (to_client, mut client_rx) = mpsc::channel::<Vec<u8>>(64);
+        let session = async move {
+            while let Some(packet) = session_rx.recv().await {
+                if to_client.send(packet).await.is_err() {
+                    break;
+                }
+            }
+        };
+        let handle = match pool {
+            Some(pool) => pool.spawn_pinned(client_id, session),
+            None => tokio::spawn(session),
+        };
+
+        let start = Instant::now();
+        for _ in 0..messages {
+            to_session.send(vec![0u8; 1400]).await.unwrap();
+            client_rx.recv().await.unwrap();
+        }
+        let elapsed = start.elapsed();
+        drop(to_session);
+        handle.await.unwrap();
+        elapsed
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    #[ignore] // Benchmark; run with --ignored --nocapture
+    async fn bench_pinned_session_latency() {
+        const CLIENTS: usize = 8;
+        const MESSAGES: usize = 20_000;
+        let pool = PinnedWorkerPool::new(4).unwrap();
+
+        for pinned in [false, true] {
+            let start = Instant::now();
+            let mut total_latency = Duration::ZERO;
+            for client in 0..CLIENTS {
+                let id = format!("client-{}", client);
+                total_latency += echo_round_trips(if pinned { Some(&pool) } else { None }, &id, MESSAGES).await;
+            }
+            let elapsed = start.elapsed();
+            println!(
+                "{}: {:.0} msgs/sec per client, {:?} mean round trip",
+                if pinned { "pinned" } else { "shared runtime" },
+                MESSAGES as f64 / (elapsed.as_secs_f64() / CLIENTS as f64),
+                total_latency / (CLIENTS * MESSAGES) as u32
+            );
+        }
+    }
+}
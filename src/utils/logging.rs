@@ -4,8 +4,11 @@
 //! This module provides functions for initializing and configuring
 //! the logging system.
 
+use std::collections::HashSet;
 use std::io;
 use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 // Import Layer trait explicitly and EnvFilter via filter module
 use tracing_subscriber::{fmt, filter::EnvFilter, layer::SubscriberExt, util::SubscriberInitExt, Layer as TracingLayer, filter::LevelFilter}; // Corrected line 10
 // Removed unused NonBlocking import (type name not directly used)
@@ -117,6 +120,116 @@ pub fn log_security_event(event_type: &str, details: &str) {
     );
 }
 
+/// Maximum distinct sources tracked per aggregation window
+const MAX_TRACKED_SOURCES: usize = 10_000;
+
+/// Aggregated occurrences of a repetitive event over one window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventSummary {
+    /// Number of occurrences
+    pub count: u64,
+    /// Number of distinct sources (capped at `MAX_TRACKED_SOURCES`)
+    pub sources: usize,
+    /// Length of the window the summary covers
+    pub window: Duration,
+}
+
+struct ThrottleState {
+    window_start: Instant,
+    count: u64,
+    sources: HashSet<String>,
+}
+
+/// Aggregating logger for high-frequency events (auth failures, TLS failures, ...).
+///
+/// Instead of one line per occurrence, emits at most one summary line per
+/// window, e.g. "120 auth failures from 37 IPs in the last 1s". This keeps
+/// log volume bounded during connection floods.
+pub struct ThrottledLogger {
+    /// Event description used in summaries (plural, e.g. "auth failures")
+    event: &'static str,
+    /// Description of the sources (plural, e.g. "IPs")
+    source_kind: &'static str,
+    /// Aggregation window
+    window: Duration,
+    state: Mutex<ThrottleState>,
+}
+
+impl ThrottledLogger {
+    /// Create a new throttled logger
+    pub fn new(event: &'static str, source_kind: &'static str, window: Duration) -> Self {
+        Self {
+            event,
+            source_kind,
+            window,
+            state: Mutex::new(ThrottleState {
+                window_start: Instant::now(),
+                count: 0,
+                sources: HashSet::new(),
+            }),
+        }
+    }
+
+    /// Record one occurrence from `source`.
+    /// Emits the previous window's summary if that window has elapsed.
+    pub fn record(&self, source: &str) {
+        let summary = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            let summary = self.rotate(&mut state);
+            state.count += 1;
+            if state.sources.len() < MAX_TRACKED_SOURCES {
+                state.sources.insert(source.to_string());
+            }
+            summary
+        };
+        if let Some(summary) = summary {
+            self.emit(&summary);
+        }
+    }
+
+    /// Emit the pending summary if its window has elapsed.
+    /// Call periodically so the last burst is reported even if no further events arrive.
+    pub fn flush(&self) -> Option<EventSummary> {
+        let summary = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            self.rotate(&mut state)
+        };
+        if let Some(summary) = &summary {
+            self.emit(summary);
+        }
+        summary
+    }
+
+    /// Close the current window if it has elapsed, returning its summary if non-empty
+    fn rotate(&self, state: &mut ThrottleState) -> Option<EventSummary> {
+        let elapsed = state.window_start.elapsed();
+        if elapsed < self.window {
+            return None;
+        }
+
+        let summary = (state.count > 0).then(|| EventSummary {
+            count: state.count,
+            sources: state.sources.len(),
+            window: elapsed,
+        });
+        state.window_start = Instant::now();
+        state.count = 0;
+        state.sources.clear();
+        summary
+    }
+
+    fn emit(&self, summary: &EventSummary) {
+        tracing::warn!(
+            "{} {} from {} {} in the last {:.1}s",
+            summary.count,
+            self.event,
+            summary.sources,
+            self.source_kind,
+            summary.window.as_secs_f64()
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -217,4 +330,24 @@ mod tests {
         log_security_event("AUTH_FAILURE", "Invalid password for user 'test'");
         // No easy way to assert output here without public TestWriter or adding dependencies.
      }
+
+    #[test]
+    fn test_throttled_logger_aggregates() {
+        let logger = ThrottledLogger::new("auth failures", "IPs", Duration::from_millis(50));
+
+        for i in 0..100 {
+            logger.record(&format!("10.0.0.{}", i % 5));
+        }
+        // Window has not elapsed yet
+        assert!(logger.flush().is_none());
+
+        std::thread::sleep(Duration::from_millis(60));
+        let summary = logger.flush().unwrap();
+        assert_eq!(summary.count, 100);
+        assert_eq!(summary.sources, 5);
+
+        // Nothing new to report
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(logger.flush().is_none());
+    }
 }
@@ -9,14 +9,46 @@ pub mod security;
 pub mod system;
 
 use rand::{distributions::Alphanumeric, Rng, thread_rng};
+use std::sync::OnceLock;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+#[cfg(test)]
+thread_local! {
+    /// Simulated wall-clock step for tests, in milliseconds
+    static WALL_CLOCK_STEP: std::cell::Cell<i64> = std::cell::Cell::new(0);
+}
+
 /// Get current timestamp in milliseconds
+///
+/// This is the wall clock and can step backwards (NTP corrections, VM
+/// resume). Use it for absolute times exchanged with clients or written to
+/// records; measure RTTs and intervals with `Instant` or `monotonic_millis`.
 pub fn current_timestamp_millis() -> u64 {
-    SystemTime::now()
+    let millis = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_else(|_| Duration::from_secs(0))
-        .as_millis() as u64
+        .as_millis() as u64;
+    #[cfg(test)]
+    let millis = millis.saturating_add_signed(WALL_CLOCK_STEP.with(|step| step.get()));
+    millis
+}
+
+/// Milliseconds since the Unix epoch, read from a monotonic clock anchored
+/// to the wall clock on first use.
+///
+/// Values look like `current_timestamp_millis` but never go backwards, so
+/// timestamps the server later compares against itself (ping payloads, RTT
+/// echoes) survive wall-clock steps.
+pub fn monotonic_millis() -> u64 {
+    static ANCHOR: OnceLock<(Instant, u64)> = OnceLock::new();
+    let (start, start_millis) = ANCHOR.get_or_init(|| (Instant::now(), current_timestamp_millis()));
+    start_millis + start.elapsed().as_millis() as u64
+}
+
+/// Step the wall clock seen by `current_timestamp_millis` on this thread
+#[cfg(test)]
+pub fn step_wall_clock(millis: i64) {
+    WALL_CLOCK_STEP.with(|step| step.set(step.get() + millis));
 }
 
 /// Check if an Instant has expired given a TTL
@@ -85,6 +117,20 @@ mod tests {
         assert!(is_expired(now, Duration::from_millis(5)));
     }
     
+    #[test]
+    fn test_wall_clock_step_backwards() {
+        let before = monotonic_millis();
+        let wall_before = current_timestamp_millis();
+        step_wall_clock(-3_600_000);
+        
+        // The wall clock jumps back an hour; the monotonic clock keeps going
+        assert!(current_timestamp_millis() < wall_before);
+        let after = monotonic_millis();
+        assert!(after >= before);
+        assert!(after - before < 1_000);
+        step_wall_clock(3_600_000);
+    }
+    
     #[test]
     fn test_hex_conversion() {
         let original = vec![0, 1, 2, 3, 255];
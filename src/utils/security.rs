@@ -159,6 +159,47 @@ impl RateLimiter {
     }
 }
 
+/// Token bucket for rate limiting by count (packets, bytes, ...)
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    /// Maximum number of tokens (burst size)
+    capacity: f64,
+    /// Currently available tokens
+    tokens: f64,
+    /// Tokens added per second
+    refill_per_sec: f64,
+    /// Last refill time
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Create a new full bucket refilling at `rate_per_sec` up to `burst` tokens
+    pub fn new(rate_per_sec: u64, burst: u64) -> Self {
+        Self {
+            capacity: burst as f64,
+            tokens: burst as f64,
+            refill_per_sec: rate_per_sec as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Try to take `amount` tokens, returning false if not enough are available
+    pub fn try_consume(&mut self, amount: u64) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+
+        let amount = amount as f64;
+        if self.tokens >= amount {
+            self.tokens -= amount;
+            true
+        } else {
+            false
+        }
+    }
+}
+
 /// Security-related string validation utilities
 pub struct StringValidator;
 
@@ -303,6 +344,23 @@ mod tests {
         assert_eq!(limiter.get_connection_count(&ip).await, Some(1));
     }
 
+    #[test]
+    fn test_token_bucket() {
+        let mut bucket = TokenBucket::new(10, 3);
+
+        // Burst is available immediately
+        assert!(bucket.try_consume(1));
+        assert!(bucket.try_consume(2));
+        assert!(!bucket.try_consume(1));
+
+        // Refills over time
+        std::thread::sleep(Duration::from_millis(150));
+        assert!(bucket.try_consume(1));
+
+        // Requests larger than the burst never succeed
+        assert!(!bucket.try_consume(4));
+    }
+
     #[test]
     fn test_is_valid_solana_pubkey() {
         // Valid-looking pubkey (this is just an example, not an actual pubkey)